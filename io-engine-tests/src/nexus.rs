@@ -2,6 +2,8 @@ use super::{
     compose::rpc::v1::{
         nexus::{
             AddChildNexusRequest,
+            AttachIoLogJournalRequest,
+            AttachReadCacheRequest,
             Child,
             ChildAction,
             ChildOperationRequest,
@@ -11,12 +13,15 @@ use super::{
             DestroyNexusRequest,
             ListNexusOptions,
             Nexus,
+            NexusEnospcPolicy,
+            NexusFlushTarget,
             PublishNexusRequest,
             RebuildHistoryRecord,
             RebuildHistoryRequest,
             RemoveChildNexusRequest,
             ShutdownNexusRequest,
         },
+        stats::{GetStatsRequest, ReadCacheStats, ResourceStats, ResourceType},
         SharedRpcHandle,
         Status,
     },
@@ -49,6 +54,18 @@ pub struct NexusBuilder {
     children: Option<Vec<String>>,
     nexus_info_key: Option<String>,
     serial: Option<String>,
+    write_cache: Option<u64>,
+    copy_on_read: bool,
+    striped_rebuild: bool,
+    rebuild_segment_size: u64,
+    rebuild_tasks: u32,
+    enospc_policy: NexusEnospcPolicy,
+    child_io_timeout: Option<Duration>,
+    child_probe_interval: Option<Duration>,
+    max_io_size: u64,
+    flush_target: NexusFlushTarget,
+    write_through: bool,
+    block_size: u64,
 }
 
 impl NexusBuilder {
@@ -66,9 +83,92 @@ impl NexusBuilder {
             children: None,
             nexus_info_key: None,
             serial: None,
+            write_cache: None,
+            copy_on_read: false,
+            striped_rebuild: false,
+            rebuild_segment_size: 0,
+            rebuild_tasks: 0,
+            enospc_policy: NexusEnospcPolicy::Fault,
+            child_io_timeout: None,
+            child_probe_interval: None,
+            max_io_size: 0,
+            flush_target: NexusFlushTarget::FlushAll,
+            write_through: false,
+            block_size: 0,
         }
     }
 
+    /// Sets the children a flush must reach, and whether writes flush the
+    /// children before they complete, once the nexus is published.
+    pub fn with_flush_policy(
+        mut self,
+        flush_target: NexusFlushTarget,
+        write_through: bool,
+    ) -> Self {
+        self.flush_target = flush_target;
+        self.write_through = write_through;
+        self
+    }
+
+    /// Splits the reads and writes larger than the given size in bytes into
+    /// several child I/Os.
+    pub fn with_max_io_size(mut self, size: u64) -> Self {
+        self.max_io_size = size;
+        self
+    }
+
+    /// Aborts the reads outstanding on a child for longer than the given
+    /// timeout, and resubmits them to another child.
+    pub fn with_child_io_timeout(mut self, timeout: Duration) -> Self {
+        self.child_io_timeout = Some(timeout);
+        self
+    }
+
+    /// Probes the healthy remote children with an admin command at the given
+    /// interval.
+    pub fn with_child_probe_interval(mut self, interval: Duration) -> Self {
+        self.child_probe_interval = Some(interval);
+        self
+    }
+
+    /// Sets the behaviour of the nexus when its children run out of space.
+    pub fn with_enospc_policy(mut self, policy: NexusEnospcPolicy) -> Self {
+        self.enospc_policy = policy;
+        self
+    }
+
+    /// Serves reads from a local child while it is rebuilt.
+    pub fn with_copy_on_read(mut self, copy_on_read: bool) -> Self {
+        self.copy_on_read = copy_on_read;
+        self
+    }
+
+    /// Stripes the rebuild reads over all the healthy children.
+    pub fn with_striped_rebuild(mut self, striped_rebuild: bool) -> Self {
+        self.striped_rebuild = striped_rebuild;
+        self
+    }
+
+    /// Sets the size in bytes of the rebuild segments, and the number of
+    /// concurrent rebuild copy tasks.
+    pub fn with_rebuild_segments(mut self, size: u64, tasks: u32) -> Self {
+        self.rebuild_segment_size = size;
+        self.rebuild_tasks = tasks;
+        self
+    }
+
+    /// Sets the logical block size in bytes, which all children must have.
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Enables the nexus write cache, with the given capacity in bytes.
+    pub fn with_write_cache(mut self, capacity: u64) -> Self {
+        self.write_cache = Some(capacity);
+        self
+    }
+
     pub fn with_name(mut self, name: &str) -> Self {
         self.name = Some(name.to_owned());
         self.nexus_info_key = Some(name.to_owned());
@@ -175,6 +275,22 @@ impl NexusBuilder {
                 nexus_info_key: self.nexus_info_key.as_ref().unwrap().clone(),
                 resv_type: self.resv_type,
                 preempt_policy: 0,
+                write_cache: self.write_cache.is_some(),
+                write_cache_size: self.write_cache.unwrap_or_default(),
+                copy_on_read: self.copy_on_read,
+                striped_rebuild: self.striped_rebuild,
+                rebuild_segment_size: self.rebuild_segment_size,
+                rebuild_tasks: self.rebuild_tasks,
+                enospc_policy: self.enospc_policy as i32,
+                child_io_timeout_us: self
+                    .child_io_timeout
+                    .map_or(0, |t| t.as_micros() as u64),
+                child_probe_interval_ms: self
+                    .child_probe_interval
+                    .map_or(0, |t| t.as_millis() as u64),
+                max_io_size: self.max_io_size,
+                block_size: self.block_size,
+                ..Default::default()
             })
             .await
             .map(|r| r.into_inner().nexus.unwrap())
@@ -187,6 +303,7 @@ impl NexusBuilder {
             .nexus
             .shutdown_nexus(ShutdownNexusRequest {
                 uuid: self.uuid(),
+                ..Default::default()
             })
             .await
             .map(|_| ())
@@ -199,6 +316,9 @@ impl NexusBuilder {
             .nexus
             .destroy_nexus(DestroyNexusRequest {
                 uuid: self.uuid(),
+                verbose: false,
+                validate_only: false,
+                ..Default::default()
             })
             .await
             .map(|_| ())
@@ -213,6 +333,8 @@ impl NexusBuilder {
                 uuid: self.uuid(),
                 key: String::new(),
                 share: 1,
+                flush_target: self.flush_target as i32,
+                write_through: self.write_through,
                 ..Default::default()
             })
             .await
@@ -232,6 +354,11 @@ impl NexusBuilder {
                 uuid: self.uuid(),
                 uri: bdev.to_owned(),
                 norebuild,
+                seed_snapshot: None,
+                rebuild_source: None,
+                rebuild_at: None,
+                rebuild_priority: None,
+                ..Default::default()
             })
             .await
             .map(|r| r.into_inner().nexus.unwrap())
@@ -253,6 +380,7 @@ impl NexusBuilder {
             .remove_child_nexus(RemoveChildNexusRequest {
                 uuid: self.uuid(),
                 uri: bdev.to_owned(),
+                ..Default::default()
             })
             .await
             .map(|r| r.into_inner().nexus.unwrap())
@@ -274,6 +402,8 @@ impl NexusBuilder {
                 nexus_uuid: self.uuid(),
                 uri: bdev.to_owned(),
                 action: ChildAction::Online as i32,
+                rebuild_source: None,
+                ..Default::default()
             })
             .await
             .map(|r| r.into_inner().nexus.unwrap())
@@ -308,6 +438,8 @@ impl NexusBuilder {
                 nexus_uuid: self.uuid(),
                 uri: bdev.to_owned(),
                 action: ChildAction::Offline as i32,
+                rebuild_source: None,
+                ..Default::default()
             })
             .await
             .map(|r| r.into_inner().nexus.unwrap())
@@ -369,6 +501,68 @@ impl NexusBuilder {
             .map(|r| r.into_inner().records)
     }
 
+    /// Attaches the given local device as a read cache of the nexus.
+    pub async fn attach_read_cache(
+        &self,
+        device: &str,
+        line_size: u64,
+    ) -> Result<Nexus, Status> {
+        self.rpc()
+            .lock()
+            .await
+            .nexus
+            .attach_read_cache(AttachReadCacheRequest {
+                uuid: self.uuid(),
+                device: device.to_owned(),
+                line_size,
+            })
+            .await
+            .map(|r| r.into_inner().nexus.unwrap())
+    }
+
+    /// Attaches the given local device as the I/O log journal of the nexus.
+    pub async fn attach_io_log_journal(
+        &self,
+        device: &str,
+    ) -> Result<Nexus, Status> {
+        self.rpc()
+            .lock()
+            .await
+            .nexus
+            .attach_io_log_journal(AttachIoLogJournalRequest {
+                uuid: self.uuid(),
+                device: device.to_owned(),
+            })
+            .await
+            .map(|r| r.into_inner().nexus.unwrap())
+    }
+
+    /// Returns the I/O statistics of the nexus.
+    pub async fn get_stats(&self) -> Result<ResourceStats, Status> {
+        self.rpc()
+            .lock()
+            .await
+            .stats
+            .get_stats(GetStatsRequest {
+                resource_types: vec![ResourceType::Nexus as i32],
+                name: Some(self.uuid()),
+                reset: false,
+            })
+            .await
+            .and_then(|r| {
+                r.into_inner().stats.into_iter().next().ok_or_else(|| {
+                    Status::not_found(format!("Nexus {}", self.uuid()))
+                })
+            })
+    }
+
+    /// Returns the statistics of the read cache of the nexus, if any.
+    pub async fn get_read_cache_stats(
+        &self,
+    ) -> Result<Option<ReadCacheStats>, Status> {
+        self.get_stats().await.map(|s| s.read_cache)
+    }
+
     pub async fn get_nexus(&self) -> Result<Nexus, Status> {
         let uuid = self.uuid();
         list_nexuses(self.rpc())
@@ -464,6 +658,9 @@ pub async fn list_nexuses(rpc: SharedRpcHandle) -> Result<Vec<Nexus>, Status> {
         .list_nexus(ListNexusOptions {
             name: None,
             uuid: None,
+            child: None,
+            state: None,
+            published: None,
         })
         .await
         .map(|r| r.into_inner().nexus_list)
@@ -4,7 +4,7 @@ use regex::Regex;
 use std::{
     collections::BTreeMap,
     net::SocketAddr,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{Command, ExitStatus},
 };
 
@@ -113,6 +113,35 @@ pub fn nvme_connect(
     status
 }
 
+/// Sends a flush to the given NVMe namespace.
+pub fn nvme_flush(path: impl AsRef<Path>) -> ExitStatus {
+    Command::new("nvme")
+        .args(["flush"])
+        .arg(path.as_ref())
+        .status()
+        .unwrap()
+}
+
+/// Writes the given number of blocks of random data at the given block of
+/// the given NVMe namespace, with the FUA bit set.
+pub fn nvme_write_fua(
+    path: impl AsRef<Path>,
+    start_block: u64,
+    blocks: u64,
+    block_len: u64,
+) -> ExitStatus {
+    Command::new("nvme")
+        .args(["write"])
+        .arg(path.as_ref())
+        .args(["--start-block", &start_block.to_string()])
+        .args(["--block-count", &(blocks - 1).to_string()])
+        .args(["--data-size", &(blocks * block_len).to_string()])
+        .args(["--data", "/dev/urandom"])
+        .args(["--force-unit-access"])
+        .status()
+        .unwrap()
+}
+
 pub fn nvme_disconnect_all() {
     let output_dis = Command::new("nvme")
         .args(["disconnect-all"])
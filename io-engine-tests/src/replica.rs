@@ -28,6 +28,7 @@ pub struct ReplicaBuilder {
     pub share: i32,
     pub shared_uri: Option<String>,
     pub serial: Option<String>,
+    pub block_size: u64,
 }
 
 impl ReplicaBuilder {
@@ -43,6 +44,7 @@ impl ReplicaBuilder {
             share: 0,
             shared_uri: None,
             serial: None,
+            block_size: 0,
         }
     }
 
@@ -82,6 +84,11 @@ impl ReplicaBuilder {
         self
     }
 
+    pub fn with_block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
     pub fn with_nvmf(mut self) -> Self {
         self.share = mayastor_api::v1::common::ShareProtocol::Nvmf as i32;
         self
@@ -144,6 +151,7 @@ impl ReplicaBuilder {
                 size: self.size.unwrap(),
                 thin: self.thin,
                 share: self.share,
+                block_size: self.block_size,
                 ..Default::default()
             })
             .await
@@ -162,6 +170,8 @@ impl ReplicaBuilder {
             .destroy_replica(DestroyReplicaRequest {
                 uuid: self.uuid(),
                 pool,
+                validate_only: false,
+                ..Default::default()
             })
             .await
             .map(|r| r.into_inner())
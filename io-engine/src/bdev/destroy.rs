@@ -0,0 +1,163 @@
+//! Destruction of devices which still have open handles.
+//!
+//! Destroying a bdev while descriptors of it are open makes SPDK send a
+//! removal event to their owners and wait for all of them to close their
+//! descriptor. Owners which do not expect the removal, or which drop their
+//! descriptor on another thread, race with the unregistration of the bdev.
+//!
+//! A destroy is therefore deferred while handles of the device are open: the
+//! device is marked `Destroying`, new handles can no longer be opened, and
+//! the device is destroyed once its last handle is closed. A forced destroy
+//! does not wait, and reports the handles it tears down instead; it is meant
+//! for owners which close their handle on removal, such as nexus children.
+//!
+//! Only the descriptors opened through `DescriptorGuard` are tracked. NVMf
+//! devices handle the teardown of their I/O handles themselves and are
+//! always destroyed immediately.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+use super::uri;
+use crate::{bdev_api::BdevError, core::Reactors};
+
+/// How a device with open handles is destroyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestroyMode {
+    /// Destroy the device once its last handle is closed.
+    Deferred,
+    /// Destroy the device immediately, regardless of its open handles.
+    Force,
+}
+
+/// Outcome of the destruction of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestroyOutcome {
+    /// The device had no open handles and was destroyed.
+    Destroyed,
+    /// The device is `Destroying`, and will be destroyed once the given
+    /// number of open handles are closed.
+    Deferred { open_handles: usize },
+    /// The device was destroyed while the given number of handles were open.
+    Forced { open_handles: usize },
+}
+
+#[derive(Debug, Default)]
+struct DeviceHandles {
+    /// Open handles, by name of the device.
+    open: HashMap<String, usize>,
+    /// URIs of the `Destroying` devices, by name.
+    destroying: HashMap<String, String>,
+}
+
+static DEVICE_HANDLES: Lazy<parking_lot::Mutex<DeviceHandles>> =
+    Lazy::new(Default::default);
+
+/// Records that a handle of the given device was opened.
+pub(crate) fn handle_opened(name: &str) {
+    *DEVICE_HANDLES
+        .lock()
+        .open
+        .entry(name.to_string())
+        .or_default() += 1;
+}
+
+/// Records that a handle of the given device was closed, and destroys the
+/// device if it was the last handle of a `Destroying` device.
+pub(crate) fn handle_closed(name: &str) {
+    let uri = {
+        let mut handles = DEVICE_HANDLES.lock();
+        match handles.open.get_mut(name) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                return;
+            }
+            Some(_) => {
+                handles.open.remove(name);
+            }
+            None => return,
+        }
+        match handles.destroying.get(name) {
+            Some(uri) => uri.clone(),
+            None => return,
+        }
+    };
+
+    let name = name.to_string();
+    Reactors::master().send_future(async move {
+        info!("Destroying device '{name}': last handle closed");
+        if let Err(error) = destroy_now(&uri).await {
+            error!("Failed to destroy device '{name}': {error}");
+        }
+        DEVICE_HANDLES.lock().destroying.remove(&name);
+    });
+}
+
+/// Returns the number of open handles of the given device.
+pub fn device_open_handles(name: &str) -> usize {
+    DEVICE_HANDLES
+        .lock()
+        .open
+        .get(name)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Checks if the given device is `Destroying`, waiting for its open handles
+/// to be closed.
+pub fn device_destroying(name: &str) -> bool {
+    DEVICE_HANDLES.lock().destroying.contains_key(name)
+}
+
+async fn destroy_now(uri: &str) -> Result<(), BdevError> {
+    uri::parse(uri)?.destroy().await
+}
+
+/// Destroys the device of the given URI, deferring its destruction while it
+/// has open handles unless forced.
+pub async fn device_destroy_with(
+    uri: &str,
+    mode: DestroyMode,
+) -> Result<DestroyOutcome, BdevError> {
+    let name = uri::parse(uri)?.get_name();
+    let deferrable = !uri.starts_with("nvmf:");
+
+    {
+        let mut handles = DEVICE_HANDLES.lock();
+        let open_handles = handles.open.get(&name).copied().unwrap_or_default();
+        if deferrable && open_handles > 0 && mode == DestroyMode::Deferred {
+            if handles
+                .destroying
+                .insert(name.clone(), uri.to_string())
+                .is_none()
+            {
+                info!(
+                    "Device '{name}' has {open_handles} open handle(s): \
+                    deferring its destruction"
+                );
+            }
+            return Ok(DestroyOutcome::Deferred {
+                open_handles,
+            });
+        }
+        // A forced destroy takes over a deferred one.
+        handles.destroying.remove(&name);
+    }
+
+    let open_handles = device_open_handles(&name);
+    if open_handles > 0 {
+        warn!(
+            "Force destroying device '{name}' with {open_handles} open \
+            handle(s)"
+        );
+    }
+    destroy_now(uri).await?;
+    Ok(if open_handles > 0 {
+        DestroyOutcome::Forced {
+            open_handles,
+        }
+    } else {
+        DestroyOutcome::Destroyed
+    })
+}
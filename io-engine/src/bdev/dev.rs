@@ -22,7 +22,10 @@
 
 use std::collections::HashMap;
 
-use super::nvmx;
+use super::{
+    destroy::{device_destroy_with, DestroyMode},
+    nvmx,
+};
 use crate::{
     bdev::SpdkBlockDevice,
     bdev_api::BdevError,
@@ -47,6 +50,7 @@ pub(crate) mod uri {
             nvmx,
             nx,
             uring,
+            util::uri as uri_util,
             BdevCreateDestroy,
         },
         bdev_api::{self, BdevError},
@@ -77,6 +81,86 @@ pub(crate) mod uri {
             }),
         }
     }
+
+    /// Returns the canonical form of a device URI, so that the URIs which
+    /// reference the same device compare equal: the host is in lower case,
+    /// the default NVMf port is omitted, the query parameters are sorted,
+    /// the parameters set to their default value are omitted, and boolean
+    /// and UUID values are spelled in a single way. The URI must be valid.
+    pub fn normalize(uri: &str) -> Result<String, BdevError> {
+        parse(uri)?;
+
+        let mut url =
+            url::Url::parse(uri).context(bdev_api::UriParseFailed {
+                uri: uri.to_string(),
+            })?;
+
+        if let Some(host) = url.host_str().map(str::to_lowercase) {
+            url.set_host(Some(&host)).ok();
+        }
+        if url.scheme() == "nvmf" && url.port() == Some(nvmx::DEFAULT_NVMF_PORT)
+        {
+            url.set_port(None).ok();
+        }
+
+        let scheme = url.scheme().to_string();
+        let mut parameters = url
+            .query_pairs()
+            .into_owned()
+            .filter_map(|(k, v)| {
+                let v = normalize_parameter(&scheme, &k, v)?;
+                Some((k, v))
+            })
+            .collect::<Vec<_>>();
+        parameters.sort();
+
+        if parameters.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(parameters);
+        }
+
+        Ok(url.to_string())
+    }
+
+    /// Returns the canonical value of a URI parameter, or `None` if the
+    /// parameter is set to its default value.
+    fn normalize_parameter(
+        scheme: &str,
+        name: &str,
+        value: String,
+    ) -> Option<String> {
+        match (scheme, name) {
+            (_, "uuid") => Some(
+                uuid::Uuid::parse_str(&value)
+                    .map(|u| u.to_hyphenated().to_string())
+                    .unwrap_or(value),
+            ),
+            ("nvmf", "reftag" | "guard") => {
+                match uri_util::boolean(&value, true) {
+                    Ok(true) => Some("true".to_string()),
+                    Ok(false) => None,
+                    Err(_) => Some(value),
+                }
+            }
+            ("aio" | "uring" | "malloc" | "null", "blk_size")
+                if value == "512" =>
+            {
+                None
+            }
+            _ => Some(value),
+        }
+    }
+
+    /// Checks if two device URIs reference the same device, once
+    /// normalized. Invalid URIs are compared as they are.
+    pub fn same_device(a: &str, b: &str) -> bool {
+        a == b
+            || match (normalize(a), normalize(b)) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => false,
+            }
+    }
 }
 
 pub(crate) fn reject_unknown_parameters(
@@ -113,8 +197,12 @@ pub async fn device_create(uri: &str) -> Result<String, BdevError> {
     uri::parse(uri)?.create().await
 }
 
+/// Destroys the device of the given URI, or marks it `Destroying` until its
+/// open handles are closed.
 pub async fn device_destroy(uri: &str) -> Result<(), BdevError> {
-    uri::parse(uri)?.destroy().await
+    device_destroy_with(uri, DestroyMode::Deferred)
+        .await
+        .map(|_| ())
 }
 
 pub fn device_open(
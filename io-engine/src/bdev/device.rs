@@ -18,11 +18,15 @@ use spdk_rs::{
         spdk_bdev_flush,
         spdk_bdev_free_io,
         spdk_bdev_io,
+        spdk_bdev_io_get_submit_tsc,
         spdk_bdev_readv_blocks_with_flags,
         spdk_bdev_reset,
         spdk_bdev_unmap_blocks,
         spdk_bdev_write_zeroes_blocks,
         spdk_bdev_writev_blocks,
+        SPDK_BDEV_IO_TYPE_READ,
+        SPDK_BDEV_IO_TYPE_WRITE,
+        SPDK_NVME_IO_FLAGS_CURRENT_UNWRITTEN_READ_FAIL,
         SPDK_NVME_IO_FLAGS_UNWRITTEN_READ_FAIL,
     },
     nvme_admin_opc,
@@ -52,6 +56,7 @@ use crate::{
         IoCompletionCallback,
         IoCompletionCallbackArg,
         IoCompletionStatus,
+        IoHistograms,
         NvmeStatus,
         ReadOptions,
         SnapshotParams,
@@ -287,6 +292,9 @@ impl BlockDeviceHandle for SpdkBlockDeviceHandle {
             ReadOptions::UnwrittenFail => {
                 SPDK_NVME_IO_FLAGS_UNWRITTEN_READ_FAIL
             }
+            ReadOptions::CurrentUnwrittenFail => {
+                SPDK_NVME_IO_FLAGS_CURRENT_UNWRITTEN_READ_FAIL
+            }
         };
 
         let ctx = alloc_bdev_io_ctx(
@@ -760,6 +768,10 @@ extern "C" fn bdev_io_completion(
     let status =
         inject_completion_error(FaultDomain::BlockDevice, &bio.inj_op, status);
 
+    if IoHistograms::any_enabled() {
+        record_io_latency(&bio.device, child_bio);
+    }
+
     (bio.cb)(&bio.device, status, bio.cb_arg);
 
     // Free ctx.
@@ -771,6 +783,20 @@ extern "C" fn bdev_io_completion(
     }
 }
 
+/// Counts a completed read or write I/O in the latency histogram of the
+/// device, if enabled.
+fn record_io_latency(device: &SpdkBlockDevice, bdev_io: *mut spdk_bdev_io) {
+    let io_type = match unsafe { (*bdev_io).type_ } as u32 {
+        SPDK_BDEV_IO_TYPE_READ => IoType::Read,
+        SPDK_BDEV_IO_TYPE_WRITE => IoType::Write,
+        _ => return,
+    };
+
+    IoHistograms::record(&device.device_name(), io_type, unsafe {
+        spdk_bdev_io_get_submit_tsc(bdev_io)
+    });
+}
+
 /// Forwards event to the high-level handler.
 fn dispatch_bdev_event(event: DeviceEventType, name: &str) {
     let mut map = BDEV_EVENT_DISPATCHER.lock().expect("lock poisoned");
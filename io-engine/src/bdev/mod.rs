@@ -1,5 +1,12 @@
 use async_trait::async_trait;
 
+pub use destroy::{
+    device_destroy_with,
+    device_destroying,
+    device_open_handles,
+    DestroyMode,
+    DestroyOutcome,
+};
 pub use dev::{device_create, device_destroy, device_lookup, device_open};
 pub use device::{bdev_event_callback, bdev_io_ctx_pool_init, SpdkBlockDevice};
 pub use nexus::{Nexus, NexusInfo, NexusState};
@@ -7,10 +14,15 @@ pub use nvmx::{
     nvme_io_ctx_pool_init,
     NvmeController,
     NvmeControllerState,
+    NvmeNegotiatedParams,
+    NvmePathInfo,
+    NvmeTimeouts,
+    NvmfRttTuning,
     NVME_CONTROLLERS,
 };
 
 mod aio;
+pub(crate) mod destroy;
 pub(crate) mod dev;
 use crate::core::{MayastorEnvironment, PtplProps};
 pub(crate) use dev::uri;
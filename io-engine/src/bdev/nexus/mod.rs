@@ -1,5 +1,6 @@
 #![allow(clippy::vec_box)]
 
+use once_cell::sync::Lazy;
 use std::{pin::Pin, sync::atomic::AtomicBool};
 
 use crate::core::VerboseError;
@@ -7,38 +8,71 @@ use futures::{future::Future, FutureExt};
 
 mod nexus_bdev;
 mod nexus_bdev_children;
+mod nexus_bdev_enospc;
 mod nexus_bdev_error;
+mod nexus_bdev_freeze;
+mod nexus_bdev_probe;
 mod nexus_bdev_rebuild;
 mod nexus_bdev_snapshot;
+mod nexus_bdev_teardown;
 mod nexus_channel;
 mod nexus_child;
+mod nexus_consistency_group;
+mod nexus_core_stats;
+mod nexus_flush_policy;
 mod nexus_io;
+mod nexus_io_copy;
+mod nexus_io_errors;
 mod nexus_io_log;
+mod nexus_io_log_journal;
+mod nexus_io_split;
 mod nexus_io_subsystem;
+mod nexus_io_timeout;
 mod nexus_iter;
 mod nexus_module;
 mod nexus_nbd;
 mod nexus_persistence;
+mod nexus_preflight;
+mod nexus_read_cache;
 mod nexus_share;
+mod nexus_write_cache;
 
 use crate::bdev::nexus::nexus_iter::NexusIterMut;
 pub(crate) use nexus_bdev::NEXUS_PRODUCT_ID;
 pub use nexus_bdev::{
     nexus_create,
     nexus_create_v2,
+    set_ana_group_state,
     Nexus,
     NexusNvmeParams,
     NexusNvmePreemption,
     NexusOperation,
+    NexusShutdownReason,
     NexusState,
     NexusStatus,
+    NexusStatusReason,
     NexusTarget,
     NvmeAnaState,
     NvmeReservation,
+    NEXUS_ALIAS_MAX_LEN,
 };
+pub use nexus_bdev_enospc::{NexusEnospcPolicy, NEXUS_ENOSPC_RETRY_INTERVAL};
 pub(crate) use nexus_bdev_error::nexus_err;
 pub use nexus_bdev_error::Error;
+pub use nexus_bdev_freeze::{NexusFreeze, NEXUS_FREEZE_MAX_TIMEOUT};
+pub use nexus_bdev_rebuild::{
+    NEXUS_REBUILD_HISTORY_MAX,
+    REBUILD_HISTORY_STASH_MAX,
+};
+use nexus_bdev_rebuild::rebuild_settings_key;
+pub use nexus_bdev_teardown::{
+    NexusTeardownPhase,
+    NexusTeardownStep,
+    NEXUS_TEARDOWN_REBUILD_TIMEOUT,
+};
+use nexus_channel::ChildHandle;
 pub(crate) use nexus_channel::{DrEvent, IoMode, NexusChannel};
+pub(crate) use nexus_child::nvme_resv_holder;
 pub use nexus_child::{
     ChildError,
     ChildState,
@@ -47,14 +81,37 @@ pub use nexus_child::{
     FaultReason,
     NexusChild,
 };
+pub use nexus_consistency_group::{
+    consistency_group_create,
+    consistency_group_destroy,
+    consistency_group_lookup,
+    consistency_groups,
+    ConsistencyGroup,
+};
+use nexus_core_stats::NexusCoreCounters;
+pub use nexus_core_stats::NexusCoreStats;
+pub use nexus_flush_policy::{NexusFlushPolicy, NexusFlushTarget};
 use nexus_io::{NexusBio, NioCtx};
+pub use nexus_io_copy::NexusIoCopyStats;
+use nexus_io_copy::{NexusIoCopy, NexusIoCopyCounters};
+use nexus_io_errors::NexusIoErrorCounters;
+pub use nexus_io_errors::{
+    NexusIoErrorClass,
+    NexusIoErrorStats,
+    NEXUS_IO_TRANSIENT_RETRIES,
+};
 use nexus_io_log::{IOLog, IOLogChannel};
+pub use nexus_io_log_journal::NexusIoLogJournalStats;
+pub(crate) use nexus_io_log_journal::{IOLogJournal, IOLogJournalSlot};
+pub use nexus_io_split::NexusIoSplitStats;
+use nexus_io_split::{NexusIoSplit, NexusIoSplitCounters};
 use nexus_io_subsystem::NexusIoSubsystem;
 pub use nexus_io_subsystem::NexusPauseState;
 pub use nexus_iter::{
     nexus_iter,
     nexus_iter_mut,
     nexus_lookup,
+    nexus_lookup_id_mut,
     nexus_lookup_mut,
     nexus_lookup_name_uuid,
     nexus_lookup_uuid_mut,
@@ -62,8 +119,31 @@ pub use nexus_iter::{
 pub(crate) use nexus_module::{NexusModule, NEXUS_MODULE_NAME};
 pub(crate) use nexus_nbd::{NbdDisk, NbdError};
 pub(crate) use nexus_persistence::PersistOp;
-pub use nexus_persistence::{ChildInfo, NexusInfo};
+pub use nexus_persistence::{
+    ChildInfo,
+    ExcludedChild,
+    NexusInfo,
+    NexusRecoveryPlan,
+};
+pub use nexus_preflight::{
+    nexus_preflight,
+    NexusChildPreflight,
+    NexusPreflight,
+    NexusPreflightReservation,
+};
+use nexus_read_cache::CacheLookup;
+pub(crate) use nexus_read_cache::NexusReadCache;
+pub use nexus_read_cache::{
+    NexusReadCacheStats,
+    NEXUS_READ_CACHE_DEFAULT_LINE_SIZE,
+};
 pub(crate) use nexus_share::NexusPtpl;
+use nexus_write_cache::CacheInsert;
+pub(crate) use nexus_write_cache::NexusWriteCache;
+pub use nexus_write_cache::{
+    NexusWriteCacheStats,
+    NEXUS_WRITE_CACHE_DEFAULT_SIZE,
+};
 
 pub use nexus_bdev_snapshot::{
     NexusReplicaSnapshotDescriptor,
@@ -188,3 +268,9 @@ pub static ENABLE_PARTIAL_REBUILD: AtomicBool = AtomicBool::new(true);
 
 /// Enables/disables nexus reset logic.
 pub static ENABLE_NEXUS_RESET: AtomicBool = AtomicBool::new(false);
+
+/// Enables/disables NVMe reservations on the nexus children.
+/// Enabled by default when NEXUS_NVMF_RESV_ENABLE is set.
+pub static ENABLE_NVMF_RESERVATIONS: Lazy<AtomicBool> = Lazy::new(|| {
+    AtomicBool::new(std::env::var("NEXUS_NVMF_RESV_ENABLE").is_ok())
+});
@@ -6,41 +6,66 @@
 
 use std::{
     cmp::min,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     convert::TryFrom,
     fmt::{Debug, Display, Formatter},
     marker::PhantomPinned,
     os::raw::c_void,
     pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use crossbeam::atomic::AtomicCell;
 use futures::channel::oneshot;
+use once_cell::sync::OnceCell;
 use serde::Serialize;
 use snafu::ResultExt;
 use uuid::Uuid;
 
 use super::{
+    nexus_bdev_teardown::NexusTeardown,
     nexus_err,
+    nexus_io_log_journal::NexusIoLogJournalDevice,
+    nexus_iter,
+    nexus_lookup,
     nexus_lookup_name_uuid,
+    nexus_read_cache::NexusReadCacheDevice,
     DrEvent,
     Error,
     NbdDisk,
     NexusBio,
     NexusChannel,
     NexusChild,
+    NexusCoreCounters,
+    NexusEnospcPolicy,
+    NexusFlushPolicy,
+    NexusFreeze,
+    NexusIoCopyCounters,
+    NexusIoErrorCounters,
+    NexusIoSplitCounters,
     NexusModule,
+    NexusTeardownPhase,
+    NexusTeardownStep,
+    NexusWriteCache,
     PersistOp,
+    NEXUS_TEARDOWN_REBUILD_TIMEOUT,
 };
 
 use crate::{
     bdev::{
+        dev::device_name,
         device_destroy,
+        device_destroy_with,
+        device_lookup,
         nexus::{
             nexus_io_subsystem::NexusPauseState,
             nexus_persistence::PersistentNexusInfo,
             NexusIoSubsystem,
         },
+        DestroyMode,
     },
     core::{
         partition,
@@ -52,7 +77,7 @@ use crate::{
         Share,
         VerboseError,
     },
-    rebuild::HistoryRecord,
+    rebuild::{HistoryRecord, RebuildPriority},
     subsys::NvmfSubsystem,
 };
 
@@ -73,6 +98,9 @@ pub static NVME_MAX_CNTLID: u16 = 0xffef;
 
 pub(crate) static NEXUS_PRODUCT_ID: &str = "Nexus CAS Driver v0.0.1";
 
+/// Maximum length of the alias of a nexus, in bytes.
+pub const NEXUS_ALIAS_MAX_LEN: usize = 64;
+
 /// TODO
 #[derive(Debug)]
 pub enum NexusTarget {
@@ -89,6 +117,7 @@ pub enum NexusOperation {
     ReplicaOnline,
     ReplicaFault,
     NexusSnapshot,
+    NexusFreeze,
 }
 
 /// TODO
@@ -176,6 +205,9 @@ pub struct NexusNvmeParams {
     pub(crate) resv_type: NvmeReservation,
     /// NVMe Preempting policy.
     pub(crate) preempt_policy: NexusNvmePreemption,
+    /// Logical block size of the namespace in bytes, which all children must
+    /// have. Zero to use the block size of the children.
+    pub(crate) block_size: u64,
 }
 
 impl Default for NexusNvmeParams {
@@ -187,6 +219,7 @@ impl Default for NexusNvmeParams {
             preempt_key: None,
             resv_type: NvmeReservation::WriteExclusiveAllRegs,
             preempt_policy: NexusNvmePreemption::ArgKey,
+            block_size: 0,
         }
     }
 }
@@ -219,6 +252,15 @@ impl NexusNvmeParams {
     pub fn set_preempt_policy(&mut self, preempt_policy: NexusNvmePreemption) {
         self.preempt_policy = preempt_policy;
     }
+    /// Set the logical block size.
+    pub fn set_block_size(&mut self, block_size: u64) {
+        self.block_size = block_size;
+    }
+    /// Check if the logical block size is valid: 512 bytes (or 512e), 4 KiB
+    /// (4Kn), or zero to use the block size of the children.
+    pub fn block_size_valid(&self) -> bool {
+        matches!(self.block_size, 0 | 512 | 4096)
+    }
     /// Check if reservations are enabled.
     pub fn reservations_enabled(&self) -> bool {
         self.resv_key != 0
@@ -267,6 +309,65 @@ pub struct Nexus<'n> {
     pub(super) rebuild_history: parking_lot::Mutex<Vec<HistoryRecord>>,
     /// Flag to control shutdown from I/O path.
     pub(crate) shutdown_requested: AtomicCell<bool>,
+    /// Reason the nexus is being or has been shut down.
+    pub(super) shutdown_reason: AtomicCell<Option<NexusShutdownReason>>,
+    /// Active consistency freeze, if any.
+    pub(super) freeze: parking_lot::Mutex<Option<NexusFreeze>>,
+    /// Number of client I/Os currently in flight on the nexus.
+    pub(super) client_io_depth: Arc<AtomicUsize>,
+    /// Behaviour when the children run out of space.
+    pub(super) enospc_policy: AtomicCell<NexusEnospcPolicy>,
+    /// Set when resubmission of writes parked due to ENOSPC is scheduled.
+    pub(super) enospc_retry_scheduled: AtomicCell<bool>,
+    /// Counters of the child I/O errors, per error class.
+    pub(super) io_errors: NexusIoErrorCounters,
+    /// Counters of the data copies of the reads and writes.
+    pub(super) io_copies: NexusIoCopyCounters,
+    /// Counters of the client I/Os, per core.
+    pub(super) core_stats: NexusCoreCounters,
+    /// Counters of the reads and writes split into several child I/Os.
+    pub(super) io_splits: NexusIoSplitCounters,
+    /// Maximum I/O size in bytes, zero if unlimited.
+    pub(super) max_io_size: AtomicCell<u64>,
+    /// Preferred rebuild sources of the children, by destination child URI.
+    pub(super) rebuild_sources: parking_lot::Mutex<HashMap<String, String>>,
+    /// Rebuild priorities of the children, by destination child URI.
+    pub(super) rebuild_priorities:
+        parking_lot::Mutex<HashMap<String, RebuildPriority>>,
+    /// Serve reads from a local child while it is being rebuilt.
+    pub(super) copy_on_read: AtomicCell<bool>,
+    /// Stripe the rebuild reads over all the healthy children.
+    pub(super) striped_rebuild: AtomicCell<bool>,
+    /// Size of the rebuild segments, in bytes. Zero selects the node default.
+    pub(super) rebuild_segment_size: AtomicCell<u64>,
+    /// Number of concurrent rebuild copy tasks. Zero selects the node
+    /// default.
+    pub(super) rebuild_segment_tasks: AtomicCell<usize>,
+    /// Exclude the remote children from the read path while a healthy local
+    /// child exists.
+    pub(super) prefer_local_reads: AtomicCell<bool>,
+    /// Time after which a read outstanding on a child is aborted and
+    /// resubmitted to another child, in microseconds. Zero disables it.
+    pub(super) child_io_timeout_us: AtomicCell<u64>,
+    /// Set when a scan for timed out reads is scheduled.
+    pub(super) io_timeout_scan_scheduled: AtomicCell<bool>,
+    /// Interval between the health probes of the children, in milliseconds.
+    /// Zero disables them.
+    pub(super) child_probe_interval_ms: AtomicCell<u64>,
+    /// Generation of the running child prober.
+    pub(super) child_probe_generation: AtomicCell<u64>,
+    /// Flush policy, set when the nexus is published.
+    pub(super) flush_policy: AtomicCell<NexusFlushPolicy>,
+    /// Human-friendly alias of the nexus, if any.
+    alias: parking_lot::Mutex<Option<String>>,
+    /// ANA group of the published nexus, if any.
+    ana_group: parking_lot::Mutex<Option<u32>>,
+    /// Volatile write cache, if enabled.
+    pub(super) write_cache: OnceCell<Arc<NexusWriteCache>>,
+    /// Read cache on a local device, if attached.
+    pub(super) read_cache: Option<NexusReadCacheDevice>,
+    /// Journal of the I/O logs on a local device, if attached.
+    pub(super) io_log_journal: Option<NexusIoLogJournalDevice>,
     /// Prevent auto-Unpin.
     _pin: PhantomPinned,
     /// Initiators.
@@ -311,6 +412,81 @@ impl Display for NexusStatus {
     }
 }
 
+/// Reason the nexus was shut down.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum NexusShutdownReason {
+    /// Shut down on request.
+    Requested,
+    /// Shut down itself after a reservation conflict on a child: another
+    /// nexus has taken over the volume.
+    ReservationConflict,
+    /// Shut down itself after failing to save its state in the persistent
+    /// store.
+    PersistenceFailure,
+}
+
+impl Display for NexusShutdownReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Requested => "shutdown requested",
+                Self::ReservationConflict => "reservation conflict on a child",
+                Self::PersistenceFailure =>
+                    "failed to save the nexus state in the persistent store",
+            }
+        )
+    }
+}
+
+/// Reason of the status of the nexus.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum NexusStatusReason {
+    /// All children are healthy.
+    None,
+    /// The nexus is being created.
+    Initializing,
+    /// The nexus is closed.
+    Closed,
+    /// The nexus has no children.
+    NoChildren,
+    /// None of the children is healthy.
+    NoHealthyChild,
+    /// Some children are not healthy.
+    ChildrenUnhealthy { healthy: usize, total: usize },
+    /// The healthy children are not a majority of the children, which
+    /// flushes must complete on under the quorum flush policy.
+    QuorumLost { healthy: usize, total: usize },
+    /// The nexus is being or has been shut down.
+    Shutdown(NexusShutdownReason),
+}
+
+impl Display for NexusStatusReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "all children are healthy"),
+            Self::Initializing => write!(f, "nexus is being created"),
+            Self::Closed => write!(f, "nexus is closed"),
+            Self::NoChildren => write!(f, "nexus has no children"),
+            Self::NoHealthyChild => write!(f, "no child is healthy"),
+            Self::ChildrenUnhealthy {
+                healthy,
+                total,
+            } => write!(f, "{healthy} of {total} children are healthy"),
+            Self::QuorumLost {
+                healthy,
+                total,
+            } => write!(
+                f,
+                "{healthy} of {total} children are healthy, \
+                which is not a flush quorum"
+            ),
+            Self::Shutdown(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
 /// Nexus state enumeration.
 #[derive(Debug, Serialize, Clone, Copy, PartialEq, PartialOrd)]
 pub enum NexusState {
@@ -375,6 +551,33 @@ impl<'n> Nexus<'n> {
             event_sink: None,
             rebuild_history: parking_lot::Mutex::new(Vec::new()),
             shutdown_requested: AtomicCell::new(false),
+            shutdown_reason: AtomicCell::new(None),
+            freeze: parking_lot::Mutex::new(None),
+            client_io_depth: Default::default(),
+            enospc_policy: AtomicCell::new(NexusEnospcPolicy::default()),
+            enospc_retry_scheduled: AtomicCell::new(false),
+            io_errors: Default::default(),
+            io_copies: Default::default(),
+            core_stats: Default::default(),
+            io_splits: Default::default(),
+            max_io_size: AtomicCell::new(0),
+            rebuild_sources: parking_lot::Mutex::new(HashMap::new()),
+            rebuild_priorities: parking_lot::Mutex::new(HashMap::new()),
+            copy_on_read: AtomicCell::new(false),
+            striped_rebuild: AtomicCell::new(false),
+            rebuild_segment_size: AtomicCell::new(0),
+            rebuild_segment_tasks: AtomicCell::new(0),
+            prefer_local_reads: AtomicCell::new(false),
+            child_io_timeout_us: AtomicCell::new(0),
+            io_timeout_scan_scheduled: AtomicCell::new(false),
+            child_probe_interval_ms: AtomicCell::new(0),
+            child_probe_generation: AtomicCell::new(0),
+            flush_policy: AtomicCell::new(NexusFlushPolicy::default()),
+            alias: parking_lot::Mutex::new(None),
+            ana_group: parking_lot::Mutex::new(None),
+            write_cache: OnceCell::new(),
+            read_cache: None,
+            io_log_journal: None,
             _pin: Default::default(),
         };
 
@@ -398,6 +601,7 @@ impl<'n> Nexus<'n> {
             // Set the nexus UUID to be the specified nexus UUID, otherwise
             // inherit the bdev UUID.
             n.nexus_uuid = nexus_uuid.unwrap_or_else(|| n.bdev().uuid());
+            n.restore_rebuild_history();
 
             // Set I/O subsystem.
             n.io_subsystem = Some(NexusIoSubsystem::new(
@@ -455,6 +659,61 @@ impl<'n> Nexus<'n> {
         self.nexus_uuid
     }
 
+    /// Returns the human-friendly alias of the nexus, if any.
+    pub fn alias(&self) -> Option<String> {
+        self.alias.lock().clone()
+    }
+
+    /// Sets or clears the human-friendly alias of the nexus. The alias is
+    /// informational only: nexuses are looked up by name or uuid.
+    pub fn set_alias(&self, alias: Option<&str>) -> Result<(), Error> {
+        if let Some(alias) = alias {
+            if alias.is_empty()
+                || alias.len() > NEXUS_ALIAS_MAX_LEN
+                || alias.trim() != alias
+                || alias.chars().any(char::is_control)
+            {
+                return Err(Error::InvalidArguments {
+                    name: self.name.clone(),
+                    args: format!(
+                        "invalid alias '{alias}': must have 1 to \
+                        {NEXUS_ALIAS_MAX_LEN} printable characters, without \
+                        leading or trailing whitespace"
+                    ),
+                });
+            }
+        }
+
+        info!("{self:?}: setting alias to {alias:?}");
+        *self.alias.lock() = alias.map(str::to_string);
+        Ok(())
+    }
+
+    /// Returns the ANA group of the published nexus, if any.
+    pub fn ana_group(&self) -> Option<u32> {
+        *self.ana_group.lock()
+    }
+
+    /// Sets or clears the ANA group of the nexus. The nexuses of a group have
+    /// their ANA state set together, see `set_ana_group_state`.
+    pub fn set_ana_group(&self, ana_group: Option<u32>) -> Result<(), Error> {
+        if ana_group == Some(0) {
+            return Err(Error::InvalidArguments {
+                name: self.name.clone(),
+                args: "ANA group 0 is reserved".to_string(),
+            });
+        }
+
+        info!("{self:?}: setting ANA group to {ana_group:?}");
+        *self.ana_group.lock() = ana_group;
+        Ok(())
+    }
+
+    /// Returns the number of client I/Os currently in flight on the nexus.
+    pub fn client_io_depth(&self) -> usize {
+        self.client_io_depth.load(Ordering::Relaxed)
+    }
+
     /// Add new initiator to the Nexus
     #[allow(dead_code)]
     pub(crate) fn add_initiator(&self, initiator: &str) {
@@ -596,6 +855,36 @@ impl<'n> Nexus<'n> {
         }
     }
 
+    /// Checks if the remote children are excluded from the read path while a
+    /// healthy local child exists.
+    pub fn prefer_local_reads(&self) -> bool {
+        self.prefer_local_reads.load()
+    }
+
+    /// Enables or disables preferred-local reads, and rebuilds the read set of
+    /// the I/O channels accordingly.
+    pub async fn set_prefer_local_reads(&self, enabled: bool) {
+        info!("{self:?}: setting preferred-local reads to {enabled}");
+        if self.prefer_local_reads.swap(enabled) != enabled {
+            self.reconfigure(DrEvent::ReadPolicy).await;
+        }
+    }
+
+    /// Checks if the reads are served by the local children only, i.e.
+    /// preferred-local reads are enabled and a healthy local child exists.
+    pub(super) fn local_reads_only(&self) -> bool {
+        self.prefer_local_reads()
+            && self
+                .children_iter()
+                .any(|c| c.is_healthy() && c.is_local() == Some(true))
+    }
+
+    /// Checks if the child is part of the read set of the nexus.
+    pub fn is_child_reader(&self, child: &NexusChild<'n>) -> bool {
+        child.is_healthy()
+            && (!self.local_reads_only() || child.is_local() == Some(true))
+    }
+
     /// Reconfigures the child event handler.
     pub(crate) async fn reconfigure(&self, event: DrEvent) {
         info!(
@@ -639,8 +928,9 @@ impl<'n> Nexus<'n> {
         // Determine Nexus block size and data start and end offsets.
         let mut start_blk = 0;
         let mut end_blk = 0;
-        let mut blk_size = 0;
+        let mut blk_size = self.nvme_params.block_size;
         let mut min_dev_size = u64::MAX;
+        let mut incompatible = Vec::new();
 
         for child in self.children_iter() {
             let dev = match child.get_device() {
@@ -664,9 +954,8 @@ impl<'n> Nexus<'n> {
             if blk_size == 0 {
                 blk_size = bs;
             } else if bs != blk_size {
-                return Err(Error::MixedBlockSizes {
-                    name: self.name.clone(),
-                });
+                incompatible.push(format!("{} ({bs} bytes)", child.uri()));
+                continue;
             }
 
             match partition::calc_data_partition(self.req_size(), nb, bs) {
@@ -696,6 +985,14 @@ impl<'n> Nexus<'n> {
             }
         }
 
+        if !incompatible.is_empty() {
+            return Err(Error::IncompatibleBlockSize {
+                name,
+                block_size: blk_size,
+                children: incompatible.join(", "),
+            });
+        }
+
         unsafe {
             self.as_mut().set_data_ent_offset(start_blk);
             self.as_mut().set_block_len(blk_size as u32);
@@ -778,31 +1075,48 @@ impl<'n> Nexus<'n> {
 
     /// Destroy the Nexus.
     pub async fn destroy(self: Pin<&mut Self>) -> Result<(), Error> {
-        self.destroy_ext(false).await
+        self.destroy_ext(false).await.map(|_| ())
     }
 
-    /// Destroy the Nexus.
+    /// Destroy the Nexus, returning the completed teardown phases.
+    /// Rebuild jobs are aborted and drained before the nexus is unshared
+    /// and its children closed.
     /// # Arguments
     /// * `sigterm`: Indicates whether this is as a result of process
     ///   termination.
     pub async fn destroy_ext(
         mut self: Pin<&mut Self>,
         sigterm: bool,
-    ) -> Result<(), Error> {
+    ) -> Result<Vec<NexusTeardownStep>, Error> {
         info!("{:?}: destroying nexus...", self);
 
+        let mut teardown = NexusTeardown::default();
+
+        // Rebuild jobs hold descriptors on the children, they must be gone
+        // before anything else is torn down.
+        teardown.enter(NexusTeardownPhase::AbortRebuilds);
+        self.abort_rebuilds(NEXUS_TEARDOWN_REBUILD_TIMEOUT).await?;
+
+        teardown.enter(NexusTeardownPhase::Unshare);
         self.as_mut().unshare_nexus().await?;
 
-        // wait for all rebuild jobs to be cancelled before proceeding with the
-        // destruction of the nexus
-        let child_uris = self.child_uris();
-        for child in child_uris {
-            self.as_mut().cancel_rebuild_jobs(&child).await;
-        }
+        // Give the cached writes a chance to reach the children.
+        teardown.enter(NexusTeardownPhase::DrainWriteCache);
+        self.drain_write_cache().await;
 
+        teardown.enter(NexusTeardownPhase::DetachReadCache);
+        self.as_mut().detach_read_cache().await;
+
+        // The slots of the I/O log journal are kept, to be recovered when the
+        // nexus is created again.
+        teardown.enter(NexusTeardownPhase::CloseIoLogJournal);
+        self.as_mut().close_io_log_journal(false).await;
+
+        teardown.enter(NexusTeardownPhase::CloseChildren);
         self.close_children().await;
 
         // Persist the fact that the nexus destruction has completed.
+        teardown.enter(NexusTeardownPhase::Persist);
         self.persist(PersistOp::Shutdown).await.ok();
 
         if !sigterm {
@@ -814,6 +1128,9 @@ impl<'n> Nexus<'n> {
             }
         }
 
+        self.stash_rebuild_history();
+
+        teardown.enter(NexusTeardownPhase::Unregister);
         unsafe {
             let name = self.name.clone();
 
@@ -822,7 +1139,7 @@ impl<'n> Nexus<'n> {
                 Ok(_) => {
                     info!("Nexus '{name}': nexus destroyed ok");
                     self.event(EventAction::Delete).generate();
-                    Ok(())
+                    Ok(teardown.finish())
                 }
                 Err(err) => {
                     error!(
@@ -918,6 +1235,8 @@ impl<'n> Nexus<'n> {
                 // Save current state and mark nexus as being under shutdown.
                 t => {
                     *s = NexusState::ShuttingDown;
+                    self.shutdown_reason
+                        .store(Some(NexusShutdownReason::Requested));
                     t
                 }
             }
@@ -938,6 +1257,7 @@ impl<'n> Nexus<'n> {
 
             // Restore previous nexus state.
             *self.state.lock() = prev_state;
+            self.shutdown_reason.store(None);
             error
         })?;
 
@@ -1036,32 +1356,64 @@ impl<'n> Nexus<'n> {
     /// At least one child must be online
     ///
     /// Faulted
-    /// No child is online so the nexus is faulted
+    /// No child is online so the nexus is faulted, or, under the quorum
+    /// flush policy, the online children are not a majority
     /// This may be made more configurable in the future
     pub fn status(&self) -> NexusStatus {
+        self.status_with_reason().0
+    }
+
+    /// Status of the nexus, and the reason of that status.
+    pub fn status_with_reason(&self) -> (NexusStatus, NexusStatusReason) {
+        let shutdown_reason = || {
+            NexusStatusReason::Shutdown(
+                self.shutdown_reason
+                    .load()
+                    .unwrap_or(NexusShutdownReason::Requested),
+            )
+        };
+
         match *self.state.lock() {
-            NexusState::Init => NexusStatus::Degraded,
-            NexusState::Closed => NexusStatus::Faulted,
-            NexusState::ShuttingDown => NexusStatus::ShuttingDown,
-            NexusState::Shutdown => NexusStatus::Shutdown,
+            NexusState::Init => {
+                (NexusStatus::Degraded, NexusStatusReason::Initializing)
+            }
+            NexusState::Closed => {
+                (NexusStatus::Faulted, NexusStatusReason::Closed)
+            }
+            NexusState::ShuttingDown => {
+                (NexusStatus::ShuttingDown, shutdown_reason())
+            }
+            NexusState::Shutdown => (NexusStatus::Shutdown, shutdown_reason()),
             NexusState::Open | NexusState::Reconfiguring => {
-                if self
-                    .children
-                    .iter()
+                let total = self.children.len();
+                let healthy =
+                    self.children.iter().filter(|c| c.is_healthy()).count();
+
+                if total == 0 {
+                    (NexusStatus::Faulted, NexusStatusReason::NoChildren)
+                } else if healthy == 0 {
+                    (NexusStatus::Faulted, NexusStatusReason::NoHealthyChild)
+                } else if healthy == total {
                     // All children are online, so the Nexus is also online
-                    .all(|c| c.is_healthy())
-                {
-                    NexusStatus::Online
-                } else if self
-                    .children
-                    .iter()
-                    // at least one child online, so the Nexus is also online
-                    .any(|c| c.is_healthy())
-                {
-                    NexusStatus::Degraded
+                    (NexusStatus::Online, NexusStatusReason::None)
+                } else if self.flush_quorum() && healthy * 2 <= total {
+                    // Flushes cannot complete on a majority of the children.
+                    (
+                        NexusStatus::Faulted,
+                        NexusStatusReason::QuorumLost {
+                            healthy,
+                            total,
+                        },
+                    )
                 } else {
-                    // nexus has no children or at least no child is online
-                    NexusStatus::Faulted
+                    // at least one child online, so the Nexus is also online
+                    (
+                        NexusStatus::Degraded,
+                        NexusStatusReason::ChildrenUnhealthy {
+                            healthy,
+                            total,
+                        },
+                    )
                 }
             }
         }
@@ -1226,6 +1578,9 @@ impl<'n> BdevOps for Nexus<'n> {
             // we always assume the device supports read/write commands
             // allow NVMe Admin as it is needed for local replicas
             IoType::Read | IoType::Write | IoType::NvmeAdmin => true,
+            // flushes drain the write cache, even if the children do not
+            // support them
+            IoType::Flush if self.write_cache().is_some() => true,
             IoType::Flush
             | IoType::Reset
             | IoType::Unmap
@@ -1266,6 +1621,42 @@ impl<'n> BdevOps for Nexus<'n> {
     }
 }
 
+/// Sets the ANA state of all the published nexuses of the given ANA group,
+/// e.g. to fail over all the volumes of a node at once. ANA groups are
+/// scoped to a subsystem in NVMe, and each nexus is a subsystem on its own:
+/// a group is a set of nexuses whose state is changed together, rather than
+/// an NVMe ANA group. Returns the outcome for each nexus of the group.
+pub async fn set_ana_group_state(
+    ana_group: u32,
+    ana_state: NvmeAnaState,
+) -> Vec<(String, Result<(), Error>)> {
+    let names = nexus_iter()
+        .filter(|n| n.ana_group() == Some(ana_group))
+        .map(|n| n.name.clone())
+        .collect::<Vec<_>>();
+
+    info!(
+        "Setting ANA state of group {ana_group} ({} nexuses) to {ana_state:?}",
+        names.len()
+    );
+
+    let mut results = Vec::with_capacity(names.len());
+    for name in names {
+        // The nexus may have been destroyed in the meantime.
+        let Some(nexus) = nexus_lookup(&name) else {
+            continue;
+        };
+        let res = nexus.set_ana_state(ana_state).await;
+        if let Err(error) = &res {
+            error!(
+                "{name}: failed to set ANA state of group {ana_group}: {error}"
+            );
+        }
+        results.push((name, res));
+    }
+    results
+}
+
 /// Create a new nexus and bring it online.
 /// If we fail to create any of the children, then we fail the whole operation.
 /// On failure, we must cleanup by destroying any children that were
@@ -1316,6 +1707,17 @@ pub async fn nexus_create_v2(
             args,
         });
     }
+    if !nvme_params.block_size_valid() {
+        let args = format!(
+            "invalid logical block size {}: must be 512 or 4096",
+            nvme_params.block_size
+        );
+        error!("failed to create nexus {}: {}", name, args);
+        return Err(Error::InvalidArguments {
+            name: name.to_owned(),
+            args,
+        });
+    }
     if !nvme_params.reservations_enabled() {
         warn!(
             "Not using nvme reservations for nexus {}: {:?}",
@@ -1364,6 +1766,38 @@ pub async fn nexus_create_v2(
     }
 }
 
+/// Checks if the device of the given URI exists.
+fn device_exists(uri: &str) -> bool {
+    device_name(uri)
+        .ok()
+        .and_then(|name| device_lookup(&name))
+        .is_some()
+}
+
+/// Rolls back a failed nexus creation: destroys the devices of the given
+/// child URIs which the creation left behind, and describes the outcome.
+/// Only the devices which did not exist before the creation are given, so
+/// that devices owned by someone else are never destroyed.
+async fn rollback_child_devices(name: &str, created: &[&String]) -> String {
+    let mut leftovers = Vec::new();
+    for uri in created.iter().filter(|u| device_exists(u)) {
+        warn!("Nexus '{name}': destroying child device '{uri}' left behind");
+        if let Err(error) = device_destroy_with(uri, DestroyMode::Force).await {
+            error!(
+                "Nexus '{name}': failed to destroy child device '{uri}': \
+                {error}"
+            );
+            leftovers.push(uri.as_str());
+        }
+    }
+
+    if leftovers.is_empty() {
+        "completed".to_string()
+    } else {
+        format!("failed, devices left behind: {}", leftovers.join(", "))
+    }
+}
+
 async fn nexus_create_internal(
     name: &str,
     size: u64,
@@ -1413,6 +1847,12 @@ async fn nexus_create_internal(
         nexus_info_key,
     );
 
+    // Child devices created by this call, to be destroyed on failure.
+    let created = children
+        .iter()
+        .filter(|u| !device_exists(u))
+        .collect::<Vec<_>>();
+
     for uri in children {
         if let Err(error) = nexus_bdev.data_mut().new_child(uri).await {
             error!(
@@ -1428,9 +1868,13 @@ async fn nexus_create_internal(
                 uri
             );
 
-            return Err(Error::CreateChild {
-                source: error,
+            return Err(Error::CreateRollback {
+                source: Box::new(Error::CreateChild {
+                    source: error,
+                    name: name.to_owned(),
+                }),
                 name: name.to_owned(),
+                rollback: rollback_child_devices(name, &created).await,
             });
         }
     }
@@ -1469,9 +1913,13 @@ async fn nexus_create_internal(
                 }
             }
 
-            Err(Error::NexusCreate {
+            Err(Error::CreateRollback {
+                rollback: rollback_child_devices(&name, &created).await,
+                source: Box::new(Error::NexusCreate {
+                    name: name.clone(),
+                    reason,
+                }),
                 name,
-                reason,
             })
         }
 
@@ -1482,7 +1930,11 @@ async fn nexus_create_internal(
                 error.verbose()
             );
             nexus_bdev.data().close_children().await;
-            Err(error)
+            Err(Error::CreateRollback {
+                source: Box::new(error),
+                name: name.to_owned(),
+                rollback: rollback_child_devices(name, &created).await,
+            })
         }
         Ok(_) => {
             info!("{:?}: nexus created ok", nexus_bdev.data());
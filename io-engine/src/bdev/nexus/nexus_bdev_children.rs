@@ -23,7 +23,12 @@
 //! When reconfiguring the nexus, we traverse all our children, create new IO
 //! channels for all children that are in the open state.
 
-use std::{cmp::min, pin::Pin};
+use std::{
+    cmp::min,
+    convert::TryFrom,
+    pin::Pin,
+    time::{Duration, Instant},
+};
 
 use snafu::ResultExt;
 
@@ -31,6 +36,7 @@ use super::{
     nexus_err,
     nexus_lookup,
     nexus_lookup_mut,
+    rebuild_settings_key,
     ChildState,
     ChildSyncState,
     Error,
@@ -40,24 +46,40 @@ use super::{
     Nexus,
     NexusChild,
     NexusOperation,
+    NexusShutdownReason,
     NexusState,
     NexusStatus,
     PersistOp,
 };
 
 use crate::{
-    bdev::{dev::device_name, device_create, device_destroy, device_lookup},
+    bdev::{
+        dev::device_name,
+        device_create,
+        device_destroy,
+        device_lookup,
+        uri::same_device,
+    },
     bdev_api::BdevError,
     core::{
         device_cmd_queue,
         DeviceCommand,
         DeviceEventListener,
         DeviceEventType,
+        LogicalVolume,
         Reactors,
+        SnapshotDescriptor,
+        SnapshotOps,
+        UntypedBdev,
         VerboseError,
     },
+    lvs::{Lvol, LvsLvol},
+    sleep::mayastor_sleep,
 };
 
+/// Interval at which the rebuilds awaited by an evacuation are checked.
+const EVACUATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 use spdk_rs::{ChannelTraverseStatus, IoDeviceChannelTraverse};
 
 impl<'n> Nexus<'n> {
@@ -108,27 +130,132 @@ impl<'n> Nexus<'n> {
         let status = self.as_mut().add_child_only(uri).await?;
 
         if !norebuild {
-            if let Err(e) = self.start_rebuild(uri).await {
-                // todo: CAS-253 retry starting the rebuild again when ready
-                error!(
-                    "Child added but rebuild failed to start: {}",
-                    e.verbose()
-                );
-                match self.child(uri) {
-                    Ok(child) => {
-                        child.close_faulted(FaultReason::RebuildFailed).await
-                    }
-                    Err(e) => error!(
-                        "Failed to find newly added child {}, error: {}",
-                        uri,
-                        e.verbose()
-                    ),
-                };
-            }
+            self.start_added_child_rebuild(uri).await;
         }
         Ok(status)
     }
 
+    /// Adds a new child which has been created as a clone of the given
+    /// snapshot, and rebuilds only the segments written on the source child
+    /// since that snapshot was taken.
+    ///
+    /// The new child must be a clone of the snapshot, and the snapshot must
+    /// belong to the most recent snapshot of every healthy child of the
+    /// nexus, any of which may be the rebuild source. The lineage can only
+    /// be verified for local children: the seeded add is refused if the new
+    /// child or a healthy child is remote.
+    pub async fn add_child_seeded(
+        mut self: Pin<&mut Self>,
+        uri: &str,
+        snapshot_uuid: &str,
+    ) -> Result<NexusStatus, Error> {
+        self.check_nexus_operation(NexusOperation::ReplicaAdd)?;
+
+        let sources = self
+            .children_iter()
+            .filter(|c| c.is_healthy() && !same_device(c.uri(), uri))
+            .map(|c| c.uri().to_owned())
+            .collect::<Vec<_>>();
+        self.verify_seed_lineage(uri, snapshot_uuid, &sources)?;
+
+        let status = self.as_mut().add_child_only(uri).await?;
+
+        info!(
+            "{self:?}: child '{uri}' is seeded from snapshot \
+            '{snapshot_uuid}'"
+        );
+        self.child(uri)?
+            .set_seed_snapshot(Some(snapshot_uuid.to_string()));
+
+        self.start_added_child_rebuild(uri).await;
+        Ok(status)
+    }
+
+    /// Verifies that the given child is a clone of the given snapshot, and
+    /// that the snapshot belongs to the same nexus snapshot as the most
+    /// recent snapshot of each of the given sources, so that the child
+    /// misses only the segments written on the sources since then.
+    pub(super) fn verify_seed_lineage(
+        &self,
+        uri: &str,
+        snapshot_uuid: &str,
+        sources: &[String],
+    ) -> Result<(), Error> {
+        let mismatch = |child: &str| Error::SeedSnapshotMismatch {
+            child: child.to_owned(),
+            name: self.name.clone(),
+            snapshot: snapshot_uuid.to_owned(),
+        };
+
+        let seed = match self.seed_lineage_lvol(uri)?.is_snapshot_clone() {
+            Some(snapshot) if snapshot.uuid() == snapshot_uuid => snapshot,
+            _ => return Err(mismatch(uri)),
+        };
+        // The snapshots of the replicas of a nexus snapshot share its
+        // transaction.
+        let txn_of = |lvol: Lvol| {
+            lvol.snapshot_descriptor(None)
+                .and_then(|d| d.snapshot_params().txn_id())
+        };
+        let txn_id = txn_of(seed).ok_or_else(|| mismatch(uri))?;
+
+        for src in sources {
+            let lvol = self.seed_lineage_lvol(src)?;
+            let latest = lvol.latest_snapshot().and_then(txn_of);
+            if latest.as_ref() == Some(&txn_id) {
+                continue;
+            }
+
+            // A newer snapshot of the source holds writes the child would
+            // miss.
+            let stale = lvol.list_snapshot_by_source_uuid().iter().any(|d| {
+                d.snapshot_params().txn_id().as_ref() == Some(&txn_id)
+            });
+            return Err(if stale {
+                Error::SeedSnapshotStale {
+                    child: src.clone(),
+                    name: self.name.clone(),
+                    snapshot: snapshot_uuid.to_owned(),
+                }
+            } else {
+                mismatch(src)
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the local replica of the given child, whose snapshots can be
+    /// inspected.
+    fn seed_lineage_lvol(&self, uri: &str) -> Result<Lvol, Error> {
+        device_name(uri)
+            .ok()
+            .and_then(|name| UntypedBdev::lookup_by_name(&name))
+            .and_then(|bdev| Lvol::try_from(bdev).ok())
+            .ok_or_else(|| Error::SeedLineageUnknown {
+                child: uri.to_owned(),
+                name: self.name.clone(),
+            })
+    }
+
+    /// Starts rebuilding a newly added child. On failure, the child is
+    /// faulted.
+    async fn start_added_child_rebuild(&self, uri: &str) {
+        if let Err(e) = self.start_rebuild(uri).await {
+            // todo: CAS-253 retry starting the rebuild again when ready
+            error!("Child added but rebuild failed to start: {}", e.verbose());
+            match self.child(uri) {
+                Ok(child) => {
+                    child.close_faulted(FaultReason::RebuildFailed).await
+                }
+                Err(e) => error!(
+                    "Failed to find newly added child {}, error: {}",
+                    uri,
+                    e.verbose()
+                ),
+            };
+        }
+    }
+
     /// The child may require a rebuild first, so the nexus will
     /// transition to degraded mode when the addition has been successful.
     async fn add_child_only(
@@ -147,7 +274,8 @@ impl<'n> Nexus<'n> {
 
         let child_bdev = match device_lookup(&name) {
             Some(child) => {
-                if child.block_len() != self.block_len()
+                let block_len = child.block_len();
+                if block_len != self.block_len()
                     || self
                         .min_num_blocks()
                         .map_or(true, |n| n > child.num_blocks())
@@ -159,6 +287,13 @@ impl<'n> Nexus<'n> {
                         );
                     }
 
+                    if block_len != self.block_len() {
+                        return Err(Error::IncompatibleBlockSize {
+                            name: self.name.clone(),
+                            block_size: self.block_len(),
+                            children: format!("{uri} ({block_len} bytes)"),
+                        });
+                    }
                     return Err(Error::ChildGeometry {
                         child: name,
                         name: self.name.clone(),
@@ -254,7 +389,7 @@ impl<'n> Nexus<'n> {
 
     /// Checks if the nexus contains the given child uri.
     pub fn contains_child_uri(&self, uri: &str) -> bool {
-        self.children_iter().any(|c| c.uri() == uri)
+        self.children_iter().any(|c| same_device(c.uri(), uri))
     }
     /// Checks if the nexus contains the given child name.
     pub fn contains_child_name(&self, name: &str) -> bool {
@@ -307,6 +442,8 @@ impl<'n> Nexus<'n> {
         // Close and remove the child.
         let res = match self.lookup_child(uri) {
             Some(child) => {
+                let child_uri = child.uri().to_owned();
+
                 // Remove child from the I/O path.
                 if let Some(device) = child.get_device_name() {
                     self.disconnect_device(&device).await;
@@ -319,13 +456,24 @@ impl<'n> Nexus<'n> {
                     source: e,
                 });
 
+                let key = rebuild_settings_key(uri);
+                self.rebuild_sources
+                    .lock()
+                    .retain(|dst, src| *dst != key && !same_device(src, uri));
+                self.rebuild_priorities.lock().remove(&key);
+
+                if let Some(journal) = self.io_log_journal() {
+                    journal.release(&child_uri);
+                }
+
                 // Remove the child from the child list.
                 unsafe {
                     self.as_mut()
                         .unpin_mut()
                         .children
-                        .retain(|c| c.uri() != uri);
+                        .retain(|c| c.uri() != child_uri);
                 }
+                self.update_rebuild_queue();
 
                 res
             }
@@ -346,6 +494,81 @@ impl<'n> Nexus<'n> {
         res
     }
 
+    /// Removes a child once at least `min_synced` other children are fully
+    /// synced, so that removing it never drops the redundancy of the nexus
+    /// below that. Out-of-sync children are rebuilt, and awaited for at most
+    /// the given time.
+    pub async fn evacuate_child(
+        self: Pin<&mut Self>,
+        uri: &str,
+        min_synced: usize,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        info!(
+            "{self:?}: evacuate child request: '{uri}', \
+            {min_synced} synced child(ren) required"
+        );
+
+        self.check_nexus_operation(NexusOperation::ReplicaRemove)?;
+        self.check_child_remove_operation(uri)?;
+
+        let evacuation = |reason: String| Error::ChildEvacuation {
+            child: uri.to_owned(),
+            name: self.name.clone(),
+            reason,
+        };
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let others = self
+                .children_iter()
+                .filter(|c| !same_device(c.uri(), uri) && c.is_opened())
+                .collect::<Vec<_>>();
+            let synced = others.iter().filter(|c| c.is_healthy()).count();
+            if synced >= min_synced {
+                break;
+            }
+            if others.len() < min_synced {
+                return Err(evacuation(format!(
+                    "only {} other child(ren) are open, {min_synced} \
+                    synced required",
+                    others.len()
+                )));
+            }
+            if Instant::now() >= deadline {
+                return Err(evacuation(format!(
+                    "only {synced} other child(ren) are synced after \
+                    {timeout:?}, {min_synced} required"
+                )));
+            }
+
+            let unsynced = others
+                .iter()
+                .filter(|c| c.is_opened_unsync() && c.rebuild_job().is_none())
+                .map(|c| c.uri().to_owned())
+                .collect::<Vec<_>>();
+            for child in unsynced {
+                info!("{self:?}: evacuate child '{uri}': rebuilding '{child}'");
+                if let Err(e) = self.start_rebuild(&child).await {
+                    warn!(
+                        "{self:?}: evacuate child '{uri}': failed to start \
+                        the rebuild of '{child}': {}",
+                        e.verbose()
+                    );
+                }
+            }
+
+            if mayastor_sleep(EVACUATION_POLL_INTERVAL).await.is_err() {
+                return Err(evacuation(
+                    "failed to wait for the rebuilds".to_string(),
+                ));
+            }
+        }
+
+        info!("{self:?}: evacuate child '{uri}': redundancy ensured");
+        self.remove_child(uri).await
+    }
+
     /// Faults a child with the given reason.
     pub async fn fault_child(
         mut self: Pin<&mut Self>,
@@ -664,9 +887,11 @@ impl<'n> Nexus<'n> {
         })
     }
 
-    /// Looks up a child by its URI.
+    /// Looks up a child by its URI, which may differ from the URI of the
+    /// child in form only.
     pub fn lookup_child(&self, child_uri: &str) -> Option<&NexusChild<'n>> {
-        self.children_iter().find(|c| c.uri() == child_uri)
+        self.children_iter()
+            .find(|c| same_device(c.uri(), child_uri))
     }
 
     /// Looks up a child by its URI.
@@ -684,7 +909,10 @@ impl<'n> Nexus<'n> {
         self: Pin<&mut Self>,
         child_uri: &str,
     ) -> Option<&mut NexusChild<'n>> {
-        unsafe { self.children_iter_mut().find(|c| c.uri() == child_uri) }
+        unsafe {
+            self.children_iter_mut()
+                .find(|c| same_device(c.uri(), child_uri))
+        }
     }
 
     /// Looks up a child by its URI and returns a mutable reference.
@@ -800,7 +1028,7 @@ impl<'n> Nexus<'n> {
         // Otherwise, any reconfiguration (Nexus::reconfigure()) that may run
         // in parallel, would skip connecting both child's device as a writer
         // and child's I/O log.
-        let has_io_log = c.start_io_log();
+        let has_io_log = c.start_io_log(self.io_log_journal());
 
         // Fail and retire an open child.
         if Ok(ChildState::Open)
@@ -827,6 +1055,8 @@ impl<'n> Nexus<'n> {
 
             // Set the timestamp of this child fault.
             c.set_fault_timestamp();
+
+            self.update_rebuild_queue();
         } else {
             warn!("{c:?}: faulted with {reason}, already retired/retiring");
         }
@@ -1089,8 +1319,9 @@ impl<'n> Nexus<'n> {
         debug!("{self:?}: set I/O mode to {mode:?}: done");
     }
 
-    /// TODO
-    pub(super) fn try_self_shutdown(&self) {
+    /// Shuts the nexus down in the background, for the given reason, unless
+    /// it is already being shut down.
+    pub(super) fn try_self_shutdown(&self, reason: NexusShutdownReason) {
         let nexus_name = self.nexus_name().to_owned();
 
         Reactors::master().send_future(async move {
@@ -1112,11 +1343,13 @@ impl<'n> Nexus<'n> {
                             info!(
                                 nexus_name,
                                 nexus_state=%nexus_state,
+                                %reason,
                                 "Initiating self shutdown for nexus"
                             );
                         }
                     };
                     *s = NexusState::ShuttingDown;
+                    nexus.shutdown_reason.store(Some(reason));
                 }
 
                 // Step 1: Close I/O channels for all children.
@@ -0,0 +1,79 @@
+//! Implements the out-of-space (ENOSPC) policy of a nexus.
+//!
+//! By default, a child which fails a write with ENOSPC is faulted, like on any
+//! other I/O error. With the `Freeze` policy, writes which failed with ENOSPC
+//! on all of the nexus children are parked on their I/O channel instead, and
+//! the children are kept. Reads continue to be served, and the parked writes
+//! are periodically resubmitted until one of the replicas has space again.
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::{nexus_lookup_mut, Nexus};
+use crate::{core::Reactors, sleep::mayastor_sleep};
+
+/// Interval between resubmissions of writes parked due to ENOSPC.
+pub const NEXUS_ENOSPC_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Nexus behaviour when its children run out of space.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum NexusEnospcPolicy {
+    /// Children which fail a write with ENOSPC are faulted.
+    #[default]
+    Fault,
+    /// Writes which fail with ENOSPC on all children are parked and retried,
+    /// without faulting the children.
+    Freeze,
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the out-of-space policy of the nexus.
+    pub fn enospc_policy(&self) -> NexusEnospcPolicy {
+        self.enospc_policy.load()
+    }
+
+    /// Sets the out-of-space policy of the nexus.
+    pub fn set_enospc_policy(&self, policy: NexusEnospcPolicy) {
+        info!("{self:?}: setting ENOSPC policy to {policy:?}");
+        self.enospc_policy.store(policy);
+    }
+
+    /// Schedules resubmission of the writes parked due to ENOSPC, unless it
+    /// has been already scheduled.
+    pub(super) fn schedule_enospc_retry(&self) {
+        if self
+            .enospc_retry_scheduled
+            .compare_exchange(false, true)
+            .is_err()
+        {
+            return;
+        }
+
+        warn!(
+            "{self:?}: all children are out of space, \
+            writes are suspended until space is available"
+        );
+
+        let name = self.name.clone();
+        Reactors::master().send_future(async move {
+            if mayastor_sleep(NEXUS_ENOSPC_RETRY_INTERVAL).await.is_err() {
+                error!("Nexus '{name}': failed to wait for ENOSPC retry");
+            }
+
+            let Some(nexus) = nexus_lookup_mut(&name) else {
+                return;
+            };
+
+            // Writes which fail with ENOSPC again are parked again and
+            // schedule the next retry.
+            nexus.enospc_retry_scheduled.store(false);
+
+            debug!("{nexus:?}: resubmitting writes parked due to ENOSPC");
+            nexus
+                .traverse_io_channels_async((), |channel, _| {
+                    channel.resubmit_no_space();
+                })
+                .await;
+        });
+    }
+}
@@ -1,12 +1,19 @@
 use nix::errno::Errno;
 use snafu::Snafu;
-use tonic::{Code, Status};
+use tonic::Status;
 
 use super::{ChildError, NbdError, NexusPauseState};
 
 use crate::{
     bdev_api::BdevError,
-    core::{CoreError, VerboseError},
+    core::{CoreError, ToErrno, VerboseError},
+    grpc::{
+        child_error_code,
+        errno_status,
+        rebuild_error_code,
+        store_error_code,
+        GrpcErrorInfo,
+    },
     rebuild::RebuildError,
     store::store_defs::StoreError,
     subsys::NvmfError,
@@ -19,6 +26,18 @@ use crate::{
 pub enum Error {
     #[snafu(display("Nexus {} does not exist", name))]
     NexusNotFound { name: String },
+    #[snafu(display(
+        "Nexus identifier \"{}\" is ambiguous: it is the name of nexus {} \
+        and the uuid of nexus {}",
+        id,
+        name_of,
+        uuid_of
+    ))]
+    AmbiguousNexus {
+        id: String,
+        name_of: String,
+        uuid_of: String,
+    },
     #[snafu(display("Nexus {} exists and is initialising", name))]
     NexusInitialising { name: String },
     #[snafu(display("Invalid nexus uuid \"{}\"", uuid))]
@@ -81,6 +100,18 @@ pub enum Error {
     },
     #[snafu(display("Children of nexus {} have mixed block sizes", name))]
     MixedBlockSizes { name: String },
+    #[snafu(display(
+        "Children of nexus {} are incompatible with its block size of {} \
+        bytes: {}",
+        name,
+        block_size,
+        children
+    ))]
+    IncompatibleBlockSize {
+        name: String,
+        block_size: u64,
+        children: String,
+    },
     #[snafu(display(
         "Child {} of nexus {} has incompatible size or block size",
         child,
@@ -131,6 +162,17 @@ pub enum Error {
         name
     ))]
     RemoveLastHealthyChild { child: String, name: String },
+    #[snafu(display(
+        "Cannot evacuate child {} of nexus {}: {}",
+        child,
+        name,
+        reason
+    ))]
+    ChildEvacuation {
+        child: String,
+        name: String,
+        reason: String,
+    },
     #[snafu(display(
         "Cannot remove or offline the last healthy child {} of nexus {}",
         child,
@@ -141,10 +183,50 @@ pub enum Error {
     ChildDeviceNotOpen { child: String, name: String },
     #[snafu(display("Child {} of nexus {} already exists", child, name))]
     ChildAlreadyExists { child: String, name: String },
+    #[snafu(display(
+        "Child {} of nexus {} does not share the lineage of seed snapshot {}",
+        child,
+        name,
+        snapshot
+    ))]
+    SeedSnapshotMismatch {
+        child: String,
+        name: String,
+        snapshot: String,
+    },
+    #[snafu(display(
+        "Child {} of nexus {} has been snapshotted after seed snapshot {}",
+        child,
+        name,
+        snapshot
+    ))]
+    SeedSnapshotStale {
+        child: String,
+        name: String,
+        snapshot: String,
+    },
+    #[snafu(display(
+        "Lineage of child {} of nexus {} cannot be verified for a seeded \
+        rebuild, as it is not local",
+        child,
+        name
+    ))]
+    SeedLineageUnknown { child: String, name: String },
     #[snafu(display("Failed to pause child {} of nexus {}", child, name))]
     PauseChild { child: String, name: String },
     #[snafu(display("Suitable rebuild source for nexus {} not found", name))]
     NoRebuildSource { name: String },
+    #[snafu(display(
+        "Child {} of nexus {} cannot be a rebuild source: {}",
+        child,
+        name,
+        reason
+    ))]
+    InvalidRebuildSource {
+        child: String,
+        name: String,
+        reason: String,
+    },
     #[snafu(display(
         "Failed to create rebuild job for child {} of nexus {}",
         child,
@@ -187,6 +269,18 @@ pub enum Error {
     NexusCreate { name: String, reason: String },
     #[snafu(display("Failed to destroy nexus {}", name))]
     NexusDestroy { name: String },
+    #[snafu(display(
+        "Timed out after {:?} waiting for {} rebuild jobs of nexus {} to \
+        terminate",
+        timeout,
+        jobs,
+        name
+    ))]
+    RebuildDrainTimeout {
+        name: String,
+        jobs: usize,
+        timeout: std::time::Duration,
+    },
     #[snafu(display(
         "Child {} of nexus {} is not degraded but {}",
         child,
@@ -219,8 +313,68 @@ pub enum Error {
     InvalidReservation { reservation: u8 },
     #[snafu(display("failed to update share properties {}", name))]
     UpdateShareProperties { source: CoreError, name: String },
+    #[snafu(display("Failed to flush nexus {}", name))]
+    FlushNexus { source: CoreError, name: String },
+    #[snafu(display(
+        "Failed to create read cache device {} for nexus {}",
+        device,
+        name
+    ))]
+    CreateReadCache {
+        source: BdevError,
+        device: String,
+        name: String,
+    },
+    #[snafu(display(
+        "Failed to open read cache device {} of nexus {}",
+        device,
+        name
+    ))]
+    OpenReadCache {
+        source: CoreError,
+        device: String,
+        name: String,
+    },
+    #[snafu(display(
+        "Failed to create I/O log journal device {} for nexus {}",
+        device,
+        name
+    ))]
+    CreateIoLogJournal {
+        source: BdevError,
+        device: String,
+        name: String,
+    },
+    #[snafu(display(
+        "Failed to open I/O log journal device {} of nexus {}",
+        device,
+        name
+    ))]
+    OpenIoLogJournal {
+        source: CoreError,
+        device: String,
+        name: String,
+    },
     #[snafu(display("failed to save nexus state {}", name))]
     SaveStateFailed { source: StoreError, name: String },
+    #[snafu(display("failed to load nexus state {}: {}", name, source))]
+    LoadStateFailed { source: StoreError, name: String },
+    #[snafu(display("{}; rollback of nexus {} {}", source, name, rollback))]
+    CreateRollback {
+        source: Box<Error>,
+        name: String,
+        rollback: String,
+    },
+    #[snafu(display("Consistency group {} not found", name))]
+    ConsistencyGroupNotFound { name: String },
+    #[snafu(display("Consistency group {} already exists", name))]
+    ConsistencyGroupExists { name: String },
+    #[snafu(display(
+        "Nexus {} is already a member of consistency group {}",
+        nexus,
+        name
+    ))]
+    ConsistencyGroupMember { nexus: String, name: String },
 }
 
 impl From<NvmfError> for Error {
@@ -233,71 +387,259 @@ impl From<NvmfError> for Error {
 
 impl From<Error> for tonic::Status {
     fn from(e: Error) -> Self {
-        match e {
+        // Failures of a child carry the reason of the child error.
+        let info = match &e {
+            Error::OpenChild {
+                source,
+                child,
+                ..
+            }
+            | Error::OnlineChild {
+                source,
+                child,
+                ..
+            }
+            | Error::ChildWriteExclusiveResvFailed {
+                source,
+                child,
+                ..
+            } => Some(GrpcErrorInfo::new(source, Some(child))),
+            _ => None,
+        };
+
+        let status = match e {
+            // The status of the original error, with the rollback outcome.
+            Error::CreateRollback {
+                source,
+                name,
+                rollback,
+            } => {
+                let status = Status::from(*source);
+                return Status::new(
+                    status.code(),
+                    format!(
+                        "{}; rollback of nexus {name} {rollback}",
+                        status.message()
+                    ),
+                );
+            }
+            // Failures of a lower layer keep the code of their errno.
+            Error::CreateCryptoBdev {
+                source, ..
+            }
+            | Error::DestroyCryptoBdev {
+                source, ..
+            }
+            | Error::RegisterNexus {
+                source, ..
+            } => errno_status(source, e.verbose()),
+            Error::ShareNbdNexus {
+                source:
+                    NbdError::StartNbd {
+                        source, ..
+                    },
+                ..
+            } => errno_status(source, e.verbose()),
+            Error::ShareNbdNexus {
+                source: NbdError::Unavailable {},
+                ..
+            } => Status::resource_exhausted(e.verbose()),
+            Error::ShareNvmfNexus {
+                ref source, ..
+            }
+            | Error::UnshareNexus {
+                ref source, ..
+            }
+            | Error::UpdateShareProperties {
+                ref source, ..
+            }
+            | Error::FlushNexus {
+                ref source, ..
+            }
+            | Error::OpenReadCache {
+                ref source, ..
+            }
+            | Error::OpenIoLogJournal {
+                ref source, ..
+            } => errno_status(source.clone().to_errno(), e.verbose()),
+            Error::CreateChild {
+                ref source, ..
+            }
+            | Error::CloseChild {
+                ref source, ..
+            }
+            | Error::CreateReadCache {
+                ref source, ..
+            }
+            | Error::CreateIoLogJournal {
+                ref source, ..
+            } => {
+                Status::new(Status::from(source.clone()).code(), e.to_string())
+            }
+            Error::OpenChild {
+                ref source, ..
+            }
+            | Error::OnlineChild {
+                ref source, ..
+            }
+            | Error::ChildWriteExclusiveResvFailed {
+                ref source, ..
+            } => Status::new(child_error_code(source), e.verbose()),
+            Error::CreateRebuild {
+                ref source, ..
+            }
+            | Error::RebuildOperation {
+                ref source, ..
+            } => Status::new(rebuild_error_code(source), e.verbose()),
+            Error::SaveStateFailed {
+                ref source, ..
+            }
+            | Error::LoadStateFailed {
+                ref source, ..
+            } => Status::new(store_error_code(source), e.to_string()),
             Error::InvalidUuid {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::InvalidKey {
+            }
+            | Error::AmbiguousNexus {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::InvalidShareProtocol {
+            }
+            | Error::InvalidKey {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::InvalidReservation {
+            }
+            | Error::InvalidShareProtocol {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::AlreadyShared {
+            }
+            | Error::InvalidNvmeAnaState {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::NotShared {
+            }
+            | Error::InvalidReservation {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::NotSharedNvmf {
+            }
+            | Error::InvalidArguments {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::CreateChild {
+            }
+            | Error::AlreadyShared {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::MixedBlockSizes {
+            }
+            | Error::NotShared {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::ChildGeometry {
+            }
+            | Error::NotSharedNvmf {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::ChildTooSmall {
+            }
+            | Error::MixedBlockSizes {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            Error::OpenChild {
+            }
+            | Error::IncompatibleBlockSize {
+                ..
+            }
+            | Error::ChildGeometry {
+                ..
+            }
+            | Error::ChildTooSmall {
                 ..
             } => Status::invalid_argument(e.to_string()),
-            Error::OperationNotAllowed {
+            Error::NexusNotFound {
                 ..
-            } => Status::failed_precondition(e.to_string()),
-            Error::RemoveLastChild {
+            }
+            | Error::ChildNotFound {
                 ..
-            } => Status::failed_precondition(e.to_string()),
-            Error::RemoveLastHealthyChild {
+            }
+            | Error::ChildMissing {
                 ..
-            } => Status::failed_precondition(e.to_string()),
-            Error::ChildNotFound {
+            }
+            | Error::ChildMissingErrStore {
                 ..
-            } => Status::not_found(e.to_string()),
-            Error::RebuildJobNotFound {
+            }
+            | Error::RebuildJobNotFound {
                 ..
-            } => Status::not_found(e.to_string()),
-            Error::NexusNotFound {
+            }
+            | Error::ConsistencyGroupNotFound {
                 ..
             } => Status::not_found(e.to_string()),
-            Error::ChildAlreadyExists {
+            Error::UuidExists {
                 ..
-            } => Status::already_exists(e.to_string()),
-            Error::NameExists {
+            }
+            | Error::NameExists {
+                ..
+            }
+            | Error::ChildAlreadyExists {
+                ..
+            }
+            | Error::RebuildJobAlreadyExists {
+                ..
+            }
+            | Error::ConsistencyGroupExists {
                 ..
             } => Status::already_exists(e.to_string()),
-            Error::InvalidArguments {
+            Error::NexusInitialising {
                 ..
-            } => Status::invalid_argument(e.to_string()),
-            e => Status::new(Code::Internal, e.verbose()),
+            } => Status::unavailable(e.to_string()),
+            Error::OperationNotAllowed {
+                ..
+            }
+            | Error::NexusIncomplete {
+                ..
+            }
+            | Error::RemoveLastChild {
+                ..
+            }
+            | Error::RemoveLastHealthyChild {
+                ..
+            }
+            | Error::ChildEvacuation {
+                ..
+            }
+            | Error::ChildDeviceNotOpen {
+                ..
+            }
+            | Error::ChildNotDegraded {
+                ..
+            }
+            | Error::SeedSnapshotMismatch {
+                ..
+            }
+            | Error::SeedSnapshotStale {
+                ..
+            }
+            | Error::SeedLineageUnknown {
+                ..
+            }
+            | Error::NoRebuildSource {
+                ..
+            }
+            | Error::InvalidRebuildSource {
+                ..
+            }
+            | Error::ConsistencyGroupMember {
+                ..
+            }
+            | Error::PauseChild {
+                ..
+            }
+            | Error::Pause {
+                ..
+            }
+            | Error::NexusCreate {
+                ..
+            }
+            | Error::FailedCreateSnapshot {
+                ..
+            } => Status::failed_precondition(e.to_string()),
+            Error::RebuildDrainTimeout {
+                ..
+            } => Status::deadline_exceeded(e.to_string()),
+            Error::NexusDestroy {
+                ..
+            } => Status::aborted(e.to_string()),
+            Error::FailedGetHandle
+            | Error::SubsysNvmf {
+                ..
+            } => Status::unavailable(e.to_string()),
+        };
+        match info {
+            Some(info) => info.attach(status),
+            None => status,
         }
     }
 }
@@ -0,0 +1,154 @@
+//! Implements freeze/thaw (consistency quiesce) operations on a nexus.
+//!
+//! A frozen nexus has its I/O subsystem paused and all of its children
+//! flushed, so that an external snapshot or backup tool can capture a
+//! crash-consistent point in time. New writes are held by the NVMf
+//! subsystem until the nexus is thawed, or until the freeze deadline expires
+//! and the nexus is thawed automatically.
+use std::{
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use snafu::ResultExt;
+
+use super::{nexus_err, nexus_lookup_mut, Error, Nexus, NexusOperation};
+use crate::{
+    core::{Reactors, UntypedBdevHandle, VerboseError},
+    sleep::mayastor_sleep,
+};
+
+/// Maximum time a nexus is allowed to stay frozen.
+pub const NEXUS_FREEZE_MAX_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Freeze generation counter, shared by all nexuses.
+static FREEZE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Information about an active nexus freeze.
+#[derive(Debug, Clone, Copy)]
+pub struct NexusFreeze {
+    /// Time when the nexus was frozen.
+    pub frozen_at: DateTime<Utc>,
+    /// Time when the nexus is going to be thawed automatically.
+    pub deadline: DateTime<Utc>,
+    /// Freeze generation, used to match an auto-thaw with its freeze.
+    generation: u64,
+}
+
+impl<'n> Nexus<'n> {
+    /// Freezes the nexus: pauses the I/O subsystem and flushes all children.
+    /// The nexus stays frozen until `thaw()` is called or the given timeout
+    /// expires, whichever comes first.
+    pub async fn freeze(
+        mut self: Pin<&mut Self>,
+        timeout: Duration,
+    ) -> Result<NexusFreeze, Error> {
+        self.check_nexus_operation(NexusOperation::NexusFreeze)?;
+
+        if timeout.is_zero() || timeout > NEXUS_FREEZE_MAX_TIMEOUT {
+            return Err(Error::InvalidArguments {
+                name: self.name.clone(),
+                args: format!(
+                    "freeze timeout must be within (0, {}s]",
+                    NEXUS_FREEZE_MAX_TIMEOUT.as_secs()
+                ),
+            });
+        }
+
+        if let Some(f) = self.freeze_info() {
+            return Err(Error::OperationNotAllowed {
+                reason: format!("Nexus is already frozen until {}", f.deadline),
+            });
+        }
+
+        info!("{self:?}: freezing nexus for {timeout:?}...");
+
+        // Step 1: pause the I/O subsystem, so no new writes are accepted.
+        self.as_mut().pause().await?;
+
+        // Step 2: flush all children, so that everything written before the
+        // freeze is persisted.
+        if let Err(error) = self.flush_children().await {
+            error!(
+                "{self:?}: failed to flush children, aborting freeze: {e}",
+                e = error.verbose()
+            );
+            self.as_mut().resume().await.ok();
+            return Err(error);
+        }
+
+        let frozen_at = Utc::now();
+        let freeze = NexusFreeze {
+            frozen_at,
+            deadline: frozen_at
+                + chrono::Duration::from_std(timeout).unwrap_or_default(),
+            generation: FREEZE_GENERATION.fetch_add(1, Ordering::SeqCst),
+        };
+        *self.freeze.lock() = Some(freeze);
+
+        // Step 3: schedule an automatic thaw, so that a lost client can't
+        // keep the volume frozen forever.
+        let name = self.name.clone();
+        Reactors::master().send_future(async move {
+            if mayastor_sleep(timeout).await.is_err() {
+                error!("Nexus '{name}': failed to wait for freeze deadline");
+            }
+
+            let Some(nexus) = nexus_lookup_mut(&name) else {
+                return;
+            };
+
+            if nexus.freeze_info().map(|f| f.generation)
+                != Some(freeze.generation)
+            {
+                return;
+            }
+
+            warn!("{nexus:?}: freeze deadline expired, thawing nexus");
+            if let Err(error) = nexus.thaw().await {
+                error!(
+                    "{name}: failed to thaw nexus on deadline: {e}",
+                    e = error.verbose()
+                );
+            }
+        });
+
+        info!(
+            "{self:?}: nexus frozen until {deadline}",
+            deadline = freeze.deadline
+        );
+
+        Ok(freeze)
+    }
+
+    /// Thaws a previously frozen nexus, resuming I/O.
+    pub async fn thaw(self: Pin<&mut Self>) -> Result<(), Error> {
+        if self.freeze.lock().take().is_none() {
+            return Err(Error::OperationNotAllowed {
+                reason: "Nexus is not frozen".to_string(),
+            });
+        }
+
+        info!("{self:?}: thawing nexus...");
+        self.resume().await
+    }
+
+    /// Returns the active freeze of the nexus, if any.
+    pub fn freeze_info(&self) -> Option<NexusFreeze> {
+        *self.freeze.lock()
+    }
+
+    /// Flushes all the children of the nexus via the nexus block device.
+    /// With the write cache enabled, this also waits for the cached writes
+    /// to reach the children.
+    pub async fn flush_children(&self) -> Result<(), Error> {
+        let hdl = UntypedBdevHandle::open(&self.bdev_name(), true, false)
+            .map_err(|_| Error::FailedGetHandle)?;
+
+        hdl.flush().await.context(nexus_err::FlushNexus {
+            name: self.name.clone(),
+        })
+    }
+}
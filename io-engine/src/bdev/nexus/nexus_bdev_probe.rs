@@ -0,0 +1,135 @@
+//! Implements the health probe of the nexus children.
+//!
+//! A failure of the admin queue of a remote child is only detected once its
+//! controller processes an admin command. With a probe interval, the nexus
+//! periodically sends an Identify Controller command to each of its healthy
+//! remote children, and retires the ones failing it with the
+//! `AdminCommandFailed` reason. The children retired this way are probed by
+//! reconnecting them: once they respond again, they are brought back online
+//! and rebuilt.
+use std::{
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::{nexus_lookup_mut, ChildState, FaultReason, Nexus, NexusState};
+use crate::{core::Reactors, sleep::mayastor_sleep};
+
+/// Generation of the last started child prober, unique across nexuses.
+static CHILD_PROBE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+impl<'n> Nexus<'n> {
+    /// Returns the interval between the health probes of the children, if
+    /// enabled.
+    pub fn child_probe_interval(&self) -> Option<Duration> {
+        match self.child_probe_interval_ms.load() {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    /// Sets the interval between the health probes of the children, zero
+    /// disabling them.
+    pub fn set_child_probe_interval(&self, interval: Duration) {
+        info!("{self:?}: setting child probe interval to {interval:?}");
+        self.child_probe_interval_ms
+            .store(interval.as_millis() as u64);
+
+        // A new generation stops the prober of the previous one.
+        let generation =
+            CHILD_PROBE_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+        self.child_probe_generation.store(generation);
+        if !interval.is_zero() {
+            Reactors::master().send_future(Nexus::child_probe_routine(
+                self.name.clone(),
+                generation,
+            ));
+        }
+    }
+
+    /// Probes the children of the given nexus periodically, until the nexus
+    /// is gone or the probe interval changes.
+    async fn child_probe_routine(nexus_name: String, generation: u64) {
+        loop {
+            let Some(interval) = nexus_lookup_mut(&nexus_name)
+                .filter(|n| n.child_probe_generation.load() == generation)
+                .and_then(|n| n.child_probe_interval())
+            else {
+                return;
+            };
+
+            if mayastor_sleep(interval).await.is_err() {
+                error!("Nexus '{nexus_name}': failed to wait for child probe");
+                return;
+            }
+
+            let Some(nexus) = nexus_lookup_mut(&nexus_name) else {
+                return;
+            };
+            if nexus.child_probe_generation.load() != generation {
+                return;
+            }
+
+            nexus.probe_children().await;
+        }
+    }
+
+    /// Probes the healthy remote children with an admin command, retiring
+    /// the failing ones, and tries to bring back online the children retired
+    /// after an admin command failure.
+    async fn probe_children(mut self: Pin<&mut Self>) {
+        if *self.state.lock() != NexusState::Open {
+            return;
+        }
+
+        let healthy: Vec<String> = self
+            .children_iter()
+            .filter(|c| c.is_healthy() && c.is_local() == Some(false))
+            .map(|c| c.uri().to_owned())
+            .collect();
+
+        let failed: Vec<String> = self
+            .children_iter()
+            .filter(|c| {
+                c.state()
+                    == ChildState::Faulted(FaultReason::AdminCommandFailed)
+                    && !c.is_destroying()
+            })
+            .map(|c| c.uri().to_owned())
+            .collect();
+
+        for uri in healthy {
+            let Some(child) = self.lookup_child(&uri) else {
+                continue;
+            };
+            let Some(device) = child.get_device_name() else {
+                continue;
+            };
+            let Ok(hdl) = child.get_io_handle_nonblock().await else {
+                continue;
+            };
+
+            if let Err(e) = hdl.nvme_identify_ctrlr().await {
+                warn!("{self:?}: child '{uri}' failed the health probe: {e}");
+                self.retire_child_device(
+                    &device,
+                    FaultReason::AdminCommandFailed,
+                    false,
+                );
+            }
+        }
+
+        for uri in failed {
+            match self.as_mut().online_child(&uri).await {
+                Ok(_) => info!(
+                    "{self:?}: child '{uri}' recovered from admin command \
+                    failure"
+                ),
+                Err(e) => {
+                    debug!("{self:?}: child '{uri}' not recovered yet: {e}")
+                }
+            }
+        }
+    }
+}
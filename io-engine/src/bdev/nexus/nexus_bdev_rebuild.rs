@@ -1,6 +1,9 @@
+use chrono::{DateTime, Utc};
 use futures::channel::oneshot::Receiver;
+use once_cell::sync::Lazy;
 use snafu::ResultExt;
-use std::{marker::PhantomData, sync::Arc};
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
+use uuid::Uuid;
 
 use super::{
     nexus_err,
@@ -14,20 +17,44 @@ use super::{
 };
 
 use crate::{
-    core::{Reactors, VerboseError},
+    bdev::uri::{normalize, same_device},
+    core::{Reactors, ReadOptions, VerboseError},
     eventing::{EventMetaGen, EventWithMeta},
     rebuild::{
         HistoryRecord,
         RebuildError,
+        RebuildIoPolicy,
         RebuildJob,
         RebuildJobOptions,
+        RebuildPriority,
+        RebuildScheduler,
         RebuildState,
         RebuildStats,
         RebuildVerifyMode,
     },
+    sleep::mayastor_sleep,
 };
 use events_api::event::EventAction;
 
+/// Maximum number of rebuild history records kept per nexus.
+/// When exceeded, the oldest records are dropped.
+pub const NEXUS_REBUILD_HISTORY_MAX: usize = 64;
+
+/// Maximum number of destroyed nexuses whose rebuild history is retained.
+/// When exceeded, the history whose last record is the oldest is evicted.
+pub const REBUILD_HISTORY_STASH_MAX: usize = 256;
+
+/// Rebuild histories of destroyed nexuses, indexed by nexus UUID.
+/// When a nexus with the same UUID is created again, its history is restored,
+/// so that rebuild records survive nexus re-creation.
+///
+/// The stash is kept in memory only: rebuild histories do not survive a
+/// restart of the io-engine. The control plane, which watches the rebuild
+/// events, is the persistent record of rebuilds.
+static REBUILD_HISTORY_STASH: Lazy<
+    parking_lot::Mutex<HashMap<Uuid, Vec<HistoryRecord>>>,
+> = Lazy::new(Default::default);
+
 /// Rebuild pause guard ensures rebuild jobs are resumed before it is dropped.
 pub(crate) struct RebuildPauseGuard<'a> {
     /// Nexus name.
@@ -75,7 +102,249 @@ impl<'a> RebuildPauseGuard<'a> {
     }
 }
 
+/// Returns the key of a child in the per-child rebuild settings of a nexus:
+/// its normalized URI, so that a setting does not depend on how the URI of
+/// the child is spelled.
+pub(super) fn rebuild_settings_key(child_uri: &str) -> String {
+    normalize(child_uri).unwrap_or_else(|_| child_uri.to_owned())
+}
+
 impl<'n> Nexus<'n> {
+    /// Checks if reads are served from a local child while it is being
+    /// rebuilt (copy-on-read).
+    pub fn copy_on_read(&self) -> bool {
+        self.copy_on_read.load()
+    }
+
+    /// Enables or disables copy-on-read for the rebuilds started from now on.
+    /// With copy-on-read, a local child being rebuilt serves the reads of the
+    /// segments already rebuilt, and reads of other segments, served by the
+    /// remote children, make the rebuild copy these segments first.
+    pub fn set_copy_on_read(&self, enabled: bool) {
+        info!("{self:?}: setting copy-on-read to {enabled}");
+        self.copy_on_read.store(enabled);
+    }
+
+    /// Returns the preferred rebuild source of the given child, if any.
+    pub fn rebuild_source(&self, child_uri: &str) -> Option<String> {
+        self.rebuild_sources
+            .lock()
+            .get(&rebuild_settings_key(child_uri))
+            .cloned()
+    }
+
+    /// Sets or clears the child the given child is to be rebuilt from. The
+    /// source must be a healthy child of the nexus. The preference applies to
+    /// the rebuilds started until the child is synced.
+    pub fn set_rebuild_source(
+        &self,
+        child_uri: &str,
+        source_uri: Option<&str>,
+    ) -> Result<(), Error> {
+        let Some(source_uri) = source_uri else {
+            self.rebuild_sources
+                .lock()
+                .remove(&rebuild_settings_key(child_uri));
+            return Ok(());
+        };
+
+        let invalid = |reason: &str| Error::InvalidRebuildSource {
+            child: source_uri.to_owned(),
+            name: self.name.clone(),
+            reason: reason.to_owned(),
+        };
+        if same_device(source_uri, child_uri) {
+            return Err(invalid("a child cannot be rebuilt from itself"));
+        }
+        let source_uri = match self.lookup_child(source_uri) {
+            Some(c) if c.is_healthy() => c.uri().to_owned(),
+            Some(_) => return Err(invalid("the child is not healthy")),
+            None => return Err(invalid("no such child")),
+        };
+
+        info!("{self:?}: '{child_uri}' is to be rebuilt from '{source_uri}'");
+        self.rebuild_sources
+            .lock()
+            .insert(rebuild_settings_key(child_uri), source_uri);
+        Ok(())
+    }
+
+    /// Returns the priority of the rebuilds of the given child.
+    pub fn rebuild_priority(&self, child_uri: &str) -> RebuildPriority {
+        self.rebuild_priorities
+            .lock()
+            .get(&rebuild_settings_key(child_uri))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sets or clears the priority of the rebuilds of the given child. The
+    /// priority orders the rebuild jobs queued by the rebuild scheduler, and
+    /// applies to the rebuilds started until the child is synced.
+    pub fn set_rebuild_priority(
+        &self,
+        child_uri: &str,
+        priority: Option<RebuildPriority>,
+    ) {
+        let mut priorities = self.rebuild_priorities.lock();
+        match priority {
+            Some(priority) => {
+                info!(
+                    "{self:?}: '{child_uri}' is to be rebuilt with \
+                    {priority:?} priority"
+                );
+                priorities.insert(rebuild_settings_key(child_uri), priority);
+            }
+            None => {
+                priorities.remove(&rebuild_settings_key(child_uri));
+            }
+        }
+    }
+
+    /// Selects the child to rebuild the given child from: the preferred
+    /// source if it is still healthy, otherwise a healthy local child if
+    /// any, the healthy child serving the fewest rebuilds being preferred.
+    fn select_rebuild_source(&self, child_uri: &str) -> Result<String, Error> {
+        if let Some(src) = self.rebuild_source(child_uri) {
+            match self.lookup_child(&src) {
+                Some(c) if c.is_healthy() => return Ok(src),
+                _ => warn!(
+                    "{self:?}: preferred rebuild source '{src}' of \
+                    '{child_uri}' is no longer healthy, selecting another one"
+                ),
+            }
+        }
+
+        self.children_iter()
+            .filter(|c| c.is_healthy() && !same_device(c.uri(), child_uri))
+            .min_by_key(|c| {
+                (
+                    c.is_local() != Some(true),
+                    RebuildJob::lookup_src(c.uri()).len(),
+                )
+            })
+            .map(|c| c.uri().to_owned())
+            .ok_or_else(|| Error::NoRebuildSource {
+                name: self.name.clone(),
+            })
+    }
+
+    /// Returns true if the rebuild reads are striped over all the healthy
+    /// children.
+    pub fn striped_rebuild(&self) -> bool {
+        self.striped_rebuild.load()
+    }
+
+    /// Enables or disables striping of the reads of the rebuilds started
+    /// from now on: segments are read in turn from each healthy child rather
+    /// than from the rebuild source only.
+    pub fn set_striped_rebuild(&self, enabled: bool) {
+        info!("{self:?}: setting striped rebuild to {enabled}");
+        self.striped_rebuild.store(enabled);
+    }
+
+    /// Returns the size of the rebuild segments and the number of concurrent
+    /// rebuild copy tasks, zero meaning the node defaults.
+    pub fn rebuild_segments(&self) -> (u64, usize) {
+        (
+            self.rebuild_segment_size.load(),
+            self.rebuild_segment_tasks.load(),
+        )
+    }
+
+    /// Sets the size of the segments, in bytes, and the number of concurrent
+    /// copy tasks of the rebuilds started from now on. Zero selects the node
+    /// defaults.
+    pub fn set_rebuild_segments(
+        &self,
+        segment_size: u64,
+        segment_tasks: usize,
+    ) -> Result<(), Error> {
+        RebuildJobOptions::resolve_segments(
+            segment_size,
+            segment_tasks,
+            self.block_len(),
+        )
+        .map_err(|e| Error::InvalidArguments {
+            name: self.name.clone(),
+            args: e.to_string(),
+        })?;
+
+        info!(
+            "{self:?}: setting rebuild segment size to {segment_size} and \
+            tasks to {segment_tasks}"
+        );
+        self.rebuild_segment_size.store(segment_size);
+        self.rebuild_segment_tasks.store(segment_tasks);
+        Ok(())
+    }
+
+    /// Defers the rebuild of an out-of-sync child until the given time. The
+    /// child stays out-of-sync until then, unless its rebuild is explicitly
+    /// started before. Scheduling again replaces the previous schedule.
+    pub fn schedule_rebuild(
+        &self,
+        child_uri: &str,
+        at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        match self.lookup_child(child_uri) {
+            Some(c) if c.is_opened_unsync() => {
+                if c.rebuild_job().is_some() {
+                    return Err(Error::RebuildJobAlreadyExists {
+                        child: child_uri.to_owned(),
+                        name: self.name.clone(),
+                    });
+                }
+                c.set_rebuild_scheduled_at(Some(at));
+            }
+            Some(c) => {
+                return Err(Error::ChildNotDegraded {
+                    child: child_uri.to_owned(),
+                    name: self.name.clone(),
+                    state: c.state().to_string(),
+                })
+            }
+            None => {
+                return Err(Error::ChildNotFound {
+                    child: child_uri.to_owned(),
+                    name: self.name.clone(),
+                })
+            }
+        }
+
+        info!("{self:?}: rebuild of '{child_uri}' is scheduled at {at}");
+
+        let delay = (at - Utc::now()).to_std().unwrap_or_default();
+        let name = self.name.clone();
+        let child_uri = child_uri.to_owned();
+        Reactors::master().send_future(async move {
+            if mayastor_sleep(delay).await.is_err() {
+                error!("Nexus '{name}': failed to wait for scheduled rebuild");
+            }
+
+            let Some(nexus) = nexus_lookup_mut(&name) else {
+                return;
+            };
+
+            // The child may have been removed, rebuilt or rescheduled in the
+            // meantime.
+            match nexus.lookup_child(&child_uri) {
+                Some(c) if c.rebuild_scheduled_at() == Some(at) => {}
+                _ => return,
+            }
+
+            if let Err(e) = nexus.start_rebuild(&child_uri).await {
+                error!(
+                    "{nexus:?}: failed to start scheduled rebuild of \
+                    '{child_uri}': {}",
+                    e.verbose()
+                );
+            }
+        });
+
+        Ok(())
+    }
+
     /// Starts a rebuild job and returns a receiver channel
     /// which can be used to await the rebuild completion
     pub async fn start_rebuild(
@@ -85,16 +354,7 @@ impl<'n> Nexus<'n> {
         let name = self.name.clone();
         info!("{self:?}: start rebuild request for {child_uri}");
 
-        // Find a healthy child to rebuild from.
-        let src_child_uri = match self
-            .children_iter()
-            .find(|c| c.is_healthy() && c.uri() != child_uri)
-        {
-            Some(child) => Ok(child.uri().to_owned()),
-            None => Err(Error::NoRebuildSource {
-                name: name.clone(),
-            }),
-        }?;
+        let src_child_uri = self.select_rebuild_source(child_uri)?;
 
         let dst_child_uri = match self.lookup_child(child_uri) {
             Some(c) if c.is_opened_unsync() => {
@@ -104,6 +364,7 @@ impl<'n> Nexus<'n> {
                         name: name.clone(),
                     })
                 } else {
+                    c.set_rebuild_scheduled_at(None);
                     Ok(c.uri().to_owned())
                 }
             }
@@ -142,9 +403,22 @@ impl<'n> Nexus<'n> {
         // As this is done after the reconfiguraion, any new write I/Os will
         // now reach the destionation child, and no rebuild will be required
         // for them.
-        let map = self
-            .lookup_child(&dst_child_uri)
-            .and_then(|c| c.stop_io_log());
+        // A child without an I/O log may have a slot recovered by the I/O log
+        // journal, if the io-engine stopped while the child was faulted.
+        let map = self.lookup_child(&dst_child_uri).and_then(|c| {
+            c.stop_io_log().or_else(|| {
+                let journal = self.io_log_journal()?;
+                let dev = c.get_device().ok()?;
+                let map = journal.take_recovered(
+                    c.uri(),
+                    &dev.device_name(),
+                    dev.num_blocks(),
+                    dev.block_len(),
+                )?;
+                info!("{c:?}: rebuilding from the I/O log journal: {map:?}");
+                Some(map)
+            })
+        });
 
         self.rebuild_job_mut(&dst_child_uri)?
             .start(map)
@@ -182,8 +456,98 @@ impl<'n> Nexus<'n> {
             _ => RebuildVerifyMode::None,
         };
 
+        // Only a local child is worth reading from while being rebuilt.
+        let copy_on_read = self.copy_on_read()
+            && self
+                .lookup_child(dst_child_uri)
+                .and_then(|c| c.is_local())
+                .unwrap_or(false);
+        if copy_on_read {
+            info!(
+                "{self:?}: rebuilding local child '{dst_child_uri}' \
+                with copy-on-read"
+            );
+        }
+
+        // The source is always read from, the other healthy children only
+        // when the reads are striped.
+        let striped_sources = if self.striped_rebuild() {
+            self.children_iter()
+                .filter(|c| {
+                    c.is_healthy()
+                        && c.uri() != src_child_uri
+                        && c.uri() != dst_child_uri
+                })
+                .map(|c| c.uri().to_owned())
+                .collect::<Vec<_>>()
+        } else {
+            Vec::new()
+        };
+
+        // A child seeded from a snapshot needs only the segments written on
+        // the sources after the snapshot, unless they have been snapshotted
+        // since it was added.
+        let read_opts = match self
+            .lookup_child(dst_child_uri)
+            .and_then(|c| c.seed_snapshot())
+        {
+            Some(snapshot) => {
+                let sources = std::iter::once(src_child_uri.to_owned())
+                    .chain(striped_sources.iter().cloned())
+                    .collect::<Vec<_>>();
+                match self.verify_seed_lineage(
+                    dst_child_uri,
+                    &snapshot,
+                    &sources,
+                ) {
+                    Ok(()) => {
+                        info!(
+                            "{self:?}: rebuilding '{dst_child_uri}' from the \
+                            delta of snapshot '{snapshot}'"
+                        );
+                        ReadOptions::CurrentUnwrittenFail
+                    }
+                    Err(e) => {
+                        warn!(
+                            "{self:?}: fully rebuilding seeded child \
+                            '{dst_child_uri}': {}",
+                            e.verbose()
+                        );
+                        if let Some(c) = self.lookup_child(dst_child_uri) {
+                            c.set_seed_snapshot(None);
+                        }
+                        ReadOptions::UnwrittenFail
+                    }
+                }
+            }
+            None => ReadOptions::UnwrittenFail,
+        };
+
+        // Account the rebuild traffic to the children it reads and writes.
+        let bandwidth = self
+            .children_iter()
+            .map(|c| (c.uri().to_owned(), c.bandwidth_counters()))
+            .collect();
+
         let opts = RebuildJobOptions {
             verify_mode,
+            priority: self.rebuild_priority(dst_child_uri),
+            healthy_children: self
+                .children_iter()
+                .filter(|c| c.is_healthy())
+                .count(),
+            read_opts,
+            io_policy: RebuildIoPolicy {
+                client_qd_threshold: RebuildScheduler::config()
+                    .client_qd_threshold,
+                client_qd: Some(self.client_io_depth.clone()),
+                ..Default::default()
+            },
+            copy_on_read,
+            striped_sources,
+            segment_size: self.rebuild_segment_size.load(),
+            segment_tasks: self.rebuild_segment_tasks.load(),
+            bandwidth,
         };
 
         RebuildJob::new(
@@ -217,7 +581,14 @@ impl<'n> Nexus<'n> {
             return;
         };
 
-        self.rebuild_history.lock().push(rec);
+        {
+            let mut hist = self.rebuild_history.lock();
+            hist.push(rec);
+            if hist.len() > NEXUS_REBUILD_HISTORY_MAX {
+                let n = hist.len() - NEXUS_REBUILD_HISTORY_MAX;
+                hist.drain(.. n);
+            }
+        }
 
         debug!(
             "{self:?}: new rebuild history record for '{dst}'; \
@@ -307,6 +678,54 @@ impl<'n> Nexus<'n> {
         self.rebuild_history.lock()
     }
 
+    /// Saves the rebuild history of the nexus being destroyed, so it can be
+    /// restored if a nexus with the same UUID is created again.
+    pub(super) fn stash_rebuild_history(&self) {
+        let hist = std::mem::take(&mut *self.rebuild_history.lock());
+        if hist.is_empty() {
+            return;
+        }
+
+        let mut stash = REBUILD_HISTORY_STASH.lock();
+        if stash.len() >= REBUILD_HISTORY_STASH_MAX
+            && !stash.contains_key(&self.uuid())
+        {
+            // Evict the history with the oldest last record.
+            if let Some(oldest) = stash
+                .iter()
+                .min_by_key(|(_, h)| h.last().map(|r| r.end_time))
+                .map(|(uuid, _)| *uuid)
+            {
+                stash.remove(&oldest);
+            }
+        }
+        stash.insert(self.uuid(), hist);
+    }
+
+    /// Restores the rebuild history previously stashed for the nexus UUID.
+    pub(super) fn restore_rebuild_history(&self) {
+        if let Some(mut prev) =
+            REBUILD_HISTORY_STASH.lock().remove(&self.uuid())
+        {
+            debug!(
+                "{self:?}: restored {n} rebuild history records",
+                n = prev.len()
+            );
+            let mut hist = self.rebuild_history.lock();
+            prev.append(&mut hist);
+            *hist = prev;
+        }
+    }
+
+    /// Updates the queued rebuild jobs of the nexus with its current number of
+    /// healthy children, after a child failed, was rebuilt or removed.
+    pub(crate) fn update_rebuild_queue(&self) {
+        RebuildScheduler::set_healthy_children(
+            &self.name,
+            self.children_iter().filter(|c| c.is_healthy()).count(),
+        );
+    }
+
     /// Returns the rebuild progress of a rebuild job for the given destination.
     pub(crate) async fn rebuild_progress(
         &self,
@@ -420,6 +839,13 @@ impl<'n> Nexus<'n> {
         if !job_state.done() {
             // Leave all states as they are.
             info!("{c:?}: rebuild state updated: {job_state:?}");
+            if job_state.no_space() {
+                warn!(
+                    "{c:?}: rebuild paused, destination is out of space: {e}",
+                    e = job.error_desc()
+                );
+                self.event(EventAction::StateChange, job.meta()).generate();
+            }
             return Ok(());
         }
 
@@ -427,6 +853,17 @@ impl<'n> Nexus<'n> {
             RebuildState::Completed => {
                 self.event(EventAction::RebuildEnd, job.meta()).generate();
                 c.set_sync_state(ChildSyncState::Synced);
+                c.set_seed_snapshot(None);
+                self.update_rebuild_queue();
+                self.rebuild_sources
+                    .lock()
+                    .remove(&rebuild_settings_key(child_uri));
+                self.rebuild_priorities
+                    .lock()
+                    .remove(&rebuild_settings_key(child_uri));
+                if let Some(journal) = self.io_log_journal() {
+                    journal.release(child_uri);
+                }
 
                 if c.is_healthy() {
                     match self
@@ -473,6 +910,10 @@ impl<'n> Nexus<'n> {
                     "{c:?}: rebuild job failed with error: {e}",
                     e = job.error_desc()
                 );
+                // The child may miss writes from now on.
+                if let Some(journal) = self.io_log_journal() {
+                    journal.release(child_uri);
+                }
                 self.event(EventAction::RebuildEnd, job.meta()).generate();
                 c.close_faulted(FaultReason::RebuildFailed).await;
             }
@@ -481,6 +922,9 @@ impl<'n> Nexus<'n> {
                     "{c:?}: rebuild job failed with state {s:?}",
                     s = job_state
                 );
+                if let Some(journal) = self.io_log_journal() {
+                    journal.release(child_uri);
+                }
                 self.event(EventAction::RebuildEnd, job.meta()).generate();
                 c.close_faulted(FaultReason::RebuildFailed).await;
             }
@@ -1,4 +1,12 @@
 //! Implements snapshot operations on a nexus.
+//!
+//! A nexus snapshot is crash-consistent across the replicas of the nexus:
+//! the I/O subsystem of the nexus is paused, so that the initiator cannot
+//! submit new writes, and the children are flushed before the replicas are
+//! snapshotted. Local replicas are snapshotted directly, remote replicas via
+//! the NVMe admin command handled by the io-engine they are on. I/O resumes
+//! once all the replica snapshots are done, each of which reports its own
+//! status.
 use std::collections::{HashMap, HashSet};
 
 use futures::future::join_all;
@@ -12,6 +20,7 @@ use crate::{
         Reactor,
         SnapshotParams,
         ToErrno,
+        VerboseError,
     },
 };
 use chrono::{DateTime, Utc};
@@ -268,21 +277,11 @@ impl<'n> Nexus<'n> {
     fn check_nexus_state(&self) -> Result<(), Error> {
         self.check_nexus_operation(NexusOperation::NexusSnapshot)?;
 
-        // Check that nexus has exactly 1 replica.
-        match self.children().len() {
-            0 => {
-                return Err(Error::FailedCreateSnapshot {
-                    name: self.bdev_name(),
-                    reason: "Nexus has no replicas".to_string(),
-                })
-            }
-            1 => {} // Only one replica nexuses are supported.
-            _ => {
-                return Err(Error::FailedCreateSnapshot {
-                    name: self.bdev_name(),
-                    reason: "Nexus has more than one replica".to_string(),
-                })
-            }
+        if self.children().is_empty() {
+            return Err(Error::FailedCreateSnapshot {
+                name: self.bdev_name(),
+                reason: "Nexus has no replicas".to_string(),
+            });
         }
 
         // Check that nexus is healthy and not being reconfigured.
@@ -297,27 +296,33 @@ impl<'n> Nexus<'n> {
         Ok(())
     }
 
-    /// Create a snapshot on all nexus replicas (currently only on 1)
+    /// Create a snapshot on all nexus replicas which are not skipped.
     async fn do_nexus_snapshot(
         self: Pin<&mut Self>,
         snapshot: SnapshotParams,
-        replicas: Vec<NexusReplicaSnapshotDescriptor>,
-    ) -> Result<NexusSnapshotStatus, Error> {
+        executor: ReplicaSnapshotExecutor,
+    ) -> NexusSnapshotStatus {
         let (replicas_done, replicas_skipped) =
-            ReplicaSnapshotExecutor::new(self.as_ref(), replicas)
-                .await?
-                .take_snapshot(&snapshot)
-                .await;
-        Ok(NexusSnapshotStatus {
+            executor.take_snapshot(&snapshot).await;
+        for r in replicas_done.iter().filter(|r| r.status != 0) {
+            warn!(
+                ?self,
+                replica_uuid = r.replica_uuid,
+                status = r.status,
+                "Failed to snapshot replica"
+            );
+        }
+        NexusSnapshotStatus {
             replicas_done,
             replicas_skipped,
             snapshot_timestamp: snapshot
                 .create_time()
                 .map(|t| t.parse::<DateTime<Utc>>().unwrap_or_default()),
-        })
+        }
     }
 
-    /// Create a snapshot on all children
+    /// Create a crash-consistent snapshot on all children which are not
+    /// skipped.
     pub async fn create_snapshot(
         mut self: Pin<&mut Self>,
         snapshot: SnapshotParams,
@@ -332,13 +337,9 @@ impl<'n> Nexus<'n> {
 
         self.check_nexus_state()?;
 
-        // For now only single replica nexus is supported.
-        if self.children_iter().len() != 1 {
-            return Err(Error::FailedCreateSnapshot {
-                name: self.bdev_name(),
-                reason: "Nexus has more than one replica".to_string(),
-            });
-        }
+        // Validate the replicas before I/O is paused.
+        let executor =
+            ReplicaSnapshotExecutor::new(self.as_ref(), replicas).await?;
 
         // Step 1: Pause I/O subsystem for nexus.
         self.as_mut().pause().await.map_err(|error| {
@@ -350,10 +351,27 @@ impl<'n> Nexus<'n> {
             error
         })?;
 
-        // Step 2: Create snapshots on all replicas.
-        let res = self.as_mut().do_nexus_snapshot(snapshot, replicas).await;
+        // Step 2: Flush all children, so that the snapshots contain every
+        // write acknowledged to the initiator.
+        let res = match self.flush_children().await {
+            Ok(()) => {
+                // Step 3: Create snapshots on all replicas.
+                Ok(self.as_mut().do_nexus_snapshot(snapshot, executor).await)
+            }
+            Err(error) => {
+                error!(
+                    ?self,
+                    error = error.verbose(),
+                    "Failed to flush children, nexus snapshot creation failed"
+                );
+                Err(Error::FailedCreateSnapshot {
+                    name: self.bdev_name(),
+                    reason: format!("failed to flush children: {error}"),
+                })
+            }
+        };
 
-        // Step 3: Resume I/O.
+        // Step 4: Resume I/O.
         if let Err(error) = self.as_mut().resume().await {
             error!(
                 ?self,
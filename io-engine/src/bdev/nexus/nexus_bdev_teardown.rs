@@ -0,0 +1,128 @@
+//! Implements the coordinated teardown of a nexus.
+//!
+//! Rebuild jobs hold descriptors on the children of the nexus, so they are
+//! aborted and their task pools drained before anything else is torn down.
+//! Only then is the nexus unshared and its children closed. The duration of
+//! each phase is recorded, so that a slow or stuck teardown can be diagnosed.
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use futures::{future::join_all, FutureExt};
+use strum_macros::{AsRefStr, Display};
+
+use super::{Error, Nexus};
+use crate::{rebuild::RebuildJob, sleep::mayastor_sleep};
+
+/// Maximum time to wait for the rebuild jobs of a nexus being destroyed to
+/// terminate.
+pub const NEXUS_TEARDOWN_REBUILD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Phases of the nexus teardown, in the order they are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, Display)]
+pub enum NexusTeardownPhase {
+    /// Rebuild jobs are aborted and their task pools drained.
+    AbortRebuilds,
+    /// The nexus is unshared.
+    Unshare,
+    /// The cached writes are written to the children.
+    DrainWriteCache,
+    /// The read cache device is detached.
+    DetachReadCache,
+    /// The I/O log journal is closed.
+    CloseIoLogJournal,
+    /// The children are closed.
+    CloseChildren,
+    /// The destruction is persisted.
+    Persist,
+    /// The nexus bdev is unregistered.
+    Unregister,
+}
+
+/// A completed phase of the nexus teardown.
+#[derive(Debug, Clone)]
+pub struct NexusTeardownStep {
+    /// The teardown phase.
+    pub phase: NexusTeardownPhase,
+    /// Time taken by the phase.
+    pub elapsed: Duration,
+}
+
+/// Records the phases of a nexus teardown.
+#[derive(Debug, Default)]
+pub(super) struct NexusTeardown {
+    steps: Vec<NexusTeardownStep>,
+    started: Option<(NexusTeardownPhase, Instant)>,
+}
+
+impl NexusTeardown {
+    /// Starts the given phase, completing the previous one.
+    pub(super) fn enter(&mut self, phase: NexusTeardownPhase) {
+        self.complete();
+        self.started = Some((phase, Instant::now()));
+    }
+
+    /// Completes the current phase and returns all completed phases.
+    pub(super) fn finish(mut self) -> Vec<NexusTeardownStep> {
+        self.complete();
+        self.steps
+    }
+
+    fn complete(&mut self) {
+        if let Some((phase, start)) = self.started.take() {
+            self.steps.push(NexusTeardownStep {
+                phase,
+                elapsed: start.elapsed(),
+            });
+        }
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Aborts all the rebuild jobs of the nexus, whether its children are
+    /// their source or their destination, and waits for them to terminate.
+    /// Fails if they are not done within the given timeout.
+    pub(super) async fn abort_rebuilds(
+        &self,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let mut jobs = HashMap::new();
+        for uri in self.child_uris() {
+            if let Ok(job) = RebuildJob::lookup(&uri) {
+                jobs.insert(job.dst_uri().to_string(), job);
+            }
+            for job in RebuildJob::lookup_src(&uri) {
+                jobs.insert(job.dst_uri().to_string(), job);
+            }
+        }
+        if jobs.is_empty() {
+            return Ok(());
+        }
+
+        info!("{self:?}: aborting {n} rebuild jobs...", n = jobs.len());
+
+        let count = jobs.len();
+        let terminated = jobs.values().map(|j| j.force_stop());
+        let mut all = join_all(terminated).fuse();
+        let mut expired = mayastor_sleep(timeout).fuse();
+
+        futures::select! {
+            _ = all => {
+                info!("{self:?}: rebuild jobs terminated");
+                Ok(())
+            },
+            _ = expired => {
+                error!(
+                    "{self:?}: timed out waiting for {count} rebuild jobs \
+                    to terminate"
+                );
+                Err(Error::RebuildDrainTimeout {
+                    name: self.name.clone(),
+                    jobs: count,
+                    timeout,
+                })
+            },
+        }
+    }
+}
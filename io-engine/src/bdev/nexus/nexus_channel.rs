@@ -3,23 +3,94 @@
 use std::{
     cell::UnsafeCell,
     fmt::{Debug, Display, Formatter},
+    ops::Deref,
     pin::Pin,
+    sync::Arc,
 };
 
-use super::{FaultReason, IOLogChannel, Nexus, NexusBio};
+use super::{
+    nexus_io_timeout::TimedRead,
+    FaultReason,
+    IOLogChannel,
+    Nexus,
+    NexusBio,
+    NexusChild,
+    NexusReadCache,
+};
+
+use crate::{
+    core::{
+        BlockDeviceHandle,
+        CoreError,
+        Cores,
+        IoBandwidth,
+        IoBandwidthClass,
+    },
+    rebuild::CopyOnReadMap,
+};
+
+/// I/O handle of a child in a channel, with the counters of the channel core
+/// in the bandwidth counters of the child, so that the I/O path counts the
+/// traffic of the child without looking it up.
+pub(super) struct ChildHandle {
+    handle: Box<dyn BlockDeviceHandle>,
+    bandwidth: Arc<IoBandwidth>,
+    slot: usize,
+}
+
+impl ChildHandle {
+    fn new(handle: Box<dyn BlockDeviceHandle>, child: &NexusChild) -> Self {
+        let bandwidth = child.bandwidth_counters();
+        let slot = bandwidth.slot(Cores::current());
+        Self {
+            handle,
+            bandwidth,
+            slot,
+        }
+    }
+
+    /// Counts bytes transferred to or from the child by client I/Os.
+    #[inline(always)]
+    pub(super) fn record_bandwidth(&self, class: IoBandwidthClass, bytes: u64) {
+        self.bandwidth.record_slot(self.slot, class, bytes);
+    }
+}
 
-use crate::core::{BlockDeviceHandle, CoreError, Cores};
+impl Deref for ChildHandle {
+    type Target = dyn BlockDeviceHandle;
+
+    fn deref(&self) -> &Self::Target {
+        self.handle.as_ref()
+    }
+}
 
 /// I/O channel, per core.
 #[repr(C)]
 pub struct NexusChannel<'n> {
-    writers: Vec<Box<dyn BlockDeviceHandle>>,
-    readers: Vec<Box<dyn BlockDeviceHandle>>,
+    writers: Vec<ChildHandle>,
+    readers: Vec<ChildHandle>,
+    /// Readers of the remote children, excluded from the read path while a
+    /// local child can serve reads.
+    standby_readers: Vec<ChildHandle>,
+    /// Reader of a local child being rebuilt with copy-on-read.
+    cor_reader: Option<(ChildHandle, Arc<CopyOnReadMap>)>,
+    /// Read cache of the nexus, if any.
+    read_cache: Option<Arc<NexusReadCache>>,
+    /// Handle of the read cache device.
+    read_cache_handle: Option<Box<dyn BlockDeviceHandle>>,
     io_logs: Vec<IOLogChannel>,
     previous_reader: UnsafeCell<usize>,
     fail_fast: u32,
     io_mode: IoMode,
     frozen_ios: Vec<NexusBio<'n>>,
+    no_space_ios: Vec<NexusBio<'n>>,
+    cache_waiting_ios: Vec<NexusBio<'n>>,
+    /// Writes waiting for the I/O log journal to be persisted.
+    journal_waiting_ios: Vec<NexusBio<'n>>,
+    /// Reads outstanding on the children, timed with the child I/O timeout.
+    pub(super) timed_reads: Vec<TimedRead<'n>>,
+    /// Completed I/Os waiting to be completed together, with their status.
+    completion_batch: Vec<(NexusBio<'n>, bool)>,
     nexus: Pin<&'n mut Nexus<'n>>,
     core: u32,
 }
@@ -28,11 +99,12 @@ impl<'n> Debug for NexusChannel<'n> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "I/O chan '{nex}' core:{core}({cur}) [R:{r} W:{w} L:{l} C:{c}]",
+            "I/O chan '{nex}' core:{core}({cur}) [R:{r}/{s} W:{w} L:{l} C:{c}]",
             nex = self.nexus.nexus_name(),
             core = self.core,
             cur = Cores::current(),
             r = self.readers.len(),
+            s = self.standby_readers.len(),
             w = self.writers.len(),
             l = self.io_logs.len(),
             c = self.nexus.child_count(),
@@ -57,6 +129,8 @@ pub enum DrEvent {
     ChildUnplug,
     /// Child rebuild event.
     ChildRebuild,
+    /// Read policy change event.
+    ReadPolicy,
 }
 
 impl Display for DrEvent {
@@ -67,6 +141,7 @@ impl Display for DrEvent {
             match self {
                 Self::ChildUnplug => "unplug",
                 Self::ChildRebuild => "rebuild",
+                Self::ReadPolicy => "read policy",
             }
         )
     }
@@ -79,14 +154,20 @@ impl<'n> NexusChannel<'n> {
 
         let mut writers = Vec::new();
         let mut readers = Vec::new();
+        let mut standby_readers = Vec::new();
+        let local_only = nexus.local_reads_only();
 
         nexus
             .children_iter()
             .filter(|c| c.is_healthy())
             .for_each(|c| match (c.get_io_handle(), c.get_io_handle()) {
                 (Ok(w), Ok(r)) => {
-                    writers.push(w);
-                    readers.push(r);
+                    writers.push(ChildHandle::new(w, c));
+                    if local_only && c.is_local() != Some(true) {
+                        standby_readers.push(ChildHandle::new(r, c));
+                    } else {
+                        readers.push(ChildHandle::new(r, c));
+                    }
                 }
                 _ => {
                     c.set_faulted_state(FaultReason::CantOpen);
@@ -98,17 +179,28 @@ impl<'n> NexusChannel<'n> {
                 }
             });
 
-        Self {
+        let mut channel = Self {
             writers,
             readers,
+            standby_readers,
+            cor_reader: None,
+            read_cache: None,
+            read_cache_handle: None,
             io_logs: nexus.io_log_channels(),
             previous_reader: UnsafeCell::new(0),
             nexus: unsafe { nexus.pinned_mut() },
             fail_fast: 0,
             io_mode: IoMode::Normal,
             frozen_ios: Vec::new(),
+            no_space_ios: Vec::new(),
+            cache_waiting_ios: Vec::new(),
+            journal_waiting_ios: Vec::new(),
+            completion_batch: Vec::new(),
+            timed_reads: Vec::new(),
             core: Cores::current(),
-        }
+        };
+        channel.connect_read_cache();
+        channel
     }
 
     /// TODO
@@ -120,7 +212,12 @@ impl<'n> NexusChannel<'n> {
         );
         self.writers.clear();
         self.readers.clear();
+        self.standby_readers.clear();
+        self.cor_reader = None;
+        self.read_cache = None;
+        self.read_cache_handle = None;
         self.io_logs.clear();
+        self.timed_reads.clear();
     }
 
     /// Returns reference to channel's Nexus.
@@ -136,18 +233,19 @@ impl<'n> NexusChannel<'n> {
         self.nexus.as_mut()
     }
 
-    /// Returns the total number of available readers in this channel.
+    /// Returns the total number of available readers in this channel,
+    /// including the standby readers.
     pub(crate) fn num_readers(&self) -> usize {
-        self.readers.len()
+        self.readers.len() + self.standby_readers.len()
     }
 
     /// Calls the given callback for each active writer.
     #[inline(always)]
-    pub(super) fn for_each_writer<F>(&self, mut f: F) -> Result<(), CoreError>
+    pub(super) fn for_each_writer<F>(&self, f: F) -> Result<(), CoreError>
     where
-        F: FnMut(&dyn BlockDeviceHandle) -> Result<(), CoreError>,
+        F: FnMut(&ChildHandle) -> Result<(), CoreError>,
     {
-        self.writers.iter().try_for_each(|h| f(h.as_ref()))
+        self.writers.iter().try_for_each(f)
     }
 
     /// Calls the given callback for each active I/O log.
@@ -164,29 +262,111 @@ impl<'n> NexusChannel<'n> {
     /// not the case but a side effect of using the async. As we poll
     /// threads more often depending on what core we are on etc, we might be
     /// "awaiting' while the thread is already trying to submit IO.
-    pub(crate) fn select_reader(&self) -> Option<&dyn BlockDeviceHandle> {
-        if self.readers.is_empty() {
+    /// The standby readers are only used once no other reader is left.
+    pub(super) fn select_reader(&self) -> Option<&ChildHandle> {
+        let readers = if self.readers.is_empty() {
+            &self.standby_readers
+        } else {
+            &self.readers
+        };
+
+        if readers.is_empty() {
             None
         } else {
             let idx = unsafe {
                 let idx = &mut *self.previous_reader.get();
-                if *idx < self.readers.len() - 1 {
+                if *idx < readers.len() - 1 {
                     *idx += 1;
                 } else {
                     *idx = 0;
                 }
                 *idx
             };
-            Some(self.readers[idx].as_ref())
+            Some(&readers[idx])
         }
     }
 
+    /// Returns a reader of a child other than the given one, if any.
+    pub(super) fn select_reader_except(
+        &self,
+        device_name: &str,
+    ) -> Option<&ChildHandle> {
+        self.readers
+            .iter()
+            .chain(self.standby_readers.iter())
+            .find(|h| h.get_device().device_name() != device_name)
+    }
+
+    /// Returns the reader of the given child device, if connected.
+    pub(super) fn find_reader(
+        &self,
+        device_name: &str,
+    ) -> Option<&ChildHandle> {
+        self.readers
+            .iter()
+            .chain(self.standby_readers.iter())
+            .chain(self.cor_reader.iter().map(|(h, _)| h))
+            .find(|h| h.get_device().device_name() == device_name)
+    }
+
+    /// Returns the read cache of the nexus, if any.
+    #[inline(always)]
+    pub(super) fn read_cache(&self) -> Option<&Arc<NexusReadCache>> {
+        self.read_cache.as_ref()
+    }
+
+    /// Returns the handle of the read cache device, if connected.
+    #[inline(always)]
+    pub(super) fn read_cache_handle(&self) -> Option<&dyn BlockDeviceHandle> {
+        self.read_cache_handle.as_deref()
+    }
+
+    /// Connects the read cache device of the nexus, or disconnects it if
+    /// the nexus has no read cache anymore. Without a device handle, the
+    /// channel still invalidates the cached lines it writes to.
+    pub(super) fn connect_read_cache(&mut self) {
+        let Some(d) = self.nexus().read_cache_device() else {
+            self.read_cache = None;
+            self.read_cache_handle = None;
+            return;
+        };
+
+        let cache = d.cache.clone();
+        let handle = d.descriptor.get_io_handle();
+
+        self.read_cache = Some(cache);
+        self.read_cache_handle = match handle {
+            Ok(h) => Some(h),
+            Err(e) => {
+                warn!("{self:?}: failed to get read cache I/O handle: {e}");
+                None
+            }
+        };
+    }
+
+    /// Returns the reader of the local child being rebuilt with
+    /// copy-on-read, and its copy-on-read map, if any.
+    pub(super) fn cor_reader(&self) -> Option<(&ChildHandle, &CopyOnReadMap)> {
+        self.cor_reader
+            .as_ref()
+            .map(|(hdl, cor)| (hdl, cor.as_ref()))
+    }
+
     /// Disconnects a child device from the I/O path.
     pub fn disconnect_device(&mut self, device_name: &str) {
         self.previous_reader = UnsafeCell::new(0);
 
+        if matches!(
+            &self.cor_reader,
+            Some((hdl, _)) if hdl.get_device().device_name() == device_name
+        ) {
+            self.cor_reader = None;
+        }
+
         self.readers
             .retain(|c| c.get_device().device_name() != device_name);
+        self.standby_readers
+            .retain(|c| c.get_device().device_name() != device_name);
         self.writers
             .retain(|c| c.get_device().device_name() != device_name);
 
@@ -210,6 +390,12 @@ impl<'n> NexusChannel<'n> {
 
         let mut writers = Vec::new();
         let mut readers = Vec::new();
+        let mut standby_readers = Vec::new();
+        let mut cor_reader = None;
+
+        // with preferred-local reads, the remote children only serve reads
+        // once no local child is left
+        let local_only = self.nexus().local_reads_only();
 
         // iterate over all our children which are in the healthy state
         self.nexus()
@@ -217,8 +403,12 @@ impl<'n> NexusChannel<'n> {
             .filter(|c| c.is_healthy())
             .for_each(|c| match (c.get_io_handle(), c.get_io_handle()) {
                 (Ok(w), Ok(r)) => {
-                    writers.push(w);
-                    readers.push(r);
+                    writers.push(ChildHandle::new(w, c));
+                    if local_only && c.is_local() != Some(true) {
+                        standby_readers.push(ChildHandle::new(r, c));
+                    } else {
+                        readers.push(ChildHandle::new(r, c));
+                    }
                 }
                 _ => {
                     c.set_faulted_state(FaultReason::CantOpen);
@@ -237,7 +427,28 @@ impl<'n> NexusChannel<'n> {
                             "{self:?}: connecting child device \
                                 in write-only mode: {c:?}"
                         );
-                        writers.push(hdl);
+                        writers.push(ChildHandle::new(hdl, c));
+
+                        // A local child rebuilt with copy-on-read also serves
+                        // reads of the segments already rebuilt.
+                        if let Some(cor) =
+                            c.rebuild_job().and_then(|j| j.cor_map())
+                        {
+                            match c.get_io_handle() {
+                                Ok(r) => {
+                                    debug!(
+                                        "{self:?}: connecting child device \
+                                        for copy-on-read: {c:?}"
+                                    );
+                                    cor_reader =
+                                        Some((ChildHandle::new(r, c), cor));
+                                }
+                                Err(e) => warn!(
+                                    "{self:?}: failed to get copy-on-read \
+                                    I/O handle for {c:?}: {e}"
+                                ),
+                            }
+                        }
                     }
                     Err(e) => {
                         c.set_faulted_state(FaultReason::CantOpen);
@@ -251,6 +462,8 @@ impl<'n> NexusChannel<'n> {
 
         self.writers = writers;
         self.readers = readers;
+        self.standby_readers = standby_readers;
+        self.cor_reader = cor_reader;
 
         self.reconnect_io_logs();
 
@@ -305,17 +518,26 @@ impl<'n> NexusChannel<'n> {
         });
     }
 
-    /// Aborts all frozen I/Os.
+    /// Aborts all frozen I/Os, including the ones parked due to ENOSPC or
+    /// waiting for cached writes or the I/O log journal.
     pub(super) fn abort_frozen(&mut self) {
         debug!(
             "{self:?}: aborting {n} frozen I/Os ...",
             n = self.frozen_ios.len()
+                + self.no_space_ios.len()
+                + self.cache_waiting_ios.len()
+                + self.journal_waiting_ios.len()
         );
 
-        self.frozen_ios.drain(..).for_each(|io| {
-            trace!("{io:?}: aborting a frozen I/O");
-            io.fail();
-        });
+        self.frozen_ios
+            .drain(..)
+            .chain(self.no_space_ios.drain(..))
+            .chain(self.cache_waiting_ios.drain(..))
+            .chain(self.journal_waiting_ios.drain(..))
+            .for_each(|io| {
+                trace!("{io:?}: aborting a frozen I/O");
+                io.fail();
+            });
     }
 
     /// Freezes submission of the given Nexus I/O.
@@ -323,4 +545,103 @@ impl<'n> NexusChannel<'n> {
         trace!("{io:?}: freezing I/O");
         self.frozen_ios.push(io)
     }
+
+    /// Parks the given Nexus I/O which failed due to ENOSPC on all children,
+    /// until space is available again.
+    pub(super) fn park_no_space_io(&mut self, io: NexusBio<'n>) {
+        trace!("{io:?}: parking I/O due to ENOSPC");
+        self.no_space_ios.push(io);
+        self.nexus().schedule_enospc_retry();
+    }
+
+    /// Resubmits all I/Os parked due to ENOSPC.
+    pub(super) fn resubmit_no_space(&mut self) {
+        if self.no_space_ios.is_empty() {
+            return;
+        }
+
+        debug!(
+            "{self:?}: resubmitting {n} I/Os parked due to ENOSPC ...",
+            n = self.no_space_ios.len()
+        );
+
+        // Writes failing with ENOSPC again are parked again upon completion.
+        std::mem::take(&mut self.no_space_ios)
+            .into_iter()
+            .for_each(|io| {
+                trace!("{io:?}: resubmitting an I/O parked due to ENOSPC");
+                io.submit_request();
+            });
+    }
+
+    /// Parks the given Nexus I/O until the overlapping writes in the nexus
+    /// write cache reach the children.
+    pub(super) fn park_cache_io(&mut self, io: NexusBio<'n>) {
+        trace!("{io:?}: parking I/O waiting for cached writes");
+        self.cache_waiting_ios.push(io);
+    }
+
+    /// Resubmits all I/Os waiting for cached writes.
+    pub(super) fn resubmit_cache_waiting(&mut self) {
+        if self.cache_waiting_ios.is_empty() {
+            return;
+        }
+
+        trace!(
+            "{self:?}: resubmitting {n} I/Os waiting for cached writes ...",
+            n = self.cache_waiting_ios.len()
+        );
+
+        // I/Os still overlapping cached writes are parked again.
+        std::mem::take(&mut self.cache_waiting_ios)
+            .into_iter()
+            .for_each(|io| io.submit_request());
+    }
+
+    /// Parks the given Nexus I/O until the I/O log journal is persisted.
+    pub(super) fn park_journal_io(&mut self, io: NexusBio<'n>) {
+        trace!("{io:?}: parking I/O waiting for the I/O log journal");
+        self.journal_waiting_ios.push(io);
+    }
+
+    /// Resubmits all I/Os waiting for the I/O log journal.
+    pub(super) fn resubmit_journal_waiting(&mut self) {
+        if self.journal_waiting_ios.is_empty() {
+            return;
+        }
+
+        trace!(
+            "{self:?}: resubmitting {n} I/Os waiting for the I/O log \
+            journal ...",
+            n = self.journal_waiting_ios.len()
+        );
+
+        // Writes to segments recorded since the last flush are parked again.
+        std::mem::take(&mut self.journal_waiting_ios)
+            .into_iter()
+            .for_each(|io| io.submit_request());
+    }
+
+    /// Adds a completed I/O to the completion batch. Returns true if it is
+    /// the first I/O of the batch.
+    pub(super) fn batch_completion(
+        &mut self,
+        io: NexusBio<'n>,
+        success: bool,
+    ) -> bool {
+        self.completion_batch.push((io, success));
+        self.completion_batch.len() == 1
+    }
+
+    /// Checks if completed I/Os are waiting in the completion batch.
+    pub(super) fn has_completion_batch(&self) -> bool {
+        !self.completion_batch.is_empty()
+    }
+
+    /// Takes the I/Os of the completion batch.
+    pub(super) fn take_completion_batch(
+        &mut self,
+    ) -> Vec<(NexusBio<'n>, bool)> {
+        std::mem::take(&mut self.completion_batch)
+    }
 }
@@ -2,7 +2,7 @@ use std::{
     convert::TryFrom,
     fmt::{Debug, Display, Formatter},
     marker::PhantomData,
-    sync::atomic::Ordering,
+    sync::{atomic::Ordering, Arc},
 };
 
 use chrono::{DateTime, Utc};
@@ -13,10 +13,19 @@ use serde::Serialize;
 use snafu::{ResultExt, Snafu};
 use url::Url;
 
-use super::{nexus_lookup_mut, DrEvent, IOLog, IOLogChannel};
+use super::{nexus_lookup_mut, DrEvent, IOLog, IOLogChannel, IOLogJournal};
 
 use crate::{
-    bdev::{device_create, device_destroy, device_lookup},
+    bdev::{
+        device_create,
+        device_destroy_with,
+        device_lookup,
+        uri::same_device,
+        DestroyMode,
+        NvmeNegotiatedParams,
+        NvmePathInfo,
+        NVME_CONTROLLERS,
+    },
     bdev_api::BdevError,
     core::{
         BlockDevice,
@@ -24,6 +33,8 @@ use crate::{
         BlockDeviceHandle,
         CoreError,
         DeviceEventSink,
+        IoBandwidth,
+        IoBandwidthStats,
         Reactor,
         Reactors,
         VerboseError,
@@ -309,6 +320,19 @@ pub struct NexusChild<'c> {
     /// I/O log.
     #[serde(skip_serializing)]
     io_log: Mutex<Option<IOLog>>,
+    /// UUID of the snapshot the child has been created from, if the child is
+    /// to be rebuilt from the snapshot delta only.
+    #[serde(skip_serializing)]
+    seed_snapshot: Mutex<Option<String>>,
+    /// Time the deferred rebuild of the child is scheduled at, if any.
+    #[serde(skip_serializing)]
+    rebuild_scheduled_at: Mutex<Option<DateTime<Utc>>>,
+    /// Maximum size of the child I/Os in bytes, zero if unlimited.
+    #[serde(skip_serializing)]
+    max_io_size: AtomicCell<u64>,
+    /// Bytes transferred to and from the child, over time windows.
+    #[serde(skip_serializing)]
+    bandwidth: Arc<IoBandwidth>,
     /// TODO
     #[serde(skip_serializing)]
     _c: PhantomData<&'c ()>,
@@ -593,72 +617,8 @@ impl<'c> NexusChild<'c> {
         &self,
         hdl: &dyn BlockDeviceHandle,
     ) -> Result<Option<(u8, u64, [u8; 16])>, ChildError> {
-        let mut buffer = hdl.dma_malloc(4096).context(HandleDmaMalloc {})?;
-        if let Err(e) = hdl.nvme_resv_report(1, &mut buffer).await {
-            return Err(ChildError::ResvReport {
-                source: e,
-            });
-        }
-
-        trace!("{:?}: received reservation report", self);
-
-        let (stext, sl) = buffer.as_slice().split_at(std::mem::size_of::<
-            spdk_nvme_reservation_status_extended_data,
-        >());
-        let (pre, resv_status_ext, post) = unsafe {
-            stext.align_to::<spdk_nvme_reservation_status_extended_data>()
-        };
-
-        assert!(pre.is_empty());
-        assert!(post.is_empty());
-
-        let regctl = resv_status_ext[0].data.regctl;
-
-        info!(
-            "reservation status: rtype {}, regctl {}, ptpls {}",
-            resv_status_ext[0].data.rtype,
-            regctl,
-            resv_status_ext[0].data.ptpls,
-        );
-
-        let (pre, reg_ctrlr_ext, _post) = unsafe {
-            sl.align_to::<spdk_nvme_registered_ctrlr_extended_data>()
-        };
-
-        if !pre.is_empty() {
-            return Ok(None);
-        }
-
-        let mut numctrlr: usize = regctl.into();
-        if numctrlr > reg_ctrlr_ext.len() {
-            numctrlr = reg_ctrlr_ext.len();
-            warn!(
-                "Expecting data for {} controllers, received {}",
-                regctl, numctrlr
-            );
-        }
-
-        for (i, c) in reg_ctrlr_ext.iter().enumerate().take(numctrlr) {
-            let cntlid = c.cntlid;
-            let rkey = c.rkey;
-            debug!(
-                "ctrlr {}: cntlid {:0x}h, status {}, hostid {:0x?}, \
-                rkey {:0x}h",
-                i,
-                cntlid,
-                c.rcsts.status(),
-                c.hostid,
-                rkey,
-            );
-            if c.rcsts.status() == 1 {
-                return Ok(Some((
-                    resv_status_ext[0].data.rtype,
-                    rkey,
-                    c.hostid,
-                )));
-            }
-        }
-        Ok(None)
+        trace!("{:?}: requesting reservation report", self);
+        nvme_resv_holder(hdl).await
     }
 
     /// Check if we're the reservation holder.
@@ -823,7 +783,7 @@ impl<'c> NexusChild<'c> {
         &self,
         params: &NexusNvmeParams,
     ) -> Result<(), ChildError> {
-        if std::env::var("NEXUS_NVMF_RESV_ENABLE").is_err() {
+        if !super::ENABLE_NVMF_RESERVATIONS.load(Ordering::SeqCst) {
             return Ok(());
         }
         if !params.reservations_enabled() {
@@ -1070,7 +1030,9 @@ impl<'c> NexusChild<'c> {
 
         // Destruction raises a device removal event.
         info!("{self:?}: destroying block device...");
-        match device_destroy(&self.name).await {
+        // The descriptor of the child is only dropped once the device
+        // removal is received, so the destroy must not wait for it.
+        match device_destroy_with(&self.name, DestroyMode::Force).await {
             Ok(_) => {
                 info!(
                     "{self:?}: block device destroyed, waiting for removal..."
@@ -1183,10 +1145,37 @@ impl<'c> NexusChild<'c> {
             faulted_at: parking_lot::Mutex::new(None),
             remove_channel: async_channel::bounded(1),
             io_log: Mutex::new(None),
+            seed_snapshot: Mutex::new(None),
+            rebuild_scheduled_at: Mutex::new(None),
+            max_io_size: AtomicCell::new(0),
+            bandwidth: Default::default(),
             _c: Default::default(),
         }
     }
 
+    /// Returns the maximum size of the child I/Os in bytes, zero if
+    /// unlimited.
+    pub fn max_io_size(&self) -> u64 {
+        self.max_io_size.load()
+    }
+
+    /// Sets the maximum size of the child I/Os in bytes, zero for unlimited.
+    pub(super) fn set_max_io_size(&self, size: u64) {
+        self.max_io_size.store(size);
+    }
+
+    /// Returns the bytes transferred to and from the child by client I/Os
+    /// and rebuilds, over the last minute, hour and day.
+    pub fn bandwidth(&self) -> IoBandwidthStats {
+        self.bandwidth.stats()
+    }
+
+    /// Returns the bandwidth counters of the child, shared with its I/O
+    /// channels and its rebuilds.
+    pub(super) fn bandwidth_counters(&self) -> Arc<IoBandwidth> {
+        self.bandwidth.clone()
+    }
+
     /// Returns reference to child's block device.
     pub fn get_device(&self) -> Result<&dyn BlockDevice, ChildError> {
         if let Some(ref device) = self.device {
@@ -1228,6 +1217,23 @@ impl<'c> NexusChild<'c> {
         }
     }
 
+    /// Returns the paths of a remote child connected over NVMe-oF, the active
+    /// one being marked as such.
+    pub fn paths(&self) -> Vec<NvmePathInfo> {
+        self.get_device_name()
+            .and_then(|name| NVME_CONTROLLERS.lookup_by_name(&name))
+            .map(|ctrlr| ctrlr.lock().paths())
+            .unwrap_or_default()
+    }
+
+    /// Returns the NVMe parameters negotiated with the target of a remote
+    /// child connected over NVMe-oF.
+    pub fn nvme_params(&self) -> Option<NvmeNegotiatedParams> {
+        self.get_device_name()
+            .and_then(|name| NVME_CONTROLLERS.lookup_by_name(&name))
+            .and_then(|ctrlr| ctrlr.lock().negotiated_params())
+    }
+
     /// Get I/O handle for the block device associated with this Nexus child.
     pub fn get_io_handle(
         &self,
@@ -1260,11 +1266,15 @@ impl<'c> NexusChild<'c> {
         self.device.as_ref().map(|d| d.device_name())
     }
 
-    /// TODO
+    /// Checks if the device of the child has the given name. The device may
+    /// also be given by its URI, in any form referencing the same device.
     pub fn match_device_name(&self, bdev_name: &str) -> bool {
         match self.get_device_name() {
-            Some(n) => n == bdev_name,
-            None => false,
+            Some(n) if n == bdev_name => true,
+            Some(_) if bdev_name.contains("://") => {
+                same_device(self.uri(), bdev_name)
+            }
+            _ => false,
         }
     }
 
@@ -1297,8 +1307,12 @@ impl<'c> NexusChild<'c> {
     /// Returns true if a log has been created or already exists, false if I/O
     /// log is disabled for this child for whatever reason.
     ///
-    /// I/O log is never created if the child is not fully synced.
-    pub(super) fn start_io_log(&self) -> bool {
+    /// I/O log is never created if the child is not fully synced. A new I/O
+    /// log is recorded in the given I/O log journal, if any.
+    pub(super) fn start_io_log(
+        &self,
+        journal: Option<&std::sync::Arc<IOLogJournal>>,
+    ) -> bool {
         if !super::ENABLE_PARTIAL_REBUILD.load(Ordering::SeqCst) {
             return false;
         }
@@ -1316,6 +1330,7 @@ impl<'c> NexusChild<'c> {
                     &d.device_name(),
                     d.num_blocks(),
                     d.block_len(),
+                    journal.and_then(|j| j.assign(self.uri())),
                 ));
 
                 debug!("{self:?}: started new I/O log: {log:?}", log = *io_log);
@@ -1331,6 +1346,28 @@ impl<'c> NexusChild<'c> {
         self.io_log.lock().take().map(|log| log.finalize())
     }
 
+    /// Returns the UUID of the snapshot the child is seeded from, if any.
+    pub fn seed_snapshot(&self) -> Option<String> {
+        self.seed_snapshot.lock().clone()
+    }
+
+    /// Sets or clears the snapshot the child is seeded from.
+    pub(super) fn set_seed_snapshot(&self, snapshot_uuid: Option<String>) {
+        *self.seed_snapshot.lock() = snapshot_uuid;
+    }
+
+    /// Returns the time the deferred rebuild of the child is scheduled at,
+    /// if any.
+    pub fn rebuild_scheduled_at(&self) -> Option<DateTime<Utc>> {
+        *self.rebuild_scheduled_at.lock()
+    }
+
+    /// Sets or clears the time the deferred rebuild of the child is
+    /// scheduled at.
+    pub(super) fn set_rebuild_scheduled_at(&self, at: Option<DateTime<Utc>>) {
+        *self.rebuild_scheduled_at.lock() = at;
+    }
+
     /// Returns I/O log channel for the current core.
     pub(super) fn io_log_channel(&self) -> Option<IOLogChannel> {
         self.io_log.lock().as_ref().map(|log| log.current_channel())
@@ -1341,3 +1378,67 @@ impl<'c> NexusChild<'c> {
         self.io_log.lock().is_some()
     }
 }
+
+/// Get the NVMe reservation holder of a device.
+/// Returns: (type, key, host id) of the reservation holder.
+pub(crate) async fn nvme_resv_holder(
+    hdl: &dyn BlockDeviceHandle,
+) -> Result<Option<(u8, u64, [u8; 16])>, ChildError> {
+    let mut buffer = hdl.dma_malloc(4096).context(HandleDmaMalloc {})?;
+    if let Err(e) = hdl.nvme_resv_report(1, &mut buffer).await {
+        return Err(ChildError::ResvReport {
+            source: e,
+        });
+    }
+
+    let (stext, sl) = buffer.as_slice().split_at(std::mem::size_of::<
+        spdk_nvme_reservation_status_extended_data,
+    >());
+    let (pre, resv_status_ext, post) = unsafe {
+        stext.align_to::<spdk_nvme_reservation_status_extended_data>()
+    };
+
+    assert!(pre.is_empty());
+    assert!(post.is_empty());
+
+    let regctl = resv_status_ext[0].data.regctl;
+
+    info!(
+        "reservation status: rtype {}, regctl {}, ptpls {}",
+        resv_status_ext[0].data.rtype, regctl, resv_status_ext[0].data.ptpls,
+    );
+
+    let (pre, reg_ctrlr_ext, _post) =
+        unsafe { sl.align_to::<spdk_nvme_registered_ctrlr_extended_data>() };
+
+    if !pre.is_empty() {
+        return Ok(None);
+    }
+
+    let mut numctrlr: usize = regctl.into();
+    if numctrlr > reg_ctrlr_ext.len() {
+        numctrlr = reg_ctrlr_ext.len();
+        warn!(
+            "Expecting data for {} controllers, received {}",
+            regctl, numctrlr
+        );
+    }
+
+    for (i, c) in reg_ctrlr_ext.iter().enumerate().take(numctrlr) {
+        let cntlid = c.cntlid;
+        let rkey = c.rkey;
+        debug!(
+            "ctrlr {}: cntlid {:0x}h, status {}, hostid {:0x?}, \
+            rkey {:0x}h",
+            i,
+            cntlid,
+            c.rcsts.status(),
+            c.hostid,
+            rkey,
+        );
+        if c.rcsts.status() == 1 {
+            return Ok(Some((resv_status_ext[0].data.rtype, rkey, c.hostid)));
+        }
+    }
+    Ok(None)
+}
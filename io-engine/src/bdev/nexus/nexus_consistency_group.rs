@@ -0,0 +1,300 @@
+//! Implements consistency groups of nexuses.
+//!
+//! A consistency group spans several nexuses of the node, typically the
+//! volumes of a single application, such as the data and the WAL volumes of
+//! a database. A group is frozen, thawed and snapshotted as a whole: the I/O
+//! of all its nexuses is paused and flushed before any of them is
+//! snapshotted, so that the snapshots of the group capture the same point in
+//! time across all of its volumes.
+//!
+//! Groups are kept in memory only: they are recreated by the control plane
+//! when the io-engine restarts.
+use std::{collections::HashMap, pin::Pin, time::Duration};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+
+use super::{
+    nexus_lookup_uuid_mut,
+    Error,
+    Nexus,
+    NexusFreeze,
+    NexusReplicaSnapshotDescriptor,
+    NexusSnapshotStatus,
+};
+use crate::core::{
+    snapshot::{SnapshotDescriptor, SnapshotParams},
+    VerboseError,
+};
+
+/// A group of nexuses which are frozen and snapshotted together.
+#[derive(Debug, Clone)]
+pub struct ConsistencyGroup {
+    /// Name of the group.
+    pub name: String,
+    /// UUIDs of the member nexuses.
+    pub nexuses: Vec<String>,
+}
+
+/// Consistency groups, by name.
+static CONSISTENCY_GROUPS: Lazy<
+    parking_lot::Mutex<HashMap<String, ConsistencyGroup>>,
+> = Lazy::new(Default::default);
+
+/// Creates a consistency group of the given nexuses. A nexus can be a member
+/// of a single group.
+pub fn consistency_group_create(
+    name: &str,
+    nexuses: Vec<String>,
+) -> Result<ConsistencyGroup, Error> {
+    if name.is_empty() || nexuses.is_empty() {
+        return Err(Error::InvalidArguments {
+            name: name.to_string(),
+            args: "a consistency group needs a name and at least one nexus"
+                .to_string(),
+        });
+    }
+
+    for (i, uuid) in nexuses.iter().enumerate() {
+        if nexuses[.. i].contains(uuid) {
+            return Err(Error::InvalidArguments {
+                name: name.to_string(),
+                args: format!("nexus {uuid} is listed more than once"),
+            });
+        }
+        member(uuid)?;
+    }
+
+    let mut groups = CONSISTENCY_GROUPS.lock();
+    if groups.contains_key(name) {
+        return Err(Error::ConsistencyGroupExists {
+            name: name.to_string(),
+        });
+    }
+    for group in groups.values() {
+        if let Some(uuid) = nexuses.iter().find(|n| group.nexuses.contains(n)) {
+            return Err(Error::ConsistencyGroupMember {
+                nexus: uuid.clone(),
+                name: group.name.clone(),
+            });
+        }
+    }
+
+    let group = ConsistencyGroup {
+        name: name.to_string(),
+        nexuses,
+    };
+    groups.insert(name.to_string(), group.clone());
+    info!("Created consistency group {group:?}");
+    Ok(group)
+}
+
+/// Destroys a consistency group. Its nexuses are left as they are.
+pub fn consistency_group_destroy(name: &str) -> Result<(), Error> {
+    match CONSISTENCY_GROUPS.lock().remove(name) {
+        Some(group) => {
+            info!("Destroyed consistency group {group:?}");
+            Ok(())
+        }
+        None => Err(Error::ConsistencyGroupNotFound {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// Looks up a consistency group by its name.
+pub fn consistency_group_lookup(name: &str) -> Result<ConsistencyGroup, Error> {
+    CONSISTENCY_GROUPS.lock().get(name).cloned().ok_or_else(|| {
+        Error::ConsistencyGroupNotFound {
+            name: name.to_string(),
+        }
+    })
+}
+
+/// Returns all the consistency groups, sorted by name.
+pub fn consistency_groups() -> Vec<ConsistencyGroup> {
+    let mut groups = CONSISTENCY_GROUPS
+        .lock()
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    groups
+}
+
+/// Looks up a member nexus of a consistency group.
+fn member<'n>(uuid: &str) -> Result<Pin<&'n mut Nexus<'n>>, Error> {
+    nexus_lookup_uuid_mut(uuid).ok_or_else(|| Error::NexusNotFound {
+        name: uuid.to_string(),
+    })
+}
+
+impl ConsistencyGroup {
+    /// Freezes all the nexuses of the group. If a nexus fails to freeze, the
+    /// nexuses which were frozen are thawed. The group is thawed
+    /// automatically when the first freeze deadline expires.
+    pub async fn freeze(
+        &self,
+        timeout: Duration,
+    ) -> Result<Vec<NexusFreeze>, Error> {
+        info!("Freezing consistency group {self:?} for {timeout:?}...");
+
+        let mut freezes = Vec::with_capacity(self.nexuses.len());
+        for uuid in &self.nexuses {
+            match async { member(uuid)?.freeze(timeout).await }.await {
+                Ok(freeze) => freezes.push(freeze),
+                Err(error) => {
+                    error!(
+                        "Consistency group '{}': failed to freeze nexus \
+                        {uuid}, thawing the group: {e}",
+                        self.name,
+                        e = error.verbose()
+                    );
+                    for uuid in self.nexuses[.. freezes.len()].iter().rev() {
+                        if let Ok(nexus) = member(uuid) {
+                            nexus.thaw().await.ok();
+                        }
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        info!("Consistency group '{}' frozen", self.name);
+        Ok(freezes)
+    }
+
+    /// Thaws all the frozen nexuses of the group. All nexuses are thawed
+    /// even if some fail to, and the first error is returned.
+    pub async fn thaw(&self) -> Result<(), Error> {
+        info!("Thawing consistency group {self:?}...");
+
+        let mut result = Ok(());
+        for uuid in &self.nexuses {
+            let nexus = match member(uuid) {
+                Ok(nexus) if nexus.freeze_info().is_some() => nexus,
+                Ok(_) => continue,
+                Err(error) => {
+                    result = result.and(Err(error));
+                    continue;
+                }
+            };
+            if let Err(error) = nexus.thaw().await {
+                error!(
+                    "Consistency group '{}': failed to thaw nexus {uuid}: {e}",
+                    self.name,
+                    e = error.verbose()
+                );
+                result = result.and(Err(error));
+            }
+        }
+        result
+    }
+
+    /// Returns the time when the group is going to be thawed automatically,
+    /// if all of its nexuses are frozen.
+    pub fn frozen_until(&self) -> Option<DateTime<Utc>> {
+        self.nexuses
+            .iter()
+            .map(|uuid| {
+                nexus_lookup_uuid_mut(uuid)
+                    .and_then(|n| n.freeze_info())
+                    .map(|f| f.deadline)
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min()
+    }
+
+    /// Creates a crash-consistent snapshot of all the nexuses of the group:
+    /// the I/O of every nexus is paused and flushed before the replicas of
+    /// any nexus are snapshotted, and resumed once all of them are done.
+    /// The replicas of every nexus must be given.
+    pub async fn create_snapshot(
+        &self,
+        snapshot: SnapshotParams,
+        mut replicas: HashMap<String, Vec<NexusReplicaSnapshotDescriptor>>,
+    ) -> Result<Vec<(String, NexusSnapshotStatus)>, Error> {
+        if let Some(uuid) =
+            self.nexuses.iter().find(|n| !replicas.contains_key(*n))
+        {
+            return Err(Error::InvalidArguments {
+                name: self.name.clone(),
+                args: format!("no replicas given for nexus {uuid}"),
+            });
+        }
+        if let Some(uuid) = replicas.keys().find(|n| !self.nexuses.contains(*n))
+        {
+            return Err(Error::InvalidArguments {
+                name: self.name.clone(),
+                args: format!("nexus {uuid} is not a member of the group"),
+            });
+        }
+
+        info!("Snapshotting consistency group {self:?}...");
+
+        // Step 1: pause and flush all nexuses.
+        let mut paused = 0;
+        let mut failure = None;
+        for uuid in &self.nexuses {
+            let r = async {
+                let mut nexus = member(uuid)?;
+                nexus.as_mut().pause().await?;
+                paused += 1;
+                nexus.flush_children().await
+            }
+            .await;
+            if let Err(error) = r {
+                failure = Some(error);
+                break;
+            }
+        }
+
+        // Step 2: snapshot the nexuses one by one. Each snapshot pauses its
+        // nexus again, which is a no-op for an already paused nexus.
+        let mut done = Vec::with_capacity(self.nexuses.len());
+        if failure.is_none() {
+            for uuid in &self.nexuses {
+                let mut params = snapshot.clone();
+                params.set_parent_id(uuid.clone());
+                let replicas = replicas.remove(uuid).unwrap_or_default();
+
+                let r = async {
+                    member(uuid)?.create_snapshot(params, replicas).await
+                }
+                .await;
+                match r {
+                    Ok(status) => done.push((uuid.clone(), status)),
+                    Err(error) => {
+                        error!(
+                            "Consistency group '{}': failed to snapshot \
+                            nexus {uuid}: {e}",
+                            self.name,
+                            e = error.verbose()
+                        );
+                        failure = Some(error);
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Step 3: resume all the nexuses which were paused.
+        for uuid in &self.nexuses[.. paused] {
+            let r = async { member(uuid)?.resume().await }.await;
+            if let Err(error) = r {
+                error!(
+                    "Consistency group '{}': failed to resume nexus {uuid}, \
+                    it might be not accessible by initiator: {e}",
+                    self.name,
+                    e = error.verbose()
+                );
+            }
+        }
+
+        match failure {
+            Some(error) => Err(error),
+            None => Ok(done),
+        }
+    }
+}
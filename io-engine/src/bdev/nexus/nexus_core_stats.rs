@@ -0,0 +1,96 @@
+//! Per-core I/O statistics of a nexus.
+//!
+//! Each core submits the nexus I/Os of the initiators connected to its poll
+//! group through its own nexus channel. The queue depth and the number of
+//! I/Os of each core show how the load of a nexus spreads over the cores.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use super::Nexus;
+use crate::core::Cores;
+
+/// I/O counters of a nexus on a single core.
+#[derive(Debug, Default)]
+struct CoreCounters {
+    queue_depth: AtomicU64,
+    num_ops: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// I/O counters of a nexus, per core.
+#[derive(Debug)]
+pub struct NexusCoreCounters {
+    cores: Vec<(u32, CoreCounters)>,
+}
+
+impl Default for NexusCoreCounters {
+    fn default() -> Self {
+        Self {
+            cores: Cores::count()
+                .into_iter()
+                .map(|core| (core, CoreCounters::default()))
+                .collect(),
+        }
+    }
+}
+
+/// Point-in-time copy of the I/O counters of a nexus on a core.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct NexusCoreStats {
+    /// Core the counters belong to.
+    pub core: u32,
+    /// Nexus I/Os in flight on the core.
+    pub queue_depth: u64,
+    /// Nexus I/Os completed on the core.
+    pub num_ops: u64,
+    /// Bytes of the reads and writes completed on the core.
+    pub bytes: u64,
+}
+
+impl NexusCoreCounters {
+    fn get(&self, core: u32) -> Option<&CoreCounters> {
+        self.cores.iter().find(|(c, _)| *c == core).map(|(_, c)| c)
+    }
+
+    /// Counts a nexus I/O submitted on the given core.
+    pub(super) fn submitted(&self, core: u32) {
+        if let Some(c) = self.get(core) {
+            c.queue_depth.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts nexus I/Os of the given total size completed on the given core.
+    pub(super) fn completed(&self, core: u32, ops: u64, bytes: u64) {
+        if let Some(c) = self.get(core) {
+            c.queue_depth.fetch_sub(ops, Ordering::Relaxed);
+            c.num_ops.fetch_add(ops, Ordering::Relaxed);
+            c.bytes.fetch_add(bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of nexus I/Os in flight on the given core.
+    pub(super) fn queue_depth(&self, core: u32) -> u64 {
+        self.get(core)
+            .map_or(0, |c| c.queue_depth.load(Ordering::Relaxed))
+    }
+
+    fn snapshot(&self) -> Vec<NexusCoreStats> {
+        self.cores
+            .iter()
+            .map(|(core, c)| NexusCoreStats {
+                core: *core,
+                queue_depth: c.queue_depth.load(Ordering::Relaxed),
+                num_ops: c.num_ops.load(Ordering::Relaxed),
+                bytes: c.bytes.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the I/O counters of the nexus, per core.
+    pub fn core_stats(&self) -> Vec<NexusCoreStats> {
+        self.core_stats.snapshot()
+    }
+}
@@ -0,0 +1,138 @@
+//! Implements the flush policy of a nexus.
+//!
+//! The policy is set when the nexus is published, and controls how the hosts
+//! are told to make their writes durable, and how this reaches the children:
+//!
+//! - A nexus advertising a volatile write cache relies on the hosts to send
+//!   flushes, or FUA writes, when they need durability. A FUA write bypasses
+//!   the nexus write cache and is followed by a flush of the children before it
+//!   completes. By default, the cache is advertised when all the children
+//!   support flushes. The nexus write cache, when enabled, is always
+//!   advertised.
+//! - A flush is propagated to all the children. With the `All` target, it
+//!   completes once it has reached all the children which remain healthy:
+//!   children which fail it are faulted. With the `Quorum` target, it also
+//!   fails unless a majority of the children it was sent to completed it.
+//! - Without an advertised cache, hosts consider a write durable once it
+//!   completes. With write-through, each write is followed by a flush of the
+//!   children before it completes, which keeps this promise when the children
+//!   have volatile caches of their own.
+//!
+//! The default policy advertises the cache and flushes all children, which
+//! is what databases expect.
+use serde::Serialize;
+
+use super::{Error, Nexus};
+use crate::core::IoType;
+
+/// Children a flush must reach before it completes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum NexusFlushTarget {
+    /// All the children which remain healthy.
+    #[default]
+    All,
+    /// A majority of the children the flush was sent to.
+    Quorum,
+}
+
+/// Flush policy of a published nexus.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NexusFlushPolicy {
+    /// Advertise a volatile write cache to the hosts. If not set, it is
+    /// advertised when all the children support flushes.
+    pub volatile_write_cache: Option<bool>,
+    /// Children a flush must reach.
+    pub flush_target: NexusFlushTarget,
+    /// Flush the children after every write, before completing it.
+    pub write_through: bool,
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the flush policy of the nexus.
+    pub fn flush_policy(&self) -> NexusFlushPolicy {
+        self.flush_policy.load()
+    }
+
+    /// Sets the flush policy of the nexus. The volatile write cache is
+    /// advertised to the hosts when they connect, so that the policy cannot
+    /// change once the nexus is published.
+    pub fn set_flush_policy(
+        &self,
+        policy: NexusFlushPolicy,
+    ) -> Result<(), Error> {
+        let invalid = |args: &str| Error::InvalidArguments {
+            name: self.name.clone(),
+            args: args.to_string(),
+        };
+
+        if self.write_cache().is_some() {
+            if policy.volatile_write_cache == Some(false) {
+                return Err(invalid(
+                    "the nexus write cache is always advertised to the hosts",
+                ));
+            }
+            if policy.write_through {
+                return Err(invalid(
+                    "write-through is not compatible with the nexus write \
+                    cache",
+                ));
+            }
+        }
+        if policy.write_through && policy.volatile_write_cache == Some(true) {
+            return Err(invalid(
+                "write-through is not compatible with an advertised volatile \
+                write cache",
+            ));
+        }
+
+        if self.shared().is_some() {
+            if policy == self.flush_policy() {
+                return Ok(());
+            }
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "cannot change the flush policy of published nexus '{}'",
+                    self.name
+                ),
+            });
+        }
+
+        info!("{self:?}: setting flush policy to {policy:?}");
+        self.flush_policy.store(policy);
+
+        let advertised = self.volatile_write_cache();
+        unsafe {
+            (*self.bdev().unsafe_inner_mut_ptr()).write_cache =
+                advertised as i32;
+        }
+        Ok(())
+    }
+
+    /// Checks if the nexus advertises a volatile write cache to the hosts.
+    pub fn volatile_write_cache(&self) -> bool {
+        if self.write_cache().is_some() {
+            return true;
+        }
+        match self.flush_policy().volatile_write_cache {
+            Some(advertised) => advertised,
+            None => {
+                !self.flush_policy().write_through
+                    && self.io_is_supported(IoType::Flush)
+            }
+        }
+    }
+
+    /// Checks if the writes of the nexus must be followed by a flush of the
+    /// children.
+    #[inline(always)]
+    pub(super) fn write_through(&self) -> bool {
+        self.flush_policy().write_through && self.io_is_supported(IoType::Flush)
+    }
+
+    /// Checks if the flushes of the nexus must complete on a majority of the
+    /// children.
+    #[inline(always)]
+    pub(super) fn flush_quorum(&self) -> bool {
+        self.flush_policy().flush_target == NexusFlushTarget::Quorum
+    }
+}
@@ -1,17 +1,43 @@
 use std::{
     fmt::{Debug, Formatter},
     ops::{Deref, DerefMut},
+    sync::{atomic::Ordering, Arc},
 };
 
 use libc::c_void;
 use nix::errno::Errno;
+use once_cell::sync::Lazy;
 
 use spdk_rs::{
-    libspdk::{spdk_bdev_io, spdk_io_channel},
+    libspdk::{
+        spdk_bdev_io,
+        spdk_bdev_io_get_submit_tsc,
+        spdk_get_thread,
+        spdk_io_channel,
+        spdk_thread_send_msg,
+    },
     BdevIo,
+    DmaBuf,
 };
 
-use super::{FaultReason, IOLogChannel, Nexus, NexusChannel, NEXUS_PRODUCT_ID};
+use super::{
+    nexus_write_cache::Destage,
+    CacheInsert,
+    CacheLookup,
+    ChildHandle,
+    FaultReason,
+    IOLogChannel,
+    Nexus,
+    NexusChannel,
+    NexusEnospcPolicy,
+    NexusIoCopy,
+    NexusIoErrorClass,
+    NexusIoSplit,
+    NexusShutdownReason,
+    NexusWriteCache,
+    NEXUS_IO_TRANSIENT_RETRIES,
+    NEXUS_PRODUCT_ID,
+};
 
 use crate::core::{
     BlockDevice,
@@ -19,16 +45,28 @@ use crate::core::{
     CoreError,
     Cores,
     GenericStatusCode,
+    IoBandwidthClass,
     IoCompletionStatus,
+    IoHistograms,
     IoStatus,
     IoSubmissionFailure,
     IoType,
     LvolFailure,
+    MayastorFeatures,
     Mthread,
     NvmeStatus,
     ReadOptions,
 };
 
+/// Queue depth of a core from which the completions of its nexus I/Os are
+/// batched.
+const NEXUS_BATCH_MIN_QUEUE_DEPTH: u64 = 4;
+
+/// Set if the nexus I/O completions are batched, with the
+/// `NEXUS_BATCHED_IO_ENABLE` feature flag.
+static NEXUS_BATCHED_IO: Lazy<bool> =
+    Lazy::new(|| MayastorFeatures::get_features().nexus_batched_io);
+
 #[cfg(feature = "nexus-io-tracing")]
 mod debug_nexus_io {
     use std::sync::atomic::{AtomicU64, Ordering};
@@ -55,17 +93,45 @@ macro_rules! trace_nexus_io {
 pub(super) struct NioCtx<'n> {
     /// Number of I/O's submitted. Nexus I/O's may never be freed until this
     /// counter drops to zero.
-    in_flight: u8,
+    in_flight: u32,
     /// Intermediate status of the I/O.
     status: IoStatus,
     /// Reference to the channel.
     channel: spdk_rs::IoChannel<NexusChannel<'n>>,
     /// Counter for successfully completed child I/Os.
-    successful: u8,
+    successful: u32,
     /// Counter for failed child I/Os.
-    failed: u8,
+    failed: u32,
     /// Number of resubmissions. Incremented with each resubmission.
     resubmits: u8,
+    /// Counter for child I/Os failed due to ENOSPC, which did not fault the
+    /// child.
+    no_space: u8,
+    /// Set when children failing with ENOSPC must be faulted, because the
+    /// I/O has succeeded on other children.
+    fault_no_space: bool,
+    /// Counter for child I/Os failed due to transient errors, which did not
+    /// fault the child.
+    transient: u8,
+    /// Number of resubmissions due to transient child errors.
+    transient_retries: u8,
+    /// For a flush with the write cache enabled, the sequence number which
+    /// the cached writes to wait for are below. Zero until first submitted.
+    flush_seq: u64,
+    /// Slot of the read cache a read is served from.
+    read_cache_slot: u32,
+    /// Set when a read has timed out on its child and been aborted.
+    timed_out: bool,
+    /// Set when a buffer has been allocated for a read submitted without one.
+    buffer_allocated: bool,
+    /// Set when a write has been copied into the write cache.
+    copied: bool,
+    /// Set when a write which succeeded on all children is flushing them,
+    /// under the write-through policy.
+    flushing: bool,
+    /// Split layout of a read or write larger than the maximum I/O size of
+    /// the nexus. Dropped when the nexus I/O completes.
+    split: Option<Box<NexusIoSplit>>,
     /// Debug serial number.
     #[cfg(feature = "nexus-io-tracing")]
     serial: u64,
@@ -140,10 +206,21 @@ impl<'n> From<*mut spdk_bdev_io> for NexusBio<'n> {
 }
 
 impl<'n> NexusBio<'n> {
-    fn as_ptr(&self) -> *mut spdk_bdev_io {
+    pub(super) fn as_ptr(&self) -> *mut spdk_bdev_io {
         self.0.legacy_as_ptr()
     }
 
+    /// Checks if the I/O is a write with the FUA bit set, i.e. a write which
+    /// must be durable once it completes. The NVMe-oF target passes the
+    /// command dword 12 of the host write down to the bdev layer, and FUA
+    /// is bit 30 of it.
+    #[inline(always)]
+    pub(super) fn is_fua(&self) -> bool {
+        self.io_type() == IoType::Write
+            && unsafe { (*self.as_ptr()).u.bdev.nvme_cdw12.raw } & (1 << 30)
+                != 0
+    }
+
     /// Makes a new instance of `NexusBio` from a channel and `BdevIo`.
     pub(super) fn new(
         channel: spdk_rs::IoChannel<NexusChannel<'n>>,
@@ -157,12 +234,28 @@ impl<'n> NexusBio<'n> {
         ctx.resubmits = 0;
         ctx.successful = 0;
         ctx.failed = 0;
+        ctx.no_space = 0;
+        ctx.fault_no_space = false;
+        ctx.transient = 0;
+        ctx.transient_retries = 0;
+        ctx.flush_seq = 0;
+        ctx.read_cache_slot = 0;
+        ctx.timed_out = false;
+        ctx.buffer_allocated = false;
+        ctx.copied = false;
+        ctx.flushing = false;
+        // The context memory is not initialized: the field must not be
+        // dropped.
+        unsafe { std::ptr::write(&mut ctx.split, None) };
 
         #[cfg(feature = "nexus-io-tracing")]
         {
             ctx.serial = debug_nexus_io::new_serial();
         }
 
+        bio.nexus().client_io_depth.fetch_add(1, Ordering::Relaxed);
+        bio.nexus().core_stats.submitted(Cores::current());
+
         trace_nexus_io!("New: {bio:?}");
 
         bio
@@ -176,14 +269,38 @@ impl<'n> NexusBio<'n> {
             return;
         }
 
+        if matches!(
+            self.io_type(),
+            IoType::Write | IoType::WriteZeros | IoType::Unmap
+        ) {
+            if let Some(cache) = self.channel().read_cache() {
+                cache.invalidate(self.effective_offset(), self.num_blocks());
+            }
+        }
+
+        if self.wait_write_cache() {
+            return;
+        }
+
+        if self.wait_io_log_journal() {
+            return;
+        }
+
         if let Err(_e) = match self.io_type() {
             IoType::Read => self.readv(),
+            IoType::Write => match self.nexus().write_cache().cloned() {
+                // FUA writes, and writes flushing the children, go straight
+                // to the children.
+                Some(cache) if !self.ctx().flushing && !self.is_fua() => {
+                    self.submit_write_cached(cache)
+                }
+                _ => self.submit_all(),
+            },
+            IoType::Flush => self.flush(),
             // these IOs are submitted to all the underlying children
-            IoType::Write
-            | IoType::WriteZeros
-            | IoType::Reset
-            | IoType::Unmap
-            | IoType::Flush => self.submit_all(),
+            IoType::WriteZeros | IoType::Reset | IoType::Unmap => {
+                self.submit_all()
+            }
             IoType::NvmeAdmin => {
                 self.fail();
                 Err(CoreError::NotSupported {
@@ -202,6 +319,155 @@ impl<'n> NexusBio<'n> {
         }
     }
 
+    /// Completes the nexus I/O with success.
+    pub(super) fn ok(&self) {
+        self.clone().release_split();
+        if self.batch_completion(true) {
+            return;
+        }
+        self.nexus().client_io_depth.fetch_sub(1, Ordering::Relaxed);
+        self.record_latency();
+        self.record_copy();
+        self.nexus().core_stats.completed(
+            Cores::current(),
+            1,
+            self.data_bytes(),
+        );
+        self.0.ok();
+    }
+
+    /// Completes the nexus I/O with failure.
+    pub(super) fn fail(&self) {
+        self.clone().release_split();
+        if self.batch_completion(false) {
+            return;
+        }
+        self.nexus().client_io_depth.fetch_sub(1, Ordering::Relaxed);
+        self.record_latency();
+        self.nexus().core_stats.completed(
+            Cores::current(),
+            1,
+            self.data_bytes(),
+        );
+        self.0.fail();
+    }
+
+    /// Adds the I/O to the completion batch of its channel, if batching is
+    /// enabled and the channel is busy. At a low queue depth, the I/O is
+    /// completed immediately so as not to add latency. The batch is
+    /// completed once the current poller returns.
+    fn batch_completion(&self, success: bool) -> bool {
+        if !*NEXUS_BATCHED_IO {
+            return false;
+        }
+
+        let core = Cores::current();
+        if !self.channel().has_completion_batch()
+            && self.nexus().core_stats.queue_depth(core)
+                < NEXUS_BATCH_MIN_QUEUE_DEPTH
+        {
+            return false;
+        }
+
+        let mut io = self.clone();
+        if io.channel_mut().batch_completion(self.clone(), success) {
+            let rc = unsafe {
+                spdk_thread_send_msg(
+                    spdk_get_thread(),
+                    Some(Self::complete_batch_cb),
+                    self.as_ptr().cast(),
+                )
+            };
+            if rc != 0 {
+                Self::complete_batch(io.channel_mut().take_completion_batch());
+            }
+        }
+        true
+    }
+
+    /// Completes the completion batch of the channel of the given I/O, the
+    /// first of the batch.
+    extern "C" fn complete_batch_cb(ctx: *mut c_void) {
+        let mut io = NexusBio::from(ctx as *mut spdk_bdev_io);
+        Self::complete_batch(io.channel_mut().take_completion_batch());
+    }
+
+    /// Completes a batch of nexus I/Os, updating the shared counters once
+    /// for the whole batch.
+    fn complete_batch(batch: Vec<(NexusBio<'n>, bool)>) {
+        let Some((first, _)) = batch.first() else {
+            return;
+        };
+
+        let mut bytes = 0;
+        for (io, success) in &batch {
+            io.record_latency();
+            if *success {
+                io.record_copy();
+            }
+            bytes += io.data_bytes();
+        }
+
+        let nexus = first.nexus();
+        let ops = batch.len();
+        nexus.client_io_depth.fetch_sub(ops, Ordering::Relaxed);
+        nexus
+            .core_stats
+            .completed(Cores::current(), ops as u64, bytes);
+
+        trace!("{nexus:?}: completing a batch of {ops} I/Os");
+
+        for (io, success) in batch {
+            if success {
+                io.0.ok();
+            } else {
+                io.0.fail();
+            }
+        }
+    }
+
+    /// Counts the I/O in the nexus latency histogram, if enabled.
+    #[inline(always)]
+    fn record_latency(&self) {
+        if IoHistograms::any_enabled() {
+            IoHistograms::record(&self.nexus().name, self.io_type(), unsafe {
+                spdk_bdev_io_get_submit_tsc(self.as_ptr())
+            });
+        }
+    }
+
+    /// Returns the number of bytes read or written by the I/O.
+    #[inline(always)]
+    fn data_bytes(&self) -> u64 {
+        match self.io_type() {
+            IoType::Read | IoType::Write => {
+                self.num_blocks() * self.nexus().block_len()
+            }
+            _ => 0,
+        }
+    }
+
+    /// Counts how the data of a read or a write reached the children.
+    #[inline(always)]
+    fn record_copy(&self) {
+        if !matches!(self.io_type(), IoType::Read | IoType::Write) {
+            return;
+        }
+
+        let copy = if self.ctx().copied {
+            NexusIoCopy::Copied
+        } else if self.ctx().buffer_allocated {
+            NexusIoCopy::BufferAllocated
+        } else {
+            NexusIoCopy::ZeroCopy {
+                segments: self.iovs().len() as u64,
+            }
+        };
+        self.nexus()
+            .io_copies
+            .record(copy, self.num_blocks() * self.nexus().block_len());
+    }
+
     /// Obtains the Nexus struct embedded within the bdev.
     pub(crate) fn nexus(&self) -> &Nexus<'n> {
         self.bdev_checked(NEXUS_PRODUCT_ID).data()
@@ -241,13 +507,43 @@ impl<'n> NexusBio<'n> {
         debug_assert!(self.ctx().in_flight > 0);
         self.ctx_mut().in_flight -= 1;
 
+        if !self.channel().timed_reads.is_empty() {
+            let io = self.clone();
+            self.channel_mut().untrack_read(&io);
+        }
+
+        if self.ctx().timed_out {
+            self.ctx_mut().timed_out = false;
+            if status != IoCompletionStatus::Success
+                && self.resubmit_timed_out_read(child)
+            {
+                return;
+            }
+        }
+
         if status == IoCompletionStatus::Success {
             self.ctx_mut().successful += 1;
         } else {
             self.ctx_mut().status = IoStatus::Failed;
             self.ctx_mut().failed += 1;
 
-            self.completion_error(child, status);
+            if self.defer_no_space(status) {
+                self.ctx_mut().no_space += 1;
+            } else {
+                let class = NexusIoErrorClass::from(status);
+                self.nexus().io_errors.record(class);
+
+                if self.defer_transient(class) {
+                    warn!(
+                        "{self:?}: transient {class:?} error on '{dev}', \
+                        retrying",
+                        dev = child.device_name()
+                    );
+                    self.ctx_mut().transient += 1;
+                } else {
+                    self.completion_error(child, status);
+                }
+            }
         }
 
         if self.ctx().in_flight > 0 {
@@ -257,11 +553,35 @@ impl<'n> NexusBio<'n> {
         }
 
         if self.ctx().failed == 0 {
+            if self.write_through_pending() {
+                // The write must reach the media of the children before it
+                // completes.
+                self.submit_write_through();
+                return;
+            }
             // No child failures, complete nexus I/O with success.
             trace_nexus_io!("Success: {self:?}");
             self.ok();
+        } else if self.flush_quorum_lost() {
+            error!(
+                "{self:?}: failing nexus flush: not completed by a majority \
+                of the children"
+            );
+            self.fail();
         } else if self.ctx().successful > 0 {
-            // Having some child failures, resubmit the I/O.
+            // Having some child failures, resubmit the I/O. Children which
+            // failed with ENOSPC have diverged from the others, and must be
+            // faulted if they fail again.
+            if self.ctx().no_space > 0 {
+                self.ctx_mut().fault_no_space = true;
+            }
+            self.resubmit();
+        } else if self.ctx().no_space > 0 {
+            // All remaining children are out of space: keep them, and park
+            // the I/O until space is available.
+            self.park_no_space();
+        } else if self.ctx().transient > 0 {
+            // All failures are transient: retry without faulting children.
             self.resubmit();
         } else {
             error!("{self:?}: failing nexus I/O: all child I/Os failed");
@@ -269,6 +589,55 @@ impl<'n> NexusBio<'n> {
         }
     }
 
+    /// Checks if the I/O is a flush, or a write flushing the children under
+    /// the write-through policy.
+    #[inline(always)]
+    fn is_flush(&self) -> bool {
+        self.io_type() == IoType::Flush || self.ctx().flushing
+    }
+
+    /// Checks if a write which succeeded on all children must now flush
+    /// them, under the write-through policy or because it is a FUA write.
+    fn write_through_pending(&self) -> bool {
+        self.io_type() == IoType::Write
+            && !self.ctx().flushing
+            && (self.nexus().write_through()
+                || (self.is_fua()
+                    && self.nexus().io_is_supported(IoType::Flush)))
+    }
+
+    /// Checks if a flush which failed on some children cannot complete under
+    /// the quorum flush policy: failures which are retried do not count.
+    fn flush_quorum_lost(&self) -> bool {
+        if !self.is_flush() || !self.nexus().flush_quorum() {
+            return false;
+        }
+        let ctx = self.ctx();
+        let lost = ctx
+            .failed
+            .saturating_sub(ctx.transient as u32 + ctx.no_space as u32);
+        lost * 2 >= ctx.successful + ctx.failed
+    }
+
+    /// Flushes the children after a write completed on all of them, under
+    /// the write-through policy.
+    fn submit_write_through(&mut self) {
+        let ctx = self.ctx_mut();
+
+        debug_assert_eq!(ctx.in_flight, 0);
+
+        ctx.status = IoStatus::Pending;
+        ctx.flushing = true;
+        ctx.successful = 0;
+        ctx.failed = 0;
+        ctx.no_space = 0;
+        ctx.transient = 0;
+
+        let bio = self.clone();
+        trace_nexus_io!("Write-through flush: {bio:?}");
+        bio.submit_request();
+    }
+
     /// Resubmits the I/O.
     fn resubmit(&mut self) {
         warn!("{self:?}: resubmitting nexus I/O due to a child I/O failure");
@@ -277,18 +646,112 @@ impl<'n> NexusBio<'n> {
 
         debug_assert_eq!(ctx.in_flight, 0);
         debug_assert!(ctx.failed > 0);
-        debug_assert!(ctx.successful > 0);
+        debug_assert!(ctx.successful > 0 || ctx.transient > 0);
+
+        let retry = ctx.transient > 0;
+        if retry {
+            ctx.transient_retries += 1;
+        }
 
         ctx.status = IoStatus::Pending;
         ctx.resubmits += 1;
         ctx.successful = 0;
         ctx.failed = 0;
+        ctx.no_space = 0;
+        ctx.transient = 0;
+
+        if retry {
+            self.nexus().io_errors.record_retry();
+        }
 
         let bio = self.clone();
         trace_nexus_io!("New resubmit: {bio:?}");
         bio.submit_request();
     }
 
+    /// Resubmits a read aborted after timing out on the given child to
+    /// another child, without faulting the former. Returns false if no other
+    /// child can serve it.
+    fn resubmit_timed_out_read(&mut self, child: &dyn BlockDevice) -> bool {
+        let device = child.device_name();
+        let Some(hdl) = self.channel().select_reader_except(&device) else {
+            return false;
+        };
+        let next = hdl.get_device().device_name();
+
+        warn!(
+            "{self:?}: read timed out on '{device}', resubmitting to '{next}'"
+        );
+
+        let submitted = match self.submit_read(hdl) {
+            Ok(submitted) => submitted,
+            Err(e) => {
+                error!("{self:?}: read I/O to '{next}' submission failed: {e}");
+                return false;
+            }
+        };
+
+        self.nexus().io_errors.record(NexusIoErrorClass::Timeout);
+
+        let ctx = self.ctx_mut();
+        ctx.status = IoStatus::Pending;
+        ctx.resubmits += 1;
+        ctx.in_flight = submitted;
+        self.start_io_timer(next);
+        true
+    }
+
+    /// Times the read just submitted to the given child device, if the
+    /// nexus has a child I/O timeout.
+    fn start_io_timer(&mut self, device: String) {
+        let Some(timeout) = self.nexus().child_io_timeout() else {
+            return;
+        };
+        let io = self.clone();
+        self.channel_mut().track_read(io, device, timeout);
+    }
+
+    /// Marks the read as timed out, to be resubmitted to another child upon
+    /// completion.
+    pub(super) fn set_timed_out(&mut self) {
+        self.ctx_mut().timed_out = true;
+    }
+
+    /// Determines if a child I/O failed due to ENOSPC should not fault the
+    /// child, according to the nexus ENOSPC policy.
+    fn defer_no_space(&self, status: IoCompletionStatus) -> bool {
+        status == IoCompletionStatus::LvolError(LvolFailure::NoSpace)
+            && !self.ctx().fault_no_space
+            && self.nexus().enospc_policy() == NexusEnospcPolicy::Freeze
+    }
+
+    /// Determines if a child I/O failed with an error of the given class
+    /// should be retried without faulting the child.
+    fn defer_transient(&self, class: NexusIoErrorClass) -> bool {
+        class.is_transient()
+            && self.ctx().transient_retries < NEXUS_IO_TRANSIENT_RETRIES
+    }
+
+    /// Parks the I/O which failed due to ENOSPC on all children, until the
+    /// nexus resubmits it.
+    fn park_no_space(&mut self) {
+        warn!("{self:?}: parking nexus I/O: all children are out of space");
+
+        let ctx = self.ctx_mut();
+
+        debug_assert_eq!(ctx.in_flight, 0);
+        debug_assert_eq!(ctx.successful, 0);
+
+        ctx.status = IoStatus::Pending;
+        ctx.successful = 0;
+        ctx.failed = 0;
+        ctx.no_space = 0;
+        ctx.transient = 0;
+
+        let bio = self.clone();
+        self.channel_mut().park_no_space_io(bio);
+    }
+
     /// reference to the channel. The channel contains the specific
     /// per-core data structures.
     #[inline(always)]
@@ -316,55 +779,138 @@ impl<'n> NexusBio<'n> {
         self.offset() + self.data_ent_offset()
     }
 
-    /// submit a read operation to one of the children of this nexus
+    /// submit a read operation to one of the children of this nexus,
+    /// returning the number of child I/Os submitted
     #[inline]
-    fn submit_read(
-        &self,
-        hdl: &dyn BlockDeviceHandle,
-    ) -> Result<(), CoreError> {
+    fn submit_read(&self, hdl: &ChildHandle) -> Result<u32, CoreError> {
         #[cfg(feature = "fault-injection")]
         self.inject_submission_error(hdl)?;
 
-        hdl.readv_blocks(
-            self.iovs_mut(),
+        let submitted = if let Some(split) = self.ctx().split.as_deref() {
+            split.submit(|iovs, offset, num_blocks| {
+                hdl.readv_blocks(
+                    iovs,
+                    offset,
+                    num_blocks,
+                    ReadOptions::None,
+                    Self::child_completion,
+                    self.as_ptr().cast(),
+                )
+            })?
+        } else {
+            hdl.readv_blocks(
+                self.iovs_mut(),
+                self.effective_offset(),
+                self.num_blocks(),
+                ReadOptions::None,
+                Self::child_completion,
+                self.as_ptr().cast(),
+            )
+            .map(|_| 1)?
+        };
+
+        hdl.record_bandwidth(IoBandwidthClass::Read, self.data_bytes());
+        Ok(submitted)
+    }
+
+    /// Splits a read or a write larger than the maximum I/O size of the
+    /// nexus, unless already split.
+    fn prepare_split(&mut self) {
+        if self.ctx().split.is_some() {
+            return;
+        }
+
+        let max_blocks = self.nexus().io_split_blocks();
+        if max_blocks == 0 || self.num_blocks() <= max_blocks {
+            return;
+        }
+
+        let split = NexusIoSplit::new(
+            self.iovs(),
             self.effective_offset(),
             self.num_blocks(),
-            ReadOptions::None,
-            Self::child_completion,
-            self.as_ptr().cast(),
-        )
+            self.nexus().block_len(),
+            max_blocks,
+        );
+        self.nexus().io_splits.record(split.len() as u64);
+        self.ctx_mut().split = Some(Box::new(split));
+    }
+
+    /// Drops the split layout of the I/O, once all its child I/Os have
+    /// completed.
+    fn release_split(&mut self) {
+        self.ctx_mut().split = None;
     }
 
-    /// Submit a Read operation to the next available replica.
+    /// Accounts the child I/Os of a split I/O which failed to submit after
+    /// others had been submitted.
+    fn account_failed_submissions(&mut self) {
+        let failed = self
+            .ctx()
+            .split
+            .as_ref()
+            .map_or(0, |s| s.take_failed_submissions());
+        if failed > 0 {
+            self.ctx_mut().status = IoStatus::Failed;
+            self.ctx_mut().failed += failed;
+        }
+    }
+
+    /// Submit a Read operation to the local child being rebuilt with
+    /// copy-on-read if it has the data already. Otherwise, the read goes to
+    /// the next available replica, and the local child is populated with the
+    /// segments it covers.
     fn __do_readv_one(&mut self) -> Result<(), CoreError> {
-        if let Some(hdl) = self.channel().select_reader() {
-            let r = self.submit_read(hdl);
-
-            if r.is_err() {
-                // Such a situation can happen when there is no active I/O in
-                // the queues, but error on qpair is observed
-                // due to network timeout, which initiates
-                // controller reset. During controller reset all
-                // I/O channels are de-initialized, so no I/O
-                // submission is possible (spdk returns -6/ENXIO), so we have to
-                // start device retire.
-                // TODO: ENOMEM and ENXIO should be handled differently and
-                // device should not be retired in case of ENOMEM.
-                let device = hdl.get_device().device_name();
-                error!(
-                    "{self:?}: read I/O to '{device}' submission failed: {r:?}"
-                );
+        let chan = self.channel();
+        let (offset, num_blocks) = (self.effective_offset(), self.num_blocks());
+        let cor_hdl = match chan.cor_reader() {
+            Some((hdl, cor)) if cor.read_hit(offset, num_blocks) => Some(hdl),
+            Some((_, cor)) => {
+                cor.populate(offset, num_blocks);
+                None
+            }
+            None => None,
+        };
 
-                self.fault_device(
-                    &device,
-                    IoCompletionStatus::IoSubmissionError(
-                        IoSubmissionFailure::Read,
-                    ),
+        if let Some(hdl) = cor_hdl.or_else(|| chan.select_reader()) {
+            match self.submit_read(hdl) {
+                Err(e) => {
+                    // Such a situation can happen when there is no active I/O
+                    // in the queues, but error on qpair is
+                    // observed due to network timeout,
+                    // which initiates controller reset.
+                    // During controller reset all
+                    // I/O channels are de-initialized, so no I/O
+                    // submission is possible (spdk returns -6/ENXIO), so we
+                    // have to start device retire.
+                    // TODO: ENOMEM and ENXIO should be handled differently and
+                    // device should not be retired in case of ENOMEM.
+                    let device = hdl.get_device().device_name();
+                    error!(
+                    "{self:?}: read I/O to '{device}' submission failed: {e:?}"
                 );
-                r
-            } else {
-                self.ctx_mut().in_flight = 1;
-                r
+
+                    self.fault_device(
+                        &device,
+                        IoCompletionStatus::IoSubmissionError(
+                            IoSubmissionFailure::Read,
+                        ),
+                    );
+                    Err(e)
+                }
+                Ok(submitted) => {
+                    let device = self
+                        .nexus()
+                        .child_io_timeout()
+                        .filter(|_| self.ctx().split.is_none())
+                        .map(|_| hdl.get_device().device_name());
+                    self.ctx_mut().in_flight = submitted;
+                    self.account_failed_submissions();
+                    if let Some(device) = device {
+                        self.start_io_timer(device);
+                    }
+                    Ok(())
+                }
             }
         } else {
             error!(
@@ -379,6 +925,12 @@ impl<'n> NexusBio<'n> {
     /// In case of submission error the requiest is transparently resubmitted
     /// to the next available replica.
     fn do_readv(&mut self) -> Result<(), CoreError> {
+        if self.read_from_cache() {
+            return Ok(());
+        }
+
+        self.prepare_split();
+
         match self.__do_readv_one() {
             Err(e) => {
                 match e {
@@ -428,6 +980,70 @@ impl<'n> NexusBio<'n> {
         }
     }
 
+    /// Submits a read to the read cache device, if the blocks are cached.
+    fn read_from_cache(&mut self) -> bool {
+        let Some(cache) = self.channel().read_cache().cloned() else {
+            return false;
+        };
+        if self.channel().read_cache_handle().is_none() {
+            return false;
+        }
+
+        let CacheLookup::Hit {
+            slot,
+            blk,
+        } = cache.lookup(self.effective_offset(), self.num_blocks())
+        else {
+            return false;
+        };
+
+        self.ctx_mut().read_cache_slot = slot;
+
+        let hdl = self.channel().read_cache_handle().unwrap();
+        match hdl.readv_blocks(
+            self.iovs_mut(),
+            blk,
+            self.num_blocks(),
+            ReadOptions::None,
+            Self::read_cache_completion,
+            self.as_ptr().cast(),
+        ) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("{self:?}: read cache submission failed: {e}");
+                cache.unpin(slot);
+                cache.invalidate(self.effective_offset(), self.num_blocks());
+                false
+            }
+        }
+    }
+
+    /// Invoked when a read from the read cache device completes. A failed
+    /// read invalidates the cached line, and is resubmitted to the children.
+    fn read_cache_completion(
+        _device: &dyn BlockDevice,
+        status: IoCompletionStatus,
+        ctx: *mut c_void,
+    ) {
+        let mut bio = NexusBio::from(ctx as *mut spdk_bdev_io);
+        let ok = status == IoCompletionStatus::Success;
+
+        // The cache may have been detached in the meantime.
+        if let Some(cache) = bio.channel().read_cache() {
+            cache.unpin(bio.ctx().read_cache_slot);
+            if !ok {
+                cache.invalidate(bio.effective_offset(), bio.num_blocks());
+            }
+        }
+
+        if ok {
+            bio.ok();
+        } else {
+            warn!("{bio:?}: read cache I/O failed: {status:?}");
+            let _ = bio.do_readv();
+        }
+    }
+
     extern "C" fn nexus_get_buf_cb(
         _ch: *mut spdk_io_channel,
         io: *mut spdk_bdev_io,
@@ -450,6 +1066,7 @@ impl<'n> NexusBio<'n> {
     /// submit read IO to some child
     fn readv(&mut self) -> Result<(), CoreError> {
         if self.need_buf() {
+            self.ctx_mut().buffer_allocated = true;
             unsafe {
                 self.alloc_buffer(Self::nexus_get_buf_cb);
             }
@@ -460,10 +1077,7 @@ impl<'n> NexusBio<'n> {
     }
 
     #[inline]
-    fn submit_write(
-        &self,
-        hdl: &dyn BlockDeviceHandle,
-    ) -> Result<(), CoreError> {
+    fn submit_write(&self, hdl: &ChildHandle) -> Result<u32, CoreError> {
         trace_nexus_io!(
             "Submitting: {self:?} -> {name}",
             name = hdl.get_device().device_name()
@@ -472,13 +1086,29 @@ impl<'n> NexusBio<'n> {
         #[cfg(feature = "fault-injection")]
         self.inject_submission_error(hdl)?;
 
-        hdl.writev_blocks(
-            self.iovs(),
-            self.effective_offset(),
-            self.num_blocks(),
-            Self::child_completion,
-            self.as_ptr().cast(),
-        )
+        let submitted = if let Some(split) = self.ctx().split.as_deref() {
+            split.submit(|iovs, offset, num_blocks| {
+                hdl.writev_blocks(
+                    iovs,
+                    offset,
+                    num_blocks,
+                    Self::child_completion,
+                    self.as_ptr().cast(),
+                )
+            })?
+        } else {
+            hdl.writev_blocks(
+                self.iovs(),
+                self.effective_offset(),
+                self.num_blocks(),
+                Self::child_completion,
+                self.as_ptr().cast(),
+            )
+            .map(|_| 1)?
+        };
+
+        hdl.record_bandwidth(IoBandwidthClass::Write, self.data_bytes());
+        Ok(submitted)
     }
 
     #[inline]
@@ -552,22 +1182,29 @@ impl<'n> NexusBio<'n> {
     /// avoid double frees. This function handles IO for a subset that must
     /// be submitted to all the underlying children.
     fn submit_all(&mut self) -> Result<(), CoreError> {
+        if self.io_type() == IoType::Write && !self.ctx().flushing {
+            self.prepare_split();
+        }
+
         let mut inflight = 0;
         // Name of the device which experiences I/O submission failures.
         let mut failed_device = None;
 
         let result = self.channel().for_each_writer(|h| {
             match self.io_type() {
+                IoType::Write if self.ctx().flushing => {
+                    self.submit_flush(h).map(|_| 1)
+                }
                 IoType::Write => self.submit_write(h),
-                IoType::Unmap => self.submit_unmap(h),
-                IoType::WriteZeros => self.submit_write_zeroes(h),
-                IoType::Reset => self.submit_reset(h),
-                IoType::Flush => self.submit_flush(h),
+                IoType::Unmap => self.submit_unmap(h).map(|_| 1),
+                IoType::WriteZeros => self.submit_write_zeroes(h).map(|_| 1),
+                IoType::Reset => self.submit_reset(h).map(|_| 1),
+                IoType::Flush => self.submit_flush(h).map(|_| 1),
                 // we should never reach here, if we do it is a bug.
                 _ => unreachable!(),
             }
-            .map(|_| {
-                inflight += 1;
+            .map(|submitted| {
+                inflight += submitted;
             })
             .map_err(|err| {
                 error!(
@@ -620,6 +1257,7 @@ impl<'n> NexusBio<'n> {
             // prior to the error condition.
             self.ctx_mut().in_flight = inflight;
             self.ctx_mut().status = IoStatus::Success;
+            self.account_failed_submissions();
         } else {
             debug_assert_eq!(self.ctx().in_flight, 0);
             error!(
@@ -631,6 +1269,187 @@ impl<'n> NexusBio<'n> {
         result
     }
 
+    /// Parks the I/O if it must wait for writes in the nexus write cache to
+    /// reach the children: reads and writes overlapping cached writes, and
+    /// flushes until all writes cached before them. FUA writes, which bypass
+    /// the cache, wait for the cached writes they overlap so that they reach
+    /// the children in order.
+    fn wait_write_cache(&mut self) -> bool {
+        let Some(cache) = self.nexus().write_cache().cloned() else {
+            return false;
+        };
+
+        let wait = match self.io_type() {
+            IoType::Read | IoType::Unmap | IoType::WriteZeros => {
+                cache.must_wait(self.effective_offset(), self.num_blocks())
+            }
+            IoType::Write if self.is_fua() && !self.ctx().flushing => {
+                cache.must_wait(self.effective_offset(), self.num_blocks())
+            }
+            IoType::Flush => {
+                if self.ctx().flush_seq == 0 {
+                    self.ctx_mut().flush_seq = cache.flush_seq();
+                }
+                cache.must_wait_flush(self.ctx().flush_seq)
+            }
+            _ => false,
+        };
+
+        if wait {
+            self.park_write_cache();
+        }
+        wait
+    }
+
+    /// Parks a write-like I/O until the segments it covers are persisted in
+    /// the I/O log journal, so that the children being logged can be rebuilt
+    /// partially even after a crash.
+    fn wait_io_log_journal(&mut self) -> bool {
+        if !matches!(
+            self.io_type(),
+            IoType::Write | IoType::WriteZeros | IoType::Unmap
+        ) {
+            return false;
+        }
+
+        let (offset, num_blocks) = (self.effective_offset(), self.num_blocks());
+        let mut wait = false;
+        self.channel().for_each_io_log(|log| {
+            wait |= log.journal_must_wait(offset, num_blocks);
+        });
+
+        if wait {
+            let bio = self.clone();
+            self.channel_mut().park_journal_io(bio);
+        }
+        wait
+    }
+
+    /// Parks the I/O until cached writes reach the children.
+    fn park_write_cache(&mut self) {
+        let bio = self.clone();
+        self.channel_mut().park_cache_io(bio);
+    }
+
+    /// Submits a write to the nexus write cache: the data is copied, and the
+    /// copy is written to all children in the background, while the write
+    /// completes right away. Writes go to the children directly when the
+    /// cache is full.
+    fn submit_write_cached(
+        &mut self,
+        cache: Arc<NexusWriteCache>,
+    ) -> Result<(), CoreError> {
+        let offset = self.effective_offset();
+        let num_blocks = self.num_blocks();
+
+        let (core, seq) = match cache.insert(offset, num_blocks) {
+            CacheInsert::Cached(core, seq) => (core, seq),
+            CacheInsert::Overlap => {
+                self.park_write_cache();
+                return Ok(());
+            }
+            CacheInsert::Full => return self.submit_all(),
+        };
+
+        let len = num_blocks * self.nexus().block_len();
+        let Ok(mut buf) = DmaBuf::new(len, self.nexus().alignment()) else {
+            warn!("{self:?}: failed to allocate write cache buffer");
+            cache.release(core, seq, false);
+            return self.submit_all();
+        };
+
+        let dst = buf.as_mut_slice();
+        let mut pos = 0;
+        for iov in self.iovs() {
+            let n = (iov.len() as usize).min(dst.len() - pos);
+            dst[pos .. pos + n].copy_from_slice(&iov.as_slice()[.. n]);
+            pos += n;
+        }
+
+        self.ctx_mut().copied = true;
+
+        let destage = Box::into_raw(Destage::new(
+            cache, core, seq, offset, num_blocks, buf,
+        ));
+
+        let mut submitted = 0;
+        let mut failed_devices = Vec::new();
+
+        self.channel().for_each_writer(|h| {
+            match h.writev_blocks(
+                unsafe { (*destage).iovs() },
+                offset,
+                num_blocks,
+                Destage::child_completion,
+                destage.cast(),
+            ) {
+                Ok(()) => {
+                    unsafe { (*destage).submitted() };
+                    h.record_bandwidth(
+                        IoBandwidthClass::Write,
+                        num_blocks * self.nexus().block_len(),
+                    );
+                    submitted += 1;
+                }
+                Err(err) => {
+                    error!(
+                        "{self:?}: cached write submission failed with \
+                        error {err:?}"
+                    );
+                    failed_devices.push(h.get_device().device_name());
+                }
+            }
+            Ok(())
+        })?;
+
+        // Submission errors trigger device retire, as with nexus I/Os.
+        for device in failed_devices {
+            self.channel_mut().disconnect_device(&device);
+
+            if let Some(log) = self.fault_device(
+                &device,
+                IoCompletionStatus::IoSubmissionError(
+                    IoSubmissionFailure::Write,
+                ),
+            ) {
+                self.log_io(&log);
+            }
+        }
+
+        if submitted == 0 {
+            Destage::cancel(destage);
+            self.ctx_mut().copied = false;
+            return self.submit_all();
+        }
+
+        self.channel().for_each_io_log(|log| self.log_io(log));
+        Destage::put(destage);
+        self.ok();
+
+        Ok(())
+    }
+
+    /// Submits a flush to all children. With the write cache enabled, the
+    /// flush fails if a cached write failed on all children since the
+    /// previous flush, and completes right away if the children do not
+    /// support flushes.
+    fn flush(&mut self) -> Result<(), CoreError> {
+        if let Some(cache) = self.nexus().write_cache() {
+            if cache.take_error() {
+                error!("{self:?}: failing flush: cached writes were lost");
+                self.fail();
+                return Ok(());
+            }
+
+            if !self.nexus().io_is_supported(IoType::Flush) {
+                self.ok();
+                return Ok(());
+            }
+        }
+
+        self.submit_all()
+    }
+
     /// Logs all write-like operation in the rebuild logs, if any exist.
     #[inline]
     fn log_io(&self, log: &IOLogChannel) {
@@ -638,7 +1457,7 @@ impl<'n> NexusBio<'n> {
     }
 
     /// Initiate shutdown of the nexus associated with this BIO request.
-    fn try_self_shutdown_nexus(&mut self) {
+    fn try_self_shutdown_nexus(&mut self, reason: NexusShutdownReason) {
         if self
             .channel_mut()
             .nexus_mut()
@@ -646,7 +1465,7 @@ impl<'n> NexusBio<'n> {
             .compare_exchange(false, true)
             .is_ok()
         {
-            self.channel().nexus().try_self_shutdown();
+            self.channel().nexus().try_self_shutdown(reason);
         }
     }
 
@@ -710,7 +1529,9 @@ impl<'n> NexusBio<'n> {
                 "{self:?}: reservation conflict on '{dev}', shutdown nexus",
                 dev = child.device_name()
             );
-            self.try_self_shutdown_nexus();
+            self.try_self_shutdown_nexus(
+                NexusShutdownReason::ReservationConflict,
+            );
             return;
         }
 
@@ -737,6 +1558,19 @@ impl<'n> NexusBio<'n> {
         }
     }
 
+    /// Returns the type of the child I/Os, as matched by injected faults:
+    /// writes flushing the children under the write-through policy are
+    /// flushes.
+    #[cfg(feature = "fault-injection")]
+    #[inline]
+    fn injected_io_type(&self) -> IoType {
+        if self.is_flush() {
+            IoType::Flush
+        } else {
+            self.io_type()
+        }
+    }
+
     /// Checks if an error is to be injected upon submission.
     #[cfg(feature = "fault-injection")]
     #[inline]
@@ -754,7 +1588,7 @@ impl<'n> NexusBio<'n> {
             Nexus,
             &InjectIoCtx::with_iovs(
                 hdl.get_device(),
-                self.io_type(),
+                self.injected_io_type(),
                 self.offset(),
                 self.num_blocks(),
                 self.iovs(),
@@ -780,7 +1614,7 @@ impl<'n> NexusBio<'n> {
             Nexus,
             &InjectIoCtx::with_iovs(
                 child,
-                self.io_type(),
+                self.injected_io_type(),
                 self.offset(),
                 self.num_blocks(),
                 self.iovs(),
@@ -0,0 +1,110 @@
+//! Statistics of the data copies of the nexus data path.
+//!
+//! These counters only report how the reads and writes of the nexus handle
+//! their buffers; they do not change the data path. A read or a write is
+//! counted as zero-copy when its scatter-gather list is submitted to the
+//! children as is. The data is copied when a write goes to the write cache,
+//! and a buffer is allocated for a read submitted without one.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use super::Nexus;
+
+/// How the data of a nexus I/O reached the children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum NexusIoCopy {
+    /// The initiator buffers were passed to the children as is.
+    ZeroCopy {
+        /// Number of scatter-gather elements.
+        segments: u64,
+    },
+    /// A buffer was allocated for a read submitted without one.
+    BufferAllocated,
+    /// The data was copied into the write cache.
+    Copied,
+}
+
+/// Counters of the data copies of the reads and writes of a nexus.
+#[derive(Debug, Default)]
+pub struct NexusIoCopyCounters {
+    zero_copy_ios: AtomicU64,
+    zero_copy_bytes: AtomicU64,
+    sgl_segments: AtomicU64,
+    max_sgl_segments: AtomicU64,
+    buffer_allocations: AtomicU64,
+    copied_ios: AtomicU64,
+    copied_bytes: AtomicU64,
+}
+
+/// Point-in-time copy of the data copy counters of a nexus.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct NexusIoCopyStats {
+    /// Reads and writes whose initiator buffers were passed to the children
+    /// as is.
+    pub zero_copy_ios: u64,
+    /// Bytes of the zero-copy reads and writes.
+    pub zero_copy_bytes: u64,
+    /// Scatter-gather elements of the zero-copy reads and writes.
+    pub sgl_segments: u64,
+    /// Largest number of scatter-gather elements of a nexus I/O.
+    pub max_sgl_segments: u64,
+    /// Reads submitted without a buffer, for which one was allocated.
+    pub buffer_allocations: u64,
+    /// Writes copied into the write cache.
+    pub copied_ios: u64,
+    /// Bytes copied into the write cache.
+    pub copied_bytes: u64,
+}
+
+impl NexusIoCopyStats {
+    /// Returns the ratio of the reads and writes which avoided copies.
+    pub fn zero_copy_rate(&self) -> f64 {
+        match self.zero_copy_ios + self.buffer_allocations + self.copied_ios {
+            0 => 0.0,
+            total => self.zero_copy_ios as f64 / total as f64,
+        }
+    }
+}
+
+impl NexusIoCopyCounters {
+    /// Counts a completed read or write of the given size.
+    pub(super) fn record(&self, copy: NexusIoCopy, bytes: u64) {
+        match copy {
+            NexusIoCopy::ZeroCopy {
+                segments,
+            } => {
+                self.zero_copy_ios.fetch_add(1, Ordering::Relaxed);
+                self.zero_copy_bytes.fetch_add(bytes, Ordering::Relaxed);
+                self.sgl_segments.fetch_add(segments, Ordering::Relaxed);
+                self.max_sgl_segments.fetch_max(segments, Ordering::Relaxed);
+            }
+            NexusIoCopy::BufferAllocated => {
+                self.buffer_allocations.fetch_add(1, Ordering::Relaxed);
+            }
+            NexusIoCopy::Copied => {
+                self.copied_ios.fetch_add(1, Ordering::Relaxed);
+                self.copied_bytes.fetch_add(bytes, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn snapshot(&self) -> NexusIoCopyStats {
+        NexusIoCopyStats {
+            zero_copy_ios: self.zero_copy_ios.load(Ordering::Relaxed),
+            zero_copy_bytes: self.zero_copy_bytes.load(Ordering::Relaxed),
+            sgl_segments: self.sgl_segments.load(Ordering::Relaxed),
+            max_sgl_segments: self.max_sgl_segments.load(Ordering::Relaxed),
+            buffer_allocations: self.buffer_allocations.load(Ordering::Relaxed),
+            copied_ios: self.copied_ios.load(Ordering::Relaxed),
+            copied_bytes: self.copied_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the data copy counters of the nexus.
+    pub fn io_copy_stats(&self) -> NexusIoCopyStats {
+        self.io_copies.snapshot()
+    }
+}
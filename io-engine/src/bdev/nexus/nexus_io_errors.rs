@@ -0,0 +1,116 @@
+//! Classification of the child I/O errors of a nexus.
+//!
+//! Media errors and unclassified errors are persistent: the child which
+//! reported them is faulted right away. Transport errors and timeouts are
+//! transient: the nexus I/O is resubmitted up to
+//! `NEXUS_IO_TRANSIENT_RETRIES` times without faulting the child, and the
+//! child is only faulted if the error persists.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use super::Nexus;
+use crate::core::{GenericStatusCode, IoCompletionStatus, NvmeStatus};
+
+/// Number of times a nexus I/O is resubmitted due to transient child errors
+/// before the child is faulted.
+pub const NEXUS_IO_TRANSIENT_RETRIES: u8 = 3;
+
+/// Class of a child I/O error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NexusIoErrorClass {
+    /// Unrecoverable media or data integrity error.
+    Media,
+    /// Error of the path to the child, e.g. a lost connection.
+    Transport,
+    /// I/O aborted after a timeout.
+    Timeout,
+    /// Any other error.
+    Other,
+}
+
+impl From<IoCompletionStatus> for NexusIoErrorClass {
+    fn from(status: IoCompletionStatus) -> Self {
+        match status {
+            IoCompletionStatus::NvmeError(NvmeStatus::MediaError(_)) => {
+                Self::Media
+            }
+            IoCompletionStatus::NvmeError(NvmeStatus::Generic(
+                GenericStatusCode::AbortedRequested,
+            )) => Self::Timeout,
+            IoCompletionStatus::NvmeError(NvmeStatus::Generic(
+                GenericStatusCode::AbortedSubmissionQueueDeleted
+                | GenericStatusCode::DataTransferError
+                | GenericStatusCode::NamespaceNotReady,
+            )) => Self::Transport,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl NexusIoErrorClass {
+    /// Checks if errors of this class may go away when the I/O is retried.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Transport | Self::Timeout)
+    }
+}
+
+/// Counters of the child I/O errors of a nexus, per error class.
+#[derive(Debug, Default)]
+pub struct NexusIoErrorCounters {
+    media: AtomicU64,
+    transport: AtomicU64,
+    timeout: AtomicU64,
+    other: AtomicU64,
+    retried: AtomicU64,
+}
+
+/// Point-in-time copy of the child I/O error counters of a nexus.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct NexusIoErrorStats {
+    /// Media errors.
+    pub media: u64,
+    /// Transport errors.
+    pub transport: u64,
+    /// Timeouts.
+    pub timeout: u64,
+    /// Unclassified errors.
+    pub other: u64,
+    /// Nexus I/Os resubmitted due to transient errors.
+    pub retried: u64,
+}
+
+impl NexusIoErrorCounters {
+    /// Counts a child I/O error of the given class.
+    pub(super) fn record(&self, class: NexusIoErrorClass) {
+        let counter = match class {
+            NexusIoErrorClass::Media => &self.media,
+            NexusIoErrorClass::Transport => &self.transport,
+            NexusIoErrorClass::Timeout => &self.timeout,
+            NexusIoErrorClass::Other => &self.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a nexus I/O resubmitted due to transient errors.
+    pub(super) fn record_retry(&self) {
+        self.retried.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> NexusIoErrorStats {
+        NexusIoErrorStats {
+            media: self.media.load(Ordering::Relaxed),
+            transport: self.transport.load(Ordering::Relaxed),
+            timeout: self.timeout.load(Ordering::Relaxed),
+            other: self.other.load(Ordering::Relaxed),
+            retried: self.retried.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the child I/O error counters of the nexus.
+    pub fn io_error_stats(&self) -> NexusIoErrorStats {
+        self.io_errors.snapshot()
+    }
+}
@@ -6,6 +6,7 @@ use std::{
     rc::Rc,
 };
 
+use super::IOLogJournalSlot;
 use crate::{
     core::SegmentMap,
     rebuild::{RebuildMap, SEGMENT_SIZE},
@@ -23,6 +24,8 @@ pub(crate) struct IOLogChannelInner {
     device_name: String,
     /// Map of device segments.
     segments: UnsafeCell<Option<SegmentMap>>,
+    /// Slot of the log in the I/O log journal, if any.
+    journal: Option<IOLogJournalSlot>,
 }
 
 impl Debug for IOLogChannelInner {
@@ -44,6 +47,7 @@ impl IOLogChannelInner {
         device_name: &str,
         num_blocks: u64,
         block_len: u64,
+        journal: Option<IOLogJournalSlot>,
     ) -> Self {
         Self {
             core,
@@ -53,6 +57,7 @@ impl IOLogChannelInner {
                 SEGMENT_SIZE,
             ))),
             device_name: device_name.to_owned(),
+            journal,
         }
     }
 
@@ -73,9 +78,21 @@ impl IOLogChannelInner {
                 .as_mut()
                 .expect("Accessing stopped I/O log channel")
                 .set(lbn, lbn_cnt, true);
+
+            if let Some(journal) = &self.journal {
+                journal.record(lbn, lbn_cnt);
+            }
         }
     }
 
+    /// Records the given blocks in the I/O log journal, if any. Returns true
+    /// if a write to these blocks must wait for the journal to be persisted.
+    pub(crate) fn journal_must_wait(&self, lbn: u64, lbn_cnt: u64) -> bool {
+        self.journal
+            .as_ref()
+            .map_or(false, |journal| journal.record(lbn, lbn_cnt))
+    }
+
     /// Returns a reference to segments.
     #[inline]
     fn segments(&self) -> &SegmentMap {
@@ -124,12 +141,14 @@ impl IOLogChannel {
         device_name: &str,
         num_blocks: u64,
         block_len: u64,
+        journal: Option<IOLogJournalSlot>,
     ) -> Self {
         Self(Rc::new(IOLogChannelInner::new(
             core,
             device_name,
             num_blocks,
             block_len,
+            journal,
         )))
     }
 }
@@ -154,11 +173,13 @@ impl Debug for IOLog {
 }
 
 impl IOLog {
-    /// Creates a new I/O log instance for the given device.
+    /// Creates a new I/O log instance for the given device, recorded in the
+    /// given I/O log journal slot, if any.
     pub(crate) fn new(
         device_name: &str,
         num_blocks: u64,
         block_len: u64,
+        journal: Option<IOLogJournalSlot>,
     ) -> Self {
         assert!(!device_name.is_empty() && num_blocks > 0 && block_len > 0);
 
@@ -167,7 +188,13 @@ impl IOLog {
         for i in Cores::list_cores() {
             channels.insert(
                 i,
-                IOLogChannel::new(i, device_name, num_blocks, block_len),
+                IOLogChannel::new(
+                    i,
+                    device_name,
+                    num_blocks,
+                    block_len,
+                    journal.clone(),
+                ),
             );
         }
 
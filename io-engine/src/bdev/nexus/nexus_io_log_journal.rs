@@ -0,0 +1,841 @@
+//! Implements the write-intent journal of the nexus I/O logs on a local
+//! device.
+//!
+//! The I/O log of a faulted child lives in memory: it does not survive a
+//! crash of the io-engine, after which the child can only be fully rebuilt.
+//! A nexus can have a small local device attached as a journal of its I/O
+//! logs. The journal device is divided into a header block followed by a
+//! fixed number of slots, each holding the bitmap of the segments written
+//! since a child has been faulted.
+//!
+//! A slot is assigned to a child when its I/O log starts. A write to a
+//! segment not yet persisted in the slots of the active I/O logs is parked
+//! until the segment is persisted, so that no write reaches the healthy
+//! children before the journal records it. A slot is released once the
+//! child has been rebuilt, or is removed from the nexus.
+//!
+//! When the journal is attached to a nexus with the same UUID again, the
+//! slots still in use are recovered, and the next rebuild of their child
+//! only transfers the segments they record. If the journal device fails,
+//! the journal is disabled and the I/O logs fall back to memory only.
+use std::{
+    collections::BTreeSet,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
+use serde::Serialize;
+use snafu::ResultExt;
+use spdk_rs::DmaBuf;
+
+use super::{nexus_err, nexus_lookup_mut, Error, Nexus};
+use crate::{
+    bdev::{device_create, device_destroy, device_open},
+    core::{
+        BlockDeviceDescriptor,
+        BlockDeviceHandle,
+        CoreError,
+        Reactors,
+        SegmentMap,
+    },
+    rebuild::{RebuildMap, SEGMENT_SIZE},
+};
+
+/// Magic number of the journal header.
+const JOURNAL_MAGIC: &[u8; 8] = b"MYIOLOGJ";
+
+/// Version of the journal layout.
+const JOURNAL_VERSION: u32 = 1;
+
+/// Maximum number of slots of a journal.
+const JOURNAL_MAX_SLOTS: u64 = 16;
+
+/// Health statistics of a nexus I/O log journal.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct NexusIoLogJournalStats {
+    /// URI of the journal device.
+    pub device: String,
+    /// False once a write to the journal device has failed.
+    pub healthy: bool,
+    /// Number of slots the journal device holds.
+    pub capacity_slots: u32,
+    /// Number of slots in use.
+    pub used_slots: u32,
+    /// Number of slots recovered when the journal was attached, and not
+    /// rebuilt from yet.
+    pub recovered_slots: u32,
+    /// Writes to the journal device.
+    pub flushes: u64,
+    /// Failed writes to the journal device.
+    pub flush_errors: u64,
+}
+
+/// Geometry of a journal device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct JournalGeometry {
+    /// Block size, in bytes.
+    block_len: u64,
+    /// Number of segments a slot records.
+    num_segments: u64,
+    /// Size of a slot, in blocks, including its header block.
+    slot_blks: u64,
+    /// Number of slots.
+    num_slots: u64,
+}
+
+impl JournalGeometry {
+    /// Size of the bitmap of a slot, in bytes.
+    fn bitmap_len(&self) -> u64 {
+        (self.num_segments + 7) / 8
+    }
+
+    /// Offset of the header of the given slot, in bytes.
+    fn slot_offset(&self, slot: usize) -> u64 {
+        (1 + slot as u64 * self.slot_blks) * self.block_len
+    }
+
+    /// Offset of the given bitmap block of the given slot, in bytes.
+    fn bitmap_offset(&self, slot: usize, blk: u64) -> u64 {
+        self.slot_offset(slot) + (1 + blk) * self.block_len
+    }
+
+    /// Encodes the journal header of the given nexus.
+    fn encode_header(&self, nexus_uuid: &[u8; 16], buf: &mut [u8]) {
+        buf.fill(0);
+        buf[0 .. 8].copy_from_slice(JOURNAL_MAGIC);
+        buf[8 .. 12].copy_from_slice(&JOURNAL_VERSION.to_le_bytes());
+        buf[12 .. 28].copy_from_slice(nexus_uuid);
+        buf[28 .. 36].copy_from_slice(&SEGMENT_SIZE.to_le_bytes());
+        buf[36 .. 44].copy_from_slice(&self.num_segments.to_le_bytes());
+        buf[44 .. 52].copy_from_slice(&self.slot_blks.to_le_bytes());
+        buf[52 .. 60].copy_from_slice(&self.num_slots.to_le_bytes());
+    }
+}
+
+/// State of a journal slot.
+#[derive(Debug)]
+struct JournalSlot {
+    /// Key of the child the slot is assigned to.
+    key: String,
+    /// False once the slot is released, until its header is written.
+    in_use: bool,
+    /// Set when the slot was recovered, until a rebuild uses it.
+    recovered: bool,
+    /// Set when the slot header must be written.
+    header_dirty: bool,
+    /// Bitmap of the segments recorded.
+    image: Vec<u8>,
+    /// Bitmap of the segments persisted on the journal device.
+    persisted: Vec<u8>,
+    /// Bitmap blocks to be written.
+    dirty_blks: BTreeSet<u64>,
+    /// Changed each time the slot is assigned or released, so that the
+    /// completion of a write issued before is ignored.
+    epoch: u64,
+}
+
+impl JournalSlot {
+    fn new(key: &str, bitmap_len: u64, epoch: u64) -> Self {
+        Self {
+            key: key.to_string(),
+            in_use: true,
+            recovered: false,
+            header_dirty: true,
+            image: vec![0; bitmap_len as usize],
+            persisted: vec![0; bitmap_len as usize],
+            dirty_blks: BTreeSet::new(),
+            epoch,
+        }
+    }
+
+    /// Encodes the slot header.
+    fn encode_header(&self, buf: &mut [u8]) {
+        buf.fill(0);
+        if !self.in_use {
+            return;
+        }
+        let key = self.key.as_bytes();
+        let n = key.len().min(buf.len() - 3);
+        buf[0] = 1;
+        buf[1 .. 3].copy_from_slice(&(n as u16).to_le_bytes());
+        buf[3 .. 3 + n].copy_from_slice(&key[.. n]);
+    }
+
+    /// Decodes a slot header, returning the key of the slot if it is in use.
+    fn decode_header(buf: &[u8]) -> Option<String> {
+        if buf[0] != 1 {
+            return None;
+        }
+        let n = u16::from_le_bytes([buf[1], buf[2]]) as usize;
+        buf.get(3 .. 3 + n)
+            .and_then(|k| std::str::from_utf8(k).ok())
+            .map(str::to_string)
+    }
+}
+
+/// A write to the journal device.
+struct JournalWrite {
+    slot: usize,
+    epoch: u64,
+    /// Bitmap block written, or None for the slot header.
+    blk: Option<u64>,
+    offset: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct JournalInner {
+    slots: Vec<Option<JournalSlot>>,
+    healthy: bool,
+    /// Set once the journal is being detached.
+    closed: bool,
+    epoch: u64,
+}
+
+/// Write-intent journal of the I/O logs of a nexus, shared with the I/O
+/// logs of its children.
+#[derive(Debug)]
+pub(crate) struct IOLogJournal {
+    /// URI of the journal device.
+    device: String,
+    geometry: JournalGeometry,
+    inner: parking_lot::Mutex<JournalInner>,
+    /// Queue of the flush requests.
+    flush_queue: mpsc::UnboundedSender<()>,
+    flushes: AtomicU64,
+    flush_errors: AtomicU64,
+}
+
+/// Reference to the journal slot of an I/O log.
+#[derive(Debug, Clone)]
+pub(crate) struct IOLogJournalSlot {
+    journal: Arc<IOLogJournal>,
+    slot: usize,
+    key: String,
+}
+
+impl IOLogJournalSlot {
+    /// Records the segments covering the given blocks. Returns true if the
+    /// segments are not persisted yet: the write must wait for the journal
+    /// to be flushed.
+    pub(crate) fn record(&self, lbn: u64, lbn_cnt: u64) -> bool {
+        self.journal.record(self.slot, &self.key, lbn, lbn_cnt)
+    }
+}
+
+/// Returns the key of a child in the journal: its URI without parameters.
+fn child_key(uri: &str) -> &str {
+    uri.split('?').next().unwrap_or(uri)
+}
+
+impl IOLogJournal {
+    /// Assigns a slot to the I/O log of the given child. Returns None if the
+    /// journal is not usable or full.
+    pub(crate) fn assign(
+        self: &Arc<Self>,
+        child_uri: &str,
+    ) -> Option<IOLogJournalSlot> {
+        let key = child_key(child_uri);
+        let mut inner = self.inner.lock();
+        if !inner.healthy || inner.closed {
+            return None;
+        }
+
+        let slot = match inner.slots.iter().position(|s| {
+            s.as_ref().map_or(false, |s| s.in_use && s.key == key)
+        }) {
+            // The slot of the child still records the segments of a previous
+            // I/O log: keep them.
+            Some(i) => {
+                inner.slots[i].as_mut().unwrap().recovered = false;
+                i
+            }
+            None => {
+                let Some(i) = inner.slots.iter().position(Option::is_none)
+                else {
+                    warn!(
+                        "I/O log journal '{dev}': no free slot for '{key}'",
+                        dev = self.device
+                    );
+                    return None;
+                };
+                inner.epoch += 1;
+                let epoch = inner.epoch;
+                inner.slots[i] = Some(JournalSlot::new(
+                    key,
+                    self.geometry.bitmap_len(),
+                    epoch,
+                ));
+                i
+            }
+        };
+        drop(inner);
+
+        debug!(
+            "I/O log journal '{dev}': slot {slot} assigned to '{key}'",
+            dev = self.device
+        );
+        self.kick();
+        Some(IOLogJournalSlot {
+            journal: self.clone(),
+            slot,
+            key: key.to_string(),
+        })
+    }
+
+    /// Releases the slot of the given child, if any.
+    pub(crate) fn release(&self, child_uri: &str) {
+        let key = child_key(child_uri);
+        let mut inner = self.inner.lock();
+        inner.epoch += 1;
+        let epoch = inner.epoch;
+        let Some(s) = inner
+            .slots
+            .iter_mut()
+            .flatten()
+            .find(|s| s.in_use && s.key == key)
+        else {
+            return;
+        };
+        s.in_use = false;
+        s.recovered = false;
+        s.header_dirty = true;
+        s.dirty_blks.clear();
+        s.epoch = epoch;
+        drop(inner);
+
+        debug!(
+            "I/O log journal '{dev}': slot of '{key}' released",
+            dev = self.device
+        );
+        self.kick();
+    }
+
+    /// Releases all the slots.
+    fn release_all(&self) {
+        let mut inner = self.inner.lock();
+        inner.epoch += 1;
+        let epoch = inner.epoch;
+        for s in inner.slots.iter_mut().flatten().filter(|s| s.in_use) {
+            s.in_use = false;
+            s.recovered = false;
+            s.header_dirty = true;
+            s.dirty_blks.clear();
+            s.epoch = epoch;
+        }
+        drop(inner);
+        self.kick();
+    }
+
+    /// Takes the segments recorded by the recovered slot of the given child,
+    /// if any, as a rebuild map of the given child device. The slot stays in
+    /// use until the child is rebuilt.
+    pub(crate) fn take_recovered(
+        &self,
+        child_uri: &str,
+        device_name: &str,
+        num_blocks: u64,
+        block_len: u64,
+    ) -> Option<RebuildMap> {
+        let key = child_key(child_uri);
+        let mut inner = self.inner.lock();
+        if !inner.healthy {
+            return None;
+        }
+        let s = inner
+            .slots
+            .iter_mut()
+            .flatten()
+            .find(|s| s.in_use && s.recovered && s.key == key)?;
+        s.recovered = false;
+
+        let segments = SegmentMap::from_bitmap(
+            num_blocks,
+            block_len,
+            SEGMENT_SIZE,
+            &s.image,
+        );
+        Some(RebuildMap::new(device_name, segments))
+    }
+
+    /// Records the segments covering the given blocks in the given slot.
+    fn record(&self, slot: usize, key: &str, lbn: u64, lbn_cnt: u64) -> bool {
+        if lbn_cnt == 0 {
+            return false;
+        }
+
+        let geom = &self.geometry;
+        let first = lbn * geom.block_len / SEGMENT_SIZE;
+        let last = ((lbn + lbn_cnt) * geom.block_len - 1) / SEGMENT_SIZE;
+        let last = last.min(geom.num_segments - 1);
+
+        let mut inner = self.inner.lock();
+        if !inner.healthy || inner.closed {
+            return false;
+        }
+        let Some(s) = inner.slots[slot]
+            .as_mut()
+            .filter(|s| s.in_use && s.key == key)
+        else {
+            return false;
+        };
+
+        let mut wait = false;
+        for seg in first ..= last {
+            let byte = (seg / 8) as usize;
+            let mask = 0x80u8 >> (seg % 8);
+            if s.image[byte] & mask == 0 {
+                s.image[byte] |= mask;
+                s.dirty_blks.insert(byte as u64 / geom.block_len);
+            }
+            if s.persisted[byte] & mask == 0 {
+                wait = true;
+            }
+        }
+        drop(inner);
+
+        if wait {
+            self.kick();
+        }
+        wait
+    }
+
+    /// Requests a flush of the journal.
+    fn kick(&self) {
+        // The receiver goes away only when the journal is detached.
+        self.flush_queue.unbounded_send(()).ok();
+    }
+
+    /// Collects the writes needed to persist the journal.
+    fn pending_writes(&self) -> Vec<JournalWrite> {
+        let geom = &self.geometry;
+        let block_len = geom.block_len as usize;
+        let mut inner = self.inner.lock();
+        if !inner.healthy {
+            return Vec::new();
+        }
+
+        let mut writes = Vec::new();
+        for (i, s) in inner.slots.iter_mut().enumerate() {
+            let Some(s) = s.as_mut() else {
+                continue;
+            };
+
+            if s.header_dirty {
+                let mut data = vec![0; block_len];
+                s.encode_header(&mut data);
+                writes.push(JournalWrite {
+                    slot: i,
+                    epoch: s.epoch,
+                    blk: None,
+                    offset: geom.slot_offset(i),
+                    data,
+                });
+            }
+
+            for blk in std::mem::take(&mut s.dirty_blks) {
+                let start = blk as usize * block_len;
+                let end = (start + block_len).min(s.image.len());
+                let mut data = vec![0; block_len];
+                data[.. end - start].copy_from_slice(&s.image[start .. end]);
+                writes.push(JournalWrite {
+                    slot: i,
+                    epoch: s.epoch,
+                    blk: Some(blk),
+                    offset: geom.bitmap_offset(i, blk),
+                    data,
+                });
+            }
+        }
+        writes
+    }
+
+    /// Marks a write to the journal device as done.
+    fn write_done(&self, write: &JournalWrite) {
+        let mut inner = self.inner.lock();
+        let entry = &mut inner.slots[write.slot];
+        let Some(s) = entry.as_mut().filter(|s| s.epoch == write.epoch) else {
+            // The slot has been assigned or released again meanwhile.
+            return;
+        };
+
+        match write.blk {
+            None if s.in_use => s.header_dirty = false,
+            // A released slot is free once its header is written.
+            None => *entry = None,
+            Some(blk) => {
+                let start = (blk * self.geometry.block_len) as usize;
+                let end = (start + write.data.len()).min(s.persisted.len());
+                s.persisted[start .. end]
+                    .copy_from_slice(&write.data[.. end - start]);
+            }
+        }
+    }
+
+    /// Disables the journal after a write failure.
+    fn fail(&self, error: &CoreError) {
+        self.flush_errors.fetch_add(1, Ordering::Relaxed);
+        let mut inner = self.inner.lock();
+        if inner.healthy {
+            error!(
+                "I/O log journal '{dev}' failed, disabling it: {error}",
+                dev = self.device
+            );
+            inner.healthy = false;
+        }
+    }
+
+    /// Returns the health statistics of the journal.
+    pub(crate) fn stats(&self) -> NexusIoLogJournalStats {
+        let inner = self.inner.lock();
+        let used = inner.slots.iter().flatten().filter(|s| s.in_use);
+        NexusIoLogJournalStats {
+            device: self.device.clone(),
+            healthy: inner.healthy,
+            capacity_slots: self.geometry.num_slots as u32,
+            used_slots: used.clone().count() as u32,
+            recovered_slots: used.filter(|s| s.recovered).count() as u32,
+            flushes: self.flushes.load(Ordering::Relaxed),
+            flush_errors: self.flush_errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// I/O log journal device attached to a nexus.
+pub(super) struct NexusIoLogJournalDevice {
+    /// Journal shared with the I/O logs.
+    pub(super) journal: Arc<IOLogJournal>,
+    /// Descriptor of the journal device.
+    _descriptor: Box<dyn BlockDeviceDescriptor>,
+    /// Completes when the flush worker exits.
+    worker_done: oneshot::Receiver<()>,
+}
+
+impl std::fmt::Debug for NexusIoLogJournalDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "I/O log journal on '{}'", self.journal.device)
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Attaches a local device as the journal of the I/O logs of the nexus.
+    /// The I/O log slots the device holds for this nexus are recovered,
+    /// except the ones of the children currently healthy; otherwise the
+    /// device is formatted.
+    pub async fn attach_io_log_journal(
+        mut self: Pin<&mut Self>,
+        uri: &str,
+    ) -> Result<(), Error> {
+        if self.io_log_journal.is_some() {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "nexus '{}' already has an I/O log journal",
+                    self.name
+                ),
+            });
+        }
+
+        let name = device_create(uri).await.context(
+            nexus_err::CreateIoLogJournal {
+                name: self.name.clone(),
+                device: uri.to_string(),
+            },
+        )?;
+
+        let (done_tx, done_rx) = oneshot::channel();
+        let (journal_device, handle, flush_rx) =
+            match self.open_io_log_journal(uri, &name, done_rx).await {
+                Ok(r) => r,
+                Err(error) => {
+                    device_destroy(uri).await.ok();
+                    return Err(error);
+                }
+            };
+        let journal = journal_device.journal.clone();
+
+        // Slots of the healthy children are stale.
+        for c in self.children_iter().filter(|c| c.is_healthy()) {
+            journal.release(c.uri());
+        }
+
+        unsafe {
+            self.as_mut().unpin_mut().io_log_journal = Some(journal_device);
+        }
+
+        Reactors::master().send_future(flush_worker(
+            self.name.clone(),
+            journal.clone(),
+            handle,
+            flush_rx,
+            done_tx,
+        ));
+
+        let stats = journal.stats();
+        info!(
+            "{self:?}: I/O log journal attached on '{uri}', {n} slots, \
+            {r} recovered",
+            n = stats.capacity_slots,
+            r = stats.recovered_slots,
+        );
+        Ok(())
+    }
+
+    /// Opens the journal device, and recovers or formats the journal.
+    #[allow(clippy::type_complexity)]
+    async fn open_io_log_journal(
+        &self,
+        uri: &str,
+        name: &str,
+        worker_done: oneshot::Receiver<()>,
+    ) -> Result<
+        (
+            NexusIoLogJournalDevice,
+            Box<dyn BlockDeviceHandle>,
+            mpsc::UnboundedReceiver<()>,
+        ),
+        Error,
+    > {
+        let err_ctx = || nexus_err::OpenIoLogJournal {
+            name: self.name.clone(),
+            device: uri.to_string(),
+        };
+
+        let descriptor = device_open(name, true).context(err_ctx())?;
+
+        let device = descriptor.get_device();
+        if device.driver_name() == "nvme" {
+            return Err(Error::OperationNotAllowed {
+                reason: format!("I/O log journal device '{uri}' is not local"),
+            });
+        }
+        if device.block_len() != self.block_len() {
+            return Err(Error::MixedBlockSizes {
+                name: self.name.clone(),
+            });
+        }
+
+        let block_len = self.block_len();
+        let num_segments = ((self.data_ent_offset + self.num_blocks())
+            * block_len
+            + SEGMENT_SIZE
+            - 1)
+            / SEGMENT_SIZE;
+        let slot_blks =
+            1 + ((num_segments + 7) / 8 + block_len - 1) / block_len;
+        let num_slots = (device.num_blocks().saturating_sub(1) / slot_blks)
+            .min(JOURNAL_MAX_SLOTS);
+        if num_slots == 0 {
+            return Err(Error::InvalidArguments {
+                name: self.name.clone(),
+                args: format!("I/O log journal device '{uri}' is too small"),
+            });
+        }
+        let geometry = JournalGeometry {
+            block_len,
+            num_segments,
+            slot_blks,
+            num_slots,
+        };
+
+        let handle = descriptor.get_io_handle().context(err_ctx())?;
+        let slots = load_journal(self, &geometry, handle.as_ref())
+            .await
+            .context(err_ctx())?;
+
+        let (flush_tx, flush_rx) = mpsc::unbounded();
+        let journal = Arc::new(IOLogJournal {
+            device: uri.to_string(),
+            geometry,
+            inner: parking_lot::Mutex::new(JournalInner {
+                slots,
+                healthy: true,
+                ..Default::default()
+            }),
+            flush_queue: flush_tx,
+            flushes: AtomicU64::new(0),
+            flush_errors: AtomicU64::new(0),
+        });
+
+        Ok((
+            NexusIoLogJournalDevice {
+                journal,
+                _descriptor: descriptor,
+                worker_done,
+            },
+            handle,
+            flush_rx,
+        ))
+    }
+
+    /// Detaches the I/O log journal of the nexus, if any, releasing all its
+    /// slots, and destroys the journal device. The I/O logs continue in
+    /// memory only.
+    pub async fn detach_io_log_journal(self: Pin<&mut Self>) {
+        self.close_io_log_journal(true).await;
+    }
+
+    /// Closes the I/O log journal of the nexus, if any. Unless released, the
+    /// slots in use are recovered when the journal is attached again.
+    pub(super) async fn close_io_log_journal(
+        mut self: Pin<&mut Self>,
+        release: bool,
+    ) {
+        let Some(d) =
+            unsafe { self.as_mut().unpin_mut() }.io_log_journal.take()
+        else {
+            return;
+        };
+
+        if release {
+            d.journal.release_all();
+        }
+        d.journal.inner.lock().closed = true;
+
+        // Let the flush worker persist the released slots and resubmit the
+        // writes waiting for the journal, and wait for it to release the
+        // device.
+        d.journal.flush_queue.close_channel();
+        d.worker_done.await.ok();
+
+        let uri = d.journal.device.clone();
+        drop(d);
+        if let Err(error) = device_destroy(&uri).await {
+            warn!(
+                "{self:?}: failed to destroy I/O log journal '{uri}': {error}"
+            );
+        }
+
+        info!("{self:?}: I/O log journal detached from '{uri}'");
+    }
+
+    /// Returns the I/O log journal of the nexus, if any.
+    pub(super) fn io_log_journal(&self) -> Option<&Arc<IOLogJournal>> {
+        self.io_log_journal.as_ref().map(|d| &d.journal)
+    }
+
+    /// Returns the health statistics of the I/O log journal, if any.
+    pub fn io_log_journal_stats(&self) -> Option<NexusIoLogJournalStats> {
+        self.io_log_journal().map(|j| j.stats())
+    }
+}
+
+/// Reads the journal header and the slots in use, if the journal belongs to
+/// the given nexus and has the same geometry. Otherwise, formats the journal.
+async fn load_journal(
+    nexus: &Nexus<'_>,
+    geom: &JournalGeometry,
+    handle: &dyn BlockDeviceHandle,
+) -> Result<Vec<Option<JournalSlot>>, CoreError> {
+    let block_len = geom.block_len;
+    let mut buf = dma_buf(handle, block_len)?;
+    let mut header = vec![0; block_len as usize];
+    geom.encode_header(nexus.uuid().as_bytes(), &mut header);
+
+    let mut slots: Vec<Option<JournalSlot>> =
+        (0 .. geom.num_slots).map(|_| None).collect();
+
+    handle.read_at(0, &mut buf).await?;
+    if buf.as_slice() != header.as_slice() {
+        info!("{nexus:?}: formatting I/O log journal");
+        buf.as_mut_slice().fill(0);
+        for i in 0 .. slots.len() {
+            handle.write_at(geom.slot_offset(i), &buf).await?;
+        }
+        buf.as_mut_slice().copy_from_slice(&header);
+        handle.write_at(0, &buf).await?;
+        return Ok(slots);
+    }
+
+    let bitmap_blks = geom.slot_blks - 1;
+    for (i, slot) in slots.iter_mut().enumerate() {
+        handle.read_at(geom.slot_offset(i), &mut buf).await?;
+        let Some(key) = JournalSlot::decode_header(buf.as_slice()) else {
+            continue;
+        };
+
+        let mut s = JournalSlot::new(&key, geom.bitmap_len(), 0);
+        for blk in 0 .. bitmap_blks {
+            handle.read_at(geom.bitmap_offset(i, blk), &mut buf).await?;
+            let start = (blk * block_len) as usize;
+            let end = (start + block_len as usize).min(s.image.len());
+            s.image[start .. end]
+                .copy_from_slice(&buf.as_slice()[.. end - start]);
+        }
+        s.persisted = s.image.clone();
+        s.recovered = true;
+        s.header_dirty = false;
+
+        info!("{nexus:?}: recovered I/O log journal slot {i} of '{key}'");
+        *slot = Some(s);
+    }
+
+    Ok(slots)
+}
+
+/// Allocates a DMA buffer of the given size for the journal device.
+fn dma_buf(
+    handle: &dyn BlockDeviceHandle,
+    size: u64,
+) -> Result<DmaBuf, CoreError> {
+    handle
+        .dma_malloc(size)
+        .map_err(|_| CoreError::DmaAllocationFailed {
+            size,
+        })
+}
+
+/// Persists the journal upon request, until the journal is detached. After
+/// each flush, the writes waiting for the journal are resubmitted.
+async fn flush_worker(
+    nexus_name: String,
+    journal: Arc<IOLogJournal>,
+    handle: Box<dyn BlockDeviceHandle>,
+    mut queue: mpsc::UnboundedReceiver<()>,
+    done: oneshot::Sender<()>,
+) {
+    while queue.next().await.is_some() {
+        // Coalesce the pending requests.
+        while let Ok(Some(())) = queue.try_next() {}
+
+        if let Err(error) = flush(&journal, handle.as_ref()).await {
+            journal.fail(&error);
+        }
+
+        if let Some(nexus) = nexus_lookup_mut(&nexus_name) {
+            nexus
+                .traverse_io_channels_async((), |channel, _| {
+                    channel.resubmit_journal_waiting();
+                })
+                .await;
+        }
+    }
+
+    done.send(()).ok();
+}
+
+/// Writes the pending changes of the journal to the journal device.
+async fn flush(
+    journal: &IOLogJournal,
+    handle: &dyn BlockDeviceHandle,
+) -> Result<(), CoreError> {
+    let writes = journal.pending_writes();
+    if writes.is_empty() {
+        return Ok(());
+    }
+
+    let mut buf = dma_buf(handle, journal.geometry.block_len)?;
+    for w in &writes {
+        buf.as_mut_slice().copy_from_slice(&w.data);
+        handle.write_at(w.offset, &buf).await?;
+        journal.flushes.fetch_add(1, Ordering::Relaxed);
+        journal.write_done(w);
+    }
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! Splitting of the nexus I/Os larger than the maximum I/O size.
+//!
+//! Some children only accept I/Os up to a maximum transfer size, e.g. the
+//! MDTS of a fabric controller, and fail the larger writes of the initiator.
+//! With a maximum I/O size configured for the nexus or for any of its
+//! children, the reads and writes larger than the smallest of them are
+//! submitted to each child as several child I/Os, and the nexus I/O completes
+//! once all of them have completed.
+//!
+//! Reads split into several child I/Os are not subject to the child I/O
+//! timeout.
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::Serialize;
+use spdk_rs::{libspdk::iovec, IoVec};
+
+use super::{Error, Nexus};
+use crate::core::CoreError;
+
+/// Child I/O of a split nexus I/O.
+#[derive(Debug, Clone, Copy)]
+struct SplitChunk {
+    /// Index of the first I/O vector of the child I/O.
+    iov_start: usize,
+    /// Number of I/O vectors of the child I/O.
+    iov_cnt: usize,
+    /// Offset of the child I/O, in blocks.
+    offset: u64,
+    /// Number of blocks of the child I/O.
+    num_blocks: u64,
+}
+
+/// Split layout of a nexus read or write. The I/O vectors of the child I/Os
+/// point into the initiator buffers, and must live until all child I/Os
+/// complete.
+pub(super) struct NexusIoSplit {
+    iovs: Vec<iovec>,
+    chunks: Vec<SplitChunk>,
+    /// Number of child I/Os which failed to submit since last checked.
+    failed_submissions: Cell<u32>,
+}
+
+impl NexusIoSplit {
+    /// Splits an I/O over the given I/O vectors into child I/Os of at most
+    /// `max_blocks` blocks.
+    pub(super) fn new(
+        iovs: &[IoVec],
+        offset: u64,
+        num_blocks: u64,
+        block_len: u64,
+        max_blocks: u64,
+    ) -> Self {
+        let src = unsafe {
+            std::slice::from_raw_parts(
+                iovs.as_ptr() as *const iovec,
+                iovs.len(),
+            )
+        };
+
+        let mut split = Self {
+            iovs: Vec::with_capacity(src.len() + 1),
+            chunks: Vec::with_capacity(num_blocks.div_ceil(max_blocks) as usize),
+            failed_submissions: Cell::new(0),
+        };
+
+        let mut idx = 0;
+        let mut iov_offset = 0;
+        let mut blk = 0;
+        while blk < num_blocks && idx < src.len() {
+            let chunk_blocks = (num_blocks - blk).min(max_blocks);
+            let iov_start = split.iovs.len();
+
+            let mut remaining = chunk_blocks * block_len;
+            while remaining > 0 && idx < src.len() {
+                let len = (src[idx].iov_len as u64 - iov_offset as u64)
+                    .min(remaining);
+                split.iovs.push(iovec {
+                    iov_base: unsafe {
+                        src[idx].iov_base.cast::<u8>().add(iov_offset).cast()
+                    },
+                    iov_len: len as _,
+                });
+
+                remaining -= len;
+                iov_offset += len as usize;
+                if iov_offset == src[idx].iov_len as usize {
+                    idx += 1;
+                    iov_offset = 0;
+                }
+            }
+
+            split.chunks.push(SplitChunk {
+                iov_start,
+                iov_cnt: split.iovs.len() - iov_start,
+                offset: offset + blk,
+                num_blocks: chunk_blocks,
+            });
+            blk += chunk_blocks;
+        }
+
+        split
+    }
+
+    /// Returns the number of child I/Os each child gets.
+    pub(super) fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Submits the child I/Os to a child with the given submission function,
+    /// which is passed the I/O vectors, the offset and the number of blocks
+    /// of each child I/O. Returns the number of child I/Os submitted. Once
+    /// some child I/Os have been submitted, a submission failure is counted
+    /// as a failed submission instead, to be taken by the caller.
+    pub(super) fn submit<F>(&self, mut f: F) -> Result<u32, CoreError>
+    where
+        F: FnMut(&mut [IoVec], u64, u64) -> Result<(), CoreError>,
+    {
+        let mut submitted = 0;
+        for c in &self.chunks {
+            let iovs = unsafe {
+                std::slice::from_raw_parts_mut(
+                    self.iovs.as_ptr().add(c.iov_start) as *mut IoVec,
+                    c.iov_cnt,
+                )
+            };
+
+            match f(iovs, c.offset, c.num_blocks) {
+                Ok(()) => submitted += 1,
+                Err(e) if submitted == 0 => return Err(e),
+                Err(e) => {
+                    error!(
+                        "Split I/O submission failed after {submitted} child \
+                        I/Os: {e}"
+                    );
+                    self.failed_submissions
+                        .set(self.failed_submissions.get() + 1);
+                    break;
+                }
+            }
+        }
+        Ok(submitted)
+    }
+
+    /// Returns and resets the number of failed submissions.
+    pub(super) fn take_failed_submissions(&self) -> u32 {
+        self.failed_submissions.take()
+    }
+}
+
+/// Counters of the split reads and writes of a nexus.
+#[derive(Debug, Default)]
+pub struct NexusIoSplitCounters {
+    split_ios: AtomicU64,
+    child_ios: AtomicU64,
+}
+
+/// Point-in-time copy of the split counters of a nexus.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct NexusIoSplitStats {
+    /// Effective maximum I/O size of the nexus in bytes, zero if unlimited.
+    pub max_io_size: u64,
+    /// Reads and writes split into several child I/Os.
+    pub split_ios: u64,
+    /// Child I/Os per child the split reads and writes were split into.
+    pub child_ios: u64,
+}
+
+impl NexusIoSplitCounters {
+    /// Counts a nexus I/O split into the given number of child I/Os.
+    pub(super) fn record(&self, child_ios: u64) {
+        self.split_ios.fetch_add(1, Ordering::Relaxed);
+        self.child_ios.fetch_add(child_ios, Ordering::Relaxed);
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the maximum I/O size of the nexus in bytes, zero if unlimited.
+    pub fn max_io_size(&self) -> u64 {
+        self.max_io_size.load()
+    }
+
+    /// Checks that the given maximum I/O size is zero or a multiple of the
+    /// block size.
+    fn check_max_io_size(&self, size: u64) -> Result<(), Error> {
+        let block_len = self.block_len();
+        if size == 0 || (block_len > 0 && size % block_len == 0) {
+            return Ok(());
+        }
+        Err(Error::InvalidArguments {
+            name: self.name.clone(),
+            args: format!(
+                "maximum I/O size {size} is not a multiple of the block \
+                size {block_len}"
+            ),
+        })
+    }
+
+    /// Sets the maximum I/O size of the nexus in bytes, zero for unlimited.
+    pub fn set_max_io_size(&self, size: u64) -> Result<(), Error> {
+        self.check_max_io_size(size)?;
+        info!("{self:?}: setting maximum I/O size to {size}");
+        self.max_io_size.store(size);
+        Ok(())
+    }
+
+    /// Sets the maximum I/O size of a child in bytes, zero for unlimited.
+    pub fn set_child_max_io_size(
+        &self,
+        child_uri: &str,
+        size: u64,
+    ) -> Result<(), Error> {
+        self.check_max_io_size(size)?;
+        let child = self.child(child_uri)?;
+        info!("{child:?}: setting maximum I/O size to {size}");
+        child.set_max_io_size(size);
+        Ok(())
+    }
+
+    /// Returns the effective maximum I/O size of the nexus in blocks: the
+    /// smallest of the maximum I/O sizes of the nexus and of its children,
+    /// zero if unlimited.
+    pub(super) fn io_split_blocks(&self) -> u64 {
+        let size = self
+            .children_iter()
+            .map(|c| c.max_io_size())
+            .chain(std::iter::once(self.max_io_size()))
+            .filter(|s| *s > 0)
+            .min()
+            .unwrap_or_default();
+
+        match self.block_len() {
+            0 => 0,
+            block_len => size / block_len,
+        }
+    }
+
+    /// Returns the split counters of the nexus.
+    pub fn io_split_stats(&self) -> NexusIoSplitStats {
+        NexusIoSplitStats {
+            max_io_size: self.io_split_blocks() * self.block_len(),
+            split_ios: self.io_splits.split_ios.load(Ordering::Relaxed),
+            child_ios: self.io_splits.child_ios.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Implements the child I/O timeout of a nexus.
+//!
+//! By default, a read stuck on a child is only failed once the controller of
+//! the child times it out, aborts it or resets. With a child I/O timeout, the
+//! reads outstanding for longer than the timeout are aborted on their child
+//! by the nexus itself with an NVMe Abort of their own commands, and
+//! resubmitted to another child once the abort completes them. The other I/Os
+//! of the child are not affected. A read which cannot be aborted, or is still
+//! outstanding another timeout after its abort, is completed by a reset of the
+//! controller of the child, as after a controller I/O timeout.
+use std::time::{Duration, Instant};
+
+use super::{nexus_lookup_mut, Nexus, NexusBio, NexusChannel};
+use crate::{
+    core::{BlockDevice, IoCompletionStatus, Reactors},
+    sleep::mayastor_sleep,
+};
+
+/// Read outstanding on a child, with the time it must complete by.
+pub(super) struct TimedRead<'n> {
+    io: NexusBio<'n>,
+    device: String,
+    deadline: Instant,
+    /// Set once an abort of the read has been sent.
+    aborted: bool,
+}
+
+/// Completion of the reset of a child with a read which could not be
+/// aborted.
+fn child_reset_done(
+    device: &dyn BlockDevice,
+    status: IoCompletionStatus,
+    _ctx: *mut std::ffi::c_void,
+) {
+    if status != IoCompletionStatus::Success {
+        error!(
+            "Failed to reset '{}' after a read timeout: {status:?}",
+            device.device_name()
+        );
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Returns the child I/O timeout of the nexus, if enabled.
+    pub fn child_io_timeout(&self) -> Option<Duration> {
+        match self.child_io_timeout_us.load() {
+            0 => None,
+            us => Some(Duration::from_micros(us)),
+        }
+    }
+
+    /// Sets the child I/O timeout of the nexus, zero disabling it.
+    pub fn set_child_io_timeout(&self, timeout: Duration) {
+        info!("{self:?}: setting child I/O timeout to {timeout:?}");
+        self.child_io_timeout_us.store(timeout.as_micros() as u64);
+    }
+
+    /// Schedules a scan of the I/O channels for timed out reads, unless it
+    /// has been already scheduled.
+    pub(super) fn schedule_io_timeout_scan(&self) {
+        let Some(timeout) = self.child_io_timeout() else {
+            return;
+        };
+
+        if self
+            .io_timeout_scan_scheduled
+            .compare_exchange(false, true)
+            .is_err()
+        {
+            return;
+        }
+
+        let name = self.name.clone();
+        Reactors::master().send_future(async move {
+            if mayastor_sleep(timeout / 2).await.is_err() {
+                error!("Nexus '{name}': failed to wait for I/O timeout scan");
+            }
+
+            let Some(nexus) = nexus_lookup_mut(&name) else {
+                return;
+            };
+
+            // Channels with reads still outstanding schedule the next scan.
+            nexus.io_timeout_scan_scheduled.store(false);
+
+            nexus
+                .traverse_io_channels_async((), |channel, _| {
+                    channel.abort_timed_out_reads();
+                })
+                .await;
+        });
+    }
+}
+
+impl<'n> NexusChannel<'n> {
+    /// Starts timing the given read submitted to the given child device.
+    pub(super) fn track_read(
+        &mut self,
+        io: NexusBio<'n>,
+        device: String,
+        timeout: Duration,
+    ) {
+        self.timed_reads.push(TimedRead {
+            io,
+            device,
+            deadline: Instant::now() + timeout,
+            aborted: false,
+        });
+        self.nexus().schedule_io_timeout_scan();
+    }
+
+    /// Stops timing the given read, upon its completion.
+    #[inline(always)]
+    pub(super) fn untrack_read(&mut self, io: &NexusBio<'n>) {
+        if let Some(idx) = self
+            .timed_reads
+            .iter()
+            .position(|r| r.io.as_ptr() == io.as_ptr())
+        {
+            self.timed_reads.swap_remove(idx);
+        }
+    }
+
+    /// Aborts the timed out reads on their children, and resets the children
+    /// of the reads whose abort did not complete them in time. The reads are
+    /// marked as timed out to be resubmitted to other children upon
+    /// completion.
+    pub(super) fn abort_timed_out_reads(&mut self) {
+        let Some(timeout) = self.nexus().child_io_timeout() else {
+            return;
+        };
+        let now = Instant::now();
+        let (expired, pending): (Vec<_>, Vec<_>) =
+            self.timed_reads.drain(..).partition(|r| r.deadline <= now);
+        self.timed_reads = pending;
+
+        let mut resets = Vec::new();
+        for mut r in expired {
+            let Some(hdl) = self.find_reader(&r.device) else {
+                continue;
+            };
+
+            if !r.aborted {
+                warn!(
+                    "{io:?}: read timed out on '{dev}', aborting it",
+                    io = r.io,
+                    dev = r.device
+                );
+                r.io.set_timed_out();
+                match hdl.abort_io(r.io.as_ptr().cast()) {
+                    Ok(()) => {
+                        r.aborted = true;
+                        r.deadline = now + timeout;
+                        self.timed_reads.push(r);
+                        continue;
+                    }
+                    Err(e) => warn!(
+                        "{io:?}: failed to abort read on '{dev}': {e}",
+                        io = r.io,
+                        dev = r.device
+                    ),
+                }
+            }
+
+            if !resets.contains(&r.device) {
+                resets.push(r.device);
+            }
+        }
+
+        for device in resets {
+            let Some(hdl) = self.find_reader(&device) else {
+                continue;
+            };
+            warn!("{self:?}: resetting '{device}' to complete timed out reads");
+            if let Err(e) =
+                hdl.reset(child_reset_done, std::ptr::null_mut())
+            {
+                error!(
+                    "{self:?}: failed to reset '{device}', waiting for the \
+                    controller timeout: {e}"
+                );
+            }
+        }
+
+        if !self.timed_reads.is_empty() {
+            self.nexus().schedule_io_timeout_scan();
+        }
+    }
+}
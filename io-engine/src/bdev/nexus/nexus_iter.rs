@@ -1,4 +1,7 @@
-use crate::bdev::{nexus::nexus_module::NexusModule, Nexus};
+use crate::bdev::{
+    nexus::{nexus_module::NexusModule, Error},
+    Nexus,
+};
 use spdk_rs::BdevModuleIter;
 use std::pin::Pin;
 
@@ -44,6 +47,32 @@ pub fn nexus_lookup_uuid_mut<'n>(
     NexusIterMut::new().find(|n| n.uuid().to_string() == uuid)
 }
 
+/// Looks up a Nexus by its name or uuid, and returns a mutable reference to
+/// it. An identifier which is the name of a nexus and the uuid of another is
+/// ambiguous, and fails the lookup.
+pub fn nexus_lookup_id_mut<'n>(
+    id: &str,
+) -> Result<Option<<NexusIterMut<'n> as Iterator>::Item>, Error> {
+    let by_name = nexus_lookup(id).map(|n| n.uuid());
+    let by_uuid = NexusIter::new()
+        .find(|n| n.uuid().to_string() == id)
+        .map(|n| n.uuid());
+
+    match (by_name, by_uuid) {
+        (Some(name_of), Some(uuid_of)) if name_of != uuid_of => {
+            Err(Error::AmbiguousNexus {
+                id: id.to_owned(),
+                name_of: name_of.to_string(),
+                uuid_of: uuid_of.to_string(),
+            })
+        }
+        (Some(uuid), _) | (None, Some(uuid)) => {
+            Ok(NexusIterMut::new().find(|n| n.uuid() == uuid))
+        }
+        (None, None) => Ok(None),
+    }
+}
+
 /// TODO
 pub struct NexusIter<'n> {
     iter: BdevModuleIter<Nexus<'n>>,
@@ -1,5 +1,13 @@
-use super::{IoMode, Nexus, NexusChild};
+use super::{
+    ChildState,
+    FaultReason,
+    IoMode,
+    Nexus,
+    NexusChild,
+    NexusShutdownReason,
+};
 use crate::{persistent_store::PersistentStore, sleep::mayastor_sleep};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
@@ -47,6 +55,140 @@ pub struct ChildInfo {
     pub uuid: String,
     /// Child's state of health.
     pub healthy: bool,
+    /// Reason the child was faulted, if it is not healthy: tells a child
+    /// offlined by an operator from a child faulted by an I/O error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_reason: Option<FaultReason>,
+    /// Time the state of the child was last recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_at: Option<DateTime<Utc>>,
+    /// Number of times the state of the child was recorded, to order the
+    /// records of the child.
+    #[serde(default)]
+    pub generation: u64,
+}
+
+impl ChildInfo {
+    /// Creates the record of a child with the given state.
+    fn new(
+        uuid: String,
+        healthy: bool,
+        fault_reason: Option<FaultReason>,
+    ) -> Self {
+        let mut info = Self {
+            uuid,
+            ..Default::default()
+        };
+        info.record(healthy, fault_reason);
+        info
+    }
+
+    /// Records a new state of the child.
+    fn record(&mut self, healthy: bool, fault_reason: Option<FaultReason>) {
+        self.healthy = healthy;
+        self.fault_reason = if healthy { None } else { fault_reason };
+        self.changed_at = Some(Utc::now());
+        self.generation += 1;
+    }
+}
+
+/// Child left out of a nexus recreated from its persisted state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludedChild {
+    /// URI of the child, `None` for a recorded child whose URI was not
+    /// given.
+    pub uri: Option<String>,
+    /// UUID of the child, if known.
+    pub uuid: Option<String>,
+    /// Reason the child was left out.
+    pub reason: String,
+}
+
+/// Children of a nexus recreated from its persisted state.
+#[derive(Debug, Default, Clone)]
+pub struct NexusRecoveryPlan {
+    /// URIs of the children recorded as healthy.
+    pub children: Vec<String>,
+    /// Children left out.
+    pub excluded: Vec<ExcludedChild>,
+}
+
+impl NexusInfo {
+    /// Loads the nexus information persisted under the given key, for the
+    /// nexus of the given name.
+    pub async fn load(key: &str, name: &str) -> Result<Self, Error> {
+        let value = PersistentStore::get(key).await.map_err(|source| {
+            Error::LoadStateFailed {
+                source,
+                name: name.to_owned(),
+            }
+        })?;
+        serde_json::from_value(value).map_err(|e| Error::NexusCreate {
+            name: name.to_owned(),
+            reason: format!("the persisted nexus state is invalid: {e}"),
+        })
+    }
+
+    /// Selects, among the given child URIs, the children recorded as
+    /// healthy. The other children, as well as the healthy children whose
+    /// URI is not given, are reported as excluded.
+    pub fn recovery_plan(&self, uris: &[String]) -> NexusRecoveryPlan {
+        let mut children = Vec::new();
+        let mut excluded = Vec::new();
+        let mut exclude = |uri: &str, uuid: Option<String>, reason: String| {
+            excluded.push(ExcludedChild {
+                uri: Some(uri.to_owned()),
+                uuid,
+                reason,
+            })
+        };
+
+        let mut found = Vec::new();
+        for uri in uris {
+            if url::Url::parse(uri).is_err() {
+                exclude(uri, None, "invalid URI".to_string());
+                continue;
+            }
+            let Some(uuid) = NexusChild::uuid(uri) else {
+                exclude(uri, None, "URI has no UUID".to_string());
+                continue;
+            };
+            match self.children.iter().find(|c| c.uuid == uuid) {
+                None => {
+                    exclude(uri, Some(uuid), "not recorded".to_string());
+                }
+                Some(_) if found.contains(&uuid) => {
+                    exclude(uri, Some(uuid), "duplicate child".to_string());
+                }
+                Some(c) if !c.healthy => {
+                    let reason = match c.fault_reason {
+                        Some(r) => format!("recorded as faulted: {r}"),
+                        None => "recorded as out-of-sync".to_string(),
+                    };
+                    exclude(uri, Some(uuid), reason);
+                }
+                Some(_) => {
+                    children.push(uri.clone());
+                    found.push(uuid);
+                }
+            }
+        }
+
+        for c in &self.children {
+            if c.healthy && !found.contains(&c.uuid) {
+                excluded.push(ExcludedChild {
+                    uri: None,
+                    uuid: Some(c.uuid.clone()),
+                    reason: "recorded as healthy, but no URI given".to_string(),
+                });
+            }
+        }
+
+        NexusRecoveryPlan {
+            children,
+            excluded,
+        }
+    }
 }
 
 /// Defines the type of persist operations.
@@ -97,11 +239,12 @@ impl<'n> Nexus<'n> {
                 assert!(nexus_info.children.is_empty());
                 assert!(!nexus_info.clean_shutdown);
                 self.children_iter().for_each(|c| {
-                    let child_info = ChildInfo {
-                        uuid: NexusChild::uuid(c.uri())
+                    let child_info = ChildInfo::new(
+                        NexusChild::uuid(c.uri())
                             .expect("Failed to get child UUID."),
-                        healthy: c.is_healthy(),
-                    };
+                        c.is_healthy(),
+                        child_fault_reason(c),
+                    );
                     nexus_info.children.push(child_info);
                 });
             }
@@ -112,21 +255,17 @@ impl<'n> Nexus<'n> {
                 // Add the state of a new child. This should only be called
                 // on adding a new child. Take into account that the same child
                 // can be readded again.
-                let child_info = ChildInfo {
-                    uuid: NexusChild::uuid(child_uri)
-                        .expect("Failed to get child UUID."),
-                    healthy: *healthy,
-                };
+                let uuid = NexusChild::uuid(child_uri)
+                    .expect("Failed to get child UUID.");
+                let reason = self.persisted_fault_reason(child_uri);
 
                 // Check if there is a child with the same UUID already
                 // and update the existing record instead of adding a new one.
-                match nexus_info
-                    .children
-                    .iter()
-                    .position(|r| r.uuid == child_info.uuid)
-                {
-                    Some(idx) => nexus_info.children[idx] = child_info,
-                    None => nexus_info.children.push(child_info),
+                match nexus_info.children.iter_mut().find(|r| r.uuid == uuid) {
+                    Some(child_info) => child_info.record(*healthy, reason),
+                    None => nexus_info
+                        .children
+                        .push(ChildInfo::new(uuid, *healthy, reason)),
                 }
             }
             PersistOp::RemoveChild {
@@ -146,9 +285,10 @@ impl<'n> Nexus<'n> {
                 // Only update the state of the child that has changed. Do not
                 // update the other children or "clean shutdown" information.
                 // This should only be called on a child state change.
+                let reason = self.persisted_fault_reason(child_uri);
                 nexus_info.children.iter_mut().for_each(|c| {
                     if c.uuid == uuid {
-                        c.healthy = *healthy;
+                        c.record(*healthy, reason);
                     }
                 });
             }
@@ -167,9 +307,10 @@ impl<'n> Nexus<'n> {
                 let uuid = NexusChild::uuid(child_uri)
                     .expect("Failed to get child UUID.");
 
+                let reason = self.persisted_fault_reason(child_uri);
                 nexus_info.children.iter_mut().for_each(|c| {
                     if c.uuid == uuid {
-                        c.healthy = *healthy;
+                        c.record(*healthy, reason);
                     }
                 });
             }
@@ -181,7 +322,11 @@ impl<'n> Nexus<'n> {
             }
         }
 
-        match self.save(&persistent_nexus_info).await {
+        // Child updates may be deferred while the store cannot be reached,
+        // instead of shutting the nexus down.
+        let deferrable = !matches!(op, PersistOp::Create);
+
+        match self.save(&persistent_nexus_info, deferrable).await {
             Ok(_) => {
                 self.set_nexus_io_mode(IoMode::Normal).await;
                 Ok(())
@@ -196,17 +341,28 @@ impl<'n> Nexus<'n> {
                         "{self:?}: failed to update persistent store, \
                         will shutdown the nexus: {e}"
                     );
-                    self.try_self_shutdown();
+                    self.try_self_shutdown(
+                        NexusShutdownReason::PersistenceFailure,
+                    );
                 }
                 Err(e)
             }
         }
     }
 
+    /// Returns the fault reason of the given child to persist.
+    fn persisted_fault_reason(&self, child_uri: &str) -> Option<FaultReason> {
+        self.lookup_child(child_uri).and_then(child_fault_reason)
+    }
+
     // Saves the nexus info to the store. This is integral to ensuring data
     // consistency across restarts of Mayastor. Therefore, keep retrying
-    // until successful.
-    async fn save(&self, info: &PersistentNexusInfo) -> Result<(), Error> {
+    // until successful, or until the save is deferred if allowed.
+    async fn save(
+        &self,
+        info: &PersistentNexusInfo,
+        deferrable: bool,
+    ) -> Result<(), Error> {
         // If a key has been provided, use it to store the NexusInfo; use the
         // nexus uuid as the key otherwise.
         let key = match &info.key {
@@ -214,6 +370,17 @@ impl<'n> Nexus<'n> {
             None => self.uuid().to_string(),
         };
 
+        // Do not wait for a store known to be unreachable, nor overtake a
+        // deferred save of the same nexus.
+        if deferrable
+            && (PersistentStore::is_unreachable()
+                || PersistentStore::is_deferred(&key))
+            && PersistentStore::defer_put(&key, &info.inner)
+        {
+            warn!("{self:?}: the state will be saved once the store is back");
+            return Ok(());
+        }
+
         let mut retry = PersistentStore::retries();
         loop {
             let Err(err) = PersistentStore::put(&key, &info.inner).await else {
@@ -223,6 +390,13 @@ impl<'n> Nexus<'n> {
 
             retry -= 1;
             if retry == 0 {
+                if deferrable && PersistentStore::defer_put(&key, &info.inner) {
+                    warn!(
+                        "{self:?}: failed to persist nexus information, \
+                        the state will be saved once the store is back: {err}"
+                    );
+                    return Ok(());
+                }
                 return Err(Error::SaveStateFailed {
                     source: err,
                     name: self.name.clone(),
@@ -242,3 +416,11 @@ impl<'n> Nexus<'n> {
         }
     }
 }
+
+/// Returns the reason the given child is faulted, if it is.
+fn child_fault_reason(child: &NexusChild) -> Option<FaultReason> {
+    match child.state() {
+        ChildState::Faulted(reason) => Some(reason),
+        _ => None,
+    }
+}
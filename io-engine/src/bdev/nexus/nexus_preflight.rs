@@ -0,0 +1,286 @@
+//! Validation of a nexus configuration before creating the nexus.
+//!
+//! The preflight opens each proposed child and checks that it is reachable,
+//! large enough for the nexus, of the same block size as the other children,
+//! and not reserved by another host in a way the nexus could not take over.
+//! Nothing is created: the devices of the children which did not exist are
+//! destroyed once checked, and no reservation is registered.
+use std::sync::atomic::Ordering;
+
+use serde::Serialize;
+
+use super::{
+    nexus_child::nvme_resv_holder,
+    nexus_iter,
+    ChildError,
+    NexusNvmeParams,
+    NexusNvmePreemption,
+    NvmeReservation,
+    ENABLE_NVMF_RESERVATIONS,
+};
+use crate::{
+    bdev::{
+        device_create,
+        device_destroy,
+        device_lookup,
+        device_open,
+        uri::same_device,
+    },
+    bdev_api::bdev_get_name,
+    core::{partition, CoreError},
+};
+
+/// NVMe reservation found on a child.
+#[derive(Debug, Clone, Serialize)]
+pub struct NexusPreflightReservation {
+    /// Type of the reservation.
+    pub resv_type: u8,
+    /// Key of the reservation holder.
+    pub key: u64,
+    /// Host id of the reservation holder.
+    pub host_id: [u8; 16],
+    /// Whether the nexus could not acquire the reservation.
+    pub conflict: bool,
+}
+
+/// Result of the preflight of a child.
+#[derive(Debug, Clone, Serialize)]
+pub struct NexusChildPreflight {
+    /// URI of the child.
+    pub uri: String,
+    /// Whether the device of the child could be opened.
+    pub reachable: bool,
+    /// Size of the device, in blocks.
+    pub num_blocks: u64,
+    /// Block size of the device, in bytes.
+    pub block_size: u64,
+    /// Reservation held on the device, if any.
+    pub reservation: Option<NexusPreflightReservation>,
+    /// Problems found, empty if the child can be used.
+    pub errors: Vec<String>,
+}
+
+impl NexusChildPreflight {
+    fn new(uri: &str) -> Self {
+        Self {
+            uri: uri.to_string(),
+            reachable: false,
+            num_blocks: 0,
+            block_size: 0,
+            reservation: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Checks if the child can be used.
+    pub fn ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Result of the preflight of a nexus configuration.
+#[derive(Debug, Clone, Serialize)]
+pub struct NexusPreflight {
+    /// Block size the nexus would have, zero if unknown.
+    pub block_size: u64,
+    /// Results of the children.
+    pub children: Vec<NexusChildPreflight>,
+    /// Problems of the whole configuration.
+    pub errors: Vec<String>,
+}
+
+impl NexusPreflight {
+    /// Checks if a nexus can be created with the configuration.
+    pub fn ok(&self) -> bool {
+        self.errors.is_empty() && self.children.iter().all(|c| c.ok())
+    }
+}
+
+/// Checks if a nexus of the given size, with the given children and NVMe
+/// parameters, can be created.
+pub async fn nexus_preflight(
+    size: u64,
+    children: &[String],
+    nvme_params: &NexusNvmeParams,
+) -> NexusPreflight {
+    let mut preflight = NexusPreflight {
+        block_size: nvme_params.block_size,
+        children: Vec::with_capacity(children.len()),
+        errors: Vec::new(),
+    };
+
+    if children.is_empty() {
+        preflight.errors.push("no children".to_string());
+    }
+    if !nvme_params.block_size_valid() {
+        preflight
+            .errors
+            .push(format!("invalid block size {}", nvme_params.block_size));
+    }
+
+    for uri in children {
+        let mut child = NexusChildPreflight::new(uri);
+        if children.iter().filter(|u| same_device(u, uri)).count() > 1 {
+            child.errors.push("child given more than once".to_string());
+        }
+        if let Some(nexus) = nexus_iter().find(|n| n.contains_child_uri(uri)) {
+            child
+                .errors
+                .push(format!("child of nexus {}", nexus.uuid()));
+        } else {
+            preflight_child(&mut child, size, nvme_params).await;
+        }
+        preflight.children.push(child);
+    }
+
+    // All children must have the block size of the nexus, which is the one
+    // of the first reachable child unless given.
+    if preflight.block_size == 0 {
+        preflight.block_size = preflight
+            .children
+            .iter()
+            .find(|c| c.reachable)
+            .map_or(0, |c| c.block_size);
+    }
+    for child in preflight.children.iter_mut().filter(|c| c.reachable) {
+        if child.block_size != preflight.block_size {
+            child.errors.push(format!(
+                "block size of {} bytes, expected {} bytes",
+                child.block_size, preflight.block_size
+            ));
+        }
+    }
+
+    preflight
+}
+
+/// Checks a child which is not part of any nexus, creating its device for
+/// the time of the checks if it does not exist.
+async fn preflight_child(
+    child: &mut NexusChildPreflight,
+    size: u64,
+    nvme_params: &NexusNvmeParams,
+) {
+    let name = match bdev_get_name(&child.uri) {
+        Ok(name) => name,
+        Err(error) => {
+            child.errors.push(error.to_string());
+            return;
+        }
+    };
+
+    let created = if device_lookup(&name).is_some() {
+        false
+    } else {
+        if let Err(error) = device_create(&child.uri).await {
+            child.errors.push(format!("unreachable: {error}"));
+            return;
+        }
+        true
+    };
+
+    if let Err(error) = check_device(child, &name, size, nvme_params).await {
+        child.errors.push(error);
+    }
+
+    if created {
+        if let Err(error) = device_destroy(&child.uri).await {
+            warn!(
+                "Nexus preflight: failed to destroy the device of child {}: \
+                {error}",
+                child.uri
+            );
+        }
+    }
+}
+
+/// Checks the size and the reservations of the device of a child.
+async fn check_device(
+    child: &mut NexusChildPreflight,
+    name: &str,
+    size: u64,
+    nvme_params: &NexusNvmeParams,
+) -> Result<(), String> {
+    let desc = device_open(name, false)
+        .map_err(|error| format!("cannot be opened: {error}"))?;
+    child.reachable = true;
+
+    let device = desc.get_device();
+    child.num_blocks = device.num_blocks();
+    child.block_size = device.block_len();
+
+    if partition::calc_data_partition(size, child.num_blocks, child.block_size)
+        .is_none()
+    {
+        child.errors.push(format!(
+            "too small: {} blocks of {} bytes",
+            child.num_blocks, child.block_size
+        ));
+    }
+
+    if !ENABLE_NVMF_RESERVATIONS.load(Ordering::SeqCst)
+        || !nvme_params.reservations_enabled()
+    {
+        return Ok(());
+    }
+
+    let hdl = desc
+        .get_io_handle_nonblock()
+        .await
+        .map_err(|error| format!("cannot be opened for I/O: {error}"))?;
+    match nvme_resv_holder(&*hdl).await {
+        Ok(Some((resv_type, key, host_id))) => {
+            let conflict = resv_conflict(resv_type, key, nvme_params);
+            if conflict {
+                child.errors.push(format!(
+                    "reserved by another host with key {key:#x}"
+                ));
+            }
+            child.reservation = Some(NexusPreflightReservation {
+                resv_type,
+                key,
+                host_id,
+                conflict,
+            });
+            Ok(())
+        }
+        Ok(None)
+        | Err(ChildError::ResvReport {
+            source: CoreError::NotSupported {
+                ..
+            },
+        }) => Ok(()),
+        Err(error) => Err(format!("reservation report failed: {error}")),
+    }
+}
+
+/// Checks if a reservation held with the given type and key would prevent
+/// the nexus from acquiring the reservation of a child.
+fn resv_conflict(
+    resv_type: u8,
+    key: u64,
+    nvme_params: &NexusNvmeParams,
+) -> bool {
+    let shared = |t| {
+        matches!(
+            t,
+            NvmeReservation::ExclusiveAccessAllRegs
+                | NvmeReservation::WriteExclusiveAllRegs
+        )
+    };
+
+    if key == nvme_params.resv_key {
+        return false;
+    }
+    if NvmeReservation::try_from(resv_type).map_or(false, shared)
+        && shared(nvme_params.resv_type)
+    {
+        return false;
+    }
+    match nvme_params.preempt_policy {
+        NexusNvmePreemption::Holder => false,
+        NexusNvmePreemption::ArgKey => {
+            nvme_params.preempt_key.map(|k| k.get()) != Some(key)
+        }
+    }
+}
@@ -0,0 +1,614 @@
+//! Implements the read cache of a nexus on a local device.
+//!
+//! A nexus whose children are all remote can have a local fast device
+//! attached as a read cache. The cache device is divided into fixed size
+//! lines, each holding a copy of an aligned range of the nexus. Reads which
+//! fall within a cached line are served by the cache device; other reads are
+//! served by the children, and the lines they cover are queued to be filled
+//! in the background. Writes, unmaps and write-zeroes invalidate the lines
+//! they cover. When the cache is full, the least recently used line is
+//! evicted.
+//!
+//! Lines are filled on the primary reactor, from a healthy child, under the
+//! nexus LBA range lock, so that a fill cannot race with a write. The index
+//! of the cache is kept in memory only: the cache starts empty each time it
+//! is attached.
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
+use serde::Serialize;
+use snafu::ResultExt;
+use spdk_rs::LbaRange;
+
+use super::{nexus_err, nexus_lookup_mut, Error, Nexus};
+use crate::{
+    bdev::{device_create, device_destroy, device_open},
+    core::{
+        BlockDeviceDescriptor,
+        BlockDeviceHandle,
+        CoreError,
+        Reactors,
+        UntypedBdev,
+    },
+};
+
+/// Default size of a read cache line, in bytes.
+pub const NEXUS_READ_CACHE_DEFAULT_LINE_SIZE: u64 = 128 * 1024;
+
+/// Statistics of a nexus read cache.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct NexusReadCacheStats {
+    /// URI of the cache device.
+    pub device: String,
+    /// Size of a cache line, in bytes.
+    pub line_size: u64,
+    /// Number of lines the cache device holds.
+    pub capacity_lines: u64,
+    /// Number of lines currently cached.
+    pub cached_lines: u64,
+    /// Reads served by the cache device.
+    pub hits: u64,
+    /// Reads served by the children.
+    pub misses: u64,
+    /// Lines filled.
+    pub fills: u64,
+    /// Lines which failed to fill.
+    pub fill_errors: u64,
+    /// Lines evicted to make room for others.
+    pub evictions: u64,
+    /// Lines invalidated by writes.
+    pub invalidations: u64,
+}
+
+impl NexusReadCacheStats {
+    /// Returns the ratio of reads served by the cache device.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// Outcome of a read cache lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CacheLookup {
+    /// The blocks are cached: read them from the cache device at the given
+    /// block, and unpin the given slot once done.
+    Hit { slot: u32, blk: u64 },
+    /// The blocks are not cached.
+    Miss,
+}
+
+#[derive(Debug, Default)]
+struct ReadCacheInner {
+    /// Slot of the cached lines, by line number.
+    lines: HashMap<u64, u32>,
+    /// Line held by each slot, if any.
+    slots: Vec<Option<u64>>,
+    /// Number of reads in flight from each slot. A pinned slot cannot be
+    /// reused.
+    pins: Vec<u32>,
+    /// Last access tick of each slot in use.
+    ticks: Vec<u64>,
+    /// Slots in use, by last access tick: the first one is the least
+    /// recently used.
+    lru: BTreeMap<u64, u32>,
+    /// Free slots.
+    free: Vec<u32>,
+    /// Lines queued to be filled.
+    pending: HashSet<u64>,
+    /// Access tick counter.
+    tick: u64,
+}
+
+impl ReadCacheInner {
+    /// Marks the given slot as just used.
+    fn touch(&mut self, slot: u32) {
+        let s = slot as usize;
+        self.lru.remove(&self.ticks[s]);
+        self.tick += 1;
+        self.ticks[s] = self.tick;
+        self.lru.insert(self.tick, slot);
+    }
+
+    /// Releases the given slot, dropping the line it holds.
+    fn drop_slot(&mut self, slot: u32) {
+        let s = slot as usize;
+        if let Some(line) = self.slots[s].take() {
+            self.lines.remove(&line);
+            self.lru.remove(&self.ticks[s]);
+            self.free.push(slot);
+        }
+    }
+}
+
+/// Read cache of a nexus, shared with its I/O channels.
+#[derive(Debug)]
+pub(crate) struct NexusReadCache {
+    /// URI of the cache device.
+    device: String,
+    /// Size of a cache line, in blocks.
+    line_blks: u64,
+    /// Block size, in bytes.
+    block_len: u64,
+    inner: parking_lot::Mutex<ReadCacheInner>,
+    /// Queue of the lines to fill.
+    fill_queue: mpsc::UnboundedSender<u64>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    fills: AtomicU64,
+    fill_errors: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl NexusReadCache {
+    fn new(
+        device: &str,
+        line_blks: u64,
+        block_len: u64,
+        num_slots: u32,
+        fill_queue: mpsc::UnboundedSender<u64>,
+    ) -> Self {
+        let n = num_slots as usize;
+        Self {
+            device: device.to_string(),
+            line_blks,
+            block_len,
+            inner: parking_lot::Mutex::new(ReadCacheInner {
+                slots: vec![None; n],
+                pins: vec![0; n],
+                ticks: vec![0; n],
+                free: (0 .. num_slots).rev().collect(),
+                ..Default::default()
+            }),
+            fill_queue,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            fills: AtomicU64::new(0),
+            fill_errors: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the lines covering the given blocks.
+    fn lines(&self, offset: u64, num_blocks: u64) -> std::ops::Range<u64> {
+        offset / self.line_blks
+            .. (offset + num_blocks + self.line_blks - 1) / self.line_blks
+    }
+
+    /// Looks up a read of the given blocks. Reads are served by the cache
+    /// device if they fall within a single cached line. Otherwise, the lines
+    /// covered by the read are queued to be filled.
+    pub(super) fn lookup(&self, offset: u64, num_blocks: u64) -> CacheLookup {
+        let lines = self.lines(offset, num_blocks);
+        let mut inner = self.inner.lock();
+
+        if lines.end - lines.start == 1 {
+            if let Some(slot) = inner.lines.get(&lines.start).copied() {
+                inner.pins[slot as usize] += 1;
+                inner.touch(slot);
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return CacheLookup::Hit {
+                    slot,
+                    blk: slot as u64 * self.line_blks + offset % self.line_blks,
+                };
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        for line in lines {
+            if !inner.lines.contains_key(&line) && inner.pending.insert(line) {
+                // The receiver goes away only when the cache is detached.
+                self.fill_queue.unbounded_send(line).ok();
+            }
+        }
+        CacheLookup::Miss
+    }
+
+    /// Unpins a slot once a read from it completes.
+    pub(super) fn unpin(&self, slot: u32) {
+        if let Some(pins) = self.inner.lock().pins.get_mut(slot as usize) {
+            *pins = pins.saturating_sub(1);
+        }
+    }
+
+    /// Invalidates the lines covering the given blocks.
+    pub(super) fn invalidate(&self, offset: u64, num_blocks: u64) {
+        let lines = self.lines(offset, num_blocks);
+        let mut inner = self.inner.lock();
+
+        for line in lines {
+            if let Some(slot) = inner.lines.get(&line).copied() {
+                inner.drop_slot(slot);
+                self.invalidations.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Allocates a slot to fill the given line, evicting the least recently
+    /// used unpinned line if needed. Returns None if the line does not need
+    /// filling anymore, or no slot is available.
+    fn alloc_slot(&self, line: u64) -> Option<u32> {
+        let mut inner = self.inner.lock();
+        if inner.lines.contains_key(&line) {
+            return None;
+        }
+
+        // Slots invalidated while being read from are still pinned.
+        if let Some(i) =
+            inner.free.iter().position(|s| inner.pins[*s as usize] == 0)
+        {
+            return Some(inner.free.swap_remove(i));
+        }
+
+        let victim = inner
+            .lru
+            .values()
+            .copied()
+            .find(|s| inner.pins[*s as usize] == 0)?;
+        inner.drop_slot(victim);
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+        inner.free.pop()
+    }
+
+    /// Completes the fill of a line. If filled, the line is cached in the
+    /// given slot; otherwise the slot is released.
+    fn fill_done(&self, line: u64, slot: Option<u32>, filled: bool) {
+        let mut inner = self.inner.lock();
+        inner.pending.remove(&line);
+
+        let Some(slot) = slot else {
+            return;
+        };
+
+        if filled {
+            inner.slots[slot as usize] = Some(line);
+            inner.lines.insert(line, slot);
+            inner.touch(slot);
+            self.fills.fetch_add(1, Ordering::Relaxed);
+        } else {
+            inner.free.push(slot);
+        }
+    }
+
+    /// Returns the statistics of the cache.
+    pub(crate) fn stats(&self) -> NexusReadCacheStats {
+        let inner = self.inner.lock();
+        NexusReadCacheStats {
+            device: self.device.clone(),
+            line_size: self.line_blks * self.block_len,
+            capacity_lines: inner.slots.len() as u64,
+            cached_lines: inner.lines.len() as u64,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            fills: self.fills.load(Ordering::Relaxed),
+            fill_errors: self.fill_errors.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Read cache device attached to a nexus.
+pub(super) struct NexusReadCacheDevice {
+    /// Cache shared with the I/O channels.
+    pub(super) cache: Arc<NexusReadCache>,
+    /// Descriptor of the cache device.
+    pub(super) descriptor: Box<dyn BlockDeviceDescriptor>,
+    /// Completes when the fill worker exits.
+    worker_done: oneshot::Receiver<()>,
+}
+
+impl std::fmt::Debug for NexusReadCacheDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "read cache on '{}'", self.cache.device)
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Attaches a local device as a read cache of the nexus. All the children
+    /// of the nexus must be remote.
+    pub async fn attach_read_cache(
+        mut self: Pin<&mut Self>,
+        uri: &str,
+        line_size: u64,
+    ) -> Result<(), Error> {
+        if self.read_cache.is_some() {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "nexus '{}' already has a read cache",
+                    self.name
+                ),
+            });
+        }
+
+        if self.children_iter().any(|c| c.is_local() != Some(false)) {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "nexus '{}' has local or closed children",
+                    self.name
+                ),
+            });
+        }
+
+        let block_len = self.block_len();
+        if line_size == 0 || line_size % block_len != 0 {
+            return Err(Error::InvalidArguments {
+                name: self.name.clone(),
+                args: format!(
+                    "read cache line size {line_size} is not a multiple of \
+                    the block size {block_len}"
+                ),
+            });
+        }
+        let line_blks = line_size / block_len;
+
+        let name =
+            device_create(uri)
+                .await
+                .context(nexus_err::CreateReadCache {
+                    name: self.name.clone(),
+                    device: uri.to_string(),
+                })?;
+
+        let (done_tx, done_rx) = oneshot::channel();
+        let (cache_device, handle, fill_rx) =
+            match self.open_read_cache(uri, &name, line_blks, done_rx) {
+                Ok(r) => r,
+                Err(error) => {
+                    device_destroy(uri).await.ok();
+                    return Err(error);
+                }
+            };
+        let cache = cache_device.cache.clone();
+
+        // Connect the I/O channels before any line is filled, so that all
+        // writes invalidate the cached lines.
+        unsafe {
+            self.as_mut().unpin_mut().read_cache = Some(cache_device);
+        }
+        self.traverse_io_channels_async((), |channel, _| {
+            channel.connect_read_cache();
+        })
+        .await;
+
+        Reactors::master().send_future(fill_worker(
+            self.name.clone(),
+            cache,
+            handle,
+            fill_rx,
+            done_tx,
+        ));
+
+        info!(
+            "{self:?}: read cache attached on '{uri}', {line_size} byte lines"
+        );
+        Ok(())
+    }
+
+    /// Opens the cache device and makes the cache.
+    #[allow(clippy::type_complexity)]
+    fn open_read_cache(
+        &self,
+        uri: &str,
+        name: &str,
+        line_blks: u64,
+        worker_done: oneshot::Receiver<()>,
+    ) -> Result<
+        (
+            NexusReadCacheDevice,
+            Box<dyn BlockDeviceHandle>,
+            mpsc::UnboundedReceiver<u64>,
+        ),
+        Error,
+    > {
+        let descriptor =
+            device_open(name, true).context(nexus_err::OpenReadCache {
+                name: self.name.clone(),
+                device: uri.to_string(),
+            })?;
+
+        let device = descriptor.get_device();
+        if device.driver_name() == "nvme" {
+            return Err(Error::OperationNotAllowed {
+                reason: format!("read cache device '{uri}' is not local"),
+            });
+        }
+        if device.block_len() != self.block_len() {
+            return Err(Error::MixedBlockSizes {
+                name: self.name.clone(),
+            });
+        }
+
+        let num_slots = (device.num_blocks() / line_blks).min(u32::MAX as u64);
+        if num_slots == 0 {
+            return Err(Error::InvalidArguments {
+                name: self.name.clone(),
+                args: format!("read cache device '{uri}' is too small"),
+            });
+        }
+
+        let handle =
+            descriptor
+                .get_io_handle()
+                .context(nexus_err::OpenReadCache {
+                    name: self.name.clone(),
+                    device: uri.to_string(),
+                })?;
+
+        let (fill_tx, fill_rx) = mpsc::unbounded();
+        let cache = Arc::new(NexusReadCache::new(
+            uri,
+            line_blks,
+            self.block_len(),
+            num_slots as u32,
+            fill_tx,
+        ));
+
+        Ok((
+            NexusReadCacheDevice {
+                cache,
+                descriptor,
+                worker_done,
+            },
+            handle,
+            fill_rx,
+        ))
+    }
+
+    /// Detaches the read cache of the nexus, if any, and destroys the cache
+    /// device.
+    pub async fn detach_read_cache(mut self: Pin<&mut Self>) {
+        let Some(d) = unsafe { self.as_mut().unpin_mut() }.read_cache.take()
+        else {
+            return;
+        };
+
+        self.traverse_io_channels_async((), |channel, _| {
+            channel.connect_read_cache();
+        })
+        .await;
+
+        // Stop the fill worker, and wait for it to release the device.
+        d.cache.fill_queue.close_channel();
+        d.worker_done.await.ok();
+
+        let uri = d.cache.device.clone();
+        drop(d);
+        if let Err(error) = device_destroy(&uri).await {
+            warn!("{self:?}: failed to destroy read cache '{uri}': {error}");
+        }
+
+        info!("{self:?}: read cache detached from '{uri}'");
+    }
+
+    /// Returns the read cache device of the nexus, if any.
+    pub(super) fn read_cache_device(&self) -> Option<&NexusReadCacheDevice> {
+        self.read_cache.as_ref()
+    }
+
+    /// Returns the statistics of the read cache, if any.
+    pub fn read_cache_stats(&self) -> Option<NexusReadCacheStats> {
+        self.read_cache.as_ref().map(|d| d.cache.stats())
+    }
+}
+
+/// Fills the queued lines of a read cache, until the cache is detached.
+async fn fill_worker(
+    nexus_name: String,
+    cache: Arc<NexusReadCache>,
+    handle: Box<dyn BlockDeviceHandle>,
+    mut queue: mpsc::UnboundedReceiver<u64>,
+    done: oneshot::Sender<()>,
+) {
+    while let Some(line) = queue.next().await {
+        let Some(nexus) = nexus_lookup_mut(&nexus_name) else {
+            cache.fill_done(line, None, false);
+            continue;
+        };
+
+        if let Err(error) =
+            fill_line(&nexus, &cache, handle.as_ref(), line).await
+        {
+            cache.fill_errors.fetch_add(1, Ordering::Relaxed);
+            debug!("{nexus:?}: failed to fill read cache line {line}: {error}");
+        }
+    }
+
+    done.send(()).ok();
+}
+
+/// Fills a line of a read cache from a healthy child, under the nexus LBA
+/// range lock.
+async fn fill_line(
+    nexus: &Nexus<'_>,
+    cache: &NexusReadCache,
+    handle: &dyn BlockDeviceHandle,
+    line: u64,
+) -> Result<(), CoreError> {
+    // Lines are in the block space of the children, which have a metadata
+    // partition, whereas the range lock is in the block space of the nexus.
+    let blk = line * cache.line_blks;
+    let nexus_blk = match blk.checked_sub(nexus.data_ent_offset) {
+        Some(b) if b + cache.line_blks <= nexus.num_blocks() => b,
+        _ => {
+            // Partial lines at the end of the nexus are not cached.
+            cache.fill_done(line, None, false);
+            return Ok(());
+        }
+    };
+
+    let slot = cache.alloc_slot(line);
+    if slot.is_none() {
+        cache.fill_done(line, None, false);
+        return Ok(());
+    }
+
+    let desc = UntypedBdev::open_by_name(&nexus.bdev_name(), false)?;
+    let lock = match desc
+        .lock_lba_range(LbaRange::new(nexus_blk, cache.line_blks))
+        .await
+    {
+        Ok(lock) => lock,
+        Err(error) => {
+            warn!("{nexus:?}: failed to lock read cache line {line}: {error}");
+            cache.fill_done(line, slot, false);
+            return Ok(());
+        }
+    };
+
+    let result = copy_line(nexus, cache, handle, blk, slot.unwrap()).await;
+
+    desc.unlock_lba_range(lock).await.ok();
+
+    cache.fill_done(line, slot, matches!(result, Ok(true)));
+    result.map(|_| ())
+}
+
+/// Copies a line from a healthy child to the given slot of the cache device.
+/// Returns false if the line must not be cached.
+async fn copy_line(
+    nexus: &Nexus<'_>,
+    cache: &NexusReadCache,
+    handle: &dyn BlockDeviceHandle,
+    blk: u64,
+    slot: u32,
+) -> Result<bool, CoreError> {
+    // Cached writes which have not reached the children yet.
+    if let Some(wc) = nexus.write_cache() {
+        if wc.overlaps(blk, cache.line_blks) {
+            return Ok(false);
+        }
+    }
+
+    let Some(child) = nexus.children_iter().find(|c| c.is_healthy()) else {
+        return Ok(false);
+    };
+    let reader = child.get_io_handle()?;
+
+    let len = cache.line_blks * cache.block_len;
+    let mut buf =
+        handle
+            .dma_malloc(len)
+            .map_err(|_| CoreError::DmaAllocationFailed {
+                size: len,
+            })?;
+
+    reader.read_at(blk * cache.block_len, &mut buf).await?;
+    handle.write_at(slot as u64 * len, &buf).await?;
+
+    Ok(true)
+}
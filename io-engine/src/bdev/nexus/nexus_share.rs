@@ -1,11 +1,14 @@
 use crate::bdev::PtplFileOps;
 use async_trait::async_trait;
 use snafu::ResultExt;
-use std::pin::Pin;
+use std::{net::Ipv4Addr, pin::Pin};
 
 use super::{nexus_err, Error, NbdDisk, Nexus, NexusTarget};
 
-use crate::core::{Protocol, Share, ShareProps, UpdateProps};
+use crate::{
+    core::{NvmeIdentity, Protocol, Share, ShareProps, UpdateProps},
+    subsys::{Config, NvmfSubsystem},
+};
 
 ///
 /// The sharing of the nexus is different compared to regular bdevs
@@ -120,15 +123,18 @@ impl<'n> Nexus<'n> {
         protocol: Protocol,
         key: Option<String>,
     ) -> Result<String, Error> {
-        self.share_ext(protocol, key, vec![]).await
+        self.share_ext(protocol, key, vec![], NvmeIdentity::default())
+            .await
     }
 
-    /// TODO
+    /// Shares the nexus with the given allowed hosts, and the given NVMe
+    /// identification when shared over NVMe-oF.
     pub async fn share_ext(
         mut self: Pin<&mut Self>,
         protocol: Protocol,
         _key: Option<String>,
         allowed_hosts: Vec<String>,
+        identity: NvmeIdentity,
     ) -> Result<String, Error> {
         // This function should be idempotent as it's possible that
         // we get called more than once for some odd reason.
@@ -177,6 +183,7 @@ impl<'n> Nexus<'n> {
                     )))
                     .with_ana(true)
                     .with_allowed_hosts(allowed_hosts)
+                    .with_identity(identity)
                     .with_ptpl(self.ptpl().create().map_err(|source| {
                         Error::ShareNvmfNexus {
                             source: crate::core::CoreError::Ptpl {
@@ -196,6 +203,51 @@ impl<'n> Nexus<'n> {
         }
     }
 
+    /// Adds a listener on the given address, formatted as `ip[:port]`, to the
+    /// NVMe-oF target of the published nexus. Its other listeners are removed
+    /// if `replace` is set, which moves the target to the new address without
+    /// recreating it. Returns the new share URI of the nexus.
+    pub async fn update_listener(
+        &self,
+        address: &str,
+        replace: bool,
+    ) -> Result<String, Error> {
+        let Some(NexusTarget::NexusNvmfTarget) = self.nexus_target else {
+            return Err(Error::NotSharedNvmf {
+                name: self.name.clone(),
+            });
+        };
+        let Some(subsystem) = NvmfSubsystem::nqn_lookup(&self.name) else {
+            return Err(Error::NotSharedNvmf {
+                name: self.name.clone(),
+            });
+        };
+
+        let invalid = |reason: &str| Error::InvalidArguments {
+            name: self.name.clone(),
+            args: format!("listener address '{address}' {reason}"),
+        };
+        let (ip, port) = match address.rsplit_once(':') {
+            Some((ip, port)) => (
+                ip,
+                port.parse::<u16>()
+                    .map_err(|_| invalid("has an invalid port"))?,
+            ),
+            None => (address, Config::get().nexus_opts.nvmf_replica_port),
+        };
+        ip.parse::<Ipv4Addr>()
+            .map_err(|_| invalid("is not an IPv4 address"))?;
+
+        info!(
+            "{self:?}: updating NVMF listener to {ip}:{port}, \
+            replace: {replace}"
+        );
+
+        subsystem.update_listener(ip, port, replace).await?;
+
+        Ok(self.get_share_uri().unwrap_or_default())
+    }
+
     /// TODO
     pub async fn unshare_nexus(mut self: Pin<&mut Self>) -> Result<(), Error> {
         match unsafe { self.as_mut().get_unchecked_mut().nexus_target.take() } {
@@ -205,6 +257,7 @@ impl<'n> Nexus<'n> {
             }
             Some(NexusTarget::NexusNvmfTarget) => {
                 info!("{:?}: unsharing NVMF target...", self);
+                self.set_ana_group(None).ok();
             }
             None => {
                 // Try unshare nexus bdev anyway, just in case it was shared
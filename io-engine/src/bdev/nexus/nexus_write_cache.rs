@@ -0,0 +1,555 @@
+//! Implements the opt-in volatile write cache of a nexus.
+//!
+//! With the cache enabled, a write is copied into a DMA buffer and completed
+//! to the client right away, while the copy is written to the children in the
+//! background. Client I/Os overlapping a cached write which has not reached
+//! the children yet are parked on their I/O channel until it has, so that
+//! reads always return the latest data and overlapping writes reach the
+//! children in order. A flush completes once all writes cached before it have
+//! reached the children, and fails if any of them failed on all children
+//! since the previous flush. Writes with the FUA bit set bypass the cache:
+//! they are written through, and flush the children before completing.
+//!
+//! The cache is split into a shard per core, each holding a share of the
+//! capacity. A write is cached in the shard of the core it is submitted on,
+//! and written to the children from that core, so that the data path of a
+//! core only contends with the other cores when they look for overlapping
+//! writes in a shard which is not empty.
+//!
+//! Cached writes are lost if the nexus goes away before they reach the
+//! children: the cache is meant for volumes which accept relaxed durability,
+//! and is reported to the hosts as a volatile write cache.
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crossbeam::atomic::AtomicCell;
+use libc::c_void;
+use serde::Serialize;
+use spdk_rs::{libspdk::spdk_get_ticks, DmaBuf, IoVec};
+
+use super::{nexus_lookup_mut, Error, FaultReason, Nexus};
+use crate::{
+    core::{BlockDevice, Cores, IoCompletionStatus, IoType, Reactors},
+    sleep::mayastor_sleep,
+};
+
+/// Default capacity of a nexus write cache, in bytes.
+pub const NEXUS_WRITE_CACHE_DEFAULT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Maximum time to wait for the cached writes to reach the children, when
+/// the nexus is destroyed.
+const NEXUS_WRITE_CACHE_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Outcome of an attempt to cache a write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CacheInsert {
+    /// The write is cached in the shard of the given core, with the given
+    /// sequence number.
+    Cached(u32, u64),
+    /// The write overlaps a cached write, and must wait for it.
+    Overlap,
+    /// The cache is full: the write goes straight to the children.
+    Full,
+}
+
+/// Cached write which has not reached the children yet.
+#[derive(Debug, Clone, Copy)]
+struct CachedWrite {
+    /// Sequence number of the write.
+    seq: u64,
+    /// Number of blocks.
+    num_blocks: u64,
+}
+
+#[derive(Debug, Default)]
+struct ShardInner {
+    /// Cached writes by starting block. Cached writes of a shard never
+    /// overlap.
+    writes: BTreeMap<u64, CachedWrite>,
+    /// Starting block of the cached writes by sequence number.
+    seqs: BTreeMap<u64, u64>,
+    /// Number of cached bytes.
+    bytes: u64,
+    /// Sequence number of the last cached write.
+    last_seq: u64,
+    /// Set when an I/O is parked waiting for cached writes of the shard.
+    waiters: bool,
+}
+
+impl ShardInner {
+    /// Checks if the given blocks overlap a cached write.
+    fn overlaps(&self, offset: u64, num_blocks: u64) -> bool {
+        self.writes
+            .range(.. offset + num_blocks)
+            .next_back()
+            .map_or(false, |(start, w)| start + w.num_blocks > offset)
+    }
+}
+
+/// Writes cached by a core. Only the owning core adds and removes writes;
+/// the other cores lock the shard to look for overlapping writes, and only
+/// when it is not empty.
+#[derive(Debug)]
+struct WriteCacheShard {
+    /// Core owning the shard.
+    core: u32,
+    inner: parking_lot::Mutex<ShardInner>,
+    /// Number of cached bytes, readable without the lock.
+    dirty: AtomicU64,
+    cached_writes: AtomicU64,
+    bypassed_writes: AtomicU64,
+    waits: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl WriteCacheShard {
+    fn new(core: u32) -> Self {
+        Self {
+            core,
+            inner: parking_lot::Mutex::new(ShardInner::default()),
+            dirty: AtomicU64::new(0),
+            cached_writes: AtomicU64::new(0),
+            bypassed_writes: AtomicU64::new(0),
+            waits: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Checks if no write is cached in the shard.
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.dirty.load(Ordering::Acquire) == 0
+    }
+}
+
+/// Statistics of a nexus write cache.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct NexusWriteCacheStats {
+    /// Capacity of the cache, in bytes.
+    pub capacity: u64,
+    /// Bytes cached and not yet written to the children.
+    pub dirty_bytes: u64,
+    /// Writes completed from the cache.
+    pub cached_writes: u64,
+    /// Writes sent straight to the children because the cache was full.
+    pub bypassed_writes: u64,
+    /// I/Os which waited for overlapping cached writes.
+    pub waits: u64,
+    /// Cached writes which failed on all children.
+    pub errors: u64,
+}
+
+/// Volatile write cache of a nexus.
+#[derive(Debug)]
+pub(crate) struct NexusWriteCache {
+    /// Name of the nexus.
+    nexus_name: String,
+    /// Block size of the nexus.
+    block_len: u64,
+    /// Capacity, in bytes.
+    capacity: u64,
+    /// Capacity of each shard, in bytes.
+    shard_capacity: u64,
+    /// Shards, one per core.
+    shards: Vec<WriteCacheShard>,
+    /// Set when a cached write failed on all children since the last flush.
+    error: AtomicCell<bool>,
+    /// Set when resubmission of the parked I/Os is scheduled.
+    resubmit_scheduled: AtomicCell<bool>,
+}
+
+impl NexusWriteCache {
+    fn new(nexus_name: &str, block_len: u64, capacity: u64) -> Self {
+        let shards = Cores::count()
+            .into_iter()
+            .map(WriteCacheShard::new)
+            .collect::<Vec<_>>();
+        Self {
+            nexus_name: nexus_name.to_string(),
+            block_len,
+            capacity,
+            shard_capacity: capacity / shards.len().max(1) as u64,
+            shards,
+            error: AtomicCell::new(false),
+            resubmit_scheduled: AtomicCell::new(false),
+        }
+    }
+
+    /// Returns the shard of the given core.
+    fn shard(&self, core: u32) -> &WriteCacheShard {
+        self.shards
+            .iter()
+            .find(|s| s.core == core)
+            .unwrap_or(&self.shards[0])
+    }
+
+    /// Tries to cache a write of the given blocks in the shard of the
+    /// current core. A write completed to the client is in the cache until it
+    /// reaches the children, so any later write overlapping it waits for it;
+    /// only writes which the client submitted concurrently may overlap in
+    /// different shards, and they have no order to keep.
+    pub(super) fn insert(&self, offset: u64, num_blocks: u64) -> CacheInsert {
+        if self.must_wait(offset, num_blocks) {
+            return CacheInsert::Overlap;
+        }
+
+        let bytes = num_blocks * self.block_len;
+        let shard = self.shard(Cores::current());
+        let mut inner = shard.inner.lock();
+
+        if inner.bytes + bytes > self.shard_capacity {
+            shard.bypassed_writes.fetch_add(1, Ordering::Relaxed);
+            return CacheInsert::Full;
+        }
+
+        // Sequence numbers follow the time stamp counter, which all cores
+        // share, so that a flush can tell the writes cached before it on
+        // any core.
+        let seq =
+            std::cmp::max(unsafe { spdk_get_ticks() }, inner.last_seq + 1);
+        inner.last_seq = seq;
+        inner.bytes += bytes;
+        inner.writes.insert(
+            offset,
+            CachedWrite {
+                seq,
+                num_blocks,
+            },
+        );
+        inner.seqs.insert(seq, offset);
+        shard.dirty.store(inner.bytes, Ordering::Release);
+        shard.cached_writes.fetch_add(1, Ordering::Relaxed);
+
+        CacheInsert::Cached(shard.core, seq)
+    }
+
+    /// Checks if an I/O to the given blocks must wait for cached writes. If
+    /// so, the I/O must be parked until the cached writes are removed.
+    pub(super) fn must_wait(&self, offset: u64, num_blocks: u64) -> bool {
+        let waiting = self.shards.iter().filter(|s| !s.is_empty()).any(|s| {
+            let mut inner = s.inner.lock();
+            if inner.overlaps(offset, num_blocks) {
+                inner.waiters = true;
+                true
+            } else {
+                false
+            }
+        });
+        if waiting {
+            self.shard(Cores::current())
+                .waits
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        waiting
+    }
+
+    /// Checks if the given blocks overlap a cached write.
+    pub(super) fn overlaps(&self, offset: u64, num_blocks: u64) -> bool {
+        self.shards
+            .iter()
+            .filter(|s| !s.is_empty())
+            .any(|s| s.inner.lock().overlaps(offset, num_blocks))
+    }
+
+    /// Returns the sequence number which the writes cached so far, on any
+    /// core, are below.
+    pub(super) fn flush_seq(&self) -> u64 {
+        unsafe { spdk_get_ticks() + 1 }
+    }
+
+    /// Checks if all writes cached before the given sequence number have
+    /// reached the children. If not, a flush I/O must be parked until they
+    /// have.
+    pub(super) fn must_wait_flush(&self, seq: u64) -> bool {
+        self.shards.iter().filter(|s| !s.is_empty()).any(|s| {
+            let mut inner = s.inner.lock();
+            if inner.seqs.range(.. seq).next().is_some() {
+                inner.waiters = true;
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Removes a cached write of the given core which reached the children,
+    /// or failed to, and resubmits the I/Os waiting for cached writes.
+    pub(super) fn release(self: &Arc<Self>, core: u32, seq: u64, failed: bool) {
+        let shard = self.shard(core);
+        if failed {
+            shard.errors.fetch_add(1, Ordering::Relaxed);
+            self.error.store(true);
+        }
+
+        let waiters = {
+            let mut inner = shard.inner.lock();
+            if let Some(offset) = inner.seqs.remove(&seq) {
+                if let Some(w) = inner.writes.remove(&offset) {
+                    inner.bytes -= w.num_blocks * self.block_len;
+                }
+            }
+            shard.dirty.store(inner.bytes, Ordering::Release);
+            std::mem::take(&mut inner.waiters)
+        };
+
+        if waiters {
+            self.schedule_resubmit();
+        }
+    }
+
+    /// Returns and clears the error of the cached writes since the previous
+    /// flush.
+    pub(super) fn take_error(&self) -> bool {
+        self.error.swap(false)
+    }
+
+    /// Checks if no write is cached.
+    fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| s.is_empty())
+    }
+
+    /// Returns the statistics of the cache, summed over the shards.
+    pub(crate) fn stats(&self) -> NexusWriteCacheStats {
+        self.shards.iter().fold(
+            NexusWriteCacheStats {
+                capacity: self.capacity,
+                ..Default::default()
+            },
+            |mut stats, s| {
+                stats.dirty_bytes += s.dirty.load(Ordering::Relaxed);
+                stats.cached_writes += s.cached_writes.load(Ordering::Relaxed);
+                stats.bypassed_writes +=
+                    s.bypassed_writes.load(Ordering::Relaxed);
+                stats.waits += s.waits.load(Ordering::Relaxed);
+                stats.errors += s.errors.load(Ordering::Relaxed);
+                stats
+            },
+        )
+    }
+
+    /// Schedules resubmission of the I/Os parked on all I/O channels of the
+    /// nexus, unless it has been already scheduled.
+    fn schedule_resubmit(self: &Arc<Self>) {
+        if self
+            .resubmit_scheduled
+            .compare_exchange(false, true)
+            .is_err()
+        {
+            return;
+        }
+
+        let cache = self.clone();
+        Reactors::master().send_future(async move {
+            cache.resubmit_scheduled.store(false);
+
+            let Some(nexus) = nexus_lookup_mut(&cache.nexus_name) else {
+                return;
+            };
+
+            nexus
+                .traverse_io_channels_async((), |channel, _| {
+                    channel.resubmit_cache_waiting();
+                })
+                .await;
+        });
+    }
+}
+
+/// Cached write being written to the children. It is written from the core it
+/// was cached on, so that its child I/Os complete, and the write leaves the
+/// cache, on that core.
+pub(super) struct Destage {
+    cache: Arc<NexusWriteCache>,
+    /// Core owning the cached write.
+    core: u32,
+    seq: u64,
+    offset: u64,
+    num_blocks: u64,
+    /// Copy of the client data.
+    _buf: DmaBuf,
+    /// I/O vector of the copy. Must live until all child I/Os complete.
+    iov: IoVec,
+    /// Number of child I/Os in flight, plus one for the submitter.
+    in_flight: usize,
+    /// Number of child I/Os completed successfully.
+    successful: usize,
+}
+
+impl Destage {
+    /// Makes a new destage context for the given cached write, with a
+    /// reference held by the submitter.
+    pub(super) fn new(
+        cache: Arc<NexusWriteCache>,
+        core: u32,
+        seq: u64,
+        offset: u64,
+        num_blocks: u64,
+        buf: DmaBuf,
+    ) -> Box<Self> {
+        debug_assert_eq!(core, Cores::current());
+        Box::new(Self {
+            cache,
+            core,
+            seq,
+            offset,
+            num_blocks,
+            iov: buf.to_io_vec(),
+            _buf: buf,
+            in_flight: 1,
+            successful: 0,
+        })
+    }
+
+    /// Returns the I/O vectors of the cached data.
+    #[inline(always)]
+    pub(super) fn iovs(&self) -> &[IoVec] {
+        std::slice::from_ref(&self.iov)
+    }
+
+    /// Accounts a child I/O submission.
+    #[inline(always)]
+    pub(super) fn submitted(&mut self) {
+        self.in_flight += 1;
+    }
+
+    /// Invoked when a child write of a cached write completes. A failed child
+    /// is faulted, as with a nexus I/O.
+    pub(super) fn child_completion(
+        device: &dyn BlockDevice,
+        status: IoCompletionStatus,
+        ctx: *mut c_void,
+    ) {
+        let destage = ctx as *mut Destage;
+        let this = unsafe { &mut *destage };
+
+        if status == IoCompletionStatus::Success {
+            this.successful += 1;
+        } else {
+            let name = device.device_name();
+            error!(
+                "Nexus '{nexus}': cached write at {offset}/{num} failed on \
+                '{name}': {status:?}",
+                nexus = this.cache.nexus_name,
+                offset = this.offset,
+                num = this.num_blocks,
+            );
+
+            if let Some(log) = nexus_lookup_mut(&this.cache.nexus_name)
+                .and_then(|n| {
+                    n.retire_child_device(&name, FaultReason::IoError, true)
+                })
+            {
+                log.log_io(IoType::Write, this.offset, this.num_blocks);
+            }
+        }
+
+        Self::put(destage);
+    }
+
+    /// Drops a reference to the destage context. When the last one is
+    /// dropped, the write is removed from the cache.
+    pub(super) fn put(destage: *mut Destage) {
+        let this = unsafe { &mut *destage };
+        this.in_flight -= 1;
+        if this.in_flight > 0 {
+            return;
+        }
+
+        let this = unsafe { Box::from_raw(destage) };
+        if this.successful == 0 {
+            error!(
+                "Nexus '{nexus}': cached write at {offset}/{num} lost: \
+                failed on all children",
+                nexus = this.cache.nexus_name,
+                offset = this.offset,
+                num = this.num_blocks,
+            );
+        }
+        this.cache
+            .release(this.core, this.seq, this.successful == 0);
+    }
+
+    /// Drops the destage context of a cached write which could not be
+    /// submitted to any child. The write is removed from the cache, with no
+    /// error, as the submitter writes it to the children directly.
+    pub(super) fn cancel(destage: *mut Destage) {
+        let this = unsafe { Box::from_raw(destage) };
+        debug_assert_eq!(this.in_flight, 1);
+        this.cache.release(this.core, this.seq, false);
+    }
+}
+
+impl<'n> Nexus<'n> {
+    /// Enables the volatile write cache of the nexus, with the given capacity
+    /// in bytes. The cache cannot be disabled afterwards.
+    pub fn enable_write_cache(&self, capacity: u64) -> Result<(), Error> {
+        if self.flush_policy().volatile_write_cache == Some(false)
+            || self.flush_policy().write_through
+        {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "flush policy of nexus '{}' does not allow a volatile \
+                    write cache",
+                    self.name
+                ),
+            });
+        }
+
+        let cache = Arc::new(NexusWriteCache::new(
+            &self.name,
+            self.block_len(),
+            capacity,
+        ));
+
+        if self.write_cache.set(cache).is_err() {
+            return Err(Error::OperationNotAllowed {
+                reason: format!(
+                    "write cache of nexus '{}' is already enabled",
+                    self.name
+                ),
+            });
+        }
+
+        // Report the volatile write cache to the hosts.
+        unsafe {
+            (*self.bdev().unsafe_inner_mut_ptr()).write_cache = 1;
+        }
+
+        info!("{self:?}: write cache enabled, {capacity} bytes");
+        Ok(())
+    }
+
+    /// Returns the write cache of the nexus, if enabled.
+    #[inline(always)]
+    pub(super) fn write_cache(&self) -> Option<&Arc<NexusWriteCache>> {
+        self.write_cache.get()
+    }
+
+    /// Returns the statistics of the write cache, if enabled.
+    pub fn write_cache_stats(&self) -> Option<NexusWriteCacheStats> {
+        self.write_cache().map(|c| c.stats())
+    }
+
+    /// Waits, for a limited time, for the cached writes to reach the
+    /// children before the nexus goes away.
+    pub(super) async fn drain_write_cache(&self) {
+        let Some(cache) = self.write_cache() else {
+            return;
+        };
+
+        let start = Instant::now();
+        while !cache.is_empty() {
+            if start.elapsed() > NEXUS_WRITE_CACHE_DRAIN_TIMEOUT {
+                error!("{self:?}: cached writes lost: {:?}", cache.stats());
+                return;
+            }
+            mayastor_sleep(Duration::from_millis(10)).await.ok();
+        }
+    }
+}
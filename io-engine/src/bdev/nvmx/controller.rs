@@ -21,10 +21,14 @@ use spdk_rs::{
         spdk_nvme_async_event_completion,
         spdk_nvme_cpl,
         spdk_nvme_ctrlr,
+        spdk_nvme_ctrlr_disconnect,
         spdk_nvme_ctrlr_fail,
         spdk_nvme_ctrlr_get_ns,
         spdk_nvme_ctrlr_is_active_ns,
+        spdk_nvme_ctrlr_reconnect_async,
+        spdk_nvme_ctrlr_reconnect_poll_async,
         spdk_nvme_ctrlr_register_aer_callback,
+        spdk_nvme_ctrlr_set_trid,
         spdk_nvme_detach,
     },
     Poller,
@@ -34,12 +38,17 @@ use spdk_rs::{
 use crate::{
     bdev::nvmx::{
         channel::{NvmeControllerIoChannel, NvmeIoChannel, NvmeIoChannelInner},
-        controller_inner::{SpdkNvmeController, TimeoutConfig},
+        controller_inner::{
+            NvmeNegotiatedParams,
+            SpdkNvmeController,
+            TimeoutConfig,
+        },
         controller_state::{
             ControllerFailureReason,
             ControllerFlag,
             ControllerStateMachine,
         },
+        multipath::{NvmePathInfo, NvmePaths},
         nvme_bdev_running_config,
         uri::NvmeControllerContext,
         utils::{
@@ -149,6 +158,10 @@ pub struct NvmeController<'a> {
     inner: Option<NvmeControllerInner<'a>>,
     state_machine: ControllerStateMachine,
     event_dispatcher: DeviceEventDispatcher,
+    /// Paths to the target.
+    paths: NvmePaths,
+    /// Poller reconnecting the controller through another path.
+    failover_poller: Option<Poller<'a>>,
     /// Timeout config is accessed by SPDK-driven timeout callback handlers,
     /// so it needs to be a raw pointer. Mutable members are made atomic to
     /// eliminate lock contention between API path and callback path.
@@ -169,8 +182,12 @@ unsafe impl<'a> Send for NvmeController<'a> {}
 unsafe impl<'a> Sync for NvmeController<'a> {}
 
 impl<'a> NvmeController<'a> {
-    /// Creates a new NVMe controller with the given name.
-    pub fn new(name: &str, prchk_flags: u32) -> Option<Self> {
+    /// Creates a new NVMe controller with the given name and paths.
+    pub(crate) fn new(
+        name: &str,
+        prchk_flags: u32,
+        paths: NvmePaths,
+    ) -> Option<Self> {
         let l = NvmeController {
             name: String::from(name),
             id: 0,
@@ -178,6 +195,8 @@ impl<'a> NvmeController<'a> {
             state_machine: ControllerStateMachine::new(name),
             inner: None,
             event_dispatcher: DeviceEventDispatcher::new(),
+            paths,
+            failover_poller: None,
             timeout_config: NonNull::new(Box::into_raw(Box::new(
                 TimeoutConfig::new(name),
             )))
@@ -728,6 +747,161 @@ impl<'a> NvmeController<'a> {
         debug!("{} added event listener", self.name);
         Ok(())
     }
+
+    /// Returns the paths of the controller.
+    pub fn paths(&self) -> Vec<NvmePathInfo> {
+        self.paths.info()
+    }
+
+    /// Returns the parameters negotiated with the target, if the controller
+    /// is connected.
+    pub fn negotiated_params(&self) -> Option<NvmeNegotiatedParams> {
+        if self.get_state() != Running {
+            return None;
+        }
+        self.controller().map(|c| c.negotiated_params())
+    }
+
+    /// Reconnects the controller through its next path, in response to a
+    /// failure of the active one. Returns false if the controller has no other
+    /// path to try, in which case the failure must be handled as usual.
+    fn failover(&mut self) -> bool {
+        if !self.paths.is_multipath() || self.get_state() != Running {
+            return false;
+        }
+
+        // Block resets until the controller is reconnected.
+        if self
+            .state_machine
+            .set_flag_exclusively(ControllerFlag::ResetActive)
+            .is_err()
+        {
+            return false;
+        }
+
+        if !self.switch_path() {
+            self.state_machine
+                .clear_flag_exclusively(ControllerFlag::ResetActive)
+                .expect("Reset flag improperly cleared during failover");
+            return false;
+        }
+
+        unsafe { self.timeout_config.as_mut().set_failover(true) };
+
+        let name = self.name.clone();
+        let ctrlr = self.ctrlr_as_ptr();
+        self.failover_poller = Some(
+            PollerBuilder::new()
+                .with_name("nvme_failover_poller")
+                .with_interval(Duration::from_millis(1))
+                .with_poll_fn(move |_| {
+                    let rc =
+                        unsafe { spdk_nvme_ctrlr_reconnect_poll_async(ctrlr) };
+                    if rc != -libc::EAGAIN {
+                        NvmeController::failover_done(&name, rc);
+                    }
+                    0
+                })
+                .build(),
+        );
+        true
+    }
+
+    /// Disconnects the controller, sets its transport ID to the one of its
+    /// next path and starts reconnecting through it.
+    fn switch_path(&mut self) -> bool {
+        let ctrlr = self.ctrlr_as_ptr();
+        let Some(trid) = self.paths.next() else {
+            error!("{}: no more paths to fail over to", self.name);
+            return false;
+        };
+
+        warn!(
+            "{}: failing over to path {}:{}",
+            self.name,
+            trid.traddr(),
+            trid.svcid()
+        );
+
+        unsafe {
+            spdk_nvme_ctrlr_fail(ctrlr);
+            let rc = spdk_nvme_ctrlr_set_trid(ctrlr, trid.as_ptr() as *mut _);
+            if rc != 0 {
+                error!("{}: failed to set transport ID: {}", self.name, rc);
+                return false;
+            }
+            let rc = spdk_nvme_ctrlr_disconnect(ctrlr);
+            if rc != 0 {
+                error!("{}: failed to disconnect: {}", self.name, rc);
+                return false;
+            }
+            spdk_nvme_ctrlr_reconnect_async(ctrlr);
+        }
+        true
+    }
+
+    /// Completes the reconnection of the controller through its new path. On
+    /// success the I/O qpairs are reconnected through it, otherwise the next
+    /// path is tried.
+    fn failover_done(name: &str, status: i32) {
+        let Some(carc) = NVME_CONTROLLERS.lookup_by_name(name) else {
+            return;
+        };
+        let mut controller = carc.lock();
+
+        if status != 0 {
+            error!(
+                "{}: failed to reconnect through path {}:{}: {}",
+                name,
+                controller.paths.active().traddr(),
+                controller.paths.active().svcid(),
+                Errno::from_i32(status.abs())
+            );
+            if controller.switch_path() {
+                return;
+            }
+        }
+
+        controller.failover_poller.take();
+        controller
+            .state_machine
+            .clear_flag_exclusively(ControllerFlag::ResetActive)
+            .expect("Reset flag improperly cleared during failover");
+        unsafe { controller.timeout_config.as_mut().set_failover(false) };
+
+        if status != 0 {
+            // All paths failed: handle it as an admin queue failure, which
+            // retires the device.
+            let destroy = unsafe { controller.timeout_config.as_mut() }
+                .start_device_destroy();
+            drop(controller);
+            if destroy {
+                notify_adminq_failure(name);
+            }
+            return;
+        }
+
+        controller.paths.connected();
+        info!(
+            "{}: reconnected through path {}:{}, resetting I/O qpairs",
+            name,
+            controller.paths.active().traddr(),
+            controller.paths.active().svcid()
+        );
+
+        fn reset_cb(success: bool, arg: *mut c_void) {
+            let name = unsafe { Box::from_raw(arg as *mut String) };
+            if !success {
+                error!("{}: failed to reset I/O qpairs after failover", name);
+            }
+        }
+
+        let arg = Box::into_raw(Box::new(name.to_string())) as *mut c_void;
+        if let Err(e) = controller.reset(reset_cb, arg, false) {
+            error!("{}: failed to reset after failover: {}", name, e);
+            unsafe { drop(Box::from_raw(arg as *mut String)) };
+        }
+    }
 }
 
 impl<'a> Drop for NvmeController<'a> {
@@ -838,31 +1012,24 @@ pub extern "C" fn nvme_poll_adminq(ctx: *mut c_void) -> i32 {
     // marked as failed.
     //
     // EAGAIN: returned whenever the controller is being reset.
+    // The admin queue is being reconnected through another path.
+    if context.failover_in_progress() {
+        return 1;
+    }
+
     let result = context.process_adminq();
 
     if result < 0 {
-        if context.start_device_destroy() {
+        let failover = NVME_CONTROLLERS
+            .lookup_by_name(&context.name)
+            .map_or(false, |carc| carc.lock().failover());
+        if !failover && context.start_device_destroy() {
             error!(
                 "process adminq: {}: {}",
                 context.name,
                 Errno::from_i32(result.abs())
             );
-            info!("dispatching nexus fault and retire: {}", context.name);
-            let dev_name = context.name.to_string();
-            let carc = NVME_CONTROLLERS.lookup_by_name(&dev_name).unwrap();
-            debug!(
-                ?dev_name,
-                "notifying listeners of admin command completion failure"
-            );
-            let controller = carc.lock();
-            let num_listeners = controller.notify_listeners(
-                DeviceEventType::AdminCommandCompletionFailed,
-            );
-            debug!(
-                ?dev_name,
-                ?num_listeners,
-                "listeners notified of admin command completion failure"
-            );
+            notify_adminq_failure(&context.name);
         }
         return 1;
     }
@@ -874,6 +1041,27 @@ pub extern "C" fn nvme_poll_adminq(ctx: *mut c_void) -> i32 {
     }
 }
 
+/// Notifies the listeners of the given controller of an admin queue failure,
+/// which retires the device.
+fn notify_adminq_failure(name: &str) {
+    info!("dispatching nexus fault and retire: {}", name);
+    let Some(carc) = NVME_CONTROLLERS.lookup_by_name(name) else {
+        return;
+    };
+    debug!(
+        ?name,
+        "notifying listeners of admin command completion failure"
+    );
+    let controller = carc.lock();
+    let num_listeners = controller
+        .notify_listeners(DeviceEventType::AdminCommandCompletionFailed);
+    debug!(
+        ?name,
+        ?num_listeners,
+        "listeners notified of admin command completion failure"
+    );
+}
+
 /// Destroy target controller and notify all listeners about device removal.
 pub(crate) async fn destroy_device(name: String) -> Result<(), BdevError> {
     let carc = NVME_CONTROLLERS.lookup_by_name(&name).ok_or(
@@ -14,6 +14,9 @@ use spdk_rs::libspdk::{
     spdk_nvme_ctrlr,
     spdk_nvme_ctrlr_cmd_abort,
     spdk_nvme_ctrlr_fail,
+    spdk_nvme_ctrlr_get_data,
+    spdk_nvme_ctrlr_get_max_xfer_size,
+    spdk_nvme_ctrlr_get_opts,
     spdk_nvme_ctrlr_get_regs_csts,
     spdk_nvme_ctrlr_process_admin_completions,
     spdk_nvme_ctrlr_register_timeout_callback,
@@ -28,6 +31,7 @@ use crate::{
         nvme_bdev_running_config,
         utils::nvme_cpl_succeeded,
         NvmeController,
+        NvmeTimeouts,
         NVME_CONTROLLERS,
     },
     core::{CoreError, DeviceIoController, DeviceTimeoutAction},
@@ -74,6 +78,7 @@ pub(crate) struct TimeoutConfig {
     reset_attempts: u32,
     next_reset_time: Instant,
     destroy_in_progress: AtomicCell<bool>,
+    failover_in_progress: AtomicCell<bool>,
 }
 
 impl Drop for TimeoutConfig {
@@ -94,6 +99,7 @@ impl TimeoutConfig {
             reset_attempts: MAX_RESET_ATTEMPTS,
             next_reset_time: Instant::now(),
             destroy_in_progress: AtomicCell::new(false),
+            failover_in_progress: AtomicCell::new(false),
         }
     }
 
@@ -106,6 +112,15 @@ impl TimeoutConfig {
             .compare_exchange(false, true)
             .is_ok()
     }
+
+    pub fn set_failover(&mut self, failover: bool) {
+        self.failover_in_progress.store(failover);
+    }
+
+    pub fn failover_in_progress(&self) -> bool {
+        self.failover_in_progress.load()
+    }
+
     pub fn set_controller(&mut self, ctrlr: SpdkNvmeController) {
         self.ctrlr = ctrlr;
     }
@@ -252,6 +267,25 @@ impl TimeoutConfig {
     }
 }
 
+/// Parameters of an NVMe controller, as negotiated with the target when the
+/// controller was connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NvmeNegotiatedParams {
+    /// Number of entries of the I/O queues.
+    pub io_queue_size: u32,
+    /// Number of I/O queues.
+    pub num_io_queues: u32,
+    /// Maximum Data Transfer Size, in units of the minimum memory page size
+    /// as a power of two; 0 means unlimited.
+    pub mdts: u8,
+    /// Maximum size of a single I/O, in bytes.
+    pub max_xfer_size: u32,
+    /// Keep-alive timeout, in milliseconds.
+    pub keep_alive_timeout_ms: u32,
+    /// Controller ID assigned by the target.
+    pub cntlid: u16,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct SpdkNvmeController(NonNull<spdk_nvme_ctrlr>);
 
@@ -308,6 +342,23 @@ impl SpdkNvmeController {
     pub fn ext_host_id(&self) -> &[u8; 16] {
         unsafe { &(*self.as_ptr()).opts.extended_host_id }
     }
+
+    /// Returns the parameters negotiated with the target. SPDK updates the
+    /// controller options with the values granted by the target.
+    pub fn negotiated_params(&self) -> NvmeNegotiatedParams {
+        unsafe {
+            let opts = &*spdk_nvme_ctrlr_get_opts(self.as_ptr());
+            let cdata = &*spdk_nvme_ctrlr_get_data(self.as_ptr());
+            NvmeNegotiatedParams {
+                io_queue_size: opts.io_queue_size,
+                num_io_queues: opts.num_io_queues,
+                mdts: cdata.mdts,
+                max_xfer_size: spdk_nvme_ctrlr_get_max_xfer_size(self.as_ptr()),
+                keep_alive_timeout_ms: opts.keep_alive_timeout_ms,
+                cntlid: cdata.cntlid,
+            }
+        }
+    }
 }
 
 impl From<*mut spdk_nvme_ctrlr> for SpdkNvmeController {
@@ -440,8 +491,9 @@ impl<'a> NvmeController<'a> {
 
     pub(crate) fn configure_timeout(&mut self) {
         let device_defaults = nvme_bdev_running_config();
+        let timeouts = NvmeTimeouts::get();
 
-        if device_defaults.timeout_us == 0 {
+        if timeouts.timeout_us == 0 {
             warn!(
                 "{} no timeout configured for NVMe controller, I/O timeout handling disabled.",
                 self.name
@@ -469,15 +521,15 @@ impl<'a> NvmeController<'a> {
         unsafe {
             spdk_nvme_ctrlr_register_timeout_callback(
                 self.ctrlr_as_ptr(),
-                device_defaults.timeout_us,
-                device_defaults.timeout_admin_us,
+                timeouts.timeout_us,
+                timeouts.timeout_admin_us,
                 Some(NvmeController::io_timeout_handler),
                 self.timeout_config.as_ptr().cast(),
             );
         }
         info!(
             "{} I/O timeout set to {} us",
-            self.name, device_defaults.timeout_us
+            self.name, timeouts.timeout_us
         );
     }
 }
@@ -9,10 +9,13 @@ use spdk_rs::{
     libspdk::{
         iovec,
         nvme_cmd_cdw10_get,
+        nvme_request,
+        nvme_transport_qpair_iterate_requests,
         spdk_get_io_channel,
         spdk_io_channel,
         spdk_nvme_cmd,
         spdk_nvme_cpl,
+        spdk_nvme_ctrlr_cmd_abort_ext,
         spdk_nvme_ctrlr_cmd_admin_raw,
         spdk_nvme_ctrlr_cmd_io_raw,
         spdk_nvme_dsm_range,
@@ -25,6 +28,7 @@ use spdk_rs::{
         spdk_nvme_ns_cmd_write,
         spdk_nvme_ns_cmd_write_zeroes,
         spdk_nvme_ns_cmd_writev,
+        SPDK_NVME_IO_FLAGS_CURRENT_UNWRITTEN_READ_FAIL,
         SPDK_NVME_IO_FLAGS_UNWRITTEN_READ_FAIL,
     },
     nvme_admin_opc,
@@ -385,6 +389,54 @@ extern "C" fn nvme_writev_done(ctx: *mut c_void, cpl: *const spdk_nvme_cpl) {
 
 /// I/O completion handler for all read requests (vectored/non-vectored)
 /// and non-vectored write requests.
+/// Search of the requests of an outstanding I/O, by the callback argument
+/// of the I/O.
+struct OutstandingIo {
+    io_cb_arg: IoCompletionCallbackArg,
+    bios: Vec<*mut c_void>,
+}
+
+/// Matches the outstanding requests of a qpair with the I/O searched.
+/// Requests split by SPDK are matched by their parent.
+extern "C" fn find_outstanding_io(
+    req: *mut nvme_request,
+    arg: *mut c_void,
+) -> i32 {
+    let search = unsafe { &mut *(arg as *mut OutstandingIo) };
+    let req = unsafe {
+        match (*req).parent {
+            parent if parent.is_null() => &*req,
+            parent => &*parent,
+        }
+    };
+
+    let is_io = req
+        .cb_fn
+        .map_or(false, |f| f as usize == nvme_io_done as usize);
+    if !is_io || req.cb_arg.is_null() {
+        return 0;
+    }
+
+    let bio = req.cb_arg as *mut NvmeIoCtx;
+    if unsafe { (*bio).cb_arg } == search.io_cb_arg
+        && !search.bios.contains(&req.cb_arg)
+    {
+        search.bios.push(req.cb_arg);
+    }
+    0
+}
+
+/// Completion of the NVMe Abort of an I/O.
+extern "C" fn nvme_abort_done(ctx: *mut c_void, cpl: *const spdk_nvme_cpl) {
+    let name = unsafe { Box::from_raw(ctx as *mut String) };
+    // Bit 0 of DW0 is cleared when the command has been aborted.
+    if !nvme_cpl_succeeded(cpl) {
+        warn!("{name}: NVMe Abort failed, waiting for the I/O timeout");
+    } else if unsafe { (*cpl).cdw0 } & 1 != 0 {
+        debug!("{name}: command not aborted, waiting for its completion");
+    }
+}
+
 extern "C" fn nvme_io_done(ctx: *mut c_void, cpl: *const spdk_nvme_cpl) {
     let nvme_io_ctx = ctx as *mut NvmeIoCtx;
 
@@ -750,6 +802,10 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
             ReadOptions::UnwrittenFail => {
                 self.prchk_flags | SPDK_NVME_IO_FLAGS_UNWRITTEN_READ_FAIL
             }
+            ReadOptions::CurrentUnwrittenFail => {
+                self.prchk_flags
+                    | SPDK_NVME_IO_FLAGS_CURRENT_UNWRITTEN_READ_FAIL
+            }
         };
 
         let channel = self.io_channel.as_ptr();
@@ -1073,6 +1129,60 @@ impl BlockDeviceHandle for NvmeDeviceHandle {
         }
     }
 
+    /// Sends an NVMe Abort for the requests of the given I/O outstanding on
+    /// the I/O qpair of the channel. The other requests of the qpair are not
+    /// affected. If the abort fails, the I/O is left to the I/O timeout of the
+    /// controller.
+    fn abort_io(
+        &self,
+        io_cb_arg: IoCompletionCallbackArg,
+    ) -> Result<(), CoreError> {
+        let inner = NvmeIoChannel::inner_from_channel(self.io_channel.as_ptr());
+        let Some(qpair) = inner.qpair() else {
+            return Err(CoreError::AbortDispatch {
+                source: Errno::ENODEV,
+            });
+        };
+
+        let mut search = OutstandingIo {
+            io_cb_arg,
+            bios: Vec::new(),
+        };
+        unsafe {
+            nvme_transport_qpair_iterate_requests(
+                qpair.as_ptr(),
+                Some(find_outstanding_io),
+                &mut search as *mut OutstandingIo as *mut c_void,
+            );
+        }
+        // Requests still queued in the qpair have not been sent yet.
+        if search.bios.is_empty() {
+            return Err(CoreError::AbortDispatch {
+                source: Errno::ENOENT,
+            });
+        }
+
+        for bio in search.bios {
+            let ctx = Box::into_raw(Box::new(self.name.clone()));
+            let rc = unsafe {
+                spdk_nvme_ctrlr_cmd_abort_ext(
+                    self.ctrlr.as_ptr(),
+                    qpair.as_ptr(),
+                    bio,
+                    Some(nvme_abort_done),
+                    ctx as *mut c_void,
+                )
+            };
+            if rc != 0 {
+                drop(unsafe { Box::from_raw(ctx) });
+                return Err(CoreError::AbortDispatch {
+                    source: Errno::from_i32(-rc),
+                });
+            }
+        }
+        Ok(())
+    }
+
     fn unmap_blocks(
         &self,
         offset_blocks: u64,
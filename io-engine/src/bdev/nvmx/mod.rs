@@ -5,15 +5,18 @@ use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 pub use channel::{NvmeControllerIoChannel, NvmeIoChannel, NvmeIoChannelInner};
 pub use controller::NvmeController;
+pub use controller_inner::NvmeNegotiatedParams;
 use controller_inner::SpdkNvmeController;
 pub use controller_state::NvmeControllerState;
 pub use device::{lookup_by_name, open_by_name, NvmeBlockDevice};
 pub use handle::{nvme_io_ctx_pool_init, NvmeDeviceHandle};
+pub use multipath::NvmePathInfo;
 pub use namespace::NvmeNamespace;
 use poll_group::PollGroup;
 pub use qpair::{QPair, QPairState};
+pub use rtt_tuning::NvmfRttTuning;
 pub use snapshot::{NvmeSnapshotMessage, NvmeSnapshotMessageV1};
-pub(crate) use uri::NvmfDeviceTemplate;
+pub(crate) use uri::{NvmfDeviceTemplate, DEFAULT_NVMF_PORT};
 
 use crate::{
     core::CoreError,
@@ -26,9 +29,11 @@ mod controller_inner;
 mod controller_state;
 mod device;
 mod handle;
+mod multipath;
 mod namespace;
 mod poll_group;
 mod qpair;
+mod rtt_tuning;
 mod snapshot;
 mod uri;
 pub mod utils;
@@ -125,3 +130,37 @@ pub static NVME_CONTROLLERS: Lazy<NVMeCtlrList> =
 pub fn nvme_bdev_running_config() -> &'static NvmeBdevOpts {
     &Config::get().nvme_bdev_opts
 }
+
+/// NVMe controller timeouts, which can be changed at runtime.
+/// Changes apply to the controllers connected afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NvmeTimeouts {
+    /// Timeout for I/O commands, in microseconds.
+    /// A timeout of 0 disables I/O timeout handling.
+    pub timeout_us: u64,
+    /// Timeout for admin commands, in microseconds.
+    pub timeout_admin_us: u64,
+    /// Keep-alive timeout, in milliseconds.
+    pub keep_alive_timeout_ms: u32,
+}
+
+static NVME_TIMEOUTS: Lazy<RwLock<NvmeTimeouts>> = Lazy::new(|| {
+    let opts = nvme_bdev_running_config();
+    RwLock::new(NvmeTimeouts {
+        timeout_us: opts.timeout_us,
+        timeout_admin_us: opts.timeout_admin_us,
+        keep_alive_timeout_ms: opts.keep_alive_timeout_ms,
+    })
+});
+
+impl NvmeTimeouts {
+    /// Returns the timeouts of new NVMe controllers.
+    pub fn get() -> Self {
+        *NVME_TIMEOUTS.read()
+    }
+
+    /// Sets the timeouts of the NVMe controllers connected from now on.
+    pub fn set(self) {
+        *NVME_TIMEOUTS.write() = self;
+    }
+}
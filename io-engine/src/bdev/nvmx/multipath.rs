@@ -0,0 +1,79 @@
+//!
+//! Paths of a multipath NVMe-oF controller.
+//!
+//! A remote target reachable through several listener addresses is connected
+//! through one of them, the active path. When the active path fails, the
+//! controller is reconnected through the next path (failover policy), so
+//! that a single fabric failure does not fault the device.
+
+use super::controller::transport::NvmeTransportId;
+
+/// Path of an NVMe-oF controller, as reported to the users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NvmePathInfo {
+    /// Address of the target.
+    pub traddr: String,
+    /// Service ID (port) of the target.
+    pub svcid: String,
+    /// Whether the controller is connected through this path.
+    pub active: bool,
+}
+
+/// Paths of an NVMe-oF controller, the first being the one it is created with.
+#[derive(Debug)]
+pub(crate) struct NvmePaths {
+    trids: Vec<NvmeTransportId>,
+    active: usize,
+    /// Paths tried since the active one last connected successfully.
+    attempts: usize,
+}
+
+impl NvmePaths {
+    pub(crate) fn new(trids: Vec<NvmeTransportId>) -> Self {
+        assert!(!trids.is_empty(), "NVMe controller needs a path");
+        Self {
+            trids,
+            active: 0,
+            attempts: 0,
+        }
+    }
+
+    /// Checks if the controller has alternate paths.
+    pub(crate) fn is_multipath(&self) -> bool {
+        self.trids.len() > 1
+    }
+
+    /// Makes the next path active and returns it, unless all paths have been
+    /// tried since the last successful connection.
+    pub(crate) fn next(&mut self) -> Option<&NvmeTransportId> {
+        if self.attempts + 1 >= self.trids.len() {
+            return None;
+        }
+        self.attempts += 1;
+        self.active = (self.active + 1) % self.trids.len();
+        Some(&self.trids[self.active])
+    }
+
+    /// Records a successful connection through the active path.
+    pub(crate) fn connected(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// Returns the active path.
+    pub(crate) fn active(&self) -> &NvmeTransportId {
+        &self.trids[self.active]
+    }
+
+    /// Returns the information of all paths.
+    pub(crate) fn info(&self) -> Vec<NvmePathInfo> {
+        self.trids
+            .iter()
+            .enumerate()
+            .map(|(i, t)| NvmePathInfo {
+                traddr: t.traddr(),
+                svcid: t.svcid(),
+                active: i == self.active,
+            })
+            .collect()
+    }
+}
@@ -40,7 +40,7 @@ use spdk_rs::{
 #[cfg(feature = "spdk-async-qpair-connect")]
 use nix::errno::Errno;
 
-use crate::core::CoreError;
+use crate::core::{CoreError, MayastorFeatures};
 
 use super::{nvme_bdev_running_config, SpdkNvmeController};
 
@@ -129,6 +129,11 @@ fn get_default_options(
     // relying on default_opts.async_mode.
     opts.async_mode = true;
 
+    // Ring the doorbell once per batch of submissions.
+    if MayastorFeatures::get_features().nexus_batched_io {
+        opts.delay_cmd_submit = default_opts.delay_cmd_submit;
+    }
+
     opts
 }
 
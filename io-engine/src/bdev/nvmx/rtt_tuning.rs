@@ -0,0 +1,165 @@
+//! Tuning of the NVMf controller timeouts to the round-trip time of their
+//! targets.
+//!
+//! The keep-alive timeout is sized for targets on the local network. Over
+//! higher-latency links, a few delayed keep-alives are enough for the
+//! controller to be considered lost, and the child to be faulted. When
+//! enabled, the round-trip time to the target is measured before connecting,
+//! from a few TCP handshakes with its NVMf port, and the keep-alive and
+//! fabrics connect timeouts of the controller are scaled to it, within the
+//! configured floor and ceiling. The timeouts are left untouched if the
+//! target cannot be measured.
+use std::{
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use futures::channel::oneshot;
+use once_cell::sync::OnceCell;
+
+use crate::core::runtime;
+
+/// Number of handshakes the round-trip time is measured from.
+const RTT_SAMPLES: usize = 3;
+/// Time allowed for each handshake.
+const RTT_SAMPLE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Multiple of the round-trip time the keep-alive timeout must cover.
+const KEEP_ALIVE_RTT_FACTOR: u32 = 20;
+/// Multiple of the round-trip time the fabrics connect timeout must cover.
+const CONNECT_RTT_FACTOR: u32 = 10;
+/// Fabrics connect timeout of SPDK, in microseconds.
+const DEFAULT_CONNECT_TIMEOUT_US: u64 = 500_000;
+
+/// Floor and ceiling of the tuned timeouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NvmfRttTuning {
+    /// Measure the round-trip time to the targets and tune the timeouts.
+    pub enabled: bool,
+    /// Lowest keep-alive timeout, in milliseconds. If 0, the configured
+    /// keep-alive timeout is the floor.
+    pub min_keep_alive_ms: u32,
+    /// Highest keep-alive timeout, in milliseconds, which also caps the
+    /// fabrics connect timeout.
+    pub max_keep_alive_ms: u32,
+}
+
+impl Default for NvmfRttTuning {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_keep_alive_ms: 0,
+            max_keep_alive_ms: 30_000,
+        }
+    }
+}
+
+static NVMF_RTT_TUNING: OnceCell<NvmfRttTuning> = OnceCell::new();
+
+impl NvmfRttTuning {
+    /// Sets the tuning of the NVMf controllers connected from now on.
+    pub fn configure(self) {
+        if NVMF_RTT_TUNING.set(self).is_err() {
+            warn!("NVMf round-trip time tuning is already configured");
+        }
+    }
+
+    /// Returns the configured tuning.
+    fn get() -> Self {
+        NVMF_RTT_TUNING.get().copied().unwrap_or_default()
+    }
+}
+
+/// Timeouts of a controller, tuned to the round-trip time of its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NvmfTunedTimeouts {
+    /// Measured round-trip time.
+    pub rtt: Duration,
+    /// Keep-alive timeout, in milliseconds.
+    pub keep_alive_timeout_ms: u32,
+    /// Fabrics connect timeout, in microseconds.
+    pub fabrics_connect_timeout_us: u64,
+}
+
+impl NvmfTunedTimeouts {
+    /// Scales the given keep-alive timeout to the round-trip time.
+    fn new(rtt: Duration, keep_alive_ms: u32, tuning: NvmfRttTuning) -> Self {
+        let rtt_ms = u32::try_from(rtt.as_millis()).unwrap_or(u32::MAX);
+
+        let floor = match tuning.min_keep_alive_ms {
+            0 => keep_alive_ms,
+            min => min,
+        };
+        let ceiling = tuning.max_keep_alive_ms.max(floor);
+
+        let keep_alive_timeout_ms = keep_alive_ms
+            .max(rtt_ms.saturating_mul(KEEP_ALIVE_RTT_FACTOR))
+            .clamp(floor, ceiling);
+
+        let fabrics_connect_timeout_us = (rtt.as_micros() as u64)
+            .saturating_mul(CONNECT_RTT_FACTOR as u64)
+            .clamp(
+                DEFAULT_CONNECT_TIMEOUT_US,
+                (ceiling as u64 * 1000).max(DEFAULT_CONNECT_TIMEOUT_US),
+            );
+
+        Self {
+            rtt,
+            keep_alive_timeout_ms,
+            fabrics_connect_timeout_us,
+        }
+    }
+}
+
+/// Measures the round-trip time to the given target, and tunes the given
+/// keep-alive timeout to it. Returns `None` if the tuning is disabled or the
+/// target cannot be reached.
+pub(crate) async fn tune_timeouts(
+    host: &str,
+    port: u16,
+    keep_alive_ms: u32,
+) -> Option<NvmfTunedTimeouts> {
+    let tuning = NvmfRttTuning::get();
+    if !tuning.enabled {
+        return None;
+    }
+
+    let Some(rtt) = measure_rtt(host, port).await else {
+        warn!(
+            "Failed to measure the round-trip time to {host}:{port}, \
+            keeping the default NVMf timeouts"
+        );
+        return None;
+    };
+
+    Some(NvmfTunedTimeouts::new(rtt, keep_alive_ms, tuning))
+}
+
+/// Measures the round-trip time to the given target, as the shortest of a
+/// few TCP handshakes with it. The handshakes block, so that they are made
+/// off the reactor.
+async fn measure_rtt(host: &str, port: u16) -> Option<Duration> {
+    let target = format!("{host}:{port}");
+    let (sender, receiver) = oneshot::channel();
+
+    runtime::spawn(async move {
+        let rtt = runtime::spawn_blocking(move || {
+            let addr = target.to_socket_addrs().ok()?.next()?;
+            (0 .. RTT_SAMPLES).filter_map(|_| handshake(&addr)).min()
+        })
+        .await
+        .unwrap_or_default();
+
+        sender.send(rtt).ok();
+    });
+
+    receiver.await.ok().flatten()
+}
+
+/// Times a TCP handshake with the given address.
+fn handshake(addr: &SocketAddr) -> Option<Duration> {
+    let start = Instant::now();
+    let stream = TcpStream::connect_timeout(addr, RTT_SAMPLE_TIMEOUT).ok()?;
+    let rtt = start.elapsed();
+    drop(stream);
+    Some(rtt)
+}
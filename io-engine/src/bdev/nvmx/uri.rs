@@ -18,7 +18,6 @@ use std::{
     time::Duration,
 };
 use url::Url;
-use uuid::Uuid;
 
 use controller::options::NvmeControllerOpts;
 
@@ -39,7 +38,10 @@ use crate::{
         nvmx::{
             controller,
             controller_inner::SpdkNvmeController,
+            multipath::NvmePaths,
+            rtt_tuning::{tune_timeouts, NvmfTunedTimeouts},
             NvmeControllerState,
+            NvmeTimeouts,
             NVME_CONTROLLERS,
         },
         util::uri,
@@ -47,15 +49,14 @@ use crate::{
         GetName,
     },
     bdev_api::{self, BdevError},
-    constants::NVME_NQN_PREFIX,
-    core::MayastorEnvironment,
     ffihelper::ErrnoResult,
+    host::identity::HostIdentity,
     subsys::Config,
 };
 
 use super::controller::transport::NvmeTransportId;
 
-const DEFAULT_NVMF_PORT: u16 = 8420;
+pub(crate) const DEFAULT_NVMF_PORT: u16 = 8420;
 // Callback to be called once NVMe controller attach sequence completes.
 extern "C" fn connect_attach_cb(
     _cb_ctx: *mut c_void,
@@ -113,6 +114,9 @@ pub struct NvmfDeviceTemplate {
     uuid: Option<uuid::Uuid>,
     /// The HostNqn to connect to the nvmf target with.
     hostnqn: Option<String>,
+    /// Alternate addresses the target can be reached through, in order of
+    /// preference, used when the primary one fails.
+    paths: Vec<(String, u16)>,
 }
 
 impl TryFrom<&Url> for NvmfDeviceTemplate {
@@ -178,20 +182,65 @@ impl TryFrom<&Url> for NvmfDeviceTemplate {
 
         let hostnqn = parameters.remove("hostnqn");
 
+        let port = url.port().unwrap_or(DEFAULT_NVMF_PORT);
+        let paths = match parameters.remove("paths") {
+            Some(value) => parse_paths(&value, port).map_err(|message| {
+                BdevError::InvalidUri {
+                    uri: url.to_string(),
+                    message,
+                }
+            })?,
+            None => Vec::new(),
+        };
+
         Ok(NvmfDeviceTemplate {
             name: url[url::Position::BeforeHost .. url::Position::AfterPath]
                 .to_string(),
             alias: url.to_string(),
             host: host.to_string(),
-            port: url.port().unwrap_or(DEFAULT_NVMF_PORT),
+            port,
             subnqn: segments[0].to_string(),
             prchk_flags,
             uuid,
             hostnqn,
+            paths,
         })
     }
 }
 
+/// Parses a comma-separated list of 'host[:port]' alternate paths, the port
+/// defaulting to the one of the primary path.
+fn parse_paths(value: &str, port: u16) -> Result<Vec<(String, u16)>, String> {
+    value
+        .split(',')
+        .filter(|p| !p.is_empty())
+        .map(|p| match p.rsplit_once(':') {
+            Some((host, svcid)) => svcid
+                .parse::<u16>()
+                .map(|svcid| (host.to_string(), svcid))
+                .map_err(|_| format!("invalid port in path '{p}'")),
+            None => Ok((p.to_string(), port)),
+        })
+        .collect()
+}
+
+impl NvmfDeviceTemplate {
+    /// Returns the transport IDs of all paths to the target, the primary one
+    /// first.
+    fn transport_ids(&self) -> Vec<NvmeTransportId> {
+        std::iter::once((self.host.as_str(), self.port))
+            .chain(self.paths.iter().map(|(h, p)| (h.as_str(), *p)))
+            .map(|(host, port)| {
+                controller::transport::Builder::new()
+                    .with_subnqn(&self.subnqn)
+                    .with_svcid(&port.to_string())
+                    .with_traddr(host)
+                    .build()
+            })
+            .collect()
+    }
+}
+
 impl GetName for NvmfDeviceTemplate {
     fn get_name(&self) -> String {
         format!("{}n1", self.name)
@@ -210,12 +259,15 @@ pub(crate) struct NvmeControllerContext<'probe> {
 }
 
 impl<'probe> NvmeControllerContext<'probe> {
-    pub fn new(template: &NvmfDeviceTemplate) -> NvmeControllerContext {
-        let trid = controller::transport::Builder::new()
-            .with_subnqn(&template.subnqn)
-            .with_svcid(&template.port.to_string())
-            .with_traddr(&template.host)
-            .build();
+    pub fn new(
+        template: &NvmfDeviceTemplate,
+        tuned: Option<NvmfTunedTimeouts>,
+    ) -> NvmeControllerContext {
+        let trid = template
+            .transport_ids()
+            .into_iter()
+            .next()
+            .expect("NVMe controller needs a path");
 
         // setting the HOSTNQN allows tracking who is connected to what. These
         // makes debugging connections easier in certain cases. If no
@@ -223,24 +275,25 @@ impl<'probe> NvmeControllerContext<'probe> {
 
         let mut opts = controller::options::Builder::new()
             .with_keep_alive_timeout_ms(
-                Config::get().nvme_bdev_opts.keep_alive_timeout_ms,
+                NvmeTimeouts::get().keep_alive_timeout_ms,
             )
             .with_transport_retry_count(
                 Config::get().nvme_bdev_opts.transport_retry_count as u8,
             );
 
-        let hostnqn = template.hostnqn.clone().or_else(|| {
-            MayastorEnvironment::global_or_default().make_hostnqn()
-        });
+        if let Some(tuned) = tuned {
+            opts = opts
+                .with_keep_alive_timeout_ms(tuned.keep_alive_timeout_ms)
+                .with_fabrics_connect_timeout_us(
+                    tuned.fabrics_connect_timeout_us,
+                );
+        }
 
-        if let Ok(ext_host_id) = std::env::var("MAYASTOR_NVMF_HOSTID") {
-            if let Ok(uuid) = Uuid::parse_str(&ext_host_id) {
-                opts = opts.with_ext_host_id(*uuid.as_bytes());
-                if hostnqn.is_none() {
-                    opts = opts
-                        .with_hostnqn(format!("{NVME_NQN_PREFIX}:uuid:{uuid}"));
-                }
-            }
+        let identity = HostIdentity::current();
+        let hostnqn = template.hostnqn.clone().or_else(|| identity.hostnqn());
+
+        if let Some(ext_host_id) = identity.ext_host_id() {
+            opts = opts.with_ext_host_id(ext_host_id);
         }
 
         if let Some(host_nqn) = hostnqn {
@@ -291,13 +344,38 @@ impl CreateDestroy for NvmfDeviceTemplate {
         // release the lock to keep the write path as short, as
         // possible.
         let rc = Arc::new(Mutex::new(
-            controller::NvmeController::new(&cname, self.prchk_flags)
-                .expect("failed to create new NVMe controller instance"),
+            controller::NvmeController::new(
+                &cname,
+                self.prchk_flags,
+                NvmePaths::new(self.transport_ids()),
+            )
+            .expect("failed to create new NVMe controller instance"),
         ));
 
         NVME_CONTROLLERS.insert_controller(cname.clone(), rc);
 
-        let mut context = NvmeControllerContext::new(self);
+        // Scale the timeouts to the round-trip time of the target, so that
+        // higher-latency links don't fault the controller.
+        let tuned = tune_timeouts(
+            &self.host,
+            self.port,
+            NvmeTimeouts::get().keep_alive_timeout_ms,
+        )
+        .await;
+        if let Some(tuned) = tuned {
+            info!(
+                "{}: round-trip time to {}:{} is {:?}, keep-alive timeout \
+                {} ms, fabrics connect timeout {} us",
+                cname,
+                self.host,
+                self.port,
+                tuned.rtt,
+                tuned.keep_alive_timeout_ms,
+                tuned.fabrics_connect_timeout_us
+            );
+        }
+
+        let mut context = NvmeControllerContext::new(self, tuned);
 
         // Initiate connection with remote NVMe target.
         let mut probe_ctx = match NonNull::new(unsafe {
@@ -5,7 +5,7 @@ use std::{convert::TryFrom, num::ParseIntError, str::ParseBoolError};
 use url::ParseError;
 
 use crate::{
-    bdev::uri,
+    bdev::{device_destroy_with, uri, DestroyMode},
     core::{Bdev, Share},
 };
 
@@ -107,10 +107,13 @@ pub async fn bdev_create(uri: &str) -> Result<String, BdevError> {
     uri::parse(uri)?.create().await
 }
 
-/// Parse URI and destroy bdev described in the URI.
+/// Parse URI and destroy bdev described in the URI, once its open handles
+/// are closed.
 pub async fn bdev_destroy(uri: &str) -> Result<(), BdevError> {
     info!(?uri, "destroy");
-    uri::parse(uri)?.destroy().await
+    device_destroy_with(uri, DestroyMode::Deferred)
+        .await
+        .map(|_| ())
 }
 
 /// TODO
@@ -118,6 +121,12 @@ pub fn bdev_get_name(uri: &str) -> Result<String, BdevError> {
     Ok(uri::parse(uri)?.get_name())
 }
 
+/// Returns the canonical form of a device URI, which is the same for all the
+/// URIs referencing the same device.
+pub fn bdev_uri_normalize(uri: &str) -> Result<String, BdevError> {
+    uri::normalize(uri)
+}
+
 /// TODO
 pub fn bdev_uri_eq<T>(bdev: &Bdev<T>, uri: &url::Url) -> bool
 where
@@ -1,11 +1,16 @@
-use crate::{BdevClient, JsonClient, MayaClient};
+use crate::{
+    profile::{self, ClientConfig, Profile, ProfileTls},
+    BdevClient,
+    JsonClient,
+    MayaClient,
+};
 use byte_unit::Byte;
 use bytes::Bytes;
 use clap::ArgMatches;
 use http::uri::{Authority, PathAndQuery, Scheme, Uri};
 use snafu::{Backtrace, ResultExt, Snafu};
-use std::{cmp::max, str::FromStr};
-use tonic::transport::Endpoint;
+use std::{cmp::max, path::PathBuf, str::FromStr};
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity};
 
 #[derive(Debug, Snafu)]
 #[snafu(context(suffix(false)))]
@@ -32,12 +37,31 @@ pub enum Error {
     },
     #[snafu(display("Invalid output format: {}", format))]
     OutputFormatInvalid { format: String },
+    #[snafu(display("Invalid connection profile: {}", source))]
+    ClientProfile {
+        source: profile::Error,
+        backtrace: Backtrace,
+    },
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    ReadTlsFile {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[snafu(display("Invalid TLS configuration: {}", source))]
+    TlsConfig {
+        source: tonic::transport::Error,
+        backtrace: Backtrace,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum OutputFormat {
     Json,
     Default,
+    /// Comma-separated values, with a header row.
+    Csv,
+    /// Default output, with additional columns.
+    Wide,
 }
 
 impl FromStr for OutputFormat {
@@ -47,6 +71,8 @@ impl FromStr for OutputFormat {
         match s.to_lowercase().as_str() {
             "json" => Ok(Self::Json),
             "default" => Ok(Self::Default),
+            "csv" => Ok(Self::Csv),
+            "wide" => Ok(Self::Wide),
             s => Err(Error::OutputFormatInvalid {
                 format: s.to_string(),
             }),
@@ -104,6 +130,27 @@ mod v1 {
     }
 }
 
+/// Builds the client TLS configuration of a connection profile.
+fn tls_config(tls: &ProfileTls) -> Result<ClientTlsConfig, Error> {
+    let read = |path: &PathBuf| {
+        std::fs::read(path).context(ReadTlsFile {
+            path: path.clone(),
+        })
+    };
+
+    let mut config = ClientTlsConfig::new();
+    if let Some(ca_cert) = &tls.ca_cert {
+        config = config.ca_certificate(Certificate::from_pem(read(ca_cert)?));
+    }
+    if let (Some(cert), Some(key)) = (&tls.cert, &tls.key) {
+        config = config.identity(Identity::from_pem(read(cert)?, read(key)?));
+    }
+    if let Some(domain) = &tls.domain {
+        config = config.domain_name(domain.clone());
+    }
+    Ok(config)
+}
+
 pub struct Context {
     pub(crate) client: MayaClient,
     pub(crate) bdev: BdevClient,
@@ -116,6 +163,19 @@ pub struct Context {
 
 impl Context {
     pub(crate) async fn new(matches: &ArgMatches<'_>) -> Result<Self, Error> {
+        let config = ClientConfig::load().context(ClientProfile)?;
+        let profile = config
+            .profile(matches.value_of("context"))
+            .context(ClientProfile)?;
+        Self::with_profile(matches, profile).await
+    }
+
+    /// Creates a context connecting to the io-engine of the given profile,
+    /// unless an endpoint is given on the command line.
+    pub(crate) async fn with_profile(
+        matches: &ArgMatches<'_>,
+        profile: Option<&Profile>,
+    ) -> Result<Self, Error> {
         let verbosity = if matches.is_present("quiet") {
             0
         } else {
@@ -125,12 +185,21 @@ impl Context {
             .value_of("units")
             .and_then(|u| u.chars().next())
             .unwrap_or('b');
+        let bind = if matches.occurrences_of("bind") > 0 {
+            matches.value_of("bind")
+        } else {
+            profile
+                .map(|p| p.endpoint.as_str())
+                .or(matches.value_of("bind"))
+        };
+        let tls = profile.and_then(|p| p.tls.as_ref());
         // Ensure the provided host is defaulted & normalized to what we expect.
-        let host = if let Some(host) = matches.value_of("bind") {
+        let host = if let Some(host) = bind {
             let uri = host.parse::<Uri>().context(InvalidUri)?;
             let mut parts = uri.into_parts();
             if parts.scheme.is_none() {
-                parts.scheme = Scheme::from_str("http").ok();
+                let scheme = if tls.is_some() { "https" } else { "http" };
+                parts.scheme = Scheme::from_str(scheme).ok();
             }
             if let Some(ref mut authority) = parts.authority {
                 if authority.port().is_none() {
@@ -148,15 +217,26 @@ impl Context {
         } else {
             Endpoint::from_static("http://127.0.0.1:10124")
         };
+        let host = match tls {
+            Some(tls) => {
+                host.tls_config(tls_config(tls)?).context(TlsConfig)?
+            }
+            None => host,
+        };
 
         if verbosity > 1 {
             println!("Connecting to {:?}", host.uri());
         }
 
-        let output = matches.value_of("output").ok_or_else(|| {
-            Error::OutputFormatInvalid {
-                format: "<none>".to_string(),
-            }
+        let output = if matches.occurrences_of("output") > 0 {
+            matches.value_of("output")
+        } else {
+            profile
+                .and_then(|p| p.output.as_deref())
+                .or(matches.value_of("output"))
+        }
+        .ok_or_else(|| Error::OutputFormatInvalid {
+            format: "<none>".to_string(),
         })?;
         let output = output.parse()?;
 
@@ -195,6 +275,11 @@ impl Context {
         }
     }
 
+    /// Checks if the list commands must print their additional columns.
+    pub(crate) fn wide(&self) -> bool {
+        self.output == OutputFormat::Wide
+    }
+
     pub(crate) fn print_list(
         &self,
         headers: Vec<&str>,
@@ -204,6 +289,14 @@ impl Context {
         let ncols = data.first().unwrap().len();
         assert_eq!(headers.len(), ncols);
 
+        if self.output == OutputFormat::Csv {
+            println!("{}", csv_header(&headers));
+            for row in data {
+                println!("{}", csv_row(&row));
+            }
+            return;
+        }
+
         let columns = if self.verbosity > 0 {
             data.insert(
                 0,
@@ -266,6 +359,15 @@ impl Context {
         let ncols = data.first().unwrap().len();
         assert_eq!(headers.len(), ncols);
 
+        if self.output == OutputFormat::Csv {
+            println!("{}", csv_header(&headers));
+            println!("{}", csv_row(&data[0]));
+            while let Some(row) = recv.recv().await {
+                println!("{}", csv_row(&row?));
+            }
+            return Ok(());
+        }
+
         let columns = if self.verbosity > 0 {
             data.insert(
                 0,
@@ -320,3 +422,26 @@ impl Context {
         Ok(())
     }
 }
+
+/// Formats the given list headers as a CSV row.
+fn csv_header(headers: &[&str]) -> String {
+    let headers: Vec<String> = headers
+        .iter()
+        .map(|h| h.strip_prefix('>').unwrap_or(h).to_string())
+        .collect();
+    csv_row(&headers)
+}
+
+/// Formats the given values as a CSV row, quoting them as needed.
+fn csv_row(row: &[String]) -> String {
+    row.iter()
+        .map(|v| {
+            if v.contains([',', '"', '\n']) {
+                format!("\"{}\"", v.replace('"', "\"\""))
+            } else {
+                v.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
@@ -8,6 +8,7 @@ use mayastor_api::v0::{
     mayastor_client::MayastorClient,
 };
 pub(crate) mod context;
+pub(crate) mod profile;
 mod v0;
 mod v1;
 
@@ -30,6 +31,11 @@ pub enum ClientError {
     },
     #[snafu(display("Missing value for {}", field))]
     MissingValue { field: String },
+    #[snafu(display("Client configuration error: {}", source))]
+    ClientConfig {
+        source: profile::Error,
+        backtrace: Backtrace,
+    },
 }
 
 pub type Result<T, E = ClientError> = std::result::Result<T, E>;
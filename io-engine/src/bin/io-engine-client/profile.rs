@@ -0,0 +1,147 @@
+//!
+//! Connection profiles of the client, stored in its configuration file, so
+//! that several io-engine instances can be managed without repeating their
+//! endpoint and TLS settings on every command.
+
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Environment variable overriding the path of the configuration file.
+const CONFIG_ENV: &str = "IO_ENGINE_CLIENT_CONFIG";
+
+#[derive(Debug, Snafu)]
+#[snafu(context(suffix(false)))]
+pub enum Error {
+    #[snafu(display(
+        "Failed to read the client configuration {}: {}",
+        path.display(),
+        source
+    ))]
+    ReadConfig {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display(
+        "Failed to write the client configuration {}: {}",
+        path.display(),
+        source
+    ))]
+    WriteConfig {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display(
+        "Failed to parse the client configuration {}: {}",
+        path.display(),
+        source
+    ))]
+    ParseConfig {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+    #[snafu(display(
+        "Failed to serialize the client configuration: {}",
+        source
+    ))]
+    SerializeConfig { source: serde_yaml::Error },
+    #[snafu(display("Context '{}' does not exist", name))]
+    ContextNotFound { name: String },
+}
+
+/// TLS settings of a connection profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileTls {
+    /// CA certificate file the server certificate is verified with.
+    pub ca_cert: Option<PathBuf>,
+    /// Client certificate file, for mutual TLS.
+    pub cert: Option<PathBuf>,
+    /// Client private key file, for mutual TLS.
+    pub key: Option<PathBuf>,
+    /// Domain name the server certificate is verified against.
+    pub domain: Option<String>,
+}
+
+/// Connection profile of an io-engine instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// gRPC endpoint of the io-engine.
+    pub endpoint: String,
+    /// TLS settings, the connection being in plain text without them.
+    pub tls: Option<ProfileTls>,
+    /// Default output format of the commands.
+    pub output: Option<String>,
+}
+
+/// Configuration of the client, with its connection profiles by name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientConfig {
+    /// Name of the profile used by default, if any.
+    pub current_context: Option<String>,
+    /// Connection profiles by name.
+    pub contexts: BTreeMap<String, Profile>,
+}
+
+impl ClientConfig {
+    /// Returns the path of the configuration file.
+    pub fn path() -> PathBuf {
+        if let Ok(path) = std::env::var(CONFIG_ENV) {
+            return path.into();
+        }
+        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home).join(".config/io-engine-client/config.yaml")
+    }
+
+    /// Loads the configuration, which is empty until saved.
+    pub fn load() -> Result<Self, Error> {
+        let path = Self::path();
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default())
+            }
+            Err(source) => {
+                return Err(Error::ReadConfig {
+                    path,
+                    source,
+                })
+            }
+        };
+        serde_yaml::from_str(&data).context(ParseConfig {
+            path,
+        })
+    }
+
+    /// Saves the configuration.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = Self::path();
+        let data = serde_yaml::to_string(self).context(SerializeConfig)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context(WriteConfig {
+                path: path.clone(),
+            })?;
+        }
+        std::fs::write(&path, data).context(WriteConfig {
+            path,
+        })
+    }
+
+    /// Returns the profile of the given name, or the current one if no name
+    /// is given.
+    pub fn profile(
+        &self,
+        name: Option<&str>,
+    ) -> Result<Option<&Profile>, Error> {
+        let Some(name) = name.or(self.current_context.as_deref()) else {
+            return Ok(None);
+        };
+        self.contexts.get(name).map(Some).ok_or_else(|| {
+            Error::ContextNotFound {
+                name: name.to_string(),
+            }
+        })
+    }
+}
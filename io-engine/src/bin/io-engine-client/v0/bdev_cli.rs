@@ -92,7 +92,7 @@ async fn list(mut ctx: Context, _args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let bdevs = &response.get_ref().bdevs;
             if bdevs.is_empty() {
                 ctx.v1("No bdevs found");
@@ -152,7 +152,7 @@ async fn create(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().name);
         }
     };
@@ -208,7 +208,7 @@ async fn destroy(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", found.name,);
         }
     };
@@ -252,7 +252,7 @@ async fn share(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uri,);
         }
     }
@@ -285,7 +285,7 @@ async fn unshare(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{name}",);
         }
     }
@@ -72,7 +72,7 @@ async fn controller_stats(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let controllers = &response.get_ref().controllers;
             if controllers.is_empty() {
                 ctx.v1("No NVMe controllers found");
@@ -127,7 +127,7 @@ async fn list_controllers(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let controllers = &response.get_ref().controllers;
             if controllers.is_empty() {
                 ctx.v1("No NVMe controllers found");
@@ -75,7 +75,7 @@ async fn list_block_devices(
                     .unwrap()
             )
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let devices: &Vec<rpc::BlockDevice> = &response.get_ref().devices;
 
             if devices.is_empty() {
@@ -52,7 +52,7 @@ pub async fn json_rpc_call(
         .await
         .context(GrpcStatus)?;
 
-    if ctx.output == OutputFormat::Default {
+    if ctx.output != OutputFormat::Json {
         debug!("Default output for jsonrpc calls is JSON.");
     };
 
@@ -61,7 +61,7 @@ pub(super) async fn main_() -> crate::Result<()> {
                 .long("output")
                 .value_name("FORMAT")
                 .default_value("default")
-                .possible_values(&["default", "json"])
+                .possible_values(&["default", "json", "csv", "wide"])
                 .global(true)
                 .help("Output format.")
         )
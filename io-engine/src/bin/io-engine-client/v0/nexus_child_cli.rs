@@ -138,7 +138,7 @@ async fn fault(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{uri}");
         }
     };
@@ -184,7 +184,7 @@ async fn child_operation(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{uri}");
         }
     };
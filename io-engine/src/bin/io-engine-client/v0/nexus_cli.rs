@@ -326,7 +326,7 @@ async fn nexus_create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uuid);
         }
     };
@@ -381,7 +381,7 @@ async fn nexus_create_v2(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uuid);
         }
     };
@@ -413,7 +413,7 @@ async fn nexus_shutdown(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,);
         }
     };
@@ -445,7 +445,7 @@ async fn nexus_destroy(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,);
         }
     };
@@ -473,7 +473,7 @@ async fn nexus_list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let nexus = &response.get_ref().nexus_list;
             if nexus.is_empty() {
                 ctx.v1("No nexus found");
@@ -538,7 +538,7 @@ async fn nexus_list_v2(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let nexus = &response.get_ref().nexus_list;
             if nexus.is_empty() {
                 ctx.v1("No nexus found");
@@ -625,7 +625,7 @@ async fn nexus_children(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let table = nexus
                 .children
                 .iter()
@@ -660,6 +660,9 @@ async fn nexus_children_2(
         .list_nexus(v1::nexus::ListNexusOptions {
             name: None,
             uuid: None,
+            child: None,
+            state: None,
+            published: None,
         })
         .await
         .context(GrpcStatus)?;
@@ -687,7 +690,7 @@ async fn nexus_children_2(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let table = nexus
                 .children
                 .iter()
@@ -755,7 +758,7 @@ async fn nexus_publish(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", response.get_ref().device_uri,)
         }
     };
@@ -792,7 +795,7 @@ async fn nexus_unpublish(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,)
         }
     };
@@ -897,7 +900,7 @@ async fn nexus_add(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,)
         }
     };
@@ -941,7 +944,7 @@ async fn nexus_remove(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri,)
         }
     };
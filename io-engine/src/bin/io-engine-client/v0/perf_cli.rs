@@ -65,7 +65,7 @@ async fn get_resource_usage(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             if let Some(usage) = &response.get_ref().usage {
                 table.push(vec![
                     usage.soft_faults.to_string(),
@@ -98,7 +98,7 @@ async fn create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &name);
         }
     };
@@ -135,7 +135,7 @@ async fn destroy(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &name);
         }
     };
@@ -165,7 +165,7 @@ async fn list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let pools: &Vec<rpc::Pool> = &response.get_ref().pools;
             if pools.is_empty() {
                 ctx.v1("No pools found");
@@ -189,7 +189,7 @@ async fn start(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -230,7 +230,7 @@ async fn stop(mut ctx: Context, matches: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -274,7 +274,7 @@ async fn pause(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -318,7 +318,7 @@ async fn resume(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -362,7 +362,7 @@ async fn state(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             ctx.print_list(
                 vec!["state"],
                 vec![vec![response.get_ref().state.clone()]],
@@ -412,7 +412,7 @@ async fn stats(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let response = &response.get_ref();
             ctx.print_list(
                 vec![
@@ -479,7 +479,7 @@ async fn progress(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             ctx.print_list(
                 vec!["progress (%)"],
                 vec![vec![response.get_ref().progress.to_string()]],
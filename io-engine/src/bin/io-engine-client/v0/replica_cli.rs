@@ -218,7 +218,7 @@ async fn replica_create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -283,7 +283,7 @@ async fn replica_create_v2(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -320,7 +320,7 @@ async fn replica_destroy(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid);
         }
     };
@@ -348,7 +348,7 @@ async fn replica_list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let replicas = &response.get_ref().replicas;
             if replicas.is_empty() {
                 ctx.v1("No replicas found");
@@ -400,7 +400,7 @@ async fn replica_list2(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let replicas = &response.get_ref().replicas;
             if replicas.is_empty() {
                 ctx.v1("No replicas found");
@@ -463,7 +463,7 @@ async fn replica_share(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -491,7 +491,7 @@ async fn replica_stat(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let replicas = &response.get_ref().replicas;
             if replicas.is_empty() {
                 ctx.v1("No replicas have been created");
@@ -22,6 +22,7 @@ pub async fn handler(
         ("share", Some(args)) => share(ctx, args).await,
         ("destroy", Some(args)) => destroy(ctx, args).await,
         ("unshare", Some(args)) => unshare(ctx, args).await,
+        ("normalize", Some(args)) => normalize(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
                 .context(GrpcStatus)
@@ -67,6 +68,10 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .about("unshare the given bdev")
         .arg(Arg::with_name("name").required(true).index(1));
 
+    let normalize = SubCommand::with_name("normalize")
+        .about("Print the canonical form of a bdev URI")
+        .arg(Arg::with_name("uri").required(true).index(1));
+
     SubCommand::with_name("bdev")
         .settings(&[
             AppSettings::SubcommandRequiredElseHelp,
@@ -78,6 +83,7 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .subcommand(share)
         .subcommand(unshare)
         .subcommand(create)
+        .subcommand(normalize)
         .subcommand(destroy)
 }
 
@@ -101,7 +107,7 @@ async fn list(mut ctx: Context, _args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let bdevs = &response.get_ref().bdevs;
             if bdevs.is_empty() {
                 ctx.v1("No bdevs found");
@@ -162,7 +168,7 @@ async fn create(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().bdev.as_ref().unwrap().name);
         }
     };
@@ -170,6 +176,44 @@ async fn create(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
     Ok(())
 }
 
+async fn normalize(
+    mut ctx: Context,
+    args: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let uri = args
+        .value_of("uri")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "uri".to_string(),
+        })?
+        .to_owned();
+
+    let response = ctx
+        .v1
+        .bdev
+        .normalize_uri(v1rpc::bdev::NormalizeUriRequest {
+            uri,
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            println!("{}", &response.get_ref().uri);
+        }
+    };
+
+    Ok(())
+}
+
 async fn destroy(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
     let name = args
         .value_of("name")
@@ -210,6 +254,7 @@ async fn destroy(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
         .bdev
         .destroy(v1rpc::bdev::DestroyBdevRequest {
             uri: found.uri.clone(),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -224,7 +269,7 @@ async fn destroy(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", found.name,);
         }
     };
@@ -275,7 +320,7 @@ async fn share(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().bdev.as_ref().unwrap().uri);
         }
     }
@@ -309,7 +354,7 @@ async fn unshare(mut ctx: Context, args: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{name}",);
         }
     }
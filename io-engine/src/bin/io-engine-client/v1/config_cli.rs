@@ -0,0 +1,221 @@
+//!
+//! methods to manage the connection profiles of the client
+
+use crate::{
+    profile::{ClientConfig, Profile, ProfileTls},
+    ClientError,
+    GrpcStatus,
+};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use snafu::ResultExt;
+use tonic::Status;
+
+pub async fn handler(matches: &ArgMatches<'_>) -> crate::Result<()> {
+    match matches.subcommand() {
+        ("get-contexts", Some(_)) => get_contexts(),
+        ("current-context", Some(_)) => current_context(),
+        ("use-context", Some(args)) => use_context(args),
+        ("set-context", Some(args)) => set_context(args),
+        ("delete-context", Some(args)) => delete_context(args),
+        (cmd, _) => {
+            Err(Status::not_found(format!("command {cmd} does not exist")))
+                .context(GrpcStatus)
+        }
+    }
+}
+
+pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
+    let get_contexts =
+        SubCommand::with_name("get-contexts").about("Lists the contexts");
+
+    let current_context = SubCommand::with_name("current-context")
+        .about("Shows the context used by default");
+
+    let use_context = SubCommand::with_name("use-context")
+        .about("Sets the context used by default")
+        .arg(
+            Arg::with_name("name")
+                .required(true)
+                .index(1)
+                .help("name of the context"),
+        );
+
+    let set_context = SubCommand::with_name("set-context")
+        .about("Creates or updates a context")
+        .arg(
+            Arg::with_name("name")
+                .required(true)
+                .index(1)
+                .help("name of the context"),
+        )
+        .arg(
+            Arg::with_name("endpoint")
+                .long("endpoint")
+                .takes_value(true)
+                .value_name("HOST")
+                .help("The URI of the io-engine instance"),
+        )
+        .arg(
+            Arg::with_name("ca-cert")
+                .long("ca-cert")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("CA certificate to verify the server certificate with"),
+        )
+        .arg(
+            Arg::with_name("cert")
+                .long("cert")
+                .takes_value(true)
+                .value_name("FILE")
+                .requires("key")
+                .help("Client certificate, for mutual TLS"),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .takes_value(true)
+                .value_name("FILE")
+                .requires("cert")
+                .help("Client private key, for mutual TLS"),
+        )
+        .arg(
+            Arg::with_name("tls-domain")
+                .long("tls-domain")
+                .takes_value(true)
+                .value_name("NAME")
+                .help("Domain name to verify the server certificate against"),
+        )
+        .arg(
+            Arg::with_name("default-output")
+                .long("default-output")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&["default", "json", "csv", "wide"])
+                .help("Output format of the commands using the context"),
+        );
+
+    let delete_context = SubCommand::with_name("delete-context")
+        .about("Deletes a context")
+        .arg(
+            Arg::with_name("name")
+                .required(true)
+                .index(1)
+                .help("name of the context"),
+        );
+
+    SubCommand::with_name("config")
+        .settings(&[
+            AppSettings::SubcommandRequiredElseHelp,
+            AppSettings::ColoredHelp,
+            AppSettings::ColorAlways,
+        ])
+        .about("Connection profiles management")
+        .subcommand(get_contexts)
+        .subcommand(current_context)
+        .subcommand(use_context)
+        .subcommand(set_context)
+        .subcommand(delete_context)
+}
+
+fn load() -> crate::Result<ClientConfig> {
+    ClientConfig::load().context(crate::ClientConfig)
+}
+
+fn save(config: &ClientConfig) -> crate::Result<()> {
+    config.save().context(crate::ClientConfig)
+}
+
+fn name(matches: &ArgMatches<'_>) -> crate::Result<String> {
+    matches
+        .value_of("name")
+        .map(|n| n.to_string())
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "name".to_string(),
+        })
+}
+
+fn get_contexts() -> crate::Result<()> {
+    let config = load()?;
+    let width = config.contexts.keys().map(|n| n.len()).max().unwrap_or(0);
+    for (name, profile) in &config.contexts {
+        let current = if config.current_context.as_ref() == Some(name) {
+            "*"
+        } else {
+            " "
+        };
+        println!(
+            "{current} {name:<width$} {} {} {}",
+            profile.endpoint,
+            if profile.tls.is_some() { "tls" } else { "-" },
+            profile.output.as_deref().unwrap_or("-"),
+        );
+    }
+    Ok(())
+}
+
+fn current_context() -> crate::Result<()> {
+    match load()?.current_context {
+        Some(name) => println!("{name}"),
+        None => println!("No current context"),
+    }
+    Ok(())
+}
+
+fn use_context(matches: &ArgMatches<'_>) -> crate::Result<()> {
+    let name = name(matches)?;
+    let mut config = load()?;
+    config.profile(Some(&name)).context(crate::ClientConfig)?;
+    config.current_context = Some(name.clone());
+    save(&config)?;
+    println!("Switched to context {name}");
+    Ok(())
+}
+
+fn set_context(matches: &ArgMatches<'_>) -> crate::Result<()> {
+    let name = name(matches)?;
+    let mut config = load()?;
+    let profile = config.contexts.entry(name.clone()).or_insert(Profile {
+        endpoint: "http://127.0.0.1:10124".to_string(),
+        ..Default::default()
+    });
+
+    if let Some(endpoint) = matches.value_of("endpoint") {
+        profile.endpoint = endpoint.to_string();
+    }
+    if let Some(output) = matches.value_of("default-output") {
+        profile.output = Some(output.to_string());
+    }
+    if ["ca-cert", "cert", "tls-domain"]
+        .iter()
+        .any(|a| matches.is_present(a))
+    {
+        let tls = profile.tls.get_or_insert_with(ProfileTls::default);
+        if let Some(ca_cert) = matches.value_of("ca-cert") {
+            tls.ca_cert = Some(ca_cert.into());
+        }
+        if let Some(cert) = matches.value_of("cert") {
+            tls.cert = Some(cert.into());
+            tls.key = matches.value_of("key").map(|k| k.into());
+        }
+        if let Some(domain) = matches.value_of("tls-domain") {
+            tls.domain = Some(domain.to_string());
+        }
+    }
+
+    save(&config)?;
+    println!("Context {name} set");
+    Ok(())
+}
+
+fn delete_context(matches: &ArgMatches<'_>) -> crate::Result<()> {
+    let name = name(matches)?;
+    let mut config = load()?;
+    config.profile(Some(&name)).context(crate::ClientConfig)?;
+    config.contexts.remove(&name);
+    if config.current_context.as_ref() == Some(&name) {
+        config.current_context = None;
+    }
+    save(&config)?;
+    println!("Context {name} deleted");
+    Ok(())
+}
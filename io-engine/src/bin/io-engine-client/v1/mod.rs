@@ -1,4 +1,5 @@
 pub mod bdev_cli;
+mod config_cli;
 pub mod controller_cli;
 pub mod device_cli;
 pub mod jsonrpc_cli;
@@ -12,10 +13,11 @@ pub mod snapshot_cli;
 mod test_cli;
 
 pub(crate) use super::context;
-use crate::ContextCreate;
 pub(crate) use crate::GrpcStatus;
-use clap::{App, AppSettings, Arg};
+use crate::{profile::ClientConfig, ContextCreate};
+use clap::{App, AppSettings, Arg, ArgMatches};
 use snafu::ResultExt;
+use std::io::{IsTerminal, Write};
 use version_info::version_info_str;
 
 pub(super) async fn main_() -> crate::Result<()> {
@@ -62,10 +64,22 @@ pub(super) async fn main_() -> crate::Result<()> {
                 .long("output")
                 .value_name("FORMAT")
                 .default_value("default")
-                .possible_values(&["default", "json"])
+                .possible_values(&["default", "json", "csv", "wide"])
                 .global(true)
                 .help("Output format.")
         )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .value_name("NAME")
+                .global(true)
+                .help("The connection profile to use instead of the current one"))
+        .arg(
+            Arg::with_name("all-nodes")
+                .long("all-nodes")
+                .global(true)
+                .conflicts_with("context")
+                .help("Run a list command against the io-engines of all connection profiles"))
         .subcommand(pool_cli::subcommands())
         .subcommand(nexus_cli::subcommands())
         .subcommand(replica_cli::subcommands())
@@ -77,12 +91,28 @@ pub(super) async fn main_() -> crate::Result<()> {
         .subcommand(jsonrpc_cli::subcommands())
         .subcommand(controller_cli::subcommands())
         .subcommand(test_cli::subcommands())
+        .subcommand(config_cli::subcommands())
         .get_matches();
 
+    if let ("config", Some(args)) = matches.subcommand() {
+        return config_cli::handler(args).await;
+    }
+
+    if matches.is_present("all-nodes") {
+        return run_all_nodes(&matches).await;
+    }
+
     let ctx = context::Context::new(&matches)
         .await
         .context(ContextCreate)?;
+    run(ctx, &matches).await
+}
 
+/// Runs the command against the io-engine of the given context.
+async fn run(
+    ctx: context::Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
     let status = match matches.subcommand() {
         ("bdev", Some(args)) => bdev_cli::handler(ctx, args).await,
         ("device", Some(args)) => device_cli::handler(ctx, args).await,
@@ -99,3 +129,77 @@ pub(super) async fn main_() -> crate::Result<()> {
     };
     status
 }
+
+/// Runs a list command against the io-engines of all connection profiles,
+/// returning the last error encountered, if any.
+async fn run_all_nodes(matches: &ArgMatches<'_>) -> crate::Result<()> {
+    // Only the list commands are safe to repeat on every node.
+    let mut command = matches.subcommand();
+    let mut leaf = "";
+    while let (name, Some(args)) = command {
+        leaf = name;
+        command = args.subcommand();
+    }
+    if leaf != "list" && !leaf.starts_with("list_") && leaf != "children" {
+        return Err(tonic::Status::invalid_argument(format!(
+            "--all-nodes is not supported by command {leaf}"
+        )))
+        .context(GrpcStatus);
+    }
+
+    let config = ClientConfig::load().context(crate::ClientConfig)?;
+    let mut result = Ok(());
+    for (name, profile) in &config.contexts {
+        let ctx = context::Context::with_profile(matches, Some(profile))
+            .await
+            .context(ContextCreate)?;
+        if matches!(
+            ctx.output,
+            context::OutputFormat::Default | context::OutputFormat::Wide
+        ) {
+            ctx.v1(&format!("{name}:"));
+        }
+        if let Err(error) = run(ctx, matches).await {
+            eprintln!("{name}: {error}");
+            result = Err(error);
+        }
+    }
+    result
+}
+
+/// Argument validating the request on the io-engine without carrying it out.
+pub(crate) fn dry_run_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("dry-run")
+        .long("dry-run")
+        .help("Validate the request without carrying it out")
+}
+
+/// Arguments of the destructive commands: `--dry-run`, and `--yes` skipping
+/// the confirmation prompt.
+pub(crate) fn destructive_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        dry_run_arg(),
+        Arg::with_name("yes")
+            .long("yes")
+            .help("Do not ask for confirmation"),
+    ]
+}
+
+/// Asks the user to confirm the given destructive operation, unless it is a
+/// dry run, it is confirmed beforehand or the input is not interactive.
+pub(crate) fn confirm(matches: &ArgMatches<'_>, operation: &str) -> bool {
+    if matches.is_present("dry-run")
+        || matches.is_present("yes")
+        || !std::io::stdin().is_terminal()
+    {
+        return true;
+    }
+
+    print!("{operation}? [y/N] ");
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
@@ -72,6 +72,13 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(2)
                 .help("uri of the child"),
+        )
+        .arg(
+            Arg::with_name("rebuild-source")
+                .long("rebuild-source")
+                .takes_value(true)
+                .value_name("URI")
+                .help("uri of the healthy child to rebuild from"),
         );
 
     let retire = SubCommand::with_name("retire")
@@ -125,6 +132,7 @@ async fn fault(
         .fault_nexus_child(v1rpc::nexus::FaultNexusChildRequest {
             uuid,
             uri: uri.clone(),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -139,7 +147,7 @@ async fn fault(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{uri}");
         }
     };
@@ -172,6 +180,10 @@ async fn child_operation(
             nexus_uuid: uuid,
             uri: uri.clone(),
             action,
+            rebuild_source: matches
+                .value_of("rebuild-source")
+                .map(str::to_string),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -186,7 +198,7 @@ async fn child_operation(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{uri}");
         }
     };
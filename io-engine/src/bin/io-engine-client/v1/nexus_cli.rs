@@ -1,4 +1,10 @@
-use super::nexus_child_cli;
+use super::{
+    confirm,
+    destructive_args,
+    dry_run_arg,
+    nexus_child_cli,
+    rebuild_cli,
+};
 use crate::{
     context::{Context, OutputFormat},
     parse_size,
@@ -10,6 +16,7 @@ use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::ToColoredJson;
 use mayastor_api::{v1, v1::nexus::NvmeReservation};
 use snafu::ResultExt;
+use std::time::SystemTime;
 use tonic::{Code, Status};
 use uuid::Uuid;
 
@@ -41,6 +48,13 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .long("name")
                 .help("name of the nexus"),
         )
+        .arg(
+            Arg::with_name("alias")
+                .required(false)
+                .long("alias")
+                .takes_value(true)
+                .help("human-friendly alias of the nexus"),
+        )
         .arg(
             Arg::with_name("min-cntlid")
                 .required(false)
@@ -83,6 +97,44 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help("Key used to persist the NexusInfo structure to the persistent store"),
         );
 
+    let recover = SubCommand::with_name("recover")
+        .about(
+            "Recreate a nexus with the children recorded as healthy in its \
+            persisted state",
+        )
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of the nexus"),
+        )
+        .arg(
+            Arg::with_name("size")
+                .required(true)
+                .index(2)
+                .help("size with optional unit suffix"),
+        )
+        .arg(
+            Arg::with_name("children")
+                .required(true)
+                .index(3)
+                .multiple(true)
+                .help("list of candidate children"),
+        )
+        .arg(
+            Arg::with_name("name")
+                .required(false)
+                .long("name")
+                .help("name of the nexus"),
+        )
+        .arg(
+            Arg::with_name("nexus-info-key")
+                .required(false)
+                .default_value("")
+                .long("nexus-info-key")
+                .help("Key the NexusInfo structure is persisted under"),
+        );
+
     let destroy = SubCommand::with_name("destroy")
         .about("destroy the nexus with given name")
         .arg(
@@ -90,7 +142,15 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(1)
                 .help("uuid for the nexus"),
-        );
+        )
+        .arg(
+            Arg::with_name("teardown")
+                .long("teardown")
+                .required(false)
+                .takes_value(false)
+                .help("show the teardown phases of the nexus"),
+        )
+        .args(&destructive_args());
 
     let shutdown = SubCommand::with_name("shutdown")
         .about("shutdown the nexus with given name")
@@ -115,7 +175,25 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(false)
                 .help("NQN of hosts which are allowed to connect to the target"))
         .arg(Arg::with_name("protocol").short("p").long("protocol").value_name("PROTOCOL")
-            .help("Name of a protocol (nvmf) used for publishing the nexus remotely"));
+            .help("Name of a protocol (nvmf) used for publishing the nexus remotely"))
+        .arg(Arg::with_name("eui64").long("eui64").takes_value(true)
+            .help("NVMe EUI64 of the namespace, 16 hex digits"))
+        .arg(Arg::with_name("nguid").long("nguid").takes_value(true)
+            .help("NVMe NGUID of the namespace, 32 hex digits"))
+        .arg(Arg::with_name("serial").long("serial").takes_value(true)
+            .help("NVMe serial number of the subsystem"))
+        .arg(Arg::with_name("model").long("model").takes_value(true)
+            .help("NVMe model number of the subsystem"))
+        .arg(Arg::with_name("volatile-write-cache").long("volatile-write-cache")
+            .takes_value(true).possible_values(&["on", "off"])
+            .help("Advertise a volatile write cache to the hosts, by default when all children support flushes"))
+        .arg(Arg::with_name("flush-target").long("flush-target")
+            .takes_value(true).possible_values(&["all", "quorum"])
+            .help("Children a flush must reach before it completes"))
+        .arg(Arg::with_name("write-through").long("write-through")
+            .help("Flush the children after every write"))
+        .arg(Arg::with_name("ana-group").long("ana-group").takes_value(true)
+            .help("ANA group of the nexus, whose nexuses have their ANA state set together"));
 
     let unpublish = SubCommand::with_name("unpublish")
         .about("unpublish the nexus")
@@ -124,6 +202,28 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(1)
                 .help("uuid for the nexus"),
+        )
+        .arg(dry_run_arg());
+
+    let listener = SubCommand::with_name("listener")
+        .about("add or move the NVMe-oF listener of a published nexus")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid for the nexus"),
+        )
+        .arg(
+            Arg::with_name("address")
+                .required(true)
+                .index(2)
+                .help("listener address, as ip[:port]"),
+        )
+        .arg(
+            Arg::with_name("replace")
+                .long("replace")
+                .takes_value(false)
+                .help("remove the other listeners of the nexus"),
         );
 
     let ana_state = SubCommand::with_name("ana_state")
@@ -144,6 +244,24 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help("NVMe ANA state of the nexus"),
         );
 
+    let ana_group_state = SubCommand::with_name("ana_group_state")
+        .about("set the NVMe ANA state of all the nexuses of an ANA group")
+        .arg(
+            Arg::with_name("group")
+                .required(true)
+                .index(1)
+                .help("ANA group of the nexuses"),
+        )
+        .arg(
+            Arg::with_name("state")
+                .required(true)
+                .index(2)
+                .possible_value("optimized")
+                .possible_value("non_optimized")
+                .possible_value("inaccessible")
+                .help("NVMe ANA state of the nexuses"),
+        );
+
     let add = SubCommand::with_name("add")
         .about("add a child")
         .arg(
@@ -163,7 +281,22 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .default_value("false")
                 .index(3)
                 .help("specify if a rebuild job runs automatically"),
-        );
+        )
+        .arg(
+            Arg::with_name("rebuild-source")
+                .long("rebuild-source")
+                .takes_value(true)
+                .value_name("URI")
+                .help("uri of the healthy child to rebuild from"),
+        )
+        .arg(
+            Arg::with_name("rebuild-in")
+                .long("rebuild-in")
+                .takes_value(true)
+                .value_name("DURATION")
+                .help("defer the rebuild of the child by the given duration"),
+        )
+        .arg(rebuild_cli::priority_arg());
 
     let remove = SubCommand::with_name("remove")
         .about("remove a child")
@@ -178,6 +311,24 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(2)
                 .help("uri of child to remove"),
+        )
+        .arg(
+            Arg::with_name("min-synced")
+                .long("min-synced")
+                .takes_value(true)
+                .value_name("NUMBER")
+                .help(
+                    "number of other children which must be synced, rebuilding \
+                    them if needed, before the child is removed",
+                ),
+        )
+        .arg(
+            Arg::with_name("evacuation-timeout")
+                .long("evacuation-timeout")
+                .takes_value(true)
+                .value_name("DURATION")
+                .requires("min-synced")
+                .help("maximum time to wait for the other children to sync"),
         );
 
     let list = SubCommand::with_name("list")
@@ -188,6 +339,38 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .long("show-children")
                 .required(false)
                 .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("child")
+                .long("child")
+                .takes_value(true)
+                .value_name("URI")
+                .help("list only the nexuses with this child URI or device"),
+        )
+        .arg(
+            Arg::with_name("state")
+                .long("state")
+                .takes_value(true)
+                .possible_values(&[
+                    "unknown",
+                    "online",
+                    "degraded",
+                    "faulted",
+                    "shutting_down",
+                    "shutdown",
+                ])
+                .help("list only the nexuses in this state"),
+        )
+        .arg(
+            Arg::with_name("published")
+                .long("published")
+                .conflicts_with("unpublished")
+                .help("list only the published nexuses"),
+        )
+        .arg(
+            Arg::with_name("unpublished")
+                .long("unpublished")
+                .help("list only the unpublished nexuses"),
         );
 
     let children = SubCommand::with_name("children")
@@ -207,16 +390,20 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         ])
         .about("Nexus device management")
         .subcommand(create)
+        .subcommand(recover)
         .subcommand(destroy)
         .subcommand(shutdown)
         .subcommand(publish)
         .subcommand(add)
         .subcommand(remove)
         .subcommand(unpublish)
+        .subcommand(listener)
         .subcommand(ana_state)
+        .subcommand(ana_group_state)
         .subcommand(list)
         .subcommand(children)
         .subcommand(nexus_child_cli::subcommands())
+        .subcommand(rebuild_cli::subcommands())
 }
 
 pub async fn handler(
@@ -225,16 +412,22 @@ pub async fn handler(
 ) -> crate::Result<()> {
     match matches.subcommand() {
         ("create", Some(args)) => nexus_create(ctx, args).await,
+        ("recover", Some(args)) => nexus_recover(ctx, args).await,
         ("destroy", Some(args)) => nexus_destroy(ctx, args).await,
         ("shutdown", Some(args)) => nexus_shutdown(ctx, args).await,
         ("list", Some(args)) => nexus_list(ctx, args).await,
         ("children", Some(args)) => nexus_children_2(ctx, args).await,
         ("publish", Some(args)) => nexus_publish(ctx, args).await,
         ("unpublish", Some(args)) => nexus_unpublish(ctx, args).await,
+        ("listener", Some(args)) => nexus_listener(ctx, args).await,
         ("ana_state", Some(args)) => nexus_nvme_ana_state(ctx, args).await,
+        ("ana_group_state", Some(args)) => {
+            nexus_nvme_ana_group_state(ctx, args).await
+        }
         ("add", Some(args)) => nexus_add(ctx, args).await,
         ("remove", Some(args)) => nexus_remove(ctx, args).await,
         ("child", Some(args)) => nexus_child_cli::handler(ctx, args).await,
+        ("rebuild", Some(args)) => rebuild_cli::handler(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
                 .context(GrpcStatus)
@@ -279,6 +472,7 @@ async fn nexus_create(
     // let (uuid, size, children) = nexus_create_parse(matches)?;
     let (uuid, size, children) = nexus_create_parse(matches)?;
     let name = matches.value_of("name").unwrap_or(&uuid).to_string();
+    let alias = matches.value_of("alias").map(str::to_string);
     let min_cntl_id = value_t!(matches.value_of("min-cntlid"), u32)
         .unwrap_or_else(|e| e.exit());
     let max_cntl_id = value_t!(matches.value_of("max-cntlid"), u32)
@@ -329,6 +523,8 @@ async fn nexus_create(
             nexus_info_key,
             resv_type,
             preempt_policy: 0,
+            alias,
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -343,7 +539,7 @@ async fn nexus_create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().nexus.as_ref().unwrap().uuid);
         }
     };
@@ -351,6 +547,62 @@ async fn nexus_create(
     Ok(())
 }
 
+async fn nexus_recover(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let (uuid, size, children) = nexus_create_parse(matches)?;
+    let name = matches.value_of("name").unwrap_or(&uuid).to_string();
+    let nexus_info_key = matches
+        .value_of("nexus-info-key")
+        .unwrap_or_default()
+        .to_string();
+
+    let response = ctx
+        .v1
+        .nexus
+        .recreate_nexus_from_store(v1::nexus::RecreateNexusFromStoreRequest {
+            nexus: Some(v1::nexus::CreateNexusRequest {
+                name,
+                uuid,
+                size,
+                min_cntl_id: 1,
+                max_cntl_id: 0xffef,
+                children,
+                nexus_info_key,
+                ..Default::default()
+            }),
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let response = response.get_ref();
+            println!("{}", response.nexus.as_ref().unwrap().uuid);
+            let table = response
+                .excluded
+                .iter()
+                .map(|e| vec![e.uri.clone(), e.uuid.clone(), e.reason.clone()])
+                .collect::<Vec<_>>();
+            if !table.is_empty() {
+                ctx.print_list(vec!["EXCLUDED", "UUID", "REASON"], table);
+            }
+        }
+    };
+
+    Ok(())
+}
+
 async fn nexus_shutdown(
     mut ctx: Context,
     matches: &ArgMatches<'_>,
@@ -361,6 +613,7 @@ async fn nexus_shutdown(
         .nexus
         .shutdown_nexus(v1::nexus::ShutdownNexusRequest {
             uuid: uuid.clone(),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -375,7 +628,7 @@ async fn nexus_shutdown(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,);
         }
     };
@@ -388,15 +641,28 @@ async fn nexus_destroy(
     matches: &ArgMatches<'_>,
 ) -> crate::Result<()> {
     let uuid = matches.value_of("uuid").unwrap().to_string();
+    let verbose = matches.is_present("teardown");
+    let dry_run = matches.is_present("dry-run");
+    if !confirm(matches, &format!("Destroy nexus {uuid}")) {
+        println!("Aborted");
+        return Ok(());
+    }
 
-    let _response = ctx
+    let destroyed = ctx
         .v1
         .nexus
         .destroy_nexus(v1::nexus::DestroyNexusRequest {
             uuid: uuid.clone(),
+            verbose,
+            validate_only: dry_run,
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
+    if dry_run {
+        ctx.v1(&format!("nexus: {uuid} can be destroyed"));
+        return Ok(());
+    }
 
     let response = ctx
         .v1
@@ -404,6 +670,9 @@ async fn nexus_destroy(
         .list_nexus(v1::nexus::ListNexusOptions {
             name: None,
             uuid: None,
+            child: None,
+            state: None,
+            published: None,
         })
         .await
         .context(GrpcStatus)?;
@@ -417,8 +686,23 @@ async fn nexus_destroy(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,);
+            let teardown = &destroyed.get_ref().teardown;
+            if verbose && !teardown.is_empty() {
+                let table = teardown
+                    .iter()
+                    .map(|p| {
+                        let duration = p
+                            .duration
+                            .clone()
+                            .and_then(|d| std::time::Duration::try_from(d).ok())
+                            .unwrap_or_default();
+                        vec![p.phase.clone(), format!("{duration:?}")]
+                    })
+                    .collect();
+                ctx.print_list(vec!["PHASE", ">DURATION"], table);
+            }
         }
     };
 
@@ -429,12 +713,34 @@ async fn nexus_list(
     mut ctx: Context,
     matches: &ArgMatches<'_>,
 ) -> crate::Result<()> {
+    let state = matches.value_of("state").map(|s| {
+        let state = match s {
+            "online" => v1::nexus::NexusState::NexusOnline,
+            "degraded" => v1::nexus::NexusState::NexusDegraded,
+            "faulted" => v1::nexus::NexusState::NexusFaulted,
+            "shutting_down" => v1::nexus::NexusState::NexusShuttingDown,
+            "shutdown" => v1::nexus::NexusState::NexusShutdown,
+            _ => v1::nexus::NexusState::NexusUnknown,
+        };
+        state as i32
+    });
+    let published = if matches.is_present("published") {
+        Some(true)
+    } else if matches.is_present("unpublished") {
+        Some(false)
+    } else {
+        None
+    };
+
     let response = ctx
         .v1
         .nexus
         .list_nexus(v1::nexus::ListNexusOptions {
             name: None,
             uuid: None,
+            child: matches.value_of("child").map(|c| c.to_string()),
+            state,
+            published,
         })
         .await
         .context(GrpcStatus)?;
@@ -448,7 +754,7 @@ async fn nexus_list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let nexus = &response.get_ref().nexus_list;
             if nexus.is_empty() {
                 ctx.v1("No nexus found");
@@ -456,7 +762,7 @@ async fn nexus_list(
             }
 
             ctx.v2("Found following nexus:");
-            let show_child = matches.is_present("children");
+            let show_child = matches.is_present("children") || ctx.wide();
 
             let table = nexus
                 .iter()
@@ -471,6 +777,18 @@ async fn nexus_list(
                         n.rebuilds.to_string(),
                         n.device_uri.clone(),
                     ];
+                    if ctx.wide() {
+                        row.push(ana_state_idx_to_str(n.ana_state).to_string());
+                        row.push(n.ana_group.map_or_else(
+                            || "-".to_string(),
+                            |g| g.to_string(),
+                        ));
+                        row.push(join_or_dash(&n.allowed_hosts));
+                        row.push(
+                            n.alias.clone().unwrap_or_else(|| "-".to_string()),
+                        );
+                        row.push(n.state_message.clone());
+                    }
                     if show_child {
                         row.push(
                             n.children
@@ -485,6 +803,15 @@ async fn nexus_list(
                 .collect();
             let mut hdr =
                 vec!["NAME", "UUID", ">SIZE", "STATE", ">REBUILDS", "PATH"];
+            if ctx.wide() {
+                hdr.extend([
+                    "ANA_STATE",
+                    "ANA_GROUP",
+                    "ALLOWED_HOSTS",
+                    "ALIAS",
+                    "STATE_REASON",
+                ]);
+            }
             if show_child {
                 hdr.push("CHILDREN");
             }
@@ -512,6 +839,9 @@ async fn nexus_children_2(
         .list_nexus(v1::nexus::ListNexusOptions {
             name: None,
             uuid: None,
+            child: None,
+            state: None,
+            published: None,
         })
         .await
         .context(GrpcStatus)?;
@@ -539,7 +869,7 @@ async fn nexus_children_2(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let table = nexus
                 .children
                 .iter()
@@ -555,18 +885,74 @@ async fn nexus_children_2(
                         Some(d) => d.to_string(),
                         None => "-".to_string(),
                     };
-                    vec![
+                    let local = match c.is_local {
+                        Some(true) => "yes",
+                        Some(false) => "no",
+                        None => "-",
+                    };
+                    let active_path = c
+                        .paths
+                        .iter()
+                        .find(|p| p.active)
+                        .map_or("-".to_string(), |p| p.address.clone());
+                    let mut row = vec![
                         c.uri.clone(),
                         state.to_string(),
                         reason.to_string(),
                         fault_timestamp,
-                    ]
+                        local.to_string(),
+                        if c.reader { "yes" } else { "no" }.to_string(),
+                        active_path,
+                    ];
+                    if ctx.wide() {
+                        row.push(
+                            c.device_name
+                                .clone()
+                                .unwrap_or_else(|| "-".to_string()),
+                        );
+                        row.push(join_or_dash(
+                            &c.paths
+                                .iter()
+                                .map(|p| p.address.clone())
+                                .collect::<Vec<String>>(),
+                        ));
+                        row.push(c.rebuild_progress.to_string());
+                        match &c.nvme_params {
+                            Some(p) => row.extend([
+                                p.controller_id.to_string(),
+                                p.num_io_queues.to_string(),
+                                p.io_queue_size.to_string(),
+                                p.max_xfer_size.to_string(),
+                                p.keep_alive_timeout_ms.to_string(),
+                            ]),
+                            None => row.extend(["-".to_string(); 5]),
+                        }
+                    }
+                    row
                 })
                 .collect();
-            ctx.print_list(
-                vec!["NAME", "STATE", "REASON", "LAST_FAULTED_AT"],
-                table,
-            );
+            let mut hdr = vec![
+                "NAME",
+                "STATE",
+                "REASON",
+                "LAST_FAULTED_AT",
+                "LOCAL",
+                "READER",
+                "PATH",
+            ];
+            if ctx.wide() {
+                hdr.extend([
+                    "DEVICE",
+                    "ALL_PATHS",
+                    ">REBUILD_PROGRESS",
+                    ">CNTLID",
+                    ">QUEUES",
+                    ">QUEUE_DEPTH",
+                    ">MAX_XFER",
+                    ">KATO_MS",
+                ]);
+            }
+            ctx.print_list(hdr, table);
         }
     };
 
@@ -598,6 +984,21 @@ async fn nexus_publish(
     };
     let allowed_hosts =
         matches.values_of_lossy("allowed-host").unwrap_or_default();
+    let volatile_write_cache = matches
+        .value_of("volatile-write-cache")
+        .map(|value| value == "on");
+    let flush_target = match matches.value_of("flush-target") {
+        Some("quorum") => v1::nexus::NexusFlushTarget::FlushQuorum,
+        _ => v1::nexus::NexusFlushTarget::FlushAll,
+    } as i32;
+    let ana_group = matches
+        .value_of("ana-group")
+        .map(|group| group.parse::<u32>())
+        .transpose()
+        .map_err(|e| {
+            Status::invalid_argument(format!("invalid ANA group: {e}"))
+        })
+        .context(GrpcStatus)?;
 
     let response = ctx
         .v1
@@ -607,6 +1008,15 @@ async fn nexus_publish(
             key,
             share: protocol,
             allowed_hosts,
+            eui64: matches.value_of("eui64").unwrap_or_default().to_string(),
+            nguid: matches.value_of("nguid").unwrap_or_default().to_string(),
+            serial: matches.value_of("serial").unwrap_or_default().to_string(),
+            model: matches.value_of("model").unwrap_or_default().to_string(),
+            volatile_write_cache,
+            flush_target,
+            write_through: matches.is_present("write-through"),
+            ana_group,
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -621,7 +1031,7 @@ async fn nexus_publish(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!(
                 "Nexus published over: {}",
                 response.get_ref().nexus.clone().unwrap().device_uri,
@@ -647,9 +1057,15 @@ async fn nexus_unpublish(
         .nexus
         .unpublish_nexus(v1::nexus::UnpublishNexusRequest {
             uuid: uuid.clone(),
+            validate_only: matches.is_present("dry-run"),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
+    if matches.is_present("dry-run") {
+        ctx.v1(&format!("nexus: {uuid} can be unpublished"));
+        return Ok(());
+    }
 
     match ctx.output {
         OutputFormat::Json => {
@@ -661,7 +1077,7 @@ async fn nexus_unpublish(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,)
         }
     };
@@ -669,6 +1085,45 @@ async fn nexus_unpublish(
     Ok(())
 }
 
+async fn nexus_listener(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let uuid = matches.value_of("uuid").unwrap().to_string();
+    let address = matches.value_of("address").unwrap().to_string();
+
+    let response = ctx
+        .v1
+        .nexus
+        .update_nexus_listener(v1::nexus::UpdateNexusListenerRequest {
+            uuid,
+            address,
+            replace: matches.is_present("replace"),
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            println!(
+                "Nexus published over: {}",
+                response.get_ref().nexus.clone().unwrap().device_uri,
+            )
+        }
+    };
+
+    Ok(())
+}
+
 async fn nexus_nvme_ana_state(
     ctx: Context,
     matches: &ArgMatches<'_>,
@@ -727,6 +1182,69 @@ async fn nexus_set_nvme_ana_state(
     Ok(())
 }
 
+async fn nexus_nvme_ana_group_state(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let ana_group = matches
+        .value_of("group")
+        .unwrap()
+        .parse::<u32>()
+        .map_err(|e| {
+            Status::invalid_argument(format!("invalid ANA group: {e}"))
+        })
+        .context(GrpcStatus)?;
+    let ana_state: v1::nexus::NvmeAnaState =
+        match matches.value_of("state").unwrap().parse() {
+            Ok(a) => a,
+            _ => {
+                return Err(Status::new(
+                    Code::Internal,
+                    "Invalid value of NVMe ANA state".to_owned(),
+                ))
+                .context(GrpcStatus);
+            }
+        };
+
+    let response = ctx
+        .v1
+        .nexus
+        .set_nvme_ana_group_state(v1::nexus::SetNvmeAnaGroupStateRequest {
+            ana_group,
+            ana_state: ana_state.into(),
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let table = response
+                .get_ref()
+                .nexuses
+                .iter()
+                .map(|n| {
+                    vec![
+                        n.name.clone(),
+                        n.error.clone().unwrap_or_else(|| "ok".to_string()),
+                    ]
+                })
+                .collect();
+            ctx.print_list(vec!["NAME", "RESULT"], table);
+        }
+    };
+
+    Ok(())
+}
+
 async fn nexus_add(
     mut ctx: Context,
     matches: &ArgMatches<'_>,
@@ -748,6 +1266,14 @@ async fn nexus_add(
         .unwrap_or("false")
         .parse::<bool>()
         .unwrap_or(false);
+    let rebuild_at = if matches.is_present("rebuild-in") {
+        let delay =
+            value_t!(matches.value_of("rebuild-in"), humantime::Duration)
+                .unwrap_or_else(|e| e.exit());
+        Some(prost_types::Timestamp::from(SystemTime::now() + *delay))
+    } else {
+        None
+    };
 
     let response = ctx
         .v1
@@ -756,6 +1282,13 @@ async fn nexus_add(
             uuid: uuid.clone(),
             uri,
             norebuild,
+            seed_snapshot: None,
+            rebuild_source: matches
+                .value_of("rebuild-source")
+                .map(str::to_string),
+            rebuild_at,
+            rebuild_priority: rebuild_cli::parse_priority(matches),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -770,7 +1303,7 @@ async fn nexus_add(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uuid,)
         }
     };
@@ -794,6 +1327,19 @@ async fn nexus_remove(
             field: "uri".to_string(),
         })?
         .to_string();
+    let min_synced_children = if matches.is_present("min-synced") {
+        value_t!(matches.value_of("min-synced"), u32)
+            .unwrap_or_else(|e| e.exit())
+    } else {
+        0
+    };
+    let evacuation_timeout_ms = if matches.is_present("evacuation-timeout") {
+        value_t!(matches.value_of("evacuation-timeout"), humantime::Duration)
+            .unwrap_or_else(|e| e.exit())
+            .as_millis() as u64
+    } else {
+        0
+    };
 
     let response = ctx
         .v1
@@ -801,6 +1347,9 @@ async fn nexus_remove(
         .remove_child_nexus(v1::nexus::RemoveChildNexusRequest {
             uuid,
             uri: uri.clone(),
+            min_synced_children,
+            evacuation_timeout_ms,
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -815,7 +1364,7 @@ async fn nexus_remove(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("Removed {} from specified nexus", &uri,)
         }
     };
@@ -836,6 +1385,15 @@ fn ana_state_idx_to_str(idx: i32) -> &'static str {
     }
 }
 
+/// Joins the given values with commas, or returns a dash if there are none.
+fn join_or_dash(values: &[String]) -> String {
+    if values.is_empty() {
+        "-".to_string()
+    } else {
+        values.join(",")
+    }
+}
+
 fn nexus_state_to_str(idx: i32) -> &'static str {
     match v1::nexus::NexusState::from_i32(idx).unwrap() {
         v1::nexus::NexusState::NexusUnknown => "unknown",
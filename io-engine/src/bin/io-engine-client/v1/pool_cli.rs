@@ -1,11 +1,13 @@
+use super::{confirm, destructive_args};
 use crate::{
     context::{Context, OutputFormat},
     ClientError,
     GrpcStatus,
 };
 use byte_unit::Byte;
-use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::ToColoredJson;
+use futures::StreamExt;
 use mayastor_api::v1 as v1rpc;
 use snafu::ResultExt;
 use tonic::Status;
@@ -26,6 +28,17 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .help("Storage pool uuid"),
         )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .takes_value(true)
+                .possible_values(&["lvs", "malloc"])
+                .default_value("lvs")
+                .help(
+                    "Pool type; the disk of a malloc pool is its size, \
+                    e.g. 64MiB, and requires an engine built for tests",
+                ),
+        )
         .arg(
             Arg::with_name("disk")
                 .required(true)
@@ -49,6 +62,15 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .help("Storage pool uuid"),
         )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .takes_value(false)
+                .help(
+                    "Import the pool even if another node appears to have \
+                    it imported; that node must no longer use the disk",
+                ),
+        )
         .arg(
             Arg::with_name("disk")
                 .required(true)
@@ -64,7 +86,8 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(1)
                 .help("Storage pool name"),
-        );
+        )
+        .args(&destructive_args());
 
     let export = SubCommand::with_name("export")
         .about("Export storage pool without destroying it")
@@ -75,6 +98,36 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help("Storage pool name"),
         );
 
+    let check = SubCommand::with_name("check")
+        .about("Check the metadata of a storage pool which is not imported")
+        .arg(
+            Arg::with_name("disk")
+                .required(true)
+                .index(1)
+                .help("Storage pool device"),
+        )
+        .arg(
+            Arg::with_name("repair")
+                .long("repair")
+                .help("Repair the metadata if possible"),
+        );
+
+    let fairness = SubCommand::with_name("fairness")
+        .about(
+            "Set the share of the pool write bandwidth left to the other \
+            replicas while a rebuild saturates the pool",
+        )
+        .arg(
+            Arg::with_name("pool")
+                .required(true)
+                .index(1)
+                .help("Storage pool name"),
+        )
+        .arg(Arg::with_name("ratio").index(2).help(
+            "Share in percent, 0 to disable the limits; the default \
+            share is used when omitted",
+        ));
+
     SubCommand::with_name("pool")
         .settings(&[
             AppSettings::SubcommandRequiredElseHelp,
@@ -86,6 +139,8 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .subcommand(import)
         .subcommand(destroy)
         .subcommand(export)
+        .subcommand(check)
+        .subcommand(fairness)
         .subcommand(SubCommand::with_name("list").about("List storage pools"))
 }
 
@@ -98,6 +153,8 @@ pub async fn handler(
         ("import", Some(args)) => import(ctx, args).await,
         ("destroy", Some(args)) => destroy(ctx, args).await,
         ("export", Some(args)) => export(ctx, args).await,
+        ("check", Some(args)) => check(ctx, args).await,
+        ("fairness", Some(args)) => fairness(ctx, args).await,
         ("list", Some(args)) => list(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
@@ -124,6 +181,10 @@ async fn create(
         })?
         .map(|dev| dev.to_owned())
         .collect();
+    let pooltype = match matches.value_of("type") {
+        Some("malloc") => v1rpc::pool::PoolType::Malloc,
+        _ => v1rpc::pool::PoolType::Lvs,
+    };
 
     let response = ctx
         .v1
@@ -132,7 +193,7 @@ async fn create(
             name: name.clone(),
             uuid: uuid.map(ToString::to_string),
             disks: disks_list,
-            pooltype: v1rpc::pool::PoolType::Lvs as i32,
+            pooltype: pooltype as i32,
         })
         .await
         .context(GrpcStatus)?;
@@ -147,7 +208,7 @@ async fn create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &name);
         }
     };
@@ -182,6 +243,7 @@ async fn import(
             uuid: uuid.map(ToString::to_string),
             disks: disks_list,
             pooltype: v1rpc::pool::PoolType::Lvs as i32,
+            force: matches.is_present("force"),
         })
         .await
         .context(GrpcStatus)?;
@@ -196,7 +258,7 @@ async fn import(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &name);
         }
     };
@@ -214,6 +276,11 @@ async fn destroy(
             field: "pool".to_string(),
         })?
         .to_owned();
+    let dry_run = matches.is_present("dry-run");
+    if !confirm(matches, &format!("Destroy pool {name}")) {
+        println!("Aborted");
+        return Ok(());
+    }
 
     let _ = ctx
         .v1
@@ -221,13 +288,19 @@ async fn destroy(
         .destroy_pool(v1rpc::pool::DestroyPoolRequest {
             name: name.clone(),
             uuid: None,
+            validate_only: dry_run,
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
+    if dry_run {
+        ctx.v1(&format!("pool: {name} can be deleted"));
+        return Ok(());
+    }
 
     match ctx.output {
         OutputFormat::Json => {}
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("pool: {} is deleted", &name);
         }
     };
@@ -252,13 +325,14 @@ async fn export(
         .export_pool(v1rpc::pool::ExportPoolRequest {
             name: name.clone(),
             uuid: None,
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
 
     match ctx.output {
         OutputFormat::Json => {}
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("pool: {} is exported", &name);
         }
     };
@@ -266,6 +340,55 @@ async fn export(
     Ok(())
 }
 
+async fn fairness(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let name = matches
+        .value_of("pool")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "pool".to_string(),
+        })?
+        .to_owned();
+    let ratio = matches
+        .value_of("ratio")
+        .map(|_| value_t!(matches.value_of("ratio"), u32))
+        .transpose()
+        .unwrap_or_else(|e| e.exit());
+
+    let response = ctx
+        .v1
+        .pool
+        .set_pool_rebuild_fairness(v1rpc::pool::SetPoolRebuildFairnessRequest {
+            name: name.clone(),
+            uuid: None,
+            ratio,
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            println!(
+                "pool: {} rebuild fairness is {}%",
+                &name,
+                response.get_ref().rebuild_fairness
+            );
+        }
+    };
+
+    Ok(())
+}
+
 async fn list(
     mut ctx: Context,
     _matches: &ArgMatches<'_>,
@@ -293,7 +416,7 @@ async fn list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let pools: &Vec<v1rpc::pool::Pool> = &response.get_ref().pools;
             if pools.is_empty() {
                 ctx.v1("No pools found");
@@ -305,27 +428,110 @@ async fn list(
                 .map(|p| {
                     let cap = Byte::from_bytes(p.capacity.into());
                     let used = Byte::from_bytes(p.used.into());
+                    let committed = Byte::from_bytes(p.committed.into());
                     let state = pool_state_to_str(p.state);
-                    vec![
+                    let mut row = vec![
                         p.name.clone(),
                         p.uuid.clone(),
                         state.to_string(),
                         ctx.units(cap),
                         ctx.units(used),
+                        ctx.units(committed),
                         p.disks.join(" "),
-                    ]
+                    ];
+                    if ctx.wide() {
+                        row.push(p.commitment_ratio.to_string());
+                        row.push(p.overcommit_limit.to_string());
+                    }
+                    row
                 })
                 .collect();
-            ctx.print_list(
-                vec!["NAME", "UUID", "STATE", ">CAPACITY", ">USED", "DISKS"],
-                table,
-            );
+            let mut hdr = vec![
+                "NAME",
+                "UUID",
+                "STATE",
+                ">CAPACITY",
+                ">USED",
+                ">COMMITTED",
+                "DISKS",
+            ];
+            if ctx.wide() {
+                hdr.extend([">COMMITMENT_RATIO", ">OVERCOMMIT_LIMIT"]);
+            }
+            ctx.print_list(hdr, table);
         }
     };
 
     Ok(())
 }
 
+async fn check(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let disk = matches
+        .value_of("disk")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "disk".to_string(),
+        })?
+        .to_owned();
+
+    let response = ctx
+        .v1
+        .pool
+        .check_pool(v1rpc::pool::CheckPoolRequest {
+            disk,
+            repair: matches.is_present("repair"),
+        })
+        .await
+        .context(GrpcStatus)?;
+    let mut resp = response.into_inner();
+
+    match ctx.output {
+        OutputFormat::Json => {
+            while let Some(response) = resp.next().await {
+                let response = response.context(GrpcStatus)?;
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&response)
+                        .unwrap()
+                        .to_colored_json_auto()
+                        .unwrap()
+                );
+            }
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let (s, r) = tokio::sync::mpsc::channel(10);
+            tokio::spawn(async move {
+                while let Some(response) = resp.next().await {
+                    let response = response.map(|response| {
+                        vec![
+                            check_severity_to_str(response.severity)
+                                .to_string(),
+                            response.message,
+                        ]
+                    });
+                    s.send(response).await.unwrap();
+                }
+            });
+            ctx.print_streamed_list(vec!["SEVERITY", "MESSAGE"], r)
+                .await
+                .context(GrpcStatus)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_severity_to_str(idx: i32) -> &'static str {
+    match v1rpc::pool::CheckSeverity::from_i32(idx) {
+        Some(v1rpc::pool::CheckSeverity::Info) => "info",
+        Some(v1rpc::pool::CheckSeverity::Warning) => "warning",
+        Some(v1rpc::pool::CheckSeverity::Error) => "error",
+        None => "unknown",
+    }
+}
+
 fn pool_state_to_str(idx: i32) -> &'static str {
     match v1rpc::pool::PoolState::from_i32(idx).unwrap() {
         v1rpc::pool::PoolState::PoolUnknown => "unknown",
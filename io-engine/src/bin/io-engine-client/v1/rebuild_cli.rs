@@ -6,11 +6,13 @@ use crate::{
     ClientError,
     GrpcStatus,
 };
-use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use byte_unit::Byte;
+use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::ToColoredJson;
 use mayastor_api::v1;
 use snafu::ResultExt;
-use tonic::Status;
+use std::{io::Write, time::Duration};
+use tonic::{Code, Status};
 
 pub async fn handler(
     ctx: Context,
@@ -25,6 +27,8 @@ pub async fn handler(
         ("stats", Some(args)) => stats(ctx, args).await,
         ("progress", Some(args)) => progress(ctx, args).await,
         ("history", Some(args)) => history(ctx, args).await,
+        ("pending", Some(args)) => pending(ctx, args).await,
+        ("watch", Some(args)) => watch(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
                 .context(GrpcStatus)
@@ -46,7 +50,8 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(2)
                 .help("uri of child to start rebuilding"),
-        );
+        )
+        .arg(priority_arg());
 
     let stop = SubCommand::with_name("stop")
         .about("stops a rebuild")
@@ -147,6 +152,38 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help("uuid of the nexus"),
         );
 
+    let pending = SubCommand::with_name("pending")
+        .about("lists the rebuilds queued by the rebuild scheduler")
+        .arg(
+            Arg::with_name("uuid")
+                .required(false)
+                .index(1)
+                .help("uuid of the nexus to list the queued rebuilds of"),
+        );
+
+    let watch = SubCommand::with_name("watch")
+        .about("follows the progress of a rebuild until it ends")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of the nexus"),
+        )
+        .arg(
+            Arg::with_name("uri")
+                .required(true)
+                .index(2)
+                .help("uri of child to follow the rebuild of"),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .short("i")
+                .long("interval")
+                .value_name("SECONDS")
+                .default_value("1")
+                .help("interval between the progress updates"),
+        );
+
     SubCommand::with_name("rebuild")
         .settings(&[
             AppSettings::SubcommandRequiredElseHelp,
@@ -162,6 +199,30 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .subcommand(stats)
         .subcommand(progress)
         .subcommand(history)
+        .subcommand(pending)
+        .subcommand(watch)
+}
+
+/// Argument giving the priority of the rebuilds of a child.
+pub(super) fn priority_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("priority")
+        .long("priority")
+        .takes_value(true)
+        .value_name("PRIORITY")
+        .possible_values(&["low", "normal", "high"])
+        .help("priority of the rebuild over the other queued rebuilds")
+}
+
+/// Parses the rebuild priority argument, if given.
+pub(super) fn parse_priority(matches: &ArgMatches<'_>) -> Option<i32> {
+    matches.value_of("priority").map(|p| {
+        let priority = match p {
+            "low" => v1::nexus::RebuildPriority::Low,
+            "high" => v1::nexus::RebuildPriority::High,
+            _ => v1::nexus::RebuildPriority::Normal,
+        };
+        priority as i32
+    })
 }
 
 async fn start(
@@ -187,6 +248,7 @@ async fn start(
         .start_rebuild(v1::nexus::StartRebuildRequest {
             nexus_uuid: uuid,
             uri: uri.clone(),
+            priority: parse_priority(matches),
         })
         .await
         .context(GrpcStatus)?;
@@ -200,7 +262,7 @@ async fn start(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -241,7 +303,7 @@ async fn stop(mut ctx: Context, matches: &ArgMatches<'_>) -> crate::Result<()> {
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -286,7 +348,7 @@ async fn pause(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -331,7 +393,7 @@ async fn resume(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &uri);
         }
     };
@@ -375,7 +437,7 @@ async fn state(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             ctx.print_list(
                 vec!["state"],
                 vec![vec![response.get_ref().state.clone()]],
@@ -386,6 +448,75 @@ async fn state(
     Ok(())
 }
 
+async fn pending(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let response = ctx
+        .v1
+        .nexus
+        .list_pending_rebuilds(v1::nexus::ListPendingRebuildsRequest {
+            nexus_uuid: matches.value_of("uuid").map(str::to_string),
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let response = &response.get_ref();
+            if response.pending.is_empty() {
+                ctx.v1("No pending rebuild");
+                return Ok(());
+            }
+            let table = response
+                .pending
+                .iter()
+                .map(|p| {
+                    vec![
+                        p.position.to_string(),
+                        p.nexus_uuid.clone(),
+                        p.dst_uri.clone(),
+                        p.src_uri.clone(),
+                        p.healthy_children.to_string(),
+                        v1::nexus::RebuildPriority::from_i32(p.priority)
+                            .map(|p| format!("{p:?}").to_lowercase())
+                            .unwrap_or_default(),
+                        p.nexus_limited.to_string(),
+                        p.queued_at
+                            .as_ref()
+                            .map(|t| t.to_string())
+                            .unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            ctx.print_list(
+                vec![
+                    ">POSITION",
+                    "NEXUS",
+                    "CHILD",
+                    "SOURCE",
+                    ">HEALTHY",
+                    "PRIORITY",
+                    "NEXUS_LIMITED",
+                    "QUEUED_AT",
+                ],
+                table,
+            );
+        }
+    };
+
+    Ok(())
+}
+
 async fn history(
     mut ctx: Context,
     matches: &ArgMatches<'_>,
@@ -415,7 +546,7 @@ async fn history(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let response = &response.get_ref();
             if response.records.is_empty() {
                 return Ok(());
@@ -503,7 +634,7 @@ async fn stats(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let response = &response.get_ref();
             ctx.print_list(
                 vec![
@@ -517,6 +648,8 @@ async fn stats(
                     ">PARTIAL",
                     ">TASKS_TOTAL",
                     ">TASKS_ACTIVE",
+                    ">THROUGHPUT",
+                    ">ETA",
                 ],
                 vec![vec![
                     response.blocks_total.to_string(),
@@ -529,8 +662,34 @@ async fn stats(
                     response.is_partial.to_string(),
                     response.tasks_total.to_string(),
                     response.tasks_active.to_string(),
+                    format!(
+                        "{}/s",
+                        Byte::from_bytes(response.throughput as u128)
+                            .get_appropriate_unit(true)
+                    ),
+                    response
+                        .eta
+                        .clone()
+                        .and_then(|d| Duration::try_from(d).ok())
+                        .map_or("-".to_string(), |d| {
+                            humantime::format_duration(Duration::from_secs(
+                                d.as_secs(),
+                            ))
+                            .to_string()
+                        }),
                 ]],
             );
+            println!("{}", progress_bar(response.progress));
+            if ctx.wide() && !response.sources.is_empty() {
+                ctx.print_list(
+                    vec!["SOURCE", ">BYTES_READ"],
+                    response
+                        .sources
+                        .iter()
+                        .map(|s| vec![s.uri.clone(), s.bytes_read.to_string()])
+                        .collect(),
+                );
+            }
         }
     };
 
@@ -574,7 +733,7 @@ async fn progress(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             ctx.print_list(
                 vec!["progress (%)"],
                 vec![vec![response.get_ref().progress.to_string()]],
@@ -584,6 +743,133 @@ async fn progress(
     Ok(())
 }
 
+async fn watch(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let uuid = matches
+        .value_of("uuid")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "uuid".to_string(),
+        })?
+        .to_string();
+    let uri = matches
+        .value_of("uri")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "uri".to_string(),
+        })?
+        .to_string();
+    let interval = value_t!(matches.value_of("interval"), u64)
+        .unwrap_or_else(|e| e.exit());
+
+    ctx.v2(&format!(
+        "Watching the rebuild of child {uri} on nexus {uuid}"
+    ));
+    loop {
+        let stats = ctx
+            .v1
+            .nexus
+            .get_rebuild_stats(v1::nexus::RebuildStatsRequest {
+                nexus_uuid: uuid.clone(),
+                uri: uri.clone(),
+            })
+            .await;
+        // The rebuild job is removed once it ends: its outcome is in the
+        // rebuild history of the nexus.
+        let stats = match stats {
+            Ok(stats) => stats.into_inner(),
+            Err(status) if status.code() == Code::NotFound => break,
+            Err(status) => return Err(status).context(GrpcStatus),
+        };
+        let state = ctx
+            .v1
+            .nexus
+            .get_rebuild_state(v1::nexus::RebuildStateRequest {
+                nexus_uuid: uuid.clone(),
+                uri: uri.clone(),
+            })
+            .await
+            .map(|r| r.into_inner().state)
+            .unwrap_or_default();
+
+        match ctx.output {
+            OutputFormat::Json => {
+                println!(
+                    "{}",
+                    serde_json::to_string(&stats)
+                        .unwrap()
+                        .to_colored_json_auto()
+                        .unwrap()
+                );
+            }
+            OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+                print!("\r{} {state:<10}", progress_bar(stats.progress));
+                std::io::stdout().flush().ok();
+            }
+        }
+
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+
+    let history = ctx
+        .v1
+        .nexus
+        .get_rebuild_history(v1::nexus::RebuildHistoryRequest {
+            uuid,
+        })
+        .await
+        .context(GrpcStatus)?
+        .into_inner();
+    let Some(record) = history
+        .records
+        .into_iter()
+        .rev()
+        .find(|r| r.child_uri == uri)
+    else {
+        if ctx.output != OutputFormat::Json {
+            println!();
+        }
+        return Err(Status::not_found(format!(
+            "no rebuild found for child {uri}"
+        )))
+        .context(GrpcStatus);
+    };
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string(&record)
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let progress = (record.blocks_transferred * 100)
+                .checked_div(record.blocks_total)
+                .unwrap_or(100);
+            let state = v1::nexus::RebuildJobState::from_i32(record.state)
+                .map(rebuild_state_to_str)
+                .unwrap_or("unknown");
+            println!("\r{} {state:<10}", progress_bar(progress));
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders the given rebuild progress, in percent, as a progress bar.
+fn progress_bar(progress: u64) -> String {
+    const WIDTH: u64 = 40;
+    let filled = progress.min(100) * WIDTH / 100;
+    format!(
+        "[{}{}] {progress:>3}%",
+        "#".repeat(filled as usize),
+        "-".repeat((WIDTH - filled) as usize)
+    )
+}
+
 fn rebuild_state_to_str(s: v1::nexus::RebuildJobState) -> &'static str {
     match s {
         v1::nexus::RebuildJobState::Init => "init",
@@ -1,3 +1,4 @@
+use super::{confirm, destructive_args};
 use crate::{
     context::{Context, OutputFormat},
     parse_size,
@@ -7,10 +8,46 @@ use crate::{
 use byte_unit::Byte;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::ToColoredJson;
+use futures::StreamExt;
 use mayastor_api::{v0 as rpc, v1 as v1_rpc};
 use snafu::ResultExt;
 use tonic::{Code, Status};
 
+/// Arguments identifying the owner of a replica, and overriding it.
+fn owner_args<'a, 'b>(override_owner: bool) -> Vec<Arg<'a, 'b>> {
+    let mut args = vec![
+        Arg::with_name("owner-volume")
+            .long("owner-volume")
+            .takes_value(true)
+            .value_name("VOLUME-UUID")
+            .requires("owner-control-plane")
+            .help("Uuid of the volume owning the replica"),
+        Arg::with_name("owner-control-plane")
+            .long("owner-control-plane")
+            .takes_value(true)
+            .value_name("ID")
+            .requires("owner-volume")
+            .help("Control plane instance owning the replica"),
+    ];
+    if override_owner {
+        args.push(
+            Arg::with_name("override-owner")
+                .long("override-owner")
+                .help("Proceed even if the replica has another owner"),
+        );
+    }
+    args
+}
+
+fn parse_owner(
+    matches: &ArgMatches<'_>,
+) -> Option<v1_rpc::replica::ReplicaOwner> {
+    Some(v1_rpc::replica::ReplicaOwner {
+        volume_uuid: matches.value_of("owner-volume")?.to_string(),
+        control_plane: matches.value_of("owner-control-plane")?.to_string(),
+    })
+}
+
 pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
     let create = SubCommand::with_name("create")
         .about("Create replica on pool")
@@ -48,6 +85,11 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .long("thin")
                 .takes_value(false)
                 .help("Whether replica is thin provisioned (default false)"))
+        .arg(
+            Arg::with_name("compress")
+                .long("compress")
+                .takes_value(false)
+                .help("Whether replica is compressed, implies thin provisioning (default false)"))
         .arg(
             Arg::with_name("allowed-host")
                 .long("allowed-host")
@@ -57,7 +99,8 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help(
                     "NQN of hosts which are allowed to connect to the target",
                 ),
-        );
+        )
+        .args(&owner_args(false));
 
     let destroy = SubCommand::with_name("destroy")
         .about("Destroy replica")
@@ -82,7 +125,9 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .conflicts_with("pool-uuid")
                 .help("Name of the pool where replica resides"),
-        );
+        )
+        .args(&owner_args(true))
+        .args(&destructive_args());
 
     let share = SubCommand::with_name("share").about("Share replica over specified protocol")
         .arg(
@@ -101,7 +146,18 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .takes_value(true)
                 .multiple(true)
                 .required(false)
-                .help("Name of a protocol (nvmf) used for sharing or \"none\" to unshare the replica"));
+                .help("Name of a protocol (nvmf) used for sharing or \"none\" to unshare the replica"))
+        .arg(Arg::with_name("eui64").long("eui64").takes_value(true)
+            .help("NVMe EUI64 of the namespace, 16 hex digits"))
+        .arg(Arg::with_name("nguid").long("nguid").takes_value(true)
+            .help("NVMe NGUID of the namespace, 32 hex digits"))
+        .arg(Arg::with_name("serial").long("serial").takes_value(true)
+            .help("NVMe serial number of the subsystem"))
+        .arg(Arg::with_name("model").long("model").takes_value(true)
+            .help("NVMe model number of the subsystem"))
+        .arg(Arg::with_name("nqn").long("nqn").takes_value(true)
+            .help("NQN of the subsystem, or a suffix of the mayastor NQN prefix"))
+        .args(&owner_args(true));
     let unshare = SubCommand::with_name("unshare")
         .about("Unshare replica")
         .arg(
@@ -109,6 +165,46 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(1)
                 .help("Replica uuid"),
+        )
+        .args(&owner_args(true));
+    let state = SubCommand::with_name("state")
+        .about("Set the state of a replica")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("Replica uuid"),
+        )
+        .arg(
+            Arg::with_name("state")
+                .required(true)
+                .index(2)
+                .possible_values(&["online", "read-only", "maintenance"])
+                .help("New state of the replica"),
+        );
+    let verify = SubCommand::with_name("verify")
+        .about("Check that the allocated clusters of a replica can be read")
+        .arg(
+            Arg::with_name("uuid")
+                .required(true)
+                .index(1)
+                .help("Replica uuid"),
+        )
+        .arg(
+            Arg::with_name("snapshot")
+                .short("s")
+                .long("snapshot")
+                .takes_value(true)
+                .value_name("SNAPSHOT-UUID")
+                .help("Snapshot the replica data is compared with"),
+        )
+        .arg(
+            Arg::with_name("chunk-size")
+                .short("c")
+                .long("chunk-size")
+                .takes_value(true)
+                .value_name("CHUNK-SIZE")
+                .help("Reporting back stats after each chunk is verified"),
         );
     SubCommand::with_name("replica")
         .settings(&[
@@ -121,6 +217,8 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .subcommand(destroy)
         .subcommand(share)
         .subcommand(unshare)
+        .subcommand(state)
+        .subcommand(verify)
         .subcommand(SubCommand::with_name("list").about("List replicas"))
         .subcommand(
             SubCommand::with_name("stats").about("IO stats of replicas"),
@@ -137,7 +235,9 @@ pub async fn handler(
         ("list", Some(args)) => replica_list(ctx, args).await,
         ("share", Some(args)) => replica_share(ctx, args).await,
         ("unshare", Some(args)) => replica_unshare(ctx, args).await,
+        ("state", Some(args)) => replica_set_state(ctx, args).await,
         ("stats", Some(args)) => replica_stat(ctx, args).await,
+        ("verify", Some(args)) => replica_verify(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
                 .context(GrpcStatus)
@@ -174,7 +274,8 @@ async fn replica_create(
     })?)
     .map_err(|s| Status::invalid_argument(format!("Bad size '{s}'")))
     .context(GrpcStatus)?;
-    let thin = matches.is_present("thin");
+    let compress = matches.is_present("compress");
+    let thin = matches.is_present("thin") || compress;
     let share = parse_replica_protocol(matches.value_of("protocol"))
         .context(GrpcStatus)?;
     let allowed_hosts =
@@ -188,6 +289,9 @@ async fn replica_create(
         share,
         size: size.get_bytes() as u64,
         allowed_hosts,
+        compress,
+        owner: parse_owner(matches),
+        ..Default::default()
     };
 
     let response = ctx
@@ -207,7 +311,7 @@ async fn replica_create(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -239,19 +343,33 @@ async fn replica_destroy(
         }),
     };
 
+    let dry_run = matches.is_present("dry-run");
+    if !confirm(matches, &format!("Destroy replica {uuid}")) {
+        println!("Aborted");
+        return Ok(());
+    }
+
     let _ = ctx
         .v1
         .replica
         .destroy_replica(v1_rpc::replica::DestroyReplicaRequest {
             uuid: uuid.clone(),
             pool,
+            validate_only: dry_run,
+            owner: parse_owner(matches),
+            override_owner: matches.is_present("override-owner"),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
+    if dry_run {
+        ctx.v1(&format!("replica: {uuid} can be deleted"));
+        return Ok(());
+    }
 
     match ctx.output {
         OutputFormat::Json => {}
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("replica: {} is deleted", &uuid);
         }
     }
@@ -286,7 +404,7 @@ async fn replica_list(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let replicas = &response.get_ref().replicas;
             if replicas.is_empty() {
                 ctx.v1("No replicas found");
@@ -303,7 +421,7 @@ async fn replica_list(
                         .units(Byte::from_bytes(usage.capacity_bytes.into()));
                     let allocated = ctx
                         .units(Byte::from_bytes(usage.allocated_bytes.into()));
-                    vec![
+                    let mut row = vec![
                         r.poolname.clone(),
                         r.name.clone(),
                         r.uuid.clone(),
@@ -320,27 +438,52 @@ async fn replica_list(
                             .allocated_bytes_snapshot_from_clone
                             .unwrap_or_default()
                             .to_string(),
-                    ]
+                    ];
+                    if ctx.wide() {
+                        row.push(r.pooluuid.clone());
+                        row.push(
+                            r.snapshot_uuid
+                                .clone()
+                                .unwrap_or_else(|| "-".to_string()),
+                        );
+                        row.push(if r.allowed_hosts.is_empty() {
+                            "-".to_string()
+                        } else {
+                            r.allowed_hosts.join(",")
+                        });
+                        row.push(r.num_snapshots.to_string());
+                        row.push(r.num_clones.to_string());
+                        row.push(r.num_connections.to_string());
+                    }
+                    row
                 })
                 .collect();
-            ctx.print_list(
-                vec![
-                    "POOL",
-                    "NAME",
-                    "UUID",
-                    ">THIN",
-                    ">SHARE",
-                    ">SIZE",
-                    ">CAP",
-                    ">ALLOC",
-                    "URI",
-                    "IS_SNAPSHOT",
-                    "IS_CLONE",
-                    "SNAP_ANCESTOR_SIZE",
-                    "CLONE_SNAP_ANCESTOR_SIZE",
-                ],
-                table,
-            );
+            let mut hdr = vec![
+                "POOL",
+                "NAME",
+                "UUID",
+                ">THIN",
+                ">SHARE",
+                ">SIZE",
+                ">CAP",
+                ">ALLOC",
+                "URI",
+                "IS_SNAPSHOT",
+                "IS_CLONE",
+                "SNAP_ANCESTOR_SIZE",
+                "CLONE_SNAP_ANCESTOR_SIZE",
+            ];
+            if ctx.wide() {
+                hdr.extend([
+                    "POOL_UUID",
+                    "SNAP_UUID",
+                    "ALLOWED_HOSTS",
+                    ">SNAPSHOTS",
+                    ">CLONES",
+                    ">CONNECTIONS",
+                ]);
+            }
+            ctx.print_list(hdr, table);
         }
     };
 
@@ -364,6 +507,14 @@ async fn replica_share(
             uuid,
             share,
             allowed_hosts,
+            eui64: matches.value_of("eui64").unwrap_or_default().to_string(),
+            nguid: matches.value_of("nguid").unwrap_or_default().to_string(),
+            serial: matches.value_of("serial").unwrap_or_default().to_string(),
+            model: matches.value_of("model").unwrap_or_default().to_string(),
+            nqn: matches.value_of("nqn").unwrap_or_default().to_string(),
+            owner: parse_owner(matches),
+            override_owner: matches.is_present("override-owner"),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -377,7 +528,7 @@ async fn replica_share(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -394,6 +545,9 @@ async fn replica_unshare(
         .replica
         .unshare_replica(v1_rpc::replica::UnshareReplicaRequest {
             uuid,
+            owner: parse_owner(matches),
+            override_owner: matches.is_present("override-owner"),
+            ..Default::default()
         })
         .await
         .context(GrpcStatus)?;
@@ -408,7 +562,7 @@ async fn replica_unshare(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("{}", &response.get_ref().uri);
         }
     };
@@ -416,6 +570,45 @@ async fn replica_unshare(
     Ok(())
 }
 
+async fn replica_set_state(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let uuid = matches.value_of("uuid").unwrap().to_owned();
+    let state = match matches.value_of("state").unwrap() {
+        "read-only" => v1_rpc::replica::ReplicaState::ReadOnly,
+        "maintenance" => v1_rpc::replica::ReplicaState::Maintenance,
+        _ => v1_rpc::replica::ReplicaState::Online,
+    };
+    let response = ctx
+        .v1
+        .replica
+        .set_replica_state(v1_rpc::replica::SetReplicaStateRequest {
+            uuid,
+            state: state as i32,
+            ..Default::default()
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            println!("{}", &response.get_ref().uuid);
+        }
+    };
+
+    Ok(())
+}
+
 // TODO : There's no v1 rpc for stat.
 async fn replica_stat(
     mut ctx: Context,
@@ -437,7 +630,7 @@ async fn replica_stat(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let replicas = &response.get_ref().replicas;
             if replicas.is_empty() {
                 ctx.v1("No replicas have been created");
@@ -471,6 +664,106 @@ async fn replica_stat(
     Ok(())
 }
 
+async fn replica_verify(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let uuid = matches
+        .value_of("uuid")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "uuid".to_string(),
+        })?
+        .to_owned();
+    let chunk_size = parse_size(matches.value_of("chunk-size").unwrap_or("0"))
+        .map_err(|s| Status::invalid_argument(format!("Bad size '{s}'")))
+        .context(GrpcStatus)?;
+
+    let response = ctx
+        .v1
+        .replica
+        .verify_replica(v1_rpc::replica::VerifyReplicaRequest {
+            uuid,
+            snapshot_uuid: matches.value_of("snapshot").map(str::to_string),
+            chunk_size: chunk_size.get_bytes() as u64,
+        })
+        .await
+        .context(GrpcStatus)?;
+    let mut resp = response.into_inner();
+
+    match ctx.output {
+        OutputFormat::Json => {
+            while let Some(response) = resp.next().await {
+                let response = response.context(GrpcStatus)?;
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&response)
+                        .unwrap()
+                        .to_colored_json_auto()
+                        .unwrap()
+                );
+            }
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let header = vec![
+                "UUID",
+                ">TOTAL_BYTES",
+                ">VERIFIED_BYTES",
+                ">REMAINING_BYTES",
+                ">IO_ERROR_BYTES",
+                ">MISMATCH_BYTES",
+                "BAD_EXTENTS",
+            ];
+
+            let (s, r) = tokio::sync::mpsc::channel(10);
+            tokio::spawn(async move {
+                while let Some(response) = resp.next().await {
+                    let response = response.map(|response| {
+                        let extents = response
+                            .extents
+                            .iter()
+                            .map(|e| {
+                                format!(
+                                    "{}:{}+{}",
+                                    verify_extent_type_to_str(e.kind),
+                                    e.offset,
+                                    e.length
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        vec![
+                            response.uuid,
+                            response.total_bytes.to_string(),
+                            response.verified_bytes.to_string(),
+                            response.remaining_bytes.to_string(),
+                            response.io_error_bytes.to_string(),
+                            response.mismatch_bytes.to_string(),
+                            if extents.is_empty() {
+                                "-".to_string()
+                            } else {
+                                extents.join(",")
+                            },
+                        ]
+                    });
+                    s.send(response).await.unwrap();
+                }
+            });
+            ctx.print_streamed_list(header, r)
+                .await
+                .context(GrpcStatus)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_extent_type_to_str(idx: i32) -> &'static str {
+    match v1_rpc::replica::VerifyExtentType::from_i32(idx) {
+        Some(v1_rpc::replica::VerifyExtentType::IoError) => "io-error",
+        Some(v1_rpc::replica::VerifyExtentType::Mismatch) => "mismatch",
+        None => "unknown",
+    }
+}
+
 fn parse_replica_protocol(pcol: Option<&str>) -> Result<i32, Status> {
     match pcol {
         None => Ok(v1_rpc::common::ShareProtocol::None as i32),
@@ -6,6 +6,7 @@ use crate::{
     ClientError,
     GrpcStatus,
 };
+use byte_unit::Byte;
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored_json::ToColoredJson;
 use mayastor_api::v1 as v1_rpc;
@@ -17,6 +18,14 @@ pub async fn handler(
     matches: &ArgMatches<'_>,
 ) -> crate::Result<()> {
     match matches.subcommand() {
+        ("create", Some(args)) => match args.subcommand() {
+            ("replica", Some(args)) => create_for_replica(ctx, args).await,
+            ("nexus", Some(args)) => create_for_nexus(ctx, args).await,
+            (cmd, _) => {
+                Err(Status::not_found(format!("command {cmd} does not exist")))
+                    .context(GrpcStatus)
+            }
+        },
         ("create_for_nexus", Some(args)) => create_for_nexus(ctx, args).await,
         ("create_for_replica", Some(args)) => {
             create_for_replica(ctx, args).await
@@ -24,7 +33,9 @@ pub async fn handler(
         ("list", Some(args)) => list(ctx, args).await,
         ("destroy", Some(args)) => destroy(ctx, args).await,
         ("create_clone", Some(args)) => create_clone(ctx, args).await,
+        ("restore", Some(args)) => restore(ctx, args).await,
         ("list_clone", Some(args)) => list_clone(ctx, args).await,
+        ("flatten_clone", Some(args)) => flatten_clone(ctx, args).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
                 .context(GrpcStatus)
@@ -33,91 +44,64 @@ pub async fn handler(
 }
 
 pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
-    let create_for_nexus = SubCommand::with_name("create_for_nexus")
-        .about("Create a snapshot for nexus")
+    let create_for_nexus = nexus_snapshot_subcommand("create_for_nexus");
+    let create_for_replica = replica_snapshot_subcommand("create_for_replica");
+    let create = SubCommand::with_name("create")
+        .settings(&[
+            AppSettings::SubcommandRequiredElseHelp,
+            AppSettings::ColoredHelp,
+            AppSettings::ColorAlways,
+        ])
+        .about("Create a snapshot for a replica or a nexus")
+        .subcommand(replica_snapshot_subcommand("replica"))
+        .subcommand(nexus_snapshot_subcommand("nexus"));
+    let list = SubCommand::with_name("list")
+        .about("List snapshots details")
         .arg(
-            Arg::with_name("nexus_uuid")
-                .required(true)
+            Arg::with_name("source_uuid")
+                .required(false)
                 .index(1)
-                .help("uuid of the nexus"),
-        )
-        .arg(
-            Arg::with_name("entity_id")
-                .required(true)
-                .index(2)
-                .help("Entity Id"),
-        )
-        .arg(
-            Arg::with_name("txn_id")
-                .required(true)
-                .index(3)
-                .help("Transaction id"),
-        )
-        .arg(
-            Arg::with_name("snapshot_name")
-                .required(true)
-                .index(4)
-                .help("Snapshot name"),
-        )
-        .arg(
-            Arg::with_name("replica_uuid")
-                .required(true)
-                .index(5)
-                .help("replica uuid"),
+                .help("Source uuid from which snapshot is created"),
         )
         .arg(
             Arg::with_name("snapshot_uuid")
-                .required(true)
-                .index(6)
-                .help("snapshot uuid"),
-        );
-    let create_for_replica = SubCommand::with_name("create_for_replica")
-        .about("Create a snapshot for replica")
-        .arg(
-            Arg::with_name("replica_uuid")
-                .required(true)
-                .index(1)
-                .help("Replica uuid"),
-        )
-        .arg(
-            Arg::with_name("snapshot_name")
-                .required(true)
+                .required(false)
                 .index(2)
-                .help("Snapshot name"),
+                .help("Snapshot uuid"),
         )
         .arg(
-            Arg::with_name("entity_id")
-                .required(true)
-                .index(3)
-                .help("Entity Id"),
+            Arg::with_name("pool")
+                .long("pool")
+                .required(false)
+                .takes_value(true)
+                .help("Name or uuid of the pool where snapshots reside"),
         )
         .arg(
-            Arg::with_name("txn_id")
-                .required(true)
-                .index(4)
-                .help("Transaction id"),
+            Arg::with_name("valid")
+                .long("valid")
+                .conflicts_with("invalid")
+                .help("List only the valid snapshots"),
         )
         .arg(
-            Arg::with_name("snapshot_uuid")
-                .required(true)
-                .index(5)
-                .help("Snapshot uuid"),
-        );
-    let list = SubCommand::with_name("list")
-        .about("List snapshots details")
+            Arg::with_name("invalid")
+                .long("invalid")
+                .conflicts_with("valid")
+                .help("List only the invalid snapshots"),
+        )
         .arg(
-            Arg::with_name("source_uuid")
-                .required(false)
-                .index(1)
-                .help("Source uuid from which snapshot is created"),
+            Arg::with_name("discarded")
+                .long("discarded")
+                .conflicts_with("not-discarded")
+                .help("List only the discarded snapshots"),
         )
         .arg(
-            Arg::with_name("snapshot_uuid")
-                .required(false)
-                .index(2)
-                .help("Snapshot uuid"),
+            Arg::with_name("not-discarded")
+                .long("not-discarded")
+                .conflicts_with("discarded")
+                .help("List only the snapshots which are not discarded"),
         );
     let destroy = SubCommand::with_name("destroy")
+        .alias("delete")
         .about("Destroy snapshot")
         .arg(
             Arg::with_name("snapshot_uuid")
@@ -142,6 +126,7 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help("Name of the pool where snapshot resides"),
         );
     let create_clone = SubCommand::with_name("create_clone")
+        .alias("clone")
         .about("Create a clone from snapshot")
         .arg(
             Arg::with_name("snapshot_uuid")
@@ -169,6 +154,34 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .index(1)
                 .help("Snapshot uuid"),
         );
+    let restore = SubCommand::with_name("restore")
+        .about("Restore a snapshot into a new replica cloned from it")
+        .arg(
+            Arg::with_name("snapshot_uuid")
+                .required(true)
+                .index(1)
+                .help("Snapshot uuid"),
+        )
+        .arg(
+            Arg::with_name("replica_name")
+                .required(true)
+                .index(2)
+                .help("Name of the restored replica"),
+        )
+        .arg(
+            Arg::with_name("replica_uuid")
+                .required(true)
+                .index(3)
+                .help("Uuid of the restored replica"),
+        );
+    let flatten_clone = SubCommand::with_name("flatten_clone")
+        .about("Detach a clone from its snapshot chain")
+        .arg(
+            Arg::with_name("clone_uuid")
+                .required(true)
+                .index(1)
+                .help("Clone uuid"),
+        );
     SubCommand::with_name("snapshot")
         .settings(&[
             AppSettings::SubcommandRequiredElseHelp,
@@ -176,12 +189,93 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
             AppSettings::ColorAlways,
         ])
         .about("Snapshot management")
+        .subcommand(create)
         .subcommand(create_for_nexus)
         .subcommand(create_for_replica)
         .subcommand(list)
         .subcommand(destroy)
         .subcommand(create_clone)
+        .subcommand(restore)
         .subcommand(list_clone)
+        .subcommand(flatten_clone)
+}
+
+/// Subcommand creating a snapshot for a nexus, with the given name.
+fn nexus_snapshot_subcommand<'a, 'b>(name: &str) -> App<'a, 'b> {
+    SubCommand::with_name(name)
+        .about("Create a snapshot for nexus")
+        .arg(
+            Arg::with_name("nexus_uuid")
+                .required(true)
+                .index(1)
+                .help("uuid of the nexus"),
+        )
+        .arg(
+            Arg::with_name("entity_id")
+                .required(true)
+                .index(2)
+                .help("Entity Id"),
+        )
+        .arg(
+            Arg::with_name("txn_id")
+                .required(true)
+                .index(3)
+                .help("Transaction id"),
+        )
+        .arg(
+            Arg::with_name("snapshot_name")
+                .required(true)
+                .index(4)
+                .help("Snapshot name"),
+        )
+        .arg(
+            Arg::with_name("replica_uuid")
+                .required(true)
+                .index(5)
+                .help("replica uuid"),
+        )
+        .arg(
+            Arg::with_name("snapshot_uuid")
+                .required(true)
+                .index(6)
+                .help("snapshot uuid"),
+        )
+}
+
+/// Subcommand creating a snapshot for a replica, with the given name.
+fn replica_snapshot_subcommand<'a, 'b>(name: &str) -> App<'a, 'b> {
+    SubCommand::with_name(name)
+        .about("Create a snapshot for replica")
+        .arg(
+            Arg::with_name("replica_uuid")
+                .required(true)
+                .index(1)
+                .help("Replica uuid"),
+        )
+        .arg(
+            Arg::with_name("snapshot_name")
+                .required(true)
+                .index(2)
+                .help("Snapshot name"),
+        )
+        .arg(
+            Arg::with_name("entity_id")
+                .required(true)
+                .index(3)
+                .help("Entity Id"),
+        )
+        .arg(
+            Arg::with_name("txn_id")
+                .required(true)
+                .index(4)
+                .help("Transaction id"),
+        )
+        .arg(
+            Arg::with_name("snapshot_uuid")
+                .required(true)
+                .index(5)
+                .help("Snapshot uuid"),
+        )
 }
 
 async fn create_for_nexus(
@@ -268,7 +362,7 @@ async fn create_for_nexus(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let replica_done = &response.get_ref().replicas_done;
             let nexus = &response.get_ref().nexus;
 
@@ -361,7 +455,7 @@ async fn create_for_replica(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let snapshots = &response.get_ref().snapshot;
             let table = snapshots
                 .iter()
@@ -408,10 +502,22 @@ async fn create_for_replica(
 async fn list(mut ctx: Context, matches: &ArgMatches<'_>) -> crate::Result<()> {
     let source_uuid = matches.value_of("source_uuid").map(|s| s.to_owned());
     let snapshot_uuid = matches.value_of("snapshot_uuid").map(|s| s.to_owned());
+    let flag = |set: &str, unset: &str| {
+        if matches.is_present(set) {
+            Some(true)
+        } else if matches.is_present(unset) {
+            Some(false)
+        } else {
+            None
+        }
+    };
     let request = v1_rpc::snapshot::ListSnapshotsRequest {
         source_uuid,
         snapshot_uuid,
-        query: None,
+        query: Some(v1_rpc::snapshot::list_snapshots_request::Query {
+            invalid: flag("invalid", "valid"),
+            discarded: flag("discarded", "not-discarded"),
+        }),
     };
 
     let response = ctx
@@ -420,40 +526,69 @@ async fn list(mut ctx: Context, matches: &ArgMatches<'_>) -> crate::Result<()> {
         .list_snapshot(request)
         .await
         .context(GrpcStatus)?;
+    let mut snapshots = response.into_inner().snapshots;
+    if let Some(pool) = matches.value_of("pool") {
+        snapshots.retain(|s| s.pool_uuid == pool || s.pool_name == pool);
+    }
+
     match ctx.output {
         OutputFormat::Json => {
             println!(
                 "{}",
-                serde_json::to_string_pretty(&response.get_ref())
-                    .unwrap()
-                    .to_colored_json_auto()
-                    .unwrap()
+                serde_json::to_string_pretty(
+                    &v1_rpc::snapshot::ListSnapshotsResponse {
+                        snapshots,
+                    }
+                )
+                .unwrap()
+                .to_colored_json_auto()
+                .unwrap()
             );
         }
-        OutputFormat::Default => {
-            let snapshots = &response.get_ref().snapshots;
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             if snapshots.is_empty() {
                 ctx.v1("No snapshots found");
                 return Ok(());
             }
 
+            // The parent of a snapshot is the snapshot its source replica has
+            // been cloned from, if any.
+            let clones = ctx
+                .v1
+                .snapshot
+                .list_snapshot_clone(
+                    v1_rpc::snapshot::ListSnapshotCloneRequest {
+                        snapshot_uuid: None,
+                    },
+                )
+                .await
+                .context(GrpcStatus)?
+                .into_inner()
+                .replicas;
+
             let table = snapshots
                 .iter()
                 .map(|r| {
+                    let parent = clones
+                        .iter()
+                        .find(|c| c.uuid == r.source_uuid)
+                        .and_then(|c| c.snapshot_uuid.clone())
+                        .unwrap_or_else(|| "-".to_string());
                     vec![
                         r.snapshot_uuid.clone(),
                         r.snapshot_name.clone(),
-                        r.snapshot_size.to_string(),
+                        ctx.units(Byte::from_bytes(r.snapshot_size.into())),
                         r.timestamp.clone().unwrap_or_default().to_string(),
                         r.num_clones.to_string(),
                         r.source_uuid.clone(),
-                        r.source_size.to_string(),
+                        ctx.units(Byte::from_bytes(r.source_size.into())),
+                        parent,
                         r.pool_uuid.to_string(),
                         r.entity_id.clone(),
                         r.txn_id.clone(),
                         r.valid_snapshot.to_string(),
                         r.discarded_snapshot.to_string(),
-                        r.referenced_bytes.to_string(),
+                        ctx.units(Byte::from_bytes(r.referenced_bytes.into())),
                     ]
                 })
                 .collect();
@@ -461,17 +596,18 @@ async fn list(mut ctx: Context, matches: &ArgMatches<'_>) -> crate::Result<()> {
                 vec![
                     "SNAP_UUID",
                     "SNAP_NAME",
-                    "SNAP_SIZE",
+                    ">SNAP_SIZE",
                     "CREATE_TIME",
-                    "CLONES",
+                    ">CLONES",
                     "REPLICA_UUID",
-                    "REPLICA_SIZE",
+                    ">REPLICA_SIZE",
+                    "PARENT_SNAP_UUID",
                     "POOL_UUID",
                     "ENTITY_ID",
                     "TXN_ID",
                     "VALID_SNAPSHOT",
                     "DISCARD_SNAPSHOT",
-                    "ANCESTOR_SIZE",
+                    ">ANCESTOR_SIZE",
                 ],
                 table,
             );
@@ -515,7 +651,7 @@ async fn destroy(
 
     match ctx.output {
         OutputFormat::Json => {}
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             println!("snapshot: {} is deleted", &snapshot_uuid);
         }
     }
@@ -569,7 +705,7 @@ async fn create_clone(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let r = &response.get_ref();
             let data = vec![vec![
                 r.name.clone(),
@@ -599,6 +735,89 @@ async fn create_clone(
 
     Ok(())
 }
+/// CLI to restore a snapshot into a new replica, that is a clone of the
+/// snapshot.
+async fn restore(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let snapshot_uuid = matches
+        .value_of("snapshot_uuid")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "snapshot_uuid".to_string(),
+        })?
+        .to_owned();
+    let replica_name = matches
+        .value_of("replica_name")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "replica_name".to_string(),
+        })?
+        .to_owned();
+    let replica_uuid = matches
+        .value_of("replica_uuid")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "replica_uuid".to_string(),
+        })?
+        .to_owned();
+
+    ctx.v2(&format!(
+        "Restoring snapshot {snapshot_uuid} into replica {replica_uuid}"
+    ));
+    let response = ctx
+        .v1
+        .snapshot
+        .create_snapshot_clone(v1_rpc::snapshot::CreateSnapshotCloneRequest {
+            snapshot_uuid,
+            clone_name: replica_name,
+            clone_uuid: replica_uuid,
+        })
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let r = &response.get_ref();
+            ctx.print_list(
+                vec![
+                    "NAME",
+                    "UUID",
+                    ">CAPACITY",
+                    ">ALLOC",
+                    "THIN",
+                    "POOL",
+                    "SNAPSHOT_UUID",
+                ],
+                vec![vec![
+                    r.name.clone(),
+                    r.uuid.clone(),
+                    ctx.units(Byte::from_bytes(r.size.into())),
+                    ctx.units(Byte::from_bytes(
+                        r.usage
+                            .as_ref()
+                            .map(|u| u.allocated_bytes)
+                            .unwrap_or_default()
+                            .into(),
+                    )),
+                    r.thin.to_string(),
+                    r.poolname.clone(),
+                    r.snapshot_uuid.clone().unwrap_or_default(),
+                ]],
+            );
+        }
+    };
+
+    Ok(())
+}
+
 async fn list_clone(
     mut ctx: Context,
     matches: &ArgMatches<'_>,
@@ -624,7 +843,7 @@ async fn list_clone(
                     .unwrap()
             );
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let clones = &response.get_ref().replicas;
             if clones.is_empty() {
                 ctx.v1("No clones found");
@@ -664,3 +883,64 @@ async fn list_clone(
 
     Ok(())
 }
+
+/// CLI to flatten a snapshot clone.
+async fn flatten_clone(
+    mut ctx: Context,
+    matches: &ArgMatches<'_>,
+) -> crate::Result<()> {
+    let clone_uuid = matches
+        .value_of("clone_uuid")
+        .ok_or_else(|| ClientError::MissingValue {
+            field: "clone_uuid".to_string(),
+        })?
+        .to_owned();
+    let request = v1_rpc::snapshot::FlattenCloneRequest {
+        clone_uuid,
+    };
+
+    let response = ctx
+        .v1
+        .snapshot
+        .flatten_clone(request)
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let r = &response.get_ref();
+            let data = vec![vec![
+                r.name.clone(),
+                r.uuid.clone(),
+                r.size.clone().to_string(),
+                r.usage.as_ref().unwrap().allocated_bytes.to_string(),
+                r.thin.clone().to_string(),
+                r.poolname.clone(),
+                r.is_clone.clone().to_string(),
+            ]];
+            ctx.print_list(
+                vec![
+                    "NAME",
+                    "UUID",
+                    "CAPACITY",
+                    "ALLOC",
+                    "THIN",
+                    "POOL",
+                    "IS_CLONE",
+                ],
+                data,
+            );
+        }
+    };
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use super::{confirm, destructive_args};
 use crate::{
     context::{Context, OutputFormat},
     parse_size,
@@ -72,6 +73,7 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .conflicts_with("pool-uuid")
                 .help("Name of the pool where the replica resides"),
         )
+        .args(&destructive_args())
         .arg(
             Arg::with_name("method")
                 .short("m")
@@ -91,6 +93,11 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
                 .help("Reporting back stats after each chunk is wiped"),
         );
 
+    let selftest = SubCommand::with_name("selftest").about(
+        "Run end-to-end validation of the node: create a temporary pool, \
+        replica and nexus, verify I/O and snapshots, and clean up",
+    );
+
     SubCommand::with_name("test")
         .settings(&[
             AppSettings::SubcommandRequiredElseHelp,
@@ -100,6 +107,7 @@ pub fn subcommands<'a, 'b>() -> App<'a, 'b> {
         .about("Test management")
         .subcommand(inject)
         .subcommand(wipe)
+        .subcommand(selftest)
 }
 
 #[derive(EnumString, EnumVariantNames, AsRefStr)]
@@ -144,6 +152,7 @@ pub async fn handler(
     match matches.subcommand() {
         ("inject", Some(args)) => injections(ctx, args).await,
         ("wipe", Some(args)) => wipe(ctx, args).await,
+        ("selftest", Some(_)) => selftest(ctx).await,
         (cmd, _) => {
             Err(Status::not_found(format!("command {cmd} does not exist")))
                 .context(GrpcStatus)
@@ -198,6 +207,11 @@ async fn replica_wipe(
     let chunk_size = parse_size(matches.value_of("chunk-size").unwrap_or("0"))
         .map_err(|s| Status::invalid_argument(format!("Bad size '{s}'")))
         .context(GrpcStatus)?;
+    let dry_run = matches.is_present("dry-run");
+    if !confirm(matches, &format!("Wipe replica {uuid}")) {
+        println!("Aborted");
+        return Ok(());
+    }
     let response = ctx
         .v1
         .test
@@ -213,9 +227,14 @@ async fn replica_wipe(
                 }),
                 chunk_size: chunk_size.get_bytes() as u64,
             }),
+            validate_only: dry_run,
         })
         .await
         .context(GrpcStatus)?;
+    if dry_run {
+        ctx.v1(&format!("replica: {uuid} can be wiped"));
+        return Ok(());
+    }
 
     let mut resp = response.into_inner();
 
@@ -253,7 +272,7 @@ async fn replica_wipe(
                 );
             }
         }
-        OutputFormat::Default => {
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
             let header = vec![
                 "UUID",
                 "TOTAL_BYTES",
@@ -295,6 +314,55 @@ async fn replica_wipe(
     Ok(())
 }
 
+async fn selftest(mut ctx: Context) -> crate::Result<()> {
+    let response = ctx
+        .v1
+        .test
+        .run_self_test(v1_rpc::test::RunSelfTestRequest {})
+        .await
+        .context(GrpcStatus)?;
+
+    match ctx.output {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(response.get_ref())
+                    .unwrap()
+                    .to_colored_json_auto()
+                    .unwrap()
+            );
+        }
+        OutputFormat::Default | OutputFormat::Csv | OutputFormat::Wide => {
+            let response = response.into_inner();
+            let table = response
+                .stages
+                .into_iter()
+                .map(|s| {
+                    let elapsed = s
+                        .elapsed
+                        .and_then(|d| {
+                            TryInto::<std::time::Duration>::try_into(d).ok()
+                        })
+                        .map_or_else(|| "??".to_string(), |d| format!("{d:?}"));
+                    vec![
+                        s.stage,
+                        if s.passed { "pass" } else { "FAIL" }.to_string(),
+                        elapsed,
+                        s.error.unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            ctx.print_list(vec!["STAGE", "RESULT", "ELAPSED", "ERROR"], table);
+            println!(
+                "Self-test {}",
+                if response.passed { "passed" } else { "FAILED" }
+            );
+        }
+    }
+
+    Ok(())
+}
+
 fn adjust_bytes(bytes: u64) -> String {
     let byte = Byte::from_bytes(bytes as u128);
     let adjusted_byte = byte.get_appropriate_unit(true);
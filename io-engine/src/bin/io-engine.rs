@@ -29,7 +29,7 @@ use io_engine::{
     grpc,
     logger,
     persistent_store::PersistentStoreBuilder,
-    subsys::Registration,
+    subsys::{registration::registration_grpc::ApiVersion, Registration},
 };
 use version_info::fmt_package_info;
 
@@ -55,7 +55,7 @@ fn start_tokio_runtime(args: &MayastorCliArgs) {
     let grpc_address = grpc::endpoint(args.grpc_endpoint.clone());
     let registration_addr = args.registration_endpoint.clone();
     let rpc_address = args.rpc_address.clone();
-    let api_versions = args.api_versions.clone();
+    let api_versions = ApiVersion::served(&args.api_versions);
     let node_name = grpc::node_name(&args.node_name);
     let node_nqn = args.make_hostnqn();
 
@@ -17,6 +17,7 @@ use io_engine::{
     core::{MayastorCliArgs, MayastorEnvironment, Mthread, Reactors, Share},
     grpc,
     logger,
+    subsys::registration::registration_grpc::ApiVersion,
 };
 use version_info::version_info_str;
 
@@ -30,7 +31,7 @@ fn start_tokio_runtime(args: &MayastorCliArgs) {
     let node_nqn = args.make_hostnqn();
     let grpc_endpoint = grpc::endpoint(args.grpc_endpoint.clone());
     let rpc_address = args.rpc_address.clone();
-    let api_versions = args.api_versions.clone();
+    let api_versions = ApiVersion::served(&args.api_versions);
 
     Mthread::spawn_unaffinitized(move || {
         let rt = tokio::runtime::Builder::new_multi_thread()
@@ -1,17 +1,37 @@
 use std::{
     fmt::{Debug, Display, Formatter},
     ops::{Deref, DerefMut},
+    os::raw::c_void,
     pin::Pin,
 };
 
 use async_trait::async_trait;
+use futures::channel::oneshot;
 use nix::errno::Errno;
 use snafu::ResultExt;
 
-use spdk_rs::libspdk::spdk_bdev;
+use spdk_rs::libspdk::{
+    spdk_bdev,
+    spdk_bdev_get_dif_type,
+    spdk_bdev_get_md_size,
+    spdk_bdev_histogram_enable,
+    spdk_bdev_histogram_get,
+    spdk_bdev_is_md_interleaved,
+    spdk_bdev_set_qos_rate_limits,
+    spdk_get_ticks_hz,
+    spdk_histogram_data,
+    spdk_histogram_data_alloc,
+    spdk_histogram_data_free,
+    spdk_histogram_data_iterate,
+    SPDK_BDEV_QOS_NUM_RATE_LIMIT_TYPES,
+    SPDK_BDEV_QOS_W_BPS_RATE_LIMIT,
+    SPDK_DIF_TYPE1,
+    SPDK_DIF_TYPE2,
+    SPDK_DIF_TYPE3,
+};
 
 use crate::{
-    bdev::bdev_event_callback,
+    bdev::{bdev_event_callback, device_destroying},
     bdev_api::bdev_uri_eq,
     core::{
         share::{Protocol, Share, ShareProps, UpdateProps},
@@ -21,6 +41,7 @@ use crate::{
         ShareNvmf,
         UnshareNvmf,
     },
+    ffihelper::{cb_arg, done_cb, done_errno_cb, ErrnoResult},
     subsys::NvmfSubsystem,
     target::nvmf,
 };
@@ -40,6 +61,63 @@ pub struct Bdev<T: spdk_rs::BdevOps> {
 /// TODO
 pub type UntypedBdev = Bdev<()>;
 
+/// End-to-end protection information (T10 PI) type of a block device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectionType {
+    None,
+    Type1,
+    Type2,
+    Type3,
+}
+
+/// Latency histogram of a block device, as collected by SPDK.
+#[derive(Debug, Clone, Default)]
+pub struct BdevHistogram {
+    /// Rate of the ticks the latencies are measured in, per second.
+    pub tick_rate: u64,
+    /// Non-empty buckets, in increasing order of latency.
+    pub buckets: Vec<BdevHistogramBucket>,
+}
+
+/// Bucket of a block device latency histogram.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BdevHistogramBucket {
+    /// Lower bound of the latencies in the bucket, in ticks.
+    pub start: u64,
+    /// Upper bound of the latencies in the bucket, in ticks.
+    pub end: u64,
+    /// Number of I/Os in the bucket.
+    pub count: u64,
+}
+
+impl BdevHistogram {
+    /// Converts a number of ticks to microseconds.
+    pub fn ticks_to_us(&self, ticks: u64) -> u64 {
+        if self.tick_rate == 0 {
+            0
+        } else {
+            (ticks as u128 * 1_000_000 / self.tick_rate as u128) as u64
+        }
+    }
+
+    /// Returns the total number of I/Os in the histogram.
+    pub fn total(&self) -> u64 {
+        self.buckets.iter().map(|b| b.count).sum()
+    }
+}
+
+/// Metadata and end-to-end data protection capabilities of a block device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataProtection {
+    /// Size of the per-block metadata, in bytes.
+    pub md_size: u32,
+    /// Whether the metadata is interleaved with the data (DIF) or kept in a
+    /// separate buffer (DIX).
+    pub md_interleaved: bool,
+    /// Protection information type carried in the metadata.
+    pub pi_type: ProtectionType,
+}
+
 /// Allow transparent use of `spdk_rs` methods.
 impl<T> Deref for Bdev<T>
 where
@@ -105,6 +183,12 @@ where
         &self,
         read_write: bool,
     ) -> Result<DescriptorGuard<T>, CoreError> {
+        // A device being destroyed does not accept new handles.
+        if device_destroying(self.name()) {
+            return Err(CoreError::OpenBdev {
+                source: Errno::ENODEV,
+            });
+        }
         match spdk_rs::BdevDesc::<T>::open(
             self.name(),
             read_write,
@@ -152,6 +236,23 @@ where
         self.inner.module_name()
     }
 
+    /// Returns the metadata and data protection capabilities of the Bdev.
+    pub fn data_protection(&self) -> DataProtection {
+        let ptr = self.inner.unsafe_inner_ptr();
+        let pi_type = match unsafe { spdk_bdev_get_dif_type(ptr) } {
+            SPDK_DIF_TYPE1 => ProtectionType::Type1,
+            SPDK_DIF_TYPE2 => ProtectionType::Type2,
+            SPDK_DIF_TYPE3 => ProtectionType::Type3,
+            _ => ProtectionType::None,
+        };
+
+        DataProtection {
+            md_size: unsafe { spdk_bdev_get_md_size(ptr) },
+            md_interleaved: unsafe { spdk_bdev_is_md_interleaved(ptr) },
+            pi_type,
+        }
+    }
+
     /// Returns the first bdev in the list.
     pub fn bdev_first() -> Option<Self> {
         BdevIter::<T>::new().next()
@@ -173,6 +274,137 @@ where
             }),
         }
     }
+
+    /// Enables or disables the collection of the SPDK latency histogram of
+    /// the Bdev. Disabling it discards the collected data.
+    pub async fn set_histogram_enabled(
+        &self,
+        enable: bool,
+    ) -> Result<(), CoreError> {
+        let (s, r) = oneshot::channel::<ErrnoResult<()>>();
+
+        unsafe {
+            spdk_bdev_histogram_enable(
+                self.inner.unsafe_inner_mut_ptr(),
+                Some(done_errno_cb),
+                cb_arg(s),
+                enable,
+            );
+        }
+
+        r.await
+            .expect("Cancellation is not supported")
+            .map_err(|source| CoreError::Histogram {
+                name: self.name().to_string(),
+                source,
+            })
+    }
+
+    /// Sets the write bandwidth limit of the Bdev, in bytes per second, or
+    /// removes it. SPDK rounds the limit up to a multiple of 1 MiB/s. The
+    /// other QoS limits of the Bdev are left as they are.
+    pub async fn set_write_bandwidth_limit(
+        &self,
+        limit: Option<u64>,
+    ) -> Result<(), CoreError> {
+        // u64::MAX is SPDK_BDEV_QOS_LIMIT_NOT_DEFINED: the limit is unchanged.
+        let mut limits =
+            [u64::MAX; SPDK_BDEV_QOS_NUM_RATE_LIMIT_TYPES as usize];
+        limits[SPDK_BDEV_QOS_W_BPS_RATE_LIMIT as usize] =
+            limit.unwrap_or_default();
+
+        let (s, r) = oneshot::channel::<ErrnoResult<()>>();
+
+        unsafe {
+            spdk_bdev_set_qos_rate_limits(
+                self.inner.unsafe_inner_mut_ptr(),
+                limits.as_mut_ptr(),
+                Some(done_errno_cb),
+                cb_arg(s),
+            );
+        }
+
+        r.await
+            .expect("Cancellation is not supported")
+            .map_err(|source| CoreError::QosLimit {
+                name: self.name().to_string(),
+                source,
+            })
+    }
+
+    /// Returns the SPDK latency histogram of the Bdev, which must have been
+    /// enabled.
+    pub async fn histogram(&self) -> Result<BdevHistogram, CoreError> {
+        extern "C" fn histogram_data_cb(
+            ctx: *mut c_void,
+            status: i32,
+            _histogram: *mut spdk_histogram_data,
+        ) {
+            done_cb(ctx, status);
+        }
+
+        extern "C" fn histogram_bucket_cb(
+            ctx: *mut c_void,
+            start: u64,
+            end: u64,
+            count: u64,
+            _total: u64,
+            _so_far: u64,
+        ) {
+            if count > 0 {
+                let buckets =
+                    unsafe { &mut *(ctx as *mut Vec<BdevHistogramBucket>) };
+                buckets.push(BdevHistogramBucket {
+                    start,
+                    end,
+                    count,
+                });
+            }
+        }
+
+        let data = unsafe { spdk_histogram_data_alloc() };
+        if data.is_null() {
+            return Err(CoreError::Histogram {
+                name: self.name().to_string(),
+                source: Errno::ENOMEM,
+            });
+        }
+
+        let (s, r) = oneshot::channel::<i32>();
+
+        unsafe {
+            spdk_bdev_histogram_get(
+                self.inner.unsafe_inner_mut_ptr(),
+                data,
+                Some(histogram_data_cb),
+                cb_arg(s),
+            );
+        }
+
+        let rc = r.await.expect("Cancellation is not supported");
+        let result = if rc == 0 {
+            let mut buckets: Vec<BdevHistogramBucket> = Vec::new();
+            unsafe {
+                spdk_histogram_data_iterate(
+                    data,
+                    Some(histogram_bucket_cb),
+                    &mut buckets as *mut _ as *mut c_void,
+                );
+            }
+            Ok(BdevHistogram {
+                tick_rate: unsafe { spdk_get_ticks_hz() },
+                buckets,
+            })
+        } else {
+            Err(CoreError::Histogram {
+                name: self.name().to_string(),
+                source: Errno::from_i32(rc.abs()),
+            })
+        };
+
+        unsafe { spdk_histogram_data_free(data) };
+        result
+    }
 }
 
 #[async_trait(? Send)]
@@ -193,7 +425,8 @@ where
 
         let ptpl = props.ptpl().as_ref().map(|ptpl| ptpl.path());
         let subsystem =
-            NvmfSubsystem::try_from_with(me, ptpl).context(ShareNvmf {})?;
+            NvmfSubsystem::try_from_with(me, ptpl, props.identity())
+                .context(ShareNvmf {})?;
 
         if let Some((cntlid_min, cntlid_max)) = props.cntlid_range() {
             subsystem
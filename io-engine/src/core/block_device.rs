@@ -124,11 +124,16 @@ pub type OpCompletionCallbackArg = *mut c_void;
 pub type OpCompletionCallback = fn(bool, OpCompletionCallbackArg) -> ();
 
 /// Read options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReadOptions {
     /// Normal read operation.
     None,
     /// Fail when reading an unwritten block of a thin-provisioned device.
     UnwrittenFail,
+    /// Fail when reading a block of a thin-provisioned device which is not
+    /// written in the device itself, i.e. not taking its parent snapshots
+    /// into account.
+    CurrentUnwrittenFail,
 }
 
 /// Core trait that represents a device I/O handle.
@@ -476,6 +481,18 @@ pub trait BlockDeviceHandle {
         cb: IoCompletionCallback,
         cb_arg: IoCompletionCallbackArg,
     ) -> Result<(), CoreError>;
+
+    /// Aborts the I/O outstanding on this handle which was submitted with the
+    /// given completion callback argument. The I/O completes with an abort
+    /// status if the device aborts it, and normally otherwise.
+    fn abort_io(
+        &self,
+        _io_cb_arg: IoCompletionCallbackArg,
+    ) -> Result<(), CoreError> {
+        Err(CoreError::NotSupported {
+            source: Errno::EOPNOTSUPP,
+        })
+    }
 }
 
 fn block_device_io_completion(
@@ -7,7 +7,10 @@ use std::{
 use spdk_rs::{BdevDesc, BdevModule, BdevOps, Thread};
 
 use crate::{
-    bdev::nexus::NEXUS_MODULE_NAME,
+    bdev::{
+        destroy::{handle_closed, handle_opened},
+        nexus::NEXUS_MODULE_NAME,
+    },
     core::{Bdev, BdevHandle, CoreError},
 };
 
@@ -28,6 +31,7 @@ impl<T: BdevOps> Deref for DescriptorGuard<T> {
 impl<T: BdevOps> DescriptorGuard<T> {
     /// TODO
     pub(crate) fn new(d: BdevDesc<T>) -> Self {
+        handle_opened(d.bdev().name());
         Self(d)
     }
 
@@ -76,10 +80,20 @@ impl<T: BdevOps> DescriptorGuard<T> {
 /// running on their own thread.
 impl<T: BdevOps> Drop for DescriptorGuard<T> {
     fn drop(&mut self) {
+        // The handle is only accounted closed once the descriptor is, so
+        // that a deferred destroy never races with the close.
+        let name = self.0.bdev().name().to_string();
         if Thread::current().unwrap() == Thread::primary() {
-            self.0.close()
+            self.0.close();
+            handle_closed(&name);
         } else {
-            Thread::primary().send_msg(self.0.clone(), |mut d| d.close());
+            Thread::primary().send_msg(
+                (self.0.clone(), name),
+                |(mut d, name)| {
+                    d.close();
+                    handle_closed(&name);
+                },
+            );
         }
     }
 }
@@ -39,25 +39,56 @@ use tokio::runtime::Builder;
 use version_info::{package_description, version_info_str};
 
 use crate::{
-    bdev::{bdev_io_ctx_pool_init, nexus, nvme_io_ctx_pool_init},
+    bdev::{
+        bdev_io_ctx_pool_init,
+        nexus,
+        nvme_io_ctx_pool_init,
+        NvmfRttTuning,
+    },
     constants::NVME_NQN_PREFIX,
     core::{
         nic,
+        numa::{parse_numa_node, Numa, NumaConfig},
         reactor::{Reactor, ReactorState, Reactors},
         Cores,
         MayastorFeatures,
         Mthread,
     },
     grpc,
-    grpc::MayastorGrpcServer,
+    grpc::{
+        v1::json::JsonRpcAllowList,
+        GrpcAudit,
+        GrpcAuditConfig,
+        GrpcAuth,
+        GrpcRateLimit,
+        GrpcRateLimitConfig,
+        GrpcTlsConfig,
+        MayastorGrpcServer,
+    },
+    host::{
+        identity::HostIdentity,
+        options::HostOptions,
+        pool_scrub::{PoolScrubConfig, PoolScrubber},
+        tombstone::{TombstoneConfig, Tombstones},
+    },
     logger,
+    lvs::{LvolChainLimits, LvolCompress, Lvs},
     persistent_store::PersistentStoreBuilder,
+    rebuild::{
+        RebuildScheduler,
+        RebuildSchedulerConfig,
+        REBUILD_CLIENT_QD_THRESHOLD,
+    },
     subsys::{
         self,
         registration::registration_grpc::ApiVersion,
         Config,
+        NvmfRebalanceConfig,
+        NvmfRebalancer,
         PoolConfig,
         Registration,
+        StartupConfig,
+        NVMF_REBALANCE_DEFAULT_THRESHOLD,
     },
 };
 
@@ -80,6 +111,13 @@ fn parse_mb(src: &str) -> Result<i32, String> {
     }
 }
 
+/// Parses a bandwidth, in bytes per second.
+fn parse_bandwidth(src: &str) -> Result<u64, String> {
+    Byte::from_str(src)
+        .map(|b| b.get_bytes() as u64)
+        .map_err(|_| format!("Invalid argument {src}"))
+}
+
 /// Parses a persistent store timeout.
 fn parse_ps_timeout(src: &str) -> Result<Duration, String> {
     humantime::parse_duration(src)
@@ -132,6 +170,10 @@ pub struct MayastorCliArgs {
     #[structopt(short = "P")]
     /// Path to pool config file.
     pub pool_config: Option<String>,
+    #[structopt(long, env = "STARTUP_CONFIG")]
+    /// Path to the startup config file (YAML or JSON) listing the bdevs,
+    /// pools and shares to create at startup.
+    pub startup_config: Option<String>,
     #[structopt(long = "huge-dir")]
     /// Path to hugedir.
     pub hugedir: Option<String>,
@@ -155,6 +197,34 @@ pub struct MayastorCliArgs {
     #[structopt(long = "ps-retries", default_value = "30")]
     /// Persistent store operation retries.
     pub ps_retries: u8,
+    /// CA certificate used to verify the persistent store, as a path to a PEM
+    /// file or as PEM data. Enables TLS for the persistent store.
+    #[structopt(long, env = "PS_TLS_CA")]
+    pub ps_tls_ca: Option<String>,
+    /// Client certificate chain presented to the persistent store, as a path
+    /// to a PEM file or as PEM data. Enables TLS for the persistent store.
+    #[structopt(long, env = "PS_TLS_CERT", requires = "ps_tls_key")]
+    pub ps_tls_cert: Option<String>,
+    /// Private key of the persistent store client certificate, as a path to
+    /// a PEM file or as PEM data.
+    #[structopt(long, env = "PS_TLS_KEY", requires = "ps_tls_cert")]
+    pub ps_tls_key: Option<String>,
+    /// User name to authenticate with the persistent store.
+    #[structopt(long, env = "PS_USERNAME", requires = "ps_password")]
+    pub ps_username: Option<String>,
+    /// Password to authenticate with the persistent store.
+    #[structopt(
+        long,
+        env = "PS_PASSWORD",
+        hide_env_values = true,
+        requires = "ps_username"
+    )]
+    pub ps_password: Option<String>,
+    /// Maximum number of nexus state updates deferred while the persistent
+    /// store cannot be reached, and replayed once it is back. 0 disables
+    /// deferral: the nexus is shut down if its state cannot be saved.
+    #[structopt(long, env = "PS_DEFERRED_OPS", default_value = "0")]
+    pub ps_deferred_ops: usize,
     #[structopt(long = "bdev-pool-size", default_value = "65535")]
     /// Number of entries in memory pool for bdev I/O contexts
     pub bdev_io_ctx_pool_size: u64,
@@ -167,7 +237,8 @@ pub struct MayastorCliArgs {
     /// NVMF target Command Retry Delay.
     #[structopt(long = "tgt-crdt", env = "NVMF_TGT_CRDT", default_value = "0")]
     pub nvmf_tgt_crdt: u16,
-    /// The gRPC api version.
+    /// The gRPC api versions to serve. The experimental versions (v2alpha)
+    /// are only served when listed, once they have services.
     #[structopt(
         long,
         value_delimiter = ",",
@@ -195,6 +266,188 @@ pub struct MayastorCliArgs {
     /// Events message-bus endpoint url.
     #[structopt(long)]
     pub events_url: Option<url::Url>,
+    /// Maximum number of rebuild jobs running at the same time on this node.
+    /// Rebuilds above this limit are queued. A value of 0 means no limit.
+    #[structopt(
+        long = "rebuild-max-concurrent",
+        env = "REBUILD_MAX_CONCURRENT",
+        default_value = "0"
+    )]
+    pub rebuild_max_concurrent: usize,
+    /// Maximum number of rebuild jobs of a nexus running at the same time.
+    /// Rebuilds above this limit are queued. A value of 0 means no limit.
+    #[structopt(
+        long = "rebuild-max-per-nexus",
+        env = "REBUILD_MAX_PER_NEXUS",
+        default_value = "0"
+    )]
+    pub rebuild_max_per_nexus: usize,
+    /// Maximum total rebuild bandwidth of this node, in bytes per second
+    /// (units are accepted, e.g. 200MiB). A value of 0 means no limit.
+    #[structopt(
+        long = "rebuild-max-bandwidth",
+        env = "REBUILD_MAX_BANDWIDTH",
+        default_value = "0",
+        parse(try_from_str = parse_bandwidth)
+    )]
+    pub rebuild_max_bandwidth: u64,
+    /// Default size of the segments copied by a rebuild job, in bytes
+    /// (units are accepted, e.g. 1MiB). A value of 0 means the built-in
+    /// default.
+    #[structopt(
+        long = "rebuild-segment-size",
+        env = "REBUILD_SEGMENT_SIZE",
+        default_value = "0",
+        parse(try_from_str = parse_bandwidth)
+    )]
+    pub rebuild_segment_size: u64,
+    /// Default number of concurrent copy tasks of a rebuild job. A value of 0
+    /// means the built-in default.
+    #[structopt(
+        long = "rebuild-tasks",
+        env = "REBUILD_TASKS",
+        default_value = "0"
+    )]
+    pub rebuild_tasks: usize,
+    /// Client I/O queue depth of a nexus above which the reads of its
+    /// rebuilds are deferred, so that the rebuilds yield to the client I/Os.
+    /// A value of 0 disables deferral.
+    #[structopt(
+        long = "rebuild-client-qd-threshold",
+        env = "REBUILD_CLIENT_QD_THRESHOLD",
+        default_value = "32"
+    )]
+    pub rebuild_client_qd_threshold: usize,
+    /// SPDK json-rpc methods which may be invoked via the gRPC json service.
+    /// An entry ending with `*` allows all methods with the given prefix.
+    /// If not set, all methods are allowed.
+    #[structopt(
+        long = "json-rpc-allow",
+        env = "JSON_RPC_ALLOW",
+        value_delimiter = ","
+    )]
+    pub json_rpc_allow: Vec<String>,
+    /// Certificate chain of the gRPC server, as a path to a PEM file or as
+    /// PEM data. Enables TLS for the gRPC server.
+    #[structopt(long, env = "GRPC_TLS_CERT", requires = "grpc_tls_key")]
+    pub grpc_tls_cert: Option<String>,
+    /// Private key of the gRPC server, as a path to a PEM file or as PEM
+    /// data.
+    #[structopt(long, env = "GRPC_TLS_KEY", requires = "grpc_tls_cert")]
+    pub grpc_tls_key: Option<String>,
+    /// CA certificate used to verify gRPC client certificates, as a path to a
+    /// PEM file or as PEM data. If set, clients must present a certificate
+    /// signed by this CA.
+    #[structopt(long, env = "GRPC_TLS_CA", requires = "grpc_tls_cert")]
+    pub grpc_tls_ca: Option<String>,
+    /// File with the tokens authorizing gRPC methods, one `<scope>:<token>`
+    /// per line. Scope is one of `read-only`, `provisioning` or
+    /// `destructive`. If set, all methods require a bearer token.
+    #[structopt(long, env = "GRPC_AUTH_TOKENS")]
+    pub grpc_auth_tokens: Option<String>,
+    /// Maximum rate of gRPC requests, in requests per second. Requests above
+    /// it are rejected with RESOURCE_EXHAUSTED. A value of 0 means no limit.
+    #[structopt(long, env = "GRPC_RATE_LIMIT", default_value = "0")]
+    pub grpc_rate_limit: u32,
+    /// Maximum rate of gRPC requests of a single client address, in requests
+    /// per second. A value of 0 means no limit.
+    #[structopt(long, env = "GRPC_CLIENT_RATE_LIMIT", default_value = "0")]
+    pub grpc_client_rate_limit: u32,
+    /// File the audit log of the gRPC requests which create, modify or
+    /// destroy resources is persisted to. If not set, the audit log is only
+    /// kept in memory.
+    #[structopt(long, env = "GRPC_AUDIT_LOG")]
+    pub grpc_audit_log: Option<String>,
+    /// Maximum number of entries of the gRPC audit log. A value of 0
+    /// disables the audit log.
+    #[structopt(long, env = "GRPC_AUDIT_LOG_SIZE", default_value = "10000")]
+    pub grpc_audit_log_size: usize,
+    /// Read rate of the background media scan of each pool disk, in MiB/s.
+    /// A value of 0 disables the media scan.
+    #[structopt(long, env = "POOL_SCRUB_RATE", default_value = "0")]
+    pub pool_scrub_rate: u32,
+    /// Pause between two media scans of the pool disks, in hours.
+    #[structopt(long, env = "POOL_SCRUB_INTERVAL", default_value = "168")]
+    pub pool_scrub_interval: u64,
+    /// Time replicas and nexuses marked for deletion are kept before they
+    /// are destroyed, in seconds.
+    #[structopt(long, env = "TOMBSTONE_GRACE_PERIOD", default_value = "3600")]
+    pub tombstone_grace_period: u64,
+    /// Directory holding the persistent memory files of compressed replicas.
+    /// Compressed replicas cannot be created unless it is set.
+    #[structopt(long, env = "COMPRESS_PM_PATH")]
+    pub compress_pm_path: Option<String>,
+    /// Maximum size of the replicas provisioned on a pool, in percent of the
+    /// pool capacity. Thin replicas cannot be created beyond this limit.
+    /// A value of 0 disables the limit.
+    #[structopt(long, env = "POOL_OVERCOMMIT_LIMIT", default_value = "0")]
+    pub pool_overcommit_limit: u32,
+    /// Share of the pool write bandwidth left to the other replicas of a pool
+    /// saturated by a rebuild, in percent. A value of 0 disables the limits.
+    #[structopt(long, env = "POOL_REBUILD_FAIRNESS", default_value = "0")]
+    pub pool_rebuild_fairness: u32,
+    /// Maximum number of snapshots of a replica.
+    /// A value of 0 disables the limit.
+    #[structopt(long, env = "MAX_SNAPSHOTS_PER_REPLICA", default_value = "0")]
+    pub max_snapshots_per_replica: u32,
+    /// Maximum number of clone generations a clone can descend from.
+    /// A value of 0 disables the limit.
+    #[structopt(long, env = "MAX_CLONE_CHAIN_DEPTH", default_value = "0")]
+    pub max_clone_chain_depth: u32,
+    /// Allocate the rebuild buffers on the NUMA node of the rebuild
+    /// destination, and create the NVMf poll groups on the cores of the NUMA
+    /// node of the NVMf target interface.
+    #[structopt(long, env = "NUMA_AWARE")]
+    pub numa_aware: bool,
+    /// NUMA nodes of devices, as `<device>=<node>`, where device is a PCI
+    /// address, a block device name or a network interface name. They
+    /// override the nodes found in sysfs. A node of -1 disables the NUMA
+    /// placement for the device.
+    #[structopt(
+        long = "numa-node",
+        env = "NUMA_NODES",
+        value_delimiter = ",",
+        parse(try_from_str = parse_numa_node)
+    )]
+    pub numa_nodes: Vec<(String, i32)>,
+    /// Pause between two passes of the rebalancing of the NVMf connections
+    /// across the poll groups, in seconds. A value of 0 disables the
+    /// periodic rebalancing.
+    #[structopt(
+        long = "nvmf-rebalance-interval",
+        env = "NVMF_REBALANCE_INTERVAL",
+        default_value = "0"
+    )]
+    pub nvmf_rebalance_interval: u64,
+    /// Load above which a poll group is hot and gives up an NVMf connection,
+    /// in percent of the average load of the poll groups.
+    #[structopt(
+        long = "nvmf-rebalance-threshold",
+        env = "NVMF_REBALANCE_THRESHOLD",
+        default_value = "150"
+    )]
+    pub nvmf_rebalance_threshold: u32,
+    /// Measure the round-trip time to the NVMf targets of the remote
+    /// children when connecting to them, and scale the keep-alive and
+    /// fabrics connect timeouts of their controllers to it.
+    #[structopt(long = "nvmf-rtt-tuning", env = "NVMF_RTT_TUNING")]
+    pub nvmf_rtt_tuning: bool,
+    /// Lowest keep-alive timeout of the tuned NVMf controllers, in
+    /// milliseconds. A value of 0 uses the configured keep-alive timeout.
+    #[structopt(
+        long = "nvmf-kato-min-ms",
+        env = "NVMF_KATO_MIN_MS",
+        default_value = "0"
+    )]
+    pub nvmf_kato_min_ms: u32,
+    /// Highest keep-alive timeout of the tuned NVMf controllers, in
+    /// milliseconds.
+    #[structopt(
+        long = "nvmf-kato-max-ms",
+        env = "NVMF_KATO_MAX_MS",
+        default_value = "30000"
+    )]
+    pub nvmf_kato_max_ms: u32,
 }
 
 /// Mayastor features.
@@ -205,8 +458,14 @@ impl MayastorFeatures {
             Err(_) => false,
         };
 
+        let batched_io = match std::env::var("NEXUS_BATCHED_IO_ENABLE") {
+            Ok(s) => s == "1",
+            Err(_) => false,
+        };
+
         MayastorFeatures {
             asymmetric_namespace_access: ana,
+            nexus_batched_io: batched_io,
         }
     }
 
@@ -223,6 +482,12 @@ impl Default for MayastorCliArgs {
             ps_endpoint: None,
             ps_timeout: Duration::from_secs(10),
             ps_retries: 30,
+            ps_tls_ca: None,
+            ps_tls_cert: None,
+            ps_tls_key: None,
+            ps_username: None,
+            ps_password: None,
+            ps_deferred_ops: 0,
             node_name: None,
             env_context: None,
             reactor_mask: "0x1".into(),
@@ -234,6 +499,7 @@ impl Default for MayastorCliArgs {
             mayastor_config: None,
             ptpl_dir: None,
             pool_config: None,
+            startup_config: None,
             hugedir: None,
             core_list: None,
             bdev_io_ctx_pool_size: 65535,
@@ -247,6 +513,36 @@ impl Default for MayastorCliArgs {
             reactor_freeze_timeout: None,
             skip_sig_handler: false,
             events_url: None,
+            rebuild_max_concurrent: 0,
+            rebuild_max_per_nexus: 0,
+            rebuild_max_bandwidth: 0,
+            rebuild_segment_size: 0,
+            rebuild_tasks: 0,
+            rebuild_client_qd_threshold: REBUILD_CLIENT_QD_THRESHOLD,
+            json_rpc_allow: vec![],
+            grpc_tls_cert: None,
+            grpc_tls_key: None,
+            grpc_tls_ca: None,
+            grpc_auth_tokens: None,
+            grpc_rate_limit: 0,
+            grpc_client_rate_limit: 0,
+            grpc_audit_log: None,
+            grpc_audit_log_size: 10000,
+            pool_scrub_rate: 0,
+            pool_scrub_interval: 168,
+            tombstone_grace_period: 3600,
+            compress_pm_path: None,
+            pool_overcommit_limit: 0,
+            pool_rebuild_fairness: 0,
+            max_snapshots_per_replica: 0,
+            max_clone_chain_depth: 0,
+            numa_aware: false,
+            numa_nodes: vec![],
+            nvmf_rebalance_interval: 0,
+            nvmf_rebalance_threshold: NVMF_REBALANCE_DEFAULT_THRESHOLD,
+            nvmf_rtt_tuning: false,
+            nvmf_kato_min_ms: 0,
+            nvmf_kato_max_ms: 30_000,
         }
     }
 }
@@ -310,9 +606,16 @@ pub struct MayastorEnvironment {
     ps_endpoint: Option<String>,
     ps_timeout: Duration,
     ps_retries: u8,
+    ps_tls_ca: Option<String>,
+    ps_tls_cert: Option<String>,
+    ps_tls_key: Option<String>,
+    ps_username: Option<String>,
+    ps_password: Option<String>,
+    ps_deferred_ops: usize,
     mayastor_config: Option<String>,
     ptpl_dir: Option<String>,
     pool_config: Option<String>,
+    startup_config: Option<String>,
     delay_subsystem_init: bool,
     enable_coredump: bool,
     env_context: Option<String>,
@@ -344,6 +647,21 @@ pub struct MayastorEnvironment {
     pub nvmf_tgt_crdt: u16,
     api_versions: Vec<ApiVersion>,
     skip_sig_handler: bool,
+    rebuild_scheduler: RebuildSchedulerConfig,
+    json_rpc_allow: Vec<String>,
+    grpc_tls: Option<GrpcTlsConfig>,
+    grpc_auth_tokens: Option<String>,
+    grpc_rate_limit: GrpcRateLimitConfig,
+    grpc_audit: GrpcAuditConfig,
+    pool_scrub: PoolScrubConfig,
+    tombstones: TombstoneConfig,
+    compress_pm_path: Option<String>,
+    pool_overcommit_limit: u32,
+    pool_rebuild_fairness: u32,
+    lvol_chain_limits: LvolChainLimits,
+    numa: NumaConfig,
+    nvmf_rebalance: NvmfRebalanceConfig,
+    nvmf_rtt_tuning: NvmfRttTuning,
 }
 
 impl Default for MayastorEnvironment {
@@ -356,9 +674,16 @@ impl Default for MayastorEnvironment {
             ps_endpoint: None,
             ps_timeout: Duration::from_secs(10),
             ps_retries: 30,
+            ps_tls_ca: None,
+            ps_tls_cert: None,
+            ps_tls_key: None,
+            ps_username: None,
+            ps_password: None,
+            ps_deferred_ops: 0,
             mayastor_config: None,
             ptpl_dir: None,
             pool_config: None,
+            startup_config: None,
             delay_subsystem_init: false,
             enable_coredump: true,
             env_context: None,
@@ -390,6 +715,21 @@ impl Default for MayastorEnvironment {
             nvmf_tgt_crdt: 0,
             api_versions: vec![ApiVersion::V0, ApiVersion::V1],
             skip_sig_handler: false,
+            rebuild_scheduler: Default::default(),
+            json_rpc_allow: vec![],
+            grpc_tls: None,
+            grpc_auth_tokens: None,
+            grpc_rate_limit: Default::default(),
+            grpc_audit: Default::default(),
+            pool_scrub: Default::default(),
+            tombstones: Default::default(),
+            compress_pm_path: None,
+            pool_overcommit_limit: 0,
+            pool_rebuild_fairness: 0,
+            lvol_chain_limits: Default::default(),
+            numa: Default::default(),
+            nvmf_rebalance: Default::default(),
+            nvmf_rtt_tuning: Default::default(),
         }
     }
 }
@@ -484,6 +824,12 @@ impl MayastorEnvironment {
             ps_endpoint: args.ps_endpoint,
             ps_timeout: args.ps_timeout,
             ps_retries: args.ps_retries,
+            ps_tls_ca: args.ps_tls_ca,
+            ps_tls_cert: args.ps_tls_cert,
+            ps_tls_key: args.ps_tls_key,
+            ps_username: args.ps_username,
+            ps_password: args.ps_password,
+            ps_deferred_ops: args.ps_deferred_ops,
             node_name: args.node_name.clone().unwrap_or_else(|| {
                 env::var("HOSTNAME").unwrap_or_else(|_| "mayastor-node".into())
             }),
@@ -495,6 +841,7 @@ impl MayastorEnvironment {
             mayastor_config: args.mayastor_config,
             ptpl_dir: args.ptpl_dir,
             pool_config: args.pool_config,
+            startup_config: args.startup_config,
             log_component: args.log_components,
             mem_size: args.mem_size,
             no_pci: args.no_pci,
@@ -507,8 +854,61 @@ impl MayastorEnvironment {
             nvme_ctl_io_ctx_pool_size: args.nvme_ctl_io_ctx_pool_size,
             nvmf_tgt_interface: args.nvmf_tgt_interface,
             nvmf_tgt_crdt: args.nvmf_tgt_crdt,
-            api_versions: args.api_versions,
+            api_versions: ApiVersion::served(&args.api_versions),
             skip_sig_handler: args.skip_sig_handler,
+            rebuild_scheduler: RebuildSchedulerConfig {
+                max_concurrent: args.rebuild_max_concurrent,
+                max_per_nexus: args.rebuild_max_per_nexus,
+                max_bandwidth: args.rebuild_max_bandwidth,
+                segment_size: args.rebuild_segment_size,
+                segment_tasks: args.rebuild_tasks,
+                client_qd_threshold: args.rebuild_client_qd_threshold,
+            },
+            json_rpc_allow: args.json_rpc_allow,
+            grpc_tls: match (args.grpc_tls_cert, args.grpc_tls_key) {
+                (Some(cert), Some(key)) => Some(GrpcTlsConfig {
+                    cert,
+                    key,
+                    ca: args.grpc_tls_ca,
+                }),
+                _ => None,
+            },
+            grpc_auth_tokens: args.grpc_auth_tokens,
+            grpc_rate_limit: GrpcRateLimitConfig {
+                global: args.grpc_rate_limit,
+                per_client: args.grpc_client_rate_limit,
+            },
+            grpc_audit: GrpcAuditConfig {
+                path: args.grpc_audit_log,
+                capacity: args.grpc_audit_log_size,
+            },
+            pool_scrub: PoolScrubConfig {
+                rate_mib: args.pool_scrub_rate,
+                interval: Duration::from_secs(args.pool_scrub_interval * 3600),
+            },
+            tombstones: TombstoneConfig {
+                grace_period: Duration::from_secs(args.tombstone_grace_period),
+            },
+            compress_pm_path: args.compress_pm_path,
+            pool_overcommit_limit: args.pool_overcommit_limit,
+            pool_rebuild_fairness: args.pool_rebuild_fairness,
+            lvol_chain_limits: LvolChainLimits {
+                max_snapshots: args.max_snapshots_per_replica,
+                max_clone_depth: args.max_clone_chain_depth,
+            },
+            numa: NumaConfig {
+                enabled: args.numa_aware,
+                nodes: args.numa_nodes.into_iter().collect(),
+            },
+            nvmf_rebalance: NvmfRebalanceConfig {
+                interval: Duration::from_secs(args.nvmf_rebalance_interval),
+                threshold: args.nvmf_rebalance_threshold,
+            },
+            nvmf_rtt_tuning: NvmfRttTuning {
+                enabled: args.nvmf_rtt_tuning,
+                min_keep_alive_ms: args.nvmf_kato_min_ms,
+                max_keep_alive_ms: args.nvmf_kato_max_ms,
+            },
             ..Default::default()
         }
         .setup_static()
@@ -858,6 +1258,20 @@ impl MayastorEnvironment {
         None
     }
 
+    /// load the startup config file.
+    fn load_startup_config(&self) -> Option<StartupConfig> {
+        let file = self.startup_config.as_ref()?;
+        info!("loading startup config file {}", file);
+        match StartupConfig::load(file) {
+            Ok(config) => Some(config),
+            Err(error) => {
+                error!("{}", error);
+                // if the configuration is invalid exit early
+                panic!("Failed to load the startup configuration")
+            }
+        }
+    }
+
     /// initialize the core, call this before all else
     pub fn init(mut self) -> Self {
         // setup the logger as soon as possible
@@ -872,6 +1286,7 @@ impl MayastorEnvironment {
         }
 
         let pool_config = self.load_pool_config();
+        let startup_config = self.load_startup_config();
 
         // bootstrap DPDK and its magic
         self.initialize_eal();
@@ -882,6 +1297,58 @@ impl MayastorEnvironment {
         // initialize memory pool for allocating NVMe controller I/O contexts
         nvme_io_ctx_pool_init(self.nvme_ctl_io_ctx_pool_size);
 
+        // set the node-wide rebuild limits
+        RebuildScheduler::configure(self.rebuild_scheduler);
+
+        // restrict the SPDK json-rpc methods exposed via gRPC
+        JsonRpcAllowList::configure(self.json_rpc_allow.clone());
+
+        // enable TLS for the gRPC server
+        if let Some(tls) = self.grpc_tls.clone() {
+            tls.configure();
+        }
+
+        // require authorization tokens for the gRPC methods
+        if let Some(path) = &self.grpc_auth_tokens {
+            if let Err(error) = GrpcAuth::configure(path) {
+                error!("{}", error);
+                panic!("Failed to load the gRPC authorization tokens");
+            }
+        }
+
+        // limit the rate of gRPC requests
+        GrpcRateLimit::configure(self.grpc_rate_limit);
+
+        // record the gRPC requests which create, modify or destroy resources
+        GrpcAudit::configure(self.grpc_audit.clone());
+
+        // scan the pool disks for unreadable blocks in the background
+        PoolScrubber::configure(self.pool_scrub);
+
+        // destroy the objects marked for deletion after a grace period
+        Tombstones::configure(self.tombstones);
+
+        // set up the persistent memory of compressed replicas
+        LvolCompress::configure(self.compress_pm_path.clone());
+
+        // limit the over-provisioning of the pools
+        Lvs::configure_overcommit(self.pool_overcommit_limit);
+
+        // limit the replica writes of the pools saturated by a rebuild
+        Lvs::configure_rebuild_fairness(self.pool_rebuild_fairness);
+
+        // limit the snapshot and clone chains of the replicas
+        self.lvol_chain_limits.configure();
+
+        // place the buffers and poll groups on the NUMA node of their device
+        Numa::configure(self.numa.clone());
+
+        // move NVMf connections off the hot poll groups
+        NvmfRebalancer::configure(self.nvmf_rebalance);
+
+        // scale the timeouts of the NVMf controllers to their targets
+        self.nvmf_rtt_tuning.configure();
+
         info!(
             "Total number of cores available: {}",
             Cores::count().into_iter().count()
@@ -939,6 +1406,11 @@ impl MayastorEnvironment {
             config.import_pools();
         }
 
+        // create the resources listed in the startup config
+        if let Some(config) = startup_config {
+            config.apply();
+        }
+
         self
     }
 
@@ -964,6 +1436,12 @@ impl MayastorEnvironment {
         let ps_endpoint = self.ps_endpoint.clone();
         let ps_timeout = self.ps_timeout;
         let ps_retries = self.ps_retries;
+        let ps_tls_ca = self.ps_tls_ca.clone();
+        let ps_tls_cert = self.ps_tls_cert.clone();
+        let ps_tls_key = self.ps_tls_key.clone();
+        let ps_deferred_ops = self.ps_deferred_ops;
+        let ps_credentials =
+            self.ps_username.clone().zip(self.ps_password.clone());
         let grpc_endpoint = self.grpc_endpoint;
         let rpc_addr = self.rpc_addr.clone();
         let api_versions = self.api_versions.clone();
@@ -974,16 +1452,38 @@ impl MayastorEnvironment {
         rt.block_on(async {
             // If a persistent store endpoint is given, configure and enable it.
             if let Some(ps_endpoint) = ps_endpoint {
-                PersistentStoreBuilder::new()
+                let mut builder = PersistentStoreBuilder::new()
                     .with_endpoint(&ps_endpoint)
                     .with_timeout(ps_timeout)
                     .with_retries(ps_retries)
-                    .connect()
-                    .await;
+                    .with_tls(ps_tls_ca, ps_tls_cert, ps_tls_key)
+                    .with_max_deferred_ops(ps_deferred_ops);
+                if let Some((user, password)) = ps_credentials {
+                    builder = builder.with_credentials(&user, &password);
+                }
+                builder.connect().await;
             }
 
             let master = Reactors::current();
+            // Restore the host options and identity changed by the control
+            // plane.
+            let restore_node = node_name.clone();
+            master.send_future(async move {
+                if let Err(error) = HostOptions::restore(&restore_node).await {
+                    error!("Failed to restore the host options: {error}");
+                }
+                if let Err(error) = HostIdentity::restore(&restore_node).await {
+                    error!("Failed to restore the host identity: {error}");
+                }
+            });
             master.send_future(async { f() });
+            if PoolScrubber::enabled() {
+                master.send_future(PoolScrubber::run());
+            }
+            master.send_future(Tombstones::run());
+            if NvmfRebalancer::enabled() {
+                master.send_future(NvmfRebalancer::run());
+            }
             let mut futures: Vec<
                 Pin<Box<dyn future::Future<Output = FutureResult>>>,
             > = Vec::new();
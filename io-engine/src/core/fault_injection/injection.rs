@@ -33,6 +33,10 @@ pub struct FaultInjection {
     pub begin: Duration,
     pub end: Duration,
     pub range: Range<u64>,
+    /// Maximum number of I/Os the fault is injected into.
+    pub count: u64,
+    /// Number of I/Os the fault has been injected into.
+    pub hits: u64,
     rng: StdRng,
 }
 
@@ -57,7 +61,7 @@ impl Display for FaultInjection {
         write!(
             f,
             "{io}::{stage}::{ft} injection <{d}::{n}> [{b:?} -> \
-            {e} ({t:?})] @ {rs}..{re}",
+            {e} ({t:?})] @ {rs}..{re} x {h}/{c}",
             io = self.fault_io_type,
             stage = self.fault_io_stage,
             ft = self.fault_type,
@@ -68,6 +72,8 @@ impl Display for FaultInjection {
             t = self.now(),
             rs = self.range.start,
             re = fmt_u64(self.range.end),
+            h = self.hits,
+            c = fmt_u64(self.count),
         )
     }
 }
@@ -118,6 +124,8 @@ impl FaultInjection {
             begin,
             end,
             range,
+            count: u64::MAX,
+            hits: 0,
             rng: new_rng(),
         }
     }
@@ -156,6 +164,8 @@ impl FaultInjection {
             begin: Duration::ZERO,
             end: Duration::MAX,
             range: 0 .. u64::MAX,
+            count: u64::MAX,
+            hits: 0,
             rng: new_rng(),
         };
 
@@ -169,6 +179,7 @@ impl FaultInjection {
                 "end" => r.end = parse_timer(&k, &v)?,
                 "offset" => r.range.start = parse_num(&k, &v)?,
                 "num_blk" => r.range.end = parse_num(&k, &v)?,
+                "count" => r.count = parse_num(&k, &v)?,
                 _ => {
                     return Err(FaultInjectionError::UnknownParameter {
                         name: k.to_string(),
@@ -201,7 +212,7 @@ impl FaultInjection {
     /// True if the injection is currently active.
     pub fn is_active(&self) -> bool {
         let d = self.now();
-        d >= self.begin && d < self.end
+        d >= self.begin && d < self.end && self.hits < self.count
     }
 
     /// Injects an error for the given I/O context.
@@ -233,6 +244,8 @@ impl FaultInjection {
             return None;
         }
 
+        self.hits += 1;
+
         match self.fault_type {
             FaultType::Status(status) => Some(status),
             FaultType::Data => {
@@ -279,6 +292,7 @@ fn parse_fault_io_type(
     let res = match v {
         "read" | "r" => FaultIoType::Read,
         "write" | "w" => FaultIoType::Write,
+        "flush" | "f" => FaultIoType::Flush,
         _ => {
             return Err(FaultInjectionError::UnknownParameter {
                 name: k.to_string(),
@@ -296,7 +310,7 @@ fn parse_fault_io_stage(
 ) -> Result<FaultIoStage, FaultInjectionError> {
     let res = match v {
         "submit" | "s" | "submission" => FaultIoStage::Submission,
-        "compl" | "c" | "completion" => FaultIoStage::Submission,
+        "compl" | "c" | "completion" => FaultIoStage::Completion,
         _ => {
             return Err(FaultInjectionError::UnknownParameter {
                 name: k.to_string(),
@@ -315,6 +329,7 @@ fn parse_fault_type(
     let res = match v {
         // TODO: add more statuses.
         "status" => FaultType::status_data_transfer_error(),
+        "nospace" => FaultType::status_no_space(),
         // TODO: add data corruption methods.
         "data" => FaultType::Data,
         _ => {
@@ -13,7 +13,7 @@ use url::ParseError;
 mod injection;
 mod injections;
 
-use crate::core::{BlockDevice, IoCompletionStatus};
+use crate::core::{BlockDevice, IoCompletionStatus, LvolFailure};
 pub use injection::FaultInjection;
 pub use injections::{
     add_fault_injection,
@@ -61,6 +61,7 @@ impl Display for DataFaultMode {
 pub enum FaultIoType {
     Read,
     Write,
+    Flush,
 }
 
 impl Display for FaultIoType {
@@ -68,6 +69,7 @@ impl Display for FaultIoType {
         match self {
             Self::Read => f.write_str("read"),
             Self::Write => f.write_str("write"),
+            Self::Flush => f.write_str("flush"),
         }
     }
 }
@@ -79,6 +81,7 @@ impl TryFrom<IoType> for FaultIoType {
         match value {
             IoType::Read => Ok(Self::Read),
             IoType::Write => Ok(Self::Write),
+            IoType::Flush => Ok(Self::Flush),
             _ => Err(()),
         }
     }
@@ -110,6 +113,9 @@ pub enum FaultType {
 impl Display for FaultType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Status(IoCompletionStatus::LvolError(
+                LvolFailure::NoSpace,
+            )) => f.write_str("nospace"),
             Self::Status(_) => f.write_str("status"),
             Self::Data => f.write_str("data"),
         }
@@ -124,6 +130,10 @@ impl FaultType {
             GenericStatusCode::DataTransferError,
         )))
     }
+
+    pub fn status_no_space() -> Self {
+        Self::Status(IoCompletionStatus::LvolError(LvolFailure::NoSpace))
+    }
 }
 
 /// Injection I/O.
@@ -0,0 +1,120 @@
+//!
+//! Generation numbers of the pools, replicas and nexuses. The generation of
+//! an object increases each time it is changed, so that a client which
+//! changes an object can require it to be still as it last saw it, and have
+//! its request rejected if another client changed it in the meantime.
+//!
+//! Generations are drawn from a single node-wide counter, which starts at
+//! the time the I/O engine started in microseconds. The generation of an
+//! object therefore never goes backwards, even across restarts of the I/O
+//! engine or destruction and re-creation of the object.
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use once_cell::sync::Lazy;
+use snafu::Snafu;
+
+/// Kind of an object with a generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Pool,
+    Replica,
+    Nexus,
+}
+
+impl Display for ResourceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pool => write!(f, "pool"),
+            Self::Replica => write!(f, "replica"),
+            Self::Nexus => write!(f, "nexus"),
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+pub enum GenerationError {
+    #[snafu(display(
+        "{} {} is at generation {}, expected generation {}",
+        kind,
+        name,
+        current,
+        expected
+    ))]
+    GenerationMismatch {
+        kind: ResourceKind,
+        name: String,
+        expected: u64,
+        current: u64,
+    },
+}
+
+/// Next generation to hand out.
+static NEXT_GENERATION: Lazy<AtomicU64> = Lazy::new(|| {
+    AtomicU64::new(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64,
+    )
+});
+
+/// Current generations of the objects, by kind and name or uuid.
+static GENERATIONS: Lazy<
+    parking_lot::Mutex<HashMap<(ResourceKind, String), u64>>,
+> = Lazy::new(Default::default);
+
+/// Generation numbers of the objects.
+pub struct ResourceGeneration {}
+
+impl ResourceGeneration {
+    /// Returns the current generation of an object, assigning it one if it
+    /// has none yet.
+    pub fn current(kind: ResourceKind, name: &str) -> u64 {
+        *GENERATIONS
+            .lock()
+            .entry((kind, name.to_string()))
+            .or_insert_with(|| NEXT_GENERATION.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Moves an object to a new generation, and returns it.
+    pub fn bump(kind: ResourceKind, name: &str) -> u64 {
+        let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+        GENERATIONS
+            .lock()
+            .insert((kind, name.to_string()), generation);
+        generation
+    }
+
+    /// Forgets the generation of a destroyed object.
+    pub fn forget(kind: ResourceKind, name: &str) {
+        GENERATIONS.lock().remove(&(kind, name.to_string()));
+    }
+
+    /// Checks that an object is at the expected generation, if any.
+    pub fn check(
+        kind: ResourceKind,
+        name: &str,
+        expected: Option<u64>,
+    ) -> Result<(), GenerationError> {
+        let Some(expected) = expected else {
+            return Ok(());
+        };
+        let current = Self::current(kind, name);
+        if current != expected {
+            return Err(GenerationError::GenerationMismatch {
+                kind,
+                name: name.to_string(),
+                expected,
+                current,
+            });
+        }
+        Ok(())
+    }
+}
@@ -11,6 +11,7 @@ use nix::errno::Errno;
 use spdk_rs::{
     libspdk::{
         spdk_bdev_desc,
+        spdk_bdev_flush,
         spdk_bdev_free_io,
         spdk_bdev_io,
         spdk_bdev_nvme_admin_passthru_ro,
@@ -240,6 +241,35 @@ impl<T: BdevOps> BdevHandle<T> {
         }
     }
 
+    /// Flushes the volatile write cache of the bdev, if any.
+    pub async fn flush(&self) -> Result<(), CoreError> {
+        let (s, r) = oneshot::channel::<NvmeStatus>();
+        let errno = unsafe {
+            spdk_bdev_flush(
+                self.desc.legacy_as_ptr(),
+                self.channel.legacy_as_ptr(),
+                0,
+                self.desc.bdev().size_in_bytes(),
+                Some(Self::io_completion_cb),
+                cb_arg(s),
+            )
+        };
+
+        if errno != 0 {
+            return Err(CoreError::FlushDispatch {
+                source: Errno::from_i32(errno.abs()),
+            });
+        }
+
+        if r.await.expect("Failed awaiting flush IO")
+            == NvmeStatus::Generic(GenericStatusCode::Success)
+        {
+            Ok(())
+        } else {
+            Err(CoreError::FlushFailed {})
+        }
+    }
+
     pub async fn write_zeroes_at(
         &self,
         offset: u64,
@@ -0,0 +1,253 @@
+//! Windowed I/O bandwidth of a device.
+//!
+//! Bytes are counted in rings of buckets of increasing length: seconds for
+//! the last minute, minutes for the last hour and hours for the last day, so
+//! that the traffic over each of these windows can be reported without
+//! keeping a sample per second for a whole day. The traffic of the client
+//! I/Os and of the rebuilds is counted apart, which lets users running
+//! replicas across zones tell where their cross-zone traffic comes from.
+//!
+//! Each core counts in rings of its own, which are summed when the counters
+//! are read, so that the I/O path of a core never writes to the counters of
+//! another core. Buckets are recycled without locking, so that the counts of
+//! I/Os completing on a bucket boundary may be slightly off.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use spdk_rs::libspdk::{spdk_get_ticks, spdk_get_ticks_hz};
+
+use super::Cores;
+
+/// Number of traffic classes.
+const CLASSES: usize = 4;
+
+/// Class of the traffic of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoBandwidthClass {
+    /// Bytes read by client I/Os.
+    Read = 0,
+    /// Bytes written by client I/Os.
+    Write = 1,
+    /// Bytes read from the device by a rebuild.
+    RebuildRead = 2,
+    /// Bytes written to the device by a rebuild.
+    RebuildWrite = 3,
+}
+
+/// Bucket of a ring, counting the bytes of its epoch.
+#[derive(Debug)]
+struct Bucket {
+    epoch: AtomicU64,
+    bytes: [AtomicU64; CLASSES],
+}
+
+impl Default for Bucket {
+    fn default() -> Self {
+        Self {
+            epoch: AtomicU64::new(u64::MAX),
+            bytes: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+/// Ring of buckets of the given length, in seconds.
+#[derive(Debug)]
+struct Ring {
+    bucket_secs: u64,
+    buckets: Vec<Bucket>,
+}
+
+impl Ring {
+    fn new(bucket_secs: u64, count: usize) -> Self {
+        Self {
+            bucket_secs,
+            buckets: (0 .. count).map(|_| Bucket::default()).collect(),
+        }
+    }
+
+    fn record(&self, now: u64, class: IoBandwidthClass, bytes: u64) {
+        let epoch = now / self.bucket_secs;
+        let bucket = &self.buckets[epoch as usize % self.buckets.len()];
+
+        let old = bucket.epoch.load(Ordering::Relaxed);
+        if old != epoch
+            && bucket
+                .epoch
+                .compare_exchange(
+                    old,
+                    epoch,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+        {
+            bucket
+                .bytes
+                .iter()
+                .for_each(|b| b.store(0, Ordering::Relaxed));
+        }
+
+        bucket.bytes[class as usize].fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Adds the buckets of the ring which are still in its window to the
+    /// given sums.
+    fn window(&self, now: u64, bytes: &mut [u64; CLASSES]) {
+        let epoch = now / self.bucket_secs;
+        let first = epoch.saturating_sub(self.buckets.len() as u64 - 1);
+
+        self.buckets
+            .iter()
+            .filter(|b| {
+                let e = b.epoch.load(Ordering::Relaxed);
+                e >= first && e <= epoch
+            })
+            .for_each(|b| {
+                bytes.iter_mut().zip(b.bytes.iter()).for_each(|(sum, n)| {
+                    *sum += n.load(Ordering::Relaxed);
+                })
+            });
+    }
+}
+
+/// Bytes transferred to and from a device over a window of time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IoBandwidthWindow {
+    /// Bytes read by client I/Os.
+    pub bytes_read: u64,
+    /// Bytes written by client I/Os.
+    pub bytes_written: u64,
+    /// Bytes read from the device by rebuilds.
+    pub rebuild_bytes_read: u64,
+    /// Bytes written to the device by rebuilds.
+    pub rebuild_bytes_written: u64,
+}
+
+impl From<[u64; CLASSES]> for IoBandwidthWindow {
+    fn from(bytes: [u64; CLASSES]) -> Self {
+        Self {
+            bytes_read: bytes[IoBandwidthClass::Read as usize],
+            bytes_written: bytes[IoBandwidthClass::Write as usize],
+            rebuild_bytes_read: bytes[IoBandwidthClass::RebuildRead as usize],
+            rebuild_bytes_written: bytes
+                [IoBandwidthClass::RebuildWrite as usize],
+        }
+    }
+}
+
+/// Point-in-time copy of the bandwidth counters of a device.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IoBandwidthStats {
+    /// Traffic of the last minute.
+    pub last_minute: IoBandwidthWindow,
+    /// Traffic of the last hour.
+    pub last_hour: IoBandwidthWindow,
+    /// Traffic of the last day.
+    pub last_day: IoBandwidthWindow,
+    /// Traffic since the counters were created.
+    pub total: IoBandwidthWindow,
+}
+
+/// Bandwidth counters of a device on a core.
+#[derive(Debug)]
+struct CoreBandwidth {
+    core: u32,
+    seconds: Ring,
+    minutes: Ring,
+    hours: Ring,
+    total: [AtomicU64; CLASSES],
+}
+
+impl CoreBandwidth {
+    fn new(core: u32) -> Self {
+        Self {
+            core,
+            seconds: Ring::new(1, 60),
+            minutes: Ring::new(60, 60),
+            hours: Ring::new(3600, 24),
+            total: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    fn record(&self, now: u64, class: IoBandwidthClass, bytes: u64) {
+        self.seconds.record(now, class, bytes);
+        self.minutes.record(now, class, bytes);
+        self.hours.record(now, class, bytes);
+        self.total[class as usize].fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+/// Bandwidth counters of a device.
+#[derive(Debug)]
+pub struct IoBandwidth {
+    cores: Vec<CoreBandwidth>,
+}
+
+impl Default for IoBandwidth {
+    fn default() -> Self {
+        Self {
+            cores: Cores::count().into_iter().map(CoreBandwidth::new).collect(),
+        }
+    }
+}
+
+impl IoBandwidth {
+    /// Returns the current time in seconds, from the TSC.
+    fn now() -> u64 {
+        unsafe { spdk_get_ticks() / spdk_get_ticks_hz() }
+    }
+
+    /// Returns the slot of the counters of the given core, to be passed to
+    /// `record_slot`.
+    pub fn slot(&self, core: u32) -> usize {
+        self.cores
+            .iter()
+            .position(|c| c.core == core)
+            .unwrap_or_default()
+    }
+
+    /// Counts bytes of the given class transferred now, on the current core.
+    pub fn record(&self, class: IoBandwidthClass, bytes: u64) {
+        self.record_slot(self.slot(Cores::current()), class, bytes);
+    }
+
+    /// Counts bytes of the given class transferred now, in the counters of
+    /// the given slot.
+    #[inline]
+    pub fn record_slot(
+        &self,
+        slot: usize,
+        class: IoBandwidthClass,
+        bytes: u64,
+    ) {
+        if bytes == 0 {
+            return;
+        }
+
+        self.cores[slot].record(Self::now(), class, bytes);
+    }
+
+    /// Returns a copy of the counters, summed over all cores.
+    pub fn stats(&self) -> IoBandwidthStats {
+        let now = Self::now();
+        let mut minute = [0; CLASSES];
+        let mut hour = [0; CLASSES];
+        let mut day = [0; CLASSES];
+        let mut total = [0; CLASSES];
+
+        self.cores.iter().for_each(|c| {
+            c.seconds.window(now, &mut minute);
+            c.minutes.window(now, &mut hour);
+            c.hours.window(now, &mut day);
+            total.iter_mut().zip(c.total.iter()).for_each(|(sum, n)| {
+                *sum += n.load(Ordering::Relaxed);
+            });
+        });
+
+        IoBandwidthStats {
+            last_minute: IoBandwidthWindow::from(minute),
+            last_hour: IoBandwidthWindow::from(hour),
+            last_day: IoBandwidthWindow::from(day),
+            total: IoBandwidthWindow::from(total),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Per-device I/O latency histograms.
+//!
+//! Histograms are collected only for the devices they have been explicitly
+//! enabled for, so that the I/O completion path of all other devices pays no
+//! more than a single atomic load. Latencies are counted in log2 buckets of
+//! microseconds: bucket `0` counts latencies below 2us, and bucket `i` counts
+//! latencies in `[2^i, 2^(i+1))` microseconds.
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use once_cell::sync::Lazy;
+use spdk_rs::libspdk::{spdk_get_ticks, spdk_get_ticks_hz};
+
+use crate::core::IoType;
+
+/// Number of histogram buckets. The last bucket also counts all latencies
+/// above its lower bound.
+pub const IO_HISTOGRAM_BUCKETS: usize = 32;
+
+/// Read and write latency histogram of a device.
+#[derive(Debug)]
+pub struct IoLatencyHistogram {
+    read: [AtomicU64; IO_HISTOGRAM_BUCKETS],
+    write: [AtomicU64; IO_HISTOGRAM_BUCKETS],
+}
+
+/// Point-in-time copy of a latency histogram.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IoLatencyHistogramSnapshot {
+    /// Read latency buckets.
+    pub read: Vec<u64>,
+    /// Write latency buckets.
+    pub write: Vec<u64>,
+}
+
+impl IoLatencyHistogramSnapshot {
+    /// Returns the upper bound in microseconds of the bucket containing the
+    /// given percentile of the given buckets, or `None` if no I/O has been
+    /// counted.
+    pub fn percentile_us(buckets: &[u64], percentile: f64) -> Option<u64> {
+        let total: u64 = buckets.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let rank = (total as f64 * percentile).ceil() as u64;
+        let mut seen = 0;
+        buckets.iter().enumerate().find_map(|(i, n)| {
+            seen += n;
+            (seen >= rank).then(|| 1u64 << (i + 1))
+        })
+    }
+}
+
+impl Default for IoLatencyHistogram {
+    fn default() -> Self {
+        Self {
+            read: std::array::from_fn(|_| AtomicU64::new(0)),
+            write: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+impl IoLatencyHistogram {
+    /// Counts an I/O of the given type which took the given number of
+    /// microseconds. I/O types other than read and write are ignored.
+    pub fn record(&self, io_type: IoType, latency_us: u64) {
+        let buckets = match io_type {
+            IoType::Read => &self.read,
+            IoType::Write => &self.write,
+            _ => return,
+        };
+
+        let idx = std::cmp::min(
+            (u64::BITS - latency_us.leading_zeros()).saturating_sub(1) as usize,
+            IO_HISTOGRAM_BUCKETS - 1,
+        );
+        buckets[idx].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns a copy of the histogram.
+    pub fn snapshot(&self) -> IoLatencyHistogramSnapshot {
+        let load = |b: &[AtomicU64; IO_HISTOGRAM_BUCKETS]| {
+            b.iter().map(|n| n.load(Ordering::Relaxed)).collect()
+        };
+
+        IoLatencyHistogramSnapshot {
+            read: load(&self.read),
+            write: load(&self.write),
+        }
+    }
+
+    /// Clears all buckets of the histogram.
+    pub fn reset(&self) {
+        self.read
+            .iter()
+            .chain(self.write.iter())
+            .for_each(|n| n.store(0, Ordering::Relaxed));
+    }
+}
+
+/// Number of devices with an enabled histogram.
+static ENABLED: AtomicUsize = AtomicUsize::new(0);
+
+/// Enabled histograms, keyed by device name.
+static HISTOGRAMS: Lazy<
+    parking_lot::RwLock<HashMap<String, Arc<IoLatencyHistogram>>>,
+> = Lazy::new(Default::default);
+
+/// Registry of device latency histograms.
+pub struct IoHistograms {}
+
+impl IoHistograms {
+    /// Enables histogram collection for the given device. Enabling an already
+    /// enabled histogram keeps its buckets.
+    pub fn enable(name: &str) {
+        let mut h = HISTOGRAMS.write();
+        if !h.contains_key(name) {
+            info!("Enabling I/O latency histogram for '{name}'");
+            h.insert(name.to_string(), Default::default());
+            ENABLED.store(h.len(), Ordering::Relaxed);
+        }
+    }
+
+    /// Disables histogram collection for the given device, and drops its
+    /// buckets.
+    pub fn disable(name: &str) {
+        let mut h = HISTOGRAMS.write();
+        if h.remove(name).is_some() {
+            info!("Disabling I/O latency histogram for '{name}'");
+            ENABLED.store(h.len(), Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the histogram of the given device, if enabled.
+    pub fn get(name: &str) -> Option<Arc<IoLatencyHistogram>> {
+        if ENABLED.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        HISTOGRAMS.read().get(name).cloned()
+    }
+
+    /// Checks if any histogram is enabled. Used by the I/O path to skip the
+    /// lookup.
+    #[inline(always)]
+    pub fn any_enabled() -> bool {
+        ENABLED.load(Ordering::Relaxed) > 0
+    }
+
+    /// Counts a completed I/O of the given device, submitted at the given
+    /// TSC value.
+    pub fn record(name: &str, io_type: IoType, submit_tsc: u64) {
+        if let Some(h) = Self::get(name) {
+            let (now, hz) = unsafe { (spdk_get_ticks(), spdk_get_ticks_hz()) };
+            let latency_us =
+                now.saturating_sub(submit_tsc).saturating_mul(1_000_000) / hz;
+            h.record(io_type, latency_us);
+        }
+    }
+}
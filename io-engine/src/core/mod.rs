@@ -5,7 +5,15 @@ use std::{fmt::Debug, sync::atomic::AtomicUsize};
 use nix::errno::Errno;
 use snafu::Snafu;
 
-pub use bdev::{Bdev, BdevIter, UntypedBdev};
+pub use bdev::{
+    Bdev,
+    BdevHistogram,
+    BdevHistogramBucket,
+    BdevIter,
+    DataProtection,
+    ProtectionType,
+    UntypedBdev,
+};
 pub use block_device::{
     BlockDevice,
     BlockDeviceDescriptor,
@@ -40,9 +48,23 @@ pub use env::{
     GLOBAL_RC,
     SIG_RECEIVED,
 };
+pub use generation::{GenerationError, ResourceGeneration, ResourceKind};
 pub use handle::{BdevHandle, UntypedBdevHandle};
+pub use io_bandwidth::{
+    IoBandwidth,
+    IoBandwidthClass,
+    IoBandwidthStats,
+    IoBandwidthWindow,
+};
 pub use io_device::IoDevice;
+pub use io_histogram::{
+    IoHistograms,
+    IoLatencyHistogram,
+    IoLatencyHistogramSnapshot,
+    IO_HISTOGRAM_BUCKETS,
+};
 pub use logical_volume::LogicalVolume;
+pub use numa::{Numa, NumaConfig};
 pub use reactor::{
     reactor_monitor_loop,
     Reactor,
@@ -61,7 +83,14 @@ pub use lock::{
 
 pub use runtime::spawn;
 pub(crate) use segment_map::SegmentMap;
-pub use share::{Protocol, PtplProps, Share, ShareProps, UpdateProps};
+pub use share::{
+    NvmeIdentity,
+    Protocol,
+    PtplProps,
+    Share,
+    ShareProps,
+    UpdateProps,
+};
 pub use spdk_rs::{cpu_cores, GenericStatusCode, IoStatus, IoType, NvmeStatus};
 pub use thread::Mthread;
 
@@ -83,17 +112,22 @@ mod device_monitor;
 pub mod diagnostics;
 mod env;
 pub mod fault_injection;
+mod generation;
 mod handle;
+mod io_bandwidth;
 mod io_device;
 pub mod io_driver;
+mod io_histogram;
 pub mod lock;
 pub mod logical_volume;
 pub mod mempool;
 mod nic;
+mod numa;
 pub mod partition;
 mod reactor;
 pub mod runtime;
 pub(crate) mod segment_map;
+pub(crate) mod self_test;
 mod share;
 pub mod snapshot;
 pub(crate) mod thread;
@@ -182,6 +216,10 @@ pub enum CoreError {
     FlushDispatch {
         source: Errno,
     },
+    #[snafu(display("Failed to dispatch abort: {}", source))]
+    AbortDispatch {
+        source: Errno,
+    },
     #[snafu(display(
         "Failed to dispatch NVMe Admin command {:x}h: {}",
         opcode,
@@ -264,6 +302,8 @@ pub enum CoreError {
     },
     #[snafu(display("Reset failed"))]
     ResetFailed {},
+    #[snafu(display("Flush failed"))]
+    FlushFailed {},
     #[snafu(display(
         "Write zeroes failed at offset {} length {}",
         offset,
@@ -334,6 +374,24 @@ pub enum CoreError {
     WipeFailed {
         source: wiper::Error,
     },
+    #[snafu(display(
+        "Failed to access the latency histogram of device {}: {}",
+        name,
+        source
+    ))]
+    Histogram {
+        name: String,
+        source: Errno,
+    },
+    #[snafu(display(
+        "Failed to set the QoS limits of device {}: {}",
+        name,
+        source
+    ))]
+    QosLimit {
+        name: String,
+        source: Errno,
+    },
 }
 
 /// Represent error as Errno value.
@@ -375,6 +433,9 @@ impl ToErrno for CoreError {
             Self::FlushDispatch {
                 source, ..
             } => source,
+            Self::AbortDispatch {
+                source, ..
+            } => source,
             Self::NvmeAdminDispatch {
                 source, ..
             } => source,
@@ -402,6 +463,9 @@ impl ToErrno for CoreError {
             | Self::ResetFailed {
                 ..
             }
+            | Self::FlushFailed {
+                ..
+            }
             | Self::WriteZeroesFailed {
                 ..
             }
@@ -447,6 +511,12 @@ impl ToErrno for CoreError {
             Self::WipeFailed {
                 ..
             } => Errno::EIO,
+            Self::Histogram {
+                source, ..
+            } => source,
+            Self::QosLimit {
+                source, ..
+            } => source,
         }
     }
 }
@@ -492,4 +562,7 @@ pub static PAUSED: AtomicUsize = AtomicUsize::new(0);
 #[derive(Debug, Clone)]
 pub struct MayastorFeatures {
     pub asymmetric_namespace_access: bool,
+    /// Batches the completions of the nexus I/Os and the submissions to the
+    /// NVMe children.
+    pub nexus_batched_io: bool,
 }
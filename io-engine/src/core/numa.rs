@@ -0,0 +1,163 @@
+//! NUMA placement of the buffers and threads serving a device.
+//!
+//! On a multi-socket node, a NIC or an NVMe device is attached to one socket,
+//! and the memory and the cores of the other sockets reach it only across the
+//! socket interconnect. When enabled, the copy buffers of a rebuild are
+//! allocated on the NUMA node of its destination, and the NVMf poll groups
+//! are created on the cores of the NUMA node of the NVMf target interface.
+//!
+//! The NUMA node of a device is read from sysfs, unless it is configured
+//! explicitly for the device.
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use once_cell::sync::OnceCell;
+use spdk_rs::libspdk::spdk_env_get_socket_id;
+use url::Url;
+
+use crate::core::{nic, MayastorEnvironment, Reactors};
+
+/// NUMA placement configuration.
+#[derive(Debug, Clone, Default)]
+pub struct NumaConfig {
+    /// Places the buffers and the poll groups on the NUMA node of their
+    /// device.
+    pub enabled: bool,
+    /// NUMA nodes of devices, by PCI address, block device name or network
+    /// interface name. They take precedence over the nodes found in sysfs.
+    /// A node of -1 disables the placement for the device.
+    pub nodes: HashMap<String, i32>,
+}
+
+static NUMA_CONFIG: OnceCell<NumaConfig> = OnceCell::new();
+
+/// Parses a `<device>=<node>` NUMA node of a device.
+pub(crate) fn parse_numa_node(src: &str) -> Result<(String, i32), String> {
+    let Some((device, node)) = src.split_once('=') else {
+        return Err(format!("Invalid NUMA node {src}: expected DEVICE=NODE"));
+    };
+
+    match node.parse::<i32>() {
+        Ok(node) if !device.is_empty() && node >= -1 => {
+            Ok((device.to_string(), node))
+        }
+        _ => Err(format!("Invalid NUMA node {src}")),
+    }
+}
+
+/// NUMA placement of the buffers and threads serving a device.
+pub struct Numa {}
+
+impl Numa {
+    /// Sets the NUMA placement configuration. Only the first call has an
+    /// effect.
+    pub fn configure(config: NumaConfig) {
+        if config.enabled {
+            info!("NUMA placement: {config:?}");
+        }
+
+        if NUMA_CONFIG.set(config).is_err() {
+            warn!("NUMA placement has already been configured");
+        }
+    }
+
+    /// Returns the NUMA placement configuration, if enabled.
+    fn config() -> Option<&'static NumaConfig> {
+        NUMA_CONFIG.get().filter(|c| c.enabled)
+    }
+
+    /// Returns the NUMA node of the given device, from its configured node
+    /// or from the first of the given sysfs files which reports one.
+    fn node_of(
+        config: &NumaConfig,
+        device: &str,
+        sysfs: &[String],
+    ) -> Option<u32> {
+        let node = match config.nodes.get(device) {
+            Some(node) => *node,
+            None => sysfs.iter().find_map(|path| {
+                fs::read_to_string(path).ok()?.trim().parse::<i32>().ok()
+            })?,
+        };
+        u32::try_from(node).ok()
+    }
+
+    /// Returns the NUMA node of the device behind the given URI, if NUMA
+    /// placement is enabled and the node is known. Remote devices are
+    /// reached via the NVMf target interface.
+    pub fn device_node(uri: &str) -> Option<u32> {
+        let config = Self::config()?;
+        let url = Url::parse(uri).ok()?;
+
+        match url.scheme() {
+            "pcie" => {
+                let addr = url.path().trim_start_matches('/');
+                Self::node_of(
+                    config,
+                    addr,
+                    &[format!("/sys/bus/pci/devices/{addr}/numa_node")],
+                )
+            }
+            "aio" | "uring" => {
+                let path = fs::canonicalize(url.path()).ok()?;
+                let name = path.file_name()?.to_str()?;
+                let class = Path::new("/sys/class/block").join(name);
+                Self::node_of(
+                    config,
+                    name,
+                    &[
+                        format!("{}/device/numa_node", class.display()),
+                        format!("{}/device/device/numa_node", class.display()),
+                    ],
+                )
+            }
+            "nvmf" => Self::nvmf_tgt_node(),
+            _ => None,
+        }
+    }
+
+    /// Returns the NUMA node of the NVMf target interface, if NUMA placement
+    /// is enabled and the node is known.
+    pub fn nvmf_tgt_node() -> Option<u32> {
+        let config = Self::config()?;
+        let ip = MayastorEnvironment::get_nvmf_tgt_ip().ok()?;
+        let iface = nic::find_all_nics().into_iter().find(|n| {
+            n.inet.addr.map(|a| a.to_string()).as_ref() == Some(&ip)
+        })?;
+
+        Self::node_of(
+            config,
+            &iface.name,
+            &[format!("/sys/class/net/{}/device/numa_node", iface.name)],
+        )
+    }
+
+    /// Returns the NUMA node of the given core.
+    pub fn core_node(core: u32) -> u32 {
+        unsafe { spdk_env_get_socket_id(core) }
+    }
+
+    /// Returns the reactor cores of the given NUMA node.
+    pub fn cores(node: u32) -> Vec<u32> {
+        Reactors::iter()
+            .map(|r| r.core())
+            .filter(|core| Self::core_node(*core) == node)
+            .collect()
+    }
+
+    /// Returns a reactor core of the given NUMA node. Successive calls
+    /// spread over the cores of the node.
+    pub fn local_core(node: u32) -> Option<u32> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let cores = Self::cores(node);
+        if cores.is_empty() {
+            return None;
+        }
+        Some(cores[NEXT.fetch_add(1, Ordering::Relaxed) % cores.len()])
+    }
+}
@@ -56,12 +56,50 @@ impl SegmentMap {
         }
     }
 
+    /// Creates a new segment map with the given parameters, and the dirty
+    /// segments marked in the given bitmap, most significant bit first.
+    pub(crate) fn from_bitmap(
+        num_blocks: u64,
+        block_len: u64,
+        segment_size: u64,
+        bitmap: &[u8],
+    ) -> Self {
+        let mut map = Self::new(num_blocks, block_len, segment_size);
+        let bits = BitVec::from_bytes(bitmap);
+        for (i, _) in bits.iter().enumerate().filter(|(_, d)| *d) {
+            if i as u64 >= map.num_segments {
+                break;
+            }
+            map.segments.set(i, true);
+        }
+        map
+    }
+
     /// Merges (bitwise OR) this map with another.
     pub(crate) fn merge(mut self, other: &SegmentMap) -> Self {
         self.segments.or(&other.segments);
         self
     }
 
+    /// Returns a copy of this map with the given segment size. A segment of
+    /// the new map is dirty if it overlaps a dirty segment of this map.
+    pub(crate) fn with_segment_size(&self, segment_size: u64) -> Self {
+        if segment_size == self.segment_size {
+            return self.clone();
+        }
+
+        let mut map = Self::new(self.num_blocks, self.block_len, segment_size);
+        let size = self.num_blocks * self.block_len;
+        for (i, _) in self.segments.iter().enumerate().filter(|(_, d)| *d) {
+            let start = i as u64 * self.segment_size;
+            let end = (start + self.segment_size).min(size);
+            for seg in start / segment_size ..= (end - 1) / segment_size {
+                map.segments.set(seg as usize, true);
+            }
+        }
+        map
+    }
+
     /// Sets a segment bit corresponding to the given logical block, to the
     /// given value.
     pub(crate) fn set(&mut self, lbn: u64, lbn_cnt: u64, value: bool) {
@@ -0,0 +1,320 @@
+//! End-to-end node validation.
+//!
+//! The self-test creates a temporary malloc pool, a replica on it and a nexus
+//! on top of the replica, verifies that data written via the nexus reads back
+//! unchanged, both from the nexus and from a snapshot of the replica, and
+//! finally removes all of the resources it has created. Each stage is
+//! reported separately, so that a failure can be attributed to the layer
+//! which caused it.
+use std::time::{Duration, Instant};
+
+use crate::{
+    bdev::nexus::{nexus_create, nexus_lookup_mut},
+    core::{
+        logical_volume::LogicalVolume,
+        snapshot::SnapshotOps,
+        SnapshotParams,
+        UntypedBdevHandle,
+        VerboseError,
+    },
+    lvs::{Lvol, Lvs, LvsLvol},
+    pool_backend::PoolArgs,
+};
+
+/// Size of the temporary malloc pool disk, in MiB.
+const SELF_TEST_POOL_SIZE_MB: u64 = 64;
+
+/// Size of the temporary replica and nexus.
+const SELF_TEST_VOLUME_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Size of each I/O of the verify pass.
+const SELF_TEST_IO_SIZE: u64 = 64 * 1024;
+
+/// Number of I/Os of the verify pass, spread evenly over the volume.
+const SELF_TEST_IO_COUNT: u64 = 16;
+
+/// Self-test stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestStage {
+    CreatePool,
+    CreateReplica,
+    CreateNexus,
+    IoVerify,
+    Snapshot,
+    Cleanup,
+}
+
+/// Result of a self-test stage.
+#[derive(Debug, Clone)]
+pub struct SelfTestStageResult {
+    /// Stage.
+    pub stage: SelfTestStage,
+    /// Error message, if the stage has failed.
+    pub error: Option<String>,
+    /// Time the stage took.
+    pub elapsed: Duration,
+}
+
+impl SelfTestStageResult {
+    /// Checks if the stage has passed.
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Temporary resources created by the self-test.
+struct SelfTest {
+    id: uuid::Uuid,
+    results: Vec<SelfTestStageResult>,
+}
+
+impl SelfTest {
+    fn pool_name(&self) -> String {
+        format!("selftest-pool-{}", self.id)
+    }
+
+    fn replica_name(&self) -> String {
+        format!("selftest-replica-{}", self.id)
+    }
+
+    fn nexus_name(&self) -> String {
+        format!("selftest-nexus-{}", self.id)
+    }
+
+    /// Runs a stage and records its result. Returns the value produced by
+    /// the stage, or `None` if it has failed.
+    async fn stage<T, F>(&mut self, stage: SelfTestStage, f: F) -> Option<T>
+    where
+        F: std::future::Future<Output = Result<T, String>>,
+    {
+        let start = Instant::now();
+        let res = f.await;
+        let elapsed = start.elapsed();
+
+        let error = match &res {
+            Ok(_) => {
+                info!("Self-test {stage:?}: passed in {elapsed:?}");
+                None
+            }
+            Err(e) => {
+                error!("Self-test {stage:?}: failed: {e}");
+                Some(e.clone())
+            }
+        };
+
+        self.results.push(SelfTestStageResult {
+            stage,
+            error,
+            elapsed,
+        });
+
+        res.ok()
+    }
+}
+
+/// Makes the data pattern of the given I/O of the verify pass.
+fn pattern(id: &uuid::Uuid, n: u64) -> Vec<u8> {
+    id.as_bytes()
+        .iter()
+        .cycle()
+        .enumerate()
+        .map(|(i, b)| b.wrapping_add(n as u8).wrapping_add(i as u8))
+        .take(SELF_TEST_IO_SIZE as usize)
+        .collect()
+}
+
+/// Offset of the given I/O of the verify pass.
+fn io_offset(n: u64) -> u64 {
+    n * (SELF_TEST_VOLUME_SIZE / SELF_TEST_IO_COUNT)
+}
+
+/// Writes the verify pattern to the given device.
+async fn write_pattern(name: &str, id: &uuid::Uuid) -> Result<(), String> {
+    let hdl =
+        UntypedBdevHandle::open(name, true, false).map_err(|e| e.verbose())?;
+    let mut buf = hdl
+        .dma_malloc(SELF_TEST_IO_SIZE)
+        .map_err(|e| e.to_string())?;
+
+    for n in 0 .. SELF_TEST_IO_COUNT {
+        buf.as_mut_slice().copy_from_slice(&pattern(id, n));
+        hdl.write_at(io_offset(n), &buf)
+            .await
+            .map_err(|e| e.verbose())?;
+    }
+
+    Ok(())
+}
+
+/// Reads the given device back and compares it to the verify pattern.
+async fn verify_pattern(name: &str, id: &uuid::Uuid) -> Result<(), String> {
+    let hdl =
+        UntypedBdevHandle::open(name, false, false).map_err(|e| e.verbose())?;
+    let mut buf = hdl
+        .dma_malloc(SELF_TEST_IO_SIZE)
+        .map_err(|e| e.to_string())?;
+
+    for n in 0 .. SELF_TEST_IO_COUNT {
+        let offset = io_offset(n);
+        hdl.read_at(offset, &mut buf)
+            .await
+            .map_err(|e| e.verbose())?;
+        if buf.as_slice() != pattern(id, n).as_slice() {
+            return Err(format!("data mismatch on '{name}' at {offset}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Snapshots the replica, verifies the snapshot data and destroys the
+/// snapshot.
+async fn snapshot_round_trip(
+    replica: &Lvol,
+    id: &uuid::Uuid,
+) -> Result<(), String> {
+    let snapshot_uuid = uuid::Uuid::new_v4().to_string();
+    let params = SnapshotParams::new(
+        Some(replica.uuid()),
+        Some(replica.uuid()),
+        Some(uuid::Uuid::new_v4().to_string()),
+        Some(format!("selftest-snapshot-{id}")),
+        Some(snapshot_uuid),
+        Some(chrono::Utc::now().to_string()),
+        false,
+    );
+
+    let snapshot = replica
+        .create_snapshot(params)
+        .await
+        .map_err(|e| e.verbose())?;
+
+    let verified = verify_pattern(&snapshot.name(), id).await;
+    let destroyed = snapshot.destroy_snapshot().await.map_err(|e| e.verbose());
+
+    verified.and(destroyed)
+}
+
+/// Removes all the resources the self-test may have created.
+async fn cleanup(nexus_name: &str, pool_name: &str) -> Result<(), String> {
+    let mut errors = Vec::new();
+
+    if let Some(nexus) = nexus_lookup_mut(nexus_name) {
+        if let Err(e) = nexus.destroy().await {
+            errors.push(e.verbose());
+        }
+    }
+
+    if let Some(lvs) = Lvs::lookup(pool_name) {
+        if let Err(e) = lvs.destroy().await {
+            errors.push(e.verbose());
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// Runs the self-test and returns the results of all stages which have been
+/// run. Stages depending on a failed stage are skipped, but the cleanup stage
+/// is always run.
+pub async fn run_self_test() -> Vec<SelfTestStageResult> {
+    let mut test = SelfTest {
+        id: uuid::Uuid::new_v4(),
+        results: Vec::new(),
+    };
+
+    info!("Running self-test {id}...", id = test.id);
+
+    let pool_args = PoolArgs {
+        name: test.pool_name(),
+        disks: vec![format!(
+            "malloc:///{}?size_mb={SELF_TEST_POOL_SIZE_MB}",
+            test.pool_name()
+        )],
+        uuid: None,
+        force: false,
+    };
+
+    let id = test.id;
+    let replica_name = test.replica_name();
+    let nexus_name = test.nexus_name();
+
+    let replica = match test
+        .stage(SelfTestStage::CreatePool, async {
+            Lvs::create_or_import(pool_args)
+                .await
+                .map_err(|e| e.verbose())
+        })
+        .await
+    {
+        Some(lvs) => {
+            test.stage(SelfTestStage::CreateReplica, async {
+                lvs.create_lvol(
+                    &replica_name,
+                    SELF_TEST_VOLUME_SIZE,
+                    None,
+                    false,
+                )
+                .await
+                .map_err(|e| e.verbose())
+            })
+            .await
+        }
+        None => None,
+    };
+
+    if let Some(replica) = &replica {
+        let child = format!(
+            "loopback:///{name}?uuid={uuid}",
+            name = replica.name(),
+            uuid = replica.uuid()
+        );
+
+        let nexus = test
+            .stage(SelfTestStage::CreateNexus, async {
+                nexus_create(
+                    &nexus_name,
+                    SELF_TEST_VOLUME_SIZE,
+                    Some(&id.to_string()),
+                    &[child],
+                )
+                .await
+                .map_err(|e| e.verbose())
+            })
+            .await;
+
+        let verified = match nexus {
+            Some(_) => test
+                .stage(SelfTestStage::IoVerify, async {
+                    write_pattern(&nexus_name, &id).await?;
+                    verify_pattern(&nexus_name, &id).await
+                })
+                .await
+                .is_some(),
+            None => false,
+        };
+
+        if verified {
+            test.stage(SelfTestStage::Snapshot, async {
+                snapshot_round_trip(replica, &id).await
+            })
+            .await;
+        }
+    }
+
+    let pool_name = test.pool_name();
+    test.stage(SelfTestStage::Cleanup, cleanup(&nexus_name, &pool_name))
+        .await;
+
+    info!(
+        "Self-test {id} finished: {passed}/{total} stages passed",
+        passed = test.results.iter().filter(|r| r.passed()).count(),
+        total = test.results.len()
+    );
+
+    test.results
+}
@@ -1,8 +1,13 @@
 use async_trait::async_trait;
 use pin_utils::core_reexport::fmt::Formatter;
+use serde::{Deserialize, Serialize};
 use std::{convert::TryFrom, fmt::Display, pin::Pin};
 
-use crate::lvs::Error as LvsError;
+use crate::{
+    constants::NVME_NQN_PREFIX,
+    lvs::Error as LvsError,
+    subsys::validate_nqn,
+};
 
 /// Indicates what protocol the bdev is shared as.
 #[derive(Debug, PartialOrd, PartialEq)]
@@ -58,6 +63,116 @@ impl PtplProps {
     }
 }
 
+/// Maximum length of the NVMe serial number.
+const NVME_SERIAL_MAX_LEN: usize = 20;
+/// Maximum length of the NVMe model number.
+const NVME_MODEL_MAX_LEN: usize = 40;
+
+/// NVMe identification of a shared device. The fields which are not set
+/// default to values derived from the UUID of the device, which are stable
+/// across shares.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NvmeIdentity {
+    /// IEEE Extended Unique Identifier of the namespace.
+    pub eui64: Option<[u8; 8]>,
+    /// Globally unique identifier of the namespace.
+    pub nguid: Option<[u8; 16]>,
+    /// Serial number of the subsystem.
+    pub serial: Option<String>,
+    /// Model number of the subsystem.
+    pub model: Option<String>,
+    /// NQN of the subsystem.
+    pub nqn: Option<String>,
+}
+impl NvmeIdentity {
+    /// Parses the given identification fields, empty fields being left to
+    /// their default values. The identifiers are hexadecimal strings, and the
+    /// serial and model numbers are printable ASCII strings.
+    pub fn parse(
+        eui64: &str,
+        nguid: &str,
+        serial: &str,
+        model: &str,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            eui64: parse_identifier("EUI64", eui64)?,
+            nguid: parse_identifier("NGUID", nguid)?,
+            serial: parse_ascii("serial number", serial, NVME_SERIAL_MAX_LEN)?,
+            model: parse_ascii("model number", model, NVME_MODEL_MAX_LEN)?,
+            nqn: None,
+        })
+    }
+    /// Sets the NQN of the subsystem, unless empty. A value which does not
+    /// start with `nqn.` is a suffix of the mayastor NQN prefix, which keeps
+    /// the NQN deterministic.
+    pub fn with_nqn(mut self, nqn: &str) -> Result<Self, String> {
+        if nqn.is_empty() {
+            return Ok(self);
+        }
+        let nqn = if nqn.starts_with("nqn.") {
+            nqn.to_string()
+        } else if nqn
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._:".contains(c))
+        {
+            format!("{NVME_NQN_PREFIX}:{nqn}")
+        } else {
+            return Err(format!(
+                "NQN suffix '{nqn}' must be made of alphanumeric characters, \
+                '-', '.', '_' and ':'"
+            ));
+        };
+        validate_nqn(&nqn)?;
+        self.nqn = Some(nqn);
+        Ok(self)
+    }
+    /// Checks if no field is set.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Parses a non-zero hexadecimal identifier of N bytes, dashes ignored.
+fn parse_identifier<const N: usize>(
+    name: &str,
+    value: &str,
+) -> Result<Option<[u8; N]>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let digits = value.replace('-', "");
+    if digits.len() != 2 * N || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{name} must be {} hexadecimal digits", 2 * N));
+    }
+    let mut id = [0u8; N];
+    for (i, byte) in id.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[2 * i .. 2 * i + 2], 16).unwrap();
+    }
+    if id.iter().all(|b| *b == 0) {
+        return Err(format!("{name} must not be zero"));
+    }
+    Ok(Some(id))
+}
+
+/// Parses a printable ASCII string of at most `max_len` characters.
+fn parse_ascii(
+    name: &str,
+    value: &str,
+    max_len: usize,
+) -> Result<Option<String>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if value.len() > max_len
+        || !value.chars().all(|c| c.is_ascii_graphic() || c == ' ')
+    {
+        return Err(format!(
+            "{name} must be at most {max_len} printable ASCII characters"
+        ));
+    }
+    Ok(Some(value.to_string()))
+}
+
 /// Share properties when sharing a device.
 #[derive(Default)]
 pub struct ShareProps {
@@ -69,6 +184,8 @@ pub struct ShareProps {
     allowed_hosts: Vec<String>,
     /// Persistent-Power-Loss settings.
     ptpl: Option<PtplProps>,
+    /// NVMe identification.
+    identity: NvmeIdentity,
 }
 impl ShareProps {
     /// Returns a new `Self`.
@@ -119,6 +236,16 @@ impl ShareProps {
     pub fn ptpl(&self) -> &Option<PtplProps> {
         &self.ptpl
     }
+    /// Modify the NVMe identification.
+    #[must_use]
+    pub fn with_identity(mut self, identity: NvmeIdentity) -> Self {
+        self.identity = identity;
+        self
+    }
+    /// Get the NVMe identification.
+    pub fn identity(&self) -> &NvmeIdentity {
+        &self.identity
+    }
 }
 impl From<Option<ShareProps>> for ShareProps {
     fn from(opts: Option<ShareProps>) -> Self {
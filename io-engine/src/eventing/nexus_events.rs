@@ -23,6 +23,7 @@ impl EventMetaGen for RebuildJob {
             RebuildState::Stopped => RebuildStatus::Stopped,
             RebuildState::Failed => RebuildStatus::Failed,
             RebuildState::Completed => RebuildStatus::Completed,
+            RebuildState::NoSpace => RebuildStatus::NoSpace,
             _ => RebuildStatus::Unknown,
         };
 
@@ -0,0 +1,180 @@
+//!
+//! Request counters per gRPC API version. The version of a request is given
+//! by the package of its service: `mayastor.v1` and `mayastor.v2alpha`
+//! services belong to their versions, and the other `mayastor` services to
+//! v0. Health and reflection requests are not counted.
+//!
+//! The counters tell which clients still use an API version, so that its
+//! removal, or the promotion of an experimental version, can be staged.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use tonic::{body::BoxBody, Code, Status};
+use tower::{Layer, Service};
+
+use crate::subsys::registration::registration_grpc::ApiVersion;
+
+/// Live counters of the requests of an API version.
+#[derive(Debug)]
+struct ApiVersionCounters {
+    requests: AtomicU64,
+    failed: AtomicU64,
+    in_flight: AtomicU64,
+}
+
+/// Counters of the requests of an API version.
+#[derive(Debug, Clone)]
+pub struct GrpcApiVersionStats {
+    /// API version.
+    pub version: ApiVersion,
+    /// Requests received.
+    pub requests: u64,
+    /// Requests which completed with an error.
+    pub failed: u64,
+    /// Requests being served.
+    pub in_flight: u64,
+}
+
+static V0_COUNTERS: ApiVersionCounters = ApiVersionCounters::new();
+static V1_COUNTERS: ApiVersionCounters = ApiVersionCounters::new();
+static V2ALPHA_COUNTERS: ApiVersionCounters = ApiVersionCounters::new();
+
+impl ApiVersionCounters {
+    const fn new() -> Self {
+        Self {
+            requests: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            in_flight: AtomicU64::new(0),
+        }
+    }
+
+    fn get(version: &ApiVersion) -> &'static Self {
+        match version {
+            ApiVersion::V0 => &V0_COUNTERS,
+            ApiVersion::V1 => &V1_COUNTERS,
+            ApiVersion::V2Alpha => &V2ALPHA_COUNTERS,
+        }
+    }
+}
+
+/// Request counted as in flight until dropped, which also covers the
+/// requests cancelled by their clients.
+struct InFlight(&'static ApiVersionCounters);
+
+impl InFlight {
+    fn new(counters: &'static ApiVersionCounters) -> Self {
+        counters.in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(counters)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Per-version counters of the gRPC requests.
+pub struct GrpcApiMetrics {}
+
+impl GrpcApiMetrics {
+    /// Returns the counters of all API versions, served or not.
+    pub fn stats() -> Vec<GrpcApiVersionStats> {
+        [ApiVersion::V0, ApiVersion::V1, ApiVersion::V2Alpha]
+            .into_iter()
+            .map(|version| {
+                let c = ApiVersionCounters::get(&version);
+                GrpcApiVersionStats {
+                    version,
+                    requests: c.requests.load(Ordering::Relaxed),
+                    failed: c.failed.load(Ordering::Relaxed),
+                    in_flight: c.in_flight.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the API version of the method with the given path, or `None` if
+/// it is not a method of the mayastor API.
+fn method_version(path: &str) -> Option<ApiVersion> {
+    let (service, _) = path.trim_start_matches('/').split_once('/')?;
+    let package = service.strip_prefix("mayastor.")?;
+
+    if package.starts_with("v1.") {
+        Some(ApiVersion::V1)
+    } else if package.starts_with("v2alpha.") {
+        Some(ApiVersion::V2Alpha)
+    } else {
+        Some(ApiVersion::V0)
+    }
+}
+
+/// Tower layer which counts the gRPC requests per API version.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcApiMetricsLayer {}
+
+impl<S> Layer<S> for GrpcApiMetricsLayer {
+    type Service = GrpcApiMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcApiMetricsService {
+            inner,
+        }
+    }
+}
+
+/// Service which counts the gRPC requests per API version.
+#[derive(Debug, Clone)]
+pub struct GrpcApiMetricsService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for GrpcApiMetricsService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let Some(version) = method_version(req.uri().path()) else {
+            return Box::pin(self.inner.call(req));
+        };
+
+        let counters = ApiVersionCounters::get(&version);
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        let in_flight = InFlight::new(counters);
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = fut.await;
+            drop(in_flight);
+
+            // Errors are returned in the headers of trailers-only responses.
+            let failed = match &response {
+                Ok(response) => Status::from_header_map(response.headers())
+                    .map_or(false, |s| s.code() != Code::Ok),
+                Err(_) => true,
+            };
+            if failed {
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+            }
+
+            response
+        })
+    }
+}
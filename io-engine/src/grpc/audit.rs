@@ -0,0 +1,459 @@
+//!
+//! Audit log of the gRPC requests which create, modify or destroy resources.
+//! Each request is recorded with its method, a hash of its arguments, the
+//! address of the client, its result and its duration, so that the node can
+//! tell who did what to its resources. Read-only, health and reflection
+//! requests are not recorded.
+//!
+//! The log keeps the most recent entries up to its capacity, and is
+//! persisted to a file with one JSON entry per line. Entries are appended to
+//! the file in batches by a writer thread, off the gRPC runtime, and the
+//! file is rewritten with the retained entries once it holds twice the
+//! capacity, so that it stays bounded. Entries of the file are loaded back
+//! on startup.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    net::SocketAddr,
+    sync::{mpsc, Arc},
+    task::{Context, Poll},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use futures::future::BoxFuture;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tonic::{
+    body::BoxBody,
+    transport::server::{TcpConnectInfo, TlsConnectInfo},
+    Code,
+    Status,
+};
+use tower::{Layer, Service};
+
+use super::auth::{GrpcAuth, GrpcAuthScope, GrpcMethodAuth};
+
+/// Configuration of the gRPC audit log.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcAuditConfig {
+    /// File the log is persisted to.
+    pub path: Option<String>,
+    /// Maximum number of entries kept.
+    pub capacity: usize,
+}
+
+/// Entry of the gRPC audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcAuditEntry {
+    /// Time the request was received, in milliseconds since the epoch.
+    pub time_ms: u64,
+    /// Full path of the method, e.g. `/mayastor.v1.ReplicaRpc/DestroyReplica`.
+    pub method: String,
+    /// SHA-256 of the arguments of the request, if known.
+    pub args_hash: Option<String>,
+    /// Address of the client.
+    pub peer: Option<String>,
+    /// gRPC status code of the result.
+    pub code: i32,
+    /// Error message of the result, empty on success.
+    pub message: String,
+    /// Duration of the request, in microseconds.
+    pub duration_us: u64,
+}
+
+impl GrpcAuditEntry {
+    /// Checks if the request failed.
+    pub fn failed(&self) -> bool {
+        self.code != Code::Ok as i32
+    }
+}
+
+/// Filter of the entries of the gRPC audit log.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcAuditFilter {
+    /// Only entries of methods containing this string.
+    pub method: Option<String>,
+    /// Only entries of clients whose address contains this string.
+    pub peer: Option<String>,
+    /// Only entries recorded at or after this time, in milliseconds since
+    /// the epoch.
+    pub since_ms: Option<u64>,
+    /// Only entries of failed requests.
+    pub failed_only: bool,
+    /// Maximum number of entries returned, the most recent ones. Zero means
+    /// no limit.
+    pub limit: usize,
+}
+
+impl GrpcAuditFilter {
+    fn matches(&self, entry: &GrpcAuditEntry) -> bool {
+        self.method
+            .as_ref()
+            .map_or(true, |m| entry.method.contains(m.as_str()))
+            && self.peer.as_ref().map_or(true, |p| {
+                entry
+                    .peer
+                    .as_ref()
+                    .map_or(false, |e| e.contains(p.as_str()))
+            })
+            && self.since_ms.map_or(true, |t| entry.time_ms >= t)
+            && (!self.failed_only || entry.failed())
+    }
+}
+
+/// Audit log state.
+#[derive(Debug)]
+struct AuditLog {
+    capacity: usize,
+    entries: VecDeque<GrpcAuditEntry>,
+    /// Channel to the writer of the log file, if the log is persisted.
+    writer: Option<mpsc::Sender<WriterMsg>>,
+}
+
+impl AuditLog {
+    fn record(&mut self, entry: GrpcAuditEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry.clone());
+
+        if let Some(writer) = &self.writer {
+            if writer.send(WriterMsg::Record(entry)).is_err() {
+                warn!("gRPC audit log writer has stopped");
+                self.writer = None;
+            }
+        }
+    }
+}
+
+/// Message to the writer of the log file.
+#[derive(Debug)]
+enum WriterMsg {
+    /// Entry to append to the file.
+    Record(GrpcAuditEntry),
+    /// Request to be notified once the previous entries are written.
+    Flush(mpsc::Sender<()>),
+}
+
+/// Writer of the log file. It runs on its own thread, so that the requests
+/// never wait for the file: the entries recorded while a batch is written
+/// are written together with the next one, with a single sync per batch.
+struct AuditWriter {
+    capacity: usize,
+    path: String,
+    /// Most recent entries, to rewrite the file with.
+    entries: VecDeque<GrpcAuditEntry>,
+    /// Number of entries in the file.
+    file_entries: usize,
+}
+
+impl AuditWriter {
+    /// Loads the most recent entries of the log file, skipping the entries
+    /// which cannot be parsed.
+    fn load(&mut self) {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return
+            }
+            Err(error) => {
+                warn!("Failed to read gRPC audit log '{}': {error}", self.path);
+                return;
+            }
+        };
+
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            self.file_entries += 1;
+            if let Ok(entry) = serde_json::from_str(&line) {
+                self.push(entry);
+            }
+        }
+    }
+
+    fn push(&mut self, entry: GrpcAuditEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Writes the batches of entries received on the channel until it is
+    /// closed.
+    fn run(mut self, receiver: mpsc::Receiver<WriterMsg>) {
+        while let Ok(msg) = receiver.recv() {
+            let mut batch = Vec::new();
+            let mut flushes = Vec::new();
+            for msg in std::iter::once(msg).chain(receiver.try_iter()) {
+                match msg {
+                    WriterMsg::Record(entry) => batch.push(entry),
+                    WriterMsg::Flush(sender) => flushes.push(sender),
+                }
+            }
+
+            if !batch.is_empty() {
+                if let Err(error) = self.write(batch) {
+                    warn!(
+                        "Failed to write gRPC audit log '{}': {error}",
+                        self.path
+                    );
+                }
+            }
+
+            for sender in flushes {
+                sender.send(()).ok();
+            }
+        }
+    }
+
+    /// Appends the given entries to the log file, or rewrites the file with
+    /// the retained entries once it holds twice the capacity.
+    fn write(&mut self, batch: Vec<GrpcAuditEntry>) -> std::io::Result<()> {
+        let appended = batch.len();
+        let lines = batch
+            .iter()
+            .map(|e| serde_json::to_string(e).map(|l| l + "\n"))
+            .collect::<Result<String, _>>()?;
+        batch.into_iter().for_each(|e| self.push(e));
+
+        if self.file_entries + appended >= 2 * self.capacity {
+            let tmp = format!("{}.tmp", self.path);
+            let mut file = File::create(&tmp)?;
+            for entry in &self.entries {
+                writeln!(file, "{}", serde_json::to_string(entry)?)?;
+            }
+            file.sync_all()?;
+            fs::rename(&tmp, &self.path)?;
+            self.file_entries = self.entries.len();
+        } else {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            file.write_all(lines.as_bytes())?;
+            file.sync_data()?;
+            self.file_entries += appended;
+        }
+        Ok(())
+    }
+}
+
+/// Audit log, if enabled.
+static GRPC_AUDIT_LOG: OnceCell<parking_lot::Mutex<AuditLog>> = OnceCell::new();
+
+/// Audit log of the gRPC requests.
+pub struct GrpcAudit {}
+
+impl GrpcAudit {
+    /// Enables the audit log. Only the first call has an effect.
+    pub fn configure(config: GrpcAuditConfig) {
+        if config.capacity == 0 {
+            return;
+        }
+
+        info!("gRPC audit log: {config:?}");
+
+        let mut log = AuditLog {
+            capacity: config.capacity,
+            entries: VecDeque::with_capacity(config.capacity),
+            writer: None,
+        };
+
+        if let Some(path) = config.path {
+            let mut writer = AuditWriter {
+                capacity: config.capacity,
+                path,
+                entries: VecDeque::with_capacity(config.capacity),
+                file_entries: 0,
+            };
+            writer.load();
+            log.entries = writer.entries.clone();
+
+            let (sender, receiver) = mpsc::channel();
+            match std::thread::Builder::new()
+                .name("audit_writer".into())
+                .spawn(move || writer.run(receiver))
+            {
+                Ok(_) => log.writer = Some(sender),
+                Err(error) => {
+                    error!("Failed to start gRPC audit log writer: {error}")
+                }
+            }
+        }
+
+        if GRPC_AUDIT_LOG.set(parking_lot::Mutex::new(log)).is_err() {
+            warn!("gRPC audit log has already been configured");
+        }
+    }
+
+    /// Checks if the audit log is enabled.
+    pub fn enabled() -> bool {
+        GRPC_AUDIT_LOG.get().is_some()
+    }
+
+    /// Checks if the requests of the given method are recorded. Unknown
+    /// methods are recorded, as they may modify resources.
+    pub fn is_audited(path: &str) -> bool {
+        !matches!(
+            GrpcAuth::method_auth(path),
+            GrpcMethodAuth::Public
+                | GrpcMethodAuth::Scope(GrpcAuthScope::ReadOnly)
+        )
+    }
+
+    /// Returns the entries matching the given filter, oldest first.
+    pub fn entries(filter: &GrpcAuditFilter) -> Vec<GrpcAuditEntry> {
+        let Some(log) = GRPC_AUDIT_LOG.get() else {
+            return Vec::new();
+        };
+
+        let log = log.lock();
+        let mut entries = log
+            .entries
+            .iter()
+            .rev()
+            .filter(|e| filter.matches(e))
+            .take(if filter.limit == 0 {
+                usize::MAX
+            } else {
+                filter.limit
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        entries.reverse();
+        entries
+    }
+
+    /// Waits for the entries recorded so far to be written to the log file.
+    /// This blocks the calling thread.
+    pub fn flush() {
+        let Some(writer) = GRPC_AUDIT_LOG
+            .get()
+            .and_then(|log| log.lock().writer.clone())
+        else {
+            return;
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        if writer.send(WriterMsg::Flush(sender)).is_ok() {
+            receiver.recv().ok();
+        }
+    }
+
+    fn record(entry: GrpcAuditEntry) {
+        if let Some(log) = GRPC_AUDIT_LOG.get() {
+            log.lock().record(entry);
+        }
+    }
+}
+
+/// Slot for the hash of the arguments of an audited request, passed to the
+/// method handler in the request extensions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GrpcAuditArgs(Arc<parking_lot::Mutex<Option<String>>>);
+
+impl GrpcAuditArgs {
+    /// Records the hash of the given arguments.
+    pub(crate) fn set(&self, args: &str) {
+        let hash = Sha256::digest(args.as_bytes());
+        *self.0.lock() = Some(hex::encode(hash));
+    }
+
+    fn take(&self) -> Option<String> {
+        self.0.lock().take()
+    }
+}
+
+/// Returns the address of the client which sent the given request.
+fn peer_addr<B>(req: &http::Request<B>) -> Option<SocketAddr> {
+    let ext = req.extensions();
+    ext.get::<TcpConnectInfo>()
+        .or_else(|| {
+            ext.get::<TlsConnectInfo<TcpConnectInfo>>()
+                .map(|i| i.get_ref())
+        })
+        .and_then(|i| i.remote_addr())
+}
+
+/// Tower layer which records the gRPC requests in the audit log.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcAuditLayer {}
+
+impl<S> Layer<S> for GrpcAuditLayer {
+    type Service = GrpcAuditService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcAuditService {
+            inner,
+        }
+    }
+}
+
+/// Service which records the gRPC requests in the audit log.
+#[derive(Debug, Clone)]
+pub struct GrpcAuditService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for GrpcAuditService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if !GrpcAudit::enabled() || !GrpcAudit::is_audited(req.uri().path()) {
+            return Box::pin(self.inner.call(req));
+        }
+
+        let method = req.uri().path().to_string();
+        let peer = peer_addr(&req).map(|a| a.to_string());
+        let args = GrpcAuditArgs::default();
+        req.extensions_mut().insert(args.clone());
+
+        let time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let started = Instant::now();
+        let fut = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = fut.await;
+
+            // Errors are returned in the headers of trailers-only responses,
+            // a response without a status in its headers is a success.
+            let (code, message) = match &response {
+                Ok(response) => Status::from_header_map(response.headers())
+                    .map_or((Code::Ok, String::new()), |s| {
+                        (s.code(), s.message().to_string())
+                    }),
+                Err(_) => (Code::Unknown, "transport error".to_string()),
+            };
+
+            GrpcAudit::record(GrpcAuditEntry {
+                time_ms,
+                method,
+                args_hash: args.take(),
+                peer,
+                code: code as i32,
+                message,
+                duration_us: started.elapsed().as_micros() as u64,
+            });
+
+            response
+        })
+    }
+}
@@ -0,0 +1,482 @@
+//!
+//! Token based authorization of gRPC methods. When enabled, every method
+//! requires a bearer token in the `authorization` metadata, and the scope of
+//! the token must cover the method: read-only tokens allow methods which only
+//! query state, provisioning tokens also allow methods which create or
+//! modify resources, and destructive tokens allow all methods. The scope of
+//! each method is listed explicitly, and methods which are not listed are
+//! denied. The health and reflection services are always allowed.
+//!
+//! Tokens are read from a file with one `<scope>:<token>` entry per line,
+//! where scope is one of `read-only`, `provisioning` or `destructive`.
+//! Empty lines and lines starting with `#` are ignored.
+
+use std::{
+    fs,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use once_cell::sync::OnceCell;
+use snafu::{ResultExt, Snafu};
+use tonic::{body::BoxBody, Status};
+use tower::{Layer, Service};
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+pub enum GrpcAuthError {
+    #[snafu(display(
+        "Failed to read gRPC tokens from '{}': {}",
+        path,
+        source
+    ))]
+    ReadTokens {
+        source: std::io::Error,
+        path: String,
+    },
+    #[snafu(display("Invalid gRPC token entry on line {}: {}", line, reason))]
+    InvalidEntry { line: usize, reason: String },
+}
+
+/// Scope of a gRPC authorization token. Each scope includes the previous
+/// ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GrpcAuthScope {
+    /// Methods which only query state.
+    ReadOnly,
+    /// Methods which create or modify resources.
+    Provisioning,
+    /// Methods which destroy resources or disrupt I/O.
+    Destructive,
+}
+
+impl FromStr for GrpcAuthScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read-only" => Ok(Self::ReadOnly),
+            "provisioning" => Ok(Self::Provisioning),
+            "destructive" => Ok(Self::Destructive),
+            _ => Err(format!("unknown scope '{s}'")),
+        }
+    }
+}
+
+use GrpcAuthScope::{Destructive, Provisioning, ReadOnly};
+
+/// Scopes required by the methods of each service. Every method served must
+/// be listed: unknown methods are rejected. SPDK json-rpc calls are
+/// root-equivalent.
+const METHOD_SCOPES: &[(&str, &[(&str, GrpcAuthScope)])] = &[
+    (
+        "mayastor.v1.BdevRpc",
+        &[
+            ("List", ReadOnly),
+            ("Create", Provisioning),
+            ("Destroy", Destructive),
+            ("Share", Provisioning),
+            ("Unshare", Destructive),
+            ("NormalizeUri", ReadOnly),
+        ],
+    ),
+    (
+        "mayastor.v1.JsonRpc",
+        &[("JsonRpcCall", Destructive), ("JsonRpcBatch", Destructive)],
+    ),
+    (
+        "mayastor.v1.PoolRpc",
+        &[
+            ("CreatePool", Provisioning),
+            ("DestroyPool", Destructive),
+            ("ExportPool", Destructive),
+            ("SetPoolRebuildFairness", Provisioning),
+            ("ImportPool", Provisioning),
+            ("ListPools", ReadOnly),
+            // May repair the pool metadata.
+            ("CheckPool", Destructive),
+        ],
+    ),
+    (
+        "mayastor.v1.ReplicaRpc",
+        &[
+            ("CreateReplica", Provisioning),
+            ("DestroyReplica", Destructive),
+            ("ListReplicas", ReadOnly),
+            ("ShareReplica", Provisioning),
+            ("ValidateShareReplica", ReadOnly),
+            ("UnshareReplica", Destructive),
+            ("FreezeReplica", Destructive),
+            ("ThawReplica", Provisioning),
+            ("SetReplicaState", Destructive),
+            ("SetReplicaOwner", Provisioning),
+            ("RestoreReplica", Provisioning),
+            ("WatchReplicaUsage", ReadOnly),
+            ("VerifyReplica", Provisioning),
+        ],
+    ),
+    (
+        "mayastor.v1.TestRpc",
+        &[
+            ("WipeReplica", Destructive),
+            ("RunSelfTest", Destructive),
+            ("AddFaultInjection", Destructive),
+            ("RemoveFaultInjection", Destructive),
+            ("ListFaultInjections", ReadOnly),
+        ],
+    ),
+    (
+        "mayastor.v1.SnapshotRpc",
+        &[
+            ("CreateNexusSnapshot", Provisioning),
+            ("CreateReplicaSnapshot", Provisioning),
+            ("ListSnapshot", ReadOnly),
+            ("StreamSnapshots", ReadOnly),
+            ("DestroySnapshot", Destructive),
+            ("CreateSnapshotClone", Provisioning),
+            ("FlattenClone", Destructive),
+            ("ListSnapshotClone", ReadOnly),
+        ],
+    ),
+    (
+        "mayastor.v1.StatsRpc",
+        &[
+            ("GetStats", ReadOnly),
+            ("SetIoHistogram", Provisioning),
+            ("EnableBdevHistogram", Provisioning),
+            ("GetBdevHistogram", ReadOnly),
+            ("GetGrpcRateLimitStats", ReadOnly),
+            ("GetGrpcApiStats", ReadOnly),
+        ],
+    ),
+    (
+        "mayastor.v1.HostRpc",
+        &[
+            ("GetMayastorInfo", ReadOnly),
+            ("ListBlockDevices", ReadOnly),
+            ("GetMayastorResourceUsage", ReadOnly),
+            ("ListNvmeControllers", ReadOnly),
+            ("StatNvmeController", ReadOnly),
+            ("GetDiskHealth", ReadOnly),
+            ("GetPoolScrubStatus", ReadOnly),
+            ("GetOptions", ReadOnly),
+            ("SetOptions", Destructive),
+            ("GetAuditLog", ReadOnly),
+            ("GetCapabilities", ReadOnly),
+            ("GetPersistentStoreHealth", ReadOnly),
+            ("GetHostIdentity", ReadOnly),
+            ("SetHostIdentity", Destructive),
+            ("ListNvmfTransports", ReadOnly),
+            ("CreateNvmfTransport", Provisioning),
+            ("RebalanceConnections", Destructive),
+            ("ResolveDevice", ReadOnly),
+        ],
+    ),
+    (
+        "mayastor.v1.NexusRpc",
+        &[
+            ("CreateNexus", Provisioning),
+            ("RecreateNexusFromStore", Destructive),
+            ("ValidateNexusConfig", ReadOnly),
+            ("DestroyNexus", Destructive),
+            ("RestoreNexus", Provisioning),
+            ("ShutdownNexus", Destructive),
+            ("ListNexus", ReadOnly),
+            ("AddChildNexus", Provisioning),
+            ("RemoveChildNexus", Destructive),
+            ("FaultNexusChild", Destructive),
+            ("PublishNexus", Provisioning),
+            ("UnpublishNexus", Destructive),
+            ("GetNvmeAnaState", ReadOnly),
+            ("SetNvmeAnaState", Destructive),
+            ("SetNvmeAnaGroupState", Destructive),
+            ("ChildOperation", Destructive),
+            ("StartRebuild", Provisioning),
+            ("StopRebuild", Destructive),
+            ("PauseRebuild", Destructive),
+            ("ResumeRebuild", Provisioning),
+            ("GetRebuildState", ReadOnly),
+            ("GetRebuildStats", ReadOnly),
+            ("GetRebuildProgress", ReadOnly),
+            ("GetRebuildHistory", ReadOnly),
+            ("ListPendingRebuilds", ReadOnly),
+            ("FreezeNexus", Destructive),
+            ("ThawNexus", Provisioning),
+            ("FlushNexus", Provisioning),
+            ("AttachReadCache", Provisioning),
+            ("DetachReadCache", Destructive),
+            ("AttachIoLogJournal", Provisioning),
+            ("DetachIoLogJournal", Destructive),
+            ("SetNexusReadPolicy", Provisioning),
+            ("SetNexusMaxIoSize", Provisioning),
+            ("UpdateNexusListener", Destructive),
+            ("ListRebuildHistory", ReadOnly),
+        ],
+    ),
+    (
+        "mayastor.v1.ConsistencyGroupRpc",
+        &[
+            ("CreateConsistencyGroup", Provisioning),
+            ("DestroyConsistencyGroup", Destructive),
+            ("ListConsistencyGroups", ReadOnly),
+            ("FreezeConsistencyGroup", Destructive),
+            ("ThawConsistencyGroup", Provisioning),
+            ("CreateConsistencyGroupSnapshot", Provisioning),
+        ],
+    ),
+    (
+        "mayastor.Mayastor",
+        &[
+            ("CreatePool", Provisioning),
+            ("DestroyPool", Destructive),
+            ("ListPools", ReadOnly),
+            ("CreateReplica", Provisioning),
+            ("CreateReplicaV2", Provisioning),
+            ("DestroyReplica", Destructive),
+            ("ListReplicas", ReadOnly),
+            ("ListReplicasV2", ReadOnly),
+            ("StatReplicas", ReadOnly),
+            // Also unshares the replica.
+            ("ShareReplica", Destructive),
+            ("CreateNexus", Provisioning),
+            ("CreateNexusV2", Provisioning),
+            ("DestroyNexus", Destructive),
+            ("ShutdownNexus", Destructive),
+            ("ListNexus", ReadOnly),
+            ("ListNexusV2", ReadOnly),
+            ("AddChildNexus", Provisioning),
+            ("RemoveChildNexus", Destructive),
+            ("FaultNexusChild", Destructive),
+            ("PublishNexus", Provisioning),
+            ("UnpublishNexus", Destructive),
+            ("GetNvmeAnaState", ReadOnly),
+            ("SetNvmeAnaState", Destructive),
+            ("ChildOperation", Destructive),
+            ("StartRebuild", Provisioning),
+            ("StopRebuild", Destructive),
+            ("PauseRebuild", Destructive),
+            ("ResumeRebuild", Provisioning),
+            ("GetRebuildState", ReadOnly),
+            ("GetRebuildStats", ReadOnly),
+            ("GetRebuildProgress", ReadOnly),
+            ("CreateSnapshot", Provisioning),
+            ("ListBlockDevices", ReadOnly),
+            ("GetResourceUsage", ReadOnly),
+            ("ListNvmeControllers", ReadOnly),
+            ("StatNvmeControllers", ReadOnly),
+            ("GetMayastorInfo", ReadOnly),
+        ],
+    ),
+    (
+        "mayastor.BdevRpc",
+        &[
+            ("List", ReadOnly),
+            ("Create", Provisioning),
+            ("Destroy", Destructive),
+            ("Share", Provisioning),
+            ("Unshare", Destructive),
+        ],
+    ),
+    ("mayastor.JsonRpc", &[("JsonRpcCall", Destructive)]),
+];
+
+/// Services which do not require authorization.
+const UNAUTHENTICATED_SERVICES: &[&str] = &[
+    "grpc.health.v1.Health",
+    "grpc.reflection.v1alpha.ServerReflection",
+];
+
+/// Authorization required by a gRPC method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrpcMethodAuth {
+    /// The method does not require authorization.
+    Public,
+    /// The method requires a token of at least this scope.
+    Scope(GrpcAuthScope),
+    /// The method is not known, and is rejected when authorization is
+    /// enabled.
+    Unknown,
+}
+
+impl GrpcMethodAuth {
+    /// Checks if the method only queries state.
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, Self::Scope(ReadOnly))
+    }
+}
+
+/// Compares two tokens in time independent of their contents.
+fn token_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Authorization tokens, if authorization is enabled.
+static GRPC_AUTH_TOKENS: OnceCell<Vec<(GrpcAuthScope, String)>> =
+    OnceCell::new();
+
+/// Token based authorization of gRPC methods.
+pub struct GrpcAuth {}
+
+impl GrpcAuth {
+    /// Enables authorization with the tokens from the given file. Only the
+    /// first call has an effect.
+    pub fn configure(path: &str) -> Result<(), GrpcAuthError> {
+        let data = fs::read_to_string(path).context(ReadTokens {
+            path: path.to_string(),
+        })?;
+
+        let mut tokens = Vec::new();
+        for (n, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |reason: String| GrpcAuthError::InvalidEntry {
+                line: n + 1,
+                reason,
+            };
+
+            let (scope, token) = line
+                .split_once(':')
+                .ok_or_else(|| invalid("expected <scope>:<token>".into()))?;
+            let scope = scope.trim().parse().map_err(invalid)?;
+            let token = token.trim();
+            if token.is_empty() {
+                return Err(invalid("empty token".into()));
+            }
+
+            tokens.push((scope, token.to_string()));
+        }
+
+        info!(
+            "gRPC authorization enabled with {} token(s) from '{path}'",
+            tokens.len()
+        );
+
+        if GRPC_AUTH_TOKENS.set(tokens).is_err() {
+            warn!("gRPC authorization has already been configured");
+        }
+
+        Ok(())
+    }
+
+    /// Returns the authorization required by the method with the given
+    /// path, e.g. `/mayastor.v1.ReplicaRpc/DestroyReplica`.
+    pub fn method_auth(path: &str) -> GrpcMethodAuth {
+        let Some((service, method)) =
+            path.trim_start_matches('/').split_once('/')
+        else {
+            return GrpcMethodAuth::Unknown;
+        };
+
+        if UNAUTHENTICATED_SERVICES.contains(&service) {
+            return GrpcMethodAuth::Public;
+        }
+
+        METHOD_SCOPES
+            .iter()
+            .find(|(s, _)| *s == service)
+            .and_then(|(_, methods)| methods.iter().find(|(m, _)| *m == method))
+            .map_or(GrpcMethodAuth::Unknown, |(_, scope)| {
+                GrpcMethodAuth::Scope(*scope)
+            })
+    }
+
+    /// Checks that the given request metadata authorizes the method with the
+    /// given path.
+    pub fn authorize(
+        path: &str,
+        headers: &http::HeaderMap,
+    ) -> Result<(), Status> {
+        let Some(tokens) = GRPC_AUTH_TOKENS.get() else {
+            return Ok(());
+        };
+        let required = match Self::method_auth(path) {
+            GrpcMethodAuth::Public => return Ok(()),
+            GrpcMethodAuth::Scope(scope) => scope,
+            GrpcMethodAuth::Unknown => {
+                warn!("{path}: denied, unknown method");
+                return Err(Status::permission_denied(format!(
+                    "{path}: unknown method"
+                )));
+            }
+        };
+
+        let token = headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| {
+                Status::unauthenticated(format!("{path}: missing bearer token"))
+            })?;
+
+        let scope = tokens
+            .iter()
+            .filter(|(_, t)| token_eq(t.as_bytes(), token.as_bytes()))
+            .map(|(s, _)| *s)
+            .max()
+            .ok_or_else(|| {
+                Status::unauthenticated(format!("{path}: invalid token"))
+            })?;
+
+        if scope < required {
+            warn!("{path}: denied to a {scope:?} token");
+            return Err(Status::permission_denied(format!(
+                "{path}: requires a {required:?} token"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Tower layer which authorizes the gRPC methods.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcAuthLayer {}
+
+impl<S> Layer<S> for GrpcAuthLayer {
+    type Service = GrpcAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcAuthService {
+            inner,
+        }
+    }
+}
+
+/// Service which rejects the gRPC methods not authorized by the request
+/// metadata.
+#[derive(Debug, Clone)]
+pub struct GrpcAuthService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for GrpcAuthService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        match GrpcAuth::authorize(req.uri().path(), req.headers()) {
+            Ok(()) => Box::pin(self.inner.call(req)),
+            Err(status) => {
+                let response = status.to_http();
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Mapping of the errors to canonical gRPC status codes.
+//!
+//! Most errors of the I/O engine carry an errno, either directly or through
+//! the error of a lower layer. The status code of such an error follows from
+//! its errno, so that a missing resource is reported as `NotFound` by every
+//! service, whatever layer it was found missing in.
+use nix::errno::Errno;
+use tonic::{Code, Status};
+
+use crate::{
+    bdev::nexus::ChildError,
+    core::ToErrno,
+    rebuild::RebuildError,
+    store::store_defs::StoreError,
+};
+
+/// Returns the canonical gRPC status code of an errno.
+pub fn errno_code(errno: Errno) -> Code {
+    match errno {
+        Errno::ENOENT | Errno::ENODEV => Code::NotFound,
+        Errno::EEXIST => Code::AlreadyExists,
+        Errno::ENOSPC | Errno::ENOMEM | Errno::EDQUOT | Errno::EMFILE => {
+            Code::ResourceExhausted
+        }
+        Errno::EBUSY
+        | Errno::EALREADY
+        | Errno::ENOMEDIUM
+        | Errno::ENOTEMPTY => Code::FailedPrecondition,
+        Errno::ETIMEDOUT | Errno::ETIME => Code::DeadlineExceeded,
+        Errno::EINVAL | Errno::EBADF | Errno::ENAMETOOLONG => {
+            Code::InvalidArgument
+        }
+        Errno::ERANGE | Errno::EOVERFLOW => Code::OutOfRange,
+        Errno::ENOTSUP | Errno::ENOSYS => Code::Unimplemented,
+        Errno::EPERM | Errno::EACCES => Code::PermissionDenied,
+        Errno::EAGAIN
+        | Errno::ENXIO
+        | Errno::ENOTCONN
+        | Errno::ECONNREFUSED
+        | Errno::ECONNRESET
+        | Errno::EHOSTUNREACH
+        | Errno::ENETUNREACH
+        | Errno::ESHUTDOWN => Code::Unavailable,
+        Errno::ECANCELED | Errno::EINTR => Code::Cancelled,
+        Errno::EMEDIUMTYPE => Code::Aborted,
+        _ => Code::Internal,
+    }
+}
+
+/// Makes a status with the canonical code of the given errno.
+pub fn errno_status(errno: Errno, message: impl Into<String>) -> Status {
+    Status::new(errno_code(errno), message)
+}
+
+/// Returns the canonical gRPC status code of a child error.
+pub(crate) fn child_error_code(error: &ChildError) -> Code {
+    match error {
+        ChildError::PermanentlyFaulted {}
+        | ChildError::ChildFaulted {}
+        | ChildError::CannotOnlineChild {}
+        | ChildError::ResvType {
+            ..
+        }
+        | ChildError::ResvNoHolder {
+            ..
+        }
+        | ChildError::Holder {
+            ..
+        } => Code::FailedPrecondition,
+        ChildError::ChildBeingDestroyed {} => Code::Aborted,
+        ChildError::ChildInaccessible {} => Code::Unavailable,
+        ChildError::ChildTooSmall {
+            ..
+        } => Code::InvalidArgument,
+        ChildError::HandleDmaMalloc {
+            ..
+        } => Code::ResourceExhausted,
+        ChildError::ChildBdevCreate {
+            source, ..
+        } => Status::from(source.clone()).code(),
+        ChildError::ClaimChild {
+            source,
+        } => errno_code(*source),
+        ChildError::OpenChild {
+            source,
+        }
+        | ChildError::HandleCreate {
+            source,
+        }
+        | ChildError::HandleOpen {
+            source,
+        }
+        | ChildError::ResvRegisterKey {
+            source,
+        }
+        | ChildError::ResvAcquire {
+            source,
+        }
+        | ChildError::ResvRelease {
+            source,
+        }
+        | ChildError::ResvReport {
+            source,
+        }
+        | ChildError::NvmeHostId {
+            source,
+        } => errno_code(source.clone().to_errno()),
+    }
+}
+
+/// Returns the canonical gRPC status code of a rebuild error.
+pub(crate) fn rebuild_error_code(error: &RebuildError) -> Code {
+    match error {
+        RebuildError::JobAlreadyExists {
+            ..
+        } => Code::AlreadyExists,
+        RebuildError::NoCopyBuffer {
+            ..
+        } => Code::ResourceExhausted,
+        RebuildError::InvalidParameters {}
+        | RebuildError::InvalidSegmentSize {
+            ..
+        }
+        | RebuildError::InvalidSegmentTasks {
+            ..
+        } => Code::InvalidArgument,
+        RebuildError::NoBdevHandle {
+            source, ..
+        }
+        | RebuildError::BdevNotFound {
+            source, ..
+        }
+        | RebuildError::IoFailed {
+            source, ..
+        }
+        | RebuildError::ReadIoFailed {
+            source, ..
+        }
+        | RebuildError::WriteIoFailed {
+            source, ..
+        }
+        | RebuildError::VerifyIoFailed {
+            source, ..
+        } => errno_code(source.clone().to_errno()),
+        RebuildError::VerifyCompareFailed {
+            ..
+        } => Code::DataLoss,
+        RebuildError::JobNotFound {
+            ..
+        }
+        | RebuildError::MissingDestination {
+            ..
+        } => Code::NotFound,
+        RebuildError::OpError {
+            ..
+        }
+        | RebuildError::StatePending {
+            ..
+        } => Code::FailedPrecondition,
+        RebuildError::RangeLockFailed {
+            ..
+        }
+        | RebuildError::RangeUnlockFailed {
+            ..
+        }
+        | RebuildError::FrontendGone
+        | RebuildError::BackendGone => Code::Aborted,
+        RebuildError::BdevInvalidUri {
+            source, ..
+        } => Status::from(source.clone()).code(),
+        RebuildError::RebuildTasksChannel {
+            ..
+        } => Code::Internal,
+    }
+}
+
+/// Returns the canonical gRPC status code of a persistent store error.
+pub(crate) fn store_error_code(error: &StoreError) -> Code {
+    match error {
+        StoreError::MissingEntry {
+            ..
+        } => Code::NotFound,
+        StoreError::OpTimeout {} => Code::DeadlineExceeded,
+        StoreError::TlsConfig {
+            ..
+        } => Code::FailedPrecondition,
+        StoreError::KeyString {
+            ..
+        }
+        | StoreError::ValueString {
+            ..
+        }
+        | StoreError::DeserialiseValue {
+            ..
+        }
+        | StoreError::SerialiseValue {
+            ..
+        } => Code::DataLoss,
+        StoreError::Connect {
+            ..
+        }
+        | StoreError::Put {
+            ..
+        }
+        | StoreError::PutWait {
+            ..
+        }
+        | StoreError::Get {
+            ..
+        }
+        | StoreError::GetWait {
+            ..
+        }
+        | StoreError::Delete {
+            ..
+        }
+        | StoreError::DeleteWait {
+            ..
+        }
+        | StoreError::Watch {
+            ..
+        } => Code::Unavailable,
+    }
+}
@@ -0,0 +1,295 @@
+//! Structured details of the gRPC errors.
+//!
+//! The status returned for an I/O engine error carries a
+//! `google.rpc.ErrorInfo` in its details, so that clients can branch on the
+//! reason of the error rather than on its message. The reason is the name of
+//! the error variant in upper snake case, e.g. `REP_CREATE`, and the metadata
+//! name the resource the error is about and whether the request may be
+//! retried as is.
+use std::{collections::HashMap, fmt::Debug};
+
+use prost::Message;
+use tonic::{Code, Status};
+
+use crate::{
+    bdev::nexus::ChildError,
+    bdev_api::BdevError,
+    lvs::Error as LvsError,
+};
+
+/// Domain of the errors of the I/O engine.
+pub const ERROR_DOMAIN: &str = "io-engine.mayastor.openebs.io";
+
+/// Metadata key of the resource the error is about.
+pub const ERROR_RESOURCE_KEY: &str = "resource";
+
+/// Metadata key of the retryability hint.
+pub const ERROR_RETRYABLE_KEY: &str = "retryable";
+
+/// Type URL of `google.rpc.ErrorInfo`.
+const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+
+/// `google.rpc.ErrorInfo` message.
+#[derive(Clone, PartialEq, Message)]
+struct ErrorInfo {
+    #[prost(string, tag = "1")]
+    reason: String,
+    #[prost(string, tag = "2")]
+    domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    metadata: HashMap<String, String>,
+}
+
+/// `google.rpc.Status` message, carried in the `grpc-status-details-bin`
+/// trailer.
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<prost_types::Any>,
+}
+
+/// Structured details of an error, to attach to its status.
+#[derive(Debug, Clone)]
+pub(crate) struct GrpcErrorInfo {
+    reason: String,
+    resource: Option<String>,
+}
+
+impl GrpcErrorInfo {
+    /// Makes the details of the given error, about the given resource.
+    pub(crate) fn new(error: &impl Debug, resource: Option<&str>) -> Self {
+        Self {
+            reason: error_reason(error),
+            resource: resource.map(String::from),
+        }
+    }
+
+    /// Attaches the details to the given status, keeping its code, message
+    /// and metadata.
+    pub(crate) fn attach(self, status: Status) -> Status {
+        let mut metadata = HashMap::new();
+        if let Some(resource) = self.resource {
+            metadata.insert(ERROR_RESOURCE_KEY.to_string(), resource);
+        }
+        metadata.insert(
+            ERROR_RETRYABLE_KEY.to_string(),
+            is_retryable(status.code()).to_string(),
+        );
+
+        let info = ErrorInfo {
+            reason: self.reason,
+            domain: ERROR_DOMAIN.to_string(),
+            metadata,
+        };
+        let details = RpcStatus {
+            code: status.code() as i32,
+            message: status.message().to_string(),
+            details: vec![prost_types::Any {
+                type_url: ERROR_INFO_TYPE_URL.to_string(),
+                value: info.encode_to_vec(),
+            }],
+        };
+
+        Status::with_details_and_metadata(
+            status.code(),
+            status.message(),
+            details.encode_to_vec().into(),
+            status.metadata().clone(),
+        )
+    }
+}
+
+/// Returns the reason of an error: the name of its variant, in upper snake
+/// case.
+fn error_reason(error: &impl Debug) -> String {
+    let name = format!("{error:?}");
+    let mut reason = String::new();
+    for (i, c) in name
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .enumerate()
+    {
+        if c.is_ascii_uppercase() && i > 0 {
+            reason.push('_');
+        }
+        reason.push(c.to_ascii_uppercase());
+    }
+    reason
+}
+
+/// Checks if a request failed with the given code may succeed if retried
+/// as is.
+fn is_retryable(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unavailable
+            | Code::Aborted
+            | Code::DeadlineExceeded
+            | Code::Cancelled
+    )
+}
+
+/// Returns the resource an lvs error is about.
+pub(crate) fn lvs_resource(error: &LvsError) -> Option<&str> {
+    match error {
+        LvsError::Import {
+            name, ..
+        }
+        | LvsError::DiskLocked {
+            name, ..
+        }
+        | LvsError::PoolCreate {
+            name, ..
+        }
+        | LvsError::Export {
+            name, ..
+        }
+        | LvsError::Destroy {
+            name, ..
+        }
+        | LvsError::InvalidBdev {
+            name, ..
+        }
+        | LvsError::RepExists {
+            name, ..
+        }
+        | LvsError::RepCreate {
+            name, ..
+        }
+        | LvsError::Overcommit {
+            name, ..
+        }
+        | LvsError::RepDestroy {
+            name, ..
+        }
+        | LvsError::NotALvol {
+            name, ..
+        }
+        | LvsError::LvolShare {
+            name, ..
+        }
+        | LvsError::UpdateShareProperties {
+            name, ..
+        }
+        | LvsError::LvolUnShare {
+            name, ..
+        }
+        | LvsError::GetProperty {
+            name, ..
+        }
+        | LvsError::SetProperty {
+            name, ..
+        }
+        | LvsError::SyncProperty {
+            name, ..
+        }
+        | LvsError::Property {
+            name, ..
+        }
+        | LvsError::SnapshotLimit {
+            name, ..
+        }
+        | LvsError::CloneDepthLimit {
+            name, ..
+        }
+        | LvsError::FlattenClone {
+            name, ..
+        }
+        | LvsError::FlushFailed {
+            name, ..
+        }
+        | LvsError::SnapshotConfigFailed {
+            name, ..
+        }
+        | LvsError::CloneConfigFailed {
+            name, ..
+        }
+        | LvsError::SetXAttr {
+            name, ..
+        }
+        | LvsError::Freeze {
+            name, ..
+        }
+        | LvsError::Compress {
+            name, ..
+        }
+        | LvsError::ReplicaState {
+            name, ..
+        }
+        | LvsError::ReplicaOwner {
+            name, ..
+        }
+        | LvsError::CreateRollback {
+            name, ..
+        } => Some(name),
+        _ => None,
+    }
+}
+
+/// Returns the resource a bdev error is about.
+pub(crate) fn bdev_resource(error: &BdevError) -> Option<&str> {
+    match error {
+        BdevError::UriParseFailed {
+            uri, ..
+        }
+        | BdevError::InvalidUri {
+            uri, ..
+        }
+        | BdevError::BoolParamParseFailed {
+            uri, ..
+        }
+        | BdevError::IntParamParseFailed {
+            uri, ..
+        }
+        | BdevError::UuidParamParseFailed {
+            uri, ..
+        } => Some(uri),
+        BdevError::BdevNoMatchingUri {
+            name, ..
+        }
+        | BdevError::BdevExists {
+            name, ..
+        }
+        | BdevError::BdevWrongUuid {
+            name, ..
+        }
+        | BdevError::BdevNotFound {
+            name, ..
+        }
+        | BdevError::CreateBdevInvalidParams {
+            name, ..
+        }
+        | BdevError::CreateBdevFailed {
+            name, ..
+        }
+        | BdevError::DestroyBdevFailed {
+            name, ..
+        }
+        | BdevError::CreateBdevFailedStr {
+            name, ..
+        }
+        | BdevError::DestroyBdevFailedStr {
+            name, ..
+        }
+        | BdevError::BdevCommandCanceled {
+            name, ..
+        } => Some(name),
+        BdevError::UriSchemeUnsupported {
+            ..
+        } => None,
+    }
+}
+
+/// Returns the resource a child error is about.
+pub(crate) fn child_resource(error: &ChildError) -> Option<&str> {
+    match error {
+        ChildError::ChildBdevCreate {
+            child, ..
+        } => Some(child),
+        _ => None,
+    }
+}
@@ -11,13 +11,23 @@ pub use server::MayastorGrpcServer;
 use tonic::{Request, Response, Status};
 
 use crate::{
+    bdev::nexus::ChildError,
     bdev_api::BdevError,
-    core::{CoreError, Reactor},
+    core::{CoreError, GenerationError, Reactor, ToErrno, VerboseError},
 };
+pub(crate) use error_codes::{
+    child_error_code,
+    rebuild_error_code,
+    store_error_code,
+};
+pub use error_codes::{errno_code, errno_status};
+use error_details::{bdev_resource, child_resource};
+pub(crate) use error_details::{lvs_resource, GrpcErrorInfo};
 
 impl From<BdevError> for tonic::Status {
     fn from(e: BdevError) -> Self {
-        match e {
+        let info = GrpcErrorInfo::new(&e, bdev_resource(&e));
+        let status = match e {
             BdevError::UriParseFailed {
                 ..
             } => Status::invalid_argument(e.to_string()),
@@ -33,41 +43,144 @@ impl From<BdevError> for tonic::Status {
             BdevError::BoolParamParseFailed {
                 ..
             } => Status::invalid_argument(e.to_string()),
+            BdevError::UuidParamParseFailed {
+                ..
+            } => Status::invalid_argument(e.to_string()),
             BdevError::CreateBdevInvalidParams {
                 source, ..
             } => match source {
-                Errno::EINVAL => Status::invalid_argument(e.to_string()),
-                Errno::ENOENT => Status::not_found(e.to_string()),
-                Errno::EEXIST => Status::already_exists(e.to_string()),
+                Errno::ENOENT | Errno::EEXIST => {
+                    errno_status(source, e.to_string())
+                }
                 _ => Status::invalid_argument(e.to_string()),
             },
+            BdevError::CreateBdevFailed {
+                source, ..
+            }
+            | BdevError::DestroyBdevFailed {
+                source, ..
+            } => errno_status(source, e.to_string()),
+            BdevError::BdevExists {
+                ..
+            } => Status::already_exists(e.to_string()),
+            BdevError::BdevNotFound {
+                ..
+            } => Status::not_found(e.to_string()),
+            BdevError::BdevCommandCanceled {
+                ..
+            } => Status::cancelled(e.to_string()),
             e => Status::internal(e.to_string()),
-        }
+        };
+        info.attach(status)
+    }
+}
+
+impl From<ChildError> for tonic::Status {
+    fn from(e: ChildError) -> Self {
+        let info = GrpcErrorInfo::new(&e, child_resource(&e));
+        let status = match e {
+            ChildError::ChildBdevCreate {
+                source, ..
+            } => return source.into(),
+            _ => Status::new(child_error_code(&e), e.verbose()),
+        };
+        info.attach(status)
+    }
+}
+
+impl From<GenerationError> for tonic::Status {
+    fn from(e: GenerationError) -> Self {
+        Status::aborted(e.to_string())
     }
 }
 
 impl From<CoreError> for tonic::Status {
     fn from(e: CoreError) -> Self {
-        Status::internal(e.to_string())
+        errno_status(e.clone().to_errno(), e.to_string())
     }
 }
 
+mod api_metrics;
+mod audit;
+mod auth;
 pub mod controller_grpc;
+mod error_codes;
+pub mod error_details;
+mod rate_limit;
 mod server;
+mod snapshot_client;
+mod tls;
+pub use api_metrics::{
+    GrpcApiMetrics,
+    GrpcApiMetricsLayer,
+    GrpcApiVersionStats,
+};
+pub(crate) use audit::GrpcAuditArgs;
+pub use audit::{
+    GrpcAudit,
+    GrpcAuditConfig,
+    GrpcAuditEntry,
+    GrpcAuditFilter,
+    GrpcAuditLayer,
+};
+pub use auth::{
+    GrpcAuth,
+    GrpcAuthError,
+    GrpcAuthLayer,
+    GrpcAuthScope,
+    GrpcMethodAuth,
+};
+pub use rate_limit::{
+    GrpcRateLimit,
+    GrpcRateLimitConfig,
+    GrpcRateLimitLayer,
+    GrpcRateLimitStats,
+    GrpcTokenBucket,
+};
+pub use snapshot_client::{
+    remote_replica_snapshots,
+    RemoteReplicaSnapshot,
+    RemoteReplicaSnapshotResult,
+    RemoteSnapshotError,
+    RemoteSnapshotOptions,
+    RemoteSnapshotReport,
+};
+pub(crate) use tls::read_pem;
+pub use tls::{GrpcTlsAcceptor, GrpcTlsConfig, GrpcTlsError};
 pub mod v0 {
     pub mod bdev_grpc;
     pub mod json_grpc;
     pub mod mayastor_grpc;
     pub mod nexus_grpc;
+
+    use tonic::{metadata::MetadataValue, Response};
+
+    /// Name of the response metadata key which marks a deprecated v0 method.
+    pub const DEPRECATED_METADATA_KEY: &str = "x-mayastor-deprecated";
+
+    /// Marks the response of a deprecated v0 method, naming the v1 method
+    /// which replaces it.
+    pub(crate) fn deprecated<T>(
+        mut response: Response<T>,
+        replacement: &'static str,
+    ) -> Response<T> {
+        response.metadata_mut().insert(
+            DEPRECATED_METADATA_KEY,
+            MetadataValue::from_static(replacement),
+        );
+        response
+    }
 }
 pub mod v1 {
     pub mod bdev;
+    pub mod consistency_group;
     pub mod host;
     pub mod json;
     pub mod nexus;
     pub mod pool;
     pub mod replica;
     pub mod snapshot;
+    pub mod stats;
     pub mod test;
 }
 
@@ -93,9 +206,14 @@ impl GrpcClientContext {
     where
         T: Debug,
     {
+        let args = format!("{:?}", req.get_ref());
+        if let Some(audit) = req.extensions().get::<GrpcAuditArgs>() {
+            audit.set(&args);
+        }
+
         Self {
             timeout: get_request_timeout(req),
-            args: format!("{:?}", req.get_ref()),
+            args,
             id: fid.to_string(),
         }
     }
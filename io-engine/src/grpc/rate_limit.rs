@@ -0,0 +1,285 @@
+//!
+//! Rate limiting of gRPC requests. Requests are limited globally and per
+//! client address with token buckets, and rejected with RESOURCE_EXHAUSTED
+//! once a bucket is empty. Read-only requests, as classified by the gRPC
+//! authorization, may not use the last part of the global bucket, which is
+//! reserved for requests creating or modifying resources, so that a storm of
+//! list calls cannot starve them. The reserve always leaves at least one
+//! token to read-only requests, so that a small limit does not reject them
+//! all. Health and reflection requests are not limited.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use futures::future::BoxFuture;
+use once_cell::sync::{Lazy, OnceCell};
+use tonic::{
+    body::BoxBody,
+    transport::server::{TcpConnectInfo, TlsConnectInfo},
+    Status,
+};
+use tower::{Layer, Service};
+
+use super::auth::{GrpcAuth, GrpcMethodAuth};
+
+/// Part of the global bucket which read-only requests may not use.
+const PRIORITY_RESERVE: f64 = 0.25;
+
+/// Number of tracked clients above which idle clients are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+/// Rate limits of the gRPC server, in requests per second. A limit of 0
+/// disables it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrpcRateLimitConfig {
+    /// Limit of all requests.
+    pub global: u32,
+    /// Limit of the requests of a single client address.
+    pub per_client: u32,
+}
+
+/// Counters of the requests seen by the rate limiter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrpcRateLimitStats {
+    /// Requests let through.
+    pub allowed: u64,
+    /// Requests rejected by the global limit.
+    pub rejected_global: u64,
+    /// Read-only requests rejected to keep the priority reserve.
+    pub rejected_low_priority: u64,
+    /// Requests rejected by the per-client limit.
+    pub rejected_client: u64,
+}
+
+/// Token bucket refilled at a constant rate, holding at most one second
+/// worth of tokens. Part of the bucket may be reserved for priority requests.
+#[derive(Debug)]
+pub struct GrpcTokenBucket {
+    rate: f64,
+    reserve: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl GrpcTokenBucket {
+    /// Makes a full bucket refilled with the given number of tokens per
+    /// second, the given part of which low priority requests may not use.
+    pub fn new(rate: u32, reserve: f64) -> Self {
+        let rate = rate as f64;
+        Self {
+            rate,
+            reserve: (rate * reserve).min(rate - 1.0).max(0.0),
+            tokens: rate,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+    }
+
+    /// Takes a token. A low priority request may not take the reserved
+    /// tokens.
+    pub fn try_take(&mut self, low_priority: bool) -> bool {
+        self.refill();
+        let reserve = if low_priority { self.reserve } else { 0.0 };
+        if self.tokens - 1.0 < reserve {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    fn is_full(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.rate
+    }
+}
+
+/// Rate limiter state.
+#[derive(Debug)]
+struct RateLimiter {
+    config: GrpcRateLimitConfig,
+    global: parking_lot::Mutex<GrpcTokenBucket>,
+    clients: parking_lot::Mutex<HashMap<IpAddr, GrpcTokenBucket>>,
+}
+
+/// Rate limiter, if any limit is enabled.
+static GRPC_RATE_LIMITER: OnceCell<RateLimiter> = OnceCell::new();
+
+static ALLOWED: AtomicU64 = AtomicU64::new(0);
+static REJECTED_GLOBAL: AtomicU64 = AtomicU64::new(0);
+static REJECTED_LOW_PRIORITY: AtomicU64 = AtomicU64::new(0);
+static REJECTED_CLIENT: AtomicU64 = AtomicU64::new(0);
+
+/// Time of the last warning about rejected requests.
+static LAST_WARNING: Lazy<parking_lot::Mutex<Option<Instant>>> =
+    Lazy::new(Default::default);
+
+/// Rate limiting of gRPC requests.
+pub struct GrpcRateLimit {}
+
+impl GrpcRateLimit {
+    /// Enables rate limiting. Only the first call has an effect.
+    pub fn configure(config: GrpcRateLimitConfig) {
+        if config.global == 0 && config.per_client == 0 {
+            return;
+        }
+
+        info!("gRPC rate limits: {config:?}");
+
+        let limiter = RateLimiter {
+            config,
+            global: parking_lot::Mutex::new(GrpcTokenBucket::new(
+                config.global,
+                PRIORITY_RESERVE,
+            )),
+            clients: Default::default(),
+        };
+
+        if GRPC_RATE_LIMITER.set(limiter).is_err() {
+            warn!("gRPC rate limits have already been configured");
+        }
+    }
+
+    /// Returns the counters of the rate limiter.
+    pub fn stats() -> GrpcRateLimitStats {
+        GrpcRateLimitStats {
+            allowed: ALLOWED.load(Ordering::Relaxed),
+            rejected_global: REJECTED_GLOBAL.load(Ordering::Relaxed),
+            rejected_low_priority: REJECTED_LOW_PRIORITY
+                .load(Ordering::Relaxed),
+            rejected_client: REJECTED_CLIENT.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Checks that the given request of the given client is within limits.
+    fn admit(path: &str, client: Option<IpAddr>) -> Result<(), Status> {
+        let Some(limiter) = GRPC_RATE_LIMITER.get() else {
+            return Ok(());
+        };
+        // Health and reflection requests are not limited.
+        let auth = GrpcAuth::method_auth(path);
+        if auth == GrpcMethodAuth::Public {
+            return Ok(());
+        }
+
+        if let (Some(client), true) = (client, limiter.config.per_client > 0) {
+            let mut clients = limiter.clients.lock();
+            if clients.len() >= MAX_TRACKED_CLIENTS {
+                clients.retain(|_, b| !b.is_full());
+            }
+
+            let admitted = clients
+                .entry(client)
+                .or_insert_with(|| {
+                    GrpcTokenBucket::new(limiter.config.per_client, 0.0)
+                })
+                .try_take(false);
+            if !admitted {
+                return Self::reject(
+                    &REJECTED_CLIENT,
+                    format!("{path}: request rate limit of {client} exceeded"),
+                );
+            }
+        }
+
+        if limiter.config.global > 0 {
+            let low_priority = auth.is_read_only();
+
+            if !limiter.global.lock().try_take(low_priority) {
+                return Self::reject(
+                    if low_priority {
+                        &REJECTED_LOW_PRIORITY
+                    } else {
+                        &REJECTED_GLOBAL
+                    },
+                    format!("{path}: request rate limit exceeded"),
+                );
+            }
+        }
+
+        ALLOWED.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn reject(counter: &AtomicU64, msg: String) -> Result<(), Status> {
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        // Warn at most once a second, as rejections come in storms.
+        let mut last = LAST_WARNING.lock();
+        if last.map_or(true, |t| t.elapsed().as_secs() >= 1) {
+            *last = Some(Instant::now());
+            warn!("{msg}, rejected so far: {:?}", Self::stats());
+        }
+
+        Err(Status::resource_exhausted(msg))
+    }
+}
+
+/// Returns the address of the client which sent the given request.
+fn client_addr<B>(req: &http::Request<B>) -> Option<IpAddr> {
+    let ext = req.extensions();
+    ext.get::<TcpConnectInfo>()
+        .or_else(|| {
+            ext.get::<TlsConnectInfo<TcpConnectInfo>>()
+                .map(|i| i.get_ref())
+        })
+        .and_then(|i| i.remote_addr())
+        .map(|a| a.ip())
+}
+
+/// Tower layer which rate limits the gRPC requests.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcRateLimitLayer {}
+
+impl<S> Layer<S> for GrpcRateLimitLayer {
+    type Service = GrpcRateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcRateLimitService {
+            inner,
+        }
+    }
+}
+
+/// Service which rejects the gRPC requests above the rate limits.
+#[derive(Debug, Clone)]
+pub struct GrpcRateLimitService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<http::Request<B>> for GrpcRateLimitService<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        match GrpcRateLimit::admit(req.uri().path(), client_addr(&req)) {
+            Ok(()) => Box::pin(self.inner.call(req)),
+            Err(status) => {
+                let response = status.to_http();
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
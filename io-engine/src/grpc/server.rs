@@ -6,14 +6,22 @@ use super::{
     },
     v1::{
         bdev::BdevService,
+        consistency_group::ConsistencyGroupService,
         host::HostService,
         json::JsonService,
         nexus::NexusService,
         pool::PoolService,
         replica::ReplicaService,
         snapshot::SnapshotService,
+        stats::StatsService,
         test::TestService,
     },
+    GrpcApiMetricsLayer,
+    GrpcAuditLayer,
+    GrpcAuthLayer,
+    GrpcRateLimitLayer,
+    GrpcTlsAcceptor,
+    GrpcTlsConfig,
 };
 
 use mayastor_api::{
@@ -25,15 +33,30 @@ use mayastor_api::{
     v1,
 };
 
-use crate::subsys::registration::registration_grpc::ApiVersion;
-use futures::{select, FutureExt, StreamExt};
+use crate::{
+    core::{runtime, Reactors},
+    subsys::registration::registration_grpc::ApiVersion,
+};
+use futures::{future, select, FutureExt, StreamExt};
 use once_cell::sync::OnceCell;
 use std::{borrow::Cow, time::Duration};
-use tonic::transport::Server;
+use tokio::{
+    net::TcpListener,
+    signal::unix::{signal, Signal, SignalKind},
+};
+use tonic::transport::{server::Router, Server};
+use tonic_health::{
+    proto::health_server::{Health, HealthServer},
+    server::HealthReporter,
+    ServingStatus,
+};
 use tracing::trace;
 
 static MAYASTOR_GRPC_SERVER: OnceCell<MayastorGrpcServer> = OnceCell::new();
 
+/// Reporter of the status served by the standard gRPC health service.
+static HEALTH_REPORTER: OnceCell<HealthReporter> = OnceCell::new();
+
 #[derive(Clone)]
 pub struct MayastorGrpcServer {
     /// Receive channel for messages and termination
@@ -54,9 +77,31 @@ impl MayastorGrpcServer {
 
     /// Terminate the grpc server.
     pub fn fini(&self) {
+        Self::set_serving(false);
         self.fini_chan.close();
     }
 
+    /// Sets the status reported by the gRPC health service. The server
+    /// reports NOT_SERVING until the reactors are running, and again once
+    /// shutdown has started.
+    pub fn set_serving(serving: bool) {
+        let Some(reporter) = HEALTH_REPORTER.get() else {
+            return;
+        };
+
+        let status = if serving {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+
+        info!("gRPC health status: {status:?}");
+        let mut reporter = reporter.clone();
+        runtime::spawn(async move {
+            reporter.set_service_status("", status).await;
+        });
+    }
+
     /// Start the grpc server.
     pub async fn run(
         node_name: &str,
@@ -69,62 +114,60 @@ impl MayastorGrpcServer {
 
         let address = Cow::from(rpc_addr);
 
-        let replica_v1 = ReplicaService::new();
-
-        let enable_v0 = api_versions.contains(&ApiVersion::V0).then_some(true);
-        let enable_v1 = api_versions.contains(&ApiVersion::V1).then_some(true);
         info!(
             "{:?} gRPC server configured at address {}",
             api_versions, endpoint
         );
-        let svc = Server::builder()
-            .add_optional_service(
-                enable_v1
-                    .map(|_| v1::bdev::BdevRpcServer::new(BdevService::new())),
-            )
-            .add_optional_service(enable_v1.map(|_| {
-                v1::json::JsonRpcServer::new(JsonService::new(address.clone()))
-            }))
-            .add_optional_service(
-                enable_v1
-                    .map(|_| v1::pool::PoolRpcServer::new(PoolService::new())),
-            )
-            .add_optional_service(enable_v1.map(|_| {
-                v1::replica::ReplicaRpcServer::new(replica_v1.clone())
-            }))
-            .add_optional_service(enable_v1.map(|_| {
-                v1::test::TestRpcServer::new(TestService::new(replica_v1))
-            }))
-            .add_optional_service(enable_v1.map(|_| {
-                v1::snapshot::SnapshotRpcServer::new(SnapshotService::new())
-            }))
-            .add_optional_service(enable_v1.map(|_| {
-                v1::host::HostRpcServer::new(HostService::new(
-                    node_name,
-                    node_nqn,
-                    endpoint,
-                    api_versions,
-                ))
-            }))
-            .add_optional_service(
-                enable_v1.map(|_| {
-                    v1::nexus::NexusRpcServer::new(NexusService::new())
-                }),
-            )
-            .add_optional_service(enable_v0.map(|_| {
-                MayastorRpcServer::new(MayastorSvc::new(Duration::from_millis(
-                    4,
-                )))
-            }))
-            .add_optional_service(
-                enable_v0.map(|_| {
-                    JsonRpcServer::new(JsonRpcSvc::new(address.clone()))
-                }),
-            )
-            .add_optional_service(
-                enable_v0.map(|_| BdevRpcServer::new(BdevSvc::new())),
-            )
-            .serve(endpoint);
+        for version in api_versions.iter().filter(|v| v.is_experimental()) {
+            warn!(
+                "Serving the experimental {version:?} gRPC API, which may \
+                change incompatibly between releases"
+            );
+        }
+
+        let (mut reporter, health) = tonic_health::server::health_reporter();
+        reporter
+            .set_service_status("", ServingStatus::NotServing)
+            .await;
+        if HEALTH_REPORTER.set(reporter).is_err() {
+            warn!("gRPC health reporter has already been initialised");
+        }
+        // The master reactor polls its futures once initialisation is
+        // complete.
+        Reactors::master().send_future(async {
+            MayastorGrpcServer::set_serving(true);
+        });
+
+        let tls = match GrpcTlsConfig::get()
+            .cloned()
+            .map(GrpcTlsAcceptor::new)
+            .transpose()
+        {
+            Ok(tls) => tls,
+            Err(error) => {
+                error!("Failed to load gRPC server TLS certificates: {error}");
+                return Err(());
+            }
+        };
+
+        // Certificates are reloaded on SIGHUP, for the new connections only:
+        // the services and the established connections are kept.
+        let hangup = match tls {
+            Some(_) => Some(signal(SignalKind::hangup()).map_err(|error| {
+                error!("Failed to install SIGHUP handler: {error}");
+            })?),
+            None => None,
+        };
+
+        let svc = Self::serve(
+            node_name,
+            node_nqn,
+            endpoint,
+            address,
+            api_versions,
+            tls.clone(),
+            health,
+        );
 
         select! {
             result = svc.fuse() => {
@@ -142,7 +185,153 @@ impl MayastorGrpcServer {
             _ = rcv_chan.next().fuse() => {
                 info!("Shutting down grpc server");
                 Ok(())
+            },
+            _ = Self::reload_on_hangup(hangup, tls).fuse() => Ok(()),
+        }
+    }
+
+    /// Reloads the TLS certificates of the server on every SIGHUP, if its
+    /// handler is installed. Never completes.
+    async fn reload_on_hangup(
+        hangup: Option<Signal>,
+        tls: Option<GrpcTlsAcceptor>,
+    ) {
+        if let (Some(mut hangup), Some(tls)) = (hangup, tls) {
+            while hangup.recv().await.is_some() {
+                match tls.reload() {
+                    Ok(()) => {
+                        info!("Reloaded gRPC server TLS certificates");
+                    }
+                    Err(error) => {
+                        error!(
+                            "Failed to reload gRPC server TLS certificates, \
+                            keeping the previous ones: {error}"
+                        );
+                    }
+                }
             }
         }
+        future::pending().await
+    }
+
+    /// Serves all gRPC services until an error occurs.
+    async fn serve<H: Health>(
+        node_name: &str,
+        node_nqn: &Option<String>,
+        endpoint: std::net::SocketAddr,
+        address: Cow<'static, str>,
+        api_versions: Vec<ApiVersion>,
+        tls: Option<GrpcTlsAcceptor>,
+        health: HealthServer<H>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let reflection = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(
+                tonic_health::proto::GRPC_HEALTH_V1_FILE_DESCRIPTOR_SET,
+            )
+            .register_encoded_file_descriptor_set(
+                mayastor_api::v0::FILE_DESCRIPTOR_SET,
+            )
+            .register_encoded_file_descriptor_set(
+                mayastor_api::v1::FILE_DESCRIPTOR_SET,
+            )
+            .build()
+            .map_err(|error| {
+                error!("Failed to build gRPC reflection service: {error}");
+            })
+            .ok();
+
+        let mut router = Server::builder()
+            .layer(GrpcApiMetricsLayer::default())
+            .layer(GrpcAuditLayer::default())
+            .layer(GrpcRateLimitLayer::default())
+            .layer(GrpcAuthLayer::default())
+            .add_service(health)
+            .add_optional_service(reflection);
+
+        for version in [ApiVersion::V0, ApiVersion::V1, ApiVersion::V2Alpha]
+            .iter()
+            .filter(|v| api_versions.contains(v))
+        {
+            router = match version {
+                ApiVersion::V0 => Self::add_v0_services(router, &address),
+                ApiVersion::V1 => Self::add_v1_services(
+                    router,
+                    node_name,
+                    node_nqn,
+                    endpoint,
+                    &address,
+                    &api_versions,
+                ),
+                ApiVersion::V2Alpha => Self::add_v2alpha_services(router),
+            };
+        }
+
+        match tls {
+            Some(tls) => {
+                let listener = TcpListener::bind(endpoint).await?;
+                router.serve_with_incoming(tls.incoming(listener)).await?;
+            }
+            None => router.serve(endpoint).await?,
+        }
+        Ok(())
+    }
+
+    /// Registers the services of the v0 API.
+    fn add_v0_services<L>(
+        router: Router<L>,
+        address: &Cow<'static, str>,
+    ) -> Router<L> {
+        router
+            .add_service(MayastorRpcServer::new(MayastorSvc::new(
+                Duration::from_millis(4),
+            )))
+            .add_service(JsonRpcServer::new(JsonRpcSvc::new(address.clone())))
+            .add_service(BdevRpcServer::new(BdevSvc::new()))
+    }
+
+    /// Registers the services of the v1 API.
+    fn add_v1_services<L>(
+        router: Router<L>,
+        node_name: &str,
+        node_nqn: &Option<String>,
+        endpoint: std::net::SocketAddr,
+        address: &Cow<'static, str>,
+        api_versions: &[ApiVersion],
+    ) -> Router<L> {
+        let replica_v1 = ReplicaService::new();
+
+        router
+            .add_service(v1::bdev::BdevRpcServer::new(BdevService::new()))
+            .add_service(v1::json::JsonRpcServer::new(JsonService::new(
+                address.clone(),
+            )))
+            .add_service(v1::pool::PoolRpcServer::new(PoolService::new()))
+            .add_service(v1::replica::ReplicaRpcServer::new(replica_v1.clone()))
+            .add_service(v1::test::TestRpcServer::new(TestService::new(
+                replica_v1,
+            )))
+            .add_service(v1::snapshot::SnapshotRpcServer::new(
+                SnapshotService::new(),
+            ))
+            .add_service(v1::stats::StatsRpcServer::new(StatsService::new()))
+            .add_service(v1::host::HostRpcServer::new(HostService::new(
+                node_name,
+                node_nqn,
+                endpoint,
+                api_versions.to_vec(),
+            )))
+            .add_service(v1::nexus::NexusRpcServer::new(NexusService::new()))
+            .add_service(v1::consistency_group::ConsistencyGroupRpcServer::new(
+                ConsistencyGroupService::new(),
+            ))
+    }
+
+    /// Registers the services of the experimental v2alpha API. Its services
+    /// may change incompatibly between releases, and are only served when
+    /// the version is explicitly enabled. It has no services yet, and is
+    /// filtered out of the served versions until it has, see
+    /// `ApiVersion::has_services`.
+    fn add_v2alpha_services<L>(router: Router<L>) -> Router<L> {
+        router
     }
 }
@@ -0,0 +1,401 @@
+//!
+//! Client pool used by the io-engine to request the snapshots of replicas
+//! hosted by remote io-engines, as part of a coordinated nexus snapshot.
+//!
+//! The snapshots are requested in parallel, one request per replica. Each
+//! request is bounded by a timeout and retried on transient errors. A retried
+//! request which finds its snapshot already created is considered successful,
+//! as the previous attempt may have completed after it timed out. The outcome
+//! of every replica is reported, so that the caller can deal with a partial
+//! failure.
+//!
+//! gRPC clients need the tokio runtime: the requests are executed there and
+//! their results are sent back to a Mayastor thread.
+
+use std::{collections::HashMap, time::Duration};
+
+use futures::{channel::oneshot, future::join_all};
+use mayastor_api::v1::snapshot::{
+    snapshot_rpc_client::SnapshotRpcClient,
+    CreateReplicaSnapshotRequest,
+    ListSnapshotsRequest,
+    SnapshotInfo,
+};
+use once_cell::sync::Lazy;
+use snafu::Snafu;
+use tonic::{transport::Channel, Code, Status};
+
+use crate::core::{
+    runtime,
+    snapshot::{SnapshotDescriptor, SnapshotParams},
+    Reactor,
+};
+
+/// Default timeout of a single snapshot request.
+const REMOTE_SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default number of times a failed snapshot request is retried.
+const REMOTE_SNAPSHOT_RETRIES: u32 = 2;
+/// Default interval between the attempts of a snapshot request.
+const REMOTE_SNAPSHOT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// The http2 keep alive interval.
+const HTTP_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+/// The http2 keep alive timeout.
+const HTTP_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+pub enum RemoteSnapshotError {
+    #[snafu(display("Invalid io-engine endpoint '{}': {}", endpoint, reason))]
+    InvalidEndpoint { endpoint: String, reason: String },
+    #[snafu(display("Invalid snapshot parameters: {}", reason))]
+    InvalidParams { reason: String },
+    #[snafu(display(
+        "Snapshot request to io-engine '{}' timed out after {:?}",
+        endpoint,
+        timeout
+    ))]
+    Timeout { endpoint: String, timeout: Duration },
+    #[snafu(display(
+        "Snapshot request to io-engine '{}' failed: {}",
+        endpoint,
+        status
+    ))]
+    Request { endpoint: String, status: Status },
+    #[snafu(display("Snapshot requests were cancelled"))]
+    Cancelled {},
+}
+
+impl RemoteSnapshotError {
+    /// Checks if the request which failed with this error may succeed if
+    /// retried.
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::Timeout {
+                ..
+            } => true,
+            Self::Request {
+                status, ..
+            } => matches!(
+                status.code(),
+                Code::Unavailable
+                    | Code::DeadlineExceeded
+                    | Code::Aborted
+                    | Code::ResourceExhausted
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl From<RemoteSnapshotError> for Status {
+    fn from(e: RemoteSnapshotError) -> Self {
+        match e {
+            RemoteSnapshotError::InvalidEndpoint {
+                ..
+            }
+            | RemoteSnapshotError::InvalidParams {
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            RemoteSnapshotError::Timeout {
+                ..
+            } => Status::deadline_exceeded(e.to_string()),
+            RemoteSnapshotError::Request {
+                ref status, ..
+            } => Status::new(status.code(), e.to_string()),
+            RemoteSnapshotError::Cancelled {} => {
+                Status::cancelled(e.to_string())
+            }
+        }
+    }
+}
+
+/// Replica to be snapshotted by a remote io-engine.
+#[derive(Debug, Clone)]
+pub struct RemoteReplicaSnapshot {
+    /// gRPC endpoint of the io-engine hosting the replica.
+    pub endpoint: String,
+    /// UUID of the replica.
+    pub replica_uuid: String,
+    /// UUID of the snapshot to be created.
+    pub snapshot_uuid: String,
+}
+
+/// Timeouts and retries of the remote snapshot requests.
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteSnapshotOptions {
+    /// Timeout of a single attempt.
+    pub timeout: Duration,
+    /// Number of times a request failing with a transient error is retried.
+    pub retries: u32,
+    /// Interval between the attempts of a request.
+    pub retry_interval: Duration,
+}
+
+impl Default for RemoteSnapshotOptions {
+    fn default() -> Self {
+        Self {
+            timeout: REMOTE_SNAPSHOT_TIMEOUT,
+            retries: REMOTE_SNAPSHOT_RETRIES,
+            retry_interval: REMOTE_SNAPSHOT_RETRY_INTERVAL,
+        }
+    }
+}
+
+/// Outcome of the snapshot of a remote replica.
+#[derive(Debug)]
+pub struct RemoteReplicaSnapshotResult {
+    /// The replica which was snapshotted.
+    pub replica: RemoteReplicaSnapshot,
+    /// Number of requests sent.
+    pub attempts: u32,
+    /// The created snapshot, or the error of the last attempt.
+    pub result: Result<SnapshotInfo, RemoteSnapshotError>,
+}
+
+/// Outcome of the snapshots of a set of remote replicas.
+#[derive(Debug, Default)]
+pub struct RemoteSnapshotReport {
+    /// Outcome of each replica, in the order they were requested.
+    pub replicas: Vec<RemoteReplicaSnapshotResult>,
+}
+
+impl RemoteSnapshotReport {
+    /// Returns the replicas which were successfully snapshotted.
+    pub fn succeeded(
+        &self,
+    ) -> impl Iterator<Item = &RemoteReplicaSnapshotResult> {
+        self.replicas.iter().filter(|r| r.result.is_ok())
+    }
+
+    /// Returns the replicas which failed to be snapshotted.
+    pub fn failed(&self) -> impl Iterator<Item = &RemoteReplicaSnapshotResult> {
+        self.replicas.iter().filter(|r| r.result.is_err())
+    }
+
+    /// Checks if all replicas were snapshotted.
+    pub fn is_complete(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+/// Snapshot clients, by io-engine endpoint.
+static SNAPSHOT_CLIENTS: Lazy<
+    parking_lot::Mutex<HashMap<String, SnapshotRpcClient<Channel>>>,
+> = Lazy::new(Default::default);
+
+/// Returns the snapshot client of the given io-engine endpoint, creating it
+/// if needed. Must be called on the tokio runtime.
+fn snapshot_client(
+    endpoint: &str,
+    timeout: Duration,
+) -> Result<SnapshotRpcClient<Channel>, RemoteSnapshotError> {
+    let mut clients = SNAPSHOT_CLIENTS.lock();
+    if let Some(client) = clients.get(endpoint) {
+        return Ok(client.clone());
+    }
+
+    let uri = if endpoint.contains("://") {
+        endpoint.to_string()
+    } else {
+        format!("http://{endpoint}")
+    };
+    let channel = tonic::transport::Endpoint::from_shared(uri)
+        .map_err(|error| RemoteSnapshotError::InvalidEndpoint {
+            endpoint: endpoint.to_string(),
+            reason: error.to_string(),
+        })?
+        .connect_timeout(timeout)
+        .http2_keep_alive_interval(HTTP_KEEP_ALIVE_INTERVAL)
+        .keep_alive_timeout(HTTP_KEEP_ALIVE_TIMEOUT)
+        .connect_lazy();
+
+    let client = SnapshotRpcClient::new(channel);
+    clients.insert(endpoint.to_string(), client.clone());
+    Ok(client)
+}
+
+/// Drops the snapshot client of the given io-engine endpoint, so that the
+/// next request reconnects.
+fn drop_snapshot_client(endpoint: &str) {
+    SNAPSHOT_CLIENTS.lock().remove(endpoint);
+}
+
+/// Sends a single snapshot request.
+async fn request_snapshot(
+    replica: &RemoteReplicaSnapshot,
+    request: CreateReplicaSnapshotRequest,
+    timeout: Duration,
+) -> Result<SnapshotInfo, RemoteSnapshotError> {
+    let mut client = snapshot_client(&replica.endpoint, timeout)?;
+
+    let response =
+        tokio::time::timeout(timeout, client.create_replica_snapshot(request))
+            .await
+            .map_err(|_| RemoteSnapshotError::Timeout {
+                endpoint: replica.endpoint.clone(),
+                timeout,
+            })?
+            .map_err(|status| RemoteSnapshotError::Request {
+                endpoint: replica.endpoint.clone(),
+                status,
+            })?;
+
+    response
+        .into_inner()
+        .snapshot
+        .ok_or_else(|| RemoteSnapshotError::Request {
+            endpoint: replica.endpoint.clone(),
+            status: Status::internal("no snapshot in the response"),
+        })
+}
+
+/// Looks up a snapshot of the replica which was created by a previous
+/// attempt.
+async fn lookup_snapshot(
+    replica: &RemoteReplicaSnapshot,
+    timeout: Duration,
+) -> Option<SnapshotInfo> {
+    let mut client = snapshot_client(&replica.endpoint, timeout).ok()?;
+    let request = ListSnapshotsRequest {
+        source_uuid: Some(replica.replica_uuid.clone()),
+        snapshot_uuid: Some(replica.snapshot_uuid.clone()),
+        query: None,
+    };
+
+    tokio::time::timeout(timeout, client.list_snapshot(request))
+        .await
+        .ok()?
+        .ok()?
+        .into_inner()
+        .snapshots
+        .into_iter()
+        .find(|s| s.snapshot_uuid == replica.snapshot_uuid)
+}
+
+/// Snapshots a remote replica, retrying on transient errors.
+async fn snapshot_replica(
+    params: &SnapshotParams,
+    replica: RemoteReplicaSnapshot,
+    opts: RemoteSnapshotOptions,
+) -> RemoteReplicaSnapshotResult {
+    let request = CreateReplicaSnapshotRequest {
+        replica_uuid: replica.replica_uuid.clone(),
+        snapshot_uuid: replica.snapshot_uuid.clone(),
+        snapshot_name: params.name().unwrap_or_default(),
+        entity_id: params.entity_id().unwrap_or_default(),
+        txn_id: params.txn_id().unwrap_or_default(),
+    };
+
+    let mut attempts = 0;
+    let result = loop {
+        attempts += 1;
+        let result =
+            request_snapshot(&replica, request.clone(), opts.timeout).await;
+
+        let error = match result {
+            Ok(snapshot) => break Ok(snapshot),
+            Err(error) => error,
+        };
+
+        // A previous attempt may have created the snapshot after timing out.
+        if attempts > 1 {
+            if let RemoteSnapshotError::Request {
+                status, ..
+            } = &error
+            {
+                if status.code() == Code::AlreadyExists {
+                    if let Some(snapshot) =
+                        lookup_snapshot(&replica, opts.timeout).await
+                    {
+                        break Ok(snapshot);
+                    }
+                }
+            }
+        }
+
+        if !error.is_transient() || attempts > opts.retries {
+            break Err(error);
+        }
+
+        warn!(
+            endpoint = replica.endpoint,
+            replica_uuid = replica.replica_uuid,
+            attempts,
+            "Remote replica snapshot failed, retrying: {error}"
+        );
+        drop_snapshot_client(&replica.endpoint);
+        tokio::time::sleep(opts.retry_interval).await;
+    };
+
+    match &result {
+        Ok(_) => info!(
+            endpoint = replica.endpoint,
+            replica_uuid = replica.replica_uuid,
+            snapshot_uuid = replica.snapshot_uuid,
+            "Remote replica snapshotted"
+        ),
+        Err(error) => error!(
+            endpoint = replica.endpoint,
+            replica_uuid = replica.replica_uuid,
+            snapshot_uuid = replica.snapshot_uuid,
+            attempts,
+            "Failed to snapshot remote replica: {error}"
+        ),
+    }
+
+    RemoteReplicaSnapshotResult {
+        replica,
+        attempts,
+        result,
+    }
+}
+
+/// Requests the snapshots of the given replicas from the io-engines hosting
+/// them, in parallel. Fails only if the request cannot be made at all: the
+/// outcome of every replica is in the returned report.
+pub async fn remote_replica_snapshots(
+    params: SnapshotParams,
+    replicas: Vec<RemoteReplicaSnapshot>,
+    opts: RemoteSnapshotOptions,
+) -> Result<RemoteSnapshotReport, RemoteSnapshotError> {
+    if params.name().is_none()
+        || params.entity_id().is_none()
+        || params.txn_id().is_none()
+    {
+        return Err(RemoteSnapshotError::InvalidParams {
+            reason: "snapshot name, entity id and transaction id must be \
+                provided"
+                .to_string(),
+        });
+    }
+
+    if replicas.is_empty() {
+        return Ok(RemoteSnapshotReport::default());
+    }
+
+    let (tx, rx) = oneshot::channel::<RemoteSnapshotReport>();
+    runtime::spawn(async move {
+        let replicas = join_all(
+            replicas
+                .into_iter()
+                .map(|replica| snapshot_replica(&params, replica, opts)),
+        )
+        .await;
+
+        // Send the report from a Mayastor thread.
+        let rx = Reactor::spawn_at_primary(async move {
+            if tx
+                .send(RemoteSnapshotReport {
+                    replicas,
+                })
+                .is_err()
+            {
+                error!("Failed to send completion of remote snapshots");
+            }
+        })
+        .expect("Failed to send future to Mayastor thread");
+        let _ = rx.await;
+    });
+
+    rx.await.map_err(|_| RemoteSnapshotError::Cancelled {})
+}
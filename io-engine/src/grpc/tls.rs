@@ -0,0 +1,253 @@
+//!
+//! TLS configuration of the gRPC server. When enabled, the server requires
+//! clients to present a certificate signed by the configured CA. The
+//! certificates are re-read on SIGHUP, so that they can be rotated without
+//! restarting the io-engine: the reloaded certificates are used for the
+//! connections accepted from then on, while the established connections and
+//! their in-flight requests are kept.
+
+use std::{fs, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use once_cell::sync::OnceCell;
+use snafu::{ResultExt, Snafu};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_rustls::{
+    rustls::{
+        server::{AllowAnyAuthenticatedClient, NoClientAuth},
+        Certificate,
+        PrivateKey,
+        RootCertStore,
+        ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+use tokio_stream::wrappers::ReceiverStream;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+pub enum GrpcTlsError {
+    #[snafu(display(
+        "Failed to read TLS {} from '{}': {}",
+        what,
+        path,
+        source
+    ))]
+    ReadPem {
+        source: std::io::Error,
+        what: &'static str,
+        path: String,
+    },
+    #[snafu(display("TLS {} is not PEM encoded", what))]
+    InvalidPem { what: &'static str },
+    #[snafu(display("Invalid TLS {}: {}", what, reason))]
+    InvalidTlsConfig { what: &'static str, reason: String },
+}
+
+/// Number of TLS connections handshaked but not yet picked up by the server.
+const TLS_ACCEPT_BACKLOG: usize = 128;
+
+/// TLS configuration of the gRPC server.
+static GRPC_TLS_CONFIG: OnceCell<GrpcTlsConfig> = OnceCell::new();
+
+/// TLS certificates of the gRPC server. Each value is either a path to a PEM
+/// file, or the PEM data itself.
+#[derive(Debug, Clone)]
+pub struct GrpcTlsConfig {
+    /// Server certificate chain.
+    pub cert: String,
+    /// Server private key.
+    pub key: String,
+    /// CA certificate used to verify client certificates. If not set, client
+    /// certificates are not requested.
+    pub ca: Option<String>,
+}
+
+impl GrpcTlsConfig {
+    /// Enables TLS for the gRPC server. Only the first call has an effect.
+    pub fn configure(self) {
+        info!(
+            "gRPC server TLS enabled, client certificates are {}",
+            if self.ca.is_some() {
+                "required"
+            } else {
+                "not verified"
+            }
+        );
+
+        if GRPC_TLS_CONFIG.set(self).is_err() {
+            warn!("gRPC server TLS has already been configured");
+        }
+    }
+
+    /// Returns the TLS configuration of the gRPC server, if enabled.
+    pub fn get() -> Option<&'static Self> {
+        GRPC_TLS_CONFIG.get()
+    }
+
+    /// Reads the certificates and makes the TLS configuration of the server.
+    pub fn load(&self) -> Result<Arc<ServerConfig>, GrpcTlsError> {
+        let certs = certificates(&read_pem(&self.cert, "certificate")?)?;
+        let key = private_key(&read_pem(&self.key, "key")?)?;
+
+        let verifier = match &self.ca {
+            Some(ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in certificates(&read_pem(ca, "CA")?)? {
+                    roots.add(&cert).map_err(|error| {
+                        GrpcTlsError::InvalidTlsConfig {
+                            what: "CA",
+                            reason: error.to_string(),
+                        }
+                    })?;
+                }
+                AllowAnyAuthenticatedClient::new(roots)
+            }
+            None => NoClientAuth::new(),
+        };
+
+        let mut config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .map_err(|error| GrpcTlsError::InvalidTlsConfig {
+                what: "certificate",
+                reason: error.to_string(),
+            })?;
+        config.alpn_protocols = vec![b"h2".to_vec()];
+
+        Ok(Arc::new(config))
+    }
+}
+
+/// Acceptor of the TLS connections of the gRPC server. Its certificates can
+/// be reloaded while serving, without affecting the established connections.
+#[derive(Clone)]
+pub struct GrpcTlsAcceptor {
+    tls: GrpcTlsConfig,
+    config: Arc<ArcSwap<ServerConfig>>,
+}
+
+impl GrpcTlsAcceptor {
+    /// Makes an acceptor with the certificates of the given configuration.
+    pub fn new(tls: GrpcTlsConfig) -> Result<Self, GrpcTlsError> {
+        let config = Arc::new(ArcSwap::new(tls.load()?));
+        Ok(Self {
+            tls,
+            config,
+        })
+    }
+
+    /// Re-reads the certificates, which are used for the connections
+    /// accepted from then on. The previous certificates are kept if the new
+    /// ones cannot be loaded.
+    pub fn reload(&self) -> Result<(), GrpcTlsError> {
+        self.config.store(self.tls.load()?);
+        Ok(())
+    }
+
+    /// Accepts the connections of the given listener, and returns the stream
+    /// of those which complete the TLS handshake. Connections which fail the
+    /// handshake are dropped without affecting the others.
+    pub fn incoming(
+        &self,
+        listener: TcpListener,
+    ) -> ReceiverStream<Result<TlsStream<TcpStream>, std::io::Error>> {
+        let (sender, receiver) = mpsc::channel(TLS_ACCEPT_BACKLOG);
+        let config = self.config.clone();
+
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(error) => {
+                        // Errors such as running out of file descriptors
+                        // are transient, and must not stop the server.
+                        warn!("Failed to accept gRPC connection: {error}");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                stream.set_nodelay(true).ok();
+
+                let acceptor = TlsAcceptor::from(config.load_full());
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match acceptor.accept(stream).await {
+                        Ok(stream) => {
+                            sender.send(Ok(stream)).await.ok();
+                        }
+                        Err(error) => {
+                            debug!(
+                                "gRPC TLS handshake with {peer} failed: {error}"
+                            );
+                        }
+                    }
+                });
+            }
+        });
+
+        ReceiverStream::new(receiver)
+    }
+}
+
+/// Parses the certificates of the given PEM data.
+fn certificates(pem: &[u8]) -> Result<Vec<Certificate>, GrpcTlsError> {
+    let certs = rustls_pemfile::certs(&mut &pem[..]).map_err(|error| {
+        GrpcTlsError::InvalidTlsConfig {
+            what: "certificate",
+            reason: error.to_string(),
+        }
+    })?;
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// Parses the first private key of the given PEM data.
+fn private_key(pem: &[u8]) -> Result<PrivateKey, GrpcTlsError> {
+    let invalid = |reason: String| GrpcTlsError::InvalidTlsConfig {
+        what: "key",
+        reason,
+    };
+
+    let mut reader = &pem[..];
+    loop {
+        match rustls_pemfile::read_one(&mut reader)
+            .map_err(|error| invalid(error.to_string()))?
+        {
+            Some(
+                rustls_pemfile::Item::RSAKey(key)
+                | rustls_pemfile::Item::PKCS8Key(key)
+                | rustls_pemfile::Item::ECKey(key),
+            ) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => return Err(invalid("no private key found".to_string())),
+        }
+    }
+}
+
+/// Returns the given PEM data, or reads it from the file at the given path.
+pub(crate) fn read_pem(
+    value: &str,
+    what: &'static str,
+) -> Result<Vec<u8>, GrpcTlsError> {
+    let pem = if value.trim_start().starts_with("-----BEGIN") {
+        value.as_bytes().to_vec()
+    } else {
+        fs::read(value).context(ReadPem {
+            what,
+            path: value.to_string(),
+        })?
+    };
+
+    if !String::from_utf8_lossy(&pem).contains("-----BEGIN") {
+        return Err(GrpcTlsError::InvalidPem {
+            what,
+        });
+    }
+
+    Ok(pem)
+}
@@ -1,7 +1,7 @@
 //!
 //! gRPC method to proxy calls to (local) SPDK json-rpc service
 
-use crate::grpc::GrpcResult;
+use crate::grpc::{v1::json::JsonRpcAllowList, GrpcResult};
 use jsonrpc::error::Error;
 use mayastor_api::v0::{
     json_rpc_server::JsonRpc,
@@ -36,6 +36,8 @@ impl JsonRpc for JsonRpcSvc {
     ) -> GrpcResult<JsonRpcReply> {
         let args = request.into_inner();
 
+        JsonRpcAllowList::check(&args.method)?;
+
         let result = self
             .spdk_jsonrpc_call(&args.method, empty_as_none(&args.params))
             .await?;
@@ -26,6 +26,7 @@ use crate::{
         BlockDeviceIoStats,
         CoreError,
         MayastorFeatures,
+        NvmeIdentity,
         Protocol,
         Share,
         ShareProps,
@@ -38,14 +39,20 @@ use crate::{
             list_controllers,
             NvmeControllerInfo,
         },
+        errno_status,
+        lvs_resource,
         rpc_submit,
-        v0::nexus_grpc::{
-            nexus_add_child,
-            nexus_destroy,
-            nexus_lookup,
-            uuid_to_name,
+        v0::{
+            deprecated,
+            nexus_grpc::{
+                nexus_add_child,
+                nexus_destroy,
+                nexus_lookup,
+                uuid_to_name,
+            },
         },
         GrpcClientContext,
+        GrpcErrorInfo,
         GrpcResult,
         Serializer,
     },
@@ -71,7 +78,7 @@ use tonic::{Request, Response, Status};
 #[derive(Debug)]
 struct UnixStream(tokio::net::UnixStream);
 
-use crate::core::{UpdateProps, VerboseError};
+use crate::core::{ToErrno, UpdateProps, VerboseError};
 use ::function_name::named;
 use std::{panic::AssertUnwindSafe, pin::Pin};
 use uuid::Uuid;
@@ -209,6 +216,7 @@ impl TryFrom<CreatePoolRequest> for PoolArgs {
                 name: args.name,
                 disks: args.disks,
                 uuid: None,
+                force: false,
             }),
         }
     }
@@ -216,78 +224,93 @@ impl TryFrom<CreatePoolRequest> for PoolArgs {
 
 impl From<LvsError> for tonic::Status {
     fn from(e: LvsError) -> Self {
-        match e {
+        let info = GrpcErrorInfo::new(&e, lvs_resource(&e));
+        let status = match e {
+            // The status of the original error, with the rollback outcome.
+            LvsError::CreateRollback {
+                source,
+                name,
+                rollback,
+            } => {
+                let status = Status::from(*source);
+                return Status::new(
+                    status.code(),
+                    format!(
+                        "{}; rollback of replica {name} {rollback}",
+                        status.message()
+                    ),
+                );
+            }
+            LvsError::RepDestroy {
+                source: Errno::ENOENT,
+                ..
+            } => {
+                let mut status = Status::not_found(e.to_string());
+                status
+                    .metadata_mut()
+                    .insert("gtm-602", tonic::metadata::MetadataValue::from(0));
+                status
+            }
             LvsError::Import {
                 source, ..
-            } => match source {
-                Errno::EINVAL => Status::invalid_argument(e.to_string()),
-                Errno::EEXIST => Status::already_exists(e.to_string()),
-                _ => Status::invalid_argument(e.to_string()),
-            },
-            LvsError::RepCreate {
+            }
+            | LvsError::RepCreate {
                 source, ..
-            } => {
-                if source == Errno::ENOSPC {
-                    Status::resource_exhausted(e.to_string())
-                } else {
-                    Status::invalid_argument(e.to_string())
-                }
             }
-            LvsError::RepDestroy {
+            | LvsError::RepDestroy {
                 source, ..
-            } => match source {
-                Errno::ENOENT => {
-                    let mut status = Status::not_found(e.to_string());
-                    status.metadata_mut().insert(
-                        "gtm-602",
-                        tonic::metadata::MetadataValue::from(0),
-                    );
-                    status
-                }
-                Errno::ENOMEDIUM => Status::failed_precondition(e.to_string()),
-                Errno::EMEDIUMTYPE => Status::aborted(e.to_string()),
-                _ => Status::internal(e.to_string()),
-            },
+            }
+            | LvsError::FlattenClone {
+                source, ..
+            }
+            | LvsError::Invalid {
+                source, ..
+            }
+            | LvsError::PoolCreate {
+                source, ..
+            }
+            | LvsError::Freeze {
+                source, ..
+            }
+            | LvsError::ReplicaState {
+                source, ..
+            } => errno_status(source, e.to_string()),
             LvsError::RepExists {
                 ..
             } => Status::already_exists(e.to_string()),
+            LvsError::Overcommit {
+                ..
+            } => Status::resource_exhausted(e.to_string()),
+            LvsError::SnapshotLimit {
+                ..
+            } => Status::resource_exhausted(e.to_string()),
+            LvsError::CloneDepthLimit {
+                ..
+            } => Status::out_of_range(e.to_string()),
             LvsError::ReplicaShareProtocol {
                 ..
             } => Status::invalid_argument(e.to_string()),
+            LvsError::ReplicaOwner {
+                ..
+            } => Status::permission_denied(e.to_string()),
             LvsError::Destroy {
                 source, ..
             } => source.into(),
-            LvsError::Invalid {
-                source, ..
-            } => match source {
-                Errno::EINVAL => Status::invalid_argument(e.to_string()),
-                Errno::ENOMEDIUM => Status::failed_precondition(e.to_string()),
-                Errno::ENOENT => Status::not_found(e.to_string()),
-                Errno::EEXIST => Status::already_exists(e.to_string()),
-                _ => Status::invalid_argument(e.to_string()),
-            },
             LvsError::PoolNotFound {
                 ..
             } => Status::not_found(e.to_string()),
-            LvsError::PoolCreate {
-                source, ..
-            } => {
-                if source == Errno::EEXIST {
-                    Status::already_exists(e.to_string())
-                } else if source == Errno::EINVAL {
-                    Status::invalid_argument(e.to_string())
-                } else {
-                    Status::internal(e.to_string())
-                }
-            }
             LvsError::InvalidBdev {
                 source, ..
             } => source.into(),
             LvsError::WipeFailed {
                 source,
             } => source.into(),
-            _ => Status::internal(e.verbose()),
-        }
+            _ => {
+                let msg = e.verbose();
+                errno_status(e.to_errno(), msg)
+            }
+        };
+        info.attach(status)
     }
 }
 
@@ -1070,6 +1093,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::create_nexus"))
     }
 
     #[named]
@@ -1107,6 +1131,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                         },
                         resv_type,
                         preempt_policy,
+                        block_size: 0,
                     },
                     &args.children,
                     nexus_info_key,
@@ -1122,6 +1147,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::create_nexus"))
     }
 
     #[named]
@@ -1146,6 +1172,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::destroy_nexus"))
     }
 
     #[named]
@@ -1170,6 +1197,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::shutdown_nexus"))
     }
 
     async fn list_nexus(
@@ -1198,6 +1226,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
             .map_err(|_| Status::cancelled("cancelled"))?
             .map_err(Status::from)
             .map(Response::new)
+            .map(|r| deprecated(r, "NexusRpc::list_nexus"))
     }
 
     async fn list_nexus_v2(
@@ -1225,6 +1254,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
             .map_err(|_| Status::cancelled("cancelled"))?
             .map_err(Status::from)
             .map(Response::new)
+            .map(|r| deprecated(r, "NexusRpc::list_nexus"))
     }
 
     #[named]
@@ -1251,6 +1281,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::add_child_nexus"))
     }
 
     #[named]
@@ -1277,6 +1308,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::remove_child_nexus"))
     }
 
     #[named]
@@ -1306,6 +1338,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::fault_nexus_child"))
     }
 
     #[named]
@@ -1342,7 +1375,12 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 };
 
                 let device_uri = nexus_lookup(&args.uuid)?
-                    .share_ext(share_protocol, key, args.allowed_hosts.clone())
+                    .share_ext(
+                        share_protocol,
+                        key,
+                        args.allowed_hosts.clone(),
+                        NvmeIdentity::default(),
+                    )
                     .await?;
 
                 info!(
@@ -1359,6 +1397,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::publish_nexus"))
     }
 
     #[named]
@@ -1385,6 +1424,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::unpublish_nexus"))
     }
 
     #[named]
@@ -1413,6 +1453,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::get_nvme_ana_state"))
     }
 
     #[named]
@@ -1441,6 +1482,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::set_nvme_ana_state"))
     }
 
     #[named]
@@ -1477,6 +1519,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::child_operation"))
     }
 
     #[named]
@@ -1503,6 +1546,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::start_rebuild"))
     }
 
     #[named]
@@ -1527,6 +1571,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::stop_rebuild"))
     }
 
     #[named]
@@ -1550,6 +1595,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::pause_rebuild"))
     }
 
     #[named]
@@ -1572,6 +1618,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::resume_rebuild"))
     }
 
     #[named]
@@ -1596,6 +1643,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::get_rebuild_state"))
     }
 
     #[named]
@@ -1620,6 +1668,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::get_rebuild_stats"))
     }
 
     #[named]
@@ -1647,6 +1696,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "NexusRpc::get_rebuild_progress"))
     }
 
     #[named]
@@ -1718,6 +1768,7 @@ impl mayastor_server::Mayastor for MayastorSvc {
                 .map(Response::new)
         })
         .await
+        .map(|r| deprecated(r, "SnapshotRpc::create_nexus_snapshot"))
     }
 
     async fn list_block_devices(
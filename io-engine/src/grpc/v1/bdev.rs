@@ -1,5 +1,12 @@
 use crate::{
-    bdev_api::{bdev_create, bdev_destroy, BdevError},
+    bdev::{
+        device_destroy_with,
+        device_destroying,
+        device_open_handles,
+        DestroyMode,
+        DestroyOutcome,
+    },
+    bdev_api::{bdev_create, bdev_get_name, bdev_uri_normalize, BdevError},
     core,
     core::{CoreError, Protocol, Share, ShareProps},
     grpc::{rpc_submit, GrpcResult},
@@ -9,17 +16,38 @@ use mayastor_api::v1::bdev::{
     BdevRpc,
     BdevShareRequest,
     BdevShareResponse,
+    BdevState,
     BdevUnshareRequest,
     CreateBdevRequest,
     CreateBdevResponse,
+    DataProtection,
     DestroyBdevRequest,
     ListBdevOptions,
     ListBdevResponse,
+    NormalizeUriRequest,
+    NormalizeUriResponse,
+    ProtectionType,
 };
 use std::{convert::TryFrom, pin::Pin};
 use tonic::{Request, Response, Status};
 use url::Url;
 
+impl From<core::DataProtection> for DataProtection {
+    fn from(p: core::DataProtection) -> Self {
+        let pi_type = match p.pi_type {
+            core::ProtectionType::None => ProtectionType::None,
+            core::ProtectionType::Type1 => ProtectionType::Type1,
+            core::ProtectionType::Type2 => ProtectionType::Type2,
+            core::ProtectionType::Type3 => ProtectionType::Type3,
+        };
+        Self {
+            md_size: p.md_size,
+            md_interleaved: p.md_interleaved,
+            pi_type: pi_type as i32,
+        }
+    }
+}
+
 impl<T> From<core::Bdev<T>> for Bdev
 where
     T: spdk_rs::BdevOps,
@@ -38,6 +66,13 @@ where
             product_name: b.product_name().to_string(),
             share_uri: b.share_uri().unwrap_or_else(|| "".into()),
             uri: Url::try_from(b).map_or("".into(), |u| u.to_string()),
+            data_protection: Some(b.data_protection().into()),
+            state: if device_destroying(b.name()) {
+                BdevState::Destroying
+            } else {
+                BdevState::Online
+            } as i32,
+            open_handles: device_open_handles(b.name()) as u32,
         }
     }
 }
@@ -118,9 +153,34 @@ impl BdevRpc for BdevService {
         &self,
         request: Request<DestroyBdevRequest>,
     ) -> GrpcResult<()> {
-        let uri = request.into_inner().uri;
+        let args = request.into_inner();
+        let mode = if args.force {
+            DestroyMode::Force
+        } else {
+            DestroyMode::Deferred
+        };
 
-        let rx = rpc_submit(async move { bdev_destroy(&uri).await })?;
+        let rx = rpc_submit(async move {
+            info!(uri = ?args.uri, ?mode, "destroy");
+            match device_destroy_with(&args.uri, mode).await? {
+                DestroyOutcome::Destroyed => {}
+                DestroyOutcome::Deferred {
+                    open_handles,
+                } => info!(
+                    "Bdev {} is destroying, waiting for {open_handles} open \
+                    handle(s) to be closed",
+                    args.uri
+                ),
+                DestroyOutcome::Forced {
+                    open_handles,
+                } => warn!(
+                    "Bdev {} was force destroyed with {open_handles} open \
+                    handle(s)",
+                    args.uri
+                ),
+            }
+            Ok::<_, BdevError>(())
+        })?;
 
         rx.await
             .map_err(|_| Status::cancelled("cancelled"))?
@@ -185,4 +245,18 @@ impl BdevRpc for BdevService {
             .map_err(Status::from)
             .map(Response::new)
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn normalize_uri(
+        &self,
+        request: Request<NormalizeUriRequest>,
+    ) -> GrpcResult<NormalizeUriResponse> {
+        let uri = request.into_inner().uri;
+        let normalized = bdev_uri_normalize(&uri).map_err(Status::from)?;
+        let name = bdev_get_name(&normalized).map_err(Status::from)?;
+        Ok(Response::new(NormalizeUriResponse {
+            uri: normalized,
+            name,
+        }))
+    }
 }
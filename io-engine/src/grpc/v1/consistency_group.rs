@@ -0,0 +1,309 @@
+use crate::{
+    bdev::{
+        nexus,
+        nexus::{
+            consistency_group_create,
+            consistency_group_destroy,
+            consistency_group_lookup,
+            consistency_groups,
+            ConsistencyGroup,
+            NexusReplicaSnapshotDescriptor,
+        },
+    },
+    core::snapshot::SnapshotParams,
+    grpc::{
+        rpc_submit,
+        v1::nexus::nexus_lookup,
+        GrpcClientContext,
+        GrpcResult,
+        Serializer,
+    },
+};
+use ::function_name::named;
+use chrono::Utc;
+use futures::FutureExt;
+use mayastor_api::v1::{
+    consistency_group as cg_rpc,
+    consistency_group::*,
+    snapshot::{NexusCreateSnapshotReplicaStatus, NexusCreateSnapshotResponse},
+};
+use std::{collections::HashMap, panic::AssertUnwindSafe};
+use tonic::{Request, Response, Status};
+
+/// RPC service for consistency groups of nexuses, which are frozen, thawed
+/// and snapshotted as a whole.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct ConsistencyGroupService {
+    name: String,
+    client_context: tokio::sync::Mutex<Option<GrpcClientContext>>,
+}
+
+#[async_trait::async_trait]
+impl<F, T> Serializer<F, T> for ConsistencyGroupService
+where
+    T: Send + 'static,
+    F: core::future::Future<Output = Result<T, Status>> + Send + 'static,
+{
+    async fn locked(&self, ctx: GrpcClientContext, f: F) -> Result<T, Status> {
+        let mut context_guard = self.client_context.lock().await;
+
+        // Store context as a marker of to detect abnormal termination of the
+        // request.
+        if let Some(c) = context_guard.replace(ctx) {
+            warn!("{}: gRPC method timed out, args: {}", c.id, c.args);
+        }
+
+        let fut = AssertUnwindSafe(f).catch_unwind();
+        let r = fut.await;
+
+        // Request completed, remove the marker.
+        let ctx = context_guard.take().expect("gRPC context disappeared");
+
+        match r {
+            Ok(r) => r,
+            Err(_e) => {
+                warn!("{}: gRPC method panicked, args: {}", ctx.id, ctx.args);
+                Err(Status::cancelled(format!(
+                    "{}: gRPC method panicked",
+                    ctx.id
+                )))
+            }
+        }
+    }
+}
+
+impl Default for ConsistencyGroupService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsistencyGroupService {
+    pub fn new() -> Self {
+        Self {
+            name: String::from("ConsistencyGroupSvc"),
+            client_context: tokio::sync::Mutex::new(None),
+        }
+    }
+}
+
+impl From<&ConsistencyGroup> for cg_rpc::ConsistencyGroup {
+    fn from(group: &ConsistencyGroup) -> Self {
+        Self {
+            name: group.name.clone(),
+            nexus_uuids: group.nexuses.clone(),
+            frozen_until: group.frozen_until().map(|t| t.into()),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ConsistencyGroupRpc for ConsistencyGroupService {
+    #[named]
+    async fn create_consistency_group(
+        &self,
+        request: Request<CreateConsistencyGroupRequest>,
+    ) -> GrpcResult<cg_rpc::ConsistencyGroup> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                    let group =
+                        consistency_group_create(&args.name, args.nexus_uuids)?;
+                    Ok(cg_rpc::ConsistencyGroup::from(&group))
+                })?;
+
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn destroy_consistency_group(
+        &self,
+        request: Request<DestroyConsistencyGroupRequest>,
+    ) -> GrpcResult<()> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                consistency_group_destroy(&args.name)
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    async fn list_consistency_groups(
+        &self,
+        request: Request<ListConsistencyGroupsRequest>,
+    ) -> GrpcResult<ListConsistencyGroupsResponse> {
+        let args = request.into_inner();
+        let rx = rpc_submit::<_, _, nexus::Error>(async move {
+            let groups = match args.name {
+                Some(name) => vec![consistency_group_lookup(&name)?],
+                None => consistency_groups(),
+            };
+            Ok(ListConsistencyGroupsResponse {
+                groups: groups
+                    .iter()
+                    .map(cg_rpc::ConsistencyGroup::from)
+                    .collect(),
+            })
+        })?;
+
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    #[named]
+    async fn freeze_consistency_group(
+        &self,
+        request: Request<FreezeConsistencyGroupRequest>,
+    ) -> GrpcResult<FreezeConsistencyGroupResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let timeout = args
+                    .timeout
+                    .and_then(|t| std::time::Duration::try_from(t).ok())
+                    .unwrap_or(nexus::NEXUS_FREEZE_MAX_TIMEOUT);
+                let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                    let group = consistency_group_lookup(&args.name)?;
+                    let freezes = group.freeze(timeout).await?;
+                    Ok(FreezeConsistencyGroupResponse {
+                        group: Some(cg_rpc::ConsistencyGroup::from(&group)),
+                        frozen_at: freezes
+                            .iter()
+                            .map(|f| f.frozen_at)
+                            .min()
+                            .map(|t| t.into()),
+                        deadline: freezes
+                            .iter()
+                            .map(|f| f.deadline)
+                            .min()
+                            .map(|t| t.into()),
+                    })
+                })?;
+
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn thaw_consistency_group(
+        &self,
+        request: Request<ThawConsistencyGroupRequest>,
+    ) -> GrpcResult<cg_rpc::ConsistencyGroup> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                    let group = consistency_group_lookup(&args.name)?;
+                    group.thaw().await?;
+                    Ok(cg_rpc::ConsistencyGroup::from(&group))
+                })?;
+
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn create_consistency_group_snapshot(
+        &self,
+        request: Request<CreateConsistencyGroupSnapshotRequest>,
+    ) -> GrpcResult<CreateConsistencyGroupSnapshotResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                    let group = consistency_group_lookup(&args.name)?;
+
+                    // The parent of each snapshot is set to its nexus.
+                    let snapshot = SnapshotParams::new(
+                        Some(args.entity_id.clone()),
+                        None,
+                        Some(args.txn_id.clone()),
+                        Some(args.snapshot_name.clone()),
+                        None, // Snapshot UUID is handled on per-replica base.
+                        Some(Utc::now().to_string()),
+                        false,
+                    );
+
+                    let replicas = args
+                        .nexuses
+                        .into_iter()
+                        .map(|n| {
+                            (
+                                n.nexus_uuid,
+                                n.replicas
+                                    .into_iter()
+                                    .map(NexusReplicaSnapshotDescriptor::from)
+                                    .collect::<Vec<_>>(),
+                            )
+                        })
+                        .collect::<HashMap<_, _>>();
+
+                    let res = group.create_snapshot(snapshot, replicas).await?;
+
+                    let mut nexuses = Vec::with_capacity(res.len());
+                    for (uuid, status) in res {
+                        nexuses.push(NexusCreateSnapshotResponse {
+                            nexus: Some(nexus_lookup(&uuid)?.into_grpc().await),
+                            snapshot_timestamp: status
+                                .snapshot_timestamp
+                                .map(|t| t.into()),
+                            replicas_done: status
+                                .replicas_done
+                                .into_iter()
+                                .map(NexusCreateSnapshotReplicaStatus::from)
+                                .collect(),
+                            replicas_skipped: status.replicas_skipped,
+                        });
+                    }
+                    info!(
+                        "Create Snapshot Success for consistency group \
+                        {group:?}"
+                    );
+                    Ok(CreateConsistencyGroupSnapshotResponse {
+                        group: Some(cg_rpc::ConsistencyGroup::from(&group)),
+                        nexuses,
+                    })
+                })?;
+
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+}
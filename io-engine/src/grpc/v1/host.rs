@@ -1,5 +1,6 @@
 use crate::{
     bdev::{nexus, NvmeControllerState},
+    bdev_api::BdevError,
     core::{BlockDeviceIoStats, CoreError, MayastorFeatures},
     grpc::{
         controller_grpc::{
@@ -8,17 +9,46 @@ use crate::{
             NvmeControllerInfo,
         },
         rpc_submit,
+        GrpcAudit,
+        GrpcAuditEntry,
+        GrpcAuditFilter,
         GrpcClientContext,
         GrpcResult,
         Serializer,
     },
-    host::{blk_device, resource},
-    subsys::{registration::registration_grpc::ApiVersion, Registration},
+    host::{
+        blk_device,
+        capabilities::{NodeCapabilities, NodeCapability},
+        device_refs,
+        disk_health,
+        identity::{HostIdentity, HostIdentityError},
+        options::{HostOptions, HostOptionsError, HostOptionsUpdate},
+        pool_scrub,
+        resource,
+    },
+    persistent_store::{PersistentStore, PersistentStoreHealth},
+    subsys::{
+        create_nvmf_transport,
+        list_nvmf_transports,
+        registration::registration_grpc::ApiVersion,
+        NvmfError,
+        NvmfPollGroupLoad,
+        NvmfRebalanceResult,
+        NvmfRebalancer,
+        NvmfTransportInfo,
+        NvmfTransportOpts,
+        NvmfTransportType,
+        Registration,
+    },
 };
 use ::function_name::named;
 use futures::FutureExt;
 use mayastor_api::v1::{host as host_rpc, registration::RegisterRequest};
-use std::panic::AssertUnwindSafe;
+use nix::errno::Errno;
+use std::{
+    panic::AssertUnwindSafe,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use tonic::{Request, Response, Status};
 use version_info::raw_version_string;
 
@@ -99,6 +129,7 @@ impl From<MayastorFeatures> for host_rpc::MayastorFeatures {
     fn from(f: MayastorFeatures) -> Self {
         Self {
             asymmetric_namespace_access: f.asymmetric_namespace_access,
+            nexus_batched_io: f.nexus_batched_io,
         }
     }
 }
@@ -197,6 +228,349 @@ impl From<NvmeControllerState> for host_rpc::NvmeControllerState {
     }
 }
 
+impl From<disk_health::DiskHealthSource> for host_rpc::DiskHealthSource {
+    fn from(s: disk_health::DiskHealthSource) -> Self {
+        match s {
+            disk_health::DiskHealthSource::NvmeSmart => Self::NvmeSmart,
+            disk_health::DiskHealthSource::Sysfs => Self::Sysfs,
+        }
+    }
+}
+
+impl From<disk_health::DiskHealth> for host_rpc::DiskHealth {
+    fn from(h: disk_health::DiskHealth) -> Self {
+        Self {
+            pool: h.pool,
+            disk: h.disk,
+            source: h
+                .source
+                .map(|s| host_rpc::DiskHealthSource::from(s) as i32),
+            critical_warning: h.critical_warning.map(u32::from),
+            temperature: h.temperature,
+            available_spare: h.available_spare.map(u32::from),
+            available_spare_threshold: h
+                .available_spare_threshold
+                .map(u32::from),
+            percentage_used: h.percentage_used.map(u32::from),
+            media_errors: h.media_errors,
+            power_on_hours: h.power_on_hours,
+            unsafe_shutdowns: h.unsafe_shutdowns,
+            error: h.error,
+        }
+    }
+}
+
+impl From<device_refs::DeviceRefs> for host_rpc::ResolveDeviceResponse {
+    fn from(r: device_refs::DeviceRefs) -> Self {
+        Self {
+            device: r.device,
+            pools: r.pools,
+            replicas: r.replicas,
+            nexus_children: r
+                .nexus_children
+                .into_iter()
+                .map(|c| host_rpc::NexusChildRef {
+                    nexus: c.nexus,
+                    uri: c.uri,
+                })
+                .collect(),
+            injections: r.injections,
+            shares: r.shares,
+        }
+    }
+}
+
+impl From<pool_scrub::PoolScrubState> for host_rpc::PoolScrubState {
+    fn from(s: pool_scrub::PoolScrubState) -> Self {
+        match s {
+            pool_scrub::PoolScrubState::Idle => Self::Idle,
+            pool_scrub::PoolScrubState::Running => Self::Running,
+        }
+    }
+}
+
+impl From<pool_scrub::PoolScrubStatus> for host_rpc::PoolScrubStatus {
+    fn from(s: pool_scrub::PoolScrubStatus) -> Self {
+        Self {
+            pool: s.pool,
+            disk: s.disk,
+            state: host_rpc::PoolScrubState::from(s.state) as i32,
+            bytes_scanned: s.bytes_scanned,
+            bytes_total: s.bytes_total,
+            unreadable_blocks: s.unreadable_blocks,
+            last_unreadable_blocks: s.last_unreadable_blocks,
+            passes: s.passes,
+            last_completed: s
+                .last_completed
+                .map(|t| prost_types::Timestamp::from(SystemTime::from(t))),
+        }
+    }
+}
+
+impl From<GrpcAuditEntry> for host_rpc::AuditLogEntry {
+    fn from(e: GrpcAuditEntry) -> Self {
+        Self {
+            time: Some(prost_types::Timestamp::from(
+                UNIX_EPOCH + Duration::from_millis(e.time_ms),
+            )),
+            method: e.method,
+            args_hash: e.args_hash,
+            peer: e.peer,
+            code: e.code,
+            message: e.message,
+            duration: prost_types::Duration::try_from(Duration::from_micros(
+                e.duration_us,
+            ))
+            .ok(),
+        }
+    }
+}
+
+impl From<host_rpc::GetAuditLogRequest> for GrpcAuditFilter {
+    fn from(r: host_rpc::GetAuditLogRequest) -> Self {
+        Self {
+            method: r.method,
+            peer: r.peer,
+            since_ms: r
+                .since
+                .and_then(|t| SystemTime::try_from(t).ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64),
+            failed_only: r.failed_only,
+            limit: r.max_entries as usize,
+        }
+    }
+}
+
+impl From<PersistentStoreHealth> for host_rpc::PersistentStoreHealth {
+    fn from(h: PersistentStoreHealth) -> Self {
+        Self {
+            endpoint: h.endpoint,
+            connected: h.connected,
+            tls: h.tls,
+            authenticated: h.authenticated,
+            last_error: h.last_error,
+            last_error_time: h
+                .last_error_time
+                .map(prost_types::Timestamp::from),
+            last_success_time: h
+                .last_success_time
+                .map(prost_types::Timestamp::from),
+            reconnects: h.reconnects,
+            deferred_ops: h.deferred_ops as u64,
+        }
+    }
+}
+
+impl From<HostOptions> for host_rpc::HostOptions {
+    fn from(o: HostOptions) -> Self {
+        Self {
+            nvmf_reservations: o.nvmf_reservations,
+            partial_rebuild: o.partial_rebuild,
+            nexus_reset: o.nexus_reset,
+            nvme_timeout_us: o.nvme_timeout_us,
+            nvme_timeout_admin_us: o.nvme_timeout_admin_us,
+            nvme_keep_alive_timeout_ms: o.nvme_keep_alive_timeout_ms,
+            rebuild_max_concurrent: o.rebuild_max_concurrent as u32,
+            rebuild_max_per_nexus: o.rebuild_max_per_nexus as u32,
+            rebuild_max_bandwidth: o.rebuild_max_bandwidth,
+            rebuild_segment_size: o.rebuild_segment_size,
+            rebuild_segment_tasks: o.rebuild_segment_tasks as u32,
+            rebuild_client_qd_threshold: o.rebuild_client_qd_threshold as u32,
+        }
+    }
+}
+
+impl From<host_rpc::SetOptionsRequest> for HostOptionsUpdate {
+    fn from(r: host_rpc::SetOptionsRequest) -> Self {
+        Self {
+            nvmf_reservations: r.nvmf_reservations,
+            partial_rebuild: r.partial_rebuild,
+            nexus_reset: r.nexus_reset,
+            nvme_timeout_us: r.nvme_timeout_us,
+            nvme_timeout_admin_us: r.nvme_timeout_admin_us,
+            nvme_keep_alive_timeout_ms: r.nvme_keep_alive_timeout_ms,
+            rebuild_max_concurrent: r
+                .rebuild_max_concurrent
+                .map(|v| v as usize),
+            rebuild_max_per_nexus: r.rebuild_max_per_nexus.map(|v| v as usize),
+            rebuild_max_bandwidth: r.rebuild_max_bandwidth,
+            rebuild_segment_size: r.rebuild_segment_size,
+            rebuild_segment_tasks: r.rebuild_segment_tasks.map(|v| v as usize),
+            rebuild_client_qd_threshold: r
+                .rebuild_client_qd_threshold
+                .map(|v| v as usize),
+        }
+    }
+}
+
+impl From<HostOptionsError> for Status {
+    fn from(e: HostOptionsError) -> Self {
+        match e {
+            HostOptionsError::InvalidOption {
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            HostOptionsError::PersistOptions {
+                ..
+            }
+            | HostOptionsError::ParseOptions {
+                ..
+            } => Status::unavailable(e.to_string()),
+        }
+    }
+}
+
+impl From<NodeCapability> for host_rpc::NodeCapability {
+    fn from(c: NodeCapability) -> Self {
+        Self {
+            name: c.name.to_string(),
+            compiled: c.compiled,
+            available: c.available,
+            enabled: c.enabled,
+        }
+    }
+}
+
+impl From<NodeCapabilities> for host_rpc::GetCapabilitiesResponse {
+    fn from(c: NodeCapabilities) -> Self {
+        Self {
+            capabilities: c
+                .capabilities
+                .into_iter()
+                .map(host_rpc::NodeCapability::from)
+                .collect(),
+            api_versions: c
+                .api_versions
+                .into_iter()
+                .map(|v| {
+                    mayastor_api::v1::registration::ApiVersion::from(v) as i32
+                })
+                .collect(),
+            supported_features: Some(c.features.into()),
+        }
+    }
+}
+
+impl From<HostIdentity> for host_rpc::HostIdentity {
+    fn from(i: HostIdentity) -> Self {
+        Self {
+            nqn: i.hostnqn().unwrap_or_default(),
+            hostid: i.hostid.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<HostIdentityError> for Status {
+    fn from(e: HostIdentityError) -> Self {
+        match e {
+            HostIdentityError::InvalidNqn {
+                ..
+            }
+            | HostIdentityError::InvalidHostId {
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            HostIdentityError::PersistIdentity {
+                ..
+            }
+            | HostIdentityError::ParseIdentity {
+                ..
+            } => Status::unavailable(e.to_string()),
+        }
+    }
+}
+
+impl From<NvmfTransportInfo> for host_rpc::NvmfTransport {
+    fn from(t: NvmfTransportInfo) -> Self {
+        Self {
+            trtype: t.trtype,
+            max_queue_depth: t.max_queue_depth as u32,
+            max_io_size: t.max_io_size,
+            io_unit_size: t.io_unit_size,
+            in_capsule_data_size: t.in_capsule_data_size,
+            num_shared_buffers: t.num_shared_buffers,
+            buf_cache_size: t.buf_cache_size,
+            poll_groups: t.poll_groups,
+        }
+    }
+}
+
+impl TryFrom<host_rpc::CreateNvmfTransportRequest> for NvmfTransportOpts {
+    type Error = Status;
+
+    fn try_from(
+        args: host_rpc::CreateNvmfTransportRequest,
+    ) -> Result<Self, Self::Error> {
+        let trtype = match host_rpc::NvmfTransportType::from_i32(args.trtype) {
+            Some(host_rpc::NvmfTransportType::Tcp) => NvmfTransportType::Tcp,
+            Some(host_rpc::NvmfTransportType::Rdma) => NvmfTransportType::Rdma,
+            None => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid transport type {}",
+                    args.trtype
+                )))
+            }
+        };
+
+        let max_queue_depth = args
+            .max_queue_depth
+            .map(u16::try_from)
+            .transpose()
+            .map_err(|_| {
+                Status::invalid_argument("Transport queue depth is too large")
+            })?;
+
+        Ok(Self {
+            trtype,
+            max_queue_depth,
+            max_io_size: args.max_io_size,
+            io_unit_size: args.io_unit_size,
+            in_capsule_data_size: args.in_capsule_data_size,
+            num_shared_buffers: args.num_shared_buffers,
+            buf_cache_size: args.buf_cache_size,
+        })
+    }
+}
+
+impl From<NvmfError> for Status {
+    fn from(e: NvmfError) -> Self {
+        match e {
+            NvmfError::Transport {
+                source: Errno::EEXIST,
+                ..
+            } => Status::already_exists(e.to_string()),
+            NvmfError::Transport {
+                source: Errno::EOPNOTSUPP,
+                ..
+            } => Status::invalid_argument(e.to_string()),
+            NvmfError::Transport {
+                source: Errno::EAGAIN,
+                ..
+            } => Status::unavailable(e.to_string()),
+            _ => Status::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<NvmfPollGroupLoad> for host_rpc::NvmfPollGroupLoad {
+    fn from(p: NvmfPollGroupLoad) -> Self {
+        Self {
+            core: p.core,
+            io_qpairs: p.io_qpairs,
+            completed_ios: p.completed_ios,
+            load: p.load,
+        }
+    }
+}
+
+impl From<NvmfRebalanceResult> for host_rpc::RebalanceConnectionsResponse {
+    fn from(r: NvmfRebalanceResult) -> Self {
+        Self {
+            poll_groups: r.poll_groups.into_iter().map(Into::into).collect(),
+            moved_from_core: r.moved_from,
+        }
+    }
+}
+
 impl From<BlockDeviceIoStats> for host_rpc::NvmeControllerIoStats {
     fn from(b: BlockDeviceIoStats) -> Self {
         Self {
@@ -236,7 +610,9 @@ impl host_rpc::HostRpc for HostService {
                 instance_uuid: Registration::get()
                     .map(|r| r.instance_uuid().to_string()),
                 api_version: api_versions,
-                hostnqn: self.node_nqn.clone(),
+                hostnqn: HostIdentity::current()
+                    .hostnqn()
+                    .or_else(|| self.node_nqn.clone()),
             }),
         };
 
@@ -329,4 +705,270 @@ impl host_rpc::HostRpc for HostService {
         )
         .await
     }
+
+    #[named]
+    async fn get_disk_health(
+        &self,
+        request: Request<host_rpc::GetDiskHealthRequest>,
+    ) -> GrpcResult<host_rpc::GetDiskHealthResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                let rx = rpc_submit::<_, _, CoreError>(async move {
+                    let disks =
+                        disk_health::get_disk_health(args.pool.as_deref())
+                            .await
+                            .into_iter()
+                            .map(host_rpc::DiskHealth::from)
+                            .collect();
+                    Ok(host_rpc::GetDiskHealthResponse {
+                        disks,
+                    })
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn get_pool_scrub_status(
+        &self,
+        request: Request<host_rpc::GetPoolScrubStatusRequest>,
+    ) -> GrpcResult<host_rpc::GetPoolScrubStatusResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                let rx = rpc_submit::<_, _, CoreError>(async move {
+                    let pools =
+                        pool_scrub::get_pool_scrub_status(args.pool.as_deref())
+                            .into_iter()
+                            .map(host_rpc::PoolScrubStatus::from)
+                            .collect();
+                    Ok(host_rpc::GetPoolScrubStatusResponse {
+                        enabled: pool_scrub::PoolScrubber::enabled(),
+                        pools,
+                    })
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    async fn get_options(
+        &self,
+        _request: Request<()>,
+    ) -> GrpcResult<host_rpc::GetOptionsResponse> {
+        let response = host_rpc::GetOptionsResponse {
+            options: Some(HostOptions::current().into()),
+        };
+        trace!("{:?}", response);
+        Ok(Response::new(response))
+    }
+
+    #[named]
+    async fn set_options(
+        &self,
+        request: Request<host_rpc::SetOptionsRequest>,
+    ) -> GrpcResult<host_rpc::SetOptionsResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let update = HostOptionsUpdate::from(request.into_inner());
+                let node_name = self.node_name.clone();
+                let rx = rpc_submit::<_, _, HostOptionsError>(async move {
+                    let options =
+                        HostOptions::update(&node_name, update).await?;
+                    Ok(host_rpc::SetOptionsResponse {
+                        options: Some(options.into()),
+                    })
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    async fn get_audit_log(
+        &self,
+        request: Request<host_rpc::GetAuditLogRequest>,
+    ) -> GrpcResult<host_rpc::GetAuditLogResponse> {
+        let filter = GrpcAuditFilter::from(request.into_inner());
+        trace!("{:?}", filter);
+        let response = host_rpc::GetAuditLogResponse {
+            enabled: GrpcAudit::enabled(),
+            entries: GrpcAudit::entries(&filter)
+                .into_iter()
+                .map(host_rpc::AuditLogEntry::from)
+                .collect(),
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn get_capabilities(
+        &self,
+        _request: Request<()>,
+    ) -> GrpcResult<host_rpc::GetCapabilitiesResponse> {
+        let api_versions = self.api_versions.clone();
+        let rx = rpc_submit::<_, _, CoreError>(async move {
+            Ok(NodeCapabilities::detect(&api_versions).into())
+        })?;
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    async fn get_persistent_store_health(
+        &self,
+        _request: Request<()>,
+    ) -> GrpcResult<host_rpc::GetPersistentStoreHealthResponse> {
+        let response = host_rpc::GetPersistentStoreHealthResponse {
+            enabled: PersistentStore::enabled(),
+            health: PersistentStore::health()
+                .map(host_rpc::PersistentStoreHealth::from),
+        };
+        trace!("{:?}", response);
+        Ok(Response::new(response))
+    }
+
+    async fn get_host_identity(
+        &self,
+        _request: Request<()>,
+    ) -> GrpcResult<host_rpc::GetHostIdentityResponse> {
+        let response = host_rpc::GetHostIdentityResponse {
+            identity: Some(HostIdentity::current().into()),
+        };
+        trace!("{:?}", response);
+        Ok(Response::new(response))
+    }
+
+    #[named]
+    async fn set_host_identity(
+        &self,
+        request: Request<host_rpc::SetHostIdentityRequest>,
+    ) -> GrpcResult<host_rpc::SetHostIdentityResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let node_name = self.node_name.clone();
+                let rx = rpc_submit::<_, _, HostIdentityError>(async move {
+                    let identity =
+                        HostIdentity::update(&node_name, args.nqn, args.hostid)
+                            .await?;
+                    Ok(host_rpc::SetHostIdentityResponse {
+                        identity: Some(identity.into()),
+                    })
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    async fn list_nvmf_transports(
+        &self,
+        _request: Request<()>,
+    ) -> GrpcResult<host_rpc::ListNvmfTransportsResponse> {
+        let rx = rpc_submit::<_, _, NvmfError>(async move {
+            Ok(host_rpc::ListNvmfTransportsResponse {
+                transports: list_nvmf_transports()?
+                    .into_iter()
+                    .map(host_rpc::NvmfTransport::from)
+                    .collect(),
+            })
+        })?;
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    #[named]
+    async fn create_nvmf_transport(
+        &self,
+        request: Request<host_rpc::CreateNvmfTransportRequest>,
+    ) -> GrpcResult<host_rpc::CreateNvmfTransportResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let opts = NvmfTransportOpts::try_from(args)?;
+                let rx = rpc_submit::<_, _, NvmfError>(async move {
+                    let transport = create_nvmf_transport(opts).await?;
+                    Ok(host_rpc::CreateNvmfTransportResponse {
+                        transport: Some(transport.into()),
+                    })
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn rebalance_connections(
+        &self,
+        request: Request<host_rpc::RebalanceConnectionsRequest>,
+    ) -> GrpcResult<host_rpc::RebalanceConnectionsResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let threshold = match args.threshold {
+                    0 => NvmfRebalancer::threshold(),
+                    threshold => threshold,
+                };
+                let rx = rpc_submit::<_, _, NvmfError>(async move {
+                    NvmfRebalancer::rebalance(threshold).await.map(Into::into)
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    async fn resolve_device(
+        &self,
+        request: Request<host_rpc::ResolveDeviceRequest>,
+    ) -> GrpcResult<host_rpc::ResolveDeviceResponse> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+        let device = args.device.clone();
+        let rx = rpc_submit::<_, _, BdevError>(async move {
+            device_refs::resolve_device(&device)
+        })?;
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)?
+            .map(|refs| Response::new(refs.into()))
+            .ok_or_else(|| {
+                Status::not_found(format!("device {} not found", args.device))
+            })
+    }
 }
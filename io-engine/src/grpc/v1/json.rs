@@ -3,9 +3,66 @@
 
 use crate::grpc::GrpcResult;
 use jsonrpc::error::Error;
-use mayastor_api::v1::json::{JsonRpc, JsonRpcRequest, JsonRpcResponse};
+use mayastor_api::v1::json::{
+    JsonRpc,
+    JsonRpcBatchItem,
+    JsonRpcBatchRequest,
+    JsonRpcBatchResponse,
+    JsonRpcError,
+    JsonRpcRequest,
+    JsonRpcResponse,
+};
+use once_cell::sync::OnceCell;
 use std::borrow::Cow;
-use tonic::{Request, Response};
+use tonic::{Request, Response, Status};
+
+/// SPDK json-rpc methods which may be invoked via gRPC.
+static JSON_RPC_ALLOW_LIST: OnceCell<Vec<String>> = OnceCell::new();
+
+/// Allow-list of the SPDK json-rpc methods which may be invoked via gRPC.
+/// An entry ending with `*` allows all methods starting with the given prefix.
+/// If no allow-list has been configured, or it is empty, all methods are
+/// allowed.
+pub struct JsonRpcAllowList {}
+
+impl JsonRpcAllowList {
+    /// Sets the allow-list. Only the first call has an effect.
+    pub fn configure(methods: Vec<String>) {
+        if methods.is_empty() {
+            warn!("All SPDK json-rpc methods are allowed via gRPC");
+        } else {
+            info!("SPDK json-rpc methods allowed via gRPC: {methods:?}");
+        }
+
+        if JSON_RPC_ALLOW_LIST.set(methods).is_err() {
+            warn!("SPDK json-rpc allow-list has already been configured");
+        }
+    }
+
+    /// Checks if the given method is allowed.
+    pub fn is_allowed(method: &str) -> bool {
+        match JSON_RPC_ALLOW_LIST.get() {
+            Some(methods) if !methods.is_empty() => {
+                methods.iter().any(|m| match m.strip_suffix('*') {
+                    Some(prefix) => method.starts_with(prefix),
+                    None => method == m,
+                })
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns an error if the given method is not allowed.
+    pub fn check(method: &str) -> Result<(), Status> {
+        if Self::is_allowed(method) {
+            Ok(())
+        } else {
+            Err(Status::permission_denied(format!(
+                "json-rpc method '{method}' is not allowed"
+            )))
+        }
+    }
+}
 
 /// RPC Service for local SPDK json-rpc calls
 #[derive(Debug)]
@@ -32,6 +89,8 @@ impl JsonRpc for JsonService {
     ) -> GrpcResult<JsonRpcResponse> {
         let args = request.into_inner();
 
+        JsonRpcAllowList::check(&args.method)?;
+
         let result = self
             .spdk_jsonrpc_call(&args.method, empty_as_none(&args.params))
             .await?;
@@ -40,6 +99,53 @@ impl JsonRpc for JsonService {
             result,
         }))
     }
+
+    /// Invoke a batch of json-rpc methods one after another, and return the
+    /// result of each of them. If requested, the batch stops at the first
+    /// failed method, and the remaining methods are not invoked.
+    #[tracing::instrument(skip(self))]
+    async fn json_rpc_batch(
+        &self,
+        request: Request<JsonRpcBatchRequest>,
+    ) -> GrpcResult<JsonRpcBatchResponse> {
+        let args = request.into_inner();
+
+        let mut items = Vec::with_capacity(args.requests.len());
+        for req in args.requests {
+            let result = match JsonRpcAllowList::check(&req.method) {
+                Ok(()) => self
+                    .spdk_jsonrpc_call(&req.method, empty_as_none(&req.params))
+                    .await
+                    .map_err(Status::from),
+                Err(status) => Err(status),
+            };
+
+            let failed = result.is_err();
+            items.push(match result {
+                Ok(result) => JsonRpcBatchItem {
+                    method: req.method,
+                    result,
+                    error: None,
+                },
+                Err(status) => JsonRpcBatchItem {
+                    method: req.method,
+                    result: String::new(),
+                    error: Some(JsonRpcError {
+                        code: status.code() as i32,
+                        message: status.message().to_string(),
+                    }),
+                },
+            });
+
+            if failed && args.stop_on_error {
+                break;
+            }
+        }
+
+        Ok(Response::new(JsonRpcBatchResponse {
+            items,
+        }))
+    }
 }
 
 fn empty_as_none(value: &str) -> Option<&str> {
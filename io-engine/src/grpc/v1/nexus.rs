@@ -2,21 +2,32 @@ use crate::{
     bdev::{
         nexus,
         nexus::{
-            nexus_lookup_uuid_mut,
+            nexus_lookup_id_mut,
             ChildStateClient,
             FaultReason,
             NexusChild,
+            NexusInfo,
+            NexusShutdownReason,
             NexusStatus,
+            NexusStatusReason,
         },
     },
     core::{
         lock::{ProtectedSubsystems, ResourceLockManager},
+        NvmeIdentity,
         Protocol,
+        ResourceGeneration,
+        ResourceKind,
         Share,
+        VerboseError,
     },
     grpc::{rpc_submit, GrpcClientContext, GrpcResult},
-    rebuild::{HistoryRecord, RebuildState, RebuildStats},
+    host::tombstone::Tombstones,
+    persistent_store::PersistentStore,
+    rebuild,
+    rebuild::{HistoryRecord, RebuildScheduler, RebuildState, RebuildStats},
 };
+use chrono::{DateTime, Utc};
 use futures::FutureExt;
 use std::{
     collections::HashMap,
@@ -24,6 +35,7 @@ use std::{
     fmt::Debug,
     ops::Deref,
     pin::Pin,
+    time::{Duration, SystemTime},
 };
 use tonic::{Request, Response, Status};
 
@@ -39,6 +51,133 @@ use std::panic::AssertUnwindSafe;
 
 use crate::eventing::Event;
 
+/// Creates the nexus of the given request, whose enumerations have been
+/// converted.
+async fn create_nexus_from_request(
+    args: CreateNexusRequest,
+    resv_type: nexus::NvmeReservation,
+    preempt_policy: nexus::NexusNvmePreemption,
+    enospc_policy: nexus::NexusEnospcPolicy,
+) -> Result<Nexus, nexus::Error> {
+    // Nexuses are looked up by name or uuid, so that the name and the uuid
+    // of the new nexus must not identify any existing nexus.
+    for n in nexus::nexus_iter() {
+        let uuid = n.uuid().to_string();
+        if n.nexus_name() == args.name {
+            return Err(nexus::Error::NameExists {
+                name: args.name.clone(),
+            });
+        }
+        if uuid == args.uuid {
+            return Err(nexus::Error::UuidExists {
+                uuid: args.uuid.clone(),
+                nexus: n.nexus_name().to_string(),
+            });
+        }
+        if uuid == args.name {
+            return Err(nexus::Error::InvalidArguments {
+                name: args.name.clone(),
+                args: format!("name is the uuid of nexus {}", n.nexus_name()),
+            });
+        }
+        if n.nexus_name() == args.uuid {
+            return Err(nexus::Error::InvalidArguments {
+                name: args.name.clone(),
+                args: format!("uuid is the name of nexus {uuid}"),
+            });
+        }
+    }
+
+    // If the control plane has supplied a key, use it to store
+    // the NexusInfo.
+    let nexus_info_key = if args.nexus_info_key.is_empty() {
+        None
+    } else {
+        Some(args.nexus_info_key.to_string())
+    };
+
+    nexus::nexus_create_v2(
+        &args.name,
+        args.size,
+        &args.uuid,
+        nexus::NexusNvmeParams {
+            min_cntlid: args.min_cntl_id as u16,
+            max_cntlid: args.max_cntl_id as u16,
+            resv_key: args.resv_key,
+            preempt_key: match args.preempt_key {
+                0 => None,
+                k => std::num::NonZeroU64::new(k),
+            },
+            resv_type,
+            preempt_policy,
+            block_size: args.block_size,
+        },
+        &args.children,
+        nexus_info_key,
+    )
+    .await?;
+
+    // A nexus which fails to be configured is destroyed, so that the
+    // creation can be retried.
+    if let Err(error) = configure_nexus(&args, enospc_policy).await {
+        error!(
+            "Failed to configure nexus {}/{}, destroying it: {}",
+            args.name,
+            args.uuid,
+            error.verbose()
+        );
+        let rollback = match nexus_destroy(&args.uuid).await {
+            Ok(_) => "completed".to_string(),
+            Err(error) => format!("failed: {}", error.verbose()),
+        };
+        return Err(nexus::Error::CreateRollback {
+            source: Box::new(error),
+            name: args.name,
+            rollback,
+        });
+    }
+
+    let nexus = nexus_lookup(&args.uuid)?;
+    nexus.event(EventAction::Create).generate();
+    info!("Created nexus {}/{}", &args.name, &args.uuid);
+    Ok(nexus.into_grpc().await)
+}
+
+/// Applies the settings of a nexus creation request to the created nexus.
+async fn configure_nexus(
+    args: &CreateNexusRequest,
+    enospc_policy: nexus::NexusEnospcPolicy,
+) -> Result<(), nexus::Error> {
+    let nexus = nexus_lookup(&args.uuid)?;
+    nexus.set_alias(args.alias.as_deref())?;
+    nexus.set_enospc_policy(enospc_policy);
+    nexus.set_copy_on_read(args.copy_on_read);
+    nexus.set_striped_rebuild(args.striped_rebuild);
+    nexus.set_rebuild_segments(
+        args.rebuild_segment_size,
+        args.rebuild_tasks as usize,
+    )?;
+    nexus.set_max_io_size(args.max_io_size)?;
+    nexus.set_prefer_local_reads(args.prefer_local_reads).await;
+    nexus.set_child_io_timeout(Duration::from_micros(args.child_io_timeout_us));
+    if args.child_probe_interval_ms > 0 {
+        nexus.set_child_probe_interval(Duration::from_millis(
+            args.child_probe_interval_ms,
+        ));
+    }
+    if args.write_cache {
+        nexus.enable_write_cache(match args.write_cache_size {
+            0 => nexus::NEXUS_WRITE_CACHE_DEFAULT_SIZE,
+            size => size,
+        })?;
+    }
+    Ok(())
+}
+
+/// Default time to wait for the other children of a nexus to sync, before
+/// removing a child.
+const CHILD_EVACUATION_TIMEOUT: Duration = Duration::from_secs(3600);
+
 /// RPC service for mayastor nexus operations
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -135,6 +274,43 @@ impl From<NexusStatus> for NexusState {
     }
 }
 
+impl From<NexusStatusReason> for NexusStateReason {
+    fn from(reason: NexusStatusReason) -> Self {
+        match reason {
+            NexusStatusReason::None => Self::None,
+            NexusStatusReason::Initializing => Self::Initializing,
+            NexusStatusReason::Closed => Self::Closed,
+            NexusStatusReason::NoChildren => Self::NoChildren,
+            NexusStatusReason::NoHealthyChild => Self::NoHealthyChild,
+            NexusStatusReason::ChildrenUnhealthy {
+                ..
+            } => Self::ChildrenUnhealthy,
+            NexusStatusReason::QuorumLost {
+                ..
+            } => Self::QuorumLost,
+            NexusStatusReason::Shutdown(NexusShutdownReason::Requested) => {
+                Self::ShutdownRequested
+            }
+            NexusStatusReason::Shutdown(
+                NexusShutdownReason::ReservationConflict,
+            ) => Self::ReservationConflict,
+            NexusStatusReason::Shutdown(
+                NexusShutdownReason::PersistenceFailure,
+            ) => Self::PersistenceFailure,
+        }
+    }
+}
+
+impl From<rebuild::RebuildPriority> for RebuildPriority {
+    fn from(priority: rebuild::RebuildPriority) -> Self {
+        match priority {
+            rebuild::RebuildPriority::Low => Self::Low,
+            rebuild::RebuildPriority::Normal => Self::Normal,
+            rebuild::RebuildPriority::High => Self::High,
+        }
+    }
+}
+
 fn map_fault_reason(r: FaultReason) -> ChildStateReason {
     use ChildStateReason::*;
 
@@ -195,6 +371,22 @@ impl From<RebuildStats> for RebuildStatsResponse {
             tasks_active: stats.tasks_active,
             is_partial: stats.is_partial,
             start_time: Some(stats.start_time.into()),
+            is_no_space: stats.is_no_space,
+            no_space_count: stats.no_space_count,
+            sources: stats
+                .sources
+                .into_iter()
+                .map(|s| RebuildSourceStats {
+                    uri: s.uri,
+                    bytes_read: s.bytes_read,
+                })
+                .collect(),
+            throughput: stats.throughput,
+            avg_throughput: stats.avg_throughput,
+            eta: stats.eta.and_then(|d| TryInto::try_into(d).ok()),
+            cor_hits: stats.copy_on_read.map(|c| c.hits),
+            cor_misses: stats.copy_on_read.map(|c| c.misses),
+            cor_populated: stats.copy_on_read.map(|c| c.populated),
         }
     }
 }
@@ -208,6 +400,7 @@ impl From<RebuildState> for mayastor_api::v1::nexus::RebuildJobState {
             RebuildState::Paused => RebuildJobState::Paused,
             RebuildState::Failed => RebuildJobState::Failed,
             RebuildState::Completed => RebuildJobState::Completed,
+            RebuildState::NoSpace => RebuildJobState::NoSpace,
         }
     }
 }
@@ -227,6 +420,7 @@ impl From<HistoryRecord> for RebuildHistoryRecord {
             end_time: Some(record.end_time.into()),
             child_uri: record.child_uri.clone(),
             src_uri: record.src_uri.clone(),
+            error: record.error.clone(),
         }
     }
 }
@@ -286,32 +480,227 @@ impl TryFrom<NvmePreemptionConv> for nexus::NexusNvmePreemption {
     }
 }
 
-/// Look up a nexus by uuid
+struct NexusFlushTargetConv(i32);
+impl TryFrom<NexusFlushTargetConv> for nexus::NexusFlushTarget {
+    type Error = tonic::Status;
+    fn try_from(value: NexusFlushTargetConv) -> Result<Self, Self::Error> {
+        match NexusFlushTarget::from_i32(value.0) {
+            Some(NexusFlushTarget::FlushAll) => {
+                Ok(nexus::NexusFlushTarget::All)
+            }
+            Some(NexusFlushTarget::FlushQuorum) => {
+                Ok(nexus::NexusFlushTarget::Quorum)
+            }
+            None => Err(tonic::Status::invalid_argument(format!(
+                "Invalid flush target {}",
+                value.0
+            ))),
+        }
+    }
+}
+
+impl From<nexus::NexusFlushTarget> for NexusFlushTarget {
+    fn from(value: nexus::NexusFlushTarget) -> Self {
+        match value {
+            nexus::NexusFlushTarget::All => Self::FlushAll,
+            nexus::NexusFlushTarget::Quorum => Self::FlushQuorum,
+        }
+    }
+}
+
+struct NexusEnospcPolicyConv(i32);
+impl TryFrom<NexusEnospcPolicyConv> for nexus::NexusEnospcPolicy {
+    type Error = tonic::Status;
+    fn try_from(value: NexusEnospcPolicyConv) -> Result<Self, Self::Error> {
+        match NexusEnospcPolicy::from_i32(value.0) {
+            Some(NexusEnospcPolicy::Fault) => {
+                Ok(nexus::NexusEnospcPolicy::Fault)
+            }
+            Some(NexusEnospcPolicy::Freeze) => {
+                Ok(nexus::NexusEnospcPolicy::Freeze)
+            }
+            None => Err(tonic::Status::invalid_argument(format!(
+                "Invalid ENOSPC policy {}",
+                value.0
+            ))),
+        }
+    }
+}
+
+struct RebuildPriorityConv(i32);
+impl TryFrom<RebuildPriorityConv> for rebuild::RebuildPriority {
+    type Error = tonic::Status;
+    fn try_from(value: RebuildPriorityConv) -> Result<Self, Self::Error> {
+        match RebuildPriority::from_i32(value.0) {
+            Some(RebuildPriority::Low) => Ok(rebuild::RebuildPriority::Low),
+            Some(RebuildPriority::Normal) => {
+                Ok(rebuild::RebuildPriority::Normal)
+            }
+            Some(RebuildPriority::High) => Ok(rebuild::RebuildPriority::High),
+            None => Err(tonic::Status::invalid_argument(format!(
+                "Invalid rebuild priority {}",
+                value.0
+            ))),
+        }
+    }
+}
+
+/// Look up a nexus by uuid or name. An identifier which is the name of a
+/// nexus and the uuid of another one is rejected as ambiguous.
 pub fn nexus_lookup<'n>(
-    uuid: &str,
+    id: &str,
 ) -> Result<Pin<&'n mut nexus::Nexus<'n>>, nexus::Error> {
-    if let Some(nexus) = nexus_lookup_uuid_mut(uuid) {
-        Ok(nexus)
-    } else {
-        Err(nexus::Error::NexusNotFound {
-            name: uuid.to_owned(),
+    nexus_lookup_id_mut(id)?.ok_or_else(|| nexus::Error::NexusNotFound {
+        name: id.to_owned(),
+    })
+}
+
+/// Resolves a nexus name or uuid to the uuid of the nexus, so that the
+/// serialization key, the generation and the tombstone of a nexus are the
+/// same however the caller addresses it. Unknown ids are returned as is.
+async fn canonical_nexus_uuid(id: String) -> Result<String, Status> {
+    let rx = rpc_submit::<_, _, nexus::Error>(async move {
+        Ok(match nexus_lookup(&id) {
+            Ok(nexus) => nexus.uuid().to_string(),
+            Err(_) => id,
         })
+    })?;
+
+    rx.await
+        .map_err(|_| Status::cancelled("cancelled"))?
+        .map_err(Status::from)
+}
+
+/// Checks that the nexus can be destroyed. A nexus which is still being
+/// created cannot be destroyed.
+fn nexus_destroy_check(n: &nexus::Nexus) -> Result<(), nexus::Error> {
+    if *n.state.lock() == nexus::NexusState::Init {
+        return Err(nexus::Error::NexusInitialising {
+            name: n.nexus_name().to_string(),
+        });
     }
+    Ok(())
 }
 
 /// Destruction of the nexus. Returns NotFound error for invalid uuid.
-pub async fn nexus_destroy(uuid: &str) -> Result<(), nexus::Error> {
+/// On success, returns the completed teardown phases.
+pub async fn nexus_destroy(
+    uuid: &str,
+) -> Result<Vec<nexus::NexusTeardownStep>, nexus::Error> {
     let n = nexus_lookup(uuid).map_err(|error| {
         if let Ok(uuid) = uuid::Uuid::parse_str(uuid) {
             NexusPtpl::new(uuid).destroy().ok();
         }
         error
     })?;
-    n.destroy().await
+    nexus_destroy_check(&n)?;
+    n.destroy_ext(false).await
+}
+
+impl From<nexus::NexusChildPreflight> for ChildValidation {
+    fn from(c: nexus::NexusChildPreflight) -> Self {
+        Self {
+            valid: c.ok(),
+            uri: c.uri,
+            reachable: c.reachable,
+            num_blocks: c.num_blocks,
+            block_size: c.block_size,
+            reservation: c.reservation.map(|r| ChildReservation {
+                resv_type: r.resv_type as u32,
+                key: r.key,
+                host_id: hex::encode(r.host_id),
+                conflict: r.conflict,
+            }),
+            errors: c.errors,
+        }
+    }
+}
+
+impl From<nexus::NexusPreflight> for ValidateNexusConfigResponse {
+    fn from(p: nexus::NexusPreflight) -> Self {
+        Self {
+            valid: p.ok(),
+            block_size: p.block_size,
+            errors: p.errors,
+            children: p.children.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<nexus::NexusTeardownStep> for NexusTeardownPhase {
+    fn from(s: nexus::NexusTeardownStep) -> Self {
+        Self {
+            phase: s.phase.to_string(),
+            duration: s.elapsed.try_into().ok(),
+        }
+    }
+}
+
+impl From<nexus::NexusIoErrorStats> for NexusIoErrorStats {
+    fn from(s: nexus::NexusIoErrorStats) -> Self {
+        Self {
+            media: s.media,
+            transport: s.transport,
+            timeout: s.timeout,
+            other: s.other,
+            retried: s.retried,
+        }
+    }
+}
+
+impl From<nexus::NexusIoCopyStats> for NexusIoCopyStats {
+    fn from(s: nexus::NexusIoCopyStats) -> Self {
+        Self {
+            zero_copy_ios: s.zero_copy_ios,
+            zero_copy_bytes: s.zero_copy_bytes,
+            sgl_segments: s.sgl_segments,
+            max_sgl_segments: s.max_sgl_segments,
+            buffer_allocations: s.buffer_allocations,
+            copied_ios: s.copied_ios,
+            copied_bytes: s.copied_bytes,
+        }
+    }
+}
+
+impl From<nexus::NexusIoSplitStats> for NexusIoSplitStats {
+    fn from(s: nexus::NexusIoSplitStats) -> Self {
+        Self {
+            max_io_size: s.max_io_size,
+            split_ios: s.split_ios,
+            child_ios: s.child_ios,
+        }
+    }
+}
+
+impl From<nexus::NexusWriteCacheStats> for NexusWriteCacheStats {
+    fn from(s: nexus::NexusWriteCacheStats) -> Self {
+        Self {
+            capacity: s.capacity,
+            dirty_bytes: s.dirty_bytes,
+            cached_writes: s.cached_writes,
+            bypassed_writes: s.bypassed_writes,
+            waits: s.waits,
+            errors: s.errors,
+        }
+    }
+}
+
+impl From<nexus::NexusIoLogJournalStats> for NexusIoLogJournalStats {
+    fn from(s: nexus::NexusIoLogJournalStats) -> Self {
+        Self {
+            device: s.device,
+            healthy: s.healthy,
+            capacity_slots: s.capacity_slots,
+            used_slots: s.used_slots,
+            recovered_slots: s.recovered_slots,
+            flushes: s.flushes,
+            flush_errors: s.flush_errors,
+        }
+    }
 }
 
 impl<'c> NexusChild<'c> {
-    async fn to_grpc_v1(&self) -> Child {
+    async fn to_grpc_v1(&self, reader: bool) -> Child {
         let (s, r) = map_child_state(self);
         Child {
             uri: self.uri().to_string(),
@@ -321,6 +710,28 @@ impl<'c> NexusChild<'c> {
             device_name: self.get_device_name(),
             fault_timestamp: self.fault_timestamp().map(|d| d.into()),
             has_io_log: self.has_io_log(),
+            rebuild_scheduled_at: self
+                .rebuild_scheduled_at()
+                .map(|t| SystemTime::from(t).into()),
+            is_local: self.is_local(),
+            reader,
+            paths: self
+                .paths()
+                .into_iter()
+                .map(|p| ChildPath {
+                    address: format!("{}:{}", p.traddr, p.svcid),
+                    active: p.active,
+                })
+                .collect(),
+            max_io_size: self.max_io_size(),
+            nvme_params: self.nvme_params().map(|p| ChildNvmeParams {
+                io_queue_size: p.io_queue_size,
+                num_io_queues: p.num_io_queues,
+                mdts: p.mdts as u32,
+                max_xfer_size: p.max_xfer_size,
+                keep_alive_timeout_ms: p.keep_alive_timeout_ms,
+                controller_id: p.cntlid as u32,
+            }),
         }
     }
 }
@@ -342,25 +753,95 @@ impl<'n> nexus::Nexus<'n> {
             }
         }
 
+        let (status, reason) = self.status_with_reason();
+
         Nexus {
             name: self.name.clone(),
             uuid: self.uuid().to_string(),
             size: self.req_size(),
-            state: NexusState::from(self.status()) as i32,
+            state: NexusState::from(status) as i32,
+            state_reason: NexusStateReason::from(reason) as i32,
+            state_message: reason.to_string(),
             device_uri: self.get_share_uri().unwrap_or_default(),
             children: {
                 let mut children =
                     Vec::with_capacity(self.children_iter().count());
                 for child in self.children_iter() {
-                    children.push(child.to_grpc_v1().await);
+                    children.push(
+                        child.to_grpc_v1(self.is_child_reader(child)).await,
+                    );
                 }
                 children
             },
             rebuilds: self.count_rebuild_jobs() as u32,
             ana_state: ana_state as i32,
+            ana_group: self.ana_group(),
             allowed_hosts: self.allowed_hosts(),
+            io_errors: Some(self.io_error_stats().into()),
+            io_copies: Some(self.io_copy_stats().into()),
+            io_splits: Some(self.io_split_stats().into()),
+            max_io_size: self.max_io_size(),
+            copy_on_read: self.copy_on_read(),
+            striped_rebuild: self.striped_rebuild(),
+            rebuild_segment_size: self.rebuild_segments().0,
+            rebuild_tasks: self.rebuild_segments().1 as u32,
+            prefer_local_reads: self.prefer_local_reads(),
+            child_io_timeout_us: self
+                .child_io_timeout()
+                .map_or(0, |t| t.as_micros() as u64),
+            child_probe_interval_ms: self
+                .child_probe_interval()
+                .map_or(0, |t| t.as_millis() as u64),
+            write_cache: self.write_cache_stats().map(Into::into),
+            volatile_write_cache: self.volatile_write_cache(),
+            flush_target: NexusFlushTarget::from(
+                self.flush_policy().flush_target,
+            ) as i32,
+            write_through: self.flush_policy().write_through,
+            alias: self.alias(),
+            io_log_journal: self.io_log_journal_stats().map(Into::into),
+            generation: ResourceGeneration::current(
+                ResourceKind::Nexus,
+                &self.uuid().to_string(),
+            ),
+            deletion_deadline: Tombstones::deadline(
+                ResourceKind::Nexus,
+                &self.uuid().to_string(),
+            )
+            .map(|t| SystemTime::from(t).into()),
+        }
+    }
+}
+
+/// Checks if the nexus passes the filters of a list request: it must have a
+/// child with the given URI or device name, be in the given state, and be
+/// published or not, as requested. Nexuses marked for deletion are only
+/// listed on request.
+fn nexus_matches(nexus: &nexus::Nexus<'_>, args: &ListNexusOptions) -> bool {
+    if !args.include_marked
+        && Tombstones::is_marked(ResourceKind::Nexus, &nexus.uuid().to_string())
+    {
+        return false;
+    }
+    if let Some(child) = &args.child {
+        if !nexus.children_iter().any(|c| {
+            c.uri() == child.as_str()
+                || c.get_device_name().as_ref() == Some(child)
+        }) {
+            return false;
         }
     }
+    if let Some(state) = args.state {
+        if NexusState::from(nexus.status()) as i32 != state {
+            return false;
+        }
+    }
+    if let Some(published) = args.published {
+        if matches!(nexus.shared(), Some(Protocol::Nvmf)) != published {
+            return false;
+        }
+    }
+    true
 }
 
 /// Add child to nexus. Normally this would have been part of grpc method
@@ -368,6 +849,7 @@ impl<'n> nexus::Nexus<'n> {
 /// So we implement it as a separate function.
 async fn nexus_add_child(
     args: &AddChildNexusRequest,
+    priority: Option<rebuild::RebuildPriority>,
 ) -> Result<Nexus, nexus::Error> {
     let mut n = nexus_lookup(&args.uuid)?;
     if n.contains_child_uri(&args.uri) || {
@@ -384,7 +866,66 @@ async fn nexus_add_child(
     debug!("Adding child {} to nexus {} ...", args.uri, args.uuid);
     // For that we need api to check existence of child by name (not uri that
     // contain parameters that may change).
-    n.as_mut().add_child(&args.uri, args.norebuild).await?;
+    let rebuild_at = match args.rebuild_at.clone() {
+        Some(_) if args.seed_snapshot.is_some() => {
+            return Err(nexus::Error::InvalidArguments {
+                name: args.uuid.clone(),
+                args: "a seeded child cannot have its rebuild deferred"
+                    .to_string(),
+            });
+        }
+        Some(ts) => {
+            let at = SystemTime::try_from(ts).map_err(|e| {
+                nexus::Error::InvalidArguments {
+                    name: args.uuid.clone(),
+                    args: format!("invalid rebuild time: {e}"),
+                }
+            })?;
+            Some(DateTime::<Utc>::from(at))
+        }
+        None => None,
+    };
+    if args.rebuild_source.is_some() {
+        n.set_rebuild_source(&args.uri, args.rebuild_source.as_deref())?;
+    }
+    if priority.is_some() {
+        n.set_rebuild_priority(&args.uri, priority);
+    }
+    let res = match &args.seed_snapshot {
+        Some(snapshot_uuid) => {
+            n.as_mut().add_child_seeded(&args.uri, snapshot_uuid).await
+        }
+        None => {
+            n.as_mut()
+                .add_child(&args.uri, args.norebuild || rebuild_at.is_some())
+                .await
+        }
+    };
+    if let Err(e) = res {
+        n.set_rebuild_source(&args.uri, None)?;
+        n.set_rebuild_priority(&args.uri, None);
+        return Err(e);
+    }
+    if let Some(at) = rebuild_at {
+        if let Err(e) = n.schedule_rebuild(&args.uri, at) {
+            // The child is not left attached without its rebuild.
+            if let Err(error) = n.as_mut().remove_child(&args.uri).await {
+                error!(
+                    "{n:?}: failed to remove child '{}' whose rebuild could \
+                    not be scheduled: {}",
+                    args.uri,
+                    error.verbose()
+                );
+            }
+            n.set_rebuild_source(&args.uri, None)?;
+            n.set_rebuild_priority(&args.uri, None);
+            return Err(e);
+        }
+    }
+    if args.max_io_size > 0 {
+        n.set_child_max_io_size(&args.uri, args.max_io_size)?;
+    }
+    ResourceGeneration::bump(ResourceKind::Nexus, &n.uuid().to_string());
     Ok(n.into_grpc().await)
 }
 
@@ -403,77 +944,209 @@ impl NexusRpc for NexusService {
             let resv_type = NvmeReservationConv(args.resv_type).try_into()?;
             let preempt_policy =
                 NvmePreemptionConv(args.preempt_policy).try_into()?;
+            let enospc_policy: nexus::NexusEnospcPolicy =
+                NexusEnospcPolicyConv(args.enospc_policy).try_into()?;
+            let rx = rpc_submit(create_nexus_from_request(
+                args,
+                resv_type,
+                preempt_policy,
+                enospc_policy,
+            ))?;
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|nexus| {
+                    Response::new(CreateNexusResponse {
+                        nexus: Some(nexus),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn recreate_nexus_from_store(
+        &self,
+        request: Request<RecreateNexusFromStoreRequest>,
+    ) -> GrpcResult<RecreateNexusFromStoreResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let Some(mut args) = request.into_inner().nexus else {
+            return Err(Status::invalid_argument("missing nexus arguments"));
+        };
+
+        self.serialized(ctx, args.uuid.clone(), true, async move {
+            trace!("{:?}", args);
+            let resv_type = NvmeReservationConv(args.resv_type).try_into()?;
+            let preempt_policy =
+                NvmePreemptionConv(args.preempt_policy).try_into()?;
+            let enospc_policy: nexus::NexusEnospcPolicy =
+                NexusEnospcPolicyConv(args.enospc_policy).try_into()?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
-                // check for nexus exists, uuid & name
-                if let Some(_n) = nexus::nexus_lookup(&args.name) {
-                    return Err(nexus::Error::NameExists {
+                if !PersistentStore::enabled() {
+                    return Err(nexus::Error::NexusCreate {
                         name: args.name.clone(),
+                        reason: "there is no persistent store".to_string(),
                     });
                 }
-                if let Ok(_n) = nexus_lookup(&args.uuid) {
-                    return Err(nexus::Error::UuidExists {
-                        uuid: args.uuid.clone(),
-                        nexus: args.name.clone(),
+                let key = match args.nexus_info_key.as_str() {
+                    "" => args.uuid.clone(),
+                    key => key.to_string(),
+                };
+                let plan = NexusInfo::load(&key, &args.name)
+                    .await?
+                    .recovery_plan(&args.children);
+                for e in &plan.excluded {
+                    warn!(
+                        "Recreating nexus {}: excluding child {:?} \
+                        ({:?}): {}",
+                        args.uuid, e.uri, e.uuid, e.reason
+                    );
+                }
+                if plan.children.is_empty() {
+                    return Err(nexus::Error::NexusCreate {
+                        name: args.name.clone(),
+                        reason: "none of the given children is recorded as \
+                            healthy"
+                            .to_string(),
                     });
                 }
 
-                // If the control plane has supplied a key, use it to store
-                // the NexusInfo.
-                let nexus_info_key = if args.nexus_info_key.is_empty() {
-                    None
-                } else {
-                    Some(args.nexus_info_key.to_string())
-                };
-
-                nexus::nexus_create_v2(
-                    &args.name,
-                    args.size,
-                    &args.uuid,
-                    nexus::NexusNvmeParams {
-                        min_cntlid: args.min_cntl_id as u16,
-                        max_cntlid: args.max_cntl_id as u16,
-                        resv_key: args.resv_key,
-                        preempt_key: match args.preempt_key {
-                            0 => None,
-                            k => std::num::NonZeroU64::new(k),
-                        },
-                        resv_type,
-                        preempt_policy,
-                    },
-                    &args.children,
-                    nexus_info_key,
+                args.children = plan.children;
+                let nexus = create_nexus_from_request(
+                    args,
+                    resv_type,
+                    preempt_policy,
+                    enospc_policy,
                 )
                 .await?;
-                let nexus = nexus_lookup(&args.uuid)?;
-                nexus.event(EventAction::Create).generate();
-                info!("Created nexus {}/{}", &args.name, &args.uuid);
-                Ok(nexus.into_grpc().await)
+                Ok(RecreateNexusFromStoreResponse {
+                    nexus: Some(nexus),
+                    excluded: plan
+                        .excluded
+                        .into_iter()
+                        .map(|e| ExcludedNexusChild {
+                            uri: e.uri.unwrap_or_default(),
+                            uuid: e.uuid.unwrap_or_default(),
+                            reason: e.reason,
+                        })
+                        .collect(),
+                })
             })?;
             rx.await
                 .map_err(|_| Status::cancelled("cancelled"))?
                 .map_err(Status::from)
-                .map(|nexus| {
-                    Response::new(CreateNexusResponse {
-                        nexus: Some(nexus),
-                    })
-                })
+                .map(Response::new)
         })
         .await
     }
 
+    async fn validate_nexus_config(
+        &self,
+        request: Request<ValidateNexusConfigRequest>,
+    ) -> GrpcResult<ValidateNexusConfigResponse> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+        let resv_type = NvmeReservationConv(args.resv_type).try_into()?;
+        let preempt_policy =
+            NvmePreemptionConv(args.preempt_policy).try_into()?;
+
+        let rx = rpc_submit::<_, _, nexus::Error>(async move {
+            let nvme_params = nexus::NexusNvmeParams {
+                resv_key: args.resv_key,
+                preempt_key: std::num::NonZeroU64::new(args.preempt_key),
+                resv_type,
+                preempt_policy,
+                block_size: args.block_size,
+                ..Default::default()
+            };
+            let preflight =
+                nexus::nexus_preflight(args.size, &args.children, &nvme_params)
+                    .await;
+            Ok(ValidateNexusConfigResponse::from(preflight))
+        })?;
+
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
     #[named]
     async fn destroy_nexus(
         &self,
         request: Request<DestroyNexusRequest>,
-    ) -> GrpcResult<()> {
+    ) -> GrpcResult<DestroyNexusResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), true, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.uuid,
+                args.expected_generation,
+            )?;
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                trace!("{:?}", args);
+                if args.validate_only || args.mark_for_deletion {
+                    nexus_destroy_check(&nexus_lookup(&args.uuid)?)?;
+                }
+                if args.validate_only {
+                    return Ok(DestroyNexusResponse {
+                        teardown: Vec::new(),
+                    });
+                }
+                if args.mark_for_deletion {
+                    Tombstones::mark(ResourceKind::Nexus, &args.uuid);
+                    ResourceGeneration::bump(ResourceKind::Nexus, &args.uuid);
+                    return Ok(DestroyNexusResponse {
+                        teardown: Vec::new(),
+                    });
+                }
+                let steps = nexus_destroy(&args.uuid).await?;
+                Tombstones::forget(ResourceKind::Nexus, &args.uuid);
+                ResourceGeneration::forget(ResourceKind::Nexus, &args.uuid);
+                Ok(DestroyNexusResponse {
+                    teardown: if args.verbose {
+                        steps.into_iter().map(Into::into).collect()
+                    } else {
+                        Vec::new()
+                    },
+                })
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(Response::new)
+        })
+        .await
+    }
+
+    #[named]
+    async fn restore_nexus(
+        &self,
+        request: Request<RestoreNexusRequest>,
+    ) -> GrpcResult<RestoreNexusResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            if !Tombstones::is_marked(ResourceKind::Nexus, &args.uuid) {
+                return Err(Status::not_found(format!(
+                    "nexus {} is not marked for deletion",
+                    args.uuid
+                )));
+            }
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
-                nexus_destroy(&args.uuid).await?;
-                Ok(())
+                let nexus = nexus_lookup(&args.uuid)?;
+                Tombstones::restore(ResourceKind::Nexus, &args.uuid);
+                ResourceGeneration::bump(ResourceKind::Nexus, &args.uuid);
+                Ok(RestoreNexusResponse {
+                    nexus: Some(nexus.into_grpc().await),
+                })
             })?;
 
             rx.await
@@ -490,12 +1163,19 @@ impl NexusRpc for NexusService {
         request: Request<ShutdownNexusRequest>,
     ) -> GrpcResult<ShutdownNexusResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.uuid,
+                args.expected_generation,
+            )?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
                 nexus_lookup(&args.uuid)?.shutdown().await?;
+                ResourceGeneration::bump(ResourceKind::Nexus, &args.uuid);
 
                 Ok(ShutdownNexusResponse {
                     nexus: Some(nexus_lookup(&args.uuid)?.into_grpc().await),
@@ -519,16 +1199,16 @@ impl NexusRpc for NexusService {
 
         let rx = rpc_submit::<_, _, nexus::Error>(async move {
             let mut nexus_list: Vec<Nexus> = Vec::new();
-            if let Some(name) = args.name {
-                if let Some(nexus) = nexus::nexus_lookup(&name) {
-                    add_nexus(nexus_list.as_mut(), nexus).await;
+            if let Some(name) = &args.name {
+                if let Some(nexus) = nexus::nexus_lookup(name) {
+                    add_nexus(nexus_list.as_mut(), nexus, &args).await;
                 }
-            } else if let Some(uuid) = args.uuid {
-                let nexus = nexus_lookup(&uuid)?;
-                add_nexus(nexus_list.as_mut(), &nexus).await;
+            } else if let Some(uuid) = &args.uuid {
+                let nexus = nexus_lookup(uuid)?;
+                add_nexus(nexus_list.as_mut(), &nexus, &args).await;
             } else {
                 for nexus in nexus::nexus_iter() {
-                    add_nexus(nexus_list.as_mut(), nexus).await;
+                    add_nexus(nexus_list.as_mut(), nexus, &args).await;
                 }
             }
 
@@ -539,8 +1219,11 @@ impl NexusRpc for NexusService {
             async fn add_nexus(
                 nexus_list: &mut Vec<Nexus>,
                 nexus: &nexus::Nexus<'_>,
+                args: &ListNexusOptions,
             ) {
-                if nexus.state.lock().deref() != &nexus::NexusState::Init {
+                if nexus.state.lock().deref() != &nexus::NexusState::Init
+                    && nexus_matches(nexus, args)
+                {
                     nexus_list.push(nexus.into_grpc().await);
                 }
             }
@@ -558,12 +1241,24 @@ impl NexusRpc for NexusService {
         request: Request<AddChildNexusRequest>,
     ) -> GrpcResult<AddChildNexusResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.uuid,
+                args.expected_generation,
+            )?;
+            let priority = args
+                .rebuild_priority
+                .map(|p| {
+                    rebuild::RebuildPriority::try_from(RebuildPriorityConv(p))
+                })
+                .transpose()?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
-                let nexus = nexus_add_child(&args).await?;
+                let nexus = nexus_add_child(&args, priority).await?;
                 info!("Added child to nexus {}", args.uuid);
                 Ok(nexus)
             })?;
@@ -586,9 +1281,15 @@ impl NexusRpc for NexusService {
         request: Request<RemoveChildNexusRequest>,
     ) -> GrpcResult<RemoveChildNexusResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.uuid,
+                args.expected_generation,
+            )?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
                 if nexus_lookup(&args.uuid)?.contains_child_uri(&args.uri) {
@@ -596,7 +1297,23 @@ impl NexusRpc for NexusService {
                         "Removing child {} from nexus {} ...",
                         args.uri, args.uuid
                     );
-                    nexus_lookup(&args.uuid)?.remove_child(&args.uri).await?;
+                    let nexus = nexus_lookup(&args.uuid)?;
+                    if args.min_synced_children > 0 {
+                        let timeout = match args.evacuation_timeout_ms {
+                            0 => CHILD_EVACUATION_TIMEOUT,
+                            ms => Duration::from_millis(ms),
+                        };
+                        nexus
+                            .evacuate_child(
+                                &args.uri,
+                                args.min_synced_children as usize,
+                                timeout,
+                            )
+                            .await?;
+                    } else {
+                        nexus.remove_child(&args.uri).await?;
+                    }
+                    ResourceGeneration::bump(ResourceKind::Nexus, &args.uuid);
                     info!(
                         "Removed child {} from nexus {}",
                         args.uri, args.uuid
@@ -623,15 +1340,22 @@ impl NexusRpc for NexusService {
         request: Request<FaultNexusChildRequest>,
     ) -> GrpcResult<FaultNexusChildResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.uuid,
+                args.expected_generation,
+            )?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
                 debug!("Faulting child {} on nexus {}", args.uri, args.uuid);
                 nexus_lookup(&args.uuid)?
                     .fault_child(&args.uri, FaultReason::OfflinePermanent)
                     .await?;
+                ResourceGeneration::bump(ResourceKind::Nexus, &args.uuid);
                 info!("Faulted child {} on nexus {}", args.uri, args.uuid);
                 Ok(FaultNexusChildResponse {
                     nexus: Some(nexus_lookup(&args.uuid)?.into_grpc().await),
@@ -652,9 +1376,17 @@ impl NexusRpc for NexusService {
         request: Request<PublishNexusRequest>,
     ) -> GrpcResult<PublishNexusResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.uuid,
+                args.expected_generation,
+            )?;
+            let flush_target: nexus::NexusFlushTarget =
+                NexusFlushTargetConv(args.flush_target).try_into()?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
                 debug!("Publishing nexus {} ...", args.uuid);
@@ -685,9 +1417,57 @@ impl NexusRpc for NexusService {
                     });
                 }
 
-                let device_uri = nexus_lookup(&args.uuid)?
-                    .share_ext(share_protocol, key, args.allowed_hosts.clone())
-                    .await?;
+                let identity = NvmeIdentity::parse(
+                    &args.eui64,
+                    &args.nguid,
+                    &args.serial,
+                    &args.model,
+                )
+                .map_err(|reason| {
+                    nexus::Error::InvalidArguments {
+                        name: args.uuid.clone(),
+                        args: reason,
+                    }
+                })?;
+
+                // The flush policy is advertised when the nexus is shared, so
+                // it is set first, and restored with the ANA group if the
+                // nexus cannot be shared.
+                let prev_policy = nexus_lookup(&args.uuid)?.flush_policy();
+                let prev_ana_group = nexus_lookup(&args.uuid)?.ana_group();
+                let shared: Result<String, nexus::Error> = async {
+                    nexus_lookup(&args.uuid)?.set_flush_policy(
+                        nexus::NexusFlushPolicy {
+                            volatile_write_cache: args.volatile_write_cache,
+                            flush_target,
+                            write_through: args.write_through,
+                        },
+                    )?;
+
+                    // Grouped nexuses have their ANA state set together.
+                    nexus_lookup(&args.uuid)?.set_ana_group(args.ana_group)?;
+
+                    nexus_lookup(&args.uuid)?
+                        .share_ext(
+                            share_protocol,
+                            key,
+                            args.allowed_hosts.clone(),
+                            identity,
+                        )
+                        .await
+                }
+                .await;
+                let device_uri = match shared {
+                    Ok(device_uri) => device_uri,
+                    Err(error) => {
+                        if let Ok(nexus) = nexus_lookup(&args.uuid) {
+                            nexus.set_flush_policy(prev_policy).ok();
+                            nexus.set_ana_group(prev_ana_group).ok();
+                        }
+                        return Err(error);
+                    }
+                };
+                ResourceGeneration::bump(ResourceKind::Nexus, &args.uuid);
 
                 info!(
                     "Published nexus {} under {} for {:?}",
@@ -714,14 +1494,24 @@ impl NexusRpc for NexusService {
         request: Request<UnpublishNexusRequest>,
     ) -> GrpcResult<UnpublishNexusResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.uuid,
+                args.expected_generation,
+            )?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 trace!("{:?}", args);
                 let uuid = args.uuid.clone();
+                if args.validate_only {
+                    return Ok(nexus_lookup(&args.uuid)?.into_grpc().await);
+                }
                 debug!("Unpublishing nexus {} ...", uuid);
                 nexus_lookup(&args.uuid)?.unshare_nexus().await?;
+                ResourceGeneration::bump(ResourceKind::Nexus, &args.uuid);
                 info!("Unpublished nexus {}", uuid);
                 Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
             })?;
@@ -744,7 +1534,8 @@ impl NexusRpc for NexusService {
         request: Request<GetNvmeAnaStateRequest>,
     ) -> GrpcResult<GetNvmeAnaStateResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
             let uuid = args.uuid.clone();
@@ -773,7 +1564,8 @@ impl NexusRpc for NexusService {
         request: Request<SetNvmeAnaStateRequest>,
     ) -> GrpcResult<SetNvmeAnaStateResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
             let uuid = args.uuid.clone();
@@ -801,14 +1593,60 @@ impl NexusRpc for NexusService {
     }
 
     #[named]
-    async fn child_operation(
+    async fn set_nvme_ana_group_state(
         &self,
-        request: Request<ChildOperationRequest>,
-    ) -> GrpcResult<ChildOperationResponse> {
+        request: Request<SetNvmeAnaGroupStateRequest>,
+    ) -> GrpcResult<SetNvmeAnaGroupStateResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
         let args = request.into_inner();
 
+        let group = format!("ana-group-{}", args.ana_group);
+        self.serialized(ctx, group, true, async move {
+            debug!(
+                "Setting NVMe ANA state of group {} to {} ...",
+                args.ana_group, args.ana_state
+            );
+
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                let ana_state = nexus::NvmeAnaState::from_i32(args.ana_state)?;
+
+                let nexuses =
+                    nexus::set_ana_group_state(args.ana_group, ana_state)
+                        .await
+                        .into_iter()
+                        .map(|(name, res)| NexusAnaGroupResult {
+                            name,
+                            error: res.err().map(|e| e.to_string()),
+                        })
+                        .collect();
+                Ok(SetNvmeAnaGroupStateResponse {
+                    nexuses,
+                })
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(Response::new)
+        })
+        .await
+    }
+
+    #[named]
+    async fn child_operation(
+        &self,
+        request: Request<ChildOperationRequest>,
+    ) -> GrpcResult<ChildOperationResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
+
         self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
+            ResourceGeneration::check(
+                ResourceKind::Nexus,
+                &args.nexus_uuid,
+                args.expected_generation,
+            )?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
                 info!("{:?}", args);
                 let mut nexus = nexus_lookup(&args.nexus_uuid)?;
@@ -820,7 +1658,15 @@ impl NexusRpc for NexusService {
                             .fault_child(&args.uri, FaultReason::Offline)
                             .await
                     }
-                    1 => nexus.as_mut().online_child(&args.uri).await,
+                    1 => {
+                        if args.rebuild_source.is_some() {
+                            nexus.set_rebuild_source(
+                                &args.uri,
+                                args.rebuild_source.as_deref(),
+                            )?;
+                        }
+                        nexus.as_mut().online_child(&args.uri).await
+                    }
                     2 => {
                         nexus
                             .as_mut()
@@ -838,6 +1684,10 @@ impl NexusRpc for NexusService {
                     }
                     _ => Err(nexus::Error::InvalidKey {}),
                 }?;
+                ResourceGeneration::bump(
+                    ResourceKind::Nexus,
+                    &nexus.uuid().to_string(),
+                );
 
                 Ok(nexus.into_grpc().await)
             })?;
@@ -860,12 +1710,23 @@ impl NexusRpc for NexusService {
         request: Request<StartRebuildRequest>,
     ) -> GrpcResult<StartRebuildResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
 
         self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
             info!("{:?}", args);
+            let priority = args
+                .priority
+                .map(|p| {
+                    rebuild::RebuildPriority::try_from(RebuildPriorityConv(p))
+                })
+                .transpose()?;
             let rx = rpc_submit::<_, _, nexus::Error>(async move {
-                nexus_lookup(&args.nexus_uuid)?
+                let nexus = nexus_lookup(&args.nexus_uuid)?;
+                if priority.is_some() {
+                    nexus.set_rebuild_priority(&args.uri, priority);
+                }
+                nexus
                     .start_rebuild(&args.uri)
                     .await
                     // todo
@@ -891,7 +1752,8 @@ impl NexusRpc for NexusService {
         request: Request<StopRebuildRequest>,
     ) -> GrpcResult<StopRebuildResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
 
         self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
             info!("{:?}", args);
@@ -921,7 +1783,8 @@ impl NexusRpc for NexusService {
         request: Request<PauseRebuildRequest>,
     ) -> GrpcResult<PauseRebuildResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
 
         self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
             info!("{:?}", args);
@@ -951,7 +1814,8 @@ impl NexusRpc for NexusService {
         request: Request<ResumeRebuildRequest>,
     ) -> GrpcResult<ResumeRebuildResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
 
         self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
             info!("{:?}", args);
@@ -980,7 +1844,8 @@ impl NexusRpc for NexusService {
         request: Request<RebuildStateRequest>,
     ) -> GrpcResult<RebuildStateResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
 
         self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
             info!("{:?}", args);
@@ -1004,7 +1869,8 @@ impl NexusRpc for NexusService {
         request: Request<RebuildStatsRequest>,
     ) -> GrpcResult<RebuildStatsResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
 
         self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
             info!("{:?}", args);
@@ -1022,13 +1888,41 @@ impl NexusRpc for NexusService {
         .await
     }
 
+    #[named]
+    async fn get_rebuild_progress(
+        &self,
+        request: Request<RebuildProgressRequest>,
+    ) -> GrpcResult<RebuildProgressResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.nexus_uuid = canonical_nexus_uuid(args.nexus_uuid).await?;
+
+        self.serialized(ctx, args.nexus_uuid.clone(), false, async move {
+            trace!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                nexus_lookup(&args.nexus_uuid)?
+                    .rebuild_progress(&args.uri)
+                    .await
+                    .map(|progress| RebuildProgressResponse {
+                        progress,
+                    })
+            })?;
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(Response::new)
+        })
+        .await
+    }
+
     #[named]
     async fn get_rebuild_history(
         &self,
         request: Request<RebuildHistoryRequest>,
     ) -> GrpcResult<RebuildHistoryResponse> {
         let ctx = GrpcClientContext::new(&request, function_name!());
-        let args = request.into_inner();
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
 
         self.serialized(ctx, args.uuid.clone(), false, async move {
             trace!("{:?}", args);
@@ -1054,6 +1948,356 @@ impl NexusRpc for NexusService {
         .await
     }
 
+    async fn list_pending_rebuilds(
+        &self,
+        request: Request<ListPendingRebuildsRequest>,
+    ) -> GrpcResult<ListPendingRebuildsResponse> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+        let rx = rpc_submit::<_, _, nexus::Error>(async move {
+            let pending = RebuildScheduler::pending()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(position, p)| {
+                    // The nexus may have been destroyed in the meantime.
+                    let uuid =
+                        nexus::nexus_lookup(&p.nexus)?.uuid().to_string();
+                    if matches!(&args.nexus_uuid, Some(u) if *u != uuid) {
+                        return None;
+                    }
+                    Some(PendingRebuild {
+                        nexus_name: p.nexus,
+                        nexus_uuid: uuid,
+                        src_uri: p.src_uri,
+                        dst_uri: p.dst_uri,
+                        priority: RebuildPriority::from(p.priority) as i32,
+                        healthy_children: p.healthy_children as u32,
+                        queued_at: Some(p.queued_at.into()),
+                        position: position as u32,
+                        nexus_limited: p.nexus_limited,
+                    })
+                })
+                .collect();
+            Ok(ListPendingRebuildsResponse {
+                pending,
+            })
+        })?;
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    #[named]
+    async fn freeze_nexus(
+        &self,
+        request: Request<FreezeNexusRequest>,
+    ) -> GrpcResult<FreezeNexusResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let timeout = args
+                .timeout
+                .and_then(|t| std::time::Duration::try_from(t).ok())
+                .unwrap_or(nexus::NEXUS_FREEZE_MAX_TIMEOUT);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                let freeze = nexus_lookup(&args.uuid)?.freeze(timeout).await?;
+                let nexus = nexus_lookup(&args.uuid)?.into_grpc().await;
+                Ok(FreezeNexusResponse {
+                    nexus: Some(nexus),
+                    frozen_at: Some(freeze.frozen_at.into()),
+                    deadline: Some(freeze.deadline.into()),
+                })
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(Response::new)
+        })
+        .await
+    }
+
+    #[named]
+    async fn thaw_nexus(
+        &self,
+        request: Request<ThawNexusRequest>,
+    ) -> GrpcResult<ThawNexusResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                nexus_lookup(&args.uuid)?.thaw().await?;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(ThawNexusResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn flush_nexus(
+        &self,
+        request: Request<FlushNexusRequest>,
+    ) -> GrpcResult<FlushNexusResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                nexus_lookup(&args.uuid)?.flush_children().await?;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(FlushNexusResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn attach_read_cache(
+        &self,
+        request: Request<AttachReadCacheRequest>,
+    ) -> GrpcResult<AttachReadCacheResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                let line_size = match args.line_size {
+                    0 => nexus::NEXUS_READ_CACHE_DEFAULT_LINE_SIZE,
+                    size => size,
+                };
+                nexus_lookup(&args.uuid)?
+                    .attach_read_cache(&args.device, line_size)
+                    .await?;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(AttachReadCacheResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn detach_read_cache(
+        &self,
+        request: Request<DetachReadCacheRequest>,
+    ) -> GrpcResult<DetachReadCacheResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                nexus_lookup(&args.uuid)?.detach_read_cache().await;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(DetachReadCacheResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn attach_io_log_journal(
+        &self,
+        request: Request<AttachIoLogJournalRequest>,
+    ) -> GrpcResult<AttachIoLogJournalResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                nexus_lookup(&args.uuid)?
+                    .attach_io_log_journal(&args.device)
+                    .await?;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(AttachIoLogJournalResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn detach_io_log_journal(
+        &self,
+        request: Request<DetachIoLogJournalRequest>,
+    ) -> GrpcResult<DetachIoLogJournalResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                nexus_lookup(&args.uuid)?.detach_io_log_journal().await;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(DetachIoLogJournalResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn set_nexus_read_policy(
+        &self,
+        request: Request<SetNexusReadPolicyRequest>,
+    ) -> GrpcResult<SetNexusReadPolicyResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                nexus_lookup(&args.uuid)?
+                    .set_prefer_local_reads(args.prefer_local_reads)
+                    .await;
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(SetNexusReadPolicyResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn set_nexus_max_io_size(
+        &self,
+        request: Request<SetNexusMaxIoSizeRequest>,
+    ) -> GrpcResult<SetNexusMaxIoSizeResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                let nexus = nexus_lookup(&args.uuid)?;
+                match &args.child_uri {
+                    Some(uri) => {
+                        nexus.set_child_max_io_size(uri, args.max_io_size)?
+                    }
+                    None => nexus.set_max_io_size(args.max_io_size)?,
+                }
+                Ok(nexus.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(SetNexusMaxIoSizeResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
+    #[named]
+    async fn update_nexus_listener(
+        &self,
+        request: Request<UpdateNexusListenerRequest>,
+    ) -> GrpcResult<UpdateNexusListenerResponse> {
+        let ctx = GrpcClientContext::new(&request, function_name!());
+        let mut args = request.into_inner();
+        args.uuid = canonical_nexus_uuid(args.uuid).await?;
+
+        self.serialized(ctx, args.uuid.clone(), false, async move {
+            info!("{:?}", args);
+            let rx = rpc_submit::<_, _, nexus::Error>(async move {
+                let device_uri = nexus_lookup(&args.uuid)?
+                    .update_listener(&args.address, args.replace)
+                    .await?;
+
+                info!(
+                    "Updated listener of nexus {} to {}, now published \
+                    under {}",
+                    args.uuid, args.address, device_uri
+                );
+
+                Ok(nexus_lookup(&args.uuid)?.into_grpc().await)
+            })?;
+
+            rx.await
+                .map_err(|_| Status::cancelled("cancelled"))?
+                .map_err(Status::from)
+                .map(|n| {
+                    Response::new(UpdateNexusListenerResponse {
+                        nexus: Some(n),
+                    })
+                })
+        })
+        .await
+    }
+
     async fn list_rebuild_history(
         &self,
         request: Request<ListRebuildHistoryRequest>,
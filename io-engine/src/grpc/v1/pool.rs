@@ -1,16 +1,20 @@
 use crate::{
-    core::Share,
+    core::{ResourceGeneration, ResourceKind, Share},
     grpc::{rpc_submit, GrpcClientContext, GrpcResult, Serializer},
-    lvs::{Error as LvsError, Lvs},
+    lvs::{Error as LvsError, Lvs, PoolCheckFinding, PoolCheckSeverity},
     pool_backend::{PoolArgs, PoolBackend},
 };
 use futures::FutureExt;
 use nix::errno::Errno;
 use std::{convert::TryFrom, fmt::Debug};
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
 use mayastor_api::v1::pool::*;
 
+#[cfg(feature = "malloc-pool-backend")]
+use crate::pool_backend::malloc_pool_disks;
+
 #[derive(Debug)]
 struct UnixStream(tokio::net::UnixStream);
 
@@ -18,11 +22,12 @@ use ::function_name::named;
 use std::panic::AssertUnwindSafe;
 
 /// RPC service for mayastor pool operations
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct PoolService {
     name: String,
-    client_context: tokio::sync::Mutex<Option<GrpcClientContext>>,
+    client_context:
+        std::sync::Arc<tokio::sync::Mutex<Option<GrpcClientContext>>>,
 }
 
 #[async_trait::async_trait]
@@ -89,6 +94,7 @@ impl TryFrom<CreatePoolRequest> for PoolArgs {
             name: args.name,
             disks: args.disks,
             uuid: args.uuid,
+            force: false,
         })
     }
 }
@@ -116,6 +122,7 @@ impl TryFrom<ImportPoolRequest> for PoolArgs {
             name: args.name,
             disks: args.disks,
             uuid: args.uuid,
+            force: args.force,
         })
     }
 }
@@ -130,31 +137,67 @@ impl PoolService {
     pub fn new() -> Self {
         Self {
             name: String::from("PoolSvc"),
-            client_context: tokio::sync::Mutex::new(None),
+            client_context: std::sync::Arc::new(tokio::sync::Mutex::new(None)),
         }
     }
 }
 
+/// Returns the type of the given pool.
+fn pool_type(l: &Lvs) -> PoolType {
+    if l.base_bdev().driver() == "malloc" {
+        PoolType::Malloc
+    } else {
+        PoolType::Lvs
+    }
+}
+
 impl From<Lvs> for Pool {
     fn from(l: Lvs) -> Self {
+        let disks =
+            vec![l.base_bdev().bdev_uri_str().unwrap_or_else(|| "".into())];
         Self {
             uuid: l.uuid(),
             name: l.name().into(),
-            disks: vec![l
-                .base_bdev()
-                .bdev_uri_str()
-                .unwrap_or_else(|| "".into())],
+            configured_disks: l
+                .disk_id()
+                .map_or_else(|| disks.clone(), |d| vec![d.configured]),
+            disks,
             state: PoolState::PoolOnline.into(),
             capacity: l.capacity(),
             used: l.used(),
             committed: l.committed(),
-            pooltype: PoolType::Lvs as i32,
+            commitment_ratio: l.commitment_ratio(),
+            overcommit_limit: l.overcommit_limit().unwrap_or_default(),
+            rebuild_fairness: l.rebuild_fairness(),
+            pooltype: pool_type(&l) as i32,
+            data_protection: Some(l.base_bdev().data_protection().into()),
+            generation: ResourceGeneration::current(
+                ResourceKind::Pool,
+                l.name(),
+            ),
+            disk_devices: l.disk_device().into_iter().collect(),
+        }
+    }
+}
+
+impl From<PoolCheckFinding> for CheckPoolResponse {
+    fn from(f: PoolCheckFinding) -> Self {
+        let severity = match f.severity {
+            PoolCheckSeverity::Info => CheckSeverity::Info,
+            PoolCheckSeverity::Warning => CheckSeverity::Warning,
+            PoolCheckSeverity::Error => CheckSeverity::Error,
+        };
+        Self {
+            severity: severity as i32,
+            message: f.message,
         }
     }
 }
 
 #[tonic::async_trait]
 impl PoolRpc for PoolService {
+    type CheckPoolStream = ReceiverStream<Result<CheckPoolResponse, Status>>;
+
     #[named]
     async fn create_pool(
         &self,
@@ -166,6 +209,24 @@ impl PoolRpc for PoolService {
                 let args = request.into_inner();
                 info!("{:?}", args);
                 match PoolBackend::try_from(args.pooltype)? {
+                    #[cfg(feature = "malloc-pool-backend")]
+                    PoolBackend::Malloc => {
+                        let mut args = args;
+                        args.disks =
+                            malloc_pool_disks(&args.name, &args.disks)?;
+                        let rx = rpc_submit::<_, _, LvsError>(async move {
+                            let pool = Lvs::create_or_import(
+                                PoolArgs::try_from(args)?,
+                            )
+                            .await?;
+                            Ok(Pool::from(pool))
+                        })?;
+
+                        rx.await
+                            .map_err(|_| Status::cancelled("cancelled"))?
+                            .map_err(Status::from)
+                            .map(Response::new)
+                    }
                     PoolBackend::Lvs => {
                         let rx = rpc_submit::<_, _, LvsError>(async move {
                             let pool = Lvs::create_or_import(
@@ -196,6 +257,11 @@ impl PoolRpc for PoolService {
             async move {
                 let args = request.into_inner();
                 info!("{:?}", args);
+                ResourceGeneration::check(
+                    ResourceKind::Pool,
+                    &args.name,
+                    args.expected_generation,
+                )?;
                 let rx = rpc_submit::<_, _, LvsError>(async move {
                     if let Some(pool) = Lvs::lookup(&args.name) {
                         if args.uuid.is_some() && args.uuid != Some(pool.uuid())
@@ -209,7 +275,13 @@ impl PoolRpc for PoolService {
                                 ),
                             });
                         }
-                        pool.destroy().await?;
+                        if !args.validate_only {
+                            pool.destroy().await?;
+                            ResourceGeneration::forget(
+                                ResourceKind::Pool,
+                                &args.name,
+                            );
+                        }
                     } else {
                         return Err(LvsError::PoolNotFound {
                             source: Errno::EINVAL,
@@ -241,6 +313,11 @@ impl PoolRpc for PoolService {
             async move {
                 let args = request.into_inner();
                 info!("{:?}", args);
+                ResourceGeneration::check(
+                    ResourceKind::Pool,
+                    &args.name,
+                    args.expected_generation,
+                )?;
                 let rx = rpc_submit::<_, _, LvsError>(async move {
                     if let Some(pool) = Lvs::lookup(&args.name) {
                         if args.uuid.is_some() && args.uuid != Some(pool.uuid())
@@ -255,6 +332,10 @@ impl PoolRpc for PoolService {
                             });
                         }
                         pool.export().await?;
+                        ResourceGeneration::forget(
+                            ResourceKind::Pool,
+                            &args.name,
+                        );
                     } else {
                         return Err(LvsError::Invalid {
                             source: Errno::EINVAL,
@@ -273,6 +354,54 @@ impl PoolRpc for PoolService {
         .await
     }
 
+    #[named]
+    async fn set_pool_rebuild_fairness(
+        &self,
+        request: Request<SetPoolRebuildFairnessRequest>,
+    ) -> GrpcResult<Pool> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let rx = rpc_submit::<_, _, LvsError>(async move {
+                    let Some(pool) = Lvs::lookup(&args.name) else {
+                        return Err(LvsError::Invalid {
+                            source: Errno::EINVAL,
+                            msg: format!("pool {} not found", args.name),
+                        });
+                    };
+                    if args.uuid.is_some() && args.uuid != Some(pool.uuid()) {
+                        return Err(LvsError::Invalid {
+                            source: Errno::EINVAL,
+                            msg: format!(
+                                "invalid uuid {}, found pool with uuid {}",
+                                args.uuid.unwrap(),
+                                pool.uuid(),
+                            ),
+                        });
+                    }
+                    if let Some(ratio) = args.ratio.filter(|r| *r > 100) {
+                        return Err(LvsError::Invalid {
+                            source: Errno::EINVAL,
+                            msg: format!(
+                                "invalid rebuild fairness ratio {ratio}%"
+                            ),
+                        });
+                    }
+                    pool.set_rebuild_fairness(args.ratio);
+                    Ok(Pool::from(pool))
+                })?;
+
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
     #[named]
     async fn import_pool(
         &self,
@@ -311,25 +440,27 @@ impl PoolRpc for PoolService {
                     Some(pool_type) => pool_type.value,
                     None => PoolType::Lvs as i32,
                 };
-                if pool_type != PoolType::Lvs as i32 {
-                    return Err(tonic::Status::invalid_argument(
-                        "Only pools of Lvs pool type are supported",
-                    ));
-                }
+                PoolBackend::try_from(pool_type)?;
 
                 let rx = rpc_submit::<_, _, LvsError>(async move {
-                    let mut pools = Vec::new();
+                    let mut lvss = Vec::new();
                     if let Some(name) = args.name {
-                        if let Some(l) = Lvs::lookup(&name) {
-                            pools.push(l.into());
-                        }
+                        lvss.extend(Lvs::lookup(&name));
                     } else if let Some(uuid) = args.uuid {
-                        if let Some(l) = Lvs::lookup_by_uuid(&uuid) {
-                            pools.push(l.into());
-                        }
+                        lvss.extend(Lvs::lookup_by_uuid(&uuid));
                     } else {
-                        Lvs::iter().for_each(|l| pools.push(l.into()));
+                        lvss.extend(Lvs::iter());
                     }
+                    // Malloc pools are Lvs pools too: only the listing of
+                    // malloc pools filters the pools.
+                    let pools = lvss
+                        .into_iter()
+                        .filter(|l| {
+                            pool_type != PoolType::Malloc as i32
+                                || self::pool_type(l) == PoolType::Malloc
+                        })
+                        .map(Pool::from)
+                        .collect();
                     Ok(ListPoolsResponse {
                         pools,
                     })
@@ -343,4 +474,49 @@ impl PoolRpc for PoolService {
         )
         .await
     }
+
+    #[named]
+    async fn check_pool(
+        &self,
+        request: Request<CheckPoolRequest>,
+    ) -> Result<Response<Self::CheckPoolStream>, Status> {
+        // The findings are sent with try_send, from the reactor the check
+        // runs on, hence a channel large enough for all of them.
+        let (tx, rx) = tokio::sync::mpsc::channel(1024);
+
+        let pool_svc = self.clone();
+        let tx_cln = tx.clone();
+        let disk = request.get_ref().disk.clone();
+
+        crate::core::spawn(async move {
+            let result = pool_svc
+                .locked(
+                    GrpcClientContext::new(&request, function_name!()),
+                    async move {
+                        let args = request.into_inner();
+                        info!("{:?}", args);
+                        let rx = rpc_submit(async move {
+                            Lvs::check(&args.disk, args.repair, |finding| {
+                                tx_cln
+                                    .try_send(Ok(finding.into()))
+                                    .unwrap_or_else(|e| {
+                                        warn!("Pool check finding lost: {e}")
+                                    });
+                            })
+                            .await
+                        })?;
+                        rx.await
+                            .map_err(|_| Status::cancelled("cancelled"))?
+                            .map_err(Status::from)
+                    },
+                )
+                .await;
+            if let Err(error) = result {
+                tracing::error!("Check of {disk} failed: {error}");
+                tx.send(Err(error)).await.ok();
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
 }
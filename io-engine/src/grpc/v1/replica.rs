@@ -5,22 +5,79 @@ use crate::{
         logical_volume::LogicalVolume,
         Bdev,
         CloneXattrs,
+        NvmeIdentity,
         Protocol,
+        ResourceGeneration,
+        ResourceKind,
         Share,
         ShareProps,
+        SnapshotOps,
         UntypedBdev,
         UpdateProps,
+        VerboseError,
     },
     grpc::{rpc_submit, GrpcClientContext, GrpcResult, Serializer},
-    lvs::{Error as LvsError, Lvol, LvolSpaceUsage, Lvs, LvsLvol},
+    host::tombstone::Tombstones,
+    lvs::{
+        Error as LvsError,
+        Lvol,
+        LvolSpaceUsage,
+        LvolState,
+        LvolUsageChange,
+        LvolUsageSample,
+        LvolUsageThresholds,
+        LvolUsageWatch,
+        Lvs,
+        LvsLvol,
+        ReplicaOwner as LvolOwner,
+        VerifyExtentKind,
+        VerifyOptions,
+        VerifyStats,
+        LVOL_FREEZE_MAX_TIMEOUT,
+    },
+    subsys::{NvmfSharePreflight, NvmfSubsystem},
 };
 use ::function_name::named;
 use futures::FutureExt;
 use mayastor_api::v1::replica::*;
 use nix::errno::Errno;
-use std::{convert::TryFrom, panic::AssertUnwindSafe, pin::Pin};
+use once_cell::sync::Lazy;
+use std::{
+    convert::TryFrom,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    time::{Duration, SystemTime},
+};
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
+/// Default interval between two samples of a replica usage watch.
+const REPLICA_USAGE_WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Lock serializing the replica operations, shared with the operations on
+/// replicas which are not requested over gRPC.
+static REPLICA_LOCK: Lazy<
+    std::sync::Arc<tokio::sync::Mutex<Option<GrpcClientContext>>>,
+> = Lazy::new(Default::default);
+
+/// Checks that a replica with the requested logical block size can be
+/// created on the pool, zero requesting the block size of the pool.
+fn check_block_size(lvs: &Lvs, requested: u64) -> Result<(), LvsError> {
+    let block_size = lvs.base_bdev().block_len() as u64;
+    if requested == 0 || requested == block_size {
+        return Ok(());
+    }
+
+    Err(LvsError::Invalid {
+        source: Errno::EINVAL,
+        msg: format!(
+            "pool {} has a block size of {block_size} bytes, replicas \
+            cannot have a block size of {requested} bytes",
+            lvs.name()
+        ),
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct ReplicaService {
     #[allow(unused)]
@@ -92,6 +149,13 @@ impl From<Lvol> for Replica {
         let usage = l.usage();
         let source_uuid =
             Lvol::get_blob_xattr(&l, CloneXattrs::SourceUuid.name());
+        let snapshots = l.list_snapshot_by_source_uuid();
+        // The clones of a replica are the clones of its snapshots.
+        let num_clones = if l.is_snapshot() {
+            l.list_clones_by_snapshot_uuid().len() as u64
+        } else {
+            snapshots.iter().map(|s| s.num_clones()).sum()
+        };
         Self {
             name: l.name(),
             uuid: l.uuid(),
@@ -106,10 +170,172 @@ impl From<Lvol> for Replica {
             is_snapshot: l.is_snapshot(),
             is_clone: l.is_snapshot_clone().is_some(),
             snapshot_uuid: source_uuid,
+            compressed: l.compressed_bdev().is_some(),
+            compression_ratio: l.compression_ratio().unwrap_or_default(),
+            state: ReplicaState::from(l.state()) as i32,
+            num_snapshots: snapshots.len() as u64,
+            num_clones,
+            num_connections: l.connected_hosts().len() as u32,
+            generation: ResourceGeneration::current(
+                ResourceKind::Replica,
+                &l.uuid(),
+            ),
+            deletion_deadline: Tombstones::deadline(
+                ResourceKind::Replica,
+                &l.uuid(),
+            )
+            .map(|t| SystemTime::from(t).into()),
+            owner: l.owner().map(ReplicaOwner::from),
+        }
+    }
+}
+
+/// Rolls back the creation of a replica which failed once its lvol was
+/// created: destroys the lvol along with its share and its persist through
+/// power loss file, and returns the original error with the outcome.
+async fn rollback_replica_create(lvol: Lvol, error: LvsError) -> LvsError {
+    debug!(
+        "failed to set up created lvol {:?}: {} (destroying)",
+        lvol,
+        error.to_string()
+    );
+    let name = lvol.name();
+    let ptpl = lvol.ptpl();
+    let rollback = match lvol.destroy().await {
+        Ok(_) => {
+            if let Err(e) = ptpl.destroy() {
+                warn!("failed to remove the ptpl file of replica {name}: {e}");
+            }
+            "completed".to_string()
+        }
+        Err(e) => format!("failed: {}", e.verbose()),
+    };
+    LvsError::CreateRollback {
+        source: Box::new(error),
+        name,
+        rollback,
+    }
+}
+
+impl From<ReplicaOwner> for LvolOwner {
+    fn from(o: ReplicaOwner) -> Self {
+        Self {
+            volume_uuid: o.volume_uuid,
+            control_plane: o.control_plane,
+        }
+    }
+}
+
+impl From<LvolOwner> for ReplicaOwner {
+    fn from(o: LvolOwner) -> Self {
+        Self {
+            volume_uuid: o.volume_uuid,
+            control_plane: o.control_plane,
+        }
+    }
+}
+
+impl From<LvolState> for ReplicaState {
+    fn from(s: LvolState) -> Self {
+        match s {
+            LvolState::Online => Self::Online,
+            LvolState::ReadOnly => Self::ReadOnly,
+            LvolState::Maintenance => Self::Maintenance,
+        }
+    }
+}
+
+impl From<ReplicaState> for LvolState {
+    fn from(s: ReplicaState) -> Self {
+        match s {
+            ReplicaState::Online => Self::Online,
+            ReplicaState::ReadOnly => Self::ReadOnly,
+            ReplicaState::Maintenance => Self::Maintenance,
+        }
+    }
+}
+
+impl From<&VerifyStats> for VerifyReplicaResponse {
+    fn from(value: &VerifyStats) -> Self {
+        Self {
+            uuid: value.uuid.clone(),
+            total_bytes: value.total_bytes,
+            verified_bytes: value.verified_bytes,
+            remaining_bytes: value.total_bytes - value.verified_bytes,
+            io_error_bytes: value.io_error_bytes,
+            mismatch_bytes: value.mismatch_bytes,
+            extents: value
+                .extents
+                .iter()
+                .map(|e| VerifyExtent {
+                    offset: e.offset,
+                    length: e.length,
+                    kind: match e.kind {
+                        VerifyExtentKind::IoError => VerifyExtentType::IoError,
+                        VerifyExtentKind::Mismatch => {
+                            VerifyExtentType::Mismatch
+                        }
+                    } as i32,
+                })
+                .collect(),
+            since: TryInto::try_into(value.since).ok(),
+        }
+    }
+}
+
+impl From<LvolUsageChange> for ReplicaUsageChange {
+    fn from(c: LvolUsageChange) -> Self {
+        Self {
+            uuid: c.sample.uuid,
+            name: c.sample.name,
+            pooluuid: c.sample.pool_uuid,
+            usage: Some(c.sample.usage.into()),
+            previous_allocated_bytes: c.previous_allocated_bytes,
         }
     }
 }
 
+impl From<NvmfSharePreflight> for ValidateShareReplicaResponse {
+    fn from(p: NvmfSharePreflight) -> Self {
+        Self {
+            valid: p.ok(),
+            nqn: p.nqn,
+            address: p.address,
+            port: p.port as u32,
+            violations: p
+                .violations
+                .into_iter()
+                .map(|v| ShareViolation {
+                    field: v.field,
+                    message: v.message,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Runs the preflight of sharing the given replica over NVMf on the default
+/// listener, failing with all the violations found.
+fn share_preflight(
+    lvol: &Lvol,
+    allowed_hosts: &[String],
+) -> Result<(), LvsError> {
+    let preflight =
+        NvmfSubsystem::share_preflight(&lvol.name(), None, None, allowed_hosts);
+    if preflight.ok() {
+        return Ok(());
+    }
+    Err(LvsError::Invalid {
+        source: Errno::EINVAL,
+        msg: preflight
+            .violations
+            .iter()
+            .map(|v| format!("{}: {}", v.field, v.message))
+            .collect::<Vec<_>>()
+            .join("; "),
+    })
+}
+
 impl Default for ReplicaService {
     fn default() -> Self {
         Self::new()
@@ -120,9 +346,16 @@ impl ReplicaService {
     pub fn new() -> Self {
         Self {
             name: String::from("ReplicaSvc"),
-            client_context: std::sync::Arc::new(tokio::sync::Mutex::new(None)),
+            client_context: REPLICA_LOCK.clone(),
         }
     }
+
+    /// Waits for the replica operation in progress, if any, and prevents
+    /// other replica operations until the returned guard is dropped.
+    pub(crate) async fn lock(
+    ) -> tokio::sync::MutexGuard<'static, Option<GrpcClientContext>> {
+        REPLICA_LOCK.lock().await
+    }
 }
 fn filter_replicas_by_replica_type(
     replica_list: Vec<Replica>,
@@ -155,45 +388,81 @@ fn filter_replicas_by_replica_type(
 }
 #[tonic::async_trait]
 impl ReplicaRpc for ReplicaService {
+    type VerifyReplicaStream =
+        ReceiverStream<Result<VerifyReplicaResponse, Status>>;
+    type WatchReplicaUsageStream =
+        ReceiverStream<Result<ReplicaUsageChange, Status>>;
+
     #[named]
     async fn create_replica(
         &self,
         request: Request<CreateReplicaRequest>,
     ) -> GrpcResult<Replica> {
-        self.locked(GrpcClientContext::new(&request, function_name!()), async move {
-
-            let args = request.into_inner();
-            info!("{:?}", args);
-            if !matches!(
-                Protocol::try_from(args.share)?,
-                Protocol::Off | Protocol::Nvmf
-            ) {
-                return Err(LvsError::ReplicaShareProtocol {
-                    value: args.share,
-                }).map_err(Status::from);
-            }
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                if !matches!(
+                    Protocol::try_from(args.share)?,
+                    Protocol::Off | Protocol::Nvmf
+                ) {
+                    return Err(LvsError::ReplicaShareProtocol {
+                        value: args.share,
+                    })
+                    .map_err(Status::from);
+                }
 
-            let rx = rpc_submit(async move {
-                let lvs = match Lvs::lookup_by_uuid(&args.pooluuid) {
-                    Some(lvs) => lvs,
-                    None => {
-                        // lookup takes care of backward compatibility
-                        match Lvs::lookup(&args.pooluuid) {
-                            Some(lvs) => lvs,
-                            None => {
-                                return Err(LvsError::Invalid {
-                                    source: Errno::ENOMEDIUM,
-                                    msg: format!("Pool {} not found", args.pooluuid),
-                                })
+                let rx = rpc_submit(async move {
+                    let lvs = match Lvs::lookup_by_uuid(&args.pooluuid) {
+                        Some(lvs) => lvs,
+                        None => {
+                            // lookup takes care of backward compatibility
+                            match Lvs::lookup(&args.pooluuid) {
+                                Some(lvs) => lvs,
+                                None => {
+                                    return Err(LvsError::Invalid {
+                                        source: Errno::ENOMEDIUM,
+                                        msg: format!(
+                                            "Pool {} not found",
+                                            args.pooluuid
+                                        ),
+                                    })
+                                }
                             }
                         }
+                    };
+                    if Tombstones::is_marked(ResourceKind::Replica, &args.uuid)
+                    {
+                        return Err(LvsError::RepExists {
+                            source: Errno::EEXIST,
+                            name: args.uuid,
+                        });
                     }
-                };
-                // if pooltype is not Lvs, the provided replica uuid need to be added as
-                // a metadata on the volume.
-                match lvs.create_lvol(&args.name, args.size, Some(&args.uuid), args.thin).await {
-                    Ok(mut lvol)
-                    if Protocol::try_from(args.share)? == Protocol::Nvmf => {
+                    check_block_size(&lvs, args.block_size)?;
+                    // if pooltype is not Lvs, the provided replica uuid need to
+                    // be added as a metadata on the volume.
+                    let mut lvol = lvs
+                        .create_lvol(
+                            &args.name,
+                            args.size,
+                            Some(&args.uuid),
+                            args.thin,
+                        )
+                        .await?;
+                    // A replica which fails to be set up once its lvol is
+                    // created is destroyed, so that the
+                    // creation can be retried.
+                    let setup: Result<Option<String>, LvsError> = async {
+                        if args.compress {
+                            lvol.compress().await?;
+                        }
+                        if let Some(owner) = args.owner.map(LvolOwner::from) {
+                            lvol.set_owner(Some(&owner)).await?;
+                        }
+                        if Protocol::try_from(args.share)? != Protocol::Nvmf {
+                            return Ok(None);
+                        }
                         let props = ShareProps::new()
                             .with_allowed_hosts(args.allowed_hosts)
                             .with_ptpl(lvol.ptpl().create().map_err(
@@ -204,34 +473,31 @@ impl ReplicaRpc for ReplicaService {
                                     name: lvol.name(),
                                 },
                             )?);
-                        match Pin::new(&mut lvol).share_nvmf(Some(props)).await {
-                            Ok(s) => {
-                                debug!("created and shared {:?} as {}", lvol, s);
-                                Ok(Replica::from(lvol))
-                            }
-                            Err(e) => {
-                                debug!(
-                                    "failed to share created lvol {:?}: {} (destroying)",
-                                    lvol,
-                                    e.to_string()
-                                );
-                                let _ = lvol.destroy().await;
-                                Err(e)
-                            }
-                        }
+                        Pin::new(&mut lvol)
+                            .share_nvmf(Some(props))
+                            .await
+                            .map(Some)
                     }
-                    Ok(lvol) => {
-                        debug!("created lvol {:?}", lvol);
-                        Ok(Replica::from(lvol))
+                    .await;
+                    match setup {
+                        Ok(Some(s)) => {
+                            debug!("created and shared {:?} as {}", lvol, s);
+                            Ok(Replica::from(lvol))
+                        }
+                        Ok(None) => {
+                            debug!("created lvol {:?}", lvol);
+                            Ok(Replica::from(lvol))
+                        }
+                        Err(e) => Err(rollback_replica_create(lvol, e).await),
                     }
-                    Err(e) => Err(e),
-                }
-            })?;
-            rx.await
-                .map_err(|_| Status::cancelled("cancelled"))?
-                .map_err(Status::from)
-                .map(Response::new)
-        }).await
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
     }
 
     #[named]
@@ -242,6 +508,11 @@ impl ReplicaRpc for ReplicaService {
         self.locked(GrpcClientContext::new(&request, function_name!()), async {
             let args = request.into_inner();
             info!("{:?}", args);
+            ResourceGeneration::check(
+                ResourceKind::Replica,
+                &args.uuid,
+                args.expected_generation,
+            )?;
             let rx = rpc_submit::<_, _, LvsError>(async move {
                 // todo: is there still a race here, can the pool be exported
                 //   right after the check here and before we
@@ -294,7 +565,21 @@ impl ReplicaRpc for ReplicaService {
                         });
                     }
                 }
+                lvol.check_owner(
+                    args.owner.map(LvolOwner::from).as_ref(),
+                    args.override_owner,
+                )?;
+                if args.validate_only {
+                    return Ok(());
+                }
+                if args.mark_for_deletion {
+                    Tombstones::mark(ResourceKind::Replica, &args.uuid);
+                    ResourceGeneration::bump(ResourceKind::Replica, &args.uuid);
+                    return Ok(());
+                }
                 lvol.destroy_replica().await?;
+                Tombstones::forget(ResourceKind::Replica, &args.uuid);
+                ResourceGeneration::forget(ResourceKind::Replica, &args.uuid);
                 Ok(())
             })?;
 
@@ -324,6 +609,12 @@ impl ReplicaRpc for ReplicaService {
                         .collect();
                 }
 
+                // hide the replicas marked for deletion
+                if !args.include_marked {
+                    lvols.retain(|l| {
+                        !Tombstones::is_marked(ResourceKind::Replica, &l.uuid())
+                    });
+                }
                 // perform filtering on lvols
                 if let Some(pool_name) = args.poolname {
                     lvols.retain(|l| l.pool_name() == pool_name);
@@ -368,15 +659,25 @@ impl ReplicaRpc for ReplicaService {
             async move {
                 let args = request.into_inner();
                 info!("{:?}", args);
+                ResourceGeneration::check(
+                    ResourceKind::Replica,
+                    &args.uuid,
+                    args.expected_generation,
+                )?;
                 let rx = rpc_submit(async move {
                     match Bdev::lookup_by_uuid_str(&args.uuid) {
                         Some(bdev) => {
                             let mut lvol = Lvol::try_from(bdev)?;
+                            lvol.check_owner(
+                                args.owner.clone().map(LvolOwner::from).as_ref(),
+                                args.override_owner,
+                            )?;
 
                             // if we are already shared with the same protocol
                             if lvol.shared()
                                 == Some(Protocol::try_from(args.share)?)
                             {
+                                share_preflight(&lvol, &args.allowed_hosts)?;
                                 Pin::new(&mut lvol)
                                     .update_properties(
                                         UpdateProps::new().with_allowed_hosts(
@@ -384,6 +685,10 @@ impl ReplicaRpc for ReplicaService {
                                         ),
                                     )
                                     .await?;
+                                ResourceGeneration::bump(
+                                    ResourceKind::Replica,
+                                    &args.uuid,
+                                );
                                 return Ok(Replica::from(lvol));
                             }
 
@@ -396,8 +701,26 @@ impl ReplicaRpc for ReplicaService {
                                     })
                                 }
                                 Protocol::Nvmf => {
+                                    share_preflight(
+                                        &lvol,
+                                        &args.allowed_hosts,
+                                    )?;
+                                    let identity = NvmeIdentity::parse(
+                                        &args.eui64,
+                                        &args.nguid,
+                                        &args.serial,
+                                        &args.model,
+                                    )
+                                    .and_then(|identity| {
+                                        identity.with_nqn(&args.nqn)
+                                    })
+                                    .map_err(|msg| LvsError::Invalid {
+                                        source: Errno::EINVAL,
+                                        msg,
+                                    })?;
                                     let props = ShareProps::new()
                                         .with_allowed_hosts(args.allowed_hosts)
+                                        .with_identity(identity)
                                         .with_ptpl(lvol.ptpl().create().map_err(
                                             |source| LvsError::LvolShare {
                                                 source: crate::core::CoreError::Ptpl {
@@ -412,6 +735,10 @@ impl ReplicaRpc for ReplicaService {
                                 }
                             }
 
+                            ResourceGeneration::bump(
+                                ResourceKind::Replica,
+                                &args.uuid,
+                            );
                             Ok(Replica::from(lvol))
                         }
 
@@ -433,6 +760,54 @@ impl ReplicaRpc for ReplicaService {
             .await
     }
 
+    async fn validate_share_replica(
+        &self,
+        request: Request<ValidateShareReplicaRequest>,
+    ) -> GrpcResult<ValidateShareReplicaResponse> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+        let rx = rpc_submit(async move {
+            let Some(bdev) = Bdev::lookup_by_uuid_str(&args.uuid) else {
+                return Err(LvsError::InvalidBdev {
+                    source: BdevError::BdevNotFound {
+                        name: args.uuid.clone(),
+                    },
+                    name: args.uuid,
+                });
+            };
+            let lvol = Lvol::try_from(bdev)?;
+            let port = match args.port.map(u16::try_from).transpose() {
+                Ok(port) => port,
+                Err(_) => {
+                    return Err(LvsError::Invalid {
+                        source: Errno::EINVAL,
+                        msg: format!("invalid port {:?}", args.port),
+                    })
+                }
+            };
+            let mut response = ValidateShareReplicaResponse::from(
+                NvmfSubsystem::share_preflight(
+                    &lvol.name(),
+                    args.address.as_deref(),
+                    port,
+                    &args.allowed_hosts,
+                ),
+            );
+            if Protocol::try_from(args.share)? != Protocol::Nvmf {
+                response.valid = false;
+                response.violations.push(ShareViolation {
+                    field: "share".to_string(),
+                    message: "only NVMf shares are supported".to_string(),
+                });
+            }
+            Ok(response)
+        })?;
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
     #[named]
     async fn unshare_replica(
         &self,
@@ -443,12 +818,204 @@ impl ReplicaRpc for ReplicaService {
             async move {
                 let args = request.into_inner();
                 info!("{:?}", args);
+                ResourceGeneration::check(
+                    ResourceKind::Replica,
+                    &args.uuid,
+                    args.expected_generation,
+                )?;
                 let rx = rpc_submit(async move {
                     match Bdev::lookup_by_uuid_str(&args.uuid) {
                         Some(bdev) => {
                             let mut lvol = Lvol::try_from(bdev)?;
+                            lvol.check_owner(
+                                args.owner.map(LvolOwner::from).as_ref(),
+                                args.override_owner,
+                            )?;
                             if lvol.shared().is_some() {
                                 Pin::new(&mut lvol).unshare().await?;
+                                ResourceGeneration::bump(
+                                    ResourceKind::Replica,
+                                    &args.uuid,
+                                );
+                            }
+                            Ok(Replica::from(lvol))
+                        }
+                        None => Err(LvsError::InvalidBdev {
+                            source: BdevError::BdevNotFound {
+                                name: args.uuid.clone(),
+                            },
+                            name: args.uuid,
+                        }),
+                    }
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+    #[named]
+    async fn freeze_replica(
+        &self,
+        request: Request<FreezeReplicaRequest>,
+    ) -> GrpcResult<FreezeReplicaResponse> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let timeout = args
+                    .timeout
+                    .and_then(|t| std::time::Duration::try_from(t).ok())
+                    .unwrap_or(LVOL_FREEZE_MAX_TIMEOUT);
+                let rx = rpc_submit(async move {
+                    match Bdev::lookup_by_uuid_str(&args.uuid) {
+                        Some(bdev) => {
+                            let lvol = Lvol::try_from(bdev)?;
+                            let freeze = lvol.freeze(timeout).await?;
+                            Ok(FreezeReplicaResponse {
+                                replica: Some(Replica::from(lvol)),
+                                frozen_at: Some(freeze.frozen_at.into()),
+                                deadline: Some(freeze.deadline.into()),
+                            })
+                        }
+                        None => Err(LvsError::InvalidBdev {
+                            source: BdevError::BdevNotFound {
+                                name: args.uuid.clone(),
+                            },
+                            name: args.uuid,
+                        }),
+                    }
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn thaw_replica(
+        &self,
+        request: Request<ThawReplicaRequest>,
+    ) -> GrpcResult<Replica> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let rx = rpc_submit(async move {
+                    match Bdev::lookup_by_uuid_str(&args.uuid) {
+                        Some(bdev) => {
+                            let lvol = Lvol::try_from(bdev)?;
+                            lvol.thaw().await?;
+                            Ok(Replica::from(lvol))
+                        }
+                        None => Err(LvsError::InvalidBdev {
+                            source: BdevError::BdevNotFound {
+                                name: args.uuid.clone(),
+                            },
+                            name: args.uuid,
+                        }),
+                    }
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn set_replica_state(
+        &self,
+        request: Request<SetReplicaStateRequest>,
+    ) -> GrpcResult<Replica> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let state = match ReplicaState::from_i32(args.state) {
+                    Some(state) => LvolState::from(state),
+                    None => {
+                        return Err(Status::invalid_argument(format!(
+                            "invalid replica state {}",
+                            args.state
+                        )))
+                    }
+                };
+                ResourceGeneration::check(
+                    ResourceKind::Replica,
+                    &args.uuid,
+                    args.expected_generation,
+                )?;
+                let rx = rpc_submit(async move {
+                    match Bdev::lookup_by_uuid_str(&args.uuid) {
+                        Some(bdev) => {
+                            let lvol = Lvol::try_from(bdev)?;
+                            lvol.set_state(state).await?;
+                            ResourceGeneration::bump(
+                                ResourceKind::Replica,
+                                &args.uuid,
+                            );
+                            Ok(Replica::from(lvol))
+                        }
+                        None => Err(LvsError::InvalidBdev {
+                            source: BdevError::BdevNotFound {
+                                name: args.uuid.clone(),
+                            },
+                            name: args.uuid,
+                        }),
+                    }
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    #[named]
+    async fn set_replica_owner(
+        &self,
+        request: Request<SetReplicaOwnerRequest>,
+    ) -> GrpcResult<Replica> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                ResourceGeneration::check(
+                    ResourceKind::Replica,
+                    &args.uuid,
+                    args.expected_generation,
+                )?;
+                let rx = rpc_submit(async move {
+                    match Bdev::lookup_by_uuid_str(&args.uuid) {
+                        Some(bdev) => {
+                            let lvol = Lvol::try_from(bdev)?;
+                            let owner = args.owner.map(LvolOwner::from);
+                            // An owned replica is handed over to another
+                            // owner, or released, only when overridden.
+                            lvol.check_owner(
+                                owner.as_ref(),
+                                args.override_owner,
+                            )?;
+                            if lvol.owner() != owner {
+                                lvol.set_owner(owner.as_ref()).await?;
+                                ResourceGeneration::bump(
+                                    ResourceKind::Replica,
+                                    &args.uuid,
+                                );
                             }
                             Ok(Replica::from(lvol))
                         }
@@ -468,4 +1035,192 @@ impl ReplicaRpc for ReplicaService {
         )
         .await
     }
+
+    #[named]
+    async fn restore_replica(
+        &self,
+        request: Request<RestoreReplicaRequest>,
+    ) -> GrpcResult<Replica> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                if !Tombstones::is_marked(ResourceKind::Replica, &args.uuid) {
+                    return Err(Status::not_found(format!(
+                        "replica {} is not marked for deletion",
+                        args.uuid
+                    )));
+                }
+                let rx = rpc_submit(async move {
+                    match Bdev::lookup_by_uuid_str(&args.uuid) {
+                        Some(bdev) => {
+                            let lvol = Lvol::try_from(bdev)?;
+                            Tombstones::restore(
+                                ResourceKind::Replica,
+                                &args.uuid,
+                            );
+                            ResourceGeneration::bump(
+                                ResourceKind::Replica,
+                                &args.uuid,
+                            );
+                            Ok(Replica::from(lvol))
+                        }
+                        None => Err(LvsError::InvalidBdev {
+                            source: BdevError::BdevNotFound {
+                                name: args.uuid.clone(),
+                            },
+                            name: args.uuid,
+                        }),
+                    }
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
+    async fn watch_replica_usage(
+        &self,
+        request: Request<WatchReplicaUsageRequest>,
+    ) -> Result<Response<Self::WatchReplicaUsageStream>, Status> {
+        let args = request.into_inner();
+        info!("{:?}", args);
+        let interval = match args.interval_ms {
+            0 => REPLICA_USAGE_WATCH_INTERVAL,
+            ms => Duration::from_millis(ms as u64),
+        };
+        let mut watch = LvolUsageWatch::new(
+            LvolUsageThresholds {
+                delta_bytes: args.delta_bytes,
+                delta_percent: args.delta_percent,
+            },
+            args.report_initial,
+        );
+
+        let (tx, rx) = tokio::sync::mpsc::channel(1024);
+        crate::core::spawn(async move {
+            loop {
+                let pool = args.pool.clone();
+                let samples = match rpc_submit::<_, _, LvsError>(async move {
+                    Ok(LvolUsageSample::sample(pool.as_deref()))
+                }) {
+                    Ok(rx) => rx.await.ok().and_then(Result::ok),
+                    Err(_) => None,
+                };
+                let Some(samples) = samples else {
+                    tx.send(Err(Status::cancelled("cancelled"))).await.ok();
+                    return;
+                };
+
+                for change in watch.update(samples) {
+                    if tx.send(Ok(change.into())).await.is_err() {
+                        return;
+                    }
+                }
+
+                // Stop sampling as soon as the client goes away.
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = tx.closed() => return,
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    #[named]
+    async fn verify_replica(
+        &self,
+        request: Request<VerifyReplicaRequest>,
+    ) -> Result<Response<Self::VerifyReplicaStream>, Status> {
+        // The progress is sent with try_send, from the reactor the check runs
+        // on, hence a channel large enough for all the notifications.
+        let max_chunks = 1024;
+        let (tx, rx) = tokio::sync::mpsc::channel(max_chunks);
+
+        let replica_svc = self.clone();
+        let tx_cln = tx.clone();
+        let uuid = request.get_ref().uuid.clone();
+
+        crate::core::spawn(async move {
+            let result = replica_svc
+                .locked(
+                    GrpcClientContext::new(&request, function_name!()),
+                    async move {
+                        let args = request.into_inner();
+                        info!("{:?}", args);
+                        let rx = rpc_submit(async move {
+                            let lookup = |uuid: &str| {
+                                Bdev::lookup_by_uuid_str(uuid)
+                                    .ok_or(LvsError::InvalidBdev {
+                                        source: BdevError::BdevNotFound {
+                                            name: uuid.to_string(),
+                                        },
+                                        name: uuid.to_string(),
+                                    })
+                                    .and_then(Lvol::try_from)
+                            };
+                            let lvol = lookup(&args.uuid)?;
+                            let snapshot = match &args.snapshot_uuid {
+                                Some(uuid) => {
+                                    let snapshot = lookup(uuid)?;
+                                    if !snapshot.is_snapshot() {
+                                        return Err(LvsError::Invalid {
+                                            source: Errno::EINVAL,
+                                            msg: format!(
+                                                "{uuid} is not a snapshot"
+                                            ),
+                                        });
+                                    }
+                                    Some(snapshot)
+                                }
+                                None => None,
+                            };
+                            let options = VerifyOptions {
+                                chunk_size: match args.chunk_size {
+                                    0 => 1024 * 1024,
+                                    size => size,
+                                },
+                                max_chunks: max_chunks as u64,
+                            };
+                            let stats = lvol
+                                .verify(snapshot.as_ref(), options, |stats| {
+                                    tx_cln
+                                        .try_send(Ok(stats.into()))
+                                        .map_err(|e| e.to_string())
+                                })
+                                .await?;
+                            info!(
+                                "Verified {} bytes of replica {}: {} bytes \
+                                unreadable, {} bytes mismatching",
+                                stats.verified_bytes,
+                                stats.uuid,
+                                stats.io_error_bytes,
+                                stats.mismatch_bytes
+                            );
+                            Result::<(), LvsError>::Ok(())
+                        })?;
+                        rx.await
+                            .map_err(|_| Status::cancelled("cancelled"))?
+                            .map_err(Status::from)
+                    },
+                )
+                .await;
+            if tx.is_closed() {
+                tracing::error!(
+                    "Verification of {uuid} aborted: client disconnected"
+                );
+            } else if let Err(error) = result {
+                tracing::error!("Verification of {uuid} failed: {error}");
+                tx.send(Err(error)).await.ok();
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
 }
@@ -35,12 +35,16 @@ use nix::errno::Errno;
 use spdk_rs::libspdk::spdk_blob_get_xattr_value;
 use std::{convert::TryFrom, panic::AssertUnwindSafe};
 use strum::IntoEnumIterator;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 
 /// Support for the snapshot's consumption as source, should be marked as true
 /// once we start supporting the feature.
 const SNAPSHOT_READY_AS_SOURCE: bool = false;
 
+/// Default number of snapshots in a message of a snapshot stream.
+const STREAM_SNAPSHOTS_PAGE_SIZE: usize = 100;
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct SnapshotService {
@@ -330,7 +334,7 @@ fn filter_snapshots_by_snapshot_query_type(
             let query = &query;
 
             let query_fields = [
-                (query.invalid, snapshot.valid_snapshot),
+                (query.invalid, !snapshot.valid_snapshot),
                 (query.discarded, snapshot.discarded_snapshot),
                 // ... add other fields here as needed
             ];
@@ -346,8 +350,63 @@ fn filter_snapshots_by_snapshot_query_type(
         .collect()
 }
 
+/// Returns the uuids of the snapshots of the given pool, or of all pools,
+/// which come after the given page token, in order.
+fn stream_snapshots_candidates(
+    pool: Option<&str>,
+    page_token: Option<&str>,
+) -> Vec<String> {
+    let Some(bdev) = UntypedBdev::bdev_first() else {
+        return Vec::new();
+    };
+    let mut uuids = bdev
+        .into_iter()
+        .filter(|b| b.driver() == "lvol")
+        .filter_map(|b| Lvol::try_from(b).ok())
+        .filter(|l| l.is_snapshot())
+        .filter(|l| {
+            pool.map_or(true, |p| l.pool_uuid() == p || l.pool_name() == p)
+        })
+        .map(|l| l.uuid())
+        .filter(|u| page_token.map_or(true, |t| u.as_str() > t))
+        .collect::<Vec<_>>();
+    uuids.sort();
+    uuids
+}
+
+/// Returns the snapshots among the given uuids which match the filters of a
+/// stream request. Snapshots destroyed in the meantime are skipped.
+fn stream_snapshots_page(
+    uuids: &[String],
+    args: &StreamSnapshotsRequest,
+) -> Vec<SnapshotInfo> {
+    let snapshots = uuids
+        .iter()
+        .filter_map(|u| UntypedBdev::lookup_by_uuid_str(u))
+        .filter_map(|b| Lvol::try_from(b).ok())
+        .filter_map(|l| l.snapshot_descriptor(None))
+        .filter(|d| {
+            args.source_uuid
+                .as_ref()
+                .map_or(true, |u| &d.source_uuid() == u)
+        })
+        .map(SnapshotInfo::from)
+        .filter(|s| {
+            args.created_after.as_ref().map_or(true, |after| {
+                s.timestamp.as_ref().map_or(false, |t| {
+                    (t.seconds, t.nanos) > (after.seconds, after.nanos)
+                })
+            })
+        })
+        .collect();
+    filter_snapshots_by_snapshot_query_type(snapshots, args.query.clone())
+}
+
 #[tonic::async_trait]
 impl SnapshotRpc for SnapshotService {
+    type StreamSnapshotsStream =
+        ReceiverStream<Result<StreamSnapshotsResponse, Status>>;
+
     #[named]
     async fn create_nexus_snapshot(
         &self,
@@ -562,6 +621,92 @@ impl SnapshotRpc for SnapshotService {
         .await
     }
 
+    async fn stream_snapshots(
+        &self,
+        request: Request<StreamSnapshotsRequest>,
+    ) -> Result<Response<Self::StreamSnapshotsStream>, Status> {
+        let args = request.into_inner();
+        trace!("{:?}", args);
+        let page_size = match args.page_size {
+            0 => STREAM_SNAPSHOTS_PAGE_SIZE,
+            n => n as usize,
+        };
+        let max_snapshots = match args.max_snapshots {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+
+        let pool = args.pool.clone();
+        let page_token = args.page_token.clone();
+        let candidates = rpc_submit::<_, _, LvsError>(async move {
+            Ok(stream_snapshots_candidates(
+                pool.as_deref(),
+                page_token.as_deref(),
+            ))
+        })?
+        .await
+        .map_err(|_| Status::cancelled("cancelled"))?
+        .map_err(Status::from)?;
+
+        // The snapshots are read from the reactor one page of candidates at a
+        // time, so that neither the reactor nor the messages grow with the
+        // number of snapshots, and are sent once the page is full.
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        crate::core::spawn(async move {
+            let mut sent = 0;
+            let mut page = Vec::with_capacity(page_size);
+            let mut chunks = candidates.chunks(page_size).peekable();
+            while let Some(chunk) = chunks.next() {
+                let chunk = chunk.to_vec();
+                let args = args.clone();
+                let snapshots = match rpc_submit::<_, _, LvsError>(async move {
+                    Ok(stream_snapshots_page(&chunk, &args))
+                }) {
+                    Ok(rx) => rx.await.ok().and_then(Result::ok),
+                    Err(_) => None,
+                };
+                let Some(snapshots) = snapshots else {
+                    tx.send(Err(Status::cancelled("cancelled"))).await.ok();
+                    return;
+                };
+
+                for snapshot in snapshots {
+                    if sent == max_snapshots {
+                        break;
+                    }
+                    page.push(snapshot);
+                    sent += 1;
+                }
+
+                let truncated = sent == max_snapshots;
+                let last = truncated || chunks.peek().is_none();
+                while page.len() >= page_size || (last && !page.is_empty()) {
+                    let rest = page.split_off(page_size.min(page.len()));
+                    let snapshots = std::mem::replace(&mut page, rest);
+                    // Only the last message of a truncated stream tells where
+                    // to resume from.
+                    let next_page_token = if truncated && page.is_empty() {
+                        snapshots.last().map(|s| s.snapshot_uuid.clone())
+                    } else {
+                        None
+                    };
+                    let response = StreamSnapshotsResponse {
+                        snapshots,
+                        next_page_token,
+                    };
+                    if tx.send(Ok(response)).await.is_err() {
+                        return;
+                    }
+                }
+                if last {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
     #[named]
     async fn destroy_snapshot(
         &self,
@@ -734,6 +879,51 @@ impl SnapshotRpc for SnapshotService {
         .await
     }
 
+    #[named]
+    async fn flatten_clone(
+        &self,
+        request: Request<FlattenCloneRequest>,
+    ) -> GrpcResult<Replica> {
+        self.locked(
+            GrpcClientContext::new(&request, function_name!()),
+            async move {
+                let args = request.into_inner();
+                info!("{:?}", args);
+                let rx = rpc_submit(async move {
+                    let clone_lvol = match UntypedBdev::lookup_by_uuid_str(
+                        &args.clone_uuid,
+                    ) {
+                        Some(bdev) => Lvol::try_from(bdev)?,
+                        None => {
+                            return Err(LvsError::FlattenClone {
+                                source: Errno::ENOENT,
+                                name: args.clone_uuid,
+                                msg: "clone not found".to_string(),
+                            })
+                        }
+                    };
+                    match clone_lvol.flatten().await {
+                        Ok(()) => {
+                            info!("Flatten Clone Success for {clone_lvol:?}");
+                            Ok(Replica::from(clone_lvol))
+                        }
+                        Err(e) => {
+                            error!(
+                                "Flatten Clone Failed for clone: {clone_lvol:?} with Error: {e:?}",
+                            );
+                            Err(e)
+                        }
+                    }
+                })?;
+                rx.await
+                    .map_err(|_| Status::cancelled("cancelled"))?
+                    .map_err(Status::from)
+                    .map(Response::new)
+            },
+        )
+        .await
+    }
+
     #[named]
     async fn list_snapshot_clone(
         &self,
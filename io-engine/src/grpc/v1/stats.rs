@@ -0,0 +1,514 @@
+use crate::{
+    bdev::nexus,
+    core::{
+        logical_volume::LogicalVolume,
+        BlockDeviceIoStats,
+        CoreError,
+        IoBandwidthStats,
+        IoBandwidthWindow,
+        IoHistograms,
+        IoLatencyHistogramSnapshot,
+        UntypedBdev,
+    },
+    grpc::{
+        rpc_submit,
+        GrpcApiMetrics,
+        GrpcApiVersionStats,
+        GrpcRateLimit,
+        GrpcRateLimitStats,
+        GrpcResult,
+    },
+    lvs::{Lvs, LvsLvol},
+    subsys::NvmfSubsystem,
+};
+use mayastor_api::v1::stats::*;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use tonic::{Request, Response, Status};
+
+/// I/O counters of all resources at the moment of their last reset, keyed by
+/// resource type and name. Reported stats are relative to these counters.
+static STATS_BASELINE: Lazy<
+    parking_lot::Mutex<HashMap<(i32, String), BlockDeviceIoStats>>,
+> = Lazy::new(Default::default);
+
+impl From<nexus::NexusReadCacheStats> for ReadCacheStats {
+    fn from(s: nexus::NexusReadCacheStats) -> Self {
+        Self {
+            hit_rate: s.hit_rate(),
+            device: s.device,
+            line_size: s.line_size,
+            capacity_lines: s.capacity_lines,
+            cached_lines: s.cached_lines,
+            hits: s.hits,
+            misses: s.misses,
+            fills: s.fills,
+            fill_errors: s.fill_errors,
+            evictions: s.evictions,
+            invalidations: s.invalidations,
+        }
+    }
+}
+
+impl From<nexus::NexusCoreStats> for CoreIoStats {
+    fn from(s: nexus::NexusCoreStats) -> Self {
+        Self {
+            core: s.core,
+            queue_depth: s.queue_depth,
+            num_ops: s.num_ops,
+            bytes: s.bytes,
+        }
+    }
+}
+
+impl From<IoBandwidthWindow> for BandwidthWindow {
+    fn from(w: IoBandwidthWindow) -> Self {
+        Self {
+            bytes_read: w.bytes_read,
+            bytes_written: w.bytes_written,
+            rebuild_bytes_read: w.rebuild_bytes_read,
+            rebuild_bytes_written: w.rebuild_bytes_written,
+        }
+    }
+}
+
+impl From<IoBandwidthStats> for BandwidthStats {
+    fn from(s: IoBandwidthStats) -> Self {
+        Self {
+            last_minute: Some(s.last_minute.into()),
+            last_hour: Some(s.last_hour.into()),
+            last_day: Some(s.last_day.into()),
+            total: Some(s.total.into()),
+        }
+    }
+}
+
+impl From<crate::core::BdevHistogram> for BdevHistogram {
+    fn from(h: crate::core::BdevHistogram) -> Self {
+        Self {
+            tick_rate: h.tick_rate,
+            total: h.total(),
+            buckets: h
+                .buckets
+                .iter()
+                .map(|b| BdevHistogramBucket {
+                    start_us: h.ticks_to_us(b.start),
+                    end_us: h.ticks_to_us(b.end),
+                    count: b.count,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<BlockDeviceIoStats> for IoStats {
+    fn from(b: BlockDeviceIoStats) -> Self {
+        Self {
+            num_read_ops: b.num_read_ops,
+            num_write_ops: b.num_write_ops,
+            bytes_read: b.bytes_read,
+            bytes_written: b.bytes_written,
+            num_unmap_ops: b.num_unmap_ops,
+            bytes_unmapped: b.bytes_unmapped,
+        }
+    }
+}
+
+impl From<IoLatencyHistogramSnapshot> for LatencyHistogram {
+    fn from(h: IoLatencyHistogramSnapshot) -> Self {
+        Self {
+            read_p99_us: IoLatencyHistogramSnapshot::percentile_us(
+                &h.read, 0.99,
+            ),
+            write_p99_us: IoLatencyHistogramSnapshot::percentile_us(
+                &h.write, 0.99,
+            ),
+            read_buckets: h.read,
+            write_buckets: h.write,
+        }
+    }
+}
+
+impl From<GrpcRateLimitStats> for GrpcRateLimitStatsResponse {
+    fn from(s: GrpcRateLimitStats) -> Self {
+        Self {
+            allowed: s.allowed,
+            rejected_global: s.rejected_global,
+            rejected_low_priority: s.rejected_low_priority,
+            rejected_client: s.rejected_client,
+        }
+    }
+}
+
+impl From<GrpcApiVersionStats> for GrpcApiVersionStatsEntry {
+    fn from(s: GrpcApiVersionStats) -> Self {
+        Self {
+            version: mayastor_api::v1::registration::ApiVersion::from(s.version)
+                as i32,
+            requests: s.requests,
+            failed: s.failed,
+            in_flight: s.in_flight,
+        }
+    }
+}
+
+/// Returns the stats relative to the given baseline. The baseline is ignored
+/// if the device counters went backwards, e.g. after the device was
+/// re-created with the same name.
+fn since_baseline(
+    stats: BlockDeviceIoStats,
+    base: &BlockDeviceIoStats,
+) -> BlockDeviceIoStats {
+    if stats.num_read_ops < base.num_read_ops
+        || stats.num_write_ops < base.num_write_ops
+        || stats.num_unmap_ops < base.num_unmap_ops
+    {
+        return stats;
+    }
+
+    BlockDeviceIoStats {
+        num_read_ops: stats.num_read_ops - base.num_read_ops,
+        num_write_ops: stats.num_write_ops - base.num_write_ops,
+        bytes_read: stats.bytes_read.saturating_sub(base.bytes_read),
+        bytes_written: stats.bytes_written.saturating_sub(base.bytes_written),
+        num_unmap_ops: stats.num_unmap_ops - base.num_unmap_ops,
+        bytes_unmapped: stats
+            .bytes_unmapped
+            .saturating_sub(base.bytes_unmapped),
+    }
+}
+
+/// A resource to collect stats for, with the block device backing it.
+struct StatsTarget {
+    resource_type: ResourceType,
+    name: String,
+    uuid: String,
+    bdev: UntypedBdev,
+}
+
+/// Lists all resources of the given type, matching the optional name or uuid.
+fn list_targets(
+    resource_type: ResourceType,
+    filter: Option<&str>,
+) -> Vec<StatsTarget> {
+    let matches = |name: &str, uuid: &str| {
+        filter.map_or(true, |f| f == name || f == uuid)
+    };
+
+    let mut targets = Vec::new();
+
+    match resource_type {
+        ResourceType::Bdev => {
+            if let Some(bdev) = UntypedBdev::bdev_first() {
+                bdev.into_iter()
+                    .filter(|b| matches(b.name(), &b.uuid_as_string()))
+                    .for_each(|b| {
+                        targets.push(StatsTarget {
+                            resource_type,
+                            name: b.name().to_string(),
+                            uuid: b.uuid_as_string(),
+                            bdev: b,
+                        })
+                    });
+            }
+        }
+        ResourceType::Replica => {
+            Lvs::iter()
+                .filter_map(|lvs| lvs.lvols())
+                .flatten()
+                .filter(|l| matches(&l.name(), &l.uuid()))
+                .for_each(|l| {
+                    targets.push(StatsTarget {
+                        resource_type,
+                        name: l.name(),
+                        uuid: l.uuid(),
+                        bdev: l.as_bdev(),
+                    })
+                });
+        }
+        ResourceType::Nexus => {
+            nexus::nexus_iter()
+                .filter(|n| matches(n.nexus_name(), &n.uuid().to_string()))
+                .for_each(|n| {
+                    if let Some(bdev) =
+                        UntypedBdev::lookup_by_name(&n.bdev_name())
+                    {
+                        targets.push(StatsTarget {
+                            resource_type,
+                            name: n.nexus_name().to_string(),
+                            uuid: n.uuid().to_string(),
+                            bdev,
+                        })
+                    }
+                });
+        }
+        ResourceType::Subsystem => {
+            if let Some(subsystem) = NvmfSubsystem::first() {
+                subsystem
+                    .into_iter()
+                    .filter(|s| matches(&s.get_nqn(), ""))
+                    .for_each(|s| {
+                        // Discovery subsystems have no namespaces.
+                        if let Some(bdev) = s.bdev() {
+                            targets.push(StatsTarget {
+                                resource_type,
+                                name: s.get_nqn(),
+                                uuid: bdev.uuid_as_string(),
+                                bdev,
+                            })
+                        }
+                    });
+            }
+        }
+    }
+
+    targets
+}
+
+/// RPC service for I/O statistics of all resources.
+#[derive(Debug)]
+pub struct StatsService {}
+
+impl StatsService {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for StatsService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[tonic::async_trait]
+impl StatsRpc for StatsService {
+    #[tracing::instrument(skip(self))]
+    async fn get_stats(
+        &self,
+        request: Request<GetStatsRequest>,
+    ) -> GrpcResult<GetStatsResponse> {
+        let args = request.into_inner();
+
+        // An empty filter selects all resource types.
+        let resource_types = if args.resource_types.is_empty() {
+            vec![
+                ResourceType::Bdev,
+                ResourceType::Replica,
+                ResourceType::Nexus,
+                ResourceType::Subsystem,
+            ]
+        } else {
+            args.resource_types
+                .iter()
+                .map(|t| {
+                    ResourceType::from_i32(*t).ok_or_else(|| {
+                        Status::invalid_argument(format!(
+                            "Invalid resource type {t}"
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let rx = rpc_submit::<_, _, CoreError>(async move {
+            let targets = resource_types
+                .into_iter()
+                .flat_map(|t| list_targets(t, args.name.as_deref()))
+                .collect::<Vec<_>>();
+
+            let mut stats = Vec::with_capacity(targets.len());
+            for t in targets {
+                let current = match t.bdev.stats_async().await {
+                    Ok(s) => s,
+                    Err(error) => {
+                        error!(
+                            "Failed to get stats for {:?} '{}': {}",
+                            t.resource_type, t.name, error
+                        );
+                        continue;
+                    }
+                };
+
+                let key = (t.resource_type as i32, t.name.clone());
+                let mut baseline = STATS_BASELINE.lock();
+                let reported = match baseline.get(&key) {
+                    Some(base) => since_baseline(current, base),
+                    None => current,
+                };
+                if args.reset {
+                    baseline.insert(key, current);
+                }
+
+                // Histograms are kept per block device, so resetting them
+                // affects all resources backed by the same device.
+                let latency = IoHistograms::get(t.bdev.name()).map(|h| {
+                    let snapshot = h.snapshot();
+                    if args.reset {
+                        h.reset();
+                    }
+                    snapshot.into()
+                });
+
+                let read_cache = match t.resource_type {
+                    ResourceType::Nexus => nexus::nexus_lookup(&t.name)
+                        .and_then(|n| n.read_cache_stats())
+                        .map(Into::into),
+                    _ => None,
+                };
+
+                let cores = match t.resource_type {
+                    ResourceType::Nexus => nexus::nexus_lookup(&t.name)
+                        .map(|n| {
+                            n.core_stats().into_iter().map(Into::into).collect()
+                        })
+                        .unwrap_or_default(),
+                    _ => Vec::new(),
+                };
+
+                let children = match t.resource_type {
+                    ResourceType::Nexus => nexus::nexus_lookup(&t.name)
+                        .map(|n| {
+                            n.children_iter()
+                                .map(|c| ChildBandwidth {
+                                    uri: c.uri().to_string(),
+                                    bandwidth: Some(c.bandwidth().into()),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    _ => Vec::new(),
+                };
+
+                stats.push(ResourceStats {
+                    resource_type: t.resource_type as i32,
+                    name: t.name,
+                    uuid: t.uuid,
+                    stats: Some(reported.into()),
+                    latency,
+                    read_cache,
+                    cores,
+                    children,
+                });
+            }
+
+            Ok(GetStatsResponse {
+                stats,
+            })
+        })?;
+
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn set_io_histogram(
+        &self,
+        request: Request<SetIoHistogramRequest>,
+    ) -> GrpcResult<()> {
+        let args = request.into_inner();
+
+        let resource_type = ResourceType::from_i32(args.resource_type)
+            .ok_or_else(|| {
+                Status::invalid_argument(format!(
+                    "Invalid resource type {}",
+                    args.resource_type
+                ))
+            })?;
+
+        let rx = rpc_submit::<_, _, CoreError>(async move {
+            let targets = list_targets(resource_type, Some(&args.name));
+            if targets.is_empty() {
+                return Err(CoreError::BdevNotFound {
+                    name: args.name,
+                });
+            }
+
+            targets.iter().for_each(|t| {
+                if args.enable {
+                    IoHistograms::enable(t.bdev.name());
+                } else {
+                    IoHistograms::disable(t.bdev.name());
+                }
+            });
+
+            Ok(())
+        })?;
+
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn enable_bdev_histogram(
+        &self,
+        request: Request<EnableBdevHistogramRequest>,
+    ) -> GrpcResult<()> {
+        let args = request.into_inner();
+
+        let rx = rpc_submit::<_, _, CoreError>(async move {
+            let bdev = UntypedBdev::lookup_by_name(&args.name).ok_or(
+                CoreError::BdevNotFound {
+                    name: args.name.clone(),
+                },
+            )?;
+            bdev.set_histogram_enabled(args.enable).await
+        })?;
+
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_bdev_histogram(
+        &self,
+        request: Request<GetBdevHistogramRequest>,
+    ) -> GrpcResult<GetBdevHistogramResponse> {
+        let args = request.into_inner();
+
+        let rx = rpc_submit::<_, _, CoreError>(async move {
+            let bdev = UntypedBdev::lookup_by_name(&args.name).ok_or(
+                CoreError::BdevNotFound {
+                    name: args.name.clone(),
+                },
+            )?;
+            let histogram = bdev.histogram().await?;
+            Ok(GetBdevHistogramResponse {
+                name: args.name,
+                histogram: Some(histogram.into()),
+            })
+        })?;
+
+        rx.await
+            .map_err(|_| Status::cancelled("cancelled"))?
+            .map_err(Status::from)
+            .map(Response::new)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_grpc_rate_limit_stats(
+        &self,
+        _request: Request<()>,
+    ) -> GrpcResult<GrpcRateLimitStatsResponse> {
+        Ok(Response::new(GrpcRateLimit::stats().into()))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_grpc_api_stats(
+        &self,
+        _request: Request<()>,
+    ) -> GrpcResult<GrpcApiStatsResponse> {
+        Ok(Response::new(GrpcApiStatsResponse {
+            versions: GrpcApiMetrics::stats()
+                .into_iter()
+                .map(GrpcApiVersionStatsEntry::from)
+                .collect(),
+        }))
+    }
+}
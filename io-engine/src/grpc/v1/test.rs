@@ -1,8 +1,10 @@
 use crate::{
     bdev_api::BdevError,
     core::{
+        self_test::{run_self_test, SelfTestStage, SelfTestStageResult},
         wiper::{Error as WipeError, StreamedWiper, WipeStats, Wiper},
         Bdev,
+        CoreError,
         VerboseError,
     },
     grpc::{rpc_submit, GrpcClientContext, GrpcResult, Serializer},
@@ -14,6 +16,9 @@ use mayastor_api::{
     v1::test::{
         wipe_options::WipeMethod,
         wipe_replica_request,
+        RunSelfTestRequest,
+        RunSelfTestResponse,
+        SelfTestStageResult as SelfTestStageResultRpc,
         StreamWipeOptions,
         TestRpc,
         WipeReplicaRequest,
@@ -99,6 +104,9 @@ impl TestRpc for TestService {
                                 max_chunks,
                                 proto_stream,
                             )?;
+                            if args.validate_only {
+                                return Ok(());
+                            }
                             let final_stats = wiper.wipe().await?;
                             final_stats.log();
                             Result::<(), LvsError>::Ok(())
@@ -120,6 +128,35 @@ impl TestRpc for TestService {
         Ok(Response::new(ReceiverStream::new(rx)))
     }
 
+    #[named]
+    async fn run_self_test(
+        &self,
+        request: Request<RunSelfTestRequest>,
+    ) -> GrpcResult<RunSelfTestResponse> {
+        self.replica_svc
+            .locked(
+                GrpcClientContext::new(&request, function_name!()),
+                async move {
+                    let rx = rpc_submit::<_, _, CoreError>(async move {
+                        let stages = run_self_test()
+                            .await
+                            .into_iter()
+                            .map(SelfTestStageResultRpc::from)
+                            .collect::<Vec<_>>();
+                        Ok(RunSelfTestResponse {
+                            passed: stages.iter().all(|s| s.passed),
+                            stages,
+                        })
+                    })?;
+                    rx.await
+                        .map_err(|_| Status::cancelled("cancelled"))?
+                        .map_err(Status::from)
+                        .map(Response::new)
+                },
+            )
+            .await
+    }
+
     async fn add_fault_injection(
         &self,
         request: Request<v1::test::AddFaultInjectionRequest>,
@@ -274,6 +311,25 @@ impl From<&WipeStats> for WipeReplicaResponse {
     }
 }
 
+impl From<SelfTestStageResult> for SelfTestStageResultRpc {
+    fn from(value: SelfTestStageResult) -> Self {
+        Self {
+            stage: match value.stage {
+                SelfTestStage::CreatePool => "CreatePool",
+                SelfTestStage::CreateReplica => "CreateReplica",
+                SelfTestStage::CreateNexus => "CreateNexus",
+                SelfTestStage::IoVerify => "IoVerify",
+                SelfTestStage::Snapshot => "Snapshot",
+                SelfTestStage::Cleanup => "Cleanup",
+            }
+            .to_string(),
+            passed: value.passed(),
+            error: value.error,
+            elapsed: TryInto::try_into(value.elapsed).ok(),
+        }
+    }
+}
+
 impl From<WipeError> for tonic::Status {
     fn from(value: WipeError) -> Self {
         match value {
@@ -0,0 +1,152 @@
+//! Capabilities of the node.
+//!
+//! The control plane uses them to decide what to schedule on a node, instead
+//! of parsing its version string. A capability is `compiled` when the
+//! io-engine and SPDK were built with it, `available` when the node can use
+//! it at runtime, and `enabled` when it is turned on or in use.
+use std::{ffi::CString, path::Path, sync::atomic::Ordering};
+
+use spdk_rs::libspdk::{
+    spdk_bdev_module_list_find,
+    spdk_nvmf_get_transport_ops,
+};
+
+use crate::{
+    bdev::{nexus::ENABLE_PARTIAL_REBUILD, util::uring},
+    core::MayastorFeatures,
+    subsys::{
+        list_nvmf_transports,
+        registration::registration_grpc::ApiVersion,
+        NvmfTransportType,
+    },
+};
+
+/// Directory of the RDMA devices of the host.
+const RDMA_DEVICES_DIR: &str = "/sys/class/infiniband";
+
+/// NVMe-oF over RDMA.
+pub const CAPABILITY_RDMA: &str = "rdma";
+/// Encryption of the replicas.
+pub const CAPABILITY_CRYPTO: &str = "crypto";
+/// Local devices accessed with io_uring.
+pub const CAPABILITY_URING: &str = "uring";
+/// Rebuild of the regions written while a child was faulted only.
+pub const CAPABILITY_PARTIAL_REBUILD: &str = "partial_rebuild";
+/// Replica and nexus snapshots and clones of the v1 API.
+pub const CAPABILITY_SNAPSHOTS_V2: &str = "snapshots_v2";
+
+/// A capability of the node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeCapability {
+    /// Name of the capability.
+    pub name: &'static str,
+    /// Set if the node was built with the capability.
+    pub compiled: bool,
+    /// Set if the node can use the capability.
+    pub available: bool,
+    /// Set if the capability is turned on or in use.
+    pub enabled: bool,
+}
+
+impl NodeCapability {
+    fn new(
+        name: &'static str,
+        compiled: bool,
+        available: bool,
+        enabled: bool,
+    ) -> Self {
+        Self {
+            name,
+            compiled,
+            available: compiled && available,
+            enabled: compiled && available && enabled,
+        }
+    }
+}
+
+/// Capabilities of the node.
+#[derive(Debug, Clone)]
+pub struct NodeCapabilities {
+    /// Capabilities, in a stable order.
+    pub capabilities: Vec<NodeCapability>,
+    /// API versions served by the node.
+    pub api_versions: Vec<ApiVersion>,
+    /// Features enabled when the node started.
+    pub features: MayastorFeatures,
+}
+
+impl NodeCapabilities {
+    /// Detects the capabilities of the node, which serves the given API
+    /// versions. Must be called on the primary reactor.
+    pub fn detect(api_versions: &[ApiVersion]) -> Self {
+        let rdma_compiled = nvmf_transport_compiled(NvmfTransportType::Rdma);
+        let rdma_enabled = list_nvmf_transports()
+            .map(|transports| {
+                transports
+                    .iter()
+                    .any(|t| t.trtype == NvmfTransportType::Rdma.as_str())
+            })
+            .unwrap_or_default();
+
+        let uring_compiled = bdev_module_compiled("uring");
+
+        let capabilities = vec![
+            NodeCapability::new(
+                CAPABILITY_RDMA,
+                rdma_compiled,
+                rdma_devices_present(),
+                rdma_enabled,
+            ),
+            NodeCapability::new(
+                CAPABILITY_CRYPTO,
+                bdev_module_compiled("crypto"),
+                true,
+                true,
+            ),
+            NodeCapability::new(
+                CAPABILITY_URING,
+                uring_compiled,
+                uring_compiled && uring::kernel_support(),
+                true,
+            ),
+            NodeCapability::new(
+                CAPABILITY_PARTIAL_REBUILD,
+                true,
+                true,
+                ENABLE_PARTIAL_REBUILD.load(Ordering::SeqCst),
+            ),
+            NodeCapability::new(
+                CAPABILITY_SNAPSHOTS_V2,
+                true,
+                true,
+                api_versions.contains(&ApiVersion::V1),
+            ),
+        ];
+
+        Self {
+            capabilities,
+            api_versions: api_versions.to_vec(),
+            features: MayastorFeatures::get_features(),
+        }
+    }
+}
+
+/// Checks if SPDK was built with the given bdev module.
+fn bdev_module_compiled(name: &str) -> bool {
+    let name = CString::new(name).unwrap();
+    !unsafe { spdk_bdev_module_list_find(name.as_ptr()) }.is_null()
+}
+
+/// Checks if SPDK was built with the given NVMf transport.
+fn nvmf_transport_compiled(trtype: NvmfTransportType) -> bool {
+    let name = CString::new(trtype.as_str()).unwrap();
+    !unsafe { spdk_nvmf_get_transport_ops(name.as_ptr()) }.is_null()
+}
+
+/// Checks if the host has RDMA devices.
+fn rdma_devices_present() -> bool {
+    Path::new(RDMA_DEVICES_DIR)
+        .read_dir()
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or_default()
+}
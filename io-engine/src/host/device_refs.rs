@@ -0,0 +1,109 @@
+//!
+//! This module implements the resolve_device() gRPC method, which finds the
+//! resources referencing a device: the pools it backs and their replicas, the
+//! nexus children opened on it, the fault injections targeting it and the
+//! NVMe-oF shares which go away with it.
+
+use crate::{
+    bdev::nexus::nexus_iter,
+    bdev_api::{bdev_get_name, BdevError},
+    core::{
+        fault_injection::list_fault_injections,
+        logical_volume::LogicalVolume,
+        Protocol,
+        Share,
+        UntypedBdev,
+    },
+    lvs::{Lvol, Lvs},
+};
+
+/// Nexus child opened on a device.
+#[derive(Debug, Clone)]
+pub struct NexusChildRef {
+    /// Name of the nexus.
+    pub nexus: String,
+    /// URI of the child.
+    pub uri: String,
+}
+
+/// Resources referencing a device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceRefs {
+    /// Name of the device.
+    pub device: String,
+    /// Names of the pools backed by the device.
+    pub pools: Vec<String>,
+    /// UUIDs of the replicas stored on the device, or of the device itself
+    /// if it is a replica.
+    pub replicas: Vec<String>,
+    /// Nexus children opened on the device.
+    pub nexus_children: Vec<NexusChildRef>,
+    /// URIs of the fault injections targeting the device.
+    pub injections: Vec<String>,
+    /// Share URIs of the device and of its replicas.
+    pub shares: Vec<String>,
+}
+
+/// Adds the share URI of the given device, if it is shared over NVMe-oF.
+fn add_share<S: Share>(shares: &mut Vec<String>, dev: &S) {
+    if matches!(dev.shared(), Some(Protocol::Nvmf)) {
+        if let Some(uri) = dev.share_uri() {
+            shares.push(uri);
+        }
+    }
+}
+
+/// Finds the resources referencing the device of the given name or URI.
+/// Returns None if there is no such device.
+pub fn resolve_device(device: &str) -> Result<Option<DeviceRefs>, BdevError> {
+    let name = if device.contains("://") {
+        bdev_get_name(device)?
+    } else {
+        device.to_string()
+    };
+
+    let Some(bdev) = UntypedBdev::lookup_by_name(&name) else {
+        return Ok(None);
+    };
+
+    let mut refs = DeviceRefs {
+        device: name.clone(),
+        ..Default::default()
+    };
+    add_share(&mut refs.shares, &bdev);
+
+    if bdev.driver() == "lvol" {
+        if let Ok(lvol) = Lvol::try_from(bdev) {
+            refs.replicas.push(lvol.uuid());
+        }
+    }
+
+    for lvs in Lvs::iter().filter(|lvs| lvs.base_bdev().name() == name) {
+        refs.pools.push(lvs.name().to_string());
+        for lvol in lvs.lvols().into_iter().flatten() {
+            add_share(&mut refs.shares, &lvol);
+            refs.replicas.push(lvol.uuid());
+        }
+    }
+
+    for nexus in nexus_iter() {
+        for child in nexus.children_iter() {
+            if child.uri() == device
+                || child.get_device_name().as_deref() == Some(name.as_str())
+            {
+                refs.nexus_children.push(NexusChildRef {
+                    nexus: nexus.name.clone(),
+                    uri: child.uri().to_string(),
+                });
+            }
+        }
+    }
+
+    refs.injections = list_fault_injections()
+        .into_iter()
+        .filter(|inj| inj.device_name == name)
+        .map(|inj| inj.uri)
+        .collect();
+
+    Ok(Some(refs))
+}
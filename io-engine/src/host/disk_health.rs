@@ -0,0 +1,227 @@
+//!
+//! This module implements the get_disk_health() gRPC method, which reports
+//! health information of the disks backing the pools. NVMe disks attached to
+//! SPDK are queried with the SMART / Health Information log page, and kernel
+//! block devices are inspected via sysfs.
+
+use std::{
+    convert::{TryFrom, TryInto},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use spdk_rs::libspdk::{
+    nvme_cmd_cdw10_get,
+    spdk_nvme_cmd,
+    SPDK_NVME_LOG_HEALTH_INFORMATION,
+    SPDK_NVME_OPC_GET_LOG_PAGE,
+};
+use url::Url;
+
+use crate::{
+    core::{CoreError, UntypedBdev, UntypedBdevHandle},
+    lvs::Lvs,
+};
+
+/// Size of the SMART / Health Information log page.
+const HEALTH_LOG_SIZE: u64 = 512;
+
+/// Source of the disk health information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskHealthSource {
+    /// NVMe SMART / Health Information log page.
+    NvmeSmart,
+    /// Kernel sysfs attributes.
+    Sysfs,
+}
+
+/// Health information of a pool disk. Values which the disk does not report
+/// are left unset.
+#[derive(Debug, Clone)]
+pub struct DiskHealth {
+    /// Name of the pool.
+    pub pool: String,
+    /// URI of the disk.
+    pub disk: String,
+    /// Where the health information has been obtained from.
+    pub source: Option<DiskHealthSource>,
+    /// NVMe critical warning bits.
+    pub critical_warning: Option<u8>,
+    /// Composite temperature in degrees Celsius.
+    pub temperature: Option<i32>,
+    /// Remaining spare capacity, in percent.
+    pub available_spare: Option<u8>,
+    /// Spare capacity threshold, in percent.
+    pub available_spare_threshold: Option<u8>,
+    /// Vendor estimate of the used life, in percent. May exceed 100.
+    pub percentage_used: Option<u8>,
+    /// Number of unrecovered data integrity errors.
+    pub media_errors: Option<u64>,
+    /// Number of power-on hours.
+    pub power_on_hours: Option<u64>,
+    /// Number of unsafe shutdowns.
+    pub unsafe_shutdowns: Option<u64>,
+    /// Reason why the health information could not be obtained.
+    pub error: Option<String>,
+}
+
+impl DiskHealth {
+    fn new(pool: &str, disk: String) -> Self {
+        Self {
+            pool: pool.to_string(),
+            disk,
+            source: None,
+            critical_warning: None,
+            temperature: None,
+            available_spare: None,
+            available_spare_threshold: None,
+            percentage_used: None,
+            media_errors: None,
+            power_on_hours: None,
+            unsafe_shutdowns: None,
+            error: None,
+        }
+    }
+}
+
+/// Reads a little-endian counter from the health log page. Counters are
+/// 128 bits wide; values beyond 64 bits are saturated.
+fn log_counter(log: &[u8], offset: usize) -> u64 {
+    let v = u128::from_le_bytes(log[offset .. offset + 16].try_into().unwrap());
+    u64::try_from(v).unwrap_or(u64::MAX)
+}
+
+/// Fills in the health information from the NVMe SMART log page.
+async fn nvme_health(
+    bdev: &UntypedBdev,
+    health: &mut DiskHealth,
+) -> Result<(), CoreError> {
+    let hdl = UntypedBdevHandle::open(&bdev.name(), false, false)?;
+    let mut buf = hdl.dma_malloc(HEALTH_LOG_SIZE).map_err(|_| {
+        CoreError::DmaAllocationFailed {
+            size: HEALTH_LOG_SIZE,
+        }
+    })?;
+
+    let mut cmd = spdk_nvme_cmd::default();
+    cmd.set_opc(SPDK_NVME_OPC_GET_LOG_PAGE as u16);
+    cmd.nsid = 0xffffffff;
+    // Number of dwords to read (zero-based) and log page identifier.
+    let numd = (HEALTH_LOG_SIZE / 4 - 1) as u32;
+    unsafe {
+        *nvme_cmd_cdw10_get(&mut cmd) =
+            (numd << 16) | SPDK_NVME_LOG_HEALTH_INFORMATION
+    };
+    hdl.nvme_admin(&cmd, Some(&mut buf)).await?;
+
+    let log = buf.as_slice();
+    let kelvin = u16::from_le_bytes([log[1], log[2]]);
+
+    health.source = Some(DiskHealthSource::NvmeSmart);
+    health.critical_warning = Some(log[0]);
+    health.temperature = (kelvin > 0).then(|| kelvin as i32 - 273);
+    health.available_spare = Some(log[3]);
+    health.available_spare_threshold = Some(log[4]);
+    health.percentage_used = Some(log[5]);
+    health.power_on_hours = Some(log_counter(log, 128));
+    health.unsafe_shutdowns = Some(log_counter(log, 144));
+    health.media_errors = Some(log_counter(log, 160));
+
+    Ok(())
+}
+
+/// Returns the sysfs directory of the whole disk containing the given kernel
+/// block device.
+fn sysfs_disk_dir(dev_path: &str) -> Option<PathBuf> {
+    let dev = fs::canonicalize(dev_path).ok()?;
+    let name = dev.file_name()?;
+    let dir =
+        fs::canonicalize(Path::new("/sys/class/block").join(name)).ok()?;
+
+    if dir.join("partition").exists() {
+        dir.parent().map(Path::to_path_buf)
+    } else {
+        Some(dir)
+    }
+}
+
+/// Returns the first temperature reported by a hwmon device under the given
+/// directory, in degrees Celsius.
+fn hwmon_temperature(dir: &Path) -> Option<i32> {
+    [dir.to_path_buf(), dir.join("hwmon")]
+        .iter()
+        .filter_map(|d| fs::read_dir(d).ok())
+        .flatten()
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("hwmon"))
+        .find_map(|e| {
+            fs::read_to_string(e.path().join("temp1_input"))
+                .ok()?
+                .trim()
+                .parse::<i32>()
+                .ok()
+        })
+        .map(|millidegrees| millidegrees / 1000)
+}
+
+/// Fills in the health information of a kernel block device from sysfs.
+fn sysfs_health(dev_path: &str, health: &mut DiskHealth) -> Result<(), String> {
+    let disk = sysfs_disk_dir(dev_path)
+        .ok_or_else(|| format!("no sysfs entry for '{dev_path}'"))?;
+    let device = disk.join("device");
+
+    health.source = Some(DiskHealthSource::Sysfs);
+    health.temperature = hwmon_temperature(&device);
+    // SCSI devices count the commands completed with an error.
+    health.media_errors = fs::read_to_string(device.join("ioerr_cnt"))
+        .ok()
+        .and_then(|s| {
+            u64::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok()
+        });
+
+    Ok(())
+}
+
+/// Obtains the health information of the disk backing the given pool.
+async fn pool_disk_health(lvs: &Lvs) -> DiskHealth {
+    let bdev = lvs.base_bdev();
+    let uri = bdev
+        .bdev_uri_str()
+        .unwrap_or_else(|| bdev.name().to_string());
+    let mut health = DiskHealth::new(lvs.name(), uri.clone());
+
+    let result = match bdev.driver() {
+        "nvme" => nvme_health(&bdev, &mut health)
+            .await
+            .map_err(|e| e.to_string()),
+        "aio" | "uring" => match Url::parse(&uri) {
+            Ok(url) => sysfs_health(url.path(), &mut health),
+            Err(e) => Err(e.to_string()),
+        },
+        driver => Err(format!("not supported for '{driver}' disks")),
+    };
+
+    if let Err(error) = result {
+        warn!(
+            "Failed to get health of disk '{uri}' of pool '{pool}': {error}",
+            pool = lvs.name()
+        );
+        health.error = Some(error);
+    }
+
+    health
+}
+
+/// Obtains the health information of the disks backing all pools, or the
+/// given pool only.
+pub async fn get_disk_health(pool: Option<&str>) -> Vec<DiskHealth> {
+    let pools = Lvs::iter()
+        .filter(|lvs| pool.map_or(true, |p| p == lvs.name() || p == lvs.uuid()))
+        .collect::<Vec<_>>();
+
+    let mut disks = Vec::with_capacity(pools.len());
+    for lvs in pools {
+        disks.push(pool_disk_health(&lvs).await);
+    }
+    disks
+}
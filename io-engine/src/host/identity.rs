@@ -0,0 +1,180 @@
+//!
+//! This module implements the NVMe host identity of the io-engine, that is
+//! the host NQN and host ID it uses when connecting to remote children. The
+//! identity defaults to the one given by the environment at startup. An
+//! identity set by the control plane is persisted in the state store, when
+//! one is configured, and restored at startup, so that nodes restored from the
+//! same image can be given distinct identities which outlive a restart.
+//! Reservations are held per host identity, hence it must be unique.
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+use uuid::Uuid;
+
+use crate::{
+    constants::NVME_NQN_PREFIX,
+    core::MayastorEnvironment,
+    persistent_store::PersistentStore,
+    store::store_defs::StoreError,
+};
+
+/// Maximum length of an NQN, as defined by the NVMe specification.
+const NVME_NQN_MAX_LEN: usize = 223;
+
+/// Errors of the host identity.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+pub enum HostIdentityError {
+    #[snafu(display("Invalid host NQN '{}': {}", nqn, reason))]
+    InvalidNqn { nqn: String, reason: String },
+    #[snafu(display("Invalid host ID '{}': {}", hostid, reason))]
+    InvalidHostId { hostid: String, reason: String },
+    #[snafu(display("Failed to persist the host identity: {}", source))]
+    PersistIdentity { source: StoreError },
+    #[snafu(display(
+        "Failed to parse the persisted host identity: {}",
+        source
+    ))]
+    ParseIdentity { source: serde_json::Error },
+}
+
+/// NVMe host identity used when connecting to remote children.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostIdentity {
+    /// Host NQN, if any.
+    pub nqn: Option<String>,
+    /// Host ID, if any.
+    pub hostid: Option<String>,
+}
+
+/// The current host identity.
+static HOST_IDENTITY: Lazy<RwLock<HostIdentity>> = Lazy::new(|| {
+    RwLock::new(HostIdentity {
+        nqn: MayastorEnvironment::global_or_default().make_hostnqn(),
+        hostid: std::env::var("MAYASTOR_NVMF_HOSTID")
+            .ok()
+            .filter(|id| Uuid::parse_str(id).is_ok()),
+    })
+});
+
+impl HostIdentity {
+    /// Returns the current host identity.
+    pub fn current() -> Self {
+        HOST_IDENTITY.read().clone()
+    }
+
+    /// Returns the host NQN, which defaults to one derived from the host ID.
+    pub fn hostnqn(&self) -> Option<String> {
+        self.nqn.clone().or_else(|| {
+            self.hostid
+                .as_ref()
+                .map(|id| format!("{NVME_NQN_PREFIX}:uuid:{id}"))
+        })
+    }
+
+    /// Returns the host ID as the NVMe extended host identifier.
+    pub fn ext_host_id(&self) -> Option<[u8; 16]> {
+        self.hostid
+            .as_ref()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .map(|id| *id.as_bytes())
+    }
+
+    /// Checks the host NQN and host ID are well formed.
+    fn validate(&self) -> Result<(), HostIdentityError> {
+        if let Some(nqn) = &self.nqn {
+            let invalid = |reason: &str| HostIdentityError::InvalidNqn {
+                nqn: nqn.clone(),
+                reason: reason.to_string(),
+            };
+            if !nqn.starts_with("nqn.") {
+                return Err(invalid("must start with 'nqn.'"));
+            }
+            if nqn.len() > NVME_NQN_MAX_LEN {
+                return Err(invalid("is too long"));
+            }
+            if nqn.contains(char::is_whitespace) {
+                return Err(invalid("must not contain whitespaces"));
+            }
+        }
+        if let Some(hostid) = &self.hostid {
+            let invalid = |reason: &str| HostIdentityError::InvalidHostId {
+                hostid: hostid.clone(),
+                reason: reason.to_string(),
+            };
+            match Uuid::parse_str(hostid) {
+                Ok(id) if id.is_nil() => {
+                    return Err(invalid("must not be nil"))
+                }
+                Ok(_) => {}
+                Err(_) => return Err(invalid("must be a UUID")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the given host NQN and host ID, unset ones being left unchanged,
+    /// and persists the resulting identity. It applies to the controllers
+    /// connected afterwards.
+    pub async fn update(
+        node_name: &str,
+        nqn: Option<String>,
+        hostid: Option<String>,
+    ) -> Result<Self, HostIdentityError> {
+        let current = Self::current();
+        let identity = Self {
+            nqn: nqn.or(current.nqn),
+            hostid: hostid.or(current.hostid),
+        };
+        identity.validate()?;
+
+        if PersistentStore::enabled() {
+            PersistentStore::put(&store_key(node_name), &identity)
+                .await
+                .map_err(|source| HostIdentityError::PersistIdentity {
+                    source,
+                })?;
+        }
+
+        *HOST_IDENTITY.write() = identity.clone();
+        info!("Host identity updated: {identity:?}");
+        Ok(identity)
+    }
+
+    /// Restores the identity persisted in the state store, if any.
+    pub async fn restore(node_name: &str) -> Result<(), HostIdentityError> {
+        if !PersistentStore::enabled() {
+            return Ok(());
+        }
+
+        let value = match PersistentStore::get(&store_key(node_name)).await {
+            Ok(value) => value,
+            Err(StoreError::MissingEntry {
+                ..
+            }) => return Ok(()),
+            Err(source) => {
+                return Err(HostIdentityError::PersistIdentity {
+                    source,
+                })
+            }
+        };
+
+        let identity: HostIdentity =
+            serde_json::from_value(value).map_err(|source| {
+                HostIdentityError::ParseIdentity {
+                    source,
+                }
+            })?;
+        identity.validate()?;
+        *HOST_IDENTITY.write() = identity.clone();
+        info!("Host identity restored: {identity:?}");
+        Ok(())
+    }
+}
+
+/// Key of the host identity of the given node in the state store.
+fn store_key(node_name: &str) -> String {
+    format!("io-engine/{node_name}/host-identity")
+}
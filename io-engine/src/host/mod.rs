@@ -1,2 +1,9 @@
 pub mod blk_device;
+pub mod capabilities;
+pub mod device_refs;
+pub mod disk_health;
+pub mod identity;
+pub mod options;
+pub mod pool_scrub;
 pub mod resource;
+pub mod tombstone;
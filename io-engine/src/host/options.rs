@@ -0,0 +1,267 @@
+//!
+//! This module implements the node options which can be changed at runtime.
+//! The options default to the values given by the environment at startup.
+//! Options changed by the control plane are persisted in the state store,
+//! when one is configured, and restored at startup so that they outlive a
+//! restart of the io-engine.
+
+use std::sync::atomic::Ordering;
+
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+
+use crate::{
+    bdev::{
+        nexus::{
+            ENABLE_NEXUS_RESET,
+            ENABLE_NVMF_RESERVATIONS,
+            ENABLE_PARTIAL_REBUILD,
+        },
+        NvmeTimeouts,
+    },
+    persistent_store::PersistentStore,
+    rebuild::{RebuildScheduler, RebuildSchedulerConfig},
+    store::store_defs::StoreError,
+};
+
+/// Errors of the host options.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)), context(suffix(false)))]
+pub enum HostOptionsError {
+    #[snafu(display("Invalid host option {}: {}", name, reason))]
+    InvalidOption { name: String, reason: String },
+    #[snafu(display("Failed to persist the host options: {}", source))]
+    PersistOptions { source: StoreError },
+    #[snafu(display(
+        "Failed to parse the persisted host options: {}",
+        source
+    ))]
+    ParseOptions { source: serde_json::Error },
+}
+
+/// Options of the node which can be changed at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostOptions {
+    /// Acquire NVMe reservations on the nexus children.
+    pub nvmf_reservations: bool,
+    /// Rebuild only the regions written while a child was out of sync.
+    pub partial_rebuild: bool,
+    /// Handle the resets issued to the nexuses.
+    pub nexus_reset: bool,
+    /// Timeout for NVMe I/O commands, in microseconds.
+    pub nvme_timeout_us: u64,
+    /// Timeout for NVMe admin commands, in microseconds.
+    pub nvme_timeout_admin_us: u64,
+    /// NVMe keep-alive timeout, in milliseconds.
+    pub nvme_keep_alive_timeout_ms: u32,
+    /// Maximum number of rebuild jobs copying data at the same time, zero if
+    /// unlimited.
+    pub rebuild_max_concurrent: usize,
+    /// Maximum number of rebuild jobs of a nexus copying data at the same
+    /// time, zero if unlimited.
+    pub rebuild_max_per_nexus: usize,
+    /// Maximum total rebuild bandwidth in bytes per second, zero if
+    /// unlimited.
+    pub rebuild_max_bandwidth: u64,
+    /// Default size of the rebuild segments in bytes, zero for the built-in
+    /// default.
+    pub rebuild_segment_size: u64,
+    /// Default number of copy tasks of a rebuild job, zero for the built-in
+    /// default.
+    pub rebuild_segment_tasks: usize,
+    /// Client I/O queue depth of a nexus above which the reads of its
+    /// rebuilds are deferred, zero to never defer them.
+    pub rebuild_client_qd_threshold: usize,
+}
+
+/// Changes to the host options. Unset options are left unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostOptionsUpdate {
+    pub nvmf_reservations: Option<bool>,
+    pub partial_rebuild: Option<bool>,
+    pub nexus_reset: Option<bool>,
+    pub nvme_timeout_us: Option<u64>,
+    pub nvme_timeout_admin_us: Option<u64>,
+    pub nvme_keep_alive_timeout_ms: Option<u32>,
+    pub rebuild_max_concurrent: Option<usize>,
+    pub rebuild_max_per_nexus: Option<usize>,
+    pub rebuild_max_bandwidth: Option<u64>,
+    pub rebuild_segment_size: Option<u64>,
+    pub rebuild_segment_tasks: Option<usize>,
+    pub rebuild_client_qd_threshold: Option<usize>,
+}
+
+impl HostOptions {
+    /// Returns the current options.
+    pub fn current() -> Self {
+        let timeouts = NvmeTimeouts::get();
+        let rebuild = RebuildScheduler::config();
+        Self {
+            nvmf_reservations: ENABLE_NVMF_RESERVATIONS.load(Ordering::SeqCst),
+            partial_rebuild: ENABLE_PARTIAL_REBUILD.load(Ordering::SeqCst),
+            nexus_reset: ENABLE_NEXUS_RESET.load(Ordering::SeqCst),
+            nvme_timeout_us: timeouts.timeout_us,
+            nvme_timeout_admin_us: timeouts.timeout_admin_us,
+            nvme_keep_alive_timeout_ms: timeouts.keep_alive_timeout_ms,
+            rebuild_max_concurrent: rebuild.max_concurrent,
+            rebuild_max_per_nexus: rebuild.max_per_nexus,
+            rebuild_max_bandwidth: rebuild.max_bandwidth,
+            rebuild_segment_size: rebuild.segment_size,
+            rebuild_segment_tasks: rebuild.segment_tasks,
+            rebuild_client_qd_threshold: rebuild.client_qd_threshold,
+        }
+    }
+
+    /// Returns these options with the given changes applied.
+    fn updated(self, update: HostOptionsUpdate) -> Self {
+        Self {
+            nvmf_reservations: update
+                .nvmf_reservations
+                .unwrap_or(self.nvmf_reservations),
+            partial_rebuild: update
+                .partial_rebuild
+                .unwrap_or(self.partial_rebuild),
+            nexus_reset: update.nexus_reset.unwrap_or(self.nexus_reset),
+            nvme_timeout_us: update
+                .nvme_timeout_us
+                .unwrap_or(self.nvme_timeout_us),
+            nvme_timeout_admin_us: update
+                .nvme_timeout_admin_us
+                .unwrap_or(self.nvme_timeout_admin_us),
+            nvme_keep_alive_timeout_ms: update
+                .nvme_keep_alive_timeout_ms
+                .unwrap_or(self.nvme_keep_alive_timeout_ms),
+            rebuild_max_concurrent: update
+                .rebuild_max_concurrent
+                .unwrap_or(self.rebuild_max_concurrent),
+            rebuild_max_per_nexus: update
+                .rebuild_max_per_nexus
+                .unwrap_or(self.rebuild_max_per_nexus),
+            rebuild_max_bandwidth: update
+                .rebuild_max_bandwidth
+                .unwrap_or(self.rebuild_max_bandwidth),
+            rebuild_segment_size: update
+                .rebuild_segment_size
+                .unwrap_or(self.rebuild_segment_size),
+            rebuild_segment_tasks: update
+                .rebuild_segment_tasks
+                .unwrap_or(self.rebuild_segment_tasks),
+            rebuild_client_qd_threshold: update
+                .rebuild_client_qd_threshold
+                .unwrap_or(self.rebuild_client_qd_threshold),
+        }
+    }
+
+    /// Checks the options are consistent.
+    fn validate(&self) -> Result<(), HostOptionsError> {
+        if self.nvme_keep_alive_timeout_ms == 0 {
+            return Err(HostOptionsError::InvalidOption {
+                name: "nvme_keep_alive_timeout_ms".to_string(),
+                reason: "fabrics connections need a keep-alive timeout"
+                    .to_string(),
+            });
+        }
+        if self.nvme_timeout_us > 0 && self.nvme_timeout_admin_us == 0 {
+            return Err(HostOptionsError::InvalidOption {
+                name: "nvme_timeout_admin_us".to_string(),
+                reason: "must be set when the I/O timeout is set".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Makes these options the current ones.
+    fn apply(&self) {
+        ENABLE_NVMF_RESERVATIONS
+            .store(self.nvmf_reservations, Ordering::SeqCst);
+        ENABLE_PARTIAL_REBUILD.store(self.partial_rebuild, Ordering::SeqCst);
+        ENABLE_NEXUS_RESET.store(self.nexus_reset, Ordering::SeqCst);
+        NvmeTimeouts {
+            timeout_us: self.nvme_timeout_us,
+            timeout_admin_us: self.nvme_timeout_admin_us,
+            keep_alive_timeout_ms: self.nvme_keep_alive_timeout_ms,
+        }
+        .set();
+        // Queued rebuilds are started if the new limits allow it.
+        RebuildScheduler::configure(RebuildSchedulerConfig {
+            max_concurrent: self.rebuild_max_concurrent,
+            max_per_nexus: self.rebuild_max_per_nexus,
+            max_bandwidth: self.rebuild_max_bandwidth,
+            segment_size: self.rebuild_segment_size,
+            segment_tasks: self.rebuild_segment_tasks,
+            client_qd_threshold: self.rebuild_client_qd_threshold,
+        });
+    }
+
+    /// Applies the given changes to the current options and persists the
+    /// result. NVMe timeouts apply to the controllers connected afterwards,
+    /// and the rebuild segment defaults and client queue depth threshold to
+    /// the rebuilds started afterwards.
+    pub async fn update(
+        node_name: &str,
+        update: HostOptionsUpdate,
+    ) -> Result<Self, HostOptionsError> {
+        let options = Self::current().updated(update);
+        options.validate()?;
+
+        if PersistentStore::enabled() {
+            PersistentStore::put(&store_key(node_name), &options)
+                .await
+                .map_err(|source| HostOptionsError::PersistOptions {
+                    source,
+                })?;
+        }
+
+        options.apply();
+        info!("Host options updated: {options:?}");
+        Ok(options)
+    }
+
+    /// Restores the options persisted in the state store, if any.
+    pub async fn restore(node_name: &str) -> Result<(), HostOptionsError> {
+        if !PersistentStore::enabled() {
+            return Ok(());
+        }
+
+        let value = match PersistentStore::get(&store_key(node_name)).await {
+            Ok(value) => value,
+            Err(StoreError::MissingEntry {
+                ..
+            }) => return Ok(()),
+            Err(source) => {
+                return Err(HostOptionsError::PersistOptions {
+                    source,
+                })
+            }
+        };
+
+        // Options missing from the persisted ones, e.g. persisted by an older
+        // version, keep their current values.
+        let mut current =
+            serde_json::to_value(Self::current()).map_err(|source| {
+                HostOptionsError::ParseOptions {
+                    source,
+                }
+            })?;
+        if let (Some(current), serde_json::Value::Object(persisted)) =
+            (current.as_object_mut(), value)
+        {
+            current.extend(persisted);
+        }
+        let options: HostOptions =
+            serde_json::from_value(current).map_err(|source| {
+                HostOptionsError::ParseOptions {
+                    source,
+                }
+            })?;
+        options.validate()?;
+        options.apply();
+        info!("Host options restored: {options:?}");
+        Ok(())
+    }
+}
+
+/// Key of the options of the given node in the state store.
+fn store_key(node_name: &str) -> String {
+    format!("io-engine/{node_name}/options")
+}
@@ -0,0 +1,257 @@
+//!
+//! This module implements a background media scan of the disks backing the
+//! pools. The scrubber reads through the base bdev of every pool at a
+//! configurable rate, on the primary reactor, and counts the blocks which
+//! cannot be read, so that failing media is noticed before the data on it is
+//! needed. Results are reported per pool by the get_pool_scrub_status() gRPC
+//! method.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::{
+    core::{CoreError, UntypedBdevHandle},
+    lvs::Lvs,
+    sleep::mayastor_sleep,
+};
+
+/// Size of the reads issued by the scrubber.
+const SCRUB_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Settings of the pool scrubber.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolScrubConfig {
+    /// Read rate per pool, in MiB/s. A rate of 0 disables the scrubber.
+    pub rate_mib: u32,
+    /// Pause between two passes over all the pools.
+    pub interval: Duration,
+}
+
+/// State of the scrubber of a pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolScrubState {
+    /// Waiting for the next pass.
+    Idle,
+    /// Reading through the pool disk.
+    Running,
+}
+
+/// Scrub status of a pool.
+#[derive(Debug, Clone)]
+pub struct PoolScrubStatus {
+    /// Name of the pool.
+    pub pool: String,
+    /// Name of the pool disk.
+    pub disk: String,
+    /// Current state.
+    pub state: PoolScrubState,
+    /// Bytes read so far by the current pass.
+    pub bytes_scanned: u64,
+    /// Size of the pool disk, in bytes.
+    pub bytes_total: u64,
+    /// Unreadable blocks found so far by the current pass.
+    pub unreadable_blocks: u64,
+    /// Unreadable blocks found by the last completed pass.
+    pub last_unreadable_blocks: u64,
+    /// Number of completed passes.
+    pub passes: u64,
+    /// Completion time of the last pass.
+    pub last_completed: Option<DateTime<Utc>>,
+}
+
+impl PoolScrubStatus {
+    fn new(pool: &str, disk: String) -> Self {
+        Self {
+            pool: pool.to_string(),
+            disk,
+            state: PoolScrubState::Idle,
+            bytes_scanned: 0,
+            bytes_total: 0,
+            unreadable_blocks: 0,
+            last_unreadable_blocks: 0,
+            passes: 0,
+            last_completed: None,
+        }
+    }
+}
+
+static POOL_SCRUB_CONFIG: OnceCell<PoolScrubConfig> = OnceCell::new();
+
+/// Scrub status of the pools, by pool name.
+static POOL_SCRUB_STATUS: Lazy<
+    parking_lot::Mutex<HashMap<String, PoolScrubStatus>>,
+> = Lazy::new(Default::default);
+
+/// Background media scan of the pool disks.
+pub struct PoolScrubber {}
+
+impl PoolScrubber {
+    /// Configures the scrubber. Only the first call has an effect.
+    pub fn configure(config: PoolScrubConfig) {
+        if config.rate_mib > 0 {
+            info!("Pool scrubber: {config:?}");
+        }
+
+        if POOL_SCRUB_CONFIG.set(config).is_err() {
+            warn!("Pool scrubber has already been configured");
+        }
+    }
+
+    /// Checks if the scrubber is enabled.
+    pub fn enabled() -> bool {
+        POOL_SCRUB_CONFIG.get().map_or(false, |c| c.rate_mib > 0)
+    }
+
+    /// Runs the scrubber forever. Must be called on the primary reactor.
+    pub async fn run() {
+        let Some(config) = POOL_SCRUB_CONFIG.get().copied() else {
+            return;
+        };
+        if config.rate_mib == 0 {
+            return;
+        }
+
+        // Time to read a chunk at the configured rate.
+        let pace = Duration::from_secs_f64(
+            SCRUB_CHUNK_SIZE as f64
+                / (config.rate_mib as f64 * 1024.0 * 1024.0),
+        );
+
+        loop {
+            let pools = Lvs::iter()
+                .map(|l| l.name().to_string())
+                .collect::<Vec<_>>();
+            POOL_SCRUB_STATUS.lock().retain(|p, _| pools.contains(p));
+
+            for pool in pools {
+                if let Err(error) = Self::scrub_pool(&pool, pace).await {
+                    warn!("Pool '{pool}': media scan aborted: {error}");
+                }
+                Self::update(&pool, |s| s.state = PoolScrubState::Idle);
+            }
+
+            if mayastor_sleep(config.interval).await.is_err() {
+                error!("Failed to wait for the next pool media scan");
+                return;
+            }
+        }
+    }
+
+    /// Reads through the disk of the given pool, pausing after each chunk to
+    /// keep to the configured rate.
+    async fn scrub_pool(pool: &str, pace: Duration) -> Result<(), CoreError> {
+        let Some(lvs) = Lvs::lookup(pool) else {
+            return Ok(());
+        };
+        let bdev = lvs.base_bdev();
+        let block_len = bdev.block_len() as u64;
+        let total = bdev.num_blocks() * block_len;
+
+        let hdl = UntypedBdevHandle::open(&bdev.name(), false, false)?;
+        let mut buf = hdl.dma_malloc(SCRUB_CHUNK_SIZE).map_err(|_| {
+            CoreError::DmaAllocationFailed {
+                size: SCRUB_CHUNK_SIZE,
+            }
+        })?;
+        let mut block = hdl.dma_malloc(block_len).map_err(|_| {
+            CoreError::DmaAllocationFailed {
+                size: block_len,
+            }
+        })?;
+
+        debug!("Pool '{pool}': starting media scan of '{}'", bdev.name());
+        Self::update(pool, |s| {
+            s.disk = bdev.name().to_string();
+            s.state = PoolScrubState::Running;
+            s.bytes_scanned = 0;
+            s.bytes_total = total;
+            s.unreadable_blocks = 0;
+        });
+
+        let mut offset = 0;
+        while offset < total {
+            // Stop if the pool went away in the meantime.
+            if Lvs::lookup(pool).is_none() {
+                return Ok(());
+            }
+
+            let len = SCRUB_CHUNK_SIZE.min(total - offset);
+            let bad = if len == SCRUB_CHUNK_SIZE
+                && hdl.read_at(offset, &mut buf).await.is_ok()
+            {
+                0
+            } else {
+                // Read block by block to count the unreadable ones.
+                let mut bad = 0;
+                for blk in (offset .. offset + len).step_by(block_len as usize)
+                {
+                    if let Err(error) = hdl.read_at(blk, &mut block).await {
+                        debug!("Pool '{pool}': {error}");
+                        bad += 1;
+                    }
+                }
+                bad
+            };
+
+            if bad > 0 {
+                warn!(
+                    "Pool '{pool}': {bad} unreadable block(s) at offset \
+                    {offset} of '{}'",
+                    bdev.name()
+                );
+            }
+
+            offset += len;
+            Self::update(pool, |s| {
+                s.bytes_scanned = offset;
+                s.unreadable_blocks += bad;
+            });
+
+            if mayastor_sleep(pace).await.is_err() {
+                error!("Failed to pace the media scan of pool '{pool}'");
+            }
+        }
+
+        Self::update(pool, |s| {
+            s.passes += 1;
+            s.last_unreadable_blocks = s.unreadable_blocks;
+            s.last_completed = Some(Utc::now());
+        });
+        info!(
+            "Pool '{pool}': media scan completed, {} unreadable block(s)",
+            Self::status(pool).map_or(0, |s| s.unreadable_blocks)
+        );
+
+        Ok(())
+    }
+
+    /// Updates the scrub status of the given pool.
+    fn update(pool: &str, f: impl FnOnce(&mut PoolScrubStatus)) {
+        let mut status = POOL_SCRUB_STATUS.lock();
+        f(status
+            .entry(pool.to_string())
+            .or_insert_with(|| PoolScrubStatus::new(pool, String::new())));
+    }
+
+    fn status(pool: &str) -> Option<PoolScrubStatus> {
+        POOL_SCRUB_STATUS.lock().get(pool).cloned()
+    }
+}
+
+/// Returns the scrub status of the given pool, or of all pools, by name or
+/// uuid. Pools which have not been scanned yet are reported idle.
+pub fn get_pool_scrub_status(pool: Option<&str>) -> Vec<PoolScrubStatus> {
+    Lvs::iter()
+        .filter(|lvs| pool.map_or(true, |p| p == lvs.name() || p == lvs.uuid()))
+        .map(|lvs| {
+            PoolScrubber::status(lvs.name()).unwrap_or_else(|| {
+                PoolScrubStatus::new(
+                    lvs.name(),
+                    lvs.base_bdev().name().to_string(),
+                )
+            })
+        })
+        .collect()
+}
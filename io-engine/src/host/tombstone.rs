@@ -0,0 +1,214 @@
+//!
+//! Two-phase destruction of replicas and nexuses. Instead of destroying a
+//! replica or a nexus right away, the control plane may mark it for
+//! deletion: the object is left untouched but hidden from the lists, and is
+//! destroyed once the grace period has passed, unless it is restored before.
+//! A control plane which deletes the wrong volume by mistake thus has the
+//! grace period to notice and take it back.
+//!
+//! Objects are purged under the same locks as the gRPC methods operating on
+//! them, so that a purge never races with the restore or the destruction of
+//! the object.
+//!
+//! Marks are kept in memory only: an object marked for deletion when the
+//! I/O engine restarts is not destroyed, and reappears in the lists. Losing
+//! a mark errs on the safe side, as it keeps data the control plane meant to
+//! delete rather than the opposite: the control plane, which reconciles the
+//! objects it expects to exist, deletes it again.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::{
+    bdev::nexus::nexus_lookup_uuid_mut,
+    core::{
+        lock::{ProtectedSubsystems, ResourceLockManager},
+        Bdev,
+        ResourceGeneration,
+        ResourceKind,
+    },
+    grpc::v1::replica::ReplicaService,
+    lvs::{Lvol, LvsLvol},
+    sleep::mayastor_sleep,
+};
+
+/// Pause between two checks for objects to purge.
+const PURGE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Settings of the two-phase destruction.
+#[derive(Debug, Clone, Copy)]
+pub struct TombstoneConfig {
+    /// Time an object marked for deletion is kept before it is destroyed.
+    pub grace_period: Duration,
+}
+
+impl Default for TombstoneConfig {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::from_secs(3600),
+        }
+    }
+}
+
+static TOMBSTONE_CONFIG: OnceCell<TombstoneConfig> = OnceCell::new();
+
+/// Objects marked for deletion and the time they were marked, by kind and
+/// uuid.
+static TOMBSTONES: Lazy<
+    parking_lot::Mutex<HashMap<(ResourceKind, String), DateTime<Utc>>>,
+> = Lazy::new(Default::default);
+
+/// Objects marked for deletion.
+pub struct Tombstones {}
+
+impl Tombstones {
+    /// Sets the grace period. Only the first call has an effect.
+    pub fn configure(config: TombstoneConfig) {
+        info!("Tombstones: {config:?}");
+
+        if TOMBSTONE_CONFIG.set(config).is_err() {
+            warn!("Tombstones have already been configured");
+        }
+    }
+
+    fn grace_period() -> chrono::Duration {
+        let grace_period = TOMBSTONE_CONFIG
+            .get()
+            .copied()
+            .unwrap_or_default()
+            .grace_period;
+        chrono::Duration::from_std(grace_period)
+            .unwrap_or_else(|_| chrono::Duration::max_value())
+    }
+
+    /// Marks an object for deletion, and returns the time it is going to be
+    /// destroyed. Marking an object again keeps its deadline.
+    pub fn mark(kind: ResourceKind, uuid: &str) -> DateTime<Utc> {
+        let marked_at = *TOMBSTONES
+            .lock()
+            .entry((kind, uuid.to_string()))
+            .or_insert_with(Utc::now);
+        let deadline = marked_at + Self::grace_period();
+        info!("{kind} {uuid}: marked for deletion, to be purged at {deadline}");
+        deadline
+    }
+
+    /// Removes the deletion mark of an object. Returns false if it was not
+    /// marked.
+    pub fn restore(kind: ResourceKind, uuid: &str) -> bool {
+        let restored = TOMBSTONES
+            .lock()
+            .remove(&(kind, uuid.to_string()))
+            .is_some();
+        if restored {
+            info!("{kind} {uuid}: restored");
+        }
+        restored
+    }
+
+    /// Removes the deletion mark of a destroyed object.
+    pub fn forget(kind: ResourceKind, uuid: &str) {
+        TOMBSTONES.lock().remove(&(kind, uuid.to_string()));
+    }
+
+    /// Returns the time a marked object is going to be destroyed, `None` if
+    /// it is not marked.
+    pub fn deadline(kind: ResourceKind, uuid: &str) -> Option<DateTime<Utc>> {
+        TOMBSTONES
+            .lock()
+            .get(&(kind, uuid.to_string()))
+            .map(|t| *t + Self::grace_period())
+    }
+
+    /// Checks if an object is marked for deletion.
+    pub fn is_marked(kind: ResourceKind, uuid: &str) -> bool {
+        TOMBSTONES.lock().contains_key(&(kind, uuid.to_string()))
+    }
+
+    /// Checks if an object is marked for deletion and its grace period has
+    /// passed.
+    fn is_expired(kind: ResourceKind, uuid: &str) -> bool {
+        Self::deadline(kind, uuid).map_or(false, |t| t <= Utc::now())
+    }
+
+    /// Destroys the marked objects whose grace period has passed, forever.
+    /// Must be called on the primary reactor.
+    pub async fn run() {
+        loop {
+            if mayastor_sleep(PURGE_CHECK_INTERVAL).await.is_err() {
+                return;
+            }
+            Self::purge_expired().await;
+        }
+    }
+
+    /// Destroys the marked objects whose grace period has passed.
+    pub async fn purge_expired() {
+        let now = Utc::now();
+        let grace_period = Self::grace_period();
+        let expired = TOMBSTONES
+            .lock()
+            .iter()
+            .filter(|(_, t)| **t + grace_period <= now)
+            .map(|(k, _)| k.clone())
+            .collect::<Vec<_>>();
+
+        for (kind, uuid) in expired {
+            match Self::purge(kind, &uuid).await {
+                Ok(true) => info!("{kind} {uuid}: purged"),
+                Ok(false) => {
+                    info!("{kind} {uuid}: restored or destroyed, not purged");
+                }
+                Err(error) => warn!("{kind} {uuid}: failed to purge: {error}"),
+            }
+        }
+    }
+
+    /// Destroys a marked object under the locks of the gRPC methods operating
+    /// on it, unless it has been restored or destroyed in the meantime. An
+    /// object which no longer exists counts as destroyed. Returns false if
+    /// the object is no longer to be purged.
+    async fn purge(kind: ResourceKind, uuid: &str) -> Result<bool, String> {
+        match kind {
+            ResourceKind::Replica => {
+                let _guard = ReplicaService::lock().await;
+                if !Self::is_expired(kind, uuid) {
+                    return Ok(false);
+                }
+
+                if let Some(lvol) = Bdev::lookup_by_uuid_str(uuid)
+                    .and_then(|b| Lvol::try_from(b).ok())
+                {
+                    lvol.destroy_replica().await.map_err(|e| e.to_string())?;
+                }
+                Self::purged(kind, uuid);
+            }
+            ResourceKind::Nexus => {
+                let lock_manager = ResourceLockManager::get_instance();
+                let _global_guard = lock_manager.lock(None).await;
+                let _resource_guard = lock_manager
+                    .get_subsystem(ProtectedSubsystems::NEXUS)
+                    .lock_resource(uuid, None)
+                    .await;
+                if !Self::is_expired(kind, uuid) {
+                    return Ok(false);
+                }
+
+                if let Some(nexus) = nexus_lookup_uuid_mut(uuid) {
+                    nexus.destroy().await.map_err(|e| e.to_string())?;
+                }
+                Self::purged(kind, uuid);
+            }
+            ResourceKind::Pool => Self::purged(kind, uuid),
+        }
+        Ok(true)
+    }
+
+    /// Forgets a purged object.
+    fn purged(kind: ResourceKind, uuid: &str) {
+        Self::forget(kind, uuid);
+        ResourceGeneration::forget(kind, uuid);
+    }
+}
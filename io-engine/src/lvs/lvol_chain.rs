@@ -0,0 +1,199 @@
+//! Limits the snapshot and clone chains of replicas.
+//!
+//! Every snapshot of a replica and every clone generation adds a blob to the
+//! backing chain which reads of unallocated clusters walk through, so long
+//! chains silently degrade performance. The number of snapshots per replica
+//! and the depth of clone chains can be limited, and a clone can be flattened
+//! to detach it from the chain it was created from.
+use std::convert::TryFrom;
+
+use futures::channel::oneshot;
+use nix::errno::Errno;
+use once_cell::sync::OnceCell;
+use spdk_rs::libspdk::{
+    spdk_blob_get_id,
+    spdk_blob_get_parent_snapshot,
+    spdk_blob_is_clone,
+    spdk_blob_reset_used_clusters_cache,
+    spdk_lvol_decouple_parent,
+};
+
+use super::{Error, Lvol, LvsLvol};
+use crate::{
+    core::{
+        logical_volume::LogicalVolume,
+        CloneXattrs,
+        SnapshotOps,
+        SnapshotXattrs,
+        UntypedBdev,
+    },
+    ffihelper::{cb_arg, done_errno_cb, ErrnoResult},
+};
+
+/// Configured limits of the snapshot and clone chains.
+static LVOL_CHAIN_LIMITS: OnceCell<LvolChainLimits> = OnceCell::new();
+
+/// Limits of the snapshot and clone chains of replicas.
+/// A limit of 0 disables it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LvolChainLimits {
+    /// Maximum number of snapshots of a replica.
+    pub max_snapshots: u32,
+    /// Maximum number of clone generations a clone descends from.
+    pub max_clone_depth: u32,
+}
+
+impl LvolChainLimits {
+    /// Sets the limits of the snapshot and clone chains.
+    pub fn configure(self) {
+        if LVOL_CHAIN_LIMITS.set(self).is_err() {
+            warn!("Snapshot and clone chain limits are already configured");
+        }
+    }
+
+    /// Returns the configured limits.
+    fn get() -> Self {
+        LVOL_CHAIN_LIMITS.get().copied().unwrap_or_default()
+    }
+}
+
+impl Lvol {
+    /// Returns the number of snapshots taken of the lvol, including the
+    /// discarded snapshots still backing clones.
+    pub fn snapshot_count(&self) -> u32 {
+        self.list_snapshot_by_source_uuid().len() as u32
+    }
+
+    /// Returns the number of clone generations the lvol descends from, 0 if
+    /// it is not a clone.
+    pub fn clone_chain_depth(&self) -> u32 {
+        let mut depth = 0;
+        let mut lvol = self.clone();
+        while let Some(snapshot) = lvol.is_snapshot_clone() {
+            depth += 1;
+            match snapshot.snapshot_parent() {
+                Some(parent) => lvol = parent,
+                None => break,
+            }
+        }
+        depth
+    }
+
+    /// Returns the snapshot the lvol is directly backed by: its most recent
+    /// snapshot, or the snapshot it was cloned from if it has not been
+    /// snapshotted since.
+    pub fn latest_snapshot(&self) -> Option<Lvol> {
+        let lvs = self.lvs();
+        let parent_id = unsafe {
+            spdk_blob_get_parent_snapshot(
+                lvs.blob_store(),
+                spdk_blob_get_id(self.blob_checked()),
+            )
+        };
+        lvs.lvols()?.find(|lvol| unsafe {
+            spdk_blob_get_id(lvol.blob_checked()) == parent_id
+        })
+    }
+
+    /// Returns the lvol a snapshot was taken from, if it still exists.
+    fn snapshot_parent(&self) -> Option<Lvol> {
+        Lvol::get_blob_xattr(self, SnapshotXattrs::ParentId.name())
+            .and_then(|uuid| UntypedBdev::lookup_by_uuid_str(&uuid))
+            .and_then(|bdev| Lvol::try_from(bdev).ok())
+    }
+
+    /// Checks another snapshot of the lvol stays within the snapshot limit.
+    pub(crate) fn check_snapshot_limit(&self) -> Result<(), Error> {
+        let limit = LvolChainLimits::get().max_snapshots;
+        if limit == 0 {
+            return Ok(());
+        }
+        let count = self.snapshot_count();
+        if count >= limit {
+            return Err(Error::SnapshotLimit {
+                name: self.name(),
+                count,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks a clone of the snapshot lvol stays within the clone chain
+    /// depth limit.
+    pub(crate) fn check_clone_depth_limit(&self) -> Result<(), Error> {
+        let limit = LvolChainLimits::get().max_clone_depth;
+        if limit == 0 {
+            return Ok(());
+        }
+        let depth = self
+            .snapshot_parent()
+            .map_or(0, |parent| parent.clone_chain_depth())
+            + 1;
+        if depth > limit {
+            return Err(Error::CloneDepthLimit {
+                name: self.name(),
+                depth,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Detaches a clone from its snapshot chain by copying the clusters it
+    /// references from its ancestors. The clone stays thin provisioned and
+    /// is no longer reported as a clone of its snapshot.
+    pub async fn flatten(&self) -> Result<(), Error> {
+        let err = |source: Errno, msg: &str| Error::FlattenClone {
+            source,
+            name: self.name(),
+            msg: msg.to_string(),
+        };
+
+        let Some(snapshot) = self.is_snapshot_clone() else {
+            return Err(err(Errno::EINVAL, "replica is not a clone"));
+        };
+        // The snapshots of the clone would still depend on the chain.
+        if self.snapshot_count() > 0 {
+            return Err(err(Errno::EBUSY, "clone has snapshots"));
+        }
+
+        info!("{self:?}: flattening clone of {snapshot:?}");
+
+        // Each decouple copies the clusters allocated in the parent blob and
+        // moves the clone onto its grandparent, until it has no parent left.
+        while unsafe { spdk_blob_is_clone(self.blob_checked()) } {
+            let (s, r) = oneshot::channel::<ErrnoResult<()>>();
+            unsafe {
+                spdk_lvol_decouple_parent(
+                    self.as_inner_ptr(),
+                    Some(done_errno_cb),
+                    cb_arg(s),
+                )
+            };
+            r.await
+                .expect("lvol decouple callback is gone")
+                .map_err(|source| {
+                    err(source, "failed to decouple from the parent blob")
+                })?;
+        }
+
+        self.remove_blob_attr(CloneXattrs::SourceUuid.name(), true)
+            .await?;
+        unsafe {
+            spdk_blob_reset_used_clusters_cache(self.blob_checked());
+        }
+
+        // The clone no longer holds the snapshot, destroy it if it was only
+        // kept for its clones.
+        if snapshot.list_clones_by_snapshot_uuid().is_empty()
+            && snapshot.is_discarded_snapshot()
+        {
+            snapshot.reset_snapshot_parent_successor_usage_cache();
+            snapshot.destroy().await?;
+        }
+
+        info!("{self:?}: clone flattened");
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! Implements compressed replicas on top of the SPDK compress vbdev.
+//!
+//! A compressed replica stacks a compress (reduce) vbdev on top of its thin
+//! provisioned lvol. The lvol is claimed by the vbdev, so the replica is
+//! shared through the compressed bdev instead. The reduce persistent memory
+//! file of each replica lives in its own directory under the configured
+//! persistent memory path.
+use std::{
+    convert::TryInto,
+    ffi::CString,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures::channel::oneshot;
+use nix::errno::Errno;
+use once_cell::sync::OnceCell;
+use spdk_rs::libspdk::{bdev_compress_delete, create_compress_bdev};
+
+use super::{Error, Lvol, LvsLvol};
+use crate::{
+    core::{logical_volume::LogicalVolume, Bdev, UntypedBdev},
+    ffihelper::{cb_arg, done_errno_cb, ErrnoResult},
+    sleep::mayastor_sleep,
+};
+
+/// Prefix the compress vbdev puts in front of the name of its base bdev.
+const COMPRESS_BDEV_PREFIX: &str = "COMP_";
+
+/// Time to wait for the compressed bdev to be registered.
+const COMPRESS_CREATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Size of the reduce superblock at the start of the persistent memory file.
+const REDUCE_SUPERBLOCK_SIZE: u64 = 4096;
+/// Offset of the chunk size within the reduce superblock.
+const REDUCE_CHUNK_SIZE_OFFSET: u64 = 32;
+/// Offset of the volume size within the reduce superblock.
+const REDUCE_VOL_SIZE_OFFSET: u64 = 40;
+/// Value of a logical map entry which has no chunk mapped.
+const REDUCE_EMPTY_MAP_ENTRY: u64 = u64::MAX;
+
+/// Persistent memory path of compressed replicas, if configured.
+static COMPRESS_PM_PATH: OnceCell<Option<PathBuf>> = OnceCell::new();
+
+/// Configuration of compressed replicas.
+pub struct LvolCompress;
+
+impl LvolCompress {
+    /// Sets the directory for the persistent memory files of compressed
+    /// replicas. Compression is disabled if no path is given.
+    pub fn configure(pm_path: Option<String>) {
+        if COMPRESS_PM_PATH.set(pm_path.map(PathBuf::from)).is_err() {
+            warn!("Replica compression is already configured");
+        }
+    }
+
+    /// Returns the configured persistent memory path.
+    fn pm_path() -> Option<&'static PathBuf> {
+        COMPRESS_PM_PATH.get().and_then(|p| p.as_ref())
+    }
+}
+
+impl Lvol {
+    /// Stacks a compress vbdev on top of the lvol. The lvol must be thin
+    /// provisioned and must not be shared yet.
+    pub async fn compress(&self) -> Result<UntypedBdev, Error> {
+        let err = |source: Errno, msg: String| Error::Compress {
+            source,
+            name: self.name(),
+            msg,
+        };
+
+        if let Some(bdev) = self.compressed_bdev() {
+            return Ok(bdev);
+        }
+        let Some(pm_path) = LvolCompress::pm_path() else {
+            return Err(err(
+                Errno::ENOTSUP,
+                "compression persistent memory path is not configured".into(),
+            ));
+        };
+        if !self.is_thin() {
+            return Err(err(
+                Errno::EINVAL,
+                "only thin provisioned replicas can be compressed".into(),
+            ));
+        }
+
+        let pm_dir = self.compress_pm_dir(pm_path);
+        std::fs::create_dir_all(&pm_dir).map_err(|e| {
+            err(Errno::EIO, format!("failed to create {pm_dir:?}: {e}"))
+        })?;
+
+        let cname = CString::new(self.as_bdev().name()).unwrap();
+        let cpm = CString::new(pm_dir.to_string_lossy().as_bytes()).unwrap();
+        let rc =
+            unsafe { create_compress_bdev(cname.as_ptr(), cpm.as_ptr(), 0) };
+        if rc != 0 {
+            std::fs::remove_dir_all(&pm_dir).ok();
+            return Err(err(
+                Errno::from_i32(rc.abs()),
+                "failed to create the compress bdev".into(),
+            ));
+        }
+
+        // The reduce volume is initialised asynchronously, the compressed
+        // bdev shows up once it is ready.
+        let step = Duration::from_millis(100);
+        let mut waited = Duration::ZERO;
+        loop {
+            if let Some(bdev) = self.compressed_bdev() {
+                info!("{self:?}: compressed as '{}'", bdev.name());
+                return Ok(bdev);
+            }
+            if waited >= COMPRESS_CREATE_TIMEOUT {
+                std::fs::remove_dir_all(&pm_dir).ok();
+                return Err(err(
+                    Errno::ETIMEDOUT,
+                    "timed out waiting for the compress bdev".into(),
+                ));
+            }
+            mayastor_sleep(step).await.ok();
+            waited += step;
+        }
+    }
+
+    /// Returns the compress vbdev stacked on top of the lvol, if any.
+    pub fn compressed_bdev(&self) -> Option<UntypedBdev> {
+        Bdev::lookup_by_name(&format!(
+            "{COMPRESS_BDEV_PREFIX}{}",
+            self.as_bdev().name()
+        ))
+    }
+
+    /// Returns the bdev the replica is shared through: the compressed bdev
+    /// for compressed replicas, the lvol bdev otherwise.
+    pub(crate) fn share_bdev(&self) -> UntypedBdev {
+        self.compressed_bdev().unwrap_or_else(|| self.as_bdev())
+    }
+
+    /// Returns the compression ratio of a compressed replica: the amount of
+    /// data written to the replica divided by the space it takes in the pool.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        self.compressed_bdev()?;
+        let pm_dir = self.compress_pm_dir(LvolCompress::pm_path()?);
+
+        let logical = match Self::reduce_mapped_bytes(pm_dir) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                debug!("{self:?}: failed to read the reduce map: {error}");
+                return None;
+            }
+        };
+
+        match self.usage().allocated_bytes {
+            0 => Some(1.0),
+            allocated => Some(logical as f64 / allocated as f64),
+        }
+    }
+
+    /// Deletes the compress vbdev stacked on top of the lvol, along with its
+    /// persistent memory.
+    pub(crate) async fn destroy_compression(&self) -> Result<(), Error> {
+        let Some(bdev) = self.compressed_bdev() else {
+            return Ok(());
+        };
+
+        let cname = CString::new(bdev.name()).unwrap();
+        let (s, r) = oneshot::channel::<ErrnoResult<()>>();
+        unsafe {
+            bdev_compress_delete(cname.as_ptr(), Some(done_errno_cb), cb_arg(s))
+        };
+        r.await.expect("compress delete callback is gone").map_err(
+            |source| Error::Compress {
+                source,
+                name: self.name(),
+                msg: "failed to delete the compress bdev".into(),
+            },
+        )?;
+
+        if let Some(pm_path) = LvolCompress::pm_path() {
+            std::fs::remove_dir_all(self.compress_pm_dir(pm_path)).ok();
+        }
+
+        info!("{self:?}: compression removed");
+        Ok(())
+    }
+
+    /// Returns the persistent memory directory of the replica.
+    fn compress_pm_dir(&self, pm_path: &Path) -> PathBuf {
+        pm_path.join(self.uuid())
+    }
+
+    /// Returns the number of bytes mapped by the logical map of the reduce
+    /// volume kept in the given persistent memory directory.
+    fn reduce_mapped_bytes(pm_dir: PathBuf) -> std::io::Result<u64> {
+        let path = std::fs::read_dir(pm_dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| p.is_file())
+            .ok_or_else(|| {
+                std::io::Error::from(std::io::ErrorKind::NotFound)
+            })?;
+        let mut file = File::open(path)?;
+
+        let mut read_u64 = |offset: u64| -> std::io::Result<u64> {
+            let mut buf = [0u8; 8];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buf)?;
+            Ok(u64::from_le_bytes(buf))
+        };
+        let chunk_size = read_u64(REDUCE_CHUNK_SIZE_OFFSET)? & 0xffff_ffff;
+        let vol_size = read_u64(REDUCE_VOL_SIZE_OFFSET)?;
+        if chunk_size == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidData));
+        }
+
+        let entries = vol_size / chunk_size;
+        let mut map = vec![0u8; (entries * 8) as usize];
+        file.seek(SeekFrom::Start(REDUCE_SUPERBLOCK_SIZE))?;
+        file.read_exact(&mut map)?;
+
+        let mapped = map
+            .chunks_exact(8)
+            .filter(|e| {
+                u64::from_le_bytes((*e).try_into().unwrap())
+                    != REDUCE_EMPTY_MAP_ENTRY
+            })
+            .count() as u64;
+
+        Ok(mapped * chunk_size)
+    }
+}
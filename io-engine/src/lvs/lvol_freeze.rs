@@ -0,0 +1,180 @@
+//! Implements freeze/thaw (consistency quiesce) operations on a replica.
+//!
+//! Freezing a replica pauses its NVMf subsystem, so that writes from remote
+//! initiators are held, and flushes the underlying lvol.
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use nix::errno::Errno;
+use once_cell::sync::Lazy;
+
+use super::{Error, Lvol, LvsLvol};
+use crate::{
+    core::{logical_volume::LogicalVolume, Bdev, Protocol, Reactors, Share},
+    sleep::mayastor_sleep,
+    subsys::NvmfSubsystem,
+};
+
+/// Maximum time a replica is allowed to stay frozen.
+pub const LVOL_FREEZE_MAX_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Information about an active replica freeze.
+#[derive(Debug, Clone, Copy)]
+pub struct LvolFreeze {
+    /// Time when the replica was frozen.
+    pub frozen_at: DateTime<Utc>,
+    /// Time when the replica is going to be thawed automatically.
+    pub deadline: DateTime<Utc>,
+    /// Freeze generation, used to match an auto-thaw with its freeze.
+    generation: u64,
+}
+
+/// Freeze generation counter, shared by all replicas.
+static FREEZE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Active replica freezes, indexed by replica UUID.
+static LVOL_FREEZES: Lazy<parking_lot::Mutex<HashMap<String, LvolFreeze>>> =
+    Lazy::new(Default::default);
+
+impl Lvol {
+    /// Freezes the replica: pauses its NVMf subsystem and flushes the lvol.
+    /// The replica stays frozen until `thaw()` is called or the given timeout
+    /// expires, whichever comes first.
+    pub async fn freeze(&self, timeout: Duration) -> Result<LvolFreeze, Error> {
+        if timeout.is_zero() || timeout > LVOL_FREEZE_MAX_TIMEOUT {
+            return Err(Error::Freeze {
+                source: Errno::EINVAL,
+                name: self.name(),
+                msg: format!(
+                    "freeze timeout must be within (0, {}s]",
+                    LVOL_FREEZE_MAX_TIMEOUT.as_secs()
+                ),
+            });
+        }
+
+        if let Some(f) = self.freeze_info() {
+            return Err(Error::Freeze {
+                source: Errno::EALREADY,
+                name: self.name(),
+                msg: format!("already frozen until {}", f.deadline),
+            });
+        }
+
+        let Some(subsystem) = self.nvmf_subsystem() else {
+            return Err(Error::Freeze {
+                source: Errno::EINVAL,
+                name: self.name(),
+                msg: "replica is not shared over NVMf".to_string(),
+            });
+        };
+
+        info!("{self:?}: freezing replica for {timeout:?}...");
+
+        subsystem.pause().await.map_err(|e| Error::Freeze {
+            source: Errno::EIO,
+            name: self.name(),
+            msg: format!("failed to pause subsystem: {e}"),
+        })?;
+
+        let flushed = match Bdev::open(&self.share_bdev(), true)
+            .and_then(|desc| desc.into_handle())
+        {
+            Ok(hdl) => hdl.flush().await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = flushed {
+            error!("{self:?}: failed to flush, aborting freeze: {e}");
+            subsystem.resume().await.ok();
+            return Err(Error::FlushFailed {
+                name: self.name(),
+            });
+        }
+
+        let frozen_at = Utc::now();
+        let freeze = LvolFreeze {
+            frozen_at,
+            deadline: frozen_at
+                + chrono::Duration::from_std(timeout).unwrap_or_default(),
+            generation: FREEZE_GENERATION.fetch_add(1, Ordering::SeqCst),
+        };
+        LVOL_FREEZES.lock().insert(self.uuid(), freeze);
+
+        let uuid = self.uuid();
+        Reactors::master().send_future(async move {
+            if mayastor_sleep(timeout).await.is_err() {
+                error!("Replica '{uuid}': failed to wait for freeze deadline");
+            }
+
+            let expired = matches!(
+                LVOL_FREEZES.lock().get(&uuid),
+                Some(f) if f.generation == freeze.generation
+            );
+            if !expired {
+                return;
+            }
+
+            let Some(lvol) = Bdev::lookup_by_uuid_str(&uuid)
+                .and_then(|b| Lvol::try_from(b).ok())
+            else {
+                LVOL_FREEZES.lock().remove(&uuid);
+                return;
+            };
+
+            warn!("{lvol:?}: freeze deadline expired, thawing replica");
+            if let Err(error) = lvol.thaw().await {
+                error!("{lvol:?}: failed to thaw replica on deadline: {error}");
+            }
+        });
+
+        info!(
+            "{self:?}: replica frozen until {deadline}",
+            deadline = freeze.deadline
+        );
+
+        Ok(freeze)
+    }
+
+    /// Thaws a previously frozen replica, resuming I/O.
+    pub async fn thaw(&self) -> Result<(), Error> {
+        if LVOL_FREEZES.lock().remove(&self.uuid()).is_none() {
+            return Err(Error::Freeze {
+                source: Errno::EALREADY,
+                name: self.name(),
+                msg: "replica is not frozen".to_string(),
+            });
+        }
+
+        info!("{self:?}: thawing replica...");
+
+        if let Some(subsystem) = self.nvmf_subsystem() {
+            subsystem.resume().await.map_err(|e| Error::Freeze {
+                source: Errno::EIO,
+                name: self.name(),
+                msg: format!("failed to resume subsystem: {e}"),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the active freeze of the replica, if any.
+    pub fn freeze_info(&self) -> Option<LvolFreeze> {
+        LVOL_FREEZES.lock().get(&self.uuid()).copied()
+    }
+
+    /// Returns the NVMf subsystem the replica is shared with, if any.
+    pub(super) fn nvmf_subsystem(&self) -> Option<NvmfSubsystem> {
+        match self.shared() {
+            Some(Protocol::Nvmf) => {
+                NvmfSubsystem::nqn_lookup(&self.share_bdev().name())
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Implements the ownership of replicas.
+//!
+//! Nodes can be shared by several control planes, each managing its own
+//! volumes. A replica records the volume and the control plane instance it
+//! belongs to in the lvol metadata, and the operations which destroy or
+//! (un)share it must then be requested by the same owner, unless the owner is
+//! explicitly overridden. Replicas without an owner can be mutated by anyone.
+use std::fmt::{Display, Formatter};
+
+use nix::errno::Errno;
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Lvol};
+use crate::core::logical_volume::LogicalVolume;
+
+/// Name of the lvol xattr holding the replica owner.
+const LVOL_OWNER_XATTR: &str = "owner";
+
+/// Owner of a replica.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicaOwner {
+    /// UUID of the volume the replica belongs to.
+    pub volume_uuid: String,
+    /// Identifier of the control plane instance managing the volume.
+    pub control_plane: String,
+}
+
+impl Display for ReplicaOwner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "volume {} of control plane '{}'",
+            self.volume_uuid, self.control_plane
+        )
+    }
+}
+
+impl Lvol {
+    /// Returns the owner of the replica, if any.
+    pub fn owner(&self) -> Option<ReplicaOwner> {
+        Lvol::get_blob_xattr(self, LVOL_OWNER_XATTR)
+            .and_then(|owner| serde_json::from_str(&owner).ok())
+    }
+
+    /// Sets the owner of the replica, or removes it.
+    pub async fn set_owner(
+        &self,
+        owner: Option<&ReplicaOwner>,
+    ) -> Result<(), Error> {
+        let Some(owner) = owner else {
+            info!("{self:?}: removing replica owner");
+            return self.remove_blob_attr(LVOL_OWNER_XATTR, true).await;
+        };
+
+        let value =
+            serde_json::to_string(owner).map_err(|e| Error::SetProperty {
+                source: Errno::EINVAL,
+                prop: format!("{LVOL_OWNER_XATTR}: {e}"),
+                name: self.name(),
+            })?;
+        info!("{self:?}: setting replica owner to {owner}");
+        self.set_blob_attr(LVOL_OWNER_XATTR, value, true).await
+    }
+
+    /// Checks that an operation on the replica is requested by its owner,
+    /// unless the owner is overridden.
+    pub fn check_owner(
+        &self,
+        owner: Option<&ReplicaOwner>,
+        override_owner: bool,
+    ) -> Result<(), Error> {
+        let Some(current) = self.owner() else {
+            return Ok(());
+        };
+        if owner == Some(&current) {
+            return Ok(());
+        }
+
+        let requester = match owner {
+            Some(owner) => owner.to_string(),
+            None => "no owner".to_string(),
+        };
+        if override_owner {
+            warn!(
+                "{self:?}: overriding replica owner {current}, requested by \
+                {requester}"
+            );
+            return Ok(());
+        }
+        Err(Error::ReplicaOwner {
+            name: self.name(),
+            owner: current.to_string(),
+            requester,
+        })
+    }
+}
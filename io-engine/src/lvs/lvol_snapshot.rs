@@ -7,6 +7,7 @@ use crate::{
         SnapshotOps,
         SnapshotParams,
         SnapshotXattrs,
+        ToErrno,
         UntypedBdev,
     },
     ffihelper::{cb_arg, IntoCString},
@@ -205,6 +206,8 @@ impl SnapshotOps for Lvol {
         done_cb: unsafe extern "C" fn(*mut c_void, *mut spdk_lvol, i32),
         done_cb_arg: *mut ::std::os::raw::c_void,
     ) -> Result<(), Error> {
+        self.check_snapshot_limit()?;
+
         let mut attr_descrs: [spdk_xattr_descriptor; SnapshotXattrs::COUNT] =
             [spdk_xattr_descriptor::default(); SnapshotXattrs::COUNT];
 
@@ -359,6 +362,8 @@ impl SnapshotOps for Lvol {
         done_cb: unsafe extern "C" fn(*mut c_void, *mut spdk_lvol, i32),
         done_cb_arg: *mut ::std::os::raw::c_void,
     ) -> Result<(), Error> {
+        self.check_clone_depth_limit()?;
+
         let mut attr_descrs: [spdk_xattr_descriptor; CloneXattrs::COUNT] =
             [spdk_xattr_descriptor::default(); CloneXattrs::COUNT];
 
@@ -552,6 +557,9 @@ impl SnapshotOps for Lvol {
                 volume = self.name(),
                 "Failed to create remote snapshot"
             );
+            // The done callback is not called if the snapshot could not be
+            // started, complete the request here.
+            nvmf_req.complete_error(error.to_errno() as i32);
         }
     }
     /// Get a Snapshot Iterator.
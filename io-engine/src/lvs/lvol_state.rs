@@ -0,0 +1,119 @@
+//! Implements the read-only and maintenance modes of a replica.
+//!
+//! A read-only replica keeps its share but rejects writes with the Namespace
+//! Is Write Protected status. A replica in maintenance also disconnects its
+//! hosts and rejects new connections, so that the lvol can be checked or
+//! copied locally without destroying its share. The state is stored in the
+//! lvol metadata and applied again whenever the replica is shared.
+use std::{pin::Pin, str::FromStr};
+
+use nix::errno::Errno;
+use strum_macros::{AsRefStr, Display, EnumString};
+
+use super::{Error, Lvol, LvsLvol, PropName, PropValue};
+use crate::core::{logical_volume::LogicalVolume, Share, UpdateProps};
+
+/// Name of the lvol xattr holding the replica state.
+const LVOL_STATE_XATTR: &str = "state";
+
+/// State of a replica.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, AsRefStr, Display, EnumString,
+)]
+pub enum LvolState {
+    /// The replica serves reads and writes.
+    #[default]
+    Online,
+    /// The replica serves reads only.
+    ReadOnly,
+    /// The replica serves no I/O and accepts no connections.
+    Maintenance,
+}
+
+impl Lvol {
+    /// Returns the state of the replica.
+    pub fn state(&self) -> LvolState {
+        Lvol::get_blob_xattr(self, LVOL_STATE_XATTR)
+            .and_then(|state| LvolState::from_str(&state).ok())
+            .unwrap_or_default()
+    }
+
+    /// Sets the state of the replica, and applies it to its share.
+    pub async fn set_state(&self, state: LvolState) -> Result<(), Error> {
+        if self.is_snapshot() {
+            return Err(Error::ReplicaState {
+                source: Errno::EINVAL,
+                name: self.name(),
+                msg: "snapshots are always read-only".to_string(),
+            });
+        }
+
+        let previous = self.state();
+        if previous == state {
+            return Ok(());
+        }
+
+        info!("{self:?}: changing replica state from {previous} to {state}");
+
+        self.set_blob_attr(LVOL_STATE_XATTR, state.to_string(), true)
+            .await?;
+        self.apply_state(previous).await
+    }
+
+    /// Applies the state of the replica to its share, given the previous state
+    /// of the replica.
+    pub(super) async fn apply_state(
+        &self,
+        previous: LvolState,
+    ) -> Result<(), Error> {
+        let Some(subsystem) = self.nvmf_subsystem() else {
+            return Ok(());
+        };
+        let state = self.state();
+        let err = |msg: String| Error::ReplicaState {
+            source: Errno::EIO,
+            name: self.name(),
+            msg,
+        };
+
+        subsystem
+            .set_write_protect(state != LvolState::Online)
+            .map_err(|e| err(format!("failed to set write protection: {e}")))?;
+
+        match state {
+            LvolState::Maintenance => {
+                subsystem.disallow_all_hosts().await.map_err(|e| {
+                    err(format!("failed to disconnect hosts: {e}"))
+                })?;
+            }
+            _ if previous == LvolState::Maintenance => {
+                let hosts = match self.get(PropName::AllowedHosts).await {
+                    Ok(PropValue::AllowedHosts(hosts)) => hosts,
+                    _ => vec![],
+                };
+                Pin::new(&mut self.share_bdev())
+                    .update_properties(
+                        UpdateProps::new().with_allowed_hosts(hosts),
+                    )
+                    .await
+                    .map_err(|e| err(format!("failed to allow hosts: {e}")))?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Checks the replica is not in maintenance, so that hosts can be allowed
+    /// to connect.
+    pub(super) fn check_not_in_maintenance(&self) -> Result<(), Error> {
+        if self.state() == LvolState::Maintenance {
+            return Err(Error::ReplicaState {
+                source: Errno::EBUSY,
+                name: self.name(),
+                msg: "replica is in maintenance".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
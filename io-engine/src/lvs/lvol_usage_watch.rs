@@ -0,0 +1,154 @@
+//! Notifications of the growth of thin replicas.
+//!
+//! Capacity tracking of thin replicas would otherwise require listing all
+//! the replicas periodically. A watch samples the allocated size of the thin
+//! replicas and reports a replica once it has grown, since it was last
+//! reported, by at least the given number of bytes or percentage of its
+//! capacity. A replica which shrinks is not reported, but its growth is
+//! measured from its new size.
+use std::collections::HashMap;
+
+use super::{Lvol, LvolSpaceUsage, LvsLvol};
+use crate::core::UntypedBdev;
+
+/// Thresholds of a usage watch. A threshold of 0 disables it; a replica is
+/// reported once either enabled threshold is reached.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LvolUsageThresholds {
+    /// Growth of the allocated size, in bytes.
+    pub delta_bytes: u64,
+    /// Growth of the allocated size, in percent of the capacity.
+    pub delta_percent: u32,
+}
+
+impl LvolUsageThresholds {
+    /// Checks if growing from `previous` to `current` allocated bytes reaches
+    /// a threshold, for a replica of the given capacity.
+    fn reached(&self, previous: u64, current: u64, capacity: u64) -> bool {
+        let Some(growth) = current.checked_sub(previous).filter(|g| *g > 0)
+        else {
+            return false;
+        };
+        let bytes = self.delta_bytes > 0 && growth >= self.delta_bytes;
+        let percent = self.delta_percent > 0
+            && capacity > 0
+            && growth as u128 * 100
+                >= capacity as u128 * self.delta_percent as u128;
+        bytes || percent || (self.delta_bytes == 0 && self.delta_percent == 0)
+    }
+}
+
+/// Usage of a replica sampled by a watch.
+#[derive(Debug, Clone)]
+pub struct LvolUsageSample {
+    /// Uuid of the replica.
+    pub uuid: String,
+    /// Name of the replica.
+    pub name: String,
+    /// Uuid of the pool of the replica.
+    pub pool_uuid: String,
+    /// Space usage of the replica.
+    pub usage: LvolSpaceUsage,
+}
+
+impl LvolUsageSample {
+    /// Samples the usage of the thin replicas of the given pool, or of all
+    /// pools, by name or uuid.
+    pub fn sample(pool: Option<&str>) -> Vec<Self> {
+        let Some(bdev) = UntypedBdev::bdev_first() else {
+            return Vec::new();
+        };
+        bdev.into_iter()
+            .filter(|b| b.driver() == "lvol")
+            .filter_map(|b| Lvol::try_from(b).ok())
+            .filter(|l| l.is_thin() && !l.is_snapshot())
+            .filter(|l| {
+                pool.map_or(true, |p| l.pool_uuid() == p || l.pool_name() == p)
+            })
+            .map(|l| Self {
+                uuid: l.uuid(),
+                name: l.name(),
+                pool_uuid: l.pool_uuid(),
+                usage: l.usage(),
+            })
+            .collect()
+    }
+}
+
+/// Growth of a replica reported by a watch.
+#[derive(Debug, Clone)]
+pub struct LvolUsageChange {
+    /// Current usage of the replica.
+    pub sample: LvolUsageSample,
+    /// Allocated bytes when the replica was last reported, `None` if the
+    /// replica is reported for the first time.
+    pub previous_allocated_bytes: Option<u64>,
+}
+
+/// Watch of the growth of thin replicas.
+#[derive(Debug)]
+pub struct LvolUsageWatch {
+    thresholds: LvolUsageThresholds,
+    /// Report the replicas when they are first seen.
+    report_new: bool,
+    /// Allocated bytes of the replicas when they were last reported.
+    baselines: HashMap<String, u64>,
+}
+
+impl LvolUsageWatch {
+    /// Creates a watch with the given thresholds. With `report_new`, the
+    /// replicas are also reported the first time they are seen, which gives
+    /// the initial usage of all the replicas.
+    pub fn new(thresholds: LvolUsageThresholds, report_new: bool) -> Self {
+        Self {
+            thresholds,
+            report_new,
+            baselines: HashMap::new(),
+        }
+    }
+
+    /// Returns the replicas of the samples which have grown past a
+    /// threshold since they were last reported, and forgets the replicas
+    /// which are no longer sampled.
+    pub fn update(
+        &mut self,
+        samples: Vec<LvolUsageSample>,
+    ) -> Vec<LvolUsageChange> {
+        self.baselines
+            .retain(|uuid, _| samples.iter().any(|s| &s.uuid == uuid));
+
+        let mut changes = Vec::new();
+        for sample in samples {
+            let current = sample.usage.allocated_bytes;
+            match self.baselines.get(&sample.uuid).copied() {
+                None => {
+                    self.baselines.insert(sample.uuid.clone(), current);
+                    if self.report_new {
+                        changes.push(LvolUsageChange {
+                            sample,
+                            previous_allocated_bytes: None,
+                        });
+                    }
+                }
+                Some(previous) if current < previous => {
+                    self.baselines.insert(sample.uuid.clone(), current);
+                }
+                Some(previous)
+                    if self.thresholds.reached(
+                        previous,
+                        current,
+                        sample.usage.capacity_bytes,
+                    ) =>
+                {
+                    self.baselines.insert(sample.uuid.clone(), current);
+                    changes.push(LvolUsageChange {
+                        sample,
+                        previous_allocated_bytes: Some(previous),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        changes
+    }
+}
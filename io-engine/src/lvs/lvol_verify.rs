@@ -0,0 +1,263 @@
+//! Implements the integrity check of a replica.
+//!
+//! The check reads the clusters allocated to the lvol itself and reports the
+//! extents which cannot be read, so that a replica can be verified before it
+//! is trusted as a rebuild source. Clusters of a thin provisioned lvol which
+//! are read from its ancestors are not checked. Optionally, the data read is
+//! compared with the same extents of a snapshot, and the extents which differ
+//! are reported as well.
+use std::ops::Range;
+
+use nix::errno::Errno;
+use spdk_rs::{
+    libspdk::{
+        spdk_blob_get_next_allocated_io_unit,
+        spdk_blob_get_next_unallocated_io_unit,
+        spdk_bs_get_io_unit_size,
+    },
+    DmaBuf,
+};
+
+use super::{Error, Lvol, LvsLvol};
+use crate::core::{logical_volume::LogicalVolume, Bdev, UntypedBdevHandle};
+
+/// Kind of a bad extent found by a replica check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyExtentKind {
+    /// The extent cannot be read.
+    IoError,
+    /// The extent differs from the snapshot the replica is compared with.
+    Mismatch,
+}
+
+/// Bad extent found by a replica check, in bytes.
+#[derive(Debug, Clone)]
+pub struct VerifyExtent {
+    pub offset: u64,
+    pub length: u64,
+    pub kind: VerifyExtentKind,
+}
+
+/// Progress of a replica check.
+#[derive(Debug, Default)]
+pub struct VerifyStats {
+    /// UUID of the replica.
+    pub uuid: String,
+    /// Bytes allocated to the replica, which are checked.
+    pub total_bytes: u64,
+    /// Bytes checked so far.
+    pub verified_bytes: u64,
+    /// Bytes which cannot be read.
+    pub io_error_bytes: u64,
+    /// Bytes which differ from the snapshot.
+    pub mismatch_bytes: u64,
+    /// Bad extents found since the previous notification.
+    pub extents: Vec<VerifyExtent>,
+    /// Time since the check started.
+    pub since: std::time::Duration,
+}
+
+impl VerifyStats {
+    /// Records a bad extent, merging it with the previous one if contiguous.
+    fn add_extent(&mut self, offset: u64, length: u64, kind: VerifyExtentKind) {
+        match kind {
+            VerifyExtentKind::IoError => self.io_error_bytes += length,
+            VerifyExtentKind::Mismatch => self.mismatch_bytes += length,
+        }
+        if let Some(last) = self.extents.last_mut() {
+            if last.kind == kind && last.offset + last.length == offset {
+                last.length += length;
+                return;
+            }
+        }
+        self.extents.push(VerifyExtent {
+            offset,
+            length,
+            kind,
+        });
+    }
+}
+
+/// Options of a replica check.
+#[derive(Debug)]
+pub struct VerifyOptions {
+    /// Size of the reads, and amount of data checked between notifications.
+    pub chunk_size: u64,
+    /// Maximum number of notifications.
+    pub max_chunks: u64,
+}
+
+fn open_handle(lvol: &Lvol) -> Result<UntypedBdevHandle, Error> {
+    Bdev::open(&lvol.as_bdev(), false)
+        .and_then(|desc| desc.into_handle())
+        .map_err(|e| Error::Invalid {
+            source: Errno::ENXIO,
+            msg: format!("failed to open {}: {e}", lvol.name()),
+        })
+}
+
+fn dma_buf(hdl: &UntypedBdevHandle, size: u64) -> Result<DmaBuf, Error> {
+    hdl.dma_malloc(size).map_err(|_| Error::Invalid {
+        source: Errno::ENOMEM,
+        msg: format!("failed to allocate {size} bytes"),
+    })
+}
+
+impl Lvol {
+    /// Returns the byte ranges of the clusters allocated to the lvol itself.
+    pub fn allocated_extents(&self) -> Vec<Range<u64>> {
+        let blob = self.blob_checked();
+        let io_unit =
+            unsafe { spdk_bs_get_io_unit_size(self.lvs().blob_store()) } as u64;
+        let size = self.size() / io_unit;
+
+        let mut extents = Vec::new();
+        let mut offset = 0;
+        while offset < size {
+            let start =
+                unsafe { spdk_blob_get_next_allocated_io_unit(blob, offset) };
+            if start >= size {
+                break;
+            }
+            let end =
+                unsafe { spdk_blob_get_next_unallocated_io_unit(blob, start) }
+                    .min(size);
+            extents.push(start * io_unit .. end * io_unit);
+            offset = end;
+        }
+        extents
+    }
+
+    /// Checks that the clusters allocated to the replica can be read, and
+    /// match the given snapshot if any, notifying the progress after every
+    /// chunk. The check stops if a notification fails.
+    pub async fn verify(
+        &self,
+        snapshot: Option<&Lvol>,
+        options: VerifyOptions,
+        mut notify: impl FnMut(&VerifyStats) -> Result<(), String>,
+    ) -> Result<VerifyStats, Error> {
+        let invalid = |msg: String| Error::Invalid {
+            source: Errno::EINVAL,
+            msg,
+        };
+
+        let block_len = self.as_bdev().block_len() as u64;
+        if options.chunk_size == 0 || options.chunk_size % block_len != 0 {
+            return Err(invalid(format!(
+                "chunk size {} is not a multiple of the block size {block_len}",
+                options.chunk_size
+            )));
+        }
+        if let Some(snapshot) = snapshot {
+            if snapshot.size() != self.size()
+                || snapshot.as_bdev().block_len() as u64 != block_len
+            {
+                return Err(invalid(format!(
+                    "snapshot {} does not match the geometry of replica {}",
+                    snapshot.uuid(),
+                    self.uuid()
+                )));
+            }
+        }
+
+        let extents = self.allocated_extents();
+        let mut stats = VerifyStats {
+            uuid: self.uuid(),
+            total_bytes: extents.iter().map(|e| e.end - e.start).sum(),
+            ..Default::default()
+        };
+        if stats.total_bytes / options.chunk_size >= options.max_chunks {
+            return Err(invalid(
+                "too many notifications, try increasing the chunk size"
+                    .to_string(),
+            ));
+        }
+
+        let hdl = open_handle(self)?;
+        let mut buf = dma_buf(&hdl, options.chunk_size)?;
+        let mut block = dma_buf(&hdl, block_len)?;
+        // Handle of the snapshot, with its chunk and block buffers.
+        let mut snap = match snapshot {
+            Some(snapshot) => {
+                let hdl = open_handle(snapshot)?;
+                let buf = dma_buf(&hdl, options.chunk_size)?;
+                let block = dma_buf(&hdl, block_len)?;
+                Some((hdl, buf, block))
+            }
+            None => None,
+        };
+
+        let notify_err = |error: String| Error::Invalid {
+            source: Errno::ECANCELED,
+            msg: format!("replica check aborted: {error}"),
+        };
+        notify(&stats).map_err(notify_err)?;
+
+        let start = std::time::Instant::now();
+        for extent in extents {
+            let mut offset = extent.start;
+            while offset < extent.end {
+                let len = options.chunk_size.min(extent.end - offset);
+                let full = len == options.chunk_size;
+
+                // Reads of partial chunks are done block by block.
+                if !(full && hdl.read_at(offset, &mut buf).await.is_ok()) {
+                    for blk in
+                        (offset .. offset + len).step_by(block_len as usize)
+                    {
+                        if hdl.read_at(blk, &mut block).await.is_err() {
+                            stats.add_extent(
+                                blk,
+                                block_len,
+                                VerifyExtentKind::IoError,
+                            );
+                        } else if let Some((snap_hdl, _, snap_block)) =
+                            &mut snap
+                        {
+                            snap_hdl.read_at(blk, snap_block).await.map_err(
+                                |e| {
+                                    invalid(format!(
+                                        "failed to read the snapshot: {e}"
+                                    ))
+                                },
+                            )?;
+                            if block.as_slice() != snap_block.as_slice() {
+                                stats.add_extent(
+                                    blk,
+                                    block_len,
+                                    VerifyExtentKind::Mismatch,
+                                );
+                            }
+                        }
+                    }
+                } else if let Some((snap_hdl, snap_buf, _)) = &mut snap {
+                    snap_hdl.read_at(offset, snap_buf).await.map_err(|e| {
+                        invalid(format!("failed to read the snapshot: {e}"))
+                    })?;
+                    let chunks = buf
+                        .as_slice()
+                        .chunks(block_len as usize)
+                        .zip(snap_buf.as_slice().chunks(block_len as usize));
+                    for (i, (data, snap)) in chunks.enumerate() {
+                        if data != snap {
+                            stats.add_extent(
+                                offset + i as u64 * block_len,
+                                block_len,
+                                VerifyExtentKind::Mismatch,
+                            );
+                        }
+                    }
+                }
+
+                offset += len;
+                stats.verified_bytes += len;
+                stats.since = start.elapsed();
+                notify(&stats).map_err(notify_err)?;
+                stats.extents.clear();
+            }
+        }
+
+        Ok(stats)
+    }
+}
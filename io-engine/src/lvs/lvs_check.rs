@@ -0,0 +1,538 @@
+//! Offline check of the blobstore metadata of a pool disk.
+//!
+//! The check reads the super block, the allocation masks and the metadata
+//! pages of a disk which is not imported, and reports what it finds without
+//! writing to the disk. A repair then loads the pool: a blobstore which has
+//! not been shut down cleanly rebuilds its allocation masks from the metadata
+//! pages while loading, and is written back clean when the pool is exported.
+//! Corrupted super blocks and metadata pages cannot be repaired this way.
+
+use std::convert::TryInto;
+
+use nix::errno::Errno;
+
+use super::{Error, Lvs};
+use crate::{
+    bdev::uri,
+    bdev_api::BdevError,
+    core::{UntypedBdev, UntypedBdevHandle},
+};
+
+/// Size of the blobstore metadata pages, the super block included.
+const PAGE_SIZE: u64 = 4096;
+/// Offset of the CRC in the super block and the metadata pages.
+const CRC_OFFSET: usize = PAGE_SIZE as usize - 4;
+/// Signature of the blobstore super block.
+const SUPER_SIGNATURE: &[u8] = b"SPDKBLOB";
+/// Latest blobstore version.
+const SUPER_VERSION: u32 = 3;
+/// Type of the blobstores holding lvol stores.
+const LVS_BSTYPE: &[u8] = b"LVOLSTORE";
+/// Types of the allocation masks.
+const MASK_USED_PAGES: u8 = 0;
+const MASK_USED_CLUSTERS: u8 = 1;
+/// Offset of the bits of an allocation mask, after its type and length.
+const MASK_BITS_OFFSET: usize = 8;
+/// Next page of the last metadata page of a blob.
+const INVALID_MD_PAGE: u32 = u32::MAX;
+/// Corrupted metadata pages reported individually, the others being counted.
+const MAX_PAGE_FINDINGS: u64 = 64;
+/// Offset of the descriptors in a metadata page.
+const DESCRIPTORS_OFFSET: usize = 16;
+/// Size of the type and length header of a metadata page descriptor.
+const DESCRIPTOR_HEADER_LEN: usize = 8;
+/// Types of the metadata page descriptors.
+const DESCRIPTOR_PADDING: u8 = 0;
+const DESCRIPTOR_XATTR: u8 = 2;
+/// Name of the xattr of the lvs super blob which holds the uuid of the lvs.
+const LVS_UUID_XATTR: &[u8] = b"uuid";
+/// Maximum length of the metadata page chain of the lvs super blob.
+const MAX_SUPER_BLOB_PAGES: usize = 16;
+
+/// Severity of a pool check finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolCheckSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Finding of a pool check.
+#[derive(Debug, Clone)]
+pub struct PoolCheckFinding {
+    pub severity: PoolCheckSeverity,
+    pub message: String,
+}
+
+/// State of the blobstore on a disk which is not imported.
+#[derive(Debug, Clone)]
+pub(super) struct DiskState {
+    /// Whether the blobstore was shut down cleanly.
+    pub(super) clean: bool,
+    /// UUID of the lvs on the disk, if it could be read.
+    pub(super) uuid: Option<String>,
+}
+
+/// Blobstore super block, with the offsets of its regions in pages.
+#[derive(Debug)]
+struct SuperBlock {
+    version: u32,
+    clean: bool,
+    super_blob: u64,
+    cluster_size: u64,
+    used_page_mask: (u64, u64),
+    used_cluster_mask: (u64, u64),
+    used_blobid_mask: (u64, u64),
+    md: (u64, u64),
+    bstype: Vec<u8>,
+    size: u64,
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset .. offset + 4].try_into().unwrap())
+}
+
+fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset .. offset + 8].try_into().unwrap())
+}
+
+/// Checks the CRC of a metadata page or of the super block.
+fn page_crc_ok(page: &[u8]) -> bool {
+    crc::crc32::checksum_castagnoli(&page[.. CRC_OFFSET])
+        == u32_at(page, CRC_OFFSET)
+}
+
+/// Checks if the given bit of an allocation mask is set.
+fn mask_bit(mask: &[u8], bit: u64) -> bool {
+    mask.get(MASK_BITS_OFFSET + (bit / 8) as usize)
+        .map_or(false, |b| b & (1 << (bit % 8)) != 0)
+}
+
+impl SuperBlock {
+    fn parse(page: &[u8]) -> Self {
+        let region = |offset| {
+            (u32_at(page, offset) as u64, u32_at(page, offset + 4) as u64)
+        };
+        Self {
+            version: u32_at(page, 8),
+            clean: u32_at(page, 16) == 1,
+            super_blob: u64_at(page, 24),
+            cluster_size: u32_at(page, 32) as u64,
+            used_page_mask: region(36),
+            used_cluster_mask: region(44),
+            md: region(52),
+            bstype: page[60 .. 76]
+                .iter()
+                .copied()
+                .take_while(|b| *b != 0)
+                .collect(),
+            used_blobid_mask: region(76),
+            size: u64_at(page, 88),
+        }
+    }
+}
+
+/// Looks for the value of the xattr with the given name in the descriptors
+/// of a metadata page.
+fn page_xattr<'a>(page: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let end = CRC_OFFSET - 4;
+    let mut offset = DESCRIPTORS_OFFSET;
+    while offset + DESCRIPTOR_HEADER_LEN <= end {
+        let kind = page[offset];
+        let len = u32_at(page, offset + 4) as usize;
+        if kind == DESCRIPTOR_PADDING && len == 0 {
+            break;
+        }
+        let desc_end = offset + DESCRIPTOR_HEADER_LEN + len;
+        if desc_end > end {
+            break;
+        }
+        if kind == DESCRIPTOR_XATTR && len >= 4 {
+            let at = offset + DESCRIPTOR_HEADER_LEN;
+            let name_len =
+                u16::from_le_bytes([page[at], page[at + 1]]) as usize;
+            let value_len =
+                u16::from_le_bytes([page[at + 2], page[at + 3]]) as usize;
+            let name_at = at + 4;
+            if name_at + name_len + value_len <= desc_end
+                && &page[name_at .. name_at + name_len] == name
+            {
+                let value_at = name_at + name_len;
+                return Some(&page[value_at .. value_at + value_len]);
+            }
+        }
+        offset = desc_end;
+    }
+    None
+}
+
+/// Reports the findings of a check and counts its errors and warnings.
+struct Reporter<F: FnMut(PoolCheckFinding)> {
+    report: F,
+    errors: u64,
+    warnings: u64,
+}
+
+impl<F: FnMut(PoolCheckFinding)> Reporter<F> {
+    fn add(&mut self, severity: PoolCheckSeverity, message: String) {
+        match severity {
+            PoolCheckSeverity::Error => self.errors += 1,
+            PoolCheckSeverity::Warning => self.warnings += 1,
+            PoolCheckSeverity::Info => {}
+        }
+        (self.report)(PoolCheckFinding {
+            severity,
+            message,
+        });
+    }
+
+    fn info(&mut self, message: String) {
+        self.add(PoolCheckSeverity::Info, message);
+    }
+
+    fn warning(&mut self, message: String) {
+        self.add(PoolCheckSeverity::Warning, message);
+    }
+
+    fn error(&mut self, message: String) {
+        self.add(PoolCheckSeverity::Error, message);
+    }
+}
+
+/// Reads the given number of metadata pages.
+async fn read_pages(
+    hdl: &UntypedBdevHandle,
+    page: u64,
+    count: u64,
+) -> Result<Vec<u8>, Error> {
+    let size = count * PAGE_SIZE;
+    let mut buf = hdl.dma_malloc(size).map_err(|_| Error::Invalid {
+        source: Errno::ENOMEM,
+        msg: format!("failed to allocate {size} bytes"),
+    })?;
+    hdl.read_at(page * PAGE_SIZE, &mut buf).await.map_err(|e| {
+        Error::Invalid {
+            source: Errno::EIO,
+            msg: format!("failed to read metadata page {page}: {e}"),
+        }
+    })?;
+    Ok(buf.as_slice().to_vec())
+}
+
+impl Lvs {
+    /// Reads the state of the blobstore on the given disk, which must not be
+    /// imported, without writing to it. Returns `None` if the disk cannot be
+    /// read, is in use, or holds no blobstore.
+    pub(super) async fn disk_state(name: &str) -> Option<DiskState> {
+        if UntypedBdev::lookup_by_name(name)?.is_claimed() {
+            return None;
+        }
+        let hdl = UntypedBdevHandle::open(name, false, false).ok()?;
+        let page = match read_pages(&hdl, 0, 1).await {
+            Ok(page) if page.starts_with(SUPER_SIGNATURE) => page,
+            Ok(_) => return None,
+            Err(error) => {
+                warn!("Failed to read the super block of {name}: {error}");
+                return None;
+            }
+        };
+        let sb = SuperBlock::parse(&page);
+        let uuid = Self::disk_lvs_uuid(&hdl, &sb).await;
+        if uuid.is_none() {
+            warn!("Failed to read the lvs uuid on {name}");
+        }
+        Some(DiskState {
+            clean: sb.clean,
+            uuid,
+        })
+    }
+
+    /// Reads the uuid of the lvs from the xattrs of the super blob.
+    async fn disk_lvs_uuid(
+        hdl: &UntypedBdevHandle,
+        sb: &SuperBlock,
+    ) -> Option<String> {
+        // The low 32 bits of a blob id are the index of its first page.
+        let mut idx = sb.super_blob & u32::MAX as u64;
+        for _ in 0 .. MAX_SUPER_BLOB_PAGES {
+            if idx >= sb.md.1 {
+                return None;
+            }
+            let page = read_pages(hdl, sb.md.0 + idx, 1).await.ok()?;
+            if !page_crc_ok(&page) || u64_at(&page, 0) != sb.super_blob {
+                return None;
+            }
+            if let Some(value) = page_xattr(&page, LVS_UUID_XATTR) {
+                let value = value.split(|b| *b == 0).next().unwrap_or_default();
+                return uuid::Uuid::parse_str(std::str::from_utf8(value).ok()?)
+                    .ok()
+                    .map(|u| u.to_string());
+            }
+            match u32_at(&page, CRC_OFFSET - 4) {
+                INVALID_MD_PAGE => return None,
+                next => idx = next as u64,
+            }
+        }
+        None
+    }
+
+    /// Checks the blobstore metadata on the given disk, which must not be
+    /// imported, and repairs it if requested and possible. The findings are
+    /// reported as they are made; the number of errors is returned.
+    pub async fn check(
+        disk: &str,
+        repair: bool,
+        report: impl FnMut(PoolCheckFinding),
+    ) -> Result<u64, Error> {
+        let parsed = uri::parse(disk).map_err(|e| Error::InvalidBdev {
+            source: e,
+            name: disk.to_string(),
+        })?;
+        let name = parsed.get_name();
+
+        if let Some(lvs) = Lvs::iter().find(|l| l.base_bdev().name() == name) {
+            return Err(Error::Invalid {
+                source: Errno::EBUSY,
+                msg: format!("disk {disk} is imported as pool {}", lvs.name()),
+            });
+        }
+
+        let created = match parsed.create().await {
+            Ok(_) => true,
+            Err(BdevError::BdevExists {
+                ..
+            }) => false,
+            Err(BdevError::CreateBdevInvalidParams {
+                source, ..
+            }) if source == Errno::EEXIST => false,
+            Err(e) => {
+                return Err(Error::InvalidBdev {
+                    source: e,
+                    name: disk.to_string(),
+                })
+            }
+        };
+
+        let mut reporter = Reporter {
+            report,
+            errors: 0,
+            warnings: 0,
+        };
+        let result = Self::check_bdev(&name, repair, &mut reporter).await;
+
+        if created {
+            if let Err(e) = parsed.destroy().await {
+                warn!("Failed to destroy bdev of checked disk {disk}: {e}");
+            }
+        }
+
+        result?;
+        let (errors, warnings) = (reporter.errors, reporter.warnings);
+        reporter.info(format!(
+            "check of {disk} complete: {errors} error(s), {warnings} \
+            warning(s)"
+        ));
+        Ok(errors)
+    }
+
+    /// Checks the blobstore on the given bdev, then repairs it if requested
+    /// and its metadata pages are intact.
+    async fn check_bdev<F: FnMut(PoolCheckFinding)>(
+        name: &str,
+        repair: bool,
+        reporter: &mut Reporter<F>,
+    ) -> Result<(), Error> {
+        let bdev =
+            UntypedBdev::lookup_by_name(name).ok_or(Error::InvalidBdev {
+                source: BdevError::BdevNotFound {
+                    name: name.to_string(),
+                },
+                name: name.to_string(),
+            })?;
+        if bdev.is_claimed() {
+            return Err(Error::Invalid {
+                source: Errno::EBUSY,
+                msg: format!("disk {name} is in use"),
+            });
+        }
+        let disk_size = bdev.size_in_bytes();
+
+        let hdl = UntypedBdevHandle::open(name, false, false).map_err(|e| {
+            Error::Invalid {
+                source: Errno::EIO,
+                msg: format!("failed to open disk {name}: {e}"),
+            }
+        })?;
+
+        let page = read_pages(&hdl, 0, 1).await?;
+        if !page.starts_with(SUPER_SIGNATURE) {
+            reporter.error("no blobstore super block found".to_string());
+            return Ok(());
+        }
+        if !page_crc_ok(&page) {
+            reporter.error("super block checksum mismatch".to_string());
+            return Ok(());
+        }
+        let sb = SuperBlock::parse(&page);
+        debug!("Pool check of '{name}': {sb:?}");
+
+        if sb.version > SUPER_VERSION {
+            reporter
+                .error(format!("unsupported blobstore version {}", sb.version));
+            return Ok(());
+        }
+        if sb.bstype != LVS_BSTYPE {
+            reporter.warning(format!(
+                "blobstore type '{}' is not an lvol store",
+                String::from_utf8_lossy(&sb.bstype)
+            ));
+        }
+        if !sb.clean {
+            reporter.warning(
+                "blobstore was not shut down cleanly: its allocation masks \
+                are rebuilt on import"
+                    .to_string(),
+            );
+        }
+
+        let mut layout_ok = true;
+        if sb.size > disk_size {
+            reporter.error(format!(
+                "blobstore size {} exceeds disk size {disk_size}",
+                sb.size
+            ));
+            layout_ok = false;
+        }
+        if sb.cluster_size == 0 || sb.cluster_size % PAGE_SIZE != 0 {
+            reporter.error(format!("invalid cluster size {}", sb.cluster_size));
+            layout_ok = false;
+        }
+        for (region, (start, len), required) in [
+            ("used page mask", sb.used_page_mask, true),
+            ("used cluster mask", sb.used_cluster_mask, true),
+            ("used blob id mask", sb.used_blobid_mask, false),
+            ("metadata", sb.md, true),
+        ] {
+            if required && len == 0 {
+                reporter.error(format!("{region} region is empty"));
+                layout_ok = false;
+            } else if (start + len) * PAGE_SIZE > sb.size.min(disk_size) {
+                reporter.error(format!(
+                    "{region} region (pages {start}+{len}) exceeds the \
+                    blobstore"
+                ));
+                layout_ok = false;
+            }
+        }
+        if !layout_ok {
+            return Ok(());
+        }
+
+        // Masks are only written on a clean shutdown: inconsistencies in the
+        // masks of a dirty blobstore are expected.
+        let mask_finding = |reporter: &mut Reporter<F>, message| {
+            if sb.clean {
+                reporter.error(message);
+            } else {
+                reporter.warning(message);
+            }
+        };
+
+        let page_mask =
+            read_pages(&hdl, sb.used_page_mask.0, sb.used_page_mask.1).await?;
+        if page_mask[0] != MASK_USED_PAGES
+            || u32_at(&page_mask, 4) as u64 != sb.md.1
+        {
+            mask_finding(reporter, "used page mask is invalid".to_string());
+        }
+
+        let mut blobs = 0;
+        let mut pages_used = 0;
+        let mut pages_corrupted = 0;
+        for idx in (0 .. sb.md.1).filter(|i| mask_bit(&page_mask, *i)) {
+            pages_used += 1;
+            let page = read_pages(&hdl, sb.md.0 + idx, 1).await?;
+            if !page_crc_ok(&page) {
+                pages_corrupted += 1;
+                if pages_corrupted <= MAX_PAGE_FINDINGS {
+                    reporter.error(format!(
+                        "metadata page {idx} checksum mismatch"
+                    ));
+                }
+                continue;
+            }
+            // The first page of a blob has sequence number 0.
+            if u32_at(&page, 8) == 0 {
+                blobs += 1;
+            }
+            let next = u32_at(&page, CRC_OFFSET - 4);
+            if next != INVALID_MD_PAGE && !mask_bit(&page_mask, next as u64) {
+                mask_finding(
+                    reporter,
+                    format!(
+                        "metadata page {idx} continues on unallocated page \
+                        {next}"
+                    ),
+                );
+            }
+        }
+        if pages_corrupted > MAX_PAGE_FINDINGS {
+            reporter.error(format!(
+                "{} more metadata pages have a checksum mismatch",
+                pages_corrupted - MAX_PAGE_FINDINGS
+            ));
+        }
+
+        let clusters = sb.size / sb.cluster_size;
+        let cluster_mask =
+            read_pages(&hdl, sb.used_cluster_mask.0, sb.used_cluster_mask.1)
+                .await?;
+        if cluster_mask[0] != MASK_USED_CLUSTERS
+            || u32_at(&cluster_mask, 4) as u64 != clusters
+        {
+            mask_finding(reporter, "used cluster mask is invalid".to_string());
+        }
+        let clusters_used = (0 .. clusters)
+            .filter(|c| mask_bit(&cluster_mask, *c))
+            .count();
+
+        reporter.info(format!(
+            "{blobs} blob(s) in {pages_used} metadata page(s), \
+            {clusters_used}/{clusters} cluster(s) allocated"
+        ));
+        drop(hdl);
+
+        if !repair {
+            return Ok(());
+        }
+        if pages_corrupted > 0 {
+            reporter.error(
+                "corrupted metadata pages cannot be repaired".to_string(),
+            );
+            return Ok(());
+        }
+        if sb.clean {
+            if reporter.errors == 0 {
+                reporter.info("nothing to repair".to_string());
+            } else {
+                reporter.error(
+                    "the masks of a blobstore shut down cleanly are not \
+                    rebuilt: nothing can be repaired"
+                        .to_string(),
+                );
+            }
+            return Ok(());
+        }
+
+        match Self::load(name, name).await {
+            Ok(lvs) => {
+                let pool = lvs.name().to_string();
+                lvs.export().await?;
+                reporter
+                    .info(format!("pool {pool} loaded and written back clean"));
+            }
+            Err(e) => reporter.error(format!("failed to load the pool: {e}")),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+//! Stable identifiers of the disks of the pools.
+//!
+//! Kernel names such as `/dev/sdb` may designate another disk after a
+//! reboot. When a pool is created or imported from a kernel device, the
+//! stable `/dev/disk/by-id` link of the device (preferably its WWN) is
+//! recorded for the pool. When the pool is imported again and the configured
+//! path no longer designates the recorded disk, the pool is imported from
+//! the stable link instead.
+//!
+//! The records are kept in the persistence directory of the node, if any.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::Lvs;
+use crate::core::MayastorEnvironment;
+
+/// Directory of the stable links of the disks.
+const DISK_BY_ID_DIR: &str = "/dev/disk/by-id";
+/// File of the records, in the persistence directory.
+const POOL_DISKS_FILE: &str = "pool-disks.json";
+
+/// Disk of a pool, as recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolDiskId {
+    /// URI of the disk given when the pool was created or imported.
+    pub configured: String,
+    /// Stable link of the disk.
+    pub stable_path: String,
+}
+
+/// Returns the path of the kernel device of the given disk URI, if it is
+/// one.
+fn device_path(uri: &str) -> Option<String> {
+    let url = Url::parse(uri).ok()?;
+    match url.scheme() {
+        "aio" | "uring" => Some(url.path().to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the stable link of the given kernel device: its WWN if it has
+/// one, otherwise its first other link in `/dev/disk/by-id`.
+pub fn stable_device_path(path: &str) -> Option<String> {
+    let device = fs::canonicalize(path).ok()?;
+    let mut links = fs::read_dir(DISK_BY_ID_DIR)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|l| fs::canonicalize(l).map_or(false, |d| d == device))
+        .collect::<Vec<_>>();
+    links.sort_by_key(|l| {
+        let name = l.file_name().unwrap_or_default().to_string_lossy();
+        (
+            !name.starts_with("wwn-"),
+            !name.starts_with("nvme-eui."),
+            name.to_string(),
+        )
+    });
+    links.first().map(|l| l.to_string_lossy().to_string())
+}
+
+/// Returns the kernel device the given path currently designates.
+pub fn resolved_device_path(path: &str) -> Option<String> {
+    fs::canonicalize(path)
+        .ok()
+        .map(|d| d.to_string_lossy().to_string())
+}
+
+fn records_file() -> Option<PathBuf> {
+    MayastorEnvironment::global_or_default()
+        .ptpl_dir()
+        .map(|dir| Path::new(&dir).join(POOL_DISKS_FILE))
+}
+
+fn load_records() -> HashMap<String, PoolDiskId> {
+    records_file()
+        .and_then(|file| fs::read(file).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn store_records(records: &HashMap<String, PoolDiskId>) {
+    let Some(file) = records_file() else {
+        return;
+    };
+    let tmp = file.with_extension("tmp");
+    let result = serde_json::to_vec_pretty(records)
+        .map_err(std::io::Error::from)
+        .and_then(|bytes| {
+            if let Some(dir) = file.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&tmp, bytes)?;
+            fs::rename(&tmp, &file)
+        });
+    if let Err(error) = result {
+        warn!("Failed to save the pool disks to {file:?}: {error}");
+    }
+}
+
+impl Lvs {
+    /// Returns the URI to load the pool with the given name from: the given
+    /// one, unless its kernel device is no longer the recorded disk of the
+    /// pool while the stable link of the disk still exists.
+    pub(super) fn resolve_disk(name: &str, disk: &str) -> String {
+        let Some(path) = device_path(disk) else {
+            return disk.to_string();
+        };
+        let Some(record) = load_records().remove(name) else {
+            return disk.to_string();
+        };
+        if record.stable_path == path {
+            return disk.to_string();
+        }
+        let Some(recorded) = resolved_device_path(&record.stable_path) else {
+            return disk.to_string();
+        };
+        if resolved_device_path(&path).as_ref() == Some(&recorded) {
+            return disk.to_string();
+        }
+
+        let mut url = Url::parse(disk).expect("device URI");
+        url.set_path(&record.stable_path);
+        warn!(
+            "Pool {name}: {path} is no longer disk {}, which is now \
+            {recorded}, importing from {url}",
+            record.stable_path
+        );
+        url.to_string()
+    }
+
+    /// Records the stable link of the disk the pool with the given name was
+    /// loaded from, when it is a kernel device, along with the configured
+    /// URI of the disk.
+    pub(super) fn record_disk(name: &str, configured: &str, disk: &str) {
+        let Some(stable_path) =
+            device_path(disk).and_then(|p| stable_device_path(&p))
+        else {
+            return;
+        };
+        let record = PoolDiskId {
+            configured: configured.to_string(),
+            stable_path,
+        };
+        let mut records = load_records();
+        if records.get(name) != Some(&record) {
+            info!("Pool {name}: recorded disk {record:?}");
+            records.insert(name.to_string(), record);
+            store_records(&records);
+        }
+    }
+
+    /// Forgets the disk of the destroyed pool with the given name.
+    pub(super) fn forget_disk(name: &str) {
+        let mut records = load_records();
+        if records.remove(name).is_some() {
+            store_records(&records);
+        }
+    }
+
+    /// Returns the recorded disk of the pool, if any.
+    pub fn disk_id(&self) -> Option<PoolDiskId> {
+        load_records().remove(self.name())
+    }
+
+    /// Returns the kernel device the disk of the pool currently is, if it
+    /// is one.
+    pub fn disk_device(&self) -> Option<String> {
+        let bdev = self.base_bdev();
+        match bdev.driver() {
+            "aio" | "uring" => resolved_device_path(bdev.name()),
+            _ => None,
+        }
+    }
+}
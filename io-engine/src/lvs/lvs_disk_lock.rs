@@ -0,0 +1,317 @@
+//! Exclusive locks of the disks of the pools.
+//!
+//! A second io-engine instance configured with the same disk would load the
+//! pool a second time and corrupt it. The disk of a pool is therefore locked
+//! for as long as the pool is imported:
+//!
+//! - a kernel block device is opened with `O_EXCL`, which fails while another
+//!   process holds it exclusively or it is mounted, and a regular file is
+//!   locked with `flock`;
+//! - an NVMe namespace is reserved for exclusive access with a key derived from
+//!   the node name, which other hosts cannot acquire without preempting it.
+//!
+//! The locks are taken before the pool is loaded or created, as both write to
+//! the disk. On conflict, nothing is written, and the processes, mounts or host
+//! holding the disk are reported.
+//! Disks of other kinds, or NVMe namespaces without reservation support, are
+//! not locked.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    os::unix::{
+        fs::{FileTypeExt, OpenOptionsExt},
+        io::AsRawFd,
+    },
+    path::Path,
+};
+
+use nix::{
+    errno::Errno,
+    fcntl::{flock, FlockArg},
+};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use spdk_rs::{
+    nvme_reservation_acquire_action,
+    nvme_reservation_register_action,
+    nvme_reservation_register_cptpl,
+};
+
+use super::{Error, Lvs};
+use crate::{
+    bdev::{
+        device_open,
+        nexus::{nvme_resv_holder, NvmeReservation},
+    },
+    core::{CoreError, MayastorEnvironment, UntypedBdev},
+};
+
+/// Lock held on the disk of a pool.
+#[derive(Debug, Default)]
+struct DiskLock {
+    /// Kernel device or file, kept open to hold the lock.
+    _file: Option<File>,
+    /// Key of the NVMe reservation.
+    resv_key: Option<u64>,
+}
+
+/// Locks of the disks of the pools, by name of the base bdev.
+static DISK_LOCKS: Lazy<parking_lot::Mutex<HashMap<String, DiskLock>>> =
+    Lazy::new(Default::default);
+
+/// NVMe reservation key of this node.
+fn resv_key() -> u64 {
+    let node = MayastorEnvironment::global_or_default().node_name;
+    let hash = Sha256::digest(format!("io-engine-pool:{node}").as_bytes());
+    u64::from_be_bytes(hash[.. 8].try_into().unwrap()).max(1)
+}
+
+/// Describes the processes, mounts and kernel devices which use the given
+/// kernel device or file.
+fn disk_users(path: &str) -> String {
+    let Ok(path) = fs::canonicalize(path) else {
+        return "an unknown owner".to_string();
+    };
+    let mut users = Vec::new();
+
+    let own_pid = std::process::id().to_string();
+    for entry in fs::read_dir("/proc").into_iter().flatten().flatten() {
+        let pid = entry.file_name().to_string_lossy().to_string();
+        if !pid.bytes().all(|b| b.is_ascii_digit()) || pid == own_pid {
+            continue;
+        }
+        let uses = fs::read_dir(entry.path().join("fd"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(|fd| fs::read_link(fd.path()).map_or(false, |l| l == path));
+        if uses {
+            let comm = fs::read_to_string(entry.path().join("comm"))
+                .unwrap_or_default();
+            users.push(format!("process {pid} ({})", comm.trim()));
+        }
+    }
+
+    let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    for mount in mounts.lines() {
+        let mut fields = mount.split_whitespace();
+        if let (Some(dev), Some(dir)) = (fields.next(), fields.next()) {
+            if fs::canonicalize(dev).map_or(false, |d| d == path) {
+                users.push(format!("mount on {dir}"));
+            }
+        }
+    }
+
+    if let Some(dev) = path.file_name() {
+        let holders = Path::new("/sys/class/block").join(dev).join("holders");
+        for holder in fs::read_dir(holders).into_iter().flatten().flatten() {
+            users.push(format!(
+                "device {}",
+                holder.file_name().to_string_lossy()
+            ));
+        }
+    }
+
+    if users.is_empty() {
+        "an unknown owner".to_string()
+    } else {
+        users.join(", ")
+    }
+}
+
+impl Lvs {
+    /// Locks the kernel device or file of the given base bdev. Bdevs of other
+    /// kinds are ignored.
+    fn lock_kernel_disk(
+        name: &str,
+        bdev: &str,
+    ) -> Result<(), Error> {
+        let Some(base) = UntypedBdev::lookup_by_name(bdev) else {
+            return Ok(());
+        };
+        if !matches!(base.driver(), "aio" | "uring") {
+            return Ok(());
+        }
+        // The bdev is named after the path of the device.
+        let locked = |owner| Error::DiskLocked {
+            name: name.to_string(),
+            disk: bdev.to_string(),
+            owner,
+        };
+        let is_block = fs::metadata(bdev)
+            .map_or(false, |m| m.file_type().is_block_device());
+        let file = if is_block {
+            OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_EXCL)
+                .open(bdev)
+                .map_err(|error| match error.raw_os_error() {
+                    Some(libc::EBUSY) => locked(disk_users(bdev)),
+                    _ => locked(format!("an unknown owner: {error}")),
+                })?
+        } else {
+            let file = File::open(bdev).map_err(|error| {
+                locked(format!("an unknown owner: {error}"))
+            })?;
+            flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock).map_err(
+                |error| match error {
+                    Errno::EWOULDBLOCK => locked(disk_users(bdev)),
+                    error => locked(format!("an unknown owner: {error}")),
+                },
+            )?;
+            file
+        };
+
+        info!("Pool {name}: locked disk {bdev}");
+        DISK_LOCKS.lock().insert(
+            bdev.to_string(),
+            DiskLock {
+                _file: Some(file),
+                resv_key: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Reserves the NVMe namespace of the given base bdev for exclusive
+    /// access, preempting the reservation of another host if `force` is set.
+    /// Namespaces without reservation support are ignored.
+    async fn lock_nvme_disk(
+        name: &str,
+        bdev: &str,
+        force: bool,
+    ) -> Result<(), Error> {
+        let locked = |owner| Error::DiskLocked {
+            name: name.to_string(),
+            disk: bdev.to_string(),
+            owner,
+        };
+        let failed = |msg: String| Error::Invalid {
+            source: Errno::EIO,
+            msg: format!("failed to reserve disk {bdev}: {msg}"),
+        };
+
+        let desc =
+            device_open(bdev, false).map_err(|e| failed(e.to_string()))?;
+        let hdl = desc
+            .get_io_handle_nonblock()
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+
+        let key = resv_key();
+        match hdl
+            .nvme_resv_register(
+                0,
+                key,
+                nvme_reservation_register_action::REPLACE_KEY,
+                nvme_reservation_register_cptpl::CLEAR_POWER_ON,
+            )
+            .await
+        {
+            Ok(()) => {}
+            Err(CoreError::NotSupported {
+                ..
+            }) => return Ok(()),
+            Err(error) => return Err(failed(error.to_string())),
+        }
+
+        let holder = nvme_resv_holder(&*hdl)
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+        let preempt_key = match holder {
+            Some((_, holder_key, host_id)) if holder_key != key => {
+                let owner = format!(
+                    "host {} holding an NVMe reservation with key {holder_key:#x}",
+                    hex::encode(host_id)
+                );
+                if !force {
+                    return Err(locked(owner));
+                }
+                warn!("Pool {name}: preempting the reservation of {owner}");
+                Some(holder_key)
+            }
+            _ => None,
+        };
+
+        hdl.nvme_resv_acquire(
+            key,
+            preempt_key.unwrap_or_default(),
+            if preempt_key.is_some() {
+                nvme_reservation_acquire_action::PREEMPT
+            } else {
+                nvme_reservation_acquire_action::ACQUIRE
+            },
+            NvmeReservation::ExclusiveAccess as u8,
+        )
+        .await
+        .map_err(|e| failed(e.to_string()))?;
+
+        info!("Pool {name}: reserved disk {bdev} with key {key:#x}");
+        DISK_LOCKS
+            .lock()
+            .entry(bdev.to_string())
+            .or_default()
+            .resv_key = Some(key);
+        Ok(())
+    }
+
+    /// Locks the given base bdev before the pool on it is loaded or created.
+    /// On conflict nothing is written to the disk: the locks taken so far
+    /// are released and the base bdev is left alone.
+    pub(super) async fn lock_disk(
+        name: &str,
+        bdev: &str,
+        force: bool,
+    ) -> Result<(), Error> {
+        // The disk of a pool imported on this node is already locked, and
+        // must stay locked when the import fails.
+        if DISK_LOCKS.lock().contains_key(bdev) {
+            return Err(Error::DiskLocked {
+                name: name.to_string(),
+                disk: bdev.to_string(),
+                owner: "a pool imported on this node".to_string(),
+            });
+        }
+        Self::lock_kernel_disk(name, bdev)?;
+        if let Err(error) = Self::lock_nvme_disk(name, bdev, force).await {
+            Self::unlock_disk(bdev).await;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Releases the lock of the given base bdev, if any. Must be called
+    /// before the base bdev is destroyed.
+    pub(super) async fn unlock_disk(bdev: &str) {
+        let Some(lock) = DISK_LOCKS.lock().remove(bdev) else {
+            return;
+        };
+        if let Some(key) = lock.resv_key {
+            let result = async {
+                let desc = device_open(bdev, false)?;
+                let hdl = desc.get_io_handle_nonblock().await?;
+                hdl.nvme_resv_release(
+                    key,
+                    NvmeReservation::ExclusiveAccess as u8,
+                    0,
+                )
+                .await?;
+                hdl.nvme_resv_register(
+                    key,
+                    0,
+                    nvme_reservation_register_action::UNREGISTER_KEY,
+                    nvme_reservation_register_cptpl::NO_CHANGES,
+                )
+                .await
+            }
+            .await;
+            if let Err(error) = result {
+                warn!(
+                    "Failed to release the reservation of disk {bdev}: {error}"
+                );
+            }
+        }
+        info!("Unlocked disk {bdev}");
+    }
+}
@@ -19,6 +19,15 @@ pub enum ImportErrorReason {
     NameClash { name: String },
     #[snafu(display(": existing pool has different uuid: {uuid}"))]
     UuidMismatch { uuid: String },
+    #[snafu(display(
+        ": pool appears to be imported by node {node}, import with force \
+        if that node no longer uses it"
+    ))]
+    OwnedBy { node: String },
+    #[snafu(display(
+        ": cannot tell if another node has the pool imported: {error}"
+    ))]
+    OwnerUnknown { error: String },
 }
 
 #[derive(Debug, Snafu)]
@@ -69,6 +78,20 @@ pub enum Error {
         source: Errno,
         name: String,
     },
+    #[snafu(display(
+        "failed to create lvol {}: pool {} would be over-committed, \
+        {} bytes provisioned out of a limit of {} bytes",
+        name,
+        pool,
+        committed,
+        limit
+    ))]
+    Overcommit {
+        name: String,
+        pool: String,
+        committed: u64,
+        limit: u64,
+    },
     #[snafu(display("failed to destroy lvol {} {}", name, if msg.is_empty() { "" } else { msg.as_str() }))]
     RepDestroy {
         source: Errno,
@@ -136,6 +159,41 @@ pub enum Error {
         source: Errno,
         msg: String,
     },
+    #[snafu(display(
+        "failed to create snapshot of replica {}: {} snapshots exist \
+        out of a limit of {}",
+        name,
+        count,
+        limit
+    ))]
+    SnapshotLimit {
+        name: String,
+        count: u32,
+        limit: u32,
+    },
+    #[snafu(display(
+        "failed to create clone of snapshot {}: clone chain depth {} \
+        exceeds the limit of {}",
+        name,
+        depth,
+        limit
+    ))]
+    CloneDepthLimit {
+        name: String,
+        depth: u32,
+        limit: u32,
+    },
+    #[snafu(display(
+        "errno {}: failed to flatten clone {}: {}",
+        source,
+        name,
+        msg
+    ))]
+    FlattenClone {
+        source: Errno,
+        name: String,
+        msg: String,
+    },
     #[snafu(display("Flush Failed for replica {}", name))]
     FlushFailed {
         name: String,
@@ -164,10 +222,66 @@ pub enum Error {
         attr: String,
         name: String,
     },
+    #[snafu(display(
+        "errno {}: failed to freeze/thaw replica {}: {}",
+        source,
+        name,
+        msg
+    ))]
+    Freeze {
+        source: Errno,
+        name: String,
+        msg: String,
+    },
+    #[snafu(display(
+        "errno {}: failed to compress replica {}: {}",
+        source,
+        name,
+        msg
+    ))]
+    Compress {
+        source: Errno,
+        name: String,
+        msg: String,
+    },
+    #[snafu(display(
+        "errno {}: failed to set the state of replica {}: {}",
+        source,
+        name,
+        msg
+    ))]
+    ReplicaState {
+        source: Errno,
+        name: String,
+        msg: String,
+    },
+    #[snafu(display(
+        "replica {} is owned by {}, operation requested by {}",
+        name,
+        owner,
+        requester
+    ))]
+    ReplicaOwner {
+        name: String,
+        owner: String,
+        requester: String,
+    },
+    #[snafu(display("{}; rollback of replica {} {}", source, name, rollback))]
+    CreateRollback {
+        source: Box<Error>,
+        name: String,
+        rollback: String,
+    },
     #[snafu(display("Failed to wipe the replica"))]
     WipeFailed {
         source: crate::core::wiper::Error,
     },
+    #[snafu(display("disk {} of pool {} is locked by {}", disk, name, owner))]
+    DiskLocked {
+        name: String,
+        disk: String,
+        owner: String,
+    },
 }
 
 /// Map CoreError to errno code.
@@ -228,6 +342,15 @@ impl ToErrno for Error {
             Self::SnapshotCreate {
                 source, ..
             } => source,
+            Self::SnapshotLimit {
+                ..
+            } => Errno::EDQUOT,
+            Self::CloneDepthLimit {
+                ..
+            } => Errno::EMLINK,
+            Self::FlattenClone {
+                source, ..
+            } => source,
             Self::FlushFailed {
                 ..
             } => Errno::EIO,
@@ -249,9 +372,30 @@ impl ToErrno for Error {
             Self::SetXAttr {
                 source, ..
             } => source,
+            Self::Freeze {
+                source, ..
+            } => source,
+            Self::Overcommit {
+                ..
+            } => Errno::ENOSPC,
+            Self::Compress {
+                source, ..
+            } => source,
+            Self::ReplicaState {
+                source, ..
+            } => source,
+            Self::ReplicaOwner {
+                ..
+            } => Errno::EPERM,
+            Self::CreateRollback {
+                source, ..
+            } => (*source).to_errno(),
             Self::WipeFailed {
                 ..
             } => Errno::EINVAL,
+            Self::DiskLocked {
+                ..
+            } => Errno::EBUSY,
         }
     }
 }
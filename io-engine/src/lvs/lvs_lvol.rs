@@ -22,6 +22,7 @@ use spdk_rs::libspdk::{
     spdk_blob_get_xattr_value,
     spdk_blob_is_read_only,
     spdk_blob_is_thin_provisioned,
+    spdk_blob_remove_xattr,
     spdk_blob_set_xattr,
     spdk_blob_sync_md,
     spdk_bs_get_cluster_size,
@@ -32,7 +33,7 @@ use spdk_rs::libspdk::{
     LVS_CLEAR_WITH_UNMAP,
 };
 
-use super::{Error, Lvs};
+use super::{Error, LvolState, Lvs};
 
 use crate::{
     bdev::PtplFileOps,
@@ -41,6 +42,7 @@ use crate::{
         wiper::{WipeMethod, Wiper},
         Bdev,
         CloneXattrs,
+        NvmeIdentity,
         Protocol,
         PtplProps,
         Share,
@@ -61,11 +63,15 @@ use crate::{
         FfiResult,
         IntoCString,
     },
+    subsys::NvmfSubsystem,
 };
 
 // Wipe `WIPE_SUPER_LEN` bytes if unmap is not supported.
 pub(crate) const WIPE_SUPER_LEN: u64 = (1 << 20) * 8;
 
+/// Name of the lvol xattr holding the NVMe identification of the replica.
+const LVOL_NVME_IDENTITY_XATTR: &str = "nvme_identity";
+
 /// properties we allow for being set on the lvol, this information is stored on
 /// disk
 #[derive(Debug, Clone, PartialEq)]
@@ -184,12 +190,15 @@ impl Share for Lvol {
         mut self: Pin<&mut Self>,
         props: Option<ShareProps>,
     ) -> Result<Self::Output, Self::Error> {
-        let allowed_hosts = props
-            .as_ref()
-            .map(|s| s.allowed_hosts().clone())
-            .unwrap_or_default();
-        let share = Pin::new(&mut self.as_bdev())
-            .share_nvmf(props)
+        let mut props = ShareProps::from(props);
+        let allowed_hosts = props.allowed_hosts().clone();
+        if props.identity().is_default() {
+            props = props.with_identity(self.nvme_identity());
+        } else if props.identity() != &self.nvme_identity() {
+            self.set_nvme_identity(props.identity()).await?;
+        }
+        let share = Pin::new(&mut self.share_bdev())
+            .share_nvmf(Some(props))
             .await
             .map_err(|e| Error::LvolShare {
                 source: e,
@@ -200,6 +209,9 @@ impl Share for Lvol {
         self.as_mut()
             .set(PropValue::AllowedHosts(allowed_hosts))
             .await?;
+        if self.state() != LvolState::Online {
+            self.apply_state(LvolState::Online).await?;
+        }
         info!("{:?}: shared as NVMF", self);
         Ok(share)
     }
@@ -208,7 +220,8 @@ impl Share for Lvol {
         self: Pin<&mut Self>,
         props: P,
     ) -> Result<(), Self::Error> {
-        Pin::new(&mut self.as_bdev())
+        self.check_not_in_maintenance()?;
+        Pin::new(&mut self.share_bdev())
             .update_properties(props)
             .await
             .map_err(|e| Error::UpdateShareProperties {
@@ -220,12 +233,13 @@ impl Share for Lvol {
 
     /// unshare the nvmf target
     async fn unshare(mut self: Pin<&mut Self>) -> Result<(), Self::Error> {
-        Pin::new(&mut self.as_bdev()).unshare().await.map_err(|e| {
-            Error::LvolUnShare {
+        Pin::new(&mut self.share_bdev())
+            .unshare()
+            .await
+            .map_err(|e| Error::LvolUnShare {
                 source: e,
                 name: self.name(),
-            }
-        })?;
+            })?;
 
         self.as_mut().set(PropValue::Shared(false)).await?;
 
@@ -235,7 +249,7 @@ impl Share for Lvol {
 
     /// return the protocol this bdev is shared under
     fn shared(&self) -> Option<Protocol> {
-        self.as_bdev().shared()
+        self.share_bdev().shared()
     }
 
     /// returns the share URI this lvol is shared as
@@ -243,12 +257,12 @@ impl Share for Lvol {
     /// uniquely identify a replica as the replica UUID is currently set to its
     /// name, which is *NOT* unique and in MOAC's use case, is the volume UUID
     fn share_uri(&self) -> Option<String> {
-        let uri_no_uuid = self.as_bdev().share_uri();
+        let uri_no_uuid = self.share_bdev().share_uri();
         uri_no_uuid.map(|uri| format!("{}?uuid={}", uri, self.uuid()))
     }
 
     fn allowed_hosts(&self) -> Vec<String> {
-        self.as_bdev().allowed_hosts()
+        self.share_bdev().allowed_hosts()
     }
 
     /// returns the URI that is used to construct the bdev. This is always None
@@ -308,6 +322,18 @@ impl Lvol {
         Ok(())
     }
 
+    /// Returns the NQNs of the hosts connected to the replica.
+    pub fn connected_hosts(&self) -> Vec<String> {
+        match self.shared() {
+            Some(Protocol::Nvmf) => {
+                NvmfSubsystem::nqn_lookup(self.share_bdev().name())
+                    .map(|ss| ss.connected_hosts())
+                    .unwrap_or_default()
+            }
+            _ => vec![],
+        }
+    }
+
     /// Get a wiper for this replica.
     pub(crate) fn wiper(
         &self,
@@ -349,6 +375,30 @@ impl Lvol {
         LvolPtpl::from(self)
     }
 
+    /// Get the NVMe identification the replica is shared with.
+    pub fn nvme_identity(&self) -> NvmeIdentity {
+        Lvol::get_blob_xattr(self, LVOL_NVME_IDENTITY_XATTR)
+            .and_then(|identity| serde_json::from_str(&identity).ok())
+            .unwrap_or_default()
+    }
+
+    /// Persist the NVMe identification the replica is shared with, so that
+    /// it is shared again with the same one.
+    async fn set_nvme_identity(
+        &self,
+        identity: &NvmeIdentity,
+    ) -> Result<(), Error> {
+        let value = serde_json::to_string(identity).map_err(|e| {
+            Error::SetProperty {
+                source: Errno::EINVAL,
+                prop: format!("{LVOL_NVME_IDENTITY_XATTR}: {e}"),
+                name: self.name(),
+            }
+        })?;
+        self.set_blob_attr(LVOL_NVME_IDENTITY_XATTR, value, true)
+            .await
+    }
+
     /// Common API to get the xattr from blob.
     pub fn get_blob_xattr(lvol: &Lvol, attr: &str) -> Option<String> {
         let mut val: *const libc::c_char = std::ptr::null::<libc::c_char>();
@@ -480,6 +530,62 @@ impl Lvol {
             }
         }
     }
+
+    /// Low-level function to remove blob attributes.
+    pub async fn remove_blob_attr<A: AsRef<str>>(
+        &self,
+        attr: A,
+        sync_metadata: bool,
+    ) -> Result<(), Error> {
+        extern "C" fn blob_attr_remove_cb(cb_arg: *mut c_void, errno: i32) {
+            done_cb(cb_arg, errno);
+        }
+
+        let attr_name = attr.as_ref().into_cstring();
+
+        let r = unsafe {
+            spdk_blob_remove_xattr(
+                self.blob_checked(),
+                attr_name.as_ptr() as *const c_char,
+            )
+        };
+
+        if r != 0 && r != -libc::ENOENT {
+            error!(
+                lvol = self.name(),
+                attr = attr.as_ref(),
+                errno = r,
+                "Failed to remove blob attribute"
+            );
+            return Err(Error::SetProperty {
+                source: Errno::from_i32(r.abs()),
+                prop: attr.as_ref().to_owned(),
+                name: self.name(),
+            });
+        }
+
+        if !sync_metadata {
+            return Ok(());
+        }
+
+        let (snd, rcv) = oneshot::channel::<i32>();
+
+        unsafe {
+            spdk_blob_sync_md(
+                self.blob_checked(),
+                Some(blob_attr_remove_cb),
+                cb_arg(snd),
+            )
+        };
+
+        match rcv.await.expect("sync attribute callback disappeared") {
+            0 => Ok(()),
+            errno => Err(Error::SyncProperty {
+                source: Errno::from_i32(errno),
+                name: self.name(),
+            }),
+        }
+    }
 }
 
 struct LvolPtpl {
@@ -830,6 +936,7 @@ impl LvsLvol for Lvol {
 
         // We must always unshare before destroying bdev.
         let _ = Pin::new(&mut self).unshare().await;
+        self.destroy_compression().await?;
 
         let name = self.name();
         let ptpl = self.ptpl();
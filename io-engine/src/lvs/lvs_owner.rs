@@ -0,0 +1,160 @@
+//! Detection of pools imported by another node.
+//!
+//! A pool disk reachable from several nodes, as during an HA failover, must
+//! not be imported by two of them at once: both would write the blobstore
+//! metadata and corrupt it. Two signals are combined to detect it:
+//!
+//! - the blobstore of an imported pool is marked dirty on disk, and only marked
+//!   clean when the pool is exported;
+//! - the node importing a pool records itself as its owner in the persistent
+//!   store, and removes the record when it exports or destroys the pool.
+//!
+//! A pool whose blobstore is clean is not imported anywhere. A dirty pool
+//! owned by another node is either imported by that node, or was left
+//! behind when the node crashed: both cases look the same from here, so the
+//! import is refused unless forced. The check reads the disk before the pool
+//! is loaded, since loading it writes its metadata. Forcing the import does not fence the
+//! other node: the caller must make sure it no longer uses the disk. The
+//! import of a dirty pool is also refused when the persistent store cannot
+//! be read, and allowed with a warning when there is no persistent store.
+
+use chrono::{DateTime, Utc};
+use nix::errno::Errno;
+use serde::{Deserialize, Serialize};
+
+use super::{lvs_check::DiskState, Error, ImportErrorReason, Lvs};
+use crate::{
+    core::MayastorEnvironment,
+    persistent_store::PersistentStore,
+    store::store_defs::StoreError,
+};
+
+/// Owner of a pool, as recorded in the persistent store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolOwner {
+    /// Name of the node which imported the pool.
+    pub node: String,
+    /// Time the pool was imported.
+    pub imported_at: DateTime<Utc>,
+}
+
+/// Key of the owner of the given pool in the persistent store.
+fn store_key(pool_uuid: &str) -> String {
+    format!("io-engine/pools/{pool_uuid}/owner")
+}
+
+fn node_name() -> String {
+    MayastorEnvironment::global_or_default().node_name
+}
+
+impl Lvs {
+    /// Returns the owner of the pool with the given uuid recorded in the
+    /// persistent store, if any. The persistent store must be enabled.
+    async fn owner(
+        name: &str,
+        pool_uuid: &str,
+    ) -> Result<Option<PoolOwner>, StoreError> {
+        match PersistentStore::get(&store_key(pool_uuid)).await {
+            Ok(value) => Ok(serde_json::from_value(value)
+                .map_err(|error| {
+                    warn!("Pool {name}: ignoring invalid pool owner: {error}")
+                })
+                .ok()),
+            Err(StoreError::MissingEntry {
+                ..
+            }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Checks that no other node appears to have the pool imported, given
+    /// the state of its disk read before the pool is loaded: loading the
+    /// pool writes its metadata, which must not happen if the pool is
+    /// imported elsewhere.
+    pub(super) async fn check_owner(
+        name: &str,
+        disk: Option<&DiskState>,
+        force: bool,
+    ) -> Result<(), Error> {
+        let Some(disk) = disk.filter(|d| !d.clean) else {
+            return Ok(());
+        };
+        if !PersistentStore::enabled() {
+            warn!(
+                "Pool {name}: blobstore was not shut down cleanly, and \
+                without a persistent store it is unknown whether another \
+                node has the pool imported"
+            );
+            return Ok(());
+        }
+
+        let node = node_name();
+        let reason = match &disk.uuid {
+            Some(uuid) => match Self::owner(name, uuid).await {
+                Ok(Some(owner)) if owner.node != node => {
+                    ImportErrorReason::OwnedBy {
+                        node: owner.node,
+                    }
+                }
+                Ok(_) => return Ok(()),
+                Err(error) => ImportErrorReason::OwnerUnknown {
+                    error: error.to_string(),
+                },
+            },
+            None => ImportErrorReason::OwnerUnknown {
+                error: "the pool uuid cannot be read from the disk".to_string(),
+            },
+        };
+
+        if force {
+            warn!("Pool {name}: forcing the import of the pool{reason}");
+            return Ok(());
+        }
+        Err(Error::Import {
+            source: Errno::EBUSY,
+            name: name.to_string(),
+            reason,
+        })
+    }
+
+    /// Records this node as the owner of the pool. Failures are logged: the
+    /// pool is imported regardless. Pools on RAM-backed malloc disks cannot
+    /// be imported by another node, and are not recorded.
+    pub(super) async fn claim_owner(&self) {
+        if !PersistentStore::enabled() || self.base_bdev().driver() == "malloc"
+        {
+            return;
+        }
+        let owner = PoolOwner {
+            node: node_name(),
+            imported_at: Utc::now(),
+        };
+        if let Err(error) =
+            PersistentStore::put(&store_key(&self.uuid()), &owner).await
+        {
+            warn!("{self:?}: failed to record the pool owner: {error}");
+        }
+    }
+
+    /// Removes the owner record of the pool with the given uuid, if this
+    /// node is the owner.
+    pub(super) async fn release_owner(pool_uuid: &str) {
+        if !PersistentStore::enabled() {
+            return;
+        }
+        let key = store_key(pool_uuid);
+        let owned = match PersistentStore::get(&key).await {
+            Ok(value) => serde_json::from_value::<PoolOwner>(value)
+                .map_or(false, |o| o.node == node_name()),
+            Err(_) => false,
+        };
+        if owned {
+            if let Err(error) = PersistentStore::delete(&key).await {
+                warn!(
+                    "Failed to remove the owner record of pool {pool_uuid}: \
+                    {error}"
+                );
+            }
+        }
+    }
+}
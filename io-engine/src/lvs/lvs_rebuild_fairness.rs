@@ -0,0 +1,292 @@
+//! Soft write limits of the pools which are the destination of a rebuild.
+//!
+//! A rebuild writes to its destination replica as fast as the source allows,
+//! and competes for the pool disk with the other replicas of the pool. While
+//! a rebuild writes to a local replica, the write throughput of the replicas
+//! of its pool is sampled every second, and the highest total throughput
+//! seen during the rebuild is taken as the bandwidth of the pool.
+//!
+//! When the pool is saturated, the other replicas are limited to the share
+//! of the pool bandwidth given by the rebuild fairness ratio of the pool,
+//! split among them in proportion to their current writes, and the rebuild
+//! gets the rest. A replica is never limited below 1 MiB/s. The limits are
+//! soft: they are lifted as soon as the pool is no longer saturated, and
+//! once the last rebuild to the pool ends.
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::{Lazy, OnceCell};
+
+use super::{Lvol, Lvs, LvsLvol};
+use crate::{
+    bdev_api::bdev_get_name,
+    core::{logical_volume::LogicalVolume, Reactors, UntypedBdev},
+    sleep::mayastor_sleep,
+};
+
+/// Interval between two samples of the write throughput of a pool.
+const FAIRNESS_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Fraction of the pool bandwidth from which the pool is saturated.
+const FAIRNESS_SATURATION: f64 = 0.9;
+
+/// Lowest write limit of a replica, which is also the granularity of the
+/// SPDK bandwidth limits.
+const FAIRNESS_MIN_LIMIT: u64 = 1024 * 1024;
+
+/// Default rebuild fairness ratio of the pools, in percent.
+static POOL_REBUILD_FAIRNESS: OnceCell<u32> = OnceCell::new();
+
+/// Rebuild fairness ratios set per pool, by pool uuid.
+static POOL_FAIRNESS_OVERRIDES: Lazy<parking_lot::Mutex<HashMap<String, u32>>> =
+    Lazy::new(Default::default);
+
+/// Rebuild destination replicas, by pool uuid. A pool is regulated as long
+/// as it has an entry.
+static POOL_REBUILDS: Lazy<parking_lot::Mutex<HashMap<String, Vec<String>>>> =
+    Lazy::new(Default::default);
+
+impl Lvs {
+    /// Sets the default rebuild fairness ratio of the pools, in percent of
+    /// the pool bandwidth. A ratio of 0 disables the limits.
+    pub fn configure_rebuild_fairness(ratio: u32) {
+        if POOL_REBUILD_FAIRNESS.set(ratio.min(100)).is_err() {
+            warn!("Pool rebuild fairness is already configured");
+        }
+    }
+
+    /// Returns the rebuild fairness ratio of the pool, in percent of the
+    /// pool bandwidth, 0 if disabled.
+    pub fn rebuild_fairness(&self) -> u32 {
+        pool_fairness(&self.uuid())
+    }
+
+    /// Sets the rebuild fairness ratio of the pool, or reverts it to the
+    /// default one.
+    pub fn set_rebuild_fairness(&self, ratio: Option<u32>) {
+        let mut overrides = POOL_FAIRNESS_OVERRIDES.lock();
+        match ratio {
+            Some(ratio) => {
+                info!("{self:?}: setting rebuild fairness to {ratio}%");
+                overrides.insert(self.uuid(), ratio.min(100));
+            }
+            None => {
+                info!("{self:?}: reverting to the default rebuild fairness");
+                overrides.remove(&self.uuid());
+            }
+        }
+    }
+
+    /// Checks if a rebuild is writing to a replica of the pool, so that the
+    /// writes of its other replicas are regulated.
+    pub fn rebuild_active(&self) -> bool {
+        POOL_REBUILDS.lock().contains_key(&self.uuid())
+    }
+}
+
+fn pool_fairness(pool_uuid: &str) -> u32 {
+    POOL_FAIRNESS_OVERRIDES
+        .lock()
+        .get(pool_uuid)
+        .copied()
+        .or_else(|| POOL_REBUILD_FAIRNESS.get().copied())
+        .unwrap_or_default()
+}
+
+/// Registration of a rebuild writing to a local replica, which regulates the
+/// writes of the other replicas of its pool until dropped.
+#[derive(Debug)]
+pub struct PoolRebuildGuard {
+    pool_uuid: String,
+    replica: String,
+}
+
+impl PoolRebuildGuard {
+    /// Registers a rebuild writing to the device of the given URI. Returns
+    /// `None` if the device is not a local replica.
+    pub fn new(dst_uri: &str) -> Option<Self> {
+        let lvol = bdev_get_name(dst_uri)
+            .ok()
+            .and_then(|name| UntypedBdev::lookup_by_name(&name))
+            .and_then(|bdev| Lvol::try_from(bdev).ok())?;
+
+        let guard = Self {
+            pool_uuid: lvol.pool_uuid(),
+            replica: lvol.name(),
+        };
+
+        let mut rebuilds = POOL_REBUILDS.lock();
+        match rebuilds.get_mut(&guard.pool_uuid) {
+            Some(replicas) => replicas.push(guard.replica.clone()),
+            None => {
+                rebuilds.insert(
+                    guard.pool_uuid.clone(),
+                    vec![guard.replica.clone()],
+                );
+                Reactors::master()
+                    .send_future(regulate_pool(guard.pool_uuid.clone()));
+            }
+        }
+        Some(guard)
+    }
+}
+
+impl Drop for PoolRebuildGuard {
+    fn drop(&mut self) {
+        if let Some(replicas) = POOL_REBUILDS.lock().get_mut(&self.pool_uuid) {
+            if let Some(idx) = replicas.iter().position(|r| r == &self.replica)
+            {
+                replicas.swap_remove(idx);
+            }
+        }
+    }
+}
+
+/// Write throughput of the replicas of a pool.
+struct PoolWrites {
+    /// Bytes written by each replica, by name.
+    bytes: HashMap<String, u64>,
+    /// Time of the sample.
+    at: Option<Instant>,
+}
+
+impl PoolWrites {
+    /// Samples the bytes written by the replicas of the pool.
+    async fn sample(pool_uuid: &str) -> Self {
+        let lvols = Lvs::lookup_by_uuid(pool_uuid)
+            .and_then(|lvs| lvs.lvols())
+            .map(|lvols| lvols.filter(|l| !l.is_snapshot()).collect())
+            .unwrap_or_else(Vec::new);
+
+        let mut bytes = HashMap::with_capacity(lvols.len());
+        for lvol in lvols {
+            if let Ok(stats) = lvol.as_bdev().stats_async().await {
+                bytes.insert(lvol.name(), stats.bytes_written);
+            }
+        }
+        Self {
+            bytes,
+            at: Some(Instant::now()),
+        }
+    }
+
+    /// Returns the write rate of each replica since the previous sample, in
+    /// bytes per second.
+    fn rates(&self, previous: &Self) -> HashMap<String, u64> {
+        let (Some(now), Some(then)) = (self.at, previous.at) else {
+            return HashMap::new();
+        };
+        let secs = (now - then).as_secs_f64();
+        if secs <= 0.0 {
+            return HashMap::new();
+        }
+        self.bytes
+            .iter()
+            .filter_map(|(name, bytes)| {
+                let before = previous.bytes.get(name)?;
+                let rate = bytes.saturating_sub(*before) as f64 / secs;
+                Some((name.clone(), rate as u64))
+            })
+            .collect()
+    }
+}
+
+/// Sets or removes the write limit of a replica of the pool.
+async fn set_limit(pool_uuid: &str, replica: &str, limit: Option<u64>) {
+    let lvol = Lvs::lookup_by_uuid(pool_uuid)
+        .and_then(|lvs| lvs.lvols())
+        .and_then(|mut lvols| lvols.find(|l| l.name() == replica));
+    let Some(lvol) = lvol else {
+        return;
+    };
+    if let Err(error) = lvol.as_bdev().set_write_bandwidth_limit(limit).await {
+        warn!("{lvol:?}: failed to set rebuild fairness write limit: {error}");
+    }
+}
+
+/// Removes the write limits of the replicas of the pool.
+async fn lift_limits(pool_uuid: &str, limits: &mut HashMap<String, u64>) {
+    if !limits.is_empty() {
+        debug!("Pool '{pool_uuid}': lifting replica write limits");
+    }
+    for replica in std::mem::take(limits).keys() {
+        set_limit(pool_uuid, replica, None).await;
+    }
+}
+
+/// Regulates the writes of the replicas of a pool, while rebuilds write to
+/// it. Runs on the primary reactor.
+async fn regulate_pool(pool_uuid: String) {
+    info!("Pool '{pool_uuid}': rebuild started, regulating replica writes");
+
+    // Highest total write throughput seen, in bytes per second.
+    let mut bandwidth = 0;
+    // Current write limits of the replicas, by name.
+    let mut limits = HashMap::<String, u64>::new();
+    let mut previous = PoolWrites::sample(&pool_uuid).await;
+
+    loop {
+        if mayastor_sleep(FAIRNESS_SAMPLE_INTERVAL).await.is_err() {
+            error!("Pool '{pool_uuid}': failed to wait for write sample");
+        }
+
+        let rebuilds = POOL_REBUILDS
+            .lock()
+            .get(&pool_uuid)
+            .cloned()
+            .unwrap_or_default();
+        if rebuilds.is_empty() {
+            lift_limits(&pool_uuid, &mut limits).await;
+
+            // A rebuild may have started while the limits were lifted.
+            let mut pools = POOL_REBUILDS.lock();
+            if pools.get(&pool_uuid).map_or(true, |r| r.is_empty()) {
+                pools.remove(&pool_uuid);
+                info!(
+                    "Pool '{pool_uuid}': no more rebuilds, stopped \
+                    regulating replica writes"
+                );
+                return;
+            }
+            continue;
+        }
+
+        let current = PoolWrites::sample(&pool_uuid).await;
+        let rates = current.rates(&previous);
+        previous = current;
+
+        let total = rates.values().sum::<u64>();
+        bandwidth = bandwidth.max(total);
+        let others = rates
+            .iter()
+            .filter(|(name, _)| !rebuilds.contains(name))
+            .collect::<Vec<_>>();
+        let others_total = others.iter().map(|(_, r)| **r).sum::<u64>();
+        let ratio = pool_fairness(&pool_uuid);
+        let budget = bandwidth / 100 * ratio as u64;
+
+        let saturated = bandwidth > 0
+            && total as f64 >= bandwidth as f64 * FAIRNESS_SATURATION;
+        if ratio == 0 || !saturated || others_total <= budget {
+            lift_limits(&pool_uuid, &mut limits).await;
+            continue;
+        }
+
+        debug!(
+            "Pool '{pool_uuid}': saturated at {total} B/s, limiting the \
+            writes of {n} replica(s) to {budget} B/s",
+            n = others.len()
+        );
+        for (name, rate) in others {
+            let share =
+                (budget as f64 * *rate as f64 / others_total as f64) as u64;
+            let limit = share.max(FAIRNESS_MIN_LIMIT);
+            if limits.get(name) != Some(&limit) {
+                set_limit(&pool_uuid, name, Some(limit)).await;
+                limits.insert(name.clone(), limit);
+            }
+        }
+    }
+}
@@ -9,6 +9,7 @@ use std::{
 use byte_unit::Byte;
 use futures::channel::oneshot;
 use nix::errno::Errno;
+use once_cell::sync::OnceCell;
 use pin_utils::core_reexport::fmt::Formatter;
 use spdk_rs::libspdk::{
     spdk_blob_store,
@@ -70,6 +71,9 @@ impl Debug for Lvs {
     }
 }
 
+/// Over-commit limit of the pools, in percent of their capacity.
+static POOL_OVERCOMMIT_LIMIT: OnceCell<u32> = OnceCell::new();
+
 /// Logical Volume Store (LVS) stores the lvols
 pub struct Lvs {
     inner: NonNull<spdk_lvol_store>,
@@ -198,6 +202,30 @@ impl Lvs {
             .map_or(0, |vols| vols.fold(0, |acc, r| acc + r.committed()))
     }
 
+    /// Sets the over-commit limit of the pools, in percent of their capacity.
+    /// A limit of 0 disables it.
+    pub fn configure_overcommit(limit: u32) {
+        if POOL_OVERCOMMIT_LIMIT.set(limit).is_err() {
+            warn!("Pool over-commit limit is already configured");
+        }
+    }
+
+    /// Returns the maximum committed size of the pool, if limited.
+    pub fn overcommit_limit(&self) -> Option<u64> {
+        match POOL_OVERCOMMIT_LIMIT.get().copied().unwrap_or_default() {
+            0 => None,
+            pct => Some(self.capacity() / 100 * pct as u64),
+        }
+    }
+
+    /// Returns the committed size of the pool relative to its capacity.
+    pub fn commitment_ratio(&self) -> f64 {
+        match self.capacity() {
+            0 => 0.0,
+            capacity => self.committed() as f64 / capacity as f64,
+        }
+    }
+
     /// returns the base bdev of this lvs
     pub fn base_bdev(&self) -> UntypedBdev {
         let p =
@@ -243,10 +271,61 @@ impl Lvs {
 
     /// imports a pool based on its name and base bdev name
     pub async fn import(name: &str, bdev: &str) -> Result<Lvs, Error> {
-        let (sender, receiver) = pair::<ErrnoResult<Lvs>>();
+        Self::import_with(name, bdev, false).await
+    }
 
+    /// imports the lvs with the given name from the given bdev, even if
+    /// another node appears to have it imported when `force` is set.
+    pub async fn import_with(
+        name: &str,
+        bdev: &str,
+        force: bool,
+    ) -> Result<Lvs, Error> {
         debug!("Trying to import lvs '{}' from '{}'...", name, bdev);
 
+        // The owner is checked on the disk before the pool is loaded, since
+        // loading it writes its metadata.
+        let disk = Self::disk_state(bdev).await;
+        Self::check_owner(name, disk.as_ref(), force).await?;
+        Self::lock_disk(name, bdev, force).await?;
+        let lvs = match Self::load(name, bdev).await {
+            Ok(lvs) => lvs,
+            Err(error) => {
+                Self::unlock_disk(bdev).await;
+                return Err(error);
+            }
+        };
+
+        if name != lvs.name() {
+            warn!(
+                "No lvs with name '{}' found on this device: '{}'; \
+                found lvs: '{}'",
+                name,
+                bdev,
+                lvs.name()
+            );
+            let pool_name = lvs.name().to_string();
+            lvs.export().await?;
+            Err(Error::Import {
+                source: Errno::EINVAL,
+                name: name.to_string(),
+                reason: ImportErrorReason::NameMismatch {
+                    name: pool_name,
+                },
+            })
+        } else {
+            lvs.claim_owner().await;
+            lvs.share_all().await;
+            info!("{:?}: existing lvs imported successfully", lvs);
+            Ok(lvs)
+        }
+    }
+
+    /// loads the lvs found on the given base bdev, whatever its name, without
+    /// sharing its lvols. The name is the one of the pool expected on it.
+    pub(super) async fn load(name: &str, bdev: &str) -> Result<Lvs, Error> {
+        let (sender, receiver) = pair::<ErrnoResult<Lvs>>();
+
         let mut bdev =
             UntypedBdev::lookup_by_name(bdev).ok_or(Error::InvalidBdev {
                 source: BdevError::BdevNotFound {
@@ -286,43 +365,21 @@ impl Lvs {
 
         // when no pool name can be determined the or failed to compare to the
         // desired pool name EILSEQ is returned
-        let lvs = receiver
+        receiver
             .await
             .expect("Cancellation is not supported")
             .map_err(|err| Error::Import {
                 source: err,
                 name: name.into(),
                 reason: ImportErrorReason::None,
-            })?;
-
-        if name != lvs.name() {
-            warn!(
-                "No lvs with name '{}' found on this device: '{}'; \
-                found lvs: '{}'",
-                name,
-                bdev,
-                lvs.name()
-            );
-            let pool_name = lvs.name().to_string();
-            lvs.export().await?;
-            Err(Error::Import {
-                source: Errno::EINVAL,
-                name: name.to_string(),
-                reason: ImportErrorReason::NameMismatch {
-                    name: pool_name,
-                },
             })
-        } else {
-            lvs.share_all().await;
-            info!("{:?}: existing lvs imported successfully", lvs);
-            Ok(lvs)
-        }
     }
 
     /// imports a pool based on its name, uuid and base bdev name
     #[tracing::instrument(level = "debug", err)]
     pub async fn import_from_args(args: PoolArgs) -> Result<Lvs, Error> {
-        let disk = Self::parse_disk(args.disks.clone())?;
+        let configured = Self::parse_disk(args.disks.clone())?;
+        let disk = Self::resolve_disk(&args.name, &configured);
 
         let parsed = uri::parse(&disk).map_err(|e| Error::InvalidBdev {
             source: e,
@@ -369,7 +426,7 @@ impl Lvs {
             Ok(name) => Ok(name),
         }?;
 
-        let pool = Self::import(&args.name, &bdev).await?;
+        let pool = Self::import_with(&args.name, &bdev, args.force).await?;
         // Try to destroy the pending snapshots without catching
         // the error.
         Lvol::destroy_pending_discarded_snapshot().await;
@@ -377,21 +434,19 @@ impl Lvs {
         // for the pool uuid to make sure it is the correct one
         if let Some(uuid) = args.uuid {
             let pool_uuid = pool.uuid();
-            if pool_uuid == uuid {
-                Ok(pool)
-            } else {
+            if pool_uuid != uuid {
                 pool.export().await?;
-                Err(Error::Import {
+                return Err(Error::Import {
                     source: Errno::EINVAL,
                     name: args.name,
                     reason: ImportErrorReason::UuidMismatch {
                         uuid: pool_uuid,
                     },
-                })
+                });
             }
-        } else {
-            Ok(pool)
         }
+        Self::record_disk(&args.name, &configured, &disk);
+        Ok(pool)
     }
 
     /// Create a pool on base bdev
@@ -399,6 +454,22 @@ impl Lvs {
         name: &str,
         bdev: &str,
         uuid: Option<String>,
+    ) -> Result<Lvs, Error> {
+        Self::lock_disk(name, bdev, false).await?;
+        match Self::create_on_disk(name, bdev, uuid).await {
+            Ok(pool) => Ok(pool),
+            Err(error) => {
+                Self::unlock_disk(bdev).await;
+                Err(error)
+            }
+        }
+    }
+
+    /// Creates a pool on a base bdev which is locked.
+    async fn create_on_disk(
+        name: &str,
+        bdev: &str,
+        uuid: Option<String>,
     ) -> Result<Lvs, Error> {
         let pool_name = name.into_cstring();
         let bdev_name = bdev.into_cstring();
@@ -469,7 +540,8 @@ impl Lvs {
     /// imports the pool if it exists, otherwise try to create it
     #[tracing::instrument(level = "debug", err)]
     pub async fn create_or_import(args: PoolArgs) -> Result<Lvs, Error> {
-        let disk = Self::parse_disk(args.disks.clone())?;
+        let configured = Self::parse_disk(args.disks.clone())?;
+        let disk = Self::resolve_disk(&args.name, &configured);
 
         info!(
             "Creating or importing lvs '{}' from '{}'...",
@@ -532,6 +604,7 @@ impl Lvs {
                     }
                     Ok(pool) => {
                         pool.event(EventAction::Create).generate();
+                        Self::record_disk(&args.name, &configured, &disk);
                         Ok(pool)
                     }
                 }
@@ -549,6 +622,7 @@ impl Lvs {
         info!("{}: exporting lvs...", self_str);
 
         let pool = self.name().to_string();
+        let pool_uuid = self.uuid();
         let base_bdev = self.base_bdev();
         let (s, r) = pair::<i32>();
 
@@ -570,6 +644,8 @@ impl Lvs {
             })?;
 
         info!("{}: lvs exported successfully", self_str);
+        Self::release_owner(&pool_uuid).await;
+        Self::unlock_disk(base_bdev.name()).await;
 
         bdev_destroy(&base_bdev.bdev_uri_original_str().unwrap_or_default())
             .await
@@ -641,6 +717,7 @@ impl Lvs {
 
         let ptpl = self.ptpl();
         let pool = self.name().to_string();
+        let pool_uuid = self.uuid();
         let (s, r) = pair::<i32>();
 
         // when destroying a pool unshare all volumes
@@ -664,6 +741,9 @@ impl Lvs {
             })?;
 
         info!("{}: lvs destroyed successfully", self_str);
+        Self::release_owner(&pool_uuid).await;
+        Self::forget_disk(&pool);
+        Self::unlock_disk(base_bdev.name()).await;
 
         self.event(EventAction::Delete).generate();
 
@@ -782,6 +862,18 @@ impl Lvs {
             });
         }
 
+        if let Some(limit) = self.overcommit_limit().filter(|_| thin) {
+            let committed = self.committed();
+            if committed + size > limit {
+                return Err(Error::Overcommit {
+                    name: name.to_string(),
+                    pool: self.name().to_string(),
+                    committed,
+                    limit,
+                });
+            }
+        }
+
         let (s, r) = pair::<ErrnoResult<*mut spdk_lvol>>();
 
         let cname = name.into_cstring();
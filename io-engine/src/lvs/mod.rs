@@ -1,13 +1,46 @@
+pub use lvol_chain::LvolChainLimits;
+pub use lvol_compress::LvolCompress;
+pub use lvol_freeze::{LvolFreeze, LVOL_FREEZE_MAX_TIMEOUT};
+pub use lvol_owner::ReplicaOwner;
 pub use lvol_snapshot::LvolSnapshotIter;
+pub use lvol_state::LvolState;
+pub use lvol_usage_watch::{
+    LvolUsageChange,
+    LvolUsageSample,
+    LvolUsageThresholds,
+    LvolUsageWatch,
+};
+pub use lvol_verify::{
+    VerifyExtent,
+    VerifyExtentKind,
+    VerifyOptions,
+    VerifyStats,
+};
 pub use lvs_bdev::LvsBdev;
+pub use lvs_check::{PoolCheckFinding, PoolCheckSeverity};
+pub use lvs_disk_id::PoolDiskId;
 pub use lvs_error::{Error, ImportErrorReason};
 pub use lvs_iter::{LvsBdevIter, LvsIter};
 pub use lvs_lvol::{Lvol, LvolSpaceUsage, LvsLvol, PropName, PropValue};
+pub use lvs_owner::PoolOwner;
+pub use lvs_rebuild_fairness::PoolRebuildGuard;
 pub use lvs_store::Lvs;
 
+mod lvol_chain;
+mod lvol_compress;
+mod lvol_freeze;
+mod lvol_owner;
 mod lvol_snapshot;
+mod lvol_state;
+mod lvol_usage_watch;
+mod lvol_verify;
 mod lvs_bdev;
+mod lvs_check;
+mod lvs_disk_id;
+mod lvs_disk_lock;
 mod lvs_error;
 mod lvs_iter;
 pub mod lvs_lvol;
+mod lvs_owner;
+mod lvs_rebuild_fairness;
 mod lvs_store;
@@ -4,11 +4,23 @@
 //! etcd is used as the backing store and is interacted with through the use of
 //! the etcd-client crate. This crate has a dependency on the tokio async
 //! runtime.
+//!
+//! The connection to etcd may be secured with TLS and authenticated with a
+//! user name and password. The health of the connection is tracked from the
+//! outcome of the store operations, so that it can be reported to the
+//! control plane.
+//!
+//! An operation timing out triggers a reconnection in the background, so
+//! that the following operations fail fast instead of waiting for etcd to
+//! come back. When enabled, puts which cannot reach the store may be
+//! deferred: they are queued, deduplicated per key, and replayed in order
+//! once the store can be reached again.
 use crate::{
     core,
-    core::Reactor,
+    core::{Reactor, Reactors},
+    sleep::mayastor_sleep,
     store::{
-        etcd::Etcd,
+        etcd::{Etcd, EtcdSecurity},
         store_defs::{
             DeleteWait,
             GetWait,
@@ -21,11 +33,16 @@ use crate::{
     },
 };
 use futures::channel::oneshot;
-use once_cell::sync::OnceCell;
+use once_cell::sync::{Lazy, OnceCell};
 use parking_lot::Mutex;
 use serde_json::Value;
 use snafu::ResultExt;
-use std::{future::Future, time::Duration};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime},
+};
 
 /// Persistent store builder.
 pub struct PersistentStoreBuilder {
@@ -37,6 +54,10 @@ pub struct PersistentStoreBuilder {
     timeout: Duration,
     /// Number of operation retries.
     retries: u8,
+    /// TLS and authentication of the connection.
+    security: EtcdSecurity,
+    /// Maximum number of deferred puts, 0 disables deferral.
+    max_deferred_ops: usize,
 }
 
 impl Default for PersistentStoreBuilder {
@@ -53,6 +74,8 @@ impl PersistentStoreBuilder {
             default_port: 2379,
             timeout: Duration::from_secs(1),
             retries: 5,
+            security: EtcdSecurity::default(),
+            max_deferred_ops: 0,
         }
     }
 
@@ -84,6 +107,35 @@ impl PersistentStoreBuilder {
         self
     }
 
+    /// Enables TLS. Each certificate is either a path to a PEM file or the
+    /// PEM data itself. The client certificate is only presented if both the
+    /// certificate and its key are set.
+    pub fn with_tls(
+        mut self,
+        ca: Option<String>,
+        cert: Option<String>,
+        key: Option<String>,
+    ) -> Self {
+        self.security.ca = ca;
+        self.security.cert = cert;
+        self.security.key = key;
+        self
+    }
+
+    /// Sets the user name and password to authenticate with.
+    pub fn with_credentials(mut self, user: &str, password: &str) -> Self {
+        self.security.credentials =
+            Some((user.to_string(), password.to_string()));
+        self
+    }
+
+    /// Sets the maximum number of puts deferred while the store cannot be
+    /// reached. 0 disables deferral.
+    pub fn with_max_deferred_ops(mut self, max_deferred_ops: usize) -> Self {
+        self.max_deferred_ops = max_deferred_ops;
+        self
+    }
+
     /// Consumes `PersistentStoreBuilder` instance and initialises the
     /// persistent store. If the supplied endpoint is 'None', the store is
     /// uninitalised and unavailable for use.
@@ -102,11 +154,69 @@ pub struct PersistentStore {
     timeout: Duration,
     /// Number of operation retries.
     retries: u8,
+    /// TLS and authentication of the connection.
+    security: EtcdSecurity,
+}
+
+/// Health of the connection to the persistent store.
+#[derive(Debug, Clone, Default)]
+pub struct PersistentStoreHealth {
+    /// Endpoint of the backing store.
+    pub endpoint: String,
+    /// Whether the last connection attempt or operation succeeded.
+    pub connected: bool,
+    /// Whether the connection uses TLS.
+    pub tls: bool,
+    /// Whether the connection is authenticated with a user name.
+    pub authenticated: bool,
+    /// Error of the last failed connection attempt or operation.
+    pub last_error: Option<String>,
+    /// Time of the last failed connection attempt or operation.
+    pub last_error_time: Option<SystemTime>,
+    /// Time of the last successful connection or operation.
+    pub last_success_time: Option<SystemTime>,
+    /// Number of reconnections after operation timeouts.
+    pub reconnects: u64,
+    /// Number of puts waiting to be replayed.
+    pub deferred_ops: usize,
+}
+
+/// Put deferred until the store can be reached again.
+#[derive(Debug, Clone)]
+struct DeferredPut {
+    key: String,
+    value: Value,
+    /// Sequence number of the put, to tell a replayed put from a newer put
+    /// of the same key.
+    seq: u64,
+}
+
+/// Puts deferred while the store cannot be reached, in the order they are
+/// to be replayed.
+#[derive(Debug, Default)]
+struct DeferredOps {
+    /// Maximum number of deferred puts, 0 disables deferral.
+    max: usize,
+    ops: VecDeque<DeferredPut>,
+    next_seq: u64,
+    /// Whether the replay of the deferred puts is running.
+    replaying: bool,
 }
 
 /// Persistent store global instance.
 static PERSISTENT_STORE: OnceCell<Mutex<PersistentStore>> = OnceCell::new();
 
+/// Health of the persistent store connection, tracked from the first
+/// connection attempt on.
+static PERSISTENT_STORE_HEALTH: OnceCell<Mutex<PersistentStoreHealth>> =
+    OnceCell::new();
+
+/// Puts deferred while the store cannot be reached.
+static DEFERRED_OPS: Lazy<Mutex<DeferredOps>> = Lazy::new(Default::default);
+
+/// Set while reconnecting to the backing store.
+static RECONNECTING: AtomicBool = AtomicBool::new(false);
+
 impl PersistentStore {
     /// Initialises the persistent store.
     /// If the supplied endpoint is 'None', the store is uninitalised and
@@ -120,11 +230,26 @@ impl PersistentStore {
 
         let timeout = bld.timeout;
         let retries = bld.retries;
-        let store = Self::connect_to_backing_store(&endpoint.clone()).await;
+        let security = bld.security;
+        DEFERRED_OPS.lock().max = bld.max_deferred_ops;
+
+        PERSISTENT_STORE_HEALTH.get_or_init(|| {
+            Mutex::new(PersistentStoreHealth {
+                endpoint: endpoint.clone(),
+                tls: security.tls_enabled(),
+                authenticated: security.credentials.is_some(),
+                ..Default::default()
+            })
+        });
+
+        let store =
+            Self::connect_to_backing_store(&endpoint.clone(), &security).await;
 
         info!(
             "Persistent store operation timeout: {timeout:?}, \
-            number of retries: {retries}"
+            number of retries: {retries}, security: {security:?}, \
+            deferred puts: {max_deferred_ops}",
+            max_deferred_ops = bld.max_deferred_ops
         );
 
         PERSISTENT_STORE.get_or_init(|| {
@@ -133,6 +258,7 @@ impl PersistentStore {
                 endpoint,
                 timeout,
                 retries,
+                security,
             })
         });
     }
@@ -141,24 +267,29 @@ impl PersistentStore {
     /// A connection to the store will be attempted continuously until
     /// successful. This is necessary as the backing store is essential to the
     /// operation of Mayastor across restarts.
-    async fn connect_to_backing_store(endpoint: &str) -> Etcd {
+    async fn connect_to_backing_store(
+        endpoint: &str,
+        security: &EtcdSecurity,
+    ) -> Etcd {
         let mut output_err = true;
         loop {
-            match Etcd::new(endpoint).await {
+            match Etcd::new(endpoint, security).await {
                 Ok(store) => {
                     info!("Connected to etcd on endpoint {}", endpoint);
+                    Self::record_success();
                     return store;
                 }
-                Err(_) => {
+                Err(error) => {
                     if output_err {
                         // Only output the error on first failure to prevent
                         // flooding the logs.
                         error!(
-                            "Failed to connect to etcd on endpoint {}. Retrying...",
-                            endpoint
+                            "Failed to connect to etcd on endpoint {}: {}. Retrying...",
+                            endpoint, error
                         );
                         output_err = false;
                     }
+                    Self::record_error(&error);
                     tokio::time::sleep(Duration::from_secs(1)).await;
                 }
             }
@@ -257,10 +388,19 @@ impl PersistentStore {
             let result = match tokio::time::timeout(op_timeout, f).await {
                 Ok(result) => result,
                 Err(_) => {
-                    Self::reconnect().await;
+                    Self::record_error(&StoreError::OpTimeout {});
+                    Self::schedule_reconnect();
                     Err(StoreError::OpTimeout {})
                 }
             };
+            match &result {
+                // A missing entry is an answer from the store.
+                Ok(_)
+                | Err(StoreError::MissingEntry {
+                    ..
+                }) => Self::record_success(),
+                Err(error) => Self::record_error(error),
+            }
 
             // Execute the sending of the result on a "Mayastor thread".
             let rx = Reactor::spawn_at_primary(async move {
@@ -308,13 +448,153 @@ impl PersistentStore {
         Self::instance().lock().retries
     }
 
+    /// Returns the health of the connection to the persistent store, or
+    /// `None` if the store is not enabled.
+    pub fn health() -> Option<PersistentStoreHealth> {
+        PERSISTENT_STORE_HEALTH
+            .get()
+            .map(|h| PersistentStoreHealth {
+                deferred_ops: DEFERRED_OPS.lock().ops.len(),
+                ..h.lock().clone()
+            })
+    }
+
+    /// Whether the last connection attempt or operation failed.
+    pub fn is_unreachable() -> bool {
+        PERSISTENT_STORE_HEALTH
+            .get()
+            .map(|h| !h.lock().connected)
+            .unwrap_or_default()
+    }
+
+    /// Whether a put of the given key is waiting to be replayed. Puts of such
+    /// a key must be deferred as well, so that they are not overwritten by
+    /// the replay.
+    pub fn is_deferred(key: &impl StoreKey) -> bool {
+        let key = key.to_string();
+        DEFERRED_OPS.lock().ops.iter().any(|op| op.key == key)
+    }
+
+    /// Defers a put of the given key-value until the store can be reached
+    /// again. A put of the same key waiting to be replayed is replaced.
+    /// Returns false if deferral is disabled or too many puts are deferred
+    /// already, in which case the put must be failed.
+    pub fn defer_put(key: &impl StoreKey, value: &impl StoreValue) -> bool {
+        let value = serde_json::to_value(value)
+            .expect("Failed to convert value to a serde_json value");
+        let key = key.to_string();
+
+        let mut deferred = DEFERRED_OPS.lock();
+        if deferred.max == 0 {
+            return false;
+        }
+        deferred.ops.retain(|op| op.key != key);
+        if deferred.ops.len() >= deferred.max {
+            error!(
+                "Too many deferred persistent store puts ({}), \
+                failed to defer key {key}",
+                deferred.ops.len()
+            );
+            return false;
+        }
+
+        let seq = deferred.next_seq;
+        deferred.next_seq += 1;
+        warn!("Deferring put of key {key} until the store is reachable");
+        deferred.ops.push_back(DeferredPut {
+            key,
+            value,
+            seq,
+        });
+
+        if !deferred.replaying {
+            deferred.replaying = true;
+            Reactors::master().send_future(Self::replay_deferred());
+        }
+        true
+    }
+
+    /// Replays the deferred puts in order, retrying each until it succeeds.
+    async fn replay_deferred() {
+        loop {
+            let op = {
+                let mut deferred = DEFERRED_OPS.lock();
+                match deferred.ops.front() {
+                    Some(op) => op.clone(),
+                    None => {
+                        deferred.replaying = false;
+                        return;
+                    }
+                }
+            };
+
+            match Self::put(&op.key, &op.value).await {
+                Ok(()) => {
+                    info!("Replayed deferred put of key {}", op.key);
+                    let mut deferred = DEFERRED_OPS.lock();
+                    if deferred.ops.front().map(|o| o.seq) == Some(op.seq) {
+                        deferred.ops.pop_front();
+                    }
+                }
+                Err(error) => {
+                    debug!(
+                        "Failed to replay deferred put of key {}: {error}",
+                        op.key
+                    );
+                    mayastor_sleep(Duration::from_secs(1)).await.ok();
+                }
+            }
+        }
+    }
+
+    /// Reconnects to the backing store in the background, unless a
+    /// reconnection is already in progress.
+    fn schedule_reconnect() {
+        if RECONNECTING
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            core::runtime::spawn(async {
+                Self::reconnect().await;
+                RECONNECTING.store(false, Ordering::Release);
+            });
+        }
+    }
+
+    /// Records a successful connection or operation.
+    fn record_success() {
+        if let Some(health) = PERSISTENT_STORE_HEALTH.get() {
+            let mut health = health.lock();
+            health.connected = true;
+            health.last_success_time = Some(SystemTime::now());
+        }
+    }
+
+    /// Records a failed connection attempt or operation.
+    fn record_error(error: &StoreError) {
+        if let Some(health) = PERSISTENT_STORE_HEALTH.get() {
+            let mut health = health.lock();
+            health.connected = false;
+            health.last_error = Some(error.to_string());
+            health.last_error_time = Some(SystemTime::now());
+        }
+    }
+
     /// Reconnects to the backing store and replaces the old connection with the
-    /// new connection.
+    /// new connection. The certificates are read again, so that rotated
+    /// certificates are picked up.
     async fn reconnect() {
         warn!("Attempting to reconnect to persistent store....");
+        if let Some(health) = PERSISTENT_STORE_HEALTH.get() {
+            health.lock().reconnects += 1;
+        }
         let persistent_store = Self::instance();
-        let backing_store =
-            Self::connect_to_backing_store(&PersistentStore::endpoint()).await;
+        let security = persistent_store.lock().security.clone();
+        let backing_store = Self::connect_to_backing_store(
+            &PersistentStore::endpoint(),
+            &security,
+        )
+        .await;
         persistent_store.lock().store = backing_store;
     }
 }
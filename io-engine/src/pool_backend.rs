@@ -8,11 +8,17 @@ pub struct PoolArgs {
     pub name: String,
     pub disks: Vec<String>,
     pub uuid: Option<String>,
+    /// Import the pool even if another node appears to have it imported.
+    pub force: bool,
 }
 
 /// PoolBackend is the type of pool underneath Lvs, Lvm, etc
 pub enum PoolBackend {
     Lvs,
+    /// Lvs on a RAM-backed malloc bdev, for tests and development only: the
+    /// pool needs no disk, and does not survive the restart of the engine.
+    #[cfg(feature = "malloc-pool-backend")]
+    Malloc,
 }
 
 impl TryFrom<i32> for PoolBackend {
@@ -21,6 +27,13 @@ impl TryFrom<i32> for PoolBackend {
     fn try_from(value: i32) -> Result<Self, Self::Error> {
         match value {
             0 => Ok(Self::Lvs),
+            #[cfg(feature = "malloc-pool-backend")]
+            1 => Ok(Self::Malloc),
+            #[cfg(not(feature = "malloc-pool-backend"))]
+            1 => Err(Self::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "malloc pools require the malloc-pool-backend feature",
+            )),
             _ => Err(Self::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 format!("invalid pool type {value}"),
@@ -28,3 +41,36 @@ impl TryFrom<i32> for PoolBackend {
         }
     }
 }
+
+/// Size of the disk of a malloc pool created without a size, in MiB.
+#[cfg(feature = "malloc-pool-backend")]
+const MALLOC_POOL_DEFAULT_SIZE_MB: u128 = 64;
+
+/// Returns the disks of a malloc pool with the given name: the given disk,
+/// if it is already a malloc URI, or a malloc disk of the given size, such
+/// as `128MiB`, or of 64MiB without size.
+#[cfg(feature = "malloc-pool-backend")]
+pub fn malloc_pool_disks(
+    name: &str,
+    disks: &[String],
+) -> Result<Vec<String>, std::io::Error> {
+    let invalid = |msg: String| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)
+    };
+    let size_mb = match disks {
+        [] => MALLOC_POOL_DEFAULT_SIZE_MB,
+        [disk] if disk.starts_with("malloc:") => return Ok(disks.to_vec()),
+        [size] => {
+            let bytes = byte_unit::Byte::from_str(size)
+                .map_err(|e| invalid(format!("invalid pool size {size}: {e}")))?
+                .get_bytes();
+            (bytes + (1 << 20) - 1) >> 20
+        }
+        _ => return Err(invalid("malloc pools have a single disk".into())),
+    };
+    if size_mb == 0 || size_mb > u32::MAX as u128 {
+        return Err(invalid(format!("invalid pool size {size_mb}MiB")));
+    }
+    Ok(vec![format!("malloc:///{name}-disk?size_mb={size_mb}")])
+}
+
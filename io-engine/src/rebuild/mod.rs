@@ -1,31 +1,60 @@
+mod rebuild_cor;
 mod rebuild_descriptor;
 mod rebuild_error;
 mod rebuild_job;
 mod rebuild_job_backend;
 mod rebuild_map;
+mod rebuild_scheduler;
 mod rebuild_state;
 mod rebuild_stats;
 mod rebuild_task;
 
-use rebuild_descriptor::RebuildDescriptor;
+pub(crate) use rebuild_cor::CopyOnReadMap;
+pub use rebuild_cor::CopyOnReadStats;
+use rebuild_descriptor::{RebuildDescriptor, RebuildSource};
 pub(crate) use rebuild_error::RebuildError;
 use rebuild_job::RebuildOperation;
-pub use rebuild_job::{RebuildJob, RebuildJobOptions, RebuildVerifyMode};
+pub use rebuild_job::{
+    RebuildIoPolicy,
+    RebuildJob,
+    RebuildJobOptions,
+    RebuildVerifyMode,
+};
 use rebuild_job_backend::{
     RebuildFBendChan,
     RebuildJobBackend,
     RebuildJobRequest,
 };
 pub(crate) use rebuild_map::RebuildMap;
+pub use rebuild_scheduler::{
+    PendingRebuild,
+    RebuildPriority,
+    RebuildScheduler,
+    RebuildSchedulerConfig,
+    REBUILD_CLIENT_QD_THRESHOLD,
+};
+use rebuild_scheduler::{RebuildSlot, RebuildSlotRequest};
 pub use rebuild_state::RebuildState;
 use rebuild_state::RebuildStates;
 pub(crate) use rebuild_stats::HistoryRecord;
-pub use rebuild_stats::RebuildStats;
+use rebuild_stats::RebuildThroughput;
+pub use rebuild_stats::{RebuildSourceStats, RebuildStats};
 use rebuild_task::{RebuildTask, RebuildTasks, TaskResult};
 
 /// Number of concurrent copy tasks per rebuild job
 const SEGMENT_TASKS: usize = 16;
 
+/// Maximum number of concurrent copy tasks a rebuild job can be configured
+/// with
+const MAX_SEGMENT_TASKS: usize = 256;
+
+/// Maximum size of each segment a rebuild job can be configured with
+const MAX_SEGMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Interval between attempts to resume a rebuild job which ran out of space
+const NO_SPACE_RETRY_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(10);
+
 /// Size of each segment used by the copy task
 pub(crate) const SEGMENT_SIZE: u64 =
     spdk_rs::libspdk::SPDK_BDEV_LARGE_BUF_MAX_SIZE as u64;
@@ -0,0 +1,169 @@
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Formatter},
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Map of the segments of a rebuild destination, shared between the rebuild
+/// job and the nexus I/O channels to serve reads from the destination while
+/// it is being rebuilt (copy-on-read).
+///
+/// Reads of segments which have already been copied are served by the
+/// destination. Reads of other segments are served by the other children,
+/// and the destination is populated with the segments they cover: they are
+/// queued to be copied by the rebuild job ahead of the sequential order, by
+/// the first rebuild task to become free. Segments are copied by the rebuild
+/// job only, under the nexus range lock, so that populating the destination
+/// cannot race with a write.
+pub(crate) struct CopyOnReadMap {
+    /// Range of the rebuild, in blocks.
+    range: Range<u64>,
+    /// Segment size in blocks.
+    segment_size_blks: u64,
+    /// Bitmap of the segments already copied to the destination.
+    synced: Vec<AtomicU64>,
+    /// Bitmap of the segments queued to be copied first.
+    requested: Vec<AtomicU64>,
+    /// Queue of the segments to be copied first.
+    queue: parking_lot::Mutex<VecDeque<u64>>,
+    /// Number of reads served by the destination.
+    hits: AtomicU64,
+    /// Number of reads served by the other children.
+    misses: AtomicU64,
+    /// Number of segments copied ahead of the sequential order, to populate
+    /// the destination after a read missed it.
+    populated: AtomicU64,
+}
+
+/// Copy-on-read statistics of a rebuild.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CopyOnReadStats {
+    /// Number of reads served by the destination.
+    pub hits: u64,
+    /// Number of reads served by the other children.
+    pub misses: u64,
+    /// Number of segments copied to populate the destination after a read
+    /// missed it.
+    pub populated: u64,
+}
+
+impl Debug for CopyOnReadMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Copy-on-read map: {range:?} / {seg} blocks \
+            (hits: {hits}, misses: {misses}, populated: {populated})",
+            range = self.range,
+            seg = self.segment_size_blks,
+            hits = self.hits.load(Ordering::Relaxed),
+            misses = self.misses.load(Ordering::Relaxed),
+            populated = self.populated.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Returns the word index and bit mask of a segment in a bitmap.
+#[inline(always)]
+fn bit(seg: u64) -> (usize, u64) {
+    ((seg / 64) as usize, 1 << (seg % 64))
+}
+
+impl CopyOnReadMap {
+    /// Creates a new map with no segment copied.
+    pub(crate) fn new(range: Range<u64>, segment_size_blks: u64) -> Self {
+        let num_segments = (range.end - range.start + segment_size_blks - 1)
+            / segment_size_blks;
+        let words = ((num_segments + 63) / 64) as usize;
+
+        Self {
+            range,
+            segment_size_blks,
+            synced: (0 .. words).map(|_| AtomicU64::new(0)).collect(),
+            requested: (0 .. words).map(|_| AtomicU64::new(0)).collect(),
+            queue: Default::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            populated: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the segments covering the given blocks.
+    fn segments(&self, blk: u64, num_blocks: u64) -> Range<u64> {
+        let start = blk.max(self.range.start) - self.range.start;
+        let end = (blk + num_blocks).min(self.range.end) - self.range.start;
+        start / self.segment_size_blks
+            .. (end + self.segment_size_blks - 1) / self.segment_size_blks
+    }
+
+    fn is_synced(&self, seg: u64) -> bool {
+        let (w, m) = bit(seg);
+        self.synced[w].load(Ordering::Acquire) & m != 0
+    }
+
+    /// Checks if a read of the given blocks can be served by the destination,
+    /// i.e. if all the segments it covers have been copied.
+    pub(crate) fn read_hit(&self, blk: u64, num_blocks: u64) -> bool {
+        let hit = self.segments(blk, num_blocks).all(|s| self.is_synced(s));
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        hit
+    }
+
+    /// Populates the destination with the segments covering the given blocks
+    /// after a read missed it: the segments not copied yet are queued to be
+    /// copied first.
+    pub(crate) fn populate(&self, blk: u64, num_blocks: u64) {
+        let segs = self.segments(blk, num_blocks);
+        for seg in segs.filter(|s| !self.is_synced(*s)) {
+            let (w, m) = bit(seg);
+            if self.requested[w].fetch_or(m, Ordering::AcqRel) & m == 0 {
+                self.queue.lock().push_back(seg);
+            }
+        }
+    }
+
+    /// Checks if the segment starting at the given block has been copied.
+    pub(crate) fn is_blk_synced(&self, blk: u64) -> bool {
+        self.is_synced((blk - self.range.start) / self.segment_size_blks)
+    }
+
+    /// Accounts a segment copied to populate the destination.
+    pub(crate) fn segment_populated(&self) {
+        self.populated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the copy-on-read statistics.
+    pub(crate) fn stats(&self) -> CopyOnReadStats {
+        CopyOnReadStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            populated: self.populated.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Marks the segment starting at the given block as copied.
+    pub(crate) fn blk_synced(&self, blk: u64) {
+        let (w, m) = bit((blk - self.range.start) / self.segment_size_blks);
+        self.synced[w].fetch_or(m, Ordering::Release);
+    }
+
+    /// Returns the first block of the next segment to be copied first, if
+    /// any.
+    pub(crate) fn next_requested(&self) -> Option<u64> {
+        let mut queue = self.queue.lock();
+        while let Some(seg) = queue.pop_front() {
+            // A segment which fails to copy may be requested again.
+            let (w, m) = bit(seg);
+            self.requested[w].fetch_and(!m, Ordering::AcqRel);
+
+            if !self.is_synced(seg) {
+                return Some(self.range.start + seg * self.segment_size_blks);
+            }
+        }
+        None
+    }
+}
@@ -1,17 +1,58 @@
 use chrono::{DateTime, Utc};
 use spdk_rs::{DmaBuf, IoVec, MediaErrorStatusCode, NvmeStatus};
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::{
+    core::{
+        BlockDeviceDescriptor,
+        BlockDeviceHandle,
+        CoreError,
+        DescriptorGuard,
+        IoBandwidthClass,
+        IoCompletionStatus,
+        ReadOptions,
+        VerboseError,
+    },
+    sleep::mayastor_sleep,
+};
 
-use crate::core::{
-    BlockDeviceDescriptor,
-    BlockDeviceHandle,
-    CoreError,
-    DescriptorGuard,
-    IoCompletionStatus,
-    ReadOptions,
+use super::{
+    CopyOnReadMap,
+    RebuildError,
+    RebuildJobOptions,
+    RebuildMap,
+    RebuildVerifyMode,
 };
 
-use super::{RebuildError, RebuildJobOptions, RebuildMap, RebuildVerifyMode};
+/// Healthy child the rebuild segments are read from.
+pub(super) struct RebuildSource {
+    /// URI of the child.
+    pub(super) uri: String,
+    /// Pre-opened descriptor for the child block device.
+    #[allow(clippy::non_send_fields_in_send_ty)]
+    pub(super) descriptor: Box<dyn BlockDeviceDescriptor>,
+    /// Number of bytes read from the child.
+    pub(super) bytes_read: AtomicU64,
+}
+
+impl RebuildSource {
+    pub(super) fn new(
+        uri: &str,
+        descriptor: Box<dyn BlockDeviceDescriptor>,
+    ) -> Self {
+        Self {
+            uri: uri.to_string(),
+            descriptor,
+            bytes_read: AtomicU64::new(0),
+        }
+    }
+}
 
 /// Contains all descriptors and their associated information which allows the
 /// tasks to copy/rebuild data from source to destination.
@@ -30,9 +71,9 @@ pub(super) struct RebuildDescriptor {
     pub(super) src_uri: String,
     /// Target URI of the out of sync child to rebuild.
     pub(super) dst_uri: String,
-    /// Pre-opened descriptor for the source block device.
-    #[allow(clippy::non_send_fields_in_send_ty)]
-    pub(super) src_descriptor: Box<dyn BlockDeviceDescriptor>,
+    /// Children the segments are read from, in turn: the source first, then
+    /// the other healthy children the reads are striped over, if any.
+    pub(super) sources: Vec<RebuildSource>,
     /// Pre-opened descriptor for destination block device.
     #[allow(clippy::non_send_fields_in_send_ty)]
     pub(super) dst_descriptor: Box<dyn BlockDeviceDescriptor>,
@@ -42,6 +83,8 @@ pub(super) struct RebuildDescriptor {
     pub(super) start_time: DateTime<Utc>,
     /// Rebuild map.
     pub(super) rebuild_map: Arc<parking_lot::Mutex<Option<RebuildMap>>>,
+    /// Copy-on-read map of the destination, if enabled.
+    pub(super) cor_map: Option<Arc<CopyOnReadMap>>,
 }
 
 impl RebuildDescriptor {
@@ -55,12 +98,31 @@ impl RebuildDescriptor {
         self.segment_size_blks
     }
 
-    /// Get a `BlockDeviceHandle` for the source.
+    /// Defers a low-priority rebuild read while the client I/O queue depth
+    /// is above the threshold of the rebuild I/O policy.
+    pub(super) async fn yield_to_client_io(&self) {
+        let policy = &self.options.io_policy;
+        let Some(client_qd) = &policy.client_qd else {
+            return;
+        };
+        if policy.client_qd_threshold == 0 {
+            return;
+        }
+
+        let start = Instant::now();
+        while client_qd.load(Ordering::Relaxed) > policy.client_qd_threshold
+            && start.elapsed() < policy.max_defer
+        {
+            mayastor_sleep(policy.defer_interval).await.ok();
+        }
+    }
+
+    /// Returns the child the segment starting from the given logical block is
+    /// read from.
     #[inline(always)]
-    pub(super) async fn src_io_handle(
-        &self,
-    ) -> Result<Box<dyn BlockDeviceHandle>, RebuildError> {
-        Self::io_handle(&*self.src_descriptor).await
+    pub(super) fn segment_source(&self, blk: u64) -> &RebuildSource {
+        let segment = (blk - self.range.start) / self.segment_size_blks;
+        &self.sources[segment as usize % self.sources.len()]
     }
 
     /// Get a `BlockDeviceHandle` for the destination.
@@ -96,6 +158,10 @@ impl RebuildDescriptor {
             .lock()
             .as_ref()
             .map_or(false, |m| m.is_blk_clean(blk))
+            || self
+                .cor_map
+                .as_ref()
+                .map_or(false, |cor| cor.is_blk_synced(blk))
     }
 
     /// Marks the rebuild segment starting from the given logical block as
@@ -105,6 +171,9 @@ impl RebuildDescriptor {
         if let Some(map) = self.rebuild_map.lock().as_mut() {
             map.blk_clean(blk);
         }
+        if let Some(cor) = &self.cor_map {
+            cor.blk_synced(blk);
+        }
     }
 
     /// Returns `IoVec` for the givem `DmaBuf`, with length adjusted to the copy
@@ -123,7 +192,9 @@ impl RebuildDescriptor {
         iov
     }
 
-    /// Reads a rebuild segment at the given offset from the source replica.
+    /// Reads a rebuild segment at the given offset from its source replica.
+    /// If the read from a striped source fails, the segment is read from the
+    /// source of the job instead.
     /// In the case the segment is not allocated on the source, returns false,
     /// and true otherwise.
     pub(super) async fn read_src_segment(
@@ -131,19 +202,48 @@ impl RebuildDescriptor {
         offset_blk: u64,
         iovs: &mut [IoVec],
     ) -> Result<bool, RebuildError> {
-        match self
-            .src_io_handle()
+        let src = self.segment_source(offset_blk);
+        match self.read_segment_from(src, offset_blk, iovs).await {
+            Err(e) if !std::ptr::eq(src, &self.sources[0]) => {
+                warn!(
+                    "Rebuild job '{src}' -> '{dst}': failed to read segment \
+                    {offset_blk} from '{uri}', reading it from '{src}': {e}",
+                    src = self.src_uri,
+                    dst = self.dst_uri,
+                    uri = src.uri,
+                    e = e.verbose()
+                );
+                self.read_segment_from(&self.sources[0], offset_blk, iovs)
+                    .await
+            }
+            r => r,
+        }
+    }
+
+    /// Reads a rebuild segment at the given offset from the given source.
+    async fn read_segment_from(
+        &self,
+        src: &RebuildSource,
+        offset_blk: u64,
+        iovs: &mut [IoVec],
+    ) -> Result<bool, RebuildError> {
+        let len = self.get_segment_size_blks(offset_blk);
+        match Self::io_handle(&*src.descriptor)
             .await?
-            .readv_blocks_async(
-                iovs,
-                offset_blk,
-                self.get_segment_size_blks(offset_blk),
-                ReadOptions::UnwrittenFail,
-            )
+            .readv_blocks_async(iovs, offset_blk, len, self.options.read_opts)
             .await
         {
             // Read is okay, data has to be copied to the destination.
-            Ok(_) => Ok(true),
+            Ok(_) => {
+                src.bytes_read
+                    .fetch_add(len * self.block_size, Ordering::Relaxed);
+                self.record_bandwidth(
+                    &src.uri,
+                    IoBandwidthClass::RebuildRead,
+                    len * self.block_size,
+                );
+                Ok(true)
+            }
 
             // Read from an unallocated block occured, no need to copy it.
             Err(CoreError::ReadFailed {
@@ -161,7 +261,7 @@ impl RebuildDescriptor {
             // Read error.
             Err(err) => Err(RebuildError::ReadIoFailed {
                 source: err,
-                bdev: self.src_uri.clone(),
+                bdev: src.uri.clone(),
             }),
         }
     }
@@ -172,18 +272,29 @@ impl RebuildDescriptor {
         offset_blk: u64,
         iovs: &[IoVec],
     ) -> Result<(), RebuildError> {
+        let len = self.get_segment_size_blks(offset_blk);
         self.dst_io_handle()
             .await?
-            .writev_blocks_async(
-                iovs,
-                offset_blk,
-                self.get_segment_size_blks(offset_blk),
-            )
+            .writev_blocks_async(iovs, offset_blk, len)
             .await
             .map_err(|err| RebuildError::WriteIoFailed {
                 source: err,
                 bdev: self.dst_uri.clone(),
-            })
+            })?;
+
+        self.record_bandwidth(
+            &self.dst_uri,
+            IoBandwidthClass::RebuildWrite,
+            len * self.block_size,
+        );
+        Ok(())
+    }
+
+    /// Counts bytes transferred by the rebuild to or from the given child.
+    fn record_bandwidth(&self, uri: &str, class: IoBandwidthClass, bytes: u64) {
+        if let Some(bandwidth) = self.options.bandwidth.get(uri) {
+            bandwidth.record(class, bytes);
+        }
     }
 
     /// Verify segment copy operation by reading destination, and comparing with
@@ -194,7 +305,7 @@ impl RebuildDescriptor {
         iovs: &mut [IoVec],
     ) -> Result<(), RebuildError> {
         // Read the source again.
-        self.src_io_handle()
+        Self::io_handle(&*self.segment_source(offset_blk).descriptor)
             .await?
             .readv_blocks_async(
                 iovs,
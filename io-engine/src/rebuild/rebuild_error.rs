@@ -1,6 +1,9 @@
 use snafu::Snafu;
 
-use crate::{bdev_api::BdevError, core::CoreError};
+use crate::{
+    bdev_api::BdevError,
+    core::{CoreError, IoCompletionStatus, LvolFailure},
+};
 use spdk_rs::{BdevDescError, DmaError};
 
 #[derive(Debug, Snafu, Clone)]
@@ -15,6 +18,14 @@ pub enum RebuildError {
     NoCopyBuffer { source: DmaError },
     #[snafu(display("Failed to validate rebuild job creation parameters"))]
     InvalidParameters {},
+    #[snafu(display(
+        "Invalid rebuild segment size {} for block size {}",
+        segment_size,
+        block_size
+    ))]
+    InvalidSegmentSize { segment_size: u64, block_size: u64 },
+    #[snafu(display("Invalid number of rebuild tasks {}", tasks))]
+    InvalidSegmentTasks { tasks: usize },
     #[snafu(display("Failed to get a handle for bdev {}", bdev))]
     NoBdevHandle { source: CoreError, bdev: String },
     #[snafu(display("Bdev {} not found", bdev))]
@@ -79,3 +90,19 @@ pub enum RebuildError {
     #[snafu(display("The rebuild task pool channel is unexpectedly closed with {} active tasks", active))]
     RebuildTasksChannel { active: usize },
 }
+
+impl RebuildError {
+    /// Checks if the error is caused by the destination running out of space.
+    pub fn is_no_space(&self) -> bool {
+        matches!(
+            self,
+            Self::WriteIoFailed {
+                source: CoreError::WriteFailed {
+                    status: IoCompletionStatus::LvolError(LvolFailure::NoSpace),
+                    ..
+                },
+                ..
+            }
+        )
+    }
+}
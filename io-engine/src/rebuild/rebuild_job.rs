@@ -1,7 +1,8 @@
 use std::{
     collections::HashMap,
     ops::Range,
-    sync::{Arc, Weak},
+    sync::{atomic::AtomicUsize, Arc, Weak},
+    time::Duration,
 };
 
 use chrono::Utc;
@@ -10,16 +11,23 @@ use once_cell::sync::OnceCell;
 use spdk_rs::Thread;
 
 use super::{
+    CopyOnReadMap,
     HistoryRecord,
     RebuildError,
     RebuildJobBackend,
     RebuildJobRequest,
     RebuildMap,
+    RebuildPriority,
+    RebuildScheduler,
     RebuildState,
     RebuildStates,
     RebuildStats,
+    MAX_SEGMENT_SIZE,
+    MAX_SEGMENT_TASKS,
+    SEGMENT_SIZE,
+    SEGMENT_TASKS,
 };
-use crate::core::{Reactors, VerboseError};
+use crate::core::{IoBandwidth, Reactors, ReadOptions, VerboseError};
 
 /// Rebuild I/O verification mode.
 #[derive(Debug, Clone)]
@@ -32,10 +40,101 @@ pub enum RebuildVerifyMode {
     Panic,
 }
 
+/// Rebuild I/O prioritization policy.
+/// Rebuild reads from the source child are low-priority: they are deferred
+/// while the client I/O queue depth is above the threshold, for at most
+/// `max_defer`, so that the rebuild still makes progress under load.
+#[derive(Debug, Clone)]
+pub struct RebuildIoPolicy {
+    /// Client I/O queue depth above which rebuild reads are deferred.
+    /// Zero disables deferral.
+    pub client_qd_threshold: usize,
+    /// Interval between client I/O queue depth checks while deferred.
+    pub defer_interval: Duration,
+    /// Maximum time a single rebuild read can be deferred.
+    pub max_defer: Duration,
+    /// Client I/O queue depth counter of the device being rebuilt.
+    pub client_qd: Option<Arc<AtomicUsize>>,
+}
+
+impl Default for RebuildIoPolicy {
+    fn default() -> Self {
+        Self {
+            client_qd_threshold: 0,
+            defer_interval: Duration::from_millis(1),
+            max_defer: Duration::from_millis(100),
+            client_qd: None,
+        }
+    }
+}
+
 /// Rebuild job options.
 #[derive(Debug, Clone)]
 pub struct RebuildJobOptions {
     pub verify_mode: RebuildVerifyMode,
+    /// Priority of the job, when queued by the rebuild scheduler.
+    pub priority: RebuildPriority,
+    /// Number of healthy children of the nexus, the jobs of the nexuses with
+    /// the fewest healthy children being started first by the rebuild
+    /// scheduler. Updated by the nexus while the job is queued.
+    pub healthy_children: usize,
+    /// Prioritization of rebuild I/O against client I/O.
+    pub io_policy: RebuildIoPolicy,
+    /// Options for reading segments from the source. Segments which are
+    /// reported as unwritten by the source are not transferred.
+    pub read_opts: ReadOptions,
+    /// Track the segments copied so far, so that reads can be served by the
+    /// destination while it is being rebuilt.
+    pub copy_on_read: bool,
+    /// Other healthy children the segments are read from, in turn with the
+    /// source, to spread the rebuild reads.
+    pub striped_sources: Vec<String>,
+    /// Size of each segment copied, in bytes. Zero selects the node default.
+    pub segment_size: u64,
+    /// Number of concurrent copy tasks. Zero selects the node default.
+    pub segment_tasks: usize,
+    /// Bandwidth counters of the children, by URI, which the bytes read and
+    /// written by the rebuild are counted against.
+    pub bandwidth: HashMap<String, Arc<IoBandwidth>>,
+}
+
+impl RebuildJobOptions {
+    /// Resolves the segment size, in bytes, and the number of concurrent copy
+    /// tasks, zero selecting the node defaults, and checks them against the
+    /// block size of the devices and the rebuild limits.
+    pub fn resolve_segments(
+        segment_size: u64,
+        segment_tasks: usize,
+        block_size: u64,
+    ) -> Result<(u64, usize), RebuildError> {
+        let config = RebuildScheduler::config();
+
+        let segment_size = match (segment_size, config.segment_size) {
+            (0, 0) => SEGMENT_SIZE,
+            (0, size) | (size, _) => size,
+        };
+        if segment_size < block_size
+            || segment_size % block_size != 0
+            || segment_size > MAX_SEGMENT_SIZE
+        {
+            return Err(RebuildError::InvalidSegmentSize {
+                segment_size,
+                block_size,
+            });
+        }
+
+        let segment_tasks = match (segment_tasks, config.segment_tasks) {
+            (0, 0) => SEGMENT_TASKS,
+            (0, tasks) | (tasks, _) => tasks,
+        };
+        if segment_tasks > MAX_SEGMENT_TASKS {
+            return Err(RebuildError::InvalidSegmentTasks {
+                tasks: segment_tasks,
+            });
+        }
+
+        Ok((segment_size, segment_tasks))
+    }
 }
 
 /// Operations used to control the state of the job.
@@ -57,6 +156,8 @@ pub(super) enum RebuildOperation {
     Fail,
     /// rebuild completed successfully
     Complete,
+    /// destination ran out of space
+    NoSpace,
 }
 
 impl std::fmt::Display for RebuildOperation {
@@ -75,6 +176,8 @@ pub struct RebuildJob {
     pub nexus_name: String,
     /// Source URI of the healthy child to rebuild from.
     src_uri: String,
+    /// URIs of the other healthy children segments are read from.
+    striped_src_uris: Vec<String>,
     /// Target URI of the out of sync child in need of a rebuild.
     pub(crate) dst_uri: String,
     /// Frontend to backend channel.
@@ -85,6 +188,8 @@ pub struct RebuildJob {
     notify_chan: crossbeam::channel::Receiver<RebuildState>,
     /// Channel used to Notify when rebuild completes.
     complete_chan: Weak<parking_lot::Mutex<Vec<oneshot::Sender<RebuildState>>>>,
+    /// Copy-on-read map of the destination, if enabled.
+    cor_map: Option<Arc<CopyOnReadMap>>,
 }
 
 impl RebuildJob {
@@ -114,11 +219,16 @@ impl RebuildJob {
         let frontend = Self {
             nexus_name: backend.nexus_name.clone(),
             src_uri: backend.src_uri.clone(),
+            striped_src_uris: backend.descriptor.sources[1 ..]
+                .iter()
+                .map(|s| s.uri.clone())
+                .collect(),
             dst_uri: backend.dst_uri.clone(),
             states: backend.states.clone(),
             comms: RebuildFBendChan::from(&backend.info_chan),
             complete_chan: Arc::downgrade(&backend.complete_chan),
             notify_chan: backend.notify_chan.1.clone(),
+            cor_map: backend.descriptor.cor_map.clone(),
         };
 
         // Kick off the rebuild task where it will "live" and await for
@@ -128,6 +238,11 @@ impl RebuildJob {
         Ok(frontend)
     }
 
+    /// Returns the copy-on-read map of the destination, if enabled.
+    pub(crate) fn cor_map(&self) -> Option<Arc<CopyOnReadMap>> {
+        self.cor_map.clone()
+    }
+
     /// Returns number of all rebuild jobs on the system.
     pub fn count() -> usize {
         Self::get_instances().len()
@@ -168,12 +283,15 @@ impl RebuildJob {
         }
     }
 
-    /// Lookup all rebuilds jobs with name as its source.
+    /// Lookup all rebuilds jobs with name as its source, including the jobs
+    /// striping their reads over it.
     pub fn lookup_src(src_uri: &str) -> Vec<Arc<Self>> {
         Self::get_instances()
             .iter_mut()
             .filter_map(|j| {
-                if j.1.src_uri == src_uri {
+                if j.1.src_uri == src_uri
+                    || j.1.striped_src_uris.iter().any(|u| u == src_uri)
+                {
                     Some(j.1.clone())
                 } else {
                     None
@@ -275,7 +393,7 @@ impl RebuildJob {
         }
     }
 
-    /// TODO
+    /// Makes a history record out of a finished rebuild job.
     pub(crate) fn history_record(&self) -> Option<HistoryRecord> {
         self.final_stats().map(|final_stats| HistoryRecord {
             child_uri: self.dst_uri.to_string(),
@@ -283,6 +401,7 @@ impl RebuildJob {
             final_stats,
             state: self.state(),
             end_time: Utc::now(),
+            error: self.error().map(|e| e.verbose()),
         })
     }
 
@@ -314,6 +433,11 @@ impl RebuildJob {
         &self.src_uri
     }
 
+    /// Get the uris of the other children the reads are striped over.
+    pub fn striped_src_uris(&self) -> &[String] {
+        &self.striped_src_uris
+    }
+
     /// Get the uri of the rebuild destination.
     pub fn dst_uri(&self) -> &str {
         &self.dst_uri
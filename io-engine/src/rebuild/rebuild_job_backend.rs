@@ -5,6 +5,7 @@ use std::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
 use chrono::Utc;
@@ -15,28 +16,44 @@ use futures::{
     StreamExt,
 };
 use snafu::ResultExt;
+use spdk_rs::{DmaBuf, DmaError};
 
 use super::{
     rebuild_error::{BdevInvalidUri, BdevNotFound, NoCopyBuffer},
+    CopyOnReadMap,
     RebuildDescriptor,
     RebuildError,
     RebuildJobOptions,
     RebuildMap,
+    RebuildScheduler,
+    RebuildSlot,
+    RebuildSlotRequest,
+    RebuildSource,
+    RebuildSourceStats,
     RebuildState,
     RebuildStates,
     RebuildStats,
     RebuildTask,
     RebuildTasks,
+    RebuildThroughput,
     TaskResult,
     Within,
-    SEGMENT_SIZE,
-    SEGMENT_TASKS,
+    NO_SPACE_RETRY_INTERVAL,
 };
 
 use crate::{
     bdev::device_open,
     bdev_api::bdev_get_name,
-    core::{BlockDevice, Reactors, UntypedBdev},
+    core::{
+        BlockDevice,
+        BlockDeviceDescriptor,
+        Cores,
+        Numa,
+        Reactors,
+        UntypedBdev,
+    },
+    lvs::PoolRebuildGuard,
+    sleep::mayastor_sleep,
 };
 
 /// Request between frontend and backend.
@@ -113,6 +130,15 @@ pub(super) struct RebuildJobBackend {
     pub(super) descriptor: Rc<RebuildDescriptor>,
     /// Job serial number.
     serial: u64,
+    /// Rebuild scheduler slot, held while the job is running.
+    slot: Option<RebuildSlot>,
+    /// Regulation of the writes to the pool of the destination, while the
+    /// job is running.
+    pool_guard: Option<PoolRebuildGuard>,
+    /// Segments to be copied again, after the destination ran out of space.
+    retry_blks: Vec<u64>,
+    /// Number of times the destination ran out of space.
+    no_space_count: u64,
 }
 
 impl std::fmt::Debug for RebuildJobBackend {
@@ -155,16 +181,7 @@ impl RebuildJobBackend {
         options: RebuildJobOptions,
         notify_fn: fn(String, String) -> (),
     ) -> Result<Self, RebuildError> {
-        let src_descriptor = device_open(
-            &bdev_get_name(src_uri).context(BdevInvalidUri {
-                uri: src_uri.to_string(),
-            })?,
-            false,
-        )
-        .map_err(|e| RebuildError::BdevNotFound {
-            source: e,
-            bdev: src_uri.to_string(),
-        })?;
+        let src_descriptor = Self::open_source(src_uri)?;
 
         let dst_descriptor = device_open(
             &bdev_get_name(dst_uri).context(BdevInvalidUri {
@@ -189,9 +206,33 @@ impl RebuildJobBackend {
             return Err(RebuildError::InvalidParameters {});
         };
 
+        let mut sources = vec![RebuildSource::new(src_uri, src_descriptor)];
+        for uri in &options.striped_sources {
+            let descriptor = Self::open_source(uri)?;
+            let hdl = RebuildDescriptor::io_handle(&*descriptor).await?;
+            if !Self::validate(
+                hdl.get_device(),
+                destination_hdl.get_device(),
+                &range,
+            ) {
+                return Err(RebuildError::InvalidParameters {});
+            }
+            sources.push(RebuildSource::new(uri, descriptor));
+        }
+
         // validation passed, block size is the same for both
         let block_size = destination_hdl.get_device().block_len();
-        let segment_size_blks = SEGMENT_SIZE / block_size;
+        let (segment_size, segment_tasks) =
+            RebuildJobOptions::resolve_segments(
+                options.segment_size,
+                options.segment_tasks,
+                block_size,
+            )?;
+        let segment_size_blks = segment_size / block_size;
+
+        let cor_map = options.copy_on_read.then(|| {
+            Arc::new(CopyOnReadMap::new(range.clone(), segment_size_blks))
+        });
 
         let mut tasks = RebuildTasks {
             tasks: Default::default(),
@@ -199,16 +240,22 @@ impl RebuildJobBackend {
             // the extra buffer
             channel: mpsc::channel(0),
             active: 0,
-            total: SEGMENT_TASKS,
+            total: segment_tasks,
             segments_done: 0,
             segments_transferred: 0,
+            throughput: RebuildThroughput::new(),
         };
 
-        for _ in 0 .. tasks.total {
-            let buffer = destination_hdl
-                .dma_malloc(segment_size_blks * block_size)
-                .context(NoCopyBuffer {})?;
+        let buffers = Self::alloc_buffers(
+            dst_uri,
+            tasks.total,
+            segment_size_blks * block_size,
+            destination_hdl.get_device().alignment(),
+        )
+        .await
+        .context(NoCopyBuffer {})?;
 
+        for buffer in buffers {
             tasks.push(RebuildTask::new(buffer, tasks.channel.0.clone()));
         }
 
@@ -240,20 +287,77 @@ impl RebuildJobBackend {
                 options,
                 block_size,
                 segment_size_blks,
-                src_descriptor,
+                sources,
                 dst_descriptor,
                 nexus_descriptor,
                 start_time: Utc::now(),
                 rebuild_map: Arc::new(parking_lot::Mutex::new(None)),
+                cor_map,
             }),
             serial,
+            slot: None,
+            pool_guard: None,
+            retry_blks: Vec::new(),
+            no_space_count: 0,
         };
 
         info!("{be}: backend created");
+        if be.descriptor.sources.len() > 1 {
+            info!(
+                "{be}: reads striped over {n} sources",
+                n = be.descriptor.sources.len()
+            );
+        }
 
         Ok(be)
     }
 
+    /// Opens the block device of a source child for reading.
+    fn open_source(
+        uri: &str,
+    ) -> Result<Box<dyn BlockDeviceDescriptor>, RebuildError> {
+        device_open(
+            &bdev_get_name(uri).context(BdevInvalidUri {
+                uri: uri.to_string(),
+            })?,
+            false,
+        )
+        .map_err(|e| RebuildError::BdevNotFound {
+            source: e,
+            bdev: uri.to_string(),
+        })
+    }
+
+    /// Allocates the copy buffers of the tasks. With NUMA placement enabled,
+    /// they are allocated by a reactor of the NUMA node of the destination,
+    /// so that they come from the memory of its socket.
+    async fn alloc_buffers(
+        dst_uri: &str,
+        count: usize,
+        size: u64,
+        alignment: u64,
+    ) -> Result<Vec<DmaBuf>, DmaError> {
+        let alloc = move || {
+            (0 .. count)
+                .map(|_| DmaBuf::new(size, alignment))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let reactor = Numa::device_node(dst_uri)
+            .and_then(Numa::local_core)
+            .filter(|core| *core != Cores::current())
+            .and_then(Reactors::get_by_core);
+        let Some(reactor) = reactor else {
+            return alloc();
+        };
+
+        let (s, r) = oneshot::channel();
+        reactor.send_future(async move {
+            s.send(alloc()).ok();
+        });
+        r.await.unwrap_or_else(|_| alloc())
+    }
+
     /// State of the rebuild job
     fn state(&self) -> RebuildState {
         self.states.read().current
@@ -281,7 +385,12 @@ impl RebuildJobBackend {
             if g.is_some() {
                 error!("{self}: rebuild map is already set");
             } else {
-                *g = Some(map);
+                // The map of the I/O log may have been recorded with another
+                // segment size.
+                *g = Some(map.with_segment_size(
+                    self.descriptor.segment_size_blks
+                        * self.descriptor.block_size,
+                ));
                 debug!("{self}: set rebuild map");
             }
         }
@@ -301,12 +410,22 @@ impl RebuildJobBackend {
     async fn run(&mut self) {
         while !self.reconcile().done() {
             if !self.state().running() {
-                match self.info_chan.recv().await {
-                    Ok(RebuildJobRequest::WakeUp) => {}
-                    Ok(RebuildJobRequest::GetStats(reply)) => {
+                // Let other jobs run while this one is not running.
+                self.slot = None;
+                self.pool_guard = None;
+
+                let request = if self.state().no_space() {
+                    self.recv_or_retry_no_space().await
+                } else {
+                    self.info_chan.recv().await.map(Some)
+                };
+
+                match request {
+                    Ok(None) | Ok(Some(RebuildJobRequest::WakeUp)) => {}
+                    Ok(Some(RebuildJobRequest::GetStats(reply))) => {
                         self.reply_stats(reply).await.ok();
                     }
-                    Ok(RebuildJobRequest::SetRebuildMap((map, s))) => {
+                    Ok(Some(RebuildJobRequest::SetRebuildMap((map, s)))) => {
                         self.set_rebuild_map(map, s).await.ok();
                     }
                     Err(error) => {
@@ -316,6 +435,13 @@ impl RebuildJobBackend {
                 continue;
             }
 
+            if self.slot.is_none() && !self.wait_for_slot().await {
+                continue;
+            }
+            if self.pool_guard.is_none() {
+                self.pool_guard = PoolRebuildGuard::new(&self.dst_uri);
+            }
+
             self.start_all_tasks();
 
             let mut recv = self.info_chan.recv_clone();
@@ -343,6 +469,81 @@ impl RebuildJobBackend {
         }
     }
 
+    /// Waits for a request from the frontend while the destination has no
+    /// space. If none arrives within the retry interval, tries to resume the
+    /// job: if the space is still exhausted, the job goes back to the
+    /// `NoSpace` state on the first failed write.
+    async fn recv_or_retry_no_space(
+        &mut self,
+    ) -> Result<Option<RebuildJobRequest>, RebuildError> {
+        let mut recv = self.info_chan.recv_clone();
+        let mut retry = mayastor_sleep(NO_SPACE_RETRY_INTERVAL).fuse();
+
+        futures::select! {
+            message = recv.next() => match message {
+                Some(m) => Ok(Some(m)),
+                None => Err(RebuildError::FrontendGone),
+            },
+            _ = retry => {
+                info!("{self}: trying to resume rebuild after running out \
+                    of space");
+                self.exec_internal_op(super::RebuildOperation::Resume).ok();
+                Ok(None)
+            },
+        }
+    }
+
+    /// Waits for a rebuild scheduler slot, while still serving requests from
+    /// the frontend. Returns false if the job state changed while waiting.
+    async fn wait_for_slot(&mut self) -> bool {
+        if !RebuildScheduler::has_free_slot(&self.nexus_name) {
+            info!(
+                "{self}: queued by the rebuild scheduler; {n} job(s) waiting",
+                n = RebuildScheduler::queued() + 1
+            );
+        }
+
+        let acquire = RebuildScheduler::acquire(RebuildSlotRequest {
+            nexus: self.nexus_name.clone(),
+            src_uri: self.descriptor.src_uri.clone(),
+            dst_uri: self.descriptor.dst_uri.clone(),
+            priority: self.descriptor.options.priority,
+            healthy_children: self.descriptor.options.healthy_children,
+        })
+        .fuse();
+        futures::pin_mut!(acquire);
+
+        let mut recv = self.info_chan.recv_clone();
+        loop {
+            futures::select! {
+                slot = acquire => {
+                    debug!("{self}: acquired rebuild scheduler slot");
+                    self.slot = Some(slot);
+                    return true;
+                },
+                message = recv.next() => {
+                    match message {
+                        Some(RebuildJobRequest::WakeUp) => {}
+                        Some(RebuildJobRequest::GetStats(reply)) => {
+                            self.reply_stats(reply).await.ok();
+                        }
+                        Some(RebuildJobRequest::SetRebuildMap((map, s))) => {
+                            self.set_rebuild_map(map, s).await.ok();
+                        }
+                        None => {
+                            self.fail_with(RebuildError::FrontendGone);
+                            return false;
+                        }
+                    }
+
+                    if self.states.read().pending.is_some() {
+                        return false;
+                    }
+                },
+            }
+        }
+    }
+
     /// Runs the management async task that kicks off N rebuild copy tasks and
     /// awaits each completion. When any task completes it kicks off another
     /// until the destination is fully rebuilt.
@@ -351,6 +552,11 @@ impl RebuildJobBackend {
             match self.await_one_task().await {
                 Some(r) => match r.error {
                     None => {
+                        if r.out_of_order && r.is_transferred {
+                            if let Some(cor) = &self.descriptor.cor_map {
+                                cor.segment_populated();
+                            }
+                        }
                         let state = self.states.read().clone();
                         match state.pending {
                             None | Some(RebuildState::Running) => {
@@ -364,6 +570,20 @@ impl RebuildJobBackend {
                             }
                         }
                     }
+                    Some(e) if e.is_no_space() => {
+                        warn!(
+                            "{self}: destination ran out of space while \
+                            rebuilding segment id={sid} block={blk}, \
+                            pausing rebuild",
+                            sid = r.id,
+                            blk = r.blk
+                        );
+                        if !r.out_of_order {
+                            self.retry_blks.push(r.blk);
+                        }
+                        self.no_space_with(e).await;
+                        break;
+                    }
                     Some(e) => {
                         error!(
                             "{self}: failed to rebuild segment \
@@ -420,6 +640,11 @@ impl RebuildJobBackend {
             (old, new)
         };
 
+        if old.no_space() && new.running() {
+            // The destination has space again.
+            self.states.write().error = None;
+        }
+
         if old != new {
             // Log the statistics and send a notification to the listeners.
             let s = self.stats();
@@ -462,6 +687,16 @@ impl RebuildJobBackend {
         let progress = (blocks_recovered * 100) / blocks_total;
         assert!(progress < 100 || blocks_remaining == 0);
 
+        let throughput = self.task_pool.throughput.current();
+        let eta = match (blocks_remaining, throughput) {
+            (0, _) => Some(Duration::ZERO),
+            (_, 0) => None,
+            (blocks, throughput) => Some(Duration::from_secs_f64(
+                (blocks * self.descriptor.block_size) as f64
+                    / throughput as f64,
+            )),
+        };
+
         RebuildStats {
             start_time: self.descriptor.start_time,
             is_partial: self.descriptor.rebuild_map.lock().is_some(),
@@ -474,6 +709,21 @@ impl RebuildJobBackend {
             block_size: self.descriptor.block_size,
             tasks_total: self.task_pool.total as u64,
             tasks_active: self.task_pool.active as u64,
+            is_no_space: self.state().no_space(),
+            no_space_count: self.no_space_count,
+            sources: self
+                .descriptor
+                .sources
+                .iter()
+                .map(|s| RebuildSourceStats {
+                    uri: s.uri.clone(),
+                    bytes_read: s.bytes_read.load(Ordering::Relaxed),
+                })
+                .collect(),
+            throughput,
+            avg_throughput: self.task_pool.throughput.average(),
+            eta,
+            copy_on_read: self.descriptor.cor_map.as_ref().map(|c| c.stats()),
         }
     }
 
@@ -488,6 +738,46 @@ impl RebuildJobBackend {
         self.states.write().error = error.into();
     }
 
+    /// Pauses the job because the destination ran out of space, and awaits
+    /// all active tasks, collecting the segments which have to be copied
+    /// again once the job resumes.
+    async fn no_space_with(&mut self, error: RebuildError) {
+        self.no_space_count += 1;
+        self.exec_internal_op(super::RebuildOperation::NoSpace).ok();
+        self.states.write().error = Some(error);
+
+        while self.task_pool.active > 0 {
+            match self.await_one_task().await {
+                Some(TaskResult {
+                    error: Some(e),
+                    blk,
+                    out_of_order,
+                    ..
+                }) => {
+                    if e.is_no_space() {
+                        // Segments copied out of order are copied again
+                        // when the sequential order reaches them.
+                        if out_of_order {
+                            continue;
+                        }
+                        self.retry_blks.push(blk);
+                    } else {
+                        error!(
+                            "{self}: failed to rebuild segment \
+                            block={blk} with error: {e}"
+                        );
+                        self.fail_with(e);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    self.task_sync_fail();
+                    return;
+                }
+            }
+        }
+    }
+
     fn task_sync_fail(&mut self) {
         let active = self.task_pool.active;
         error!(
@@ -583,6 +873,30 @@ impl RebuildJobBackend {
     /// Sends one segment worth of data in a reactor future and notifies the
     /// management channel. Returns the next segment offset to rebuild, if any.
     fn send_segment_task(&mut self, id: usize) -> Option<u64> {
+        // Segments which failed due to lack of space go first.
+        if let Some(blk) = self.retry_blks.pop() {
+            self.task_pool.send_segment(
+                id,
+                blk,
+                self.descriptor.clone(),
+                false,
+            );
+            return Some(self.next);
+        }
+
+        // Then the segments read from the other children, so that further
+        // reads can be served by the destination (copy-on-read).
+        if let Some(blk) = self
+            .descriptor
+            .cor_map
+            .as_ref()
+            .and_then(|cor| cor.next_requested())
+        {
+            self.task_pool
+                .send_segment(id, blk, self.descriptor.clone(), true);
+            return Some(self.next);
+        }
+
         if self.next >= self.descriptor.range.end {
             None
         } else {
@@ -592,8 +906,12 @@ impl RebuildJobBackend {
                 self.descriptor.range.end,
             );
 
-            self.task_pool
-                .send_segment(id, blk, self.descriptor.clone());
+            self.task_pool.send_segment(
+                id,
+                blk,
+                self.descriptor.clone(),
+                false,
+            );
 
             Some(next)
         }
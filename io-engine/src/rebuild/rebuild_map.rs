@@ -30,6 +30,14 @@ impl RebuildMap {
         }
     }
 
+    /// Converts the map to the given segment size, in bytes.
+    pub(crate) fn with_segment_size(self, segment_size: u64) -> Self {
+        Self {
+            segments: self.segments.with_segment_size(segment_size),
+            ..self
+        }
+    }
+
     /// Determines if the given logical block is clean (no need to transfer).
     ///
     /// # Arguments
@@ -0,0 +1,386 @@
+//! Node-wide rebuild scheduling policy.
+//!
+//! Limits the number of rebuild jobs which are allowed to copy data at the
+//! same time, on the node and per nexus, and the total rebuild bandwidth of
+//! the node. Jobs which exceed the concurrency limits are queued and started
+//! in the order of their priority, then of the number of healthy children of
+//! their nexus, so that the nexuses most at risk are rebuilt first, and then
+//! in the order they were queued. The number of healthy children of the
+//! queued jobs is kept up to date by their nexus as its children fail or get
+//! rebuilt.
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures::channel::oneshot;
+use once_cell::sync::Lazy;
+
+/// Priority of a rebuild job, used to order queued jobs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RebuildPriority {
+    /// Started after all other queued jobs.
+    Low,
+    /// Default priority.
+    #[default]
+    Normal,
+    /// Started before all other queued jobs.
+    High,
+}
+
+/// Default client I/O queue depth above which rebuild reads are deferred.
+pub const REBUILD_CLIENT_QD_THRESHOLD: usize = 32;
+
+/// Node-wide rebuild limits and defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RebuildSchedulerConfig {
+    /// Maximum number of rebuild jobs copying data at the same time.
+    /// Zero means no limit.
+    pub max_concurrent: usize,
+    /// Maximum number of rebuild jobs of a nexus copying data at the same
+    /// time. Zero means no limit.
+    pub max_per_nexus: usize,
+    /// Maximum total rebuild bandwidth in bytes per second.
+    /// Zero means no limit.
+    pub max_bandwidth: u64,
+    /// Default size of the segments copied by a rebuild job, in bytes.
+    /// Zero means the built-in default.
+    pub segment_size: u64,
+    /// Default number of concurrent copy tasks of a rebuild job.
+    /// Zero means the built-in default.
+    pub segment_tasks: usize,
+    /// Client I/O queue depth of a nexus above which the reads of its
+    /// rebuilds are deferred. Zero disables deferral.
+    pub client_qd_threshold: usize,
+}
+
+impl Default for RebuildSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 0,
+            max_per_nexus: 0,
+            max_bandwidth: 0,
+            segment_size: 0,
+            segment_tasks: 0,
+            client_qd_threshold: REBUILD_CLIENT_QD_THRESHOLD,
+        }
+    }
+}
+
+/// Rebuild job asking for a slot.
+#[derive(Debug, Clone)]
+pub(super) struct RebuildSlotRequest {
+    /// Name of the nexus of the job.
+    pub nexus: String,
+    /// URI of the child rebuilt from.
+    pub src_uri: String,
+    /// URI of the child being rebuilt.
+    pub dst_uri: String,
+    /// Priority of the job.
+    pub priority: RebuildPriority,
+    /// Number of healthy children of the nexus.
+    pub healthy_children: usize,
+}
+
+/// Rebuild job waiting for a slot.
+#[derive(Debug, Clone)]
+pub struct PendingRebuild {
+    /// Name of the nexus of the job.
+    pub nexus: String,
+    /// URI of the child rebuilt from.
+    pub src_uri: String,
+    /// URI of the child being rebuilt.
+    pub dst_uri: String,
+    /// Priority of the job.
+    pub priority: RebuildPriority,
+    /// Number of healthy children of the nexus.
+    pub healthy_children: usize,
+    /// Time the job was queued.
+    pub queued_at: SystemTime,
+    /// Whether the job waits for the per-nexus limit, rather than for the
+    /// node-wide limit.
+    pub nexus_limited: bool,
+}
+
+/// A rebuild job waiting for a free slot.
+struct Waiter {
+    request: RebuildSlotRequest,
+    serial: u64,
+    queued_at: SystemTime,
+    sender: oneshot::Sender<()>,
+}
+
+impl Waiter {
+    /// Key the waiters are started in the order of, highest first.
+    fn order(&self) -> (RebuildPriority, Reverse<usize>, Reverse<u64>) {
+        (
+            self.request.priority,
+            Reverse(self.request.healthy_children),
+            Reverse(self.serial),
+        )
+    }
+}
+
+/// Scheduler state.
+struct SchedulerInner {
+    config: RebuildSchedulerConfig,
+    /// Number of jobs holding a slot.
+    active: usize,
+    /// Number of jobs holding a slot, per nexus.
+    active_per_nexus: HashMap<String, usize>,
+    /// Jobs waiting for a slot.
+    queue: Vec<Waiter>,
+    /// Serial number of the next queued job.
+    serial: u64,
+    /// Point in time from which the bandwidth budget is available again.
+    next_free: Instant,
+}
+
+impl SchedulerInner {
+    /// Checks if another job can be started right now.
+    fn has_free_slot(&self) -> bool {
+        self.config.max_concurrent == 0
+            || self.active < self.config.max_concurrent
+    }
+
+    /// Checks if another job of the given nexus can be started, as far as
+    /// the per-nexus limit is concerned.
+    fn has_free_nexus_slot(&self, nexus: &str) -> bool {
+        self.config.max_per_nexus == 0
+            || self
+                .active_per_nexus
+                .get(nexus)
+                .copied()
+                .unwrap_or_default()
+                < self.config.max_per_nexus
+    }
+
+    /// Takes a slot for a job of the given nexus.
+    fn take_slot(&mut self, nexus: &str) {
+        self.active += 1;
+        *self.active_per_nexus.entry(nexus.to_string()).or_default() += 1;
+    }
+
+    /// Releases a slot of a job of the given nexus.
+    fn release_slot(&mut self, nexus: &str) {
+        self.active -= 1;
+        if let Some(n) = self.active_per_nexus.get_mut(nexus) {
+            *n -= 1;
+            if *n == 0 {
+                self.active_per_nexus.remove(nexus);
+            }
+        }
+    }
+
+    /// Hands over free slots to the queued jobs, in the order of priority
+    /// and of degradation of their nexus, skipping the jobs of the nexuses
+    /// at their limit.
+    fn dispatch(&mut self) {
+        while self.has_free_slot() {
+            let Some(idx) = self
+                .queue
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| self.has_free_nexus_slot(&w.request.nexus))
+                .max_by(|(_, a), (_, b)| a.order().cmp(&b.order()))
+                .map(|(idx, _)| idx)
+            else {
+                break;
+            };
+
+            // The waiter may have been dropped, e.g. if the job was stopped
+            // while being queued.
+            let waiter = self.queue.remove(idx);
+            if waiter.sender.send(()).is_ok() {
+                self.take_slot(&waiter.request.nexus);
+            }
+        }
+    }
+}
+
+static SCHEDULER: Lazy<parking_lot::Mutex<SchedulerInner>> = Lazy::new(|| {
+    parking_lot::Mutex::new(SchedulerInner {
+        config: Default::default(),
+        active: 0,
+        active_per_nexus: HashMap::new(),
+        queue: Vec::new(),
+        serial: 0,
+        next_free: Instant::now(),
+    })
+});
+
+/// Slot held by a running rebuild job. Dropping the slot lets the next queued
+/// job run.
+#[derive(Debug)]
+pub(super) struct RebuildSlot {
+    nexus: String,
+}
+
+impl Drop for RebuildSlot {
+    fn drop(&mut self) {
+        let mut inner = SCHEDULER.lock();
+        inner.release_slot(&self.nexus);
+        inner.dispatch();
+    }
+}
+
+/// Pending slot request. If dropped after a slot has been granted but before
+/// it was received, the slot is released.
+struct PendingSlot {
+    receiver: oneshot::Receiver<()>,
+    nexus: String,
+}
+
+impl Drop for PendingSlot {
+    fn drop(&mut self) {
+        self.receiver.close();
+        if let Ok(Some(())) = self.receiver.try_recv() {
+            drop(RebuildSlot {
+                nexus: std::mem::take(&mut self.nexus),
+            });
+        }
+    }
+}
+
+/// Node-wide rebuild scheduler.
+pub struct RebuildScheduler {}
+
+impl RebuildScheduler {
+    /// Sets the node-wide rebuild limits and defaults.
+    pub fn configure(config: RebuildSchedulerConfig) {
+        info!("Rebuild scheduler configuration: {config:?}");
+        let mut inner = SCHEDULER.lock();
+        inner.config = config;
+        inner.dispatch();
+    }
+
+    /// Returns the node-wide rebuild limits and defaults.
+    pub fn config() -> RebuildSchedulerConfig {
+        SCHEDULER.lock().config
+    }
+
+    /// Returns the number of rebuild jobs waiting for a slot.
+    pub fn queued() -> usize {
+        SCHEDULER
+            .lock()
+            .queue
+            .iter()
+            .filter(|w| !w.sender.is_canceled())
+            .count()
+    }
+
+    /// Returns the rebuild jobs waiting for a slot, in the order they are
+    /// to be started.
+    pub fn pending() -> Vec<PendingRebuild> {
+        let inner = SCHEDULER.lock();
+        let mut queue = inner
+            .queue
+            .iter()
+            .filter(|w| !w.sender.is_canceled())
+            .collect::<Vec<_>>();
+        queue.sort_by(|a, b| b.order().cmp(&a.order()));
+
+        queue
+            .into_iter()
+            .map(|w| PendingRebuild {
+                nexus: w.request.nexus.clone(),
+                src_uri: w.request.src_uri.clone(),
+                dst_uri: w.request.dst_uri.clone(),
+                priority: w.request.priority,
+                healthy_children: w.request.healthy_children,
+                queued_at: w.queued_at,
+                nexus_limited: inner.has_free_slot()
+                    && !inner.has_free_nexus_slot(&w.request.nexus),
+            })
+            .collect()
+    }
+
+    /// Updates the number of healthy children of the nexus of the queued
+    /// jobs, so that they are started in the order of the current
+    /// degradation of their nexus.
+    pub fn set_healthy_children(nexus: &str, healthy_children: usize) {
+        SCHEDULER
+            .lock()
+            .queue
+            .iter_mut()
+            .filter(|w| w.request.nexus == nexus)
+            .for_each(|w| w.request.healthy_children = healthy_children);
+    }
+
+    /// Checks if a new rebuild job of the given nexus would be started
+    /// without queueing.
+    pub(super) fn has_free_slot(nexus: &str) -> bool {
+        let inner = SCHEDULER.lock();
+        inner.queue.is_empty()
+            && inner.has_free_slot()
+            && inner.has_free_nexus_slot(nexus)
+    }
+
+    /// Waits until a rebuild slot is available.
+    pub(super) async fn acquire(request: RebuildSlotRequest) -> RebuildSlot {
+        let nexus = request.nexus.clone();
+        let mut pending = {
+            let mut inner = SCHEDULER.lock();
+            if inner.queue.is_empty()
+                && inner.has_free_slot()
+                && inner.has_free_nexus_slot(&nexus)
+            {
+                inner.take_slot(&nexus);
+                return RebuildSlot {
+                    nexus,
+                };
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let serial = inner.serial;
+            inner.serial += 1;
+            inner.queue.push(Waiter {
+                request,
+                serial,
+                queued_at: SystemTime::now(),
+                sender,
+            });
+            // Other jobs may be startable, if this one is held by the
+            // per-nexus limit.
+            inner.dispatch();
+            PendingSlot {
+                receiver,
+                nexus,
+            }
+        };
+
+        // The sender is only dropped on a successful send, so the receiver
+        // can't be cancelled. Once received, dropping the pending request
+        // doesn't release the slot anymore.
+        (&mut pending.receiver).await.ok();
+
+        RebuildSlot {
+            nexus: std::mem::take(&mut pending.nexus),
+        }
+    }
+
+    /// Reserves bandwidth for transferring the given number of bytes, and
+    /// returns how long the transfer must be delayed to stay within the
+    /// bandwidth limit.
+    pub(super) fn throttle(bytes: u64) -> Option<Duration> {
+        let mut inner = SCHEDULER.lock();
+        if inner.config.max_bandwidth == 0 {
+            return None;
+        }
+
+        let now = Instant::now();
+        let start = std::cmp::max(now, inner.next_free);
+        inner.next_free = start
+            + Duration::from_secs_f64(
+                bytes as f64 / inner.config.max_bandwidth as f64,
+            );
+
+        let delay = start - now;
+        if delay.is_zero() {
+            None
+        } else {
+            Some(delay)
+        }
+    }
+}
@@ -19,6 +19,9 @@ pub enum RebuildState {
     Failed,
     /// Completed when the rebuild was successfully completed
     Completed,
+    /// NoSpace when the destination ran out of space; the job is resumed
+    /// automatically once the space is freed
+    NoSpace,
 }
 
 impl std::fmt::Display for RebuildState {
@@ -30,6 +33,7 @@ impl std::fmt::Display for RebuildState {
             RebuildState::Paused => write!(f, "paused"),
             RebuildState::Failed => write!(f, "failed"),
             RebuildState::Completed => write!(f, "completed"),
+            RebuildState::NoSpace => write!(f, "no_space"),
         }
     }
 }
@@ -43,6 +47,10 @@ impl RebuildState {
     pub fn running(&self) -> bool {
         matches!(self, Self::Running)
     }
+    /// Waiting for the destination to have free space.
+    pub fn no_space(&self) -> bool {
+        matches!(self, Self::NoSpace)
+    }
 }
 
 /// Rebuild state information containing the current and pending states.
@@ -131,7 +139,11 @@ impl RebuildStates {
             RebuildOperation::Start => {
                 match self.current {
                     // start only allowed when... starting
-                    S::Stopped | S::Paused | S::Failed | S::Completed => Err(e),
+                    S::Stopped
+                    | S::Paused
+                    | S::Failed
+                    | S::Completed
+                    | S::NoSpace => Err(e),
                     // for idempotence sake
                     S::Running => Ok(false),
                     S::Init => {
@@ -150,7 +162,7 @@ impl RebuildStates {
                         self.set_pending(S::Stopped, override_pending)?;
                         Ok(false)
                     }
-                    S::Init | S::Paused => {
+                    S::Init | S::Paused | S::NoSpace => {
                         self.set_pending(S::Stopped, override_pending)?;
                         Ok(true)
                     }
@@ -162,16 +174,24 @@ impl RebuildStates {
                     self.set_pending(S::Paused, false)?;
                     Ok(false)
                 }
+                S::NoSpace => {
+                    self.set_pending(S::Paused, false)?;
+                    Ok(true)
+                }
             },
             RebuildOperation::Resume => match self.current {
                 S::Init | S::Stopped | S::Failed | S::Completed => Err(e),
-                S::Running | S::Paused => {
+                S::Running | S::Paused | S::NoSpace => {
                     self.set_pending(S::Running, false)?;
                     Ok(true)
                 }
             },
             RebuildOperation::Fail => match self.current {
-                S::Init | S::Stopped | S::Paused | S::Completed => Err(e),
+                S::Init
+                | S::Stopped
+                | S::Paused
+                | S::Completed
+                | S::NoSpace => Err(e),
                 // for idempotence sake
                 S::Failed => Ok(false),
                 S::Running => {
@@ -180,11 +200,25 @@ impl RebuildStates {
                 }
             },
             RebuildOperation::Complete => match self.current {
+                S::Init
+                | S::Paused
+                | S::Stopped
+                | S::Failed
+                | S::Completed
+                | S::NoSpace => Err(e),
+                S::Running => {
+                    self.set_pending(S::Completed, override_pending)?;
+                    Ok(false)
+                }
+            },
+            RebuildOperation::NoSpace => match self.current {
                 S::Init | S::Paused | S::Stopped | S::Failed | S::Completed => {
                     Err(e)
                 }
+                // for idempotence sake
+                S::NoSpace => Ok(false),
                 S::Running => {
-                    self.set_pending(S::Completed, override_pending)?;
+                    self.set_pending(S::NoSpace, override_pending)?;
                     Ok(false)
                 }
             },
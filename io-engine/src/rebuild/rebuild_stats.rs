@@ -1,6 +1,16 @@
-use super::RebuildState;
+use super::{CopyOnReadStats, RebuildState};
 use chrono::{DateTime, Utc};
-use std::ops::Deref;
+use std::{
+    ops::Deref,
+    time::{Duration, Instant},
+};
+
+/// Interval over which the transferred bytes are sampled to compute the
+/// current throughput of a rebuild.
+const THROUGHPUT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Weight of the last sample in the current throughput of a rebuild.
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
 
 /// Rebuild statistics.
 #[derive(Debug, Clone)]
@@ -27,6 +37,31 @@ pub struct RebuildStats {
     pub start_time: DateTime<Utc>,
     /// Is this a partial rebuild?
     pub is_partial: bool,
+    /// Is the rebuild waiting for the destination to have free space?
+    pub is_no_space: bool,
+    /// Number of times the rebuild ran out of space on the destination.
+    pub no_space_count: u64,
+    /// Bytes read from each source of the rebuild.
+    pub sources: Vec<RebuildSourceStats>,
+    /// Current throughput in bytes per second, exponentially smoothed.
+    pub throughput: u64,
+    /// Average throughput in bytes per second since the start.
+    pub avg_throughput: u64,
+    /// Estimated time to complete the rebuild at the current throughput,
+    /// if known.
+    pub eta: Option<Duration>,
+    /// Copy-on-read statistics, if the destination serves reads while being
+    /// rebuilt.
+    pub copy_on_read: Option<CopyOnReadStats>,
+}
+
+/// Bytes read from a source of a rebuild.
+#[derive(Debug, Clone)]
+pub struct RebuildSourceStats {
+    /// URI of the source child.
+    pub uri: String,
+    /// Number of bytes read from the source.
+    pub bytes_read: u64,
 }
 
 impl Default for RebuildStats {
@@ -43,6 +78,87 @@ impl Default for RebuildStats {
             tasks_active: 0,
             start_time: Utc::now(),
             is_partial: false,
+            is_no_space: false,
+            no_space_count: 0,
+            sources: Vec::new(),
+            throughput: 0,
+            avg_throughput: 0,
+            eta: None,
+            copy_on_read: None,
+        }
+    }
+}
+
+/// Throughput of a rebuild, computed from the completions of the segments.
+#[derive(Debug)]
+pub(super) struct RebuildThroughput {
+    /// Creation time of the tracker.
+    start: Instant,
+    /// Start of the current sample.
+    sample_start: Instant,
+    /// Bytes transferred during the current sample.
+    sample_bytes: u64,
+    /// Bytes transferred in total.
+    total_bytes: u64,
+    /// Smoothed throughput of the previous samples, in bytes per second.
+    smoothed: Option<f64>,
+}
+
+impl RebuildThroughput {
+    pub(super) fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            sample_start: now,
+            sample_bytes: 0,
+            total_bytes: 0,
+            smoothed: None,
+        }
+    }
+
+    /// Smooths the throughput of the given sample with the previous ones.
+    fn smooth(&self, bytes: u64, elapsed: Duration) -> f64 {
+        let rate = bytes as f64 / elapsed.as_secs_f64();
+        match self.smoothed {
+            Some(s) => {
+                THROUGHPUT_SMOOTHING * rate + (1.0 - THROUGHPUT_SMOOTHING) * s
+            }
+            None => rate,
+        }
+    }
+
+    /// Records the transfer of a segment of the given size.
+    pub(super) fn record(&mut self, bytes: u64) {
+        self.sample_bytes += bytes;
+        self.total_bytes += bytes;
+
+        let elapsed = self.sample_start.elapsed();
+        if elapsed >= THROUGHPUT_SAMPLE_INTERVAL {
+            self.smoothed = Some(self.smooth(self.sample_bytes, elapsed));
+            self.sample_start = Instant::now();
+            self.sample_bytes = 0;
+        }
+    }
+
+    /// Returns the current throughput, in bytes per second. A sample which
+    /// lasts longer than the interval is accounted for, so that the
+    /// throughput goes down when the rebuild stalls.
+    pub(super) fn current(&self) -> u64 {
+        let elapsed = self.sample_start.elapsed();
+        if elapsed >= THROUGHPUT_SAMPLE_INTERVAL {
+            self.smooth(self.sample_bytes, elapsed) as u64
+        } else {
+            self.smoothed.unwrap_or_default() as u64
+        }
+    }
+
+    /// Returns the average throughput, in bytes per second.
+    pub(super) fn average(&self) -> u64 {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            (self.total_bytes as f64 / elapsed) as u64
+        } else {
+            0
         }
     }
 }
@@ -62,6 +178,8 @@ pub struct HistoryRecord {
     pub state: RebuildState,
     /// End time of this rebuild.
     pub end_time: DateTime<Utc>,
+    /// Error description, if the rebuild failed.
+    pub error: Option<String>,
 }
 
 impl Deref for HistoryRecord {
@@ -4,12 +4,17 @@ use snafu::ResultExt;
 use spdk_rs::{DmaBuf, LbaRange};
 use std::{rc::Rc, sync::Arc};
 
-use crate::core::{Reactors, VerboseError};
+use crate::{
+    core::{Reactors, VerboseError},
+    sleep::mayastor_sleep,
+};
 
 use super::{
     rebuild_error::{RangeLockFailed, RangeUnlockFailed},
     RebuildDescriptor,
     RebuildError,
+    RebuildScheduler,
+    RebuildThroughput,
     RebuildVerifyMode,
 };
 
@@ -26,7 +31,13 @@ pub(super) struct TaskResult {
     pub(super) error: Option<RebuildError>,
     /// Indicates if the segment was actually transferred (partial rebuild may
     /// skip segments).
-    is_transferred: bool,
+    pub(super) is_transferred: bool,
+    /// Size of the segment, in bytes.
+    len_bytes: u64,
+    /// Indicates if the segment was copied ahead of the sequential order, on
+    /// a copy-on-read request. Such segments are accounted for when the
+    /// sequential order reaches them.
+    pub(super) out_of_order: bool,
 }
 
 /// Each rebuild task needs a unique buffer to read/write from source to target.
@@ -72,10 +83,22 @@ impl RebuildTask {
         descriptor: &RebuildDescriptor,
     ) -> Result<bool, RebuildError> {
         if descriptor.is_blk_sync(blk) {
+            descriptor.blk_synced(blk);
             return Ok(false);
         }
 
         let len = descriptor.get_segment_size_blks(blk);
+
+        // Stay within the node-wide rebuild bandwidth limit.
+        if let Some(delay) =
+            RebuildScheduler::throttle(len * descriptor.block_size)
+        {
+            mayastor_sleep(delay).await.ok();
+        }
+
+        // Rebuild reads are low-priority: let client I/O go first.
+        descriptor.yield_to_client_io().await;
+
         // The nexus children have metadata and data partitions, whereas the
         // nexus has a data partition only. Because we are locking the range on
         // the nexus, we need to calculate the offset from the start of the data
@@ -157,6 +180,8 @@ pub(super) struct RebuildTasks {
     pub(super) segments_done: u64,
     /// How many segments have been actually transferred so far.
     pub(super) segments_transferred: u64,
+    /// Throughput of the transfers.
+    pub(super) throughput: RebuildThroughput,
 }
 
 impl std::fmt::Debug for RebuildTasks {
@@ -182,10 +207,11 @@ impl RebuildTasks {
     pub(super) async fn await_one_task(&mut self) -> Option<TaskResult> {
         self.channel.1.next().await.map(|f| {
             self.active -= 1;
-            if f.error.is_none() {
+            if f.error.is_none() && !f.out_of_order {
                 self.segments_done += 1;
                 if f.is_transferred {
                     self.segments_transferred += 1;
+                    self.throughput.record(f.len_bytes);
                 }
             }
             f
@@ -199,6 +225,7 @@ impl RebuildTasks {
         id: usize,
         blk: u64,
         descriptor: Rc<RebuildDescriptor>,
+        out_of_order: bool,
     ) {
         let task = self.tasks[id].clone();
 
@@ -212,6 +239,9 @@ impl RebuildTasks {
                 blk,
                 error: result.err(),
                 is_transferred,
+                len_bytes: descriptor.get_segment_size_blks(blk)
+                    * descriptor.block_size,
+                out_of_order,
             };
             task.error = Some(error.clone());
             if let Err(e) = task.sender.send(error).await {
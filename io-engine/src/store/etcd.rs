@@ -1,21 +1,25 @@
 //! Implementation of an etcd key-value store.
 
-use crate::store::store_defs::{
-    Connect,
-    Delete,
-    DeserialiseValue,
-    Get,
-    Put,
-    SerialiseValue,
-    Store,
-    StoreError,
-    StoreError::MissingEntry,
-    StoreKey,
-    StoreValue,
-    ValueString,
+use crate::{
+    grpc::read_pem,
+    store::store_defs::{
+        Connect,
+        Delete,
+        DeserialiseValue,
+        Get,
+        Put,
+        SerialiseValue,
+        Store,
+        StoreError,
+        StoreError::MissingEntry,
+        StoreKey,
+        StoreValue,
+        TlsConfig,
+        ValueString,
+    },
 };
 use async_trait::async_trait;
-use etcd_client::Client;
+use etcd_client::{Certificate, Client, ConnectOptions, Identity, TlsOptions};
 use serde_json::Value;
 use snafu::ResultExt;
 
@@ -29,11 +33,79 @@ impl std::fmt::Debug for Etcd {
     }
 }
 
+/// Security options of the connection to etcd. Certificates are either a
+/// path to a PEM file, or the PEM data itself.
+#[derive(Clone, Default)]
+pub struct EtcdSecurity {
+    /// CA certificate used to verify the etcd server. Enables TLS.
+    pub ca: Option<String>,
+    /// Client certificate chain, presented to the etcd server.
+    pub cert: Option<String>,
+    /// Private key of the client certificate.
+    pub key: Option<String>,
+    /// User name and password to authenticate with.
+    pub credentials: Option<(String, String)>,
+}
+
+impl std::fmt::Debug for EtcdSecurity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EtcdSecurity")
+            .field("tls", &self.tls_enabled())
+            .field("client_cert", &self.cert.is_some())
+            .field("user", &self.credentials.as_ref().map(|(user, _)| user))
+            .finish()
+    }
+}
+
+impl EtcdSecurity {
+    /// Whether the connection uses TLS.
+    pub fn tls_enabled(&self) -> bool {
+        self.ca.is_some() || self.cert.is_some()
+    }
+
+    /// Makes the connect options of the etcd client, reading the
+    /// certificates. Returns `None` for a plaintext, unauthenticated
+    /// connection.
+    fn connect_options(&self) -> Result<Option<ConnectOptions>, StoreError> {
+        if !self.tls_enabled() && self.credentials.is_none() {
+            return Ok(None);
+        }
+
+        let mut options = ConnectOptions::new();
+
+        if let Some((user, password)) = &self.credentials {
+            options = options.with_user(user.clone(), password.clone());
+        }
+
+        if self.tls_enabled() {
+            let mut tls = TlsOptions::new();
+            if let Some(ca) = &self.ca {
+                tls = tls.ca_certificate(Certificate::from_pem(
+                    read_pem(ca, "CA").context(TlsConfig)?,
+                ));
+            }
+            if let (Some(cert), Some(key)) = (&self.cert, &self.key) {
+                tls = tls.identity(Identity::from_pem(
+                    read_pem(cert, "certificate").context(TlsConfig)?,
+                    read_pem(key, "key").context(TlsConfig)?,
+                ));
+            }
+            options = options.with_tls(tls);
+        }
+
+        Ok(Some(options))
+    }
+}
+
 impl Etcd {
     /// Create a new instance of the etcd client
-    pub async fn new(endpoint: &str) -> Result<Etcd, StoreError> {
+    pub async fn new(
+        endpoint: &str,
+        security: &EtcdSecurity,
+    ) -> Result<Etcd, StoreError> {
+        let options = security.connect_options()?;
         Ok(Self(
-            Client::connect([endpoint], None)
+            Client::connect([endpoint], options)
                 .await
                 .context(Connect {})?,
         ))
@@ -5,6 +5,8 @@ use etcd_client::Error;
 use serde_json::{Error as SerdeError, Value};
 use snafu::Snafu;
 
+use crate::grpc::GrpcTlsError;
+
 /// Definition of errors that can be returned from the key-value store.
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub(crate)), context(suffix(false)))]
@@ -96,6 +98,12 @@ pub enum StoreError {
     /// Operation timed out.
     #[snafu(display("Store operation timed out.",))]
     OpTimeout {},
+    /// Failed to load the TLS certificates of the store connection.
+    #[snafu(display(
+        "Failed to load store TLS configuration. Error {}",
+        source
+    ))]
+    TlsConfig { source: GrpcTlsError },
 }
 
 /// Store keys type trait
@@ -43,6 +43,7 @@ impl RpcErrorCode for Error {
 
 pub(crate) mod opts;
 pub(crate) mod pool;
+pub(crate) mod startup;
 
 pub static CONFIG: OnceCell<Config> = OnceCell::new();
 
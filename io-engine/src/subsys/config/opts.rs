@@ -137,7 +137,8 @@ pub struct NvmfTcpTransportOpts {
     num_shared_buf: u32,
     /// cache size
     buf_cache_size: u32,
-    /// dif
+    /// insert and strip the protection information of bdevs formatted with
+    /// DIF, for hosts which do not support it
     dif_insert_or_strip: bool,
     /// abort execution timeout
     abort_timeout_sec: u32,
@@ -183,7 +184,10 @@ impl Default for NvmfTcpTransportOpts {
             ),
             num_shared_buf: try_from_env("NVMF_TCP_NUM_SHARED_BUF", 2048),
             buf_cache_size: try_from_env("NVMF_TCP_BUF_CACHE_SIZE", 64),
-            dif_insert_or_strip: false,
+            dif_insert_or_strip: try_from_env(
+                "NVMF_TCP_DIF_INSERT_OR_STRIP",
+                false,
+            ),
             max_aq_depth: 32,
             abort_timeout_sec: 1,
             acceptor_poll_rate: try_from_env("NVMF_ACCEPTOR_POLL_RATE", 10_000),
@@ -0,0 +1,220 @@
+//! Resources to reconstruct at startup, for deployments without a control
+//! plane.
+//!
+//! The startup config file lists the bdevs to create, the pools to import or
+//! create and the bdevs or replicas to share. It is YAML, which makes JSON
+//! files valid as well. The config is applied once the subsystems are up,
+//! and applying it again leaves existing resources untouched.
+use std::{convert::TryFrom, fmt::Display, fs, path::Path, pin::Pin};
+
+use serde::{Deserialize, Serialize};
+
+use super::pool::ShareType;
+use crate::{
+    bdev_api::{bdev_create, bdev_get_name, BdevError},
+    core::{
+        CoreError,
+        Cores,
+        Protocol,
+        Reactor,
+        Share,
+        ShareProps,
+        UntypedBdev,
+        VerboseError,
+    },
+    lvs::{Error as LvsError, Lvol, Lvs},
+    pool_backend::PoolArgs,
+};
+
+/// Resources to reconstruct at startup.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StartupConfig {
+    /// Bdevs to create, by URI.
+    bdevs: Vec<String>,
+    /// Pools to import or create.
+    pools: Vec<StartupPool>,
+    /// Bdevs or replicas to share.
+    shares: Vec<StartupShare>,
+}
+
+/// Pool to import, or to create if its disks hold no pool.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+struct StartupPool {
+    /// Name of the pool.
+    name: String,
+    /// Disks of the pool.
+    disks: Vec<String>,
+    /// UUID of the pool, if it must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+}
+
+/// Bdev or replica to share.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+struct StartupShare {
+    /// Name or UUID of the bdev or replica.
+    name: String,
+    /// Protocol to share it with.
+    #[serde(default = "default_share_type")]
+    protocol: ShareType,
+    /// Host NQNs allowed to connect, any host if empty.
+    #[serde(default)]
+    allowed_hosts: Vec<String>,
+}
+
+fn default_share_type() -> ShareType {
+    ShareType::Nvmf
+}
+
+impl From<&StartupPool> for PoolArgs {
+    fn from(pool: &StartupPool) -> Self {
+        Self {
+            name: pool.name.clone(),
+            disks: pool.disks.clone(),
+            uuid: pool.uuid.clone(),
+            force: false,
+        }
+    }
+}
+
+impl StartupConfig {
+    /// Load the startup configuration from a file.
+    pub fn load<P>(file: P) -> Result<StartupConfig, serde_yaml::Error>
+    where
+        P: AsRef<Path> + Display,
+    {
+        let bytes = fs::read(&file).unwrap_or_else(|error| {
+            warn!("failed to read startup config file {}: {}", file, error);
+            Vec::new()
+        });
+
+        if bytes.is_empty() {
+            return Ok(StartupConfig::default());
+        }
+
+        serde_yaml::from_slice(&bytes)
+    }
+
+    /// Create the bdevs, pools and shares of this configuration which do
+    /// not exist yet.
+    pub fn apply(self) {
+        assert_eq!(Cores::current(), Cores::first());
+        Reactor::block_on(async move {
+            let errors = self.create_bdevs().await
+                + self.create_pools().await
+                + self.create_shares().await;
+            if errors != 0 {
+                warn!(
+                    "Not all startup resources were applied successfully \
+                    ({} errors)",
+                    errors
+                );
+            }
+        });
+    }
+
+    /// Create the bdevs of this configuration.
+    async fn create_bdevs(&self) -> usize {
+        let mut failures = 0;
+        for uri in self.bdevs.iter() {
+            if let Err(error) = create_bdev(uri).await {
+                error!("failed to create bdev {}: {}", uri, error.verbose());
+                failures += 1;
+            }
+        }
+        failures
+    }
+
+    /// Import or create the pools of this configuration.
+    async fn create_pools(&self) -> usize {
+        let mut failures = 0;
+        for pool in self.pools.iter() {
+            if Lvs::lookup(&pool.name).is_some() {
+                debug!("pool {} already exists", pool.name);
+                continue;
+            }
+            info!("creating pool {}", pool.name);
+            if let Err(error) = Lvs::create_or_import(pool.into()).await {
+                error!(
+                    "failed to create pool {}: {}",
+                    pool.name,
+                    error.verbose()
+                );
+                failures += 1;
+            }
+        }
+        failures
+    }
+
+    /// Share the bdevs and replicas of this configuration.
+    async fn create_shares(&self) -> usize {
+        let mut failures = 0;
+        for share in self.shares.iter() {
+            if let Err(error) = create_share(share).await {
+                error!("failed to share {}: {}", share.name, error);
+                failures += 1;
+            }
+        }
+        failures
+    }
+}
+
+/// Create a bdev unless it already exists.
+async fn create_bdev(uri: &str) -> Result<(), BdevError> {
+    let name = bdev_get_name(uri)?;
+    if UntypedBdev::lookup_by_name(&name).is_some() {
+        debug!("bdev {} already exists", name);
+        return Ok(());
+    }
+    match bdev_create(uri).await {
+        Ok(_)
+        | Err(BdevError::BdevExists {
+            ..
+        }) => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Share a bdev or a replica unless it is already shared.
+async fn create_share(share: &StartupShare) -> Result<(), String> {
+    let Some(mut bdev) = UntypedBdev::lookup_by_name(&share.name)
+        .or_else(|| UntypedBdev::lookup_by_uuid_str(&share.name))
+    else {
+        return Err("no such bdev or replica".to_string());
+    };
+    // Replicas are shared as lvols, which persists their share.
+    let mut lvol = Lvol::try_from(bdev).ok();
+
+    let shared = match &lvol {
+        Some(lvol) => lvol.shared(),
+        None => bdev.shared(),
+    };
+    if shared == Some(Protocol::Nvmf) {
+        debug!("{} is already shared", share.name);
+        return Ok(());
+    }
+
+    let props =
+        ShareProps::new().with_allowed_hosts(share.allowed_hosts.clone());
+    match share.protocol {
+        ShareType::Nvmf => match lvol.as_mut() {
+            Some(lvol) => {
+                Pin::new(lvol)
+                    .share_nvmf(Some(props))
+                    .await
+                    .map_err(|error: LvsError| error.verbose())?;
+            }
+            None => {
+                Pin::new(&mut bdev)
+                    .share_nvmf(Some(props))
+                    .await
+                    .map_err(|error: CoreError| error.verbose())?;
+            }
+        },
+    }
+    info!("shared {} at startup", share.name);
+    Ok(())
+}
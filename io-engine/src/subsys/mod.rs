@@ -4,18 +4,32 @@
 pub use config::{
     opts::{NexusOpts, NvmeBdevOpts},
     pool::PoolConfig,
+    startup::StartupConfig,
     Config,
     ConfigSubsystem,
 };
 pub use nvmf::{
     create_snapshot,
+    create_transport as create_nvmf_transport,
+    list_transports as list_nvmf_transports,
     set_snapshot_time,
+    validate_nqn,
     Error as NvmfError,
     NvmeCpl,
+    NvmfPollGroupLoad,
+    NvmfRebalanceConfig,
+    NvmfRebalanceResult,
+    NvmfRebalancer,
     NvmfReq,
+    NvmfSharePreflight,
+    NvmfShareViolation,
     NvmfSubsystem,
+    NvmfTransportInfo,
+    NvmfTransportOpts,
+    NvmfTransportType,
     SubType,
     Target as NvmfTarget,
+    NVMF_REBALANCE_DEFAULT_THRESHOLD,
 };
 use spdk_rs::libspdk::{
     spdk_add_subsystem,
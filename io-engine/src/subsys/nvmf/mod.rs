@@ -15,6 +15,18 @@ use snafu::Snafu;
 
 pub use admin_cmd::{create_snapshot, set_snapshot_time, NvmeCpl, NvmfReq};
 use poll_groups::PollGroup;
+pub use rebalance::{
+    NvmfPollGroupLoad,
+    NvmfRebalanceConfig,
+    NvmfRebalanceResult,
+    NvmfRebalancer,
+    NVMF_REBALANCE_DEFAULT_THRESHOLD,
+};
+pub use share_preflight::{
+    validate_nqn,
+    NvmfSharePreflight,
+    NvmfShareViolation,
+};
 use spdk_rs::libspdk::{
     spdk_subsystem,
     spdk_subsystem_fini_next,
@@ -22,6 +34,13 @@ use spdk_rs::libspdk::{
 };
 pub use subsystem::{NvmfSubsystem, SubType};
 pub use target::Target;
+pub use transport::{
+    create_transport,
+    list_transports,
+    NvmfTransportInfo,
+    NvmfTransportOpts,
+    NvmfTransportType,
+};
 
 use crate::{
     jsonrpc::{Code, RpcErrorCode},
@@ -30,6 +49,8 @@ use crate::{
 
 mod admin_cmd;
 mod poll_groups;
+mod rebalance;
+mod share_preflight;
 mod subsystem;
 mod target;
 mod transport;
@@ -62,6 +83,8 @@ pub enum Error {
     DestroyTarget { source: Errno, endpoint: String },
     #[snafu(display("Failed to create poll groups {}", msg))]
     PgError { msg: String },
+    #[snafu(display("Failed to rebalance the connections: {}", msg))]
+    Rebalance { msg: String },
     #[snafu(display("Failed to create transport {}", msg))]
     Transport { source: Errno, msg: String },
     #[snafu(display(
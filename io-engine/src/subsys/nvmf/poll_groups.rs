@@ -4,7 +4,7 @@ use spdk_rs::libspdk::{
     spdk_nvmf_tgt,
 };
 
-use crate::core::Mthread;
+use crate::core::{Cores, Mthread};
 
 #[derive(Clone, Debug)]
 struct Pg(*mut spdk_nvmf_poll_group);
@@ -13,6 +13,8 @@ struct Pg(*mut spdk_nvmf_poll_group);
 #[derive(Clone, Debug)]
 pub(crate) struct PollGroup {
     pub thread: Mthread,
+    /// the core the poll group is polled on
+    pub core: u32,
     group: Pg,
 }
 
@@ -20,6 +22,7 @@ impl PollGroup {
     pub fn new(tgt: *mut spdk_nvmf_tgt, mt: Mthread) -> Self {
         Self {
             thread: mt,
+            core: Cores::current(),
             group: Pg(unsafe { spdk_nvmf_poll_group_create(tgt) }),
         }
     }
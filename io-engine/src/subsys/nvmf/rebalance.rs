@@ -0,0 +1,245 @@
+//! Rebalancing of the NVMf connections across the poll groups.
+//!
+//! A new connection is placed on a poll group, and so on a core, when it is
+//! established, regardless of the load it carries later on. When a poll group
+//! completes much more I/Os than the average of all poll groups, one of its
+//! I/O connections is disconnected: the host reconnects it, and the new
+//! connection is placed on another poll group.
+use std::{collections::HashMap, ptr, time::Duration};
+
+use nix::errno::Errno;
+use once_cell::sync::{Lazy, OnceCell};
+
+use spdk_rs::libspdk::{
+    spdk_nvmf_poll_group_get_stat,
+    spdk_nvmf_poll_group_stat,
+    spdk_nvmf_qpair_disconnect,
+};
+
+use crate::{
+    core::Reactor,
+    sleep::mayastor_sleep,
+    subsys::nvmf::{transport::running_target, Error, NVMF_PGS},
+};
+
+/// Default load of a hot poll group, in percent of the average load.
+pub const NVMF_REBALANCE_DEFAULT_THRESHOLD: u32 = 150;
+
+/// Configuration of the periodic rebalancing of the NVMf connections.
+#[derive(Debug, Clone, Copy)]
+pub struct NvmfRebalanceConfig {
+    /// Pause between two rebalancing passes. A zero interval disables the
+    /// periodic rebalancing.
+    pub interval: Duration,
+    /// Load above which a poll group is hot, in percent of the average load.
+    pub threshold: u32,
+}
+
+impl Default for NvmfRebalanceConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::ZERO,
+            threshold: NVMF_REBALANCE_DEFAULT_THRESHOLD,
+        }
+    }
+}
+
+static NVMF_REBALANCE_CONFIG: OnceCell<NvmfRebalanceConfig> = OnceCell::new();
+
+/// Completed I/Os of the poll groups at the last pass, by core.
+static LAST_COMPLETED: Lazy<parking_lot::Mutex<HashMap<u32, u64>>> =
+    Lazy::new(Default::default);
+
+/// Load of a poll group.
+#[derive(Debug, Clone, Copy)]
+pub struct NvmfPollGroupLoad {
+    /// Core the poll group is polled on.
+    pub core: u32,
+    /// Number of I/O connections of the poll group.
+    pub io_qpairs: u32,
+    /// I/Os completed by the poll group.
+    pub completed_ios: u64,
+    /// I/Os completed by the poll group since the previous pass.
+    pub load: u64,
+}
+
+/// Outcome of a rebalancing pass.
+#[derive(Debug, Clone)]
+pub struct NvmfRebalanceResult {
+    /// Load of the poll groups.
+    pub poll_groups: Vec<NvmfPollGroupLoad>,
+    /// Core of the poll group an I/O connection was moved from, if any.
+    pub moved_from: Option<u32>,
+}
+
+/// Rebalancing of the NVMf connections across the poll groups.
+pub struct NvmfRebalancer {}
+
+impl NvmfRebalancer {
+    /// Configures the periodic rebalancing. Only the first call has an
+    /// effect.
+    pub fn configure(config: NvmfRebalanceConfig) {
+        if !config.interval.is_zero() {
+            info!("NVMf connection rebalancing: {config:?}");
+        }
+
+        if NVMF_REBALANCE_CONFIG.set(config).is_err() {
+            warn!("NVMf connection rebalancing has already been configured");
+        }
+    }
+
+    /// Checks if the periodic rebalancing is enabled.
+    pub fn enabled() -> bool {
+        NVMF_REBALANCE_CONFIG
+            .get()
+            .map_or(false, |c| !c.interval.is_zero())
+    }
+
+    /// Returns the configured hot poll group threshold.
+    pub fn threshold() -> u32 {
+        NVMF_REBALANCE_CONFIG
+            .get()
+            .map_or(NVMF_REBALANCE_DEFAULT_THRESHOLD, |c| c.threshold)
+    }
+
+    /// Runs the periodic rebalancing forever. Must be called on the primary
+    /// reactor.
+    pub async fn run() {
+        let Some(config) = NVMF_REBALANCE_CONFIG.get().copied() else {
+            return;
+        };
+        if config.interval.is_zero() {
+            return;
+        }
+
+        loop {
+            if mayastor_sleep(config.interval).await.is_err() {
+                error!("Failed to wait for the next NVMf rebalancing pass");
+                return;
+            }
+
+            if let Err(error) = Self::rebalance(config.threshold).await {
+                debug!("NVMf rebalancing pass skipped: {error}");
+            }
+        }
+    }
+
+    /// Moves an I/O connection off the most loaded poll group, if its load
+    /// is above the given threshold, in percent of the average load. Must be
+    /// called on the primary reactor.
+    pub async fn rebalance(
+        threshold: u32,
+    ) -> Result<NvmfRebalanceResult, Error> {
+        let poll_groups = Self::poll_group_loads().await?;
+
+        let avg = poll_groups.iter().map(|p| p.load).sum::<u64>()
+            / poll_groups.len().max(1) as u64;
+        let hot = poll_groups
+            .iter()
+            .max_by_key(|p| p.load)
+            .filter(|p| {
+                p.io_qpairs > 1
+                    && p.load * 100 > avg * threshold as u64
+                    && poll_groups.iter().any(|c| c.load < avg)
+            })
+            .map(|p| p.core);
+
+        let mut moved_from = None;
+        if let Some(core) = hot {
+            if Self::disconnect_one(core).await? {
+                info!(
+                    "Moving an NVMf connection off the poll group of core \
+                    {core}: {poll_groups:?}"
+                );
+                moved_from = Some(core);
+            }
+        }
+
+        Ok(NvmfRebalanceResult {
+            poll_groups,
+            moved_from,
+        })
+    }
+
+    /// Collects the load of the poll groups since the previous pass.
+    async fn poll_group_loads() -> Result<Vec<NvmfPollGroupLoad>, Error> {
+        let tgt = running_target()?;
+        let pgs = NVMF_PGS.with(|p| p.borrow().clone());
+
+        let mut loads = Vec::with_capacity(pgs.len());
+        for pg in pgs {
+            let stat = Reactor::spawn_at(&pg.thread, async move {
+                let mut stat = spdk_nvmf_poll_group_stat::default();
+                match unsafe { spdk_nvmf_poll_group_get_stat(tgt, &mut stat) } {
+                    0 => Some((stat.current_io_qpairs, stat.completed_nvme_io)),
+                    _ => None,
+                }
+            })
+            .map_err(|_| Error::Rebalance {
+                msg: format!("poll group of core {} is unreachable", pg.core),
+            })?
+            .await
+            .ok()
+            .flatten();
+
+            let Some((io_qpairs, completed_ios)) = stat else {
+                continue;
+            };
+            let last = LAST_COMPLETED.lock().insert(pg.core, completed_ios);
+            loads.push(NvmfPollGroupLoad {
+                core: pg.core,
+                io_qpairs,
+                completed_ios,
+                load: completed_ios.saturating_sub(last.unwrap_or_default()),
+            });
+        }
+        Ok(loads)
+    }
+
+    /// Disconnects the most recent I/O connection of the poll group of the
+    /// given core.
+    async fn disconnect_one(core: u32) -> Result<bool, Error> {
+        let Some(pg) = NVMF_PGS
+            .with(|p| p.borrow().iter().find(|p| p.core == core).cloned())
+        else {
+            return Ok(false);
+        };
+        let group = pg.group_ptr();
+
+        let rc = Reactor::spawn_at(&pg.thread, async move {
+            let mut qpair = unsafe { (*group).qpairs.tqh_first };
+            let mut last = ptr::null_mut();
+            while !qpair.is_null() {
+                if unsafe { (*qpair).qid } != 0 {
+                    last = qpair;
+                }
+                qpair = unsafe { (*qpair).link.tqe_next };
+            }
+
+            if last.is_null() {
+                return None;
+            }
+            Some(unsafe {
+                spdk_nvmf_qpair_disconnect(last, None, ptr::null_mut())
+            })
+        })
+        .map_err(|_| Error::Rebalance {
+            msg: format!("poll group of core {core} is unreachable"),
+        })?
+        .await
+        .ok()
+        .flatten();
+
+        match rc {
+            None => Ok(false),
+            Some(0) => Ok(true),
+            Some(rc) => Err(Error::Rebalance {
+                msg: format!(
+                    "failed to disconnect a connection of the poll group \
+                    of core {core}: {}",
+                    Errno::from_i32(-rc)
+                ),
+            }),
+        }
+    }
+}
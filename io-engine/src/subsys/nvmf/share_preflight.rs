@@ -0,0 +1,241 @@
+//! Validation of a request to share a bdev over NVMf.
+//!
+//! Sharing fails on the first problem SPDK runs into, with an errno which
+//! does not tell which part of the request is wrong. The preflight checks
+//! the whole request up front and reports every violation it finds: the
+//! listener address must be an IPv4 address of the node, the listener port
+//! must be free or already used by the NVMf target, the NQN of the bdev must
+//! not be used by the subsystem of another bdev, and the allowed hosts must
+//! be well formed NQNs.
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddrV4, TcpListener},
+};
+
+use super::{transport::get_ipv4_address, NvmfSubsystem};
+use crate::{constants::NVME_NQN_PREFIX, core::UntypedBdev, subsys::Config};
+
+/// Maximum length of an NQN, in bytes.
+const NQN_MAX_LEN: usize = 223;
+
+/// Problem found in a share request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NvmfShareViolation {
+    /// Part of the request the problem is about, e.g. `port` or
+    /// `allowed_hosts[1]`.
+    pub field: String,
+    /// Description of the problem.
+    pub message: String,
+}
+
+/// Result of the preflight of a share request.
+#[derive(Debug, Clone)]
+pub struct NvmfSharePreflight {
+    /// NQN the bdev would be shared under.
+    pub nqn: String,
+    /// Listener address.
+    pub address: String,
+    /// Listener port.
+    pub port: u16,
+    /// Problems found, empty if the bdev can be shared.
+    pub violations: Vec<NvmfShareViolation>,
+}
+
+impl NvmfSharePreflight {
+    /// Checks if the bdev can be shared as requested.
+    pub fn ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    fn violation(&mut self, field: impl Into<String>, message: String) {
+        self.violations.push(NvmfShareViolation {
+            field: field.into(),
+            message,
+        });
+    }
+}
+
+impl NvmfSubsystem {
+    /// Checks if the given bdev can be shared over NVMf with the given
+    /// listener and allowed hosts. The listener defaults to the address of
+    /// the NVMf target and to the replica port.
+    pub fn share_preflight(
+        bdev_name: &str,
+        address: Option<&str>,
+        port: Option<u16>,
+        allowed_hosts: &[String],
+    ) -> NvmfSharePreflight {
+        let mut preflight = NvmfSharePreflight {
+            nqn: format!("{NVME_NQN_PREFIX}:{bdev_name}"),
+            address: address
+                .map(String::from)
+                .or_else(|| get_ipv4_address().ok())
+                .unwrap_or_default(),
+            port: port
+                .unwrap_or_else(|| Config::get().nexus_opts.nvmf_replica_port),
+            violations: Vec::new(),
+        };
+
+        Self::check_bdev(&mut preflight, bdev_name);
+        Self::check_listener(&mut preflight);
+        for (i, host) in allowed_hosts.iter().enumerate() {
+            if let Err(message) = validate_nqn(host) {
+                preflight.violation(format!("allowed_hosts[{i}]"), message);
+            }
+        }
+
+        preflight
+    }
+
+    /// Checks that the bdev exists and that its NQN is free or already used
+    /// by its own subsystem.
+    fn check_bdev(preflight: &mut NvmfSharePreflight, bdev_name: &str) {
+        let Some(bdev) = UntypedBdev::lookup_by_name(bdev_name) else {
+            preflight.violation("bdev", format!("bdev {bdev_name} not found"));
+            return;
+        };
+
+        let subsystem = NvmfSubsystem::first().and_then(|ss| {
+            ss.into_iter().find(|s| s.get_nqn() == preflight.nqn)
+        });
+        match subsystem {
+            Some(ss) => {
+                if let Some(other) = ss.bdev().filter(|b| b.name() != bdev_name)
+                {
+                    preflight.violation(
+                        "nqn",
+                        format!(
+                            "NQN {} is in use by bdev {}",
+                            preflight.nqn,
+                            other.name()
+                        ),
+                    );
+                }
+            }
+            None if bdev.is_claimed() => {
+                preflight.violation(
+                    "bdev",
+                    format!("bdev {bdev_name} is claimed by another module"),
+                );
+            }
+            None => {}
+        }
+    }
+
+    /// Checks that the listener address belongs to the node and that the
+    /// port is free or used by the NVMf target.
+    fn check_listener(preflight: &mut NvmfSharePreflight) {
+        let ip = match preflight.address.parse::<Ipv4Addr>() {
+            Ok(ip) => ip,
+            Err(_) => {
+                preflight.violation(
+                    "address",
+                    format!("'{}' is not an IPv4 address", preflight.address),
+                );
+                return;
+            }
+        };
+        if preflight.port == 0 {
+            preflight.violation("port", "port 0 is invalid".to_string());
+            return;
+        }
+
+        // Binding the address tells both whether it is local and whether
+        // the port is free.
+        match TcpListener::bind(SocketAddrV4::new(ip, preflight.port)) {
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::AddrNotAvailable => {
+                preflight.violation(
+                    "address",
+                    format!("{ip} is not an address of this node"),
+                );
+            }
+            Err(error) if error.kind() == ErrorKind::AddrInUse => {
+                if !Self::is_target_listener(&preflight.address, preflight.port)
+                {
+                    preflight.violation(
+                        "port",
+                        format!(
+                            "port {} of {ip} is in use by another process",
+                            preflight.port
+                        ),
+                    );
+                }
+            }
+            Err(error) => {
+                preflight.violation(
+                    "address",
+                    format!(
+                        "cannot listen on {ip}:{}: {error}",
+                        preflight.port
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Checks if the NVMf target listens on the given address and port.
+    fn is_target_listener(address: &str, port: u16) -> bool {
+        let opts = &Config::get().nexus_opts;
+        if get_ipv4_address().ok().as_deref() == Some(address)
+            && (port == opts.nvmf_replica_port || port == opts.nvmf_nexus_port)
+        {
+            return true;
+        }
+
+        let endpoint = format!("nvmf://{address}:{port}");
+        NvmfSubsystem::first().map_or(false, |ss| {
+            ss.into_iter().any(|s| {
+                s.listeners_to_vec()
+                    .unwrap_or_default()
+                    .iter()
+                    .any(|t| t.to_string() == endpoint)
+            })
+        })
+    }
+}
+
+/// Checks that the given string is a well formed NQN: either
+/// `nqn.yyyy-mm.<reverse domain>[:<name>]` or
+/// `nqn.2014-08.org.nvmexpress:uuid:<uuid>`, of at most 223 bytes.
+pub fn validate_nqn(nqn: &str) -> Result<(), String> {
+    if nqn.len() > NQN_MAX_LEN {
+        return Err(format!("NQN '{nqn}' is longer than {NQN_MAX_LEN} bytes"));
+    }
+
+    if let Some(uuid) = nqn.strip_prefix("nqn.2014-08.org.nvmexpress:uuid:") {
+        return uuid::Uuid::parse_str(uuid)
+            .map(|_| ())
+            .map_err(|_| format!("NQN '{nqn}' has an invalid UUID"));
+    }
+
+    let invalid = || {
+        Err(format!(
+            "NQN '{nqn}' is not of the form nqn.yyyy-mm.<reverse domain>"
+        ))
+    };
+    let Some(rest) = nqn.strip_prefix("nqn.") else {
+        return invalid();
+    };
+    let (Some(date), Some(rest)) = (rest.get(.. 7), rest.get(7 ..)) else {
+        return invalid();
+    };
+    let date = date.as_bytes();
+    let date_ok = date[.. 4].iter().all(u8::is_ascii_digit)
+        && date[4] == b'-'
+        && date[5 ..].iter().all(u8::is_ascii_digit)
+        && matches!(&date[5 ..], [b'0', b'1' ..= b'9'] | [b'1', b'0' ..= b'2']);
+    let domain = rest
+        .strip_prefix('.')
+        .map(|r| r.split(':').next().unwrap_or_default());
+    match domain {
+        Some(domain)
+            if date_ok
+                && !domain.is_empty()
+                && domain.split('.').all(|l| !l.is_empty()) =>
+        {
+            Ok(())
+        }
+        _ => invalid(),
+    }
+}
@@ -22,6 +22,7 @@ use spdk_rs::libspdk::{
     spdk_nvmf_ctrlr,
     spdk_nvmf_ns_get_bdev,
     spdk_nvmf_ns_opts,
+    spdk_nvmf_ns_set_write_protect,
     spdk_nvmf_subsystem,
     spdk_nvmf_subsystem_add_host,
     spdk_nvmf_subsystem_add_listener,
@@ -40,6 +41,7 @@ use spdk_rs::libspdk::{
     spdk_nvmf_subsystem_listener_get_trid,
     spdk_nvmf_subsystem_pause,
     spdk_nvmf_subsystem_remove_host,
+    spdk_nvmf_subsystem_remove_listener,
     spdk_nvmf_subsystem_remove_ns,
     spdk_nvmf_subsystem_resume,
     spdk_nvmf_subsystem_set_allow_any_host,
@@ -66,7 +68,7 @@ use spdk_rs::libspdk::{
 use crate::{
     bdev::nexus::ENABLE_NEXUS_RESET,
     constants::{NVME_CONTROLLER_MODEL_ID, NVME_NQN_PREFIX},
-    core::{Bdev, Reactors, UntypedBdev},
+    core::{Bdev, NvmeIdentity, Reactors, UntypedBdev},
     ffihelper::{cb_arg, done_cb, AsStr, FfiResult, IntoCString},
     subsys::{
         make_subsystem_serial,
@@ -173,6 +175,7 @@ impl NvmfSubsystem {
     pub fn try_from_with<T>(
         bdev: &Bdev<T>,
         ptpl: Option<&std::path::PathBuf>,
+        identity: &NvmeIdentity,
     ) -> Result<Self, Error>
     where
         T: spdk_rs::BdevOps,
@@ -182,10 +185,16 @@ impl NvmfSubsystem {
                 msg: "already shared".to_string(),
             });
         }
-        let ss = NvmfSubsystem::new(bdev.name())?;
+        let ss = match &identity.nqn {
+            Some(nqn) => NvmfSubsystem::new_with_nqn(bdev.name(), nqn)?,
+            None => NvmfSubsystem::new(bdev.name())?,
+        };
         ss.set_ana_reporting(false)?;
         ss.allow_any(false);
-        if let Err(e) = ss.add_namespace(bdev, ptpl) {
+        if let Err(e) = ss
+            .set_identity(identity)
+            .and_then(|_| ss.add_namespace(bdev, ptpl, identity))
+        {
             unsafe {
                 ss.destroy_unsafe();
             }
@@ -198,7 +207,7 @@ impl NvmfSubsystem {
     where
         T: spdk_rs::BdevOps,
     {
-        Self::try_from_with(bdev, None)
+        Self::try_from_with(bdev, None, &NvmeIdentity::default())
     }
 }
 
@@ -421,7 +430,13 @@ impl NvmfSubsystem {
 
     /// create a new subsystem where the NQN is based on the UUID
     pub fn new(uuid: &str) -> Result<Self, Error> {
-        let nqn = make_nqn(uuid).into_cstring();
+        Self::new_with_nqn(uuid, &make_nqn(uuid))
+    }
+
+    /// Creates a new subsystem with the given NQN. The default serial number
+    /// is still derived from the UUID.
+    pub fn new_with_nqn(uuid: &str, nqn: &str) -> Result<Self, Error> {
+        let nqn = nqn.into_cstring();
         let ss = NVMF_TGT
             .with(|t| {
                 let tgt = t.borrow().tgt.as_ptr();
@@ -489,21 +504,54 @@ impl NvmfSubsystem {
         let ss = NvmfSubsystem::new(uuid)?;
         ss.set_ana_reporting(false)?;
         ss.allow_any(false);
-        ss.add_namespace(bdev, None)?;
+        ss.add_namespace(bdev, None, &NvmeIdentity::default())?;
         Ok(ss)
     }
 
-    /// add the given bdev to this namespace
+    /// Overrides the serial and model numbers of this subsystem with the
+    /// ones of the given identity, if set.
+    pub fn set_identity(&self, identity: &NvmeIdentity) -> Result<(), Error> {
+        if let Some(serial) = &identity.serial {
+            let sn = CString::new(serial.as_str()).unwrap();
+            unsafe { spdk_nvmf_subsystem_set_sn(self.0.as_ptr(), sn.as_ptr()) }
+                .to_result(|e| Error::Subsystem {
+                    source: Errno::from_i32(e),
+                    nqn: self.get_nqn(),
+                    msg: format!("failed to set serial '{serial}'"),
+                })?;
+        }
+        if let Some(model) = &identity.model {
+            let mn = CString::new(model.as_str()).unwrap();
+            unsafe { spdk_nvmf_subsystem_set_mn(self.0.as_ptr(), mn.as_ptr()) }
+                .to_result(|e| Error::Subsystem {
+                    source: Errno::from_i32(e),
+                    nqn: self.get_nqn(),
+                    msg: format!("failed to set model number '{model}'"),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// add the given bdev to this namespace.
+    /// The NGUID of the namespace defaults to the bdev UUID, and its EUI64
+    /// to the first bytes of the bdev UUID.
     pub fn add_namespace<T>(
         &self,
         bdev: &Bdev<T>,
         ptpl: Option<&std::path::PathBuf>,
+        identity: &NvmeIdentity,
     ) -> Result<(), Error>
     where
         T: spdk_rs::BdevOps,
     {
+        let uuid = *bdev.uuid().as_bytes();
         let opts = spdk_nvmf_ns_opts {
-            nguid: *bdev.uuid().as_bytes(),
+            nguid: identity.nguid.unwrap_or(uuid),
+            eui64: identity.eui64.unwrap_or_else(|| {
+                let mut eui64 = [0u8; 8];
+                eui64.copy_from_slice(&uuid[.. 8]);
+                eui64
+            }),
             ..Default::default()
         };
         let bdev_cname = CString::new(bdev.name()).unwrap();
@@ -728,6 +776,58 @@ impl NvmfSubsystem {
         })
     }
 
+    /// Get the host nqn's of the controllers connected to the subsystem.
+    pub fn connected_hosts(&self) -> Vec<String> {
+        let mut hosts = Vec::new();
+
+        let mut ctrlr = unsafe { self.0.as_ref().ctrlrs.tqh_first };
+        while !ctrlr.is_null() {
+            let host = SpdkNvmfController::from(ctrlr).hostnqn();
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+            ctrlr = unsafe { (*ctrlr).link.tqe_next };
+        }
+
+        hosts
+    }
+
+    /// Disallow all hosts from connecting to the subsystem and disconnect the
+    /// connected ones. New connections are rejected with an invalid host
+    /// status until hosts are allowed again.
+    pub async fn disallow_all_hosts(&self) -> Result<(), Error> {
+        self.allow_any(false);
+        self.disallow_hosts(&self.allowed_hosts())?;
+
+        for host in self.connected_hosts() {
+            self.disconnect_host(&host).await?;
+        }
+        Ok(())
+    }
+
+    /// Enable or disable the write protection of the namespace of the
+    /// subsystem. Writes to a write protected namespace fail with the
+    /// Namespace Is Write Protected status.
+    pub fn set_write_protect(&self, enable: bool) -> Result<(), Error> {
+        let ns = unsafe { spdk_nvmf_subsystem_get_first_ns(self.0.as_ptr()) };
+
+        if ns.is_null() {
+            return Err(Error::Subsystem {
+                source: Errno::ENODEV,
+                nqn: self.get_nqn(),
+                msg: "subsystem has no namespace".to_string(),
+            });
+        }
+
+        unsafe { spdk_nvmf_ns_set_write_protect(ns, enable) }.to_result(
+            |errno| Error::Subsystem {
+                source: Errno::from_i32(errno),
+                nqn: self.get_nqn(),
+                msg: format!("failed to set write protection to {enable}"),
+            },
+        )
+    }
+
     /// enable Asymmetric Namespace Access (ANA) reporting
     pub fn set_ana_reporting(&self, enable: bool) -> Result<(), Error> {
         match std::env::var("NEXUS_NVMF_ANA_ENABLE") {
@@ -774,22 +874,28 @@ impl NvmfSubsystem {
 
     // we currently allow all listeners to the subsystem
     async fn add_listener(&self) -> Result<(), Error> {
-        extern "C" fn listen_cb(arg: *mut c_void, status: i32) {
-            let s = unsafe { Box::from_raw(arg as *mut oneshot::Sender<i32>) };
-            s.send(status).unwrap();
-        }
-
         let cfg = Config::get();
 
         // dont yet enable both ports, IOW just add one transportID now
 
         let trid_replica = TransportId::new(cfg.nexus_opts.nvmf_replica_port);
 
+        self.add_listener_trid(&trid_replica).await
+    }
+
+    /// Adds a listener for the given transport ID to the subsystem, which must
+    /// be inactive or paused.
+    async fn add_listener_trid(&self, trid: &TransportId) -> Result<(), Error> {
+        extern "C" fn listen_cb(arg: *mut c_void, status: i32) {
+            let s = unsafe { Box::from_raw(arg as *mut oneshot::Sender<i32>) };
+            s.send(status).unwrap();
+        }
+
         let (s, r) = oneshot::channel::<i32>();
         unsafe {
             spdk_nvmf_subsystem_add_listener(
                 self.0.as_ptr(),
-                trid_replica.as_ptr(),
+                trid.as_ptr(),
                 Some(listen_cb),
                 cb_arg(s),
             );
@@ -798,11 +904,62 @@ impl NvmfSubsystem {
         r.await.expect("listener callback gone").to_result(|e| {
             Error::Transport {
                 source: Errno::from_i32(e),
-                msg: "Failed to add listener".to_string(),
+                msg: format!("Failed to add listener {trid}"),
             }
         })
     }
 
+    /// Removes the listener of the given transport ID from the subsystem,
+    /// which must be inactive or paused. The hosts connected through it are
+    /// disconnected.
+    fn remove_listener_trid(&self, trid: &TransportId) -> Result<(), Error> {
+        unsafe {
+            spdk_nvmf_subsystem_remove_listener(self.0.as_ptr(), trid.as_ptr())
+        }
+        .to_result(|e| Error::Subsystem {
+            source: Errno::from_i32(e.abs()),
+            nqn: self.get_nqn(),
+            msg: format!("failed to remove listener {trid}"),
+        })
+    }
+
+    /// Adds a listener for the given address to the running subsystem, and
+    /// removes its other listeners if `replace` is set. The subsystem is only
+    /// paused meanwhile, so the hosts connected through the listeners which
+    /// are kept remain connected.
+    pub async fn update_listener(
+        &self,
+        address: &str,
+        port: u16,
+        replace: bool,
+    ) -> Result<(), Error> {
+        let trid = TransportId::with_address(address, port);
+        NVMF_TGT.with(|t| t.borrow().listen_on(&trid))?;
+
+        let current = self.listeners_to_vec().unwrap_or_default();
+        let exists = current.iter().any(|t| t.to_string() == trid.to_string());
+
+        self.pause().await?;
+
+        let mut res = Ok(());
+        if !exists {
+            res = self.add_listener_trid(&trid).await;
+        }
+        if res.is_ok() && replace {
+            res = current
+                .iter()
+                .filter(|t| t.to_string() != trid.to_string())
+                .try_for_each(|t| self.remove_listener_trid(t));
+        }
+
+        self.resume().await?;
+
+        if res.is_ok() {
+            info!(?self, "Subsystem listening on {trid}, replace: {replace}");
+        }
+        res
+    }
+
     /// TODO
     async fn change_state(
         &self,
@@ -934,8 +1091,7 @@ impl NvmfSubsystem {
 
     /// get ANA state
     pub async fn get_ana_state(&self) -> Result<u32, Error> {
-        let cfg = Config::get();
-        let trid_replica = TransportId::new(cfg.nexus_opts.nvmf_replica_port);
+        let trid_replica = self.first_listener_trid();
         let listener = unsafe {
             nvmf_subsystem_find_listener(self.0.as_ptr(), trid_replica.as_ptr())
         };
@@ -956,28 +1112,46 @@ impl NvmfSubsystem {
             let s = unsafe { Box::from_raw(arg as *mut oneshot::Sender<i32>) };
             s.send(status).unwrap();
         }
-        let cfg = Config::get();
-        let trid_replica = TransportId::new(cfg.nexus_opts.nvmf_replica_port);
 
-        let (s, r) = oneshot::channel::<i32>();
+        let listeners = self
+            .listeners_to_vec()
+            .unwrap_or_else(|| vec![self.first_listener_trid()]);
 
-        unsafe {
-            nvmf_subsystem_set_ana_state(
-                self.0.as_ptr(),
-                trid_replica.as_ptr(),
-                ana_state,
-                0,
-                Some(set_ana_state_cb),
-                cb_arg(s),
-            );
+        for trid in listeners {
+            let (s, r) = oneshot::channel::<i32>();
+
+            unsafe {
+                nvmf_subsystem_set_ana_state(
+                    self.0.as_ptr(),
+                    trid.as_ptr(),
+                    ana_state,
+                    0,
+                    Some(set_ana_state_cb),
+                    cb_arg(s),
+                );
+            }
+
+            r.await
+                .expect("Cancellation is not supported")
+                .to_result(|e| Error::Subsystem {
+                    source: Errno::from_i32(-e),
+                    nqn: self.get_nqn(),
+                    msg: format!(
+                        "failed to set_ana_state of the subsystem on {trid}"
+                    ),
+                })?;
         }
 
-        r.await
-            .expect("Cancellation is not supported")
-            .to_result(|e| Error::Subsystem {
-                source: Errno::from_i32(-e),
-                nqn: self.get_nqn(),
-                msg: "failed to set_ana_state of the subsystem".to_string(),
+        Ok(())
+    }
+
+    /// Returns the transport ID of the first listener of the subsystem, or the
+    /// default one if it has no listener.
+    fn first_listener_trid(&self) -> TransportId {
+        self.listeners_to_vec()
+            .and_then(|v| v.into_iter().next())
+            .unwrap_or_else(|| {
+                TransportId::new(Config::get().nexus_opts.nvmf_replica_port)
             })
     }
 
@@ -1027,13 +1201,20 @@ impl NvmfSubsystem {
         })
     }
 
-    /// lookup a subsystem by its UUID
+    /// lookup a subsystem by its UUID. A subsystem created with a custom
+    /// NQN is found by the name of its bdev instead.
     pub fn nqn_lookup(uuid: &str) -> Option<NvmfSubsystem> {
         let nqn = make_nqn(uuid);
         NvmfSubsystem::first()
             .unwrap()
             .into_iter()
             .find(|s| s.get_nqn() == nqn)
+            .or_else(|| {
+                NvmfSubsystem::first().unwrap().into_iter().find(|s| {
+                    s.subtype() == SubType::Nvme
+                        && s.bdev().map_or(false, |b| b.name() == uuid)
+                })
+            })
     }
 
     /// get the bdev associated with this subsystem -- we implicitly assume the
@@ -1048,7 +1229,7 @@ impl NvmfSubsystem {
         Bdev::checked_from_ptr(unsafe { spdk_nvmf_ns_get_bdev(ns) })
     }
 
-    fn listeners_to_vec(&self) -> Option<Vec<TransportId>> {
+    pub(super) fn listeners_to_vec(&self) -> Option<Vec<TransportId>> {
         unsafe {
             let mut listener =
                 spdk_nvmf_subsystem_get_first_listener(self.0.as_ptr());
@@ -7,7 +7,6 @@ use std::{
 use nix::errno::Errno;
 
 use spdk_rs::libspdk::{
-    spdk_env_get_core_count,
     spdk_nvmf_listen_opts,
     spdk_nvmf_listen_opts_init,
     spdk_nvmf_poll_group_destroy,
@@ -27,7 +26,7 @@ use spdk_rs::libspdk::{
 
 use crate::{
     constants::NVME_CONTROLLER_MODEL_ID,
-    core::{Cores, Mthread, Reactor, Reactors},
+    core::{Cores, Mthread, Numa, Reactor, Reactors},
     ffihelper::{AsStr, FfiResult},
     subsys::{
         nvmf::{
@@ -54,6 +53,8 @@ pub struct Target {
     pub(crate) tgt: NonNull<spdk_nvmf_tgt>,
     /// the number of poll groups created for this target
     poll_group_count: u16,
+    /// the number of poll groups to create for this target
+    poll_group_expected: u16,
     /// The current state of the target
     next_state: TargetState,
 }
@@ -100,6 +101,7 @@ impl Target {
         Self {
             tgt: NonNull::dangling(),
             poll_group_count: 0,
+            poll_group_expected: 0,
             next_state: TargetState::Init,
         }
     }
@@ -184,16 +186,41 @@ impl Target {
         })
     }
 
-    /// init the poll groups per core
-    fn init_poll_groups(&self) {
-        Reactors::iter().for_each(|r| {
-            if let Some(t) = Mthread::new(
-                format!("mayastor_nvmf_tcp_pg_core_{}", r.core()),
-                r.core(),
-            ) {
-                r.send_future(Self::create_poll_group(self.tgt.as_ptr(), t));
+    /// init the poll groups per core, or only on the cores of the NUMA node
+    /// of the NVMf target interface when NUMA placement is enabled
+    fn init_poll_groups(&mut self) {
+        let cores = Self::poll_group_cores();
+        self.poll_group_expected = cores.len() as u16;
+
+        Reactors::iter()
+            .filter(|r| cores.contains(&r.core()))
+            .for_each(|r| {
+                if let Some(t) = Mthread::new(
+                    format!("mayastor_nvmf_tcp_pg_core_{}", r.core()),
+                    r.core(),
+                ) {
+                    r.send_future(Self::create_poll_group(
+                        self.tgt.as_ptr(),
+                        t,
+                    ));
+                }
+            });
+    }
+
+    /// Returns the cores to create the poll groups on.
+    fn poll_group_cores() -> Vec<u32> {
+        if let Some(node) = Numa::nvmf_tgt_node() {
+            let cores = Numa::cores(node);
+            if !cores.is_empty() {
+                info!(
+                    "Creating NVMf poll groups on the cores {cores:?} of \
+                    NUMA node {node}"
+                );
+                return cores;
             }
-        });
+            warn!("No reactor core on NUMA node {node} of NVMf target");
+        }
+        Reactors::iter().map(|r| r.core()).collect()
     }
 
     /// init the poll groups implementation
@@ -206,9 +233,7 @@ impl Target {
                     let mut tgt = tgt.borrow_mut();
                     NVMF_PGS.with(|p| p.borrow_mut().push(pg));
                     tgt.poll_group_count += 1;
-                    if tgt.poll_group_count
-                        == unsafe { spdk_env_get_core_count() as u16 }
-                    {
+                    if tgt.poll_group_count == tgt.poll_group_expected {
                         Reactors::master().send_future(async {
                             NVMF_TGT.with(|tgt| {
                                 tgt.borrow_mut().next_state();
@@ -270,6 +295,34 @@ impl Target {
         Ok(())
     }
 
+    /// Listen for incoming connections on the given transport ID, in addition
+    /// to the default listeners.
+    pub(crate) fn listen_on(&self, trid: &TransportId) -> Result<()> {
+        let mut opts = spdk_nvmf_listen_opts::default();
+        unsafe {
+            spdk_nvmf_listen_opts_init(
+                &mut opts,
+                std::mem::size_of::<spdk_nvmf_listen_opts>() as u64,
+            );
+        }
+        let rc = unsafe {
+            spdk_nvmf_tgt_listen_ext(
+                self.tgt.as_ptr(),
+                trid.as_ptr(),
+                &mut opts,
+            )
+        };
+
+        if rc != 0 {
+            return Err(Error::Transport {
+                source: Errno::from_i32(rc.abs()),
+                msg: format!("failed to listen on {trid}"),
+            });
+        }
+        info!("nvmf target listening on {trid}");
+        Ok(())
+    }
+
     /// enable discovery for the target -- note that the discovery system is not
     /// started
     fn enable_discovery(&self) {
@@ -353,6 +406,12 @@ impl Target {
         })
     }
 
+    /// Checks if the target has completed its initialization and serves
+    /// subsystems.
+    pub(crate) fn is_running(&self) -> bool {
+        self.next_state == TargetState::Running
+    }
+
     /// Final state for the target during init.
     pub fn running(&mut self) {
         self.enable_discovery();
@@ -1,5 +1,5 @@
 use std::{
-    ffi::CString,
+    ffi::{CStr, CString},
     fmt::{Debug, Display, Formatter},
     ops::{Deref, DerefMut},
 };
@@ -12,8 +12,17 @@ use spdk_rs::{
     ffihelper::{copy_cstr_with_null, copy_str_with_null},
     libspdk::{
         spdk_nvme_transport_id,
+        spdk_nvmf_get_transport_name,
+        spdk_nvmf_get_transport_opts,
+        spdk_nvmf_tgt,
         spdk_nvmf_tgt_add_transport,
+        spdk_nvmf_tgt_get_transport,
+        spdk_nvmf_transport,
         spdk_nvmf_transport_create,
+        spdk_nvmf_transport_get_first,
+        spdk_nvmf_transport_get_next,
+        spdk_nvmf_transport_opts,
+        spdk_nvmf_transport_opts_init,
         SPDK_NVME_TRANSPORT_TCP,
         SPDK_NVMF_ADRFAM_IPV4,
         SPDK_NVMF_TRSVCID_MAX_LEN,
@@ -24,7 +33,7 @@ use crate::{
     core::MayastorEnvironment,
     ffihelper::{cb_arg, done_errno_cb, AsStr, ErrnoResult, FfiResult},
     subsys::{
-        nvmf::{Error, NVMF_TGT},
+        nvmf::{Error, NVMF_PGS, NVMF_TGT},
         Config,
     },
 };
@@ -32,12 +41,133 @@ use crate::{
 static TCP_TRANSPORT: Lazy<CString> =
     Lazy::new(|| CString::new("TCP").unwrap());
 
+/// Type of an NVMf transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NvmfTransportType {
+    Tcp,
+    Rdma,
+}
+
+impl NvmfTransportType {
+    /// Returns the SPDK name of the transport type.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "TCP",
+            Self::Rdma => "RDMA",
+        }
+    }
+}
+
+impl Display for NvmfTransportType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Options of an NVMf transport to create. The options left unset take the
+/// SPDK defaults of the transport type.
+#[derive(Debug, Clone)]
+pub struct NvmfTransportOpts {
+    /// Type of the transport.
+    pub trtype: NvmfTransportType,
+    /// Maximum queue depth of the I/O queues.
+    pub max_queue_depth: Option<u16>,
+    /// Maximum I/O size in bytes.
+    pub max_io_size: Option<u32>,
+    /// I/O unit size in bytes.
+    pub io_unit_size: Option<u32>,
+    /// Maximum in-capsule data size in bytes.
+    pub in_capsule_data_size: Option<u32>,
+    /// Number of buffers shared by the poll groups.
+    pub num_shared_buffers: Option<u32>,
+    /// Number of buffers cached per poll group.
+    pub buf_cache_size: Option<u32>,
+}
+
+impl NvmfTransportOpts {
+    /// Overrides the given SPDK options with the ones set.
+    fn apply(&self, opts: &mut spdk_nvmf_transport_opts) {
+        if let Some(v) = self.max_queue_depth {
+            opts.max_queue_depth = v;
+        }
+        if let Some(v) = self.max_io_size {
+            opts.max_io_size = v;
+        }
+        if let Some(v) = self.io_unit_size {
+            opts.io_unit_size = v;
+        }
+        if let Some(v) = self.in_capsule_data_size {
+            opts.in_capsule_data_size = v;
+        }
+        if let Some(v) = self.num_shared_buffers {
+            opts.num_shared_buffers = v;
+        }
+        if let Some(v) = self.buf_cache_size {
+            opts.buf_cache_size = v;
+        }
+    }
+}
+
+/// NVMf transport of the target.
+#[derive(Debug, Clone)]
+pub struct NvmfTransportInfo {
+    /// Name of the transport type.
+    pub trtype: String,
+    /// Maximum queue depth of the I/O queues.
+    pub max_queue_depth: u16,
+    /// Maximum I/O size in bytes.
+    pub max_io_size: u32,
+    /// I/O unit size in bytes.
+    pub io_unit_size: u32,
+    /// Maximum in-capsule data size in bytes.
+    pub in_capsule_data_size: u32,
+    /// Number of buffers shared by the poll groups.
+    pub num_shared_buffers: u32,
+    /// Number of buffers cached per poll group.
+    pub buf_cache_size: u32,
+    /// Number of poll groups the transport is polled by.
+    pub poll_groups: u32,
+}
+
+impl NvmfTransportInfo {
+    fn new(transport: *mut spdk_nvmf_transport) -> Self {
+        let (trtype, opts) = unsafe {
+            (
+                CStr::from_ptr(spdk_nvmf_get_transport_name(transport))
+                    .to_string_lossy()
+                    .into_owned(),
+                &*spdk_nvmf_get_transport_opts(transport),
+            )
+        };
+
+        Self {
+            trtype,
+            max_queue_depth: opts.max_queue_depth,
+            max_io_size: opts.max_io_size,
+            io_unit_size: opts.io_unit_size,
+            in_capsule_data_size: opts.in_capsule_data_size,
+            num_shared_buffers: opts.num_shared_buffers,
+            buf_cache_size: opts.buf_cache_size,
+            poll_groups: NVMF_PGS.with(|p| p.borrow().len() as u32),
+        }
+    }
+}
+
 pub async fn add_tcp_transport() -> Result<(), Error> {
     let cfg = Config::get();
-    let mut opts = cfg.nvmf_tcp_tgt_conf.opts.into();
-    let transport = unsafe {
-        spdk_nvmf_transport_create(TCP_TRANSPORT.as_ptr(), &mut opts)
-    };
+    add_transport(&TCP_TRANSPORT, cfg.nvmf_tcp_tgt_conf.opts.into()).await?;
+
+    debug!("Added TCP nvmf transport");
+    Ok(())
+}
+
+/// Creates a transport with the given options and adds it to the target.
+async fn add_transport(
+    name: &CStr,
+    mut opts: spdk_nvmf_transport_opts,
+) -> Result<(), Error> {
+    let transport =
+        unsafe { spdk_nvmf_transport_create(name.as_ptr(), &mut opts) };
 
     transport.to_result(|_| Error::Transport {
         source: Errno::UnknownErrno,
@@ -56,10 +186,82 @@ pub async fn add_tcp_transport() -> Result<(), Error> {
         })
     };
 
-    let _result = r.await.unwrap();
+    r.await
+        .expect("done callback dropped")
+        .map_err(|source| Error::Transport {
+            source,
+            msg: format!("failed to add transport {}", name.to_string_lossy()),
+        })
+}
 
-    debug!("Added TCP nvmf transport");
-    Ok(())
+/// Returns the target, once it serves subsystems.
+pub(super) fn running_target() -> Result<*mut spdk_nvmf_tgt, Error> {
+    NVMF_TGT.with(|t| {
+        let t = t.borrow();
+        if t.is_running() {
+            Ok(t.tgt.as_ptr())
+        } else {
+            Err(Error::Transport {
+                source: Errno::EAGAIN,
+                msg: "nvmf target is not running".into(),
+            })
+        }
+    })
+}
+
+/// Creates an NVMf transport and adds it to the target, so that subsystems
+/// can listen on its fabric.
+pub async fn create_transport(
+    opts: NvmfTransportOpts,
+) -> Result<NvmfTransportInfo, Error> {
+    let tgt = running_target()?;
+    let name = CString::new(opts.trtype.as_str()).unwrap();
+
+    if !unsafe { spdk_nvmf_tgt_get_transport(tgt, name.as_ptr()) }.is_null() {
+        return Err(Error::Transport {
+            source: Errno::EEXIST,
+            msg: format!("{} transport already exists", opts.trtype),
+        });
+    }
+
+    let mut spdk_opts = spdk_nvmf_transport_opts::default();
+    let found = unsafe {
+        spdk_nvmf_transport_opts_init(
+            name.as_ptr(),
+            &mut spdk_opts,
+            std::mem::size_of::<spdk_nvmf_transport_opts>() as u64,
+        )
+    };
+    if !found {
+        return Err(Error::Transport {
+            source: Errno::EOPNOTSUPP,
+            msg: format!("{} transport is not supported", opts.trtype),
+        });
+    }
+    opts.apply(&mut spdk_opts);
+
+    add_transport(&name, spdk_opts).await?;
+    info!("Added {} nvmf transport: {opts:?}", opts.trtype);
+
+    let transport = unsafe { spdk_nvmf_tgt_get_transport(tgt, name.as_ptr()) };
+    transport.to_result(|_| Error::Transport {
+        source: Errno::ENOENT,
+        msg: format!("{} transport not found", opts.trtype),
+    })?;
+    Ok(NvmfTransportInfo::new(transport))
+}
+
+/// Lists the NVMf transports of the target.
+pub fn list_transports() -> Result<Vec<NvmfTransportInfo>, Error> {
+    let tgt = running_target()?;
+
+    let mut transports = Vec::new();
+    let mut transport = unsafe { spdk_nvmf_transport_get_first(tgt) };
+    while !transport.is_null() {
+        transports.push(NvmfTransportInfo::new(transport));
+        transport = unsafe { spdk_nvmf_transport_get_next(transport) };
+    }
+    Ok(transports)
 }
 
 pub struct TransportId(pub(crate) spdk_nvme_transport_id);
@@ -79,8 +281,11 @@ impl DerefMut for TransportId {
 
 impl TransportId {
     pub fn new(port: u16) -> Self {
-        let address = get_ipv4_address().unwrap();
+        Self::with_address(&get_ipv4_address().unwrap(), port)
+    }
 
+    /// Transport ID of the given IPv4 address and port.
+    pub fn with_address(address: &str, port: u16) -> Self {
         let mut trid = spdk_nvme_transport_id {
             trtype: SPDK_NVME_TRANSPORT_TCP,
             adrfam: SPDK_NVMF_ADRFAM_IPV4,
@@ -91,7 +296,7 @@ impl TransportId {
         assert!(port.len() < SPDK_NVMF_TRSVCID_MAX_LEN as usize);
 
         copy_cstr_with_null(&TCP_TRANSPORT, &mut trid.trstring);
-        copy_str_with_null(&address, &mut trid.traddr);
+        copy_str_with_null(address, &mut trid.traddr);
         copy_str_with_null(&port, &mut trid.trsvcid);
 
         Self(trid)
@@ -26,6 +26,37 @@ pub enum ApiVersion {
     V0,
     /// V1 version of api
     V1,
+    /// Experimental version of api, which may change incompatibly.
+    V2Alpha,
+}
+
+impl ApiVersion {
+    /// Checks if the version is experimental, and must be enabled
+    /// explicitly.
+    pub fn is_experimental(&self) -> bool {
+        matches!(self, Self::V2Alpha)
+    }
+
+    /// Checks if the version has services to serve. The experimental v2alpha
+    /// API has none yet, so it is neither served nor advertised.
+    pub fn has_services(&self) -> bool {
+        !matches!(self, Self::V2Alpha)
+    }
+
+    /// Returns the given versions which have services to serve, so that only
+    /// these are served and advertised to the control plane.
+    pub fn served(versions: &[ApiVersion]) -> Vec<ApiVersion> {
+        versions
+            .iter()
+            .filter(|v| {
+                if !v.has_services() {
+                    warn!("The {v:?} gRPC API has no services, not serving it");
+                }
+                v.has_services()
+            })
+            .cloned()
+            .collect()
+    }
 }
 
 impl FromStr for ApiVersion {
@@ -35,6 +66,7 @@ impl FromStr for ApiVersion {
         match s.to_lowercase().as_str() {
             "v0" => Ok(Self::V0),
             "v1" => Ok(Self::V1),
+            "v2alpha" => Ok(Self::V2Alpha),
             _ => Err(format!("The version : {s} entered is not supported")),
         }
     }
@@ -259,6 +291,7 @@ impl From<ApiVersion> for mayastor_api::v1::registration::ApiVersion {
         match api_version {
             ApiVersion::V0 => Self::V0,
             ApiVersion::V1 => Self::V1,
+            ApiVersion::V2Alpha => Self::V2alpha,
         }
     }
 }
@@ -0,0 +1,57 @@
+pub mod common;
+
+use common::{
+    bdev::list_bdevs,
+    compose::{
+        rpc::v1::{
+            bdev::{DataProtection, ProtectionType},
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+};
+
+/// The per-block metadata of the bdevs is reported, and the pools report the
+/// metadata of their base bdev.
+#[tokio::test]
+async fn bdev_data_protection() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool_0 = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 60);
+    pool_0.create().await.unwrap();
+
+    // Malloc bdevs are formatted without any per-block metadata.
+    let unprotected = DataProtection {
+        md_size: 0,
+        md_interleaved: false,
+        pi_type: ProtectionType::None as i32,
+    };
+
+    let bdevs = list_bdevs(&ms_0).await.unwrap();
+    let mem0 = bdevs.iter().find(|b| b.name == "mem0").unwrap();
+    assert_eq!(mem0.data_protection, Some(unprotected.clone()), "{mem0:?}");
+
+    let pool = pool_0.get_pool().await.unwrap();
+    assert_eq!(pool.data_protection, Some(unprotected), "{pool:?}");
+}
@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+pub mod common;
+
+use common::MayastorTest;
+use io_engine::{
+    bdev::{
+        device_create,
+        device_destroy_with,
+        device_destroying,
+        device_open_handles,
+        DestroyMode,
+        DestroyOutcome,
+    },
+    core::{CoreError, MayastorCliArgs, UntypedBdev},
+    sleep::mayastor_sleep,
+};
+use nix::errno::Errno;
+
+const URI: &str = "malloc:///d0?size_mb=8";
+const NAME: &str = "d0";
+
+/// A device with open handles is destroyed once they are all closed, and
+/// cannot be opened again in the meantime.
+#[tokio::test]
+async fn bdev_destroy_deferred() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        device_create(URI).await.unwrap();
+        let bdev = UntypedBdev::lookup_by_name(NAME).unwrap();
+        let first = bdev.open(true).unwrap();
+        let second = bdev.open(false).unwrap();
+        assert_eq!(device_open_handles(NAME), 2);
+
+        let outcome = device_destroy_with(URI, DestroyMode::Deferred)
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            DestroyOutcome::Deferred {
+                open_handles: 2
+            }
+        );
+        assert!(device_destroying(NAME));
+        assert!(matches!(
+            bdev.open(true),
+            Err(CoreError::OpenBdev {
+                source: Errno::ENODEV
+            })
+        ));
+
+        drop(first);
+        mayastor_sleep(Duration::from_millis(100)).await.unwrap();
+        assert!(UntypedBdev::lookup_by_name(NAME).is_some());
+        assert_eq!(device_open_handles(NAME), 1);
+
+        // The last handle destroys the device.
+        drop(second);
+        for _ in 0 .. 50 {
+            if UntypedBdev::lookup_by_name(NAME).is_none() {
+                break;
+            }
+            mayastor_sleep(Duration::from_millis(100)).await.unwrap();
+        }
+        assert!(UntypedBdev::lookup_by_name(NAME).is_none());
+        assert!(!device_destroying(NAME));
+        assert_eq!(device_open_handles(NAME), 0);
+
+        // Without open handles, the device is destroyed at once.
+        for mode in [DestroyMode::Deferred, DestroyMode::Force] {
+            device_create(URI).await.unwrap();
+            let outcome = device_destroy_with(URI, mode).await.unwrap();
+            assert_eq!(outcome, DestroyOutcome::Destroyed);
+            assert!(UntypedBdev::lookup_by_name(NAME).is_none());
+        }
+
+        assert!(device_destroy_with("bogus:///d0", DestroyMode::Deferred)
+            .await
+            .is_err());
+    })
+    .await;
+}
@@ -0,0 +1,136 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            stats::{
+                BdevHistogram,
+                EnableBdevHistogramRequest,
+                GetBdevHistogramRequest,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn enable_histogram(
+    rpc: &SharedRpcHandle,
+    name: &str,
+    enable: bool,
+) -> Result<(), Status> {
+    rpc.lock()
+        .await
+        .stats
+        .enable_bdev_histogram(EnableBdevHistogramRequest {
+            name: name.to_string(),
+            enable,
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn get_histogram(
+    rpc: &SharedRpcHandle,
+    name: &str,
+) -> Result<BdevHistogram, Status> {
+    rpc.lock()
+        .await
+        .stats
+        .get_bdev_histogram(GetBdevHistogramRequest {
+            name: name.to_string(),
+        })
+        .await
+        .map(|r| r.into_inner().histogram.unwrap())
+}
+
+/// The SPDK latency histogram of a bdev counts the I/Os submitted once it is
+/// enabled, and cannot be fetched once disabled.
+#[tokio::test]
+async fn bdev_histogram() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repl);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    enable_histogram(&ms_0, "nexus0", true).await.unwrap();
+    let histogram = get_histogram(&ms_0, "nexus0").await.unwrap();
+    assert!(histogram.tick_rate > 0);
+    assert_eq!(histogram.total, 0);
+
+    test_write_to_nexus(&nex, DataSize::from_bytes(0), 8, DataSize::from_kb(4))
+        .await
+        .unwrap();
+
+    let histogram = get_histogram(&ms_0, "nexus0").await.unwrap();
+    assert!(histogram.total >= 8, "{histogram:?}");
+    assert_eq!(
+        histogram.buckets.iter().map(|b| b.count).sum::<u64>(),
+        histogram.total
+    );
+    assert!(histogram.buckets.iter().all(|b| b.count > 0));
+    assert!(histogram
+        .buckets
+        .windows(2)
+        .all(|w| w[0].start_us <= w[1].start_us));
+
+    // Disabling the histogram discards its data.
+    enable_histogram(&ms_0, "nexus0", false).await.unwrap();
+    assert!(get_histogram(&ms_0, "nexus0").await.is_err());
+
+    assert_eq!(
+        enable_histogram(&ms_0, "nobdev", true)
+            .await
+            .unwrap_err()
+            .code(),
+        Code::NotFound
+    );
+    assert_eq!(
+        get_histogram(&ms_0, "nobdev").await.unwrap_err().code(),
+        Code::NotFound
+    );
+}
@@ -0,0 +1,37 @@
+use io_engine::bdev_api::bdev_uri_normalize;
+
+#[test]
+fn bdev_uri_normalize_same_device() {
+    let uris = [
+        "aio:///tmp/disk1.img?uuid=2E2A1A5C-7AF8-44A7-B3AE-05390BE75D83",
+        "aio:///tmp/disk1.img?blk_size=512&uuid=2e2a1a5c-7af8-44a7-b3ae-05390be75d83",
+        "AIO:///tmp/disk1.img?uuid=2e2a1a5c7af844a7b3ae05390be75d83&blk_size=512",
+    ];
+    let normalized = uris
+        .iter()
+        .map(|uri| bdev_uri_normalize(uri).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        normalized[0],
+        "aio:///tmp/disk1.img?uuid=2e2a1a5c-7af8-44a7-b3ae-05390be75d83"
+    );
+    assert!(normalized.iter().all(|uri| uri == &normalized[0]));
+
+    let a = bdev_uri_normalize(
+        "nvmf://Node-1:8420/nqn.2019-05.io.openebs:replica?reftag=no&guard=1",
+    )
+    .unwrap();
+    let b = bdev_uri_normalize(
+        "nvmf://node-1/nqn.2019-05.io.openebs:replica?guard=yes",
+    )
+    .unwrap();
+    assert_eq!(a, b);
+    assert_eq!(a, "nvmf://node-1/nqn.2019-05.io.openebs:replica?guard=true");
+
+    // distinct devices stay distinct
+    assert_ne!(
+        bdev_uri_normalize("aio:///tmp/disk1.img?blk_size=4096").unwrap(),
+        bdev_uri_normalize("aio:///tmp/disk1.img").unwrap()
+    );
+    assert!(bdev_uri_normalize("aio:///tmp/disk1.img?bogus=1").is_err());
+}
@@ -0,0 +1,114 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{bdev::ListBdevOptions, GrpcConnect},
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::Code;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+static DISK_SIZE: u64 = 32;
+
+fn malloc(name: &str, block_size: u64) -> String {
+    format!("malloc:///{name}?size_mb={DISK_SIZE}&blk_size={block_size}")
+}
+
+/// Nexuses and replicas created with an explicit logical block size only
+/// accept children and pools of that block size.
+#[tokio::test]
+async fn block_size() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    // The pool has the 512 bytes blocks of its malloc disk.
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+    for (name, block_size) in [("r0", 0), ("r1", 512)] {
+        let mut repl = ReplicaBuilder::new(ms_0.clone())
+            .with_pool(&pool)
+            .with_name(name)
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_block_size(block_size);
+        repl.create().await.unwrap();
+    }
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r2")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_block_size(4096);
+    let err = repl.create().await.unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    let mut nex_0 = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_block_size(4096)
+        .with_bdev(&malloc("m0", 4096));
+    nex_0.create().await.unwrap();
+    let bdevs = ms_0
+        .lock()
+        .await
+        .bdev
+        .list(ListBdevOptions {
+            name: Some(nex_0.name()),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .bdevs;
+    assert_eq!(bdevs.len(), 1);
+    assert_eq!(bdevs[0].blk_size, 4096);
+
+    // A child of another block size cannot be added.
+    let err = nex_0.add_child(&malloc("m1", 512), true).await.unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    assert_eq!(nex_0.get_nexus().await.unwrap().children.len(), 1);
+
+    // Nor can a nexus be created over such children, or with a block size
+    // other than 512 or 4096 bytes.
+    for (i, (block_size, children)) in [
+        (4096, vec![malloc("m2", 512)]),
+        (0, vec![malloc("m3", 512), malloc("m4", 4096)]),
+        (1000, vec![malloc("m5", 512)]),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let mut nex = NexusBuilder::new(ms_0.clone())
+            .with_name(&format!("nexus_bad{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_block_size(block_size)
+            .with_children(children);
+        let err = nex.create().await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument, "{block_size}");
+    }
+}
@@ -0,0 +1,354 @@
+pub mod common;
+
+use std::process::Command;
+
+use common::{
+    compose::{rpc::v1::GrpcConnect, Binary, Builder, ComposeTest},
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::{list_replicas, ReplicaBuilder},
+};
+use uuid::Uuid;
+
+static CLIENT: &str = env!("CARGO_BIN_EXE_io-engine-client");
+static CLIENT_CONFIG: &str = "/tmp/io-engine-client-test.yaml";
+
+/// Starts an io-engine, returning the test and the endpoint of the io-engine.
+async fn start_test() -> (ComposeTest, String) {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let endpoint = format!("http://{}:10124", test.container_ip("ms_0"));
+    (test, endpoint)
+}
+
+/// Runs the client, returning whether it succeeded and what it printed.
+fn run_client(command: &mut Command) -> (bool, String) {
+    let output = command.output().unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+/// Runs the client against the given io-engine.
+fn client(endpoint: &str, args: &[&str]) -> (bool, String) {
+    run_client(Command::new(CLIENT).args(["-b", endpoint]).args(args))
+}
+
+/// The rebuild of a child is followed until it ends, and following a child
+/// which is not rebuilt fails.
+#[tokio::test]
+async fn client_cli_rebuild_watch() {
+    let (test, endpoint) = start_test().await;
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(60)
+        .with_bdev("malloc:///m0?size_mb=64");
+    nex.create().await.unwrap();
+    nex.add_child("malloc:///m1?size_mb=64", false).await.unwrap();
+
+    let uuid = nex.uuid();
+    let (success, output) = client(
+        &endpoint,
+        &["nexus", "rebuild", "watch", &uuid, "malloc:///m1?size_mb=64"],
+    );
+    assert!(success, "{output}");
+    let last = output.rsplit('\r').next().unwrap();
+    assert!(last.contains("100%") && last.contains("completed"), "{output}");
+
+    let (success, output) = client(
+        &endpoint,
+        &["nexus", "rebuild", "watch", &uuid, "malloc:///m0?size_mb=64"],
+    );
+    assert!(!success, "{output}");
+}
+
+/// Snapshots are created, listed with filters and restored into a new
+/// replica.
+#[tokio::test]
+async fn client_cli_snapshot() {
+    let (test, endpoint) = start_test().await;
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 60);
+    pool.create().await.unwrap();
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(8)
+        .with_thin(false);
+    repl.create().await.unwrap();
+
+    let snap_uuid = Uuid::new_v4().to_string();
+    let repl_uuid = repl.uuid();
+    let (success, output) = client(
+        &endpoint,
+        &[
+            "snapshot",
+            "create",
+            "replica",
+            &repl_uuid,
+            "snap0",
+            "e1",
+            "t1",
+            &snap_uuid,
+        ],
+    );
+    assert!(success, "{output}");
+
+    for (filter, listed) in [
+        ("--valid", true),
+        ("--invalid", false),
+        ("--not-discarded", true),
+    ] {
+        let (success, output) =
+            client(&endpoint, &["-o", "json", "snapshot", "list", filter]);
+        assert!(success, "{output}");
+        assert_eq!(output.contains(&snap_uuid), listed, "{filter} {output}");
+    }
+    for (pool_name, listed) in [("pool0", true), ("pool1", false)] {
+        let (success, output) = client(
+            &endpoint,
+            &["-o", "json", "snapshot", "list", "--pool", pool_name],
+        );
+        assert!(success, "{output}");
+        assert_eq!(output.contains(&snap_uuid), listed, "{output}");
+    }
+    let (success, _) =
+        client(&endpoint, &["snapshot", "list", "--valid", "--invalid"]);
+    assert!(!success);
+
+    let clone_uuid = Uuid::new_v4().to_string();
+    let (success, output) = client(
+        &endpoint,
+        &["snapshot", "restore", &snap_uuid, "restored", &clone_uuid],
+    );
+    assert!(success, "{output}");
+    let replicas = list_replicas(ms_0.clone()).await.unwrap();
+    assert!(replicas.iter().any(|r| r.uuid == clone_uuid), "{replicas:?}");
+
+    // Only snapshots are restored.
+    let clone_uuid = Uuid::new_v4().to_string();
+    let (success, _) = client(
+        &endpoint,
+        &["snapshot", "restore", &repl_uuid, "restored2", &clone_uuid],
+    );
+    assert!(!success);
+}
+
+/// The io-engine to connect to is taken from the connection profiles, and
+/// list commands run against the io-engines of all the profiles.
+#[tokio::test]
+async fn client_cli_profiles() {
+    let (_test, endpoint) = start_test().await;
+    std::fs::remove_file(CLIENT_CONFIG).ok();
+    let config = |args: &[&str]| {
+        run_client(
+            Command::new(CLIENT)
+                .env("IO_ENGINE_CLIENT_CONFIG", CLIENT_CONFIG)
+                .args(args),
+        )
+    };
+
+    let (success, output) = config(&[
+        "config",
+        "set-context",
+        "ms_0",
+        "--endpoint",
+        &endpoint,
+        "--default-output",
+        "json",
+    ]);
+    assert!(success, "{output}");
+    assert!(config(&["config", "use-context", "ms_0"]).0);
+    let (success, output) = config(&["config", "get-contexts"]);
+    assert!(success && output.contains(&endpoint), "{output}");
+
+    // The current context gives the endpoint and the output format.
+    let (success, output) = config(&["pool", "list"]);
+    assert!(success, "{output}");
+    assert!(output.contains("\"pools\""), "{output}");
+    let (success, output) = config(&["-o", "default", "pool", "list"]);
+    assert!(success && !output.contains("\"pools\""), "{output}");
+
+    let (success, output) = config(&["--all-nodes", "pool", "list"]);
+    assert!(success, "{output}");
+    assert!(output.contains("\"pools\""), "{output}");
+
+    // Only list commands run against all the nodes, and only known contexts
+    // are used.
+    assert!(!config(&["--all-nodes", "pool", "destroy", "pool0"]).0);
+    assert!(!config(&["--context", "ms_1", "pool", "list"]).0);
+    assert!(!config(&["config", "use-context", "ms_1"]).0);
+
+    assert!(config(&["config", "delete-context", "ms_0"]).0);
+    assert!(!config(&["config", "delete-context", "ms_0"]).0);
+    std::fs::remove_file(CLIENT_CONFIG).ok();
+}
+
+/// List commands print comma-separated values, or additional columns.
+#[tokio::test]
+async fn client_cli_output_formats() {
+    let (test, endpoint) = start_test().await;
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 60);
+    pool.create().await.unwrap();
+
+    let (success, output) = client(&endpoint, &["-o", "csv", "pool", "list"]);
+    assert!(success, "{output}");
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        "NAME,UUID,STATE,CAPACITY,USED,COMMITTED,DISKS",
+        "{output}"
+    );
+    assert!(
+        lines[1].starts_with(&format!("pool0,{},", pool.uuid())),
+        "{output}"
+    );
+    assert_eq!(lines[1].split(',').count(), 7, "{output}");
+
+    let (success, output) = client(&endpoint, &["-o", "wide", "pool", "list"]);
+    assert!(success && output.contains("COMMITMENT_RATIO"), "{output}");
+    let (success, output) = client(&endpoint, &["pool", "list"]);
+    assert!(success && !output.contains("COMMITMENT_RATIO"), "{output}");
+
+    let (success, _) = client(&endpoint, &["-o", "yaml", "pool", "list"]);
+    assert!(!success);
+}
+
+/// Destructive commands only validate their request on a dry run, and are
+/// carried out without a prompt when confirmed beforehand or when the input
+/// is not interactive.
+#[tokio::test]
+async fn client_cli_dry_run() {
+    let (test, endpoint) = start_test().await;
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 60);
+    pool.create().await.unwrap();
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(8)
+        .with_thin(false);
+    repl.create().await.unwrap();
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(4)
+        .with_bdev("malloc:///m0?size_mb=8");
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    let nexus_uuid = nex.uuid();
+    let nexus_uuid = nexus_uuid.as_str();
+    let repl_uuid = repl.uuid();
+    let repl_uuid = repl_uuid.as_str();
+    let pool_uuid = pool.uuid();
+    let unknown = Uuid::new_v4().to_string();
+    let unknown = unknown.as_str();
+
+    for (args, output) in [
+        (
+            vec!["nexus", "destroy", nexus_uuid],
+            format!("nexus: {nexus_uuid} can be destroyed"),
+        ),
+        (
+            vec!["nexus", "unpublish", nexus_uuid],
+            format!("nexus: {nexus_uuid} can be unpublished"),
+        ),
+        (
+            vec![
+                "test",
+                "wipe",
+                "replica",
+                repl_uuid,
+                "--pool-uuid",
+                &pool_uuid,
+            ],
+            format!("replica: {repl_uuid} can be wiped"),
+        ),
+        (
+            vec!["replica", "destroy", repl_uuid],
+            format!("replica: {repl_uuid} can be deleted"),
+        ),
+        (
+            vec!["pool", "destroy", "pool0"],
+            "pool: pool0 can be deleted".to_string(),
+        ),
+    ] {
+        let (success, printed) =
+            client(&endpoint, &[&["-v"][..], &args, &["--dry-run"]].concat());
+        assert!(success, "{args:?}: {printed}");
+        assert!(printed.contains(&output), "{args:?}: {printed}");
+    }
+    let nexus = nex.get_nexus().await.unwrap();
+    assert!(!nexus.device_uri.is_empty(), "{nexus:?}");
+    repl.get_replica().await.unwrap();
+    pool.get_pool().await.unwrap();
+
+    // A dry run fails as the command itself would.
+    for args in [
+        vec!["nexus", "destroy", unknown],
+        vec!["nexus", "unpublish", unknown],
+        vec!["replica", "destroy", unknown],
+        vec!["pool", "destroy", "pool1"],
+    ] {
+        let (success, printed) =
+            client(&endpoint, &[&args[..], &["--dry-run"]].concat());
+        assert!(!success, "{args:?}: {printed}");
+    }
+
+    // Only destructive commands take the confirmation flag.
+    let (success, _) =
+        client(&endpoint, &["nexus", "unpublish", nexus_uuid, "--yes"]);
+    assert!(!success);
+
+    let (success, printed) =
+        client(&endpoint, &["nexus", "destroy", nexus_uuid, "--yes"]);
+    assert!(success, "{printed}");
+    assert!(nex.get_nexus().await.is_err());
+    let (success, printed) =
+        client(&endpoint, &["replica", "destroy", repl_uuid, "--yes"]);
+    assert!(success, "{printed}");
+    assert!(repl.get_replica().await.is_err());
+
+    // Without a terminal to ask for confirmation, the command is carried out.
+    let (success, printed) = client(&endpoint, &["pool", "destroy", "pool0"]);
+    assert!(success, "{printed}");
+    assert!(!printed.contains("[y/N]"), "{printed}");
+    assert!(pool.get_pool().await.is_err());
+}
@@ -0,0 +1,172 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            bdev::{CreateBdevRequest, ListBdevOptions},
+            replica::CreateReplicaRequest,
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::{list_nexuses, NexusBuilder},
+    pool::PoolBuilder,
+    replica::{list_replicas, ReplicaBuilder},
+};
+use tonic::Code;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 8;
+static BAD_CHILD: &str = "aio:///tmp/no_such_disk.img?blk_size=512";
+
+async fn bdev_names(rpc: &SharedRpcHandle) -> Vec<String> {
+    rpc.lock()
+        .await
+        .bdev
+        .list(ListBdevOptions {
+            name: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .bdevs
+        .into_iter()
+        .map(|b| b.name)
+        .collect()
+}
+
+/// A failed nexus creation destroys the child devices it created, but not
+/// the ones which existed before, and a nexus failing to be configured is
+/// destroyed, so that the creation can be retried.
+#[tokio::test]
+async fn nexus_create_rollback() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    ms_0.lock()
+        .await
+        .bdev
+        .create(CreateBdevRequest {
+            uri: "malloc:///existing?size_mb=32".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_bdev("malloc:///created?size_mb=32")
+        .with_bdev("malloc:///existing?size_mb=32")
+        .with_bdev(BAD_CHILD);
+    let err = nex.create().await.unwrap_err();
+    assert!(
+        err.message().contains("rollback of nexus nexus0 completed"),
+        "{err:?}"
+    );
+    let names = bdev_names(&ms_0).await;
+    assert!(!names.contains(&"created".to_string()), "{names:?}");
+    assert!(names.contains(&"existing".to_string()), "{names:?}");
+    assert!(list_nexuses(ms_0.clone()).await.unwrap().is_empty());
+
+    // The rebuild segments are checked once the nexus is created.
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_rebuild_segments(1000, 0)
+        .with_bdev("malloc:///m1?size_mb=32");
+    let err = nex.create().await.unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    assert!(
+        err.message().contains("rollback of nexus nexus1 completed"),
+        "{err:?}"
+    );
+    assert!(list_nexuses(ms_0.clone()).await.unwrap().is_empty());
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus1")
+        .with_uuid(&nex.uuid())
+        .with_size_mb(REPL_SIZE)
+        .with_bdev("malloc:///m1?size_mb=32");
+    nex.create().await.unwrap();
+    assert_eq!(list_nexuses(ms_0.clone()).await.unwrap().len(), 1);
+}
+
+/// A replica failing to be set up once its lvol is created is destroyed, so
+/// that the creation can be retried.
+#[tokio::test]
+async fn replica_create_rollback() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(true);
+
+    // Compression needs a persistent memory path, which is not configured.
+    let err = ms_0
+        .lock()
+        .await
+        .replica
+        .create_replica(CreateReplicaRequest {
+            name: repl.name(),
+            uuid: repl.uuid(),
+            pooluuid: pool.uuid(),
+            size: REPL_SIZE * 1024 * 1024,
+            thin: true,
+            compress: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        err.message().contains("rollback of replica r0 completed"),
+        "{err:?}"
+    );
+    assert!(list_replicas(ms_0.clone()).await.unwrap().is_empty());
+
+    repl.create().await.unwrap();
+    assert_eq!(list_replicas(ms_0.clone()).await.unwrap().len(), 1);
+}
@@ -0,0 +1,77 @@
+pub mod common;
+
+use common::MayastorTest;
+use io_engine::{
+    core::MayastorCliArgs,
+    host::disk_health::get_disk_health,
+    lvs::Lvs,
+    pool_backend::PoolArgs,
+};
+
+const DISK_NAME: &str = "/tmp/disk_health.img";
+
+fn pool_args(name: &str, disk: String) -> PoolArgs {
+    PoolArgs {
+        name: name.to_string(),
+        disks: vec![disk],
+        uuid: None,
+        force: false,
+    }
+}
+
+#[tokio::test]
+async fn disk_health_of_pools() {
+    common::composer_init();
+    common::delete_file(&[DISK_NAME.to_string()]);
+    common::truncate_file(DISK_NAME, 64 * 1024);
+
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        let malloc = Lvs::create_or_import(pool_args(
+            "health_malloc",
+            "malloc:///health_m0?size_mb=64".to_string(),
+        ))
+        .await
+        .unwrap();
+        let aio = Lvs::create_or_import(pool_args(
+            "health_aio",
+            format!("aio://{DISK_NAME}?blk_size=512"),
+        ))
+        .await
+        .unwrap();
+
+        let disks = get_disk_health(None).await;
+        assert_eq!(disks.len(), 2);
+
+        // The health of memory disks cannot be obtained, which is reported
+        // per disk instead of failing the request.
+        let health = get_disk_health(Some("health_malloc")).await;
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].pool, "health_malloc");
+        assert!(health[0].source.is_none());
+        assert!(health[0].temperature.is_none());
+        assert!(health[0].media_errors.is_none());
+        assert_eq!(
+            health[0].error.as_deref(),
+            Some("not supported for 'malloc' disks")
+        );
+
+        // A file is not a block device, so it has no sysfs entry.
+        let health = get_disk_health(Some(&aio.uuid())).await;
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].pool, "health_aio");
+        assert!(health[0].disk.starts_with("aio://"));
+        assert!(health[0].source.is_none());
+        assert!(health[0].error.as_ref().unwrap().contains("no sysfs entry"));
+
+        assert!(get_disk_health(Some("health_none")).await.is_empty());
+
+        malloc.destroy().await.unwrap();
+        aio.destroy().await.unwrap();
+        assert!(get_disk_health(None).await.is_empty());
+    })
+    .await;
+
+    common::delete_file(&[DISK_NAME.to_string()]);
+}
@@ -0,0 +1,231 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{DestroyNexusRequest, UnpublishNexusRequest},
+            pool::DestroyPoolRequest,
+            replica::{destroy_replica_request, DestroyReplicaRequest},
+            test::{
+                wipe_options::WipeMethod,
+                wipe_replica_request,
+                StreamWipeOptions,
+                WipeOptions,
+                WipeReplicaRequest,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+async fn destroy_nexus(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    validate_only: bool,
+) -> Result<(), Status> {
+    rpc.lock()
+        .await
+        .nexus
+        .destroy_nexus(DestroyNexusRequest {
+            uuid: uuid.to_string(),
+            validate_only,
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn unpublish_nexus(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    validate_only: bool,
+) -> Result<(), Status> {
+    rpc.lock()
+        .await
+        .nexus
+        .unpublish_nexus(UnpublishNexusRequest {
+            uuid: uuid.to_string(),
+            validate_only,
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn destroy_pool(
+    rpc: &SharedRpcHandle,
+    name: &str,
+    uuid: Option<String>,
+    validate_only: bool,
+) -> Result<(), Status> {
+    rpc.lock()
+        .await
+        .pool
+        .destroy_pool(DestroyPoolRequest {
+            name: name.to_string(),
+            uuid,
+            validate_only,
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn destroy_replica(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    pool_uuid: &str,
+    validate_only: bool,
+) -> Result<(), Status> {
+    rpc.lock()
+        .await
+        .replica
+        .destroy_replica(DestroyReplicaRequest {
+            uuid: uuid.to_string(),
+            pool: Some(destroy_replica_request::Pool::PoolUuid(
+                pool_uuid.to_string(),
+            )),
+            validate_only,
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+/// Wipes a replica, returning the number of progress reports.
+async fn wipe_replica(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    pool_uuid: &str,
+    validate_only: bool,
+) -> Result<usize, Status> {
+    let mut stream = rpc
+        .lock()
+        .await
+        .test
+        .wipe_replica(WipeReplicaRequest {
+            uuid: uuid.to_string(),
+            pool: Some(wipe_replica_request::Pool::PoolUuid(
+                pool_uuid.to_string(),
+            )),
+            wipe_options: Some(StreamWipeOptions {
+                options: Some(WipeOptions {
+                    wipe_method: WipeMethod::WriteZeroes as i32,
+                    write_pattern: None,
+                }),
+                chunk_size: 0,
+            }),
+            validate_only,
+        })
+        .await?
+        .into_inner();
+    let mut reports = 0;
+    while let Some(_response) = stream.message().await? {
+        reports += 1;
+    }
+    Ok(reports)
+}
+
+/// A request validated only fails as the request itself would, and leaves
+/// its resource as it is.
+#[tokio::test]
+async fn dry_run() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 60);
+    pool.create().await.unwrap();
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(8)
+        .with_thin(false);
+    repl.create().await.unwrap();
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(4)
+        .with_bdev("malloc:///m0?size_mb=8");
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    let unknown = uuid::Uuid::new_v4().to_string();
+
+    // Nexus destruction.
+    destroy_nexus(&ms_0, &nex.uuid(), true).await.unwrap();
+    nex.get_nexus().await.unwrap();
+    let err = destroy_nexus(&ms_0, &unknown, true).await.unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    // Nexus unpublishing.
+    unpublish_nexus(&ms_0, &nex.uuid(), true).await.unwrap();
+    assert!(!nex.get_nexus().await.unwrap().device_uri.is_empty());
+    let err = unpublish_nexus(&ms_0, &unknown, true).await.unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    // Replica wiping.
+    assert_eq!(
+        wipe_replica(&ms_0, &repl.uuid(), &pool.uuid(), true)
+            .await
+            .unwrap(),
+        0
+    );
+    let err = wipe_replica(&ms_0, &unknown, &pool.uuid(), true)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    // Replica destruction.
+    destroy_replica(&ms_0, &repl.uuid(), &pool.uuid(), true).await.unwrap();
+    repl.get_replica().await.unwrap();
+    let err = destroy_replica(&ms_0, &unknown, &pool.uuid(), true)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    // Pool destruction.
+    destroy_pool(&ms_0, "pool0", Some(pool.uuid()), true).await.unwrap();
+    pool.get_pool().await.unwrap();
+    let err = destroy_pool(&ms_0, "pool0", Some(unknown), true)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    let err = destroy_pool(&ms_0, "pool1", None, true).await.unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    // The requests which are carried out.
+    unpublish_nexus(&ms_0, &nex.uuid(), false).await.unwrap();
+    assert!(nex.get_nexus().await.unwrap().device_uri.is_empty());
+    destroy_nexus(&ms_0, &nex.uuid(), false).await.unwrap();
+    assert_eq!(nex.get_nexus().await.unwrap_err().code(), Code::NotFound);
+    destroy_replica(&ms_0, &repl.uuid(), &pool.uuid(), false).await.unwrap();
+    assert_eq!(repl.get_replica().await.unwrap_err().code(), Code::NotFound);
+    destroy_pool(&ms_0, "pool0", None, false).await.unwrap();
+    assert_eq!(pool.get_pool().await.unwrap_err().code(), Code::NotFound);
+}
@@ -0,0 +1,26 @@
+use std::str::FromStr;
+
+use io_engine::subsys::registration::registration_grpc::ApiVersion;
+
+#[test]
+fn grpc_api_versions_served() {
+    let versions = ["v0", "V1", "v2alpha"]
+        .iter()
+        .map(|v| ApiVersion::from_str(v).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        versions,
+        vec![ApiVersion::V0, ApiVersion::V1, ApiVersion::V2Alpha]
+    );
+    assert!(ApiVersion::from_str("v3").is_err());
+
+    // The experimental API has no services yet: it is neither served nor
+    // advertised, even when enabled.
+    assert!(ApiVersion::V2Alpha.is_experimental());
+    assert!(!ApiVersion::V2Alpha.has_services());
+    assert_eq!(
+        ApiVersion::served(&versions),
+        vec![ApiVersion::V0, ApiVersion::V1]
+    );
+    assert!(ApiVersion::served(&[ApiVersion::V2Alpha]).is_empty());
+}
@@ -0,0 +1,148 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use io_engine::grpc::{
+    GrpcAudit,
+    GrpcAuditConfig,
+    GrpcAuditFilter,
+    GrpcAuditLayer,
+};
+use tonic::{body::BoxBody, Code, Status};
+use tower::{Layer, Service};
+
+const LOG_FILE: &str = "/tmp/grpc_audit.log";
+const CREATE_REPLICA: &str = "/mayastor.v1.ReplicaRpc/CreateReplica";
+const DESTROY_REPLICA: &str = "/mayastor.v1.ReplicaRpc/DestroyReplica";
+
+/// Method handler which fails the destruction of replicas.
+struct Handler {}
+
+impl Service<http::Request<()>> for Handler {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<()>) -> Self::Future {
+        let response = if req.uri().path() == DESTROY_REPLICA {
+            Status::not_found("no such replica").to_http()
+        } else {
+            http::Response::new(tonic::body::empty_body())
+        };
+        ready(Ok(response))
+    }
+}
+
+async fn call(service: &mut impl Service<http::Request<()>>, path: &str) {
+    let req = http::Request::builder().uri(path).body(()).unwrap();
+    assert!(service.call(req).await.is_ok());
+}
+
+fn file_lines() -> Vec<String> {
+    std::fs::read_to_string(LOG_FILE)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn grpc_audit_method_classification() {
+    for path in [
+        CREATE_REPLICA,
+        DESTROY_REPLICA,
+        "/mayastor.v1.NexusRpc/FreezeNexus",
+        "/mayastor.v1.JsonRpc/JsonRpcCall",
+        // Unknown methods may modify resources.
+        "/mayastor.v1.ReplicaRpc/UnknownMethod",
+        "/mayastor.v9.Unknown/Method",
+    ] {
+        assert!(GrpcAudit::is_audited(path), "{path}");
+    }
+
+    for path in [
+        "/mayastor.v1.ReplicaRpc/ListReplicas",
+        "/mayastor.v1.NexusRpc/ListNexus",
+        "/mayastor.v1.HostRpc/GetAuditLog",
+        "/grpc.health.v1.Health/Check",
+        "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo",
+    ] {
+        assert!(!GrpcAudit::is_audited(path), "{path}");
+    }
+}
+
+#[tokio::test]
+async fn grpc_audit_log_records() {
+    std::fs::remove_file(LOG_FILE).ok();
+    GrpcAudit::configure(GrpcAuditConfig {
+        path: Some(LOG_FILE.to_string()),
+        capacity: 4,
+    });
+    assert!(GrpcAudit::enabled());
+
+    let mut service = GrpcAuditLayer::default().layer(Handler {});
+    for path in [
+        CREATE_REPLICA,
+        "/mayastor.v1.ReplicaRpc/ListReplicas",
+        DESTROY_REPLICA,
+        "/grpc.health.v1.Health/Check",
+    ] {
+        call(&mut service, path).await;
+    }
+
+    // Only the mutating requests are recorded, one JSON entry per line.
+    GrpcAudit::flush();
+    let lines = file_lines();
+    assert_eq!(lines.len(), 2);
+
+    let created: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(created["method"], CREATE_REPLICA);
+    assert_eq!(created["code"], Code::Ok as i32);
+    assert_eq!(created["message"], "");
+
+    let destroyed: serde_json::Value =
+        serde_json::from_str(&lines[1]).unwrap();
+    assert_eq!(destroyed["method"], DESTROY_REPLICA);
+    assert_eq!(destroyed["code"], Code::NotFound as i32);
+    assert_eq!(destroyed["message"], "no such replica");
+    assert!(destroyed["args_hash"].is_null());
+    assert!(destroyed["peer"].is_null());
+    assert!(destroyed["time_ms"].as_u64().unwrap() > 0);
+    assert!(destroyed["duration_us"].is_u64());
+    assert_eq!(destroyed.as_object().unwrap().len(), 7);
+
+    let failed = GrpcAudit::entries(&GrpcAuditFilter {
+        failed_only: true,
+        ..Default::default()
+    });
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].method, DESTROY_REPLICA);
+
+    // The file is rewritten with the retained entries once it holds twice
+    // the capacity.
+    for _ in 0 .. 10 {
+        call(&mut service, CREATE_REPLICA).await;
+    }
+    GrpcAudit::flush();
+    let lines = file_lines();
+    assert!(lines.len() < 8, "{} entries in the file", lines.len());
+
+    let entries = GrpcAudit::entries(&GrpcAuditFilter::default());
+    assert_eq!(entries.len(), 4);
+    let retained = entries
+        .iter()
+        .map(|e| serde_json::to_string(e).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines[lines.len() - 4 ..], retained[..]);
+
+    std::fs::remove_file(LOG_FILE).unwrap();
+}
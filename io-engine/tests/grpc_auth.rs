@@ -0,0 +1,121 @@
+use io_engine::grpc::{GrpcAuth, GrpcAuthScope, GrpcMethodAuth};
+use tonic::Code;
+
+const TOKENS_FILE: &str = "/tmp/grpc_auth_tokens";
+
+fn headers(token: Option<&str>) -> http::HeaderMap {
+    let mut headers = http::HeaderMap::new();
+    if let Some(token) = token {
+        headers.insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+    }
+    headers
+}
+
+fn code(path: &str, token: Option<&str>) -> Code {
+    match GrpcAuth::authorize(path, &headers(token)) {
+        Ok(()) => Code::Ok,
+        Err(status) => status.code(),
+    }
+}
+
+#[test]
+fn grpc_auth_method_scopes() {
+    let scope = |path| GrpcAuth::method_auth(path);
+
+    assert_eq!(
+        scope("/mayastor.v1.NexusRpc/ListNexus"),
+        GrpcMethodAuth::Scope(GrpcAuthScope::ReadOnly)
+    );
+    assert_eq!(
+        scope("/mayastor.v1.ReplicaRpc/CreateReplica"),
+        GrpcMethodAuth::Scope(GrpcAuthScope::Provisioning)
+    );
+
+    // Methods which disrupt I/O without a destructive name.
+    for path in [
+        "/mayastor.v1.PoolRpc/ExportPool",
+        "/mayastor.v1.PoolRpc/CheckPool",
+        "/mayastor.v1.NexusRpc/FreezeNexus",
+        "/mayastor.v1.ReplicaRpc/FreezeReplica",
+        "/mayastor.v1.ConsistencyGroupRpc/FreezeConsistencyGroup",
+        "/mayastor.v1.NexusRpc/StopRebuild",
+        "/mayastor.v1.NexusRpc/PauseRebuild",
+        "/mayastor.v1.ReplicaRpc/SetReplicaState",
+        "/mayastor.v1.NexusRpc/SetNvmeAnaGroupState",
+        "/mayastor.v1.HostRpc/SetHostIdentity",
+        "/mayastor.v1.NexusRpc/UpdateNexusListener",
+        "/mayastor.v1.NexusRpc/DetachIoLogJournal",
+        "/mayastor.v1.SnapshotRpc/FlattenClone",
+        "/mayastor.v1.NexusRpc/RecreateNexusFromStore",
+        "/mayastor.v1.JsonRpc/JsonRpcCall",
+        "/mayastor.Mayastor/ShareReplica",
+    ] {
+        assert_eq!(
+            scope(path),
+            GrpcMethodAuth::Scope(GrpcAuthScope::Destructive),
+            "{path}"
+        );
+    }
+
+    assert_eq!(scope("/grpc.health.v1.Health/Check"), GrpcMethodAuth::Public);
+    assert_eq!(
+        scope("/mayastor.v1.NexusRpc/GetNexusSecrets"),
+        GrpcMethodAuth::Unknown
+    );
+    assert_eq!(
+        scope("/mayastor.v1.UnknownRpc/ListNexus"),
+        GrpcMethodAuth::Unknown
+    );
+    assert_eq!(scope("/ListNexus"), GrpcMethodAuth::Unknown);
+}
+
+#[test]
+fn grpc_auth_tokens() {
+    // Methods are allowed until authorization is configured.
+    assert_eq!(code("/mayastor.v1.NexusRpc/DestroyNexus", None), Code::Ok);
+
+    std::fs::write(
+        TOKENS_FILE,
+        "# tokens\n\
+        read-only: ro-token\n\
+        provisioning:prov-token\n\
+        \n\
+        destructive:root-token\n",
+    )
+    .unwrap();
+    GrpcAuth::configure(TOKENS_FILE).unwrap();
+    std::fs::remove_file(TOKENS_FILE).unwrap();
+
+    let list = "/mayastor.v1.NexusRpc/ListNexus";
+    let create = "/mayastor.v1.NexusRpc/CreateNexus";
+    let destroy = "/mayastor.v1.NexusRpc/DestroyNexus";
+    let unknown = "/mayastor.v1.NexusRpc/GetNexusSecrets";
+
+    assert_eq!(code(list, None), Code::Unauthenticated);
+    assert_eq!(code(list, Some("bad-token")), Code::Unauthenticated);
+    assert_eq!(code("/grpc.health.v1.Health/Check", None), Code::Ok);
+
+    assert_eq!(code(list, Some("ro-token")), Code::Ok);
+    assert_eq!(code(create, Some("ro-token")), Code::PermissionDenied);
+
+    assert_eq!(code(list, Some("prov-token")), Code::Ok);
+    assert_eq!(code(create, Some("prov-token")), Code::Ok);
+    assert_eq!(code(destroy, Some("prov-token")), Code::PermissionDenied);
+
+    assert_eq!(code(destroy, Some("root-token")), Code::Ok);
+    assert_eq!(code(unknown, Some("root-token")), Code::PermissionDenied);
+}
+
+#[test]
+fn grpc_auth_invalid_tokens_file() {
+    let path = "/tmp/grpc_auth_invalid_tokens";
+    std::fs::write(path, "admin:token\n").unwrap();
+    assert!(GrpcAuth::configure(path).is_err());
+    std::fs::write(path, "read-only:\n").unwrap();
+    assert!(GrpcAuth::configure(path).is_err());
+    std::fs::remove_file(path).unwrap();
+    assert!(GrpcAuth::configure(path).is_err());
+}
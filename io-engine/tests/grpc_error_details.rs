@@ -0,0 +1,154 @@
+pub mod common;
+
+use std::collections::HashMap;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            bdev::CreateBdevRequest,
+            stats::GetStatsRequest,
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use io_engine::grpc::error_details::{
+    ERROR_DOMAIN,
+    ERROR_RESOURCE_KEY,
+    ERROR_RETRYABLE_KEY,
+};
+use prost::Message;
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+#[derive(Clone, PartialEq, Message)]
+struct ErrorInfo {
+    #[prost(string, tag = "1")]
+    reason: String,
+    #[prost(string, tag = "2")]
+    domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    code: i32,
+    #[prost(string, tag = "2")]
+    message: String,
+    #[prost(message, repeated, tag = "3")]
+    details: Vec<prost_types::Any>,
+}
+
+/// Decodes the `google.rpc.ErrorInfo` attached to a status.
+fn error_info(status: &Status) -> ErrorInfo {
+    let details = RpcStatus::decode(status.details()).unwrap();
+    assert_eq!(details.code, status.code() as i32);
+    assert_eq!(details.message, status.message());
+    assert_eq!(details.details.len(), 1);
+    assert_eq!(
+        details.details[0].type_url,
+        "type.googleapis.com/google.rpc.ErrorInfo"
+    );
+    let info = ErrorInfo::decode(details.details[0].value.as_slice()).unwrap();
+    assert_eq!(info.domain, ERROR_DOMAIN);
+    info
+}
+
+async fn create_bdev(rpc: &SharedRpcHandle, uri: &str) -> Status {
+    rpc.lock()
+        .await
+        .bdev
+        .create(CreateBdevRequest {
+            uri: uri.to_string(),
+        })
+        .await
+        .unwrap_err()
+}
+
+/// The statuses of the lvs and bdev errors carry the reason of the error,
+/// the resource it is about and whether it may be retried.
+#[tokio::test]
+async fn grpc_error_details() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE);
+    repl.create().await.unwrap();
+
+    // Another replica of the same name.
+    let err = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .create()
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::AlreadyExists);
+    let info = error_info(&err);
+    assert_eq!(info.reason, "REP_EXISTS");
+    assert_eq!(info.metadata[ERROR_RESOURCE_KEY], "r0");
+    assert_eq!(info.metadata[ERROR_RETRYABLE_KEY], "false");
+
+    let uri = "malloc:///m0?size_mb=many";
+    let err = create_bdev(&ms_0, uri).await;
+    assert_eq!(err.code(), Code::InvalidArgument);
+    let info = error_info(&err);
+    assert_eq!(info.reason, "INT_PARAM_PARSE_FAILED");
+    assert_eq!(info.metadata[ERROR_RESOURCE_KEY], uri);
+
+    // Errors about no resource in particular only carry the hint.
+    let err = create_bdev(&ms_0, "bogus:///m0").await;
+    assert_eq!(err.code(), Code::InvalidArgument);
+    let info = error_info(&err);
+    assert_eq!(info.reason, "URI_SCHEME_UNSUPPORTED");
+    assert!(!info.metadata.contains_key(ERROR_RESOURCE_KEY));
+    assert_eq!(info.metadata[ERROR_RETRYABLE_KEY], "false");
+
+    // Statuses made from other errors have no details.
+    let err = ms_0
+        .lock()
+        .await
+        .stats
+        .get_stats(GetStatsRequest {
+            resource_types: vec![99],
+            name: None,
+            reset: false,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    assert!(err.details().is_empty());
+}
@@ -0,0 +1,108 @@
+pub mod common;
+
+use common::compose::{rpc::v1::GrpcConnect, Binary, Builder};
+use tokio_stream::StreamExt;
+use tonic::Code;
+use tonic_health::proto::{
+    health_check_response::ServingStatus,
+    health_client::HealthClient,
+    HealthCheckRequest,
+};
+use tonic_reflection::pb::{
+    server_reflection_client::ServerReflectionClient,
+    server_reflection_request::MessageRequest,
+    server_reflection_response::MessageResponse,
+    ServerReflectionRequest,
+};
+
+#[tokio::test]
+async fn grpc_health_and_reflection() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let ms_0 = GrpcConnect::new(&test)
+        .grpc_handle_shared("ms_0")
+        .await
+        .unwrap();
+    let endpoint = format!("http://{}", ms_0.endpoint());
+
+    // The server reports serving once the reactors are running.
+    let mut health = HealthClient::connect(endpoint.clone()).await.unwrap();
+    let status = health
+        .check(HealthCheckRequest {
+            service: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .status;
+    assert_eq!(status, ServingStatus::Serving as i32);
+
+    // Only the status of the server as a whole is reported.
+    let error = health
+        .check(HealthCheckRequest {
+            service: "mayastor.v1.NexusRpc".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+
+    // The services are listed by reflection.
+    let mut reflection =
+        ServerReflectionClient::connect(endpoint).await.unwrap();
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut responses = reflection
+        .server_reflection_info(tokio_stream::iter(vec![request]))
+        .await
+        .unwrap()
+        .into_inner();
+    let response = responses.next().await.unwrap().unwrap();
+    let services = match response.message_response {
+        Some(MessageResponse::ListServicesResponse(list)) => list
+            .service
+            .into_iter()
+            .map(|s| s.name)
+            .collect::<Vec<_>>(),
+        r => panic!("unexpected reflection response {r:?}"),
+    };
+    for service in [
+        "grpc.health.v1.Health",
+        "mayastor.v1.NexusRpc",
+        "mayastor.v1.ReplicaRpc",
+    ] {
+        assert!(
+            services.iter().any(|s| s == service),
+            "{service} is not listed: {services:?}"
+        );
+    }
+
+    // Unknown symbols are not found.
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::FileContainingSymbol(
+            "mayastor.v1.NoSuchRpc".to_string(),
+        )),
+    };
+    let mut responses = reflection
+        .server_reflection_info(tokio_stream::iter(vec![request]))
+        .await
+        .unwrap()
+        .into_inner();
+    let error = responses.next().await.unwrap().unwrap_err();
+    assert_eq!(error.code(), Code::NotFound);
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use io_engine::grpc::GrpcTokenBucket;
+
+/// Takes tokens until the bucket refuses, and returns how many were taken.
+fn drain(bucket: &mut GrpcTokenBucket, low_priority: bool) -> u32 {
+    let mut taken = 0;
+    while bucket.try_take(low_priority) {
+        taken += 1;
+        assert!(taken <= 1000, "bucket never runs out");
+    }
+    taken
+}
+
+#[test]
+fn grpc_rate_limit_priority_reserve() {
+    // A quarter of the bucket is reserved for priority requests.
+    let mut bucket = GrpcTokenBucket::new(8, 0.25);
+    assert_eq!(drain(&mut bucket, true), 6);
+    assert_eq!(drain(&mut bucket, false), 2);
+    assert!(!bucket.try_take(true));
+
+    // Without a reserve, all requests share the bucket.
+    let mut bucket = GrpcTokenBucket::new(8, 0.0);
+    assert_eq!(drain(&mut bucket, true), 8);
+    assert!(!bucket.try_take(false));
+}
+
+#[test]
+fn grpc_rate_limit_small_rates() {
+    // The reserve leaves a token to read-only requests.
+    let mut bucket = GrpcTokenBucket::new(1, 0.25);
+    assert!(bucket.try_take(true));
+    assert!(!bucket.try_take(true));
+    assert!(!bucket.try_take(false));
+
+    let mut bucket = GrpcTokenBucket::new(2, 0.25);
+    assert_eq!(drain(&mut bucket, true), 1);
+    assert_eq!(drain(&mut bucket, false), 1);
+}
+
+#[test]
+fn grpc_rate_limit_refill() {
+    let mut bucket = GrpcTokenBucket::new(10, 0.0);
+    assert_eq!(drain(&mut bucket, false), 10);
+
+    // Tokens come back at the configured rate.
+    std::thread::sleep(Duration::from_millis(350));
+    let refilled = drain(&mut bucket, false);
+    assert!((3 ..= 5).contains(&refilled), "{refilled} tokens refilled");
+
+    // The bucket holds at most one second worth of tokens.
+    std::thread::sleep(Duration::from_millis(1500));
+    assert_eq!(drain(&mut bucket, false), 10);
+}
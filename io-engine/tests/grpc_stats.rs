@@ -0,0 +1,183 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            stats::{GetStatsRequest, ResourceStats, ResourceType},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::Code;
+
+const POOL_SIZE: u64 = 60;
+const REPL_SIZE: u64 = 20;
+const WRITE_COUNT: usize = 4;
+
+async fn get_stats(
+    rpc: &SharedRpcHandle,
+    resource_types: Vec<i32>,
+    name: Option<String>,
+    reset: bool,
+) -> Result<Vec<ResourceStats>, tonic::Status> {
+    rpc.lock()
+        .await
+        .stats
+        .get_stats(GetStatsRequest {
+            resource_types,
+            name,
+            reset,
+        })
+        .await
+        .map(|r| r.into_inner().stats)
+}
+
+/// Returns the number of bytes written to the only resource of the given
+/// type and name.
+async fn bytes_written(
+    rpc: &SharedRpcHandle,
+    resource_type: ResourceType,
+    name: &str,
+) -> u64 {
+    let stats =
+        get_stats(rpc, vec![resource_type as i32], Some(name.into()), false)
+            .await
+            .unwrap();
+    assert_eq!(stats.len(), 1, "{resource_type:?} '{name}'");
+    assert_eq!(stats[0].resource_type, resource_type as i32);
+    stats[0].stats.as_ref().unwrap().bytes_written
+}
+
+#[tokio::test]
+async fn grpc_stats_resource_counters() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    test_write_to_nexus(
+        &nex,
+        DataSize::from_bytes(0),
+        WRITE_COUNT,
+        DataSize::from_mb(1),
+    )
+    .await
+    .unwrap();
+
+    // The writes are counted by the nexus and by the replica below it,
+    // whether looked up by name or by uuid.
+    let written = WRITE_COUNT as u64 * 1024 * 1024;
+    for (resource_type, name) in [
+        (ResourceType::Nexus, "nexus0".to_string()),
+        (ResourceType::Nexus, nex.uuid()),
+        (ResourceType::Replica, "r0".to_string()),
+        (ResourceType::Replica, repl.uuid()),
+    ] {
+        assert!(bytes_written(&ms_0, resource_type, &name).await >= written);
+    }
+
+    // The subsystem of the published nexus counts its writes too.
+    let subsystems =
+        get_stats(&ms_0, vec![ResourceType::Subsystem as i32], None, false)
+            .await
+            .unwrap();
+    assert!(subsystems
+        .iter()
+        .filter(|s| s.uuid == nex.uuid())
+        .any(|s| s.stats.as_ref().unwrap().bytes_written >= written));
+
+    // All resource types are reported without a filter.
+    let all = get_stats(&ms_0, Vec::new(), None, false).await.unwrap();
+    for resource_type in [
+        ResourceType::Bdev,
+        ResourceType::Replica,
+        ResourceType::Nexus,
+        ResourceType::Subsystem,
+    ] {
+        assert!(
+            all.iter().any(|s| s.resource_type == resource_type as i32),
+            "no {resource_type:?} stats"
+        );
+    }
+
+    // Counters are reported relative to the last reset.
+    let before = get_stats(
+        &ms_0,
+        vec![ResourceType::Nexus as i32],
+        Some("nexus0".into()),
+        true,
+    )
+    .await
+    .unwrap();
+    assert!(before[0].stats.as_ref().unwrap().bytes_written >= written);
+    assert_eq!(bytes_written(&ms_0, ResourceType::Nexus, "nexus0").await, 0);
+    assert!(bytes_written(&ms_0, ResourceType::Replica, "r0").await >= written);
+
+    test_write_to_nexus(
+        &nex,
+        DataSize::from_bytes(0),
+        1,
+        DataSize::from_mb(1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        bytes_written(&ms_0, ResourceType::Nexus, "nexus0").await,
+        1024 * 1024
+    );
+
+    // Unknown resources are not reported, and unknown resource types are
+    // rejected.
+    let none = get_stats(&ms_0, Vec::new(), Some("nexus1".into()), false)
+        .await
+        .unwrap();
+    assert!(none.is_empty());
+    assert_eq!(
+        get_stats(&ms_0, vec![99], None, false)
+            .await
+            .unwrap_err()
+            .code(),
+        Code::InvalidArgument
+    );
+}
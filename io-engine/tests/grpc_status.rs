@@ -0,0 +1,230 @@
+use io_engine::{
+    bdev::nexus::{ChildError, Error as NexusError},
+    bdev_api::BdevError,
+    core::CoreError,
+    grpc::errno_code,
+    store::store_defs::StoreError,
+};
+use nix::errno::Errno;
+use tonic::{Code, Status};
+
+const NEXUS: &str = "nexus0";
+const CHILD: &str = "malloc:///m0";
+
+fn code(e: NexusError) -> Code {
+    Status::from(e).code()
+}
+
+#[test]
+fn errno_codes() {
+    assert_eq!(errno_code(Errno::ENOENT), Code::NotFound);
+    assert_eq!(errno_code(Errno::ENODEV), Code::NotFound);
+    assert_eq!(errno_code(Errno::EEXIST), Code::AlreadyExists);
+    assert_eq!(errno_code(Errno::ENOSPC), Code::ResourceExhausted);
+    assert_eq!(errno_code(Errno::ENOMEM), Code::ResourceExhausted);
+    assert_eq!(errno_code(Errno::EBUSY), Code::FailedPrecondition);
+    assert_eq!(errno_code(Errno::ENOMEDIUM), Code::FailedPrecondition);
+    assert_eq!(errno_code(Errno::ETIMEDOUT), Code::DeadlineExceeded);
+    assert_eq!(errno_code(Errno::EINVAL), Code::InvalidArgument);
+    assert_eq!(errno_code(Errno::ENOTSUP), Code::Unimplemented);
+    assert_eq!(errno_code(Errno::ENXIO), Code::Unavailable);
+    assert_eq!(errno_code(Errno::ECANCELED), Code::Cancelled);
+    assert_eq!(errno_code(Errno::EIO), Code::Internal);
+}
+
+/// Child creation failures keep the code of the bdev error.
+#[test]
+fn nexus_create_child_status() {
+    let create = |source| NexusError::CreateChild {
+        source,
+        name: NEXUS.to_string(),
+    };
+
+    assert_eq!(
+        code(create(BdevError::CreateBdevFailed {
+            source: Errno::ENOSPC,
+            name: CHILD.to_string(),
+        })),
+        Code::ResourceExhausted
+    );
+    assert_eq!(
+        code(create(BdevError::CreateBdevFailed {
+            source: Errno::ENODEV,
+            name: CHILD.to_string(),
+        })),
+        Code::NotFound
+    );
+    assert_eq!(
+        code(create(BdevError::BdevNotFound {
+            name: CHILD.to_string(),
+        })),
+        Code::NotFound
+    );
+}
+
+/// Child open failures keep the code of the errno of the child error.
+#[test]
+fn nexus_open_child_status() {
+    let open = |source| NexusError::OpenChild {
+        source,
+        child: CHILD.to_string(),
+        name: NEXUS.to_string(),
+    };
+
+    assert_eq!(
+        code(open(ChildError::OpenChild {
+            source: CoreError::OpenBdev {
+                source: Errno::EBUSY,
+            },
+        })),
+        Code::FailedPrecondition
+    );
+    assert_eq!(
+        code(open(ChildError::OpenChild {
+            source: CoreError::BdevNotFound {
+                name: CHILD.to_string(),
+            },
+        })),
+        Code::NotFound
+    );
+    assert_eq!(
+        code(open(ChildError::ClaimChild {
+            source: Errno::EPERM,
+        })),
+        Code::PermissionDenied
+    );
+    assert_eq!(
+        code(open(ChildError::ChildInaccessible {})),
+        Code::Unavailable
+    );
+
+    let status = Status::from(open(ChildError::OpenChild {
+        source: CoreError::OpenBdev {
+            source: Errno::EBUSY,
+        },
+    }));
+    assert!(status.message().contains(CHILD));
+    assert!(status.message().contains("failed to open bdev"));
+}
+
+/// Nexus failures caused by a lower layer keep its errno.
+#[test]
+fn nexus_errno_status() {
+    assert_eq!(
+        code(NexusError::RegisterNexus {
+            source: Errno::ENOMEM,
+            name: NEXUS.to_string(),
+        }),
+        Code::ResourceExhausted
+    );
+    assert_eq!(
+        code(NexusError::ShareNvmfNexus {
+            source: CoreError::BdevNotFound {
+                name: NEXUS.to_string(),
+            },
+            name: NEXUS.to_string(),
+        }),
+        Code::NotFound
+    );
+    assert_eq!(
+        code(NexusError::FlushNexus {
+            source: CoreError::FlushDispatch {
+                source: Errno::ETIMEDOUT,
+            },
+            name: NEXUS.to_string(),
+        }),
+        Code::DeadlineExceeded
+    );
+}
+
+/// Persistent store failures map to the store condition.
+#[test]
+fn nexus_store_status() {
+    assert_eq!(
+        code(NexusError::LoadStateFailed {
+            source: StoreError::MissingEntry {
+                key: NEXUS.to_string(),
+            },
+            name: NEXUS.to_string(),
+        }),
+        Code::NotFound
+    );
+    assert_eq!(
+        code(NexusError::SaveStateFailed {
+            source: StoreError::OpTimeout {},
+            name: NEXUS.to_string(),
+        }),
+        Code::DeadlineExceeded
+    );
+}
+
+/// Nexus errors without a lower layer cause have their own code, none of
+/// them being reported as internal.
+#[test]
+fn nexus_status() {
+    assert_eq!(
+        code(NexusError::NexusNotFound {
+            name: NEXUS.to_string(),
+        }),
+        Code::NotFound
+    );
+    assert_eq!(
+        code(NexusError::NexusInitialising {
+            name: NEXUS.to_string(),
+        }),
+        Code::Unavailable
+    );
+    assert_eq!(
+        code(NexusError::ChildAlreadyExists {
+            child: CHILD.to_string(),
+            name: NEXUS.to_string(),
+        }),
+        Code::AlreadyExists
+    );
+    assert_eq!(
+        code(NexusError::RemoveLastChild {
+            child: CHILD.to_string(),
+            name: NEXUS.to_string(),
+        }),
+        Code::FailedPrecondition
+    );
+    assert_eq!(code(NexusError::FailedGetHandle), Code::Unavailable);
+}
+
+/// A failed creation reports the code of its cause, with the rollback.
+#[test]
+fn nexus_create_rollback_status() {
+    let status = Status::from(NexusError::CreateRollback {
+        source: Box::new(NexusError::CreateChild {
+            source: BdevError::CreateBdevFailed {
+                source: Errno::ENOSPC,
+                name: CHILD.to_string(),
+            },
+            name: NEXUS.to_string(),
+        }),
+        name: NEXUS.to_string(),
+        rollback: "succeeded".to_string(),
+    });
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert!(status.message().contains("rollback of nexus"));
+}
+
+#[test]
+fn child_status() {
+    assert_eq!(
+        Status::from(ChildError::ClaimChild {
+            source: Errno::EBUSY,
+        })
+        .code(),
+        Code::FailedPrecondition
+    );
+    assert_eq!(
+        Status::from(ChildError::HandleOpen {
+            source: CoreError::OpenBdev {
+                source: Errno::ENXIO,
+            },
+        })
+        .code(),
+        Code::Unavailable
+    );
+}
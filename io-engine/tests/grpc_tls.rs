@@ -0,0 +1,209 @@
+use std::{path::Path, process::Command, time::Duration};
+
+use io_engine::grpc::{GrpcTlsAcceptor, GrpcTlsConfig};
+use tokio::net::TcpListener;
+use tonic::transport::{
+    Certificate,
+    Channel,
+    ClientTlsConfig,
+    Identity,
+    Server,
+};
+use tonic_health::{
+    proto::{
+        health_check_response::ServingStatus as Status,
+        health_client::HealthClient,
+        HealthCheckRequest,
+    },
+    ServingStatus,
+};
+
+const CERT_DIR: &str = "/tmp/grpc_tls";
+
+fn openssl(args: &[&str]) {
+    let output = Command::new("openssl")
+        .current_dir(CERT_DIR)
+        .args(args)
+        .output()
+        .expect("failed to run openssl");
+    assert!(
+        output.status.success(),
+        "openssl {args:?}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Makes a CA with the given name.
+fn make_ca(name: &str) {
+    openssl(&[
+        "req",
+        "-x509",
+        "-newkey",
+        "rsa:2048",
+        "-nodes",
+        "-days",
+        "1",
+        "-subj",
+        &format!("/CN={name}"),
+        "-addext",
+        "basicConstraints=critical,CA:TRUE",
+        "-addext",
+        "keyUsage=critical,keyCertSign",
+        "-keyout",
+        &format!("{name}.key"),
+        "-out",
+        &format!("{name}.pem"),
+    ]);
+}
+
+/// Makes a certificate for localhost, signed by the given CA.
+fn make_cert(name: &str, ca: &str) {
+    std::fs::write(
+        Path::new(CERT_DIR).join("ext.cnf"),
+        "subjectAltName=DNS:localhost\n",
+    )
+    .unwrap();
+    openssl(&[
+        "req",
+        "-newkey",
+        "rsa:2048",
+        "-nodes",
+        "-subj",
+        "/CN=localhost",
+        "-keyout",
+        &format!("{name}.key"),
+        "-out",
+        &format!("{name}.csr"),
+    ]);
+    openssl(&[
+        "x509",
+        "-req",
+        "-days",
+        "1",
+        "-in",
+        &format!("{name}.csr"),
+        "-CA",
+        &format!("{ca}.pem"),
+        "-CAkey",
+        &format!("{ca}.key"),
+        "-CAcreateserial",
+        "-extfile",
+        "ext.cnf",
+        "-out",
+        &format!("{name}.pem"),
+    ]);
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(Path::new(CERT_DIR).join(name)).unwrap()
+}
+
+async fn connect(
+    port: u16,
+    server_ca: &str,
+) -> Result<HealthClient<Channel>, tonic::transport::Error> {
+    let tls = ClientTlsConfig::new()
+        .domain_name("localhost")
+        .ca_certificate(Certificate::from_pem(read(&format!(
+            "{server_ca}.pem"
+        ))))
+        .identity(Identity::from_pem(read("client.pem"), read("client.key")));
+
+    let channel = Channel::from_shared(format!("https://127.0.0.1:{port}"))
+        .unwrap()
+        .tls_config(tls)?
+        .connect_timeout(Duration::from_secs(5))
+        .connect()
+        .await?;
+    Ok(HealthClient::new(channel))
+}
+
+/// Reloads the server certificates while a request is in-flight: the request
+/// and its connection are kept, and new connections use the new certificates.
+#[tokio::test]
+async fn grpc_tls_reload() {
+    std::fs::remove_dir_all(CERT_DIR).ok();
+    std::fs::create_dir_all(CERT_DIR).unwrap();
+    make_ca("ca1");
+    make_ca("ca2");
+    make_ca("client-ca");
+    make_cert("client", "client-ca");
+    make_cert("server", "ca1");
+
+    let path = |name: &str| format!("{CERT_DIR}/{name}");
+    let acceptor = GrpcTlsAcceptor::new(GrpcTlsConfig {
+        cert: path("server.pem"),
+        key: path("server.key"),
+        ca: Some(path("client-ca.pem")),
+    })
+    .unwrap();
+
+    let (mut reporter, health) = tonic_health::server::health_reporter();
+    reporter.set_service_status("", ServingStatus::Serving).await;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let incoming = acceptor.incoming(listener);
+    tokio::spawn(async move {
+        Server::builder()
+            .add_service(health)
+            .serve_with_incoming(incoming)
+            .await
+            .unwrap();
+    });
+
+    let mut client = connect(port, "ca1").await.unwrap();
+    let mut watch = client
+        .watch(HealthCheckRequest {
+            service: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let status = watch.message().await.unwrap().unwrap().status;
+    assert_eq!(status, Status::Serving as i32);
+
+    // Rotate the server certificate to one signed by another CA.
+    make_cert("server", "ca2");
+    acceptor.reload().unwrap();
+
+    // The watch started before the reload keeps receiving updates.
+    reporter
+        .set_service_status("", ServingStatus::NotServing)
+        .await;
+    let status = tokio::time::timeout(Duration::from_secs(5), watch.message())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+        .status;
+    assert_eq!(status, Status::NotServing as i32);
+
+    // The established connection is still usable.
+    let status = client
+        .check(HealthCheckRequest {
+            service: String::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .status;
+    assert_eq!(status, Status::NotServing as i32);
+
+    // New connections are served with the new certificate only.
+    assert!(connect(port, "ca1").await.is_err());
+    let mut client = connect(port, "ca2").await.unwrap();
+    client
+        .check(HealthCheckRequest {
+            service: String::new(),
+        })
+        .await
+        .unwrap();
+
+    // Invalid certificates are refused, and the previous ones are kept.
+    std::fs::write(path("server.pem"), "not a certificate").unwrap();
+    assert!(acceptor.reload().is_err());
+    assert!(connect(port, "ca2").await.is_ok());
+
+    std::fs::remove_dir_all(CERT_DIR).unwrap();
+}
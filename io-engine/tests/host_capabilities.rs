@@ -0,0 +1,122 @@
+pub mod common;
+
+use std::sync::atomic::Ordering;
+
+use common::{
+    compose::{rpc::v1::GrpcConnect, Binary, Builder},
+    MayastorTest,
+};
+use io_engine::{
+    bdev::nexus::ENABLE_PARTIAL_REBUILD,
+    core::MayastorCliArgs,
+    host::capabilities::{
+        NodeCapabilities,
+        CAPABILITY_CRYPTO,
+        CAPABILITY_PARTIAL_REBUILD,
+        CAPABILITY_RDMA,
+        CAPABILITY_SNAPSHOTS_V2,
+        CAPABILITY_URING,
+    },
+    subsys::registration::registration_grpc::ApiVersion,
+};
+use mayastor_api::v1::registration::ApiVersion as RpcApiVersion;
+
+fn enabled(caps: &NodeCapabilities, name: &str) -> bool {
+    caps.capabilities
+        .iter()
+        .find(|c| c.name == name)
+        .unwrap()
+        .enabled
+}
+
+/// A capability is only available when compiled, and only enabled when
+/// available.
+#[tokio::test]
+async fn host_capabilities_detect() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        let caps = NodeCapabilities::detect(&[ApiVersion::V0, ApiVersion::V1]);
+        let names = caps
+            .capabilities
+            .iter()
+            .map(|c| c.name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                CAPABILITY_RDMA,
+                CAPABILITY_CRYPTO,
+                CAPABILITY_URING,
+                CAPABILITY_PARTIAL_REBUILD,
+                CAPABILITY_SNAPSHOTS_V2,
+            ]
+        );
+        for c in &caps.capabilities {
+            assert!(c.compiled || !c.available, "{c:?}");
+            assert!(c.available || !c.enabled, "{c:?}");
+        }
+        assert_eq!(caps.api_versions, [ApiVersion::V0, ApiVersion::V1]);
+        assert!(enabled(&caps, CAPABILITY_SNAPSHOTS_V2));
+        assert!(enabled(&caps, CAPABILITY_PARTIAL_REBUILD));
+
+        // Without the v1 API, there are no snapshots of the v1 API.
+        let caps = NodeCapabilities::detect(&[ApiVersion::V0]);
+        assert!(!enabled(&caps, CAPABILITY_SNAPSHOTS_V2));
+
+        ENABLE_PARTIAL_REBUILD.store(false, Ordering::SeqCst);
+        let caps = NodeCapabilities::detect(&[ApiVersion::V1]);
+        assert!(!enabled(&caps, CAPABILITY_PARTIAL_REBUILD));
+        ENABLE_PARTIAL_REBUILD.store(true, Ordering::SeqCst);
+    })
+    .await;
+}
+
+/// The capabilities of a node are served along with the API versions it
+/// serves.
+#[tokio::test]
+async fn host_capabilities_grpc() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let response = ms_0
+        .lock()
+        .await
+        .host
+        .get_capabilities(())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.capabilities.len(), 5);
+    let cap = |name: &str| {
+        response
+            .capabilities
+            .iter()
+            .find(|c| c.name == name)
+            .cloned()
+            .unwrap()
+    };
+    let snapshots = cap(CAPABILITY_SNAPSHOTS_V2);
+    assert!(snapshots.compiled && snapshots.enabled, "{snapshots:?}");
+    assert!(cap(CAPABILITY_PARTIAL_REBUILD).enabled);
+    assert_eq!(
+        response.api_versions,
+        [RpcApiVersion::V0 as i32, RpcApiVersion::V1 as i32]
+    );
+    assert!(response.supported_features.is_some());
+}
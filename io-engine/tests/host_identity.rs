@@ -0,0 +1,155 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            host::{HostIdentity, SetHostIdentityRequest},
+            replica::ShareReplicaRequest,
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+const HOSTNQN: &str = "nqn.2019-05.io.openebs:host-identity";
+const HOSTID: &str = "4c2d9a6e-8b1f-4e57-a3c0-6d5f1e2b7a90";
+
+async fn get_identity(rpc: &SharedRpcHandle) -> HostIdentity {
+    rpc.lock()
+        .await
+        .host
+        .get_host_identity(())
+        .await
+        .unwrap()
+        .into_inner()
+        .identity
+        .unwrap()
+}
+
+async fn set_identity(
+    rpc: &SharedRpcHandle,
+    nqn: Option<&str>,
+    hostid: Option<&str>,
+) -> Result<HostIdentity, Status> {
+    rpc.lock()
+        .await
+        .host
+        .set_host_identity(SetHostIdentityRequest {
+            nqn: nqn.map(String::from),
+            hostid: hostid.map(String::from),
+        })
+        .await
+        .map(|r| r.into_inner().identity.unwrap())
+}
+
+/// Remote children are connected with the host identity set at runtime.
+#[tokio::test]
+async fn host_identity_update() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    // Only the new host identity may connect to the replica.
+    let mut pool = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+    let uri = ms_1
+        .lock()
+        .await
+        .replica
+        .share_replica(ShareReplicaRequest {
+            uuid: repl.uuid(),
+            share: 1,
+            allowed_hosts: vec![HOSTNQN.to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .uri;
+
+    let initial = get_identity(&ms_nex).await;
+    assert_ne!(initial.nqn, HOSTNQN);
+
+    let mut nex = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_bdev(&uri);
+    assert!(nex.create().await.is_err());
+
+    // Unset fields are left unchanged.
+    let identity = set_identity(&ms_nex, Some(HOSTNQN), None).await.unwrap();
+    assert_eq!(identity.nqn, HOSTNQN);
+    assert_eq!(identity.hostid, initial.hostid);
+    let identity = set_identity(&ms_nex, None, Some(HOSTID)).await.unwrap();
+    assert_eq!(identity.nqn, HOSTNQN);
+    assert_eq!(identity.hostid, HOSTID);
+    assert_eq!(get_identity(&ms_nex).await, identity);
+
+    let info = ms_nex
+        .lock()
+        .await
+        .host
+        .get_mayastor_info(())
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(
+        info.registration_info.unwrap().hostnqn.as_deref(),
+        Some(HOSTNQN)
+    );
+
+    nex.create().await.unwrap();
+
+    // Malformed identities are refused, and the identity is left unchanged.
+    for (nqn, hostid) in [
+        (Some("host-identity"), None),
+        (Some("nqn.2019-05.io.openebs:host identity"), None),
+        (None, Some("not-a-uuid")),
+        (None, Some("00000000-0000-0000-0000-000000000000")),
+    ] {
+        assert_eq!(
+            set_identity(&ms_nex, nqn, hostid).await.unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+    assert_eq!(get_identity(&ms_nex).await, identity);
+}
@@ -0,0 +1,162 @@
+pub mod common;
+
+use common::compose::{
+    rpc::v1::{host::SetOptionsRequest, GrpcConnect},
+    Binary,
+    Builder,
+};
+use tonic::Code;
+
+/// The rebuild knobs given on the command line are reported by the host
+/// options, and can be changed at runtime.
+#[tokio::test]
+async fn host_options_rebuild() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--rebuild-max-concurrent",
+                "2",
+                "--rebuild-client-qd-threshold",
+                "16",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let options = ms_0
+        .lock()
+        .await
+        .host
+        .get_options(())
+        .await
+        .unwrap()
+        .into_inner()
+        .options
+        .unwrap();
+    assert_eq!(options.rebuild_max_concurrent, 2);
+    assert_eq!(options.rebuild_max_per_nexus, 0);
+    assert_eq!(options.rebuild_client_qd_threshold, 16);
+
+    // Unset options are left unchanged.
+    let options = ms_0
+        .lock()
+        .await
+        .host
+        .set_options(SetOptionsRequest {
+            rebuild_max_per_nexus: Some(1),
+            rebuild_client_qd_threshold: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .options
+        .unwrap();
+    assert_eq!(options.rebuild_max_concurrent, 2);
+    assert_eq!(options.rebuild_max_per_nexus, 1);
+    assert_eq!(options.rebuild_client_qd_threshold, 0);
+}
+
+/// The NVMe and nexus options can be changed at runtime, and inconsistent
+/// changes are refused without changing any option.
+#[tokio::test]
+async fn host_options_update() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let initial = ms_0
+        .lock()
+        .await
+        .host
+        .get_options(())
+        .await
+        .unwrap()
+        .into_inner()
+        .options
+        .unwrap();
+
+    let options = ms_0
+        .lock()
+        .await
+        .host
+        .set_options(SetOptionsRequest {
+            partial_rebuild: Some(!initial.partial_rebuild),
+            nexus_reset: Some(!initial.nexus_reset),
+            nvme_timeout_us: Some(5_000_000),
+            nvme_timeout_admin_us: Some(10_000_000),
+            nvme_keep_alive_timeout_ms: Some(20_000),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .options
+        .unwrap();
+    assert_eq!(options.nvmf_reservations, initial.nvmf_reservations);
+    assert_eq!(options.partial_rebuild, !initial.partial_rebuild);
+    assert_eq!(options.nexus_reset, !initial.nexus_reset);
+    assert_eq!(options.nvme_timeout_us, 5_000_000);
+    assert_eq!(options.nvme_timeout_admin_us, 10_000_000);
+    assert_eq!(options.nvme_keep_alive_timeout_ms, 20_000);
+
+    for invalid in [
+        SetOptionsRequest {
+            nvme_keep_alive_timeout_ms: Some(0),
+            ..Default::default()
+        },
+        SetOptionsRequest {
+            nexus_reset: Some(initial.nexus_reset),
+            nvme_timeout_admin_us: Some(0),
+            ..Default::default()
+        },
+    ] {
+        let error = ms_0
+            .lock()
+            .await
+            .host
+            .set_options(invalid)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+    }
+
+    let current = ms_0
+        .lock()
+        .await
+        .host
+        .get_options(())
+        .await
+        .unwrap()
+        .into_inner()
+        .options
+        .unwrap();
+    assert_eq!(current, options);
+}
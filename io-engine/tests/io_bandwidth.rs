@@ -0,0 +1,71 @@
+use io_engine::core::{
+    mayastor_env_stop,
+    Cores,
+    IoBandwidth,
+    IoBandwidthClass,
+    MayastorCliArgs,
+    MayastorEnvironment,
+    Reactors,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use once_cell::sync::Lazy;
+
+pub mod common;
+
+// This test requires the system to have at least 2 cpus
+#[common::spdk_test]
+fn io_bandwidth_per_core() {
+    let args = MayastorCliArgs {
+        reactor_mask: "0x3".into(),
+        ..Default::default()
+    };
+
+    let ms = MayastorEnvironment::new(args);
+
+    static WAIT_FOR: Lazy<AtomicUsize> =
+        Lazy::new(|| AtomicUsize::new(Cores::count().into_iter().count()));
+
+    ms.start(|| {
+        let bandwidth = Arc::new(IoBandwidth::default());
+        let cores = Cores::count().into_iter().count() as u64;
+        assert!(cores > 1);
+
+        // Each core counts in its own slot.
+        Reactors::iter().for_each(|r| {
+            let bandwidth = bandwidth.clone();
+            r.send_future(async move {
+                let slot = bandwidth.slot(Cores::current());
+                bandwidth.record_slot(slot, IoBandwidthClass::Read, 4096);
+                bandwidth.record_slot(slot, IoBandwidthClass::Write, 512);
+                bandwidth.record(IoBandwidthClass::RebuildRead, 1024);
+                bandwidth.record(IoBandwidthClass::Write, 0);
+                WAIT_FOR.fetch_sub(1, Ordering::SeqCst);
+            });
+        });
+
+        while WAIT_FOR.load(Ordering::SeqCst) != 0 {
+            Reactors::master().poll_once();
+        }
+
+        // The counters of all cores are summed when read.
+        let stats = bandwidth.stats();
+        for window in [
+            stats.last_minute,
+            stats.last_hour,
+            stats.last_day,
+            stats.total,
+        ] {
+            assert_eq!(window.bytes_read, 4096 * cores);
+            assert_eq!(window.bytes_written, 512 * cores);
+            assert_eq!(window.rebuild_bytes_read, 1024 * cores);
+            assert_eq!(window.rebuild_bytes_written, 0);
+        }
+
+        mayastor_env_stop(0);
+    })
+    .unwrap();
+}
@@ -0,0 +1,204 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            stats::{
+                GetStatsRequest,
+                LatencyHistogram,
+                ResourceType,
+                SetIoHistogramRequest,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use io_engine::core::{
+    IoLatencyHistogram,
+    IoLatencyHistogramSnapshot,
+    IoType,
+    IO_HISTOGRAM_BUCKETS,
+};
+use tonic::Code;
+
+async fn set_histogram(
+    rpc: &SharedRpcHandle,
+    resource_type: ResourceType,
+    name: &str,
+    enable: bool,
+) -> Result<(), tonic::Status> {
+    rpc.lock()
+        .await
+        .stats
+        .set_io_histogram(SetIoHistogramRequest {
+            resource_type: resource_type as i32,
+            name: name.to_string(),
+            enable,
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn latency(
+    rpc: &SharedRpcHandle,
+    resource_type: ResourceType,
+    name: &str,
+    reset: bool,
+) -> Option<LatencyHistogram> {
+    let stats = rpc
+        .lock()
+        .await
+        .stats
+        .get_stats(GetStatsRequest {
+            resource_types: vec![resource_type as i32],
+            name: Some(name.to_string()),
+            reset,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .stats;
+    assert_eq!(stats.len(), 1);
+    stats.into_iter().next().unwrap().latency
+}
+
+#[test]
+fn io_histogram_buckets() {
+    let h = IoLatencyHistogram::default();
+    for latency_us in [0, 1, 2, 3, 4, 1000, u64::MAX] {
+        h.record(IoType::Write, latency_us);
+    }
+    h.record(IoType::Read, 5);
+    // Other I/O types are not counted.
+    h.record(IoType::Unmap, 5);
+
+    let snapshot = h.snapshot();
+    assert_eq!(snapshot.read.len(), IO_HISTOGRAM_BUCKETS);
+    assert_eq!(snapshot.read.iter().sum::<u64>(), 1);
+    assert_eq!(snapshot.read[2], 1);
+
+    // Latencies below 2us share the first bucket, and the last bucket
+    // counts all latencies above its lower bound.
+    assert_eq!(snapshot.write[0], 2);
+    assert_eq!(snapshot.write[1], 2);
+    assert_eq!(snapshot.write[2], 1);
+    assert_eq!(snapshot.write[9], 1);
+    assert_eq!(snapshot.write[IO_HISTOGRAM_BUCKETS - 1], 1);
+
+    h.reset();
+    assert_eq!(h.snapshot(), IoLatencyHistogramSnapshot {
+        read: vec![0; IO_HISTOGRAM_BUCKETS],
+        write: vec![0; IO_HISTOGRAM_BUCKETS],
+    });
+}
+
+#[test]
+fn io_histogram_percentile() {
+    assert_eq!(IoLatencyHistogramSnapshot::percentile_us(&[0; 4], 0.99), None);
+
+    // The percentile is reported as the upper bound of its bucket.
+    let buckets = [90, 8, 0, 2];
+    for (percentile, upper_us) in [(0.5, 2), (0.95, 4), (0.99, 16), (1.0, 16)]
+    {
+        assert_eq!(
+            IoLatencyHistogramSnapshot::percentile_us(&buckets, percentile),
+            Some(upper_us)
+        );
+    }
+}
+
+#[tokio::test]
+async fn io_histogram_nexus() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 60);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(20)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(20)
+        .with_replica(&repl);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    // Histograms are only reported once enabled.
+    assert!(latency(&ms_0, ResourceType::Nexus, "nexus0", false)
+        .await
+        .is_none());
+    set_histogram(&ms_0, ResourceType::Nexus, "nexus0", true)
+        .await
+        .unwrap();
+
+    test_write_to_nexus(&nex, DataSize::from_bytes(0), 8, DataSize::from_kb(4))
+        .await
+        .unwrap();
+
+    let h = latency(&ms_0, ResourceType::Nexus, "nexus0", true)
+        .await
+        .unwrap();
+    assert_eq!(h.write_buckets.len(), IO_HISTOGRAM_BUCKETS);
+    assert!(h.write_buckets.iter().sum::<u64>() >= 8);
+    assert!(h.write_p99_us.is_some());
+
+    // The histogram of the replica is not enabled with the nexus.
+    assert!(latency(&ms_0, ResourceType::Replica, "r0", false)
+        .await
+        .is_none());
+
+    // Reset clears the buckets.
+    let h = latency(&ms_0, ResourceType::Nexus, "nexus0", false)
+        .await
+        .unwrap();
+    assert_eq!(h.write_buckets.iter().sum::<u64>(), 0);
+    assert!(h.write_p99_us.is_none());
+
+    set_histogram(&ms_0, ResourceType::Nexus, "nexus0", false)
+        .await
+        .unwrap();
+    assert!(latency(&ms_0, ResourceType::Nexus, "nexus0", false)
+        .await
+        .is_none());
+
+    // Histograms cannot be enabled for unknown resources.
+    assert_eq!(
+        set_histogram(&ms_0, ResourceType::Nexus, "nexus1", true)
+            .await
+            .unwrap_err()
+            .code(),
+        Code::NotFound
+    );
+}
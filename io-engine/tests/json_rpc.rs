@@ -0,0 +1,160 @@
+pub mod common;
+
+use common::compose::{
+    rpc::v1::{
+        json::{JsonRpcBatchRequest, JsonRpcRequest},
+        GrpcConnect,
+        SharedRpcHandle,
+    },
+    Binary,
+    Builder,
+};
+use tonic::Code;
+
+fn request(method: &str) -> JsonRpcRequest {
+    JsonRpcRequest {
+        method: method.to_string(),
+        params: String::new(),
+    }
+}
+
+async fn call(rpc: &SharedRpcHandle, method: &str) -> Result<String, Code> {
+    rpc.lock()
+        .await
+        .json
+        .json_rpc_call(request(method))
+        .await
+        .map(|r| r.into_inner().result)
+        .map_err(|s| s.code())
+}
+
+#[tokio::test]
+async fn json_rpc_allow_list() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--json-rpc-allow",
+                "bdev_get_*,spdk_get_version",
+            ]),
+        )
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+
+    // Methods are allowed by name or by prefix.
+    assert!(call(&ms_0, "spdk_get_version").await.is_ok());
+    assert!(call(&ms_0, "bdev_get_bdevs").await.is_ok());
+    assert_eq!(
+        call(&ms_0, "framework_get_reactors").await.unwrap_err(),
+        Code::PermissionDenied
+    );
+    // A prefix must be marked as such.
+    assert_eq!(
+        call(&ms_0, "spdk_get_version_x").await.unwrap_err(),
+        Code::PermissionDenied
+    );
+
+    // Without an allow-list, all methods are allowed.
+    assert!(call(&ms_1, "framework_get_reactors").await.is_ok());
+}
+
+#[tokio::test]
+async fn json_rpc_batch() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--json-rpc-allow",
+                "bdev_get_*,spdk_get_version,no_such_method",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let batch = |stop_on_error| JsonRpcBatchRequest {
+        requests: vec![
+            request("spdk_get_version"),
+            request("framework_get_reactors"),
+            request("no_such_method"),
+            request("bdev_get_bdevs"),
+        ],
+        stop_on_error,
+    };
+
+    // Each method is reported separately, in order.
+    let items = ms_0
+        .lock()
+        .await
+        .json
+        .json_rpc_batch(batch(false))
+        .await
+        .unwrap()
+        .into_inner()
+        .items;
+    let methods = items.iter().map(|i| i.method.as_str()).collect::<Vec<_>>();
+    assert_eq!(methods, [
+        "spdk_get_version",
+        "framework_get_reactors",
+        "no_such_method",
+        "bdev_get_bdevs",
+    ]);
+
+    assert!(items[0].error.is_none());
+    assert!(!items[0].result.is_empty());
+
+    // Methods not allowed are not invoked.
+    let error = items[1].error.as_ref().unwrap();
+    assert_eq!(error.code, Code::PermissionDenied as i32);
+    assert!(items[1].result.is_empty());
+
+    // Methods failed by SPDK do not fail the batch.
+    assert!(items[2].error.is_some());
+    assert_ne!(
+        items[2].error.as_ref().unwrap().code,
+        Code::PermissionDenied as i32
+    );
+    assert!(items[3].error.is_none());
+
+    // The batch can be stopped at the first failed method.
+    let items = ms_0
+        .lock()
+        .await
+        .json
+        .json_rpc_batch(batch(true))
+        .await
+        .unwrap()
+        .into_inner()
+        .items;
+    assert_eq!(items.len(), 2);
+    assert!(items[0].error.is_none());
+    assert!(items[1].error.is_some());
+}
@@ -0,0 +1,130 @@
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+pub mod common;
+
+use common::MayastorTest;
+use io_engine::{
+    core::MayastorCliArgs,
+    lvs::{Error, Lvs, PoolCheckFinding, PoolCheckSeverity},
+    pool_backend::PoolArgs,
+};
+use nix::errno::Errno;
+
+const DISK_NAME: &str = "/tmp/disk_check.img";
+const BDEV_NAME: &str = "aio:///tmp/disk_check.img?blk_size=512";
+const POOL_NAME: &str = "pool_check";
+
+/// Rewrites the super block of the test disk with the given change.
+fn patch_super_block(patch: impl FnOnce(&mut [u8])) {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(DISK_NAME)
+        .unwrap();
+    let mut page = vec![0u8; 4096];
+    file.read_exact(&mut page).unwrap();
+    patch(&mut page);
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.write_all(&page).unwrap();
+    file.sync_all().unwrap();
+}
+
+/// Updates the CRC of the given super block.
+fn update_crc(page: &mut [u8]) {
+    let crc = crc::crc32::checksum_castagnoli(&page[.. 4092]);
+    page[4092 ..].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Checks the test disk, returning the number of errors and the findings.
+async fn check(repair: bool) -> Result<(u64, Vec<PoolCheckFinding>), Error> {
+    let mut findings = Vec::new();
+    let errors = Lvs::check(BDEV_NAME, repair, |f| findings.push(f)).await?;
+    Ok((errors, findings))
+}
+
+fn count(
+    findings: &[PoolCheckFinding],
+    severity: PoolCheckSeverity,
+) -> usize {
+    findings.iter().filter(|f| f.severity == severity).count()
+}
+
+#[tokio::test]
+async fn lvs_check() {
+    common::delete_file(&[DISK_NAME.into()]);
+    common::truncate_file(DISK_NAME, 64 * 1024);
+
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    // A disk without a pool has no super block.
+    ms.spawn(async {
+        let (errors, findings) = check(false).await.unwrap();
+        assert_eq!(errors, 1);
+        assert!(findings[0].message.contains("no blobstore super block"));
+    })
+    .await;
+
+    // An imported pool is not checked.
+    ms.spawn(async {
+        let pool = Lvs::create_or_import(PoolArgs {
+            name: POOL_NAME.to_string(),
+            disks: vec![BDEV_NAME.to_string()],
+            uuid: None,
+            force: false,
+        })
+        .await
+        .unwrap();
+        match check(false).await {
+            Err(Error::Invalid {
+                source, ..
+            }) => assert_eq!(source, Errno::EBUSY),
+            result => panic!("imported pool was checked: {result:?}"),
+        }
+        pool.export().await.unwrap();
+    })
+    .await;
+
+    // A cleanly exported pool is consistent.
+    ms.spawn(async {
+        let (errors, findings) = check(false).await.unwrap();
+        assert_eq!(errors, 0, "{findings:?}");
+        assert_eq!(count(&findings, PoolCheckSeverity::Warning), 0);
+        assert!(findings.last().unwrap().message.contains("complete"));
+    })
+    .await;
+
+    // A pool which was not shut down cleanly is repaired.
+    patch_super_block(|page| {
+        page[16 .. 20].copy_from_slice(&0u32.to_le_bytes());
+        update_crc(page);
+    });
+    ms.spawn(async {
+        let (errors, findings) = check(true).await.unwrap();
+        assert_eq!(errors, 0, "{findings:?}");
+        assert!(findings
+            .iter()
+            .any(|f| f.severity == PoolCheckSeverity::Warning
+                && f.message.contains("not shut down cleanly")));
+
+        let (_, findings) = check(false).await.unwrap();
+        assert_eq!(count(&findings, PoolCheckSeverity::Warning), 0);
+        assert!(Lvs::lookup(POOL_NAME).is_none());
+    })
+    .await;
+
+    // A corrupted super block is reported, and cannot be repaired.
+    patch_super_block(|page| page[100] ^= 0xff);
+    ms.spawn(async {
+        let (errors, findings) = check(true).await.unwrap();
+        assert_eq!(errors, 1);
+        assert!(findings
+            .iter()
+            .any(|f| f.message.contains("super block checksum mismatch")));
+    })
+    .await;
+
+    common::delete_file(&[DISK_NAME.into()]);
+}
@@ -0,0 +1,85 @@
+use std::{collections::HashMap, fs, os::unix::fs::symlink};
+
+pub mod common;
+
+use common::MayastorTest;
+use io_engine::{
+    core::MayastorCliArgs,
+    lvs::{Lvs, PoolDiskId},
+    pool_backend::PoolArgs,
+};
+
+const DISK_A: &str = "/tmp/disk_id_a.img";
+const DISK_B: &str = "/tmp/disk_id_b.img";
+const DISK_LINK: &str = "/tmp/disk_id_link";
+const PTPL_DIR: &str = "/tmp/disk_id_ptpl";
+const POOL_NAME: &str = "pool_disk_id";
+
+fn pool_args(disk: &str) -> PoolArgs {
+    PoolArgs {
+        name: POOL_NAME.to_string(),
+        disks: vec![format!("aio://{disk}?blk_size=512")],
+        uuid: None,
+        force: false,
+    }
+}
+
+fn cleanup() {
+    common::delete_file(&[DISK_A.into(), DISK_B.into()]);
+    fs::remove_file(DISK_LINK).ok();
+    fs::remove_dir_all(PTPL_DIR).ok();
+}
+
+/// A pool is imported from the stable link recorded for its disk when the
+/// configured path designates another disk.
+#[tokio::test]
+async fn lvs_disk_id() {
+    cleanup();
+    common::truncate_file(DISK_A, 64 * 1024);
+    common::truncate_file(DISK_B, 64 * 1024);
+    symlink(DISK_A, DISK_LINK).unwrap();
+
+    let args = MayastorCliArgs {
+        ptpl_dir: Some(PTPL_DIR.to_string()),
+        ..Default::default()
+    };
+    let ms = MayastorTest::new(args);
+
+    ms.spawn(async {
+        // Files have no stable link: nothing is recorded.
+        let pool = Lvs::create_or_import(pool_args(DISK_A)).await.unwrap();
+        assert_eq!(pool.disk_id(), None);
+        assert_eq!(pool.disk_device().as_deref(), Some(DISK_A));
+        pool.export().await.unwrap();
+
+        // Without a record, the configured disk is used as is.
+        assert!(Lvs::import_from_args(pool_args(DISK_B)).await.is_err());
+
+        // The disk was recorded as the link, which now differs from the
+        // configured path.
+        let record = PoolDiskId {
+            configured: pool_args(DISK_B).disks[0].clone(),
+            stable_path: DISK_LINK.to_string(),
+        };
+        let records = HashMap::from([(POOL_NAME.to_string(), record.clone())]);
+        fs::write(
+            format!("{PTPL_DIR}/pool-disks.json"),
+            serde_json::to_vec(&records).unwrap(),
+        )
+        .unwrap();
+
+        let pool = Lvs::import_from_args(pool_args(DISK_B)).await.unwrap();
+        assert_eq!(pool.disk_id(), Some(record));
+        assert_eq!(pool.disk_device().as_deref(), Some(DISK_A));
+
+        // Once destroyed, the pool is forgotten.
+        pool.destroy().await.unwrap();
+        let records = fs::read(format!("{PTPL_DIR}/pool-disks.json")).unwrap();
+        let records: HashMap<String, PoolDiskId> =
+            serde_json::from_slice(&records).unwrap();
+        assert!(records.is_empty());
+    })
+    .await;
+
+    cleanup();
+}
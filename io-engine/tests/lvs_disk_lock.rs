@@ -0,0 +1,108 @@
+pub mod common;
+
+use common::{
+    compose::{rpc::v1::GrpcConnect, Binary, Builder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+    MayastorTest,
+};
+
+use io_engine::{
+    core::MayastorCliArgs,
+    lvs::{Error, Lvs},
+    pool_backend::PoolArgs,
+};
+
+const POOL_SIZE: u64 = 60;
+const REPL_SIZE: u64 = 40;
+
+fn pool_args(disk: &str, force: bool) -> PoolArgs {
+    PoolArgs {
+        name: "pool_remote".to_string(),
+        disks: vec![disk.to_string()],
+        uuid: None,
+        force,
+    }
+}
+
+/// Imports a pool whose NVMe namespace another host has reserved: the import
+/// is refused unless forced, in which case the reservation is preempted.
+#[tokio::test]
+async fn lvs_nvme_reservation_conflict() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("lvs-disk-lock")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine")
+                .with_args(vec!["-l", "2", "-N", "ms_1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+
+    // The disk of the pool is a replica of ms_0 exported over NVMf, which
+    // supports reservations.
+    let mut pool_0 = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool_0)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_0.create().await.unwrap();
+    repl_0.create().await.unwrap();
+    repl_0.share().await.unwrap();
+    let disk = repl_0.shared_uri();
+
+    // ms_1 creates a pool on the disk and reserves it.
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool_remote")
+        .with_new_uuid()
+        .with_bdev(&disk);
+    pool_1.create().await.unwrap();
+
+    let ms = MayastorTest::new(MayastorCliArgs {
+        node_name: Some("ms_local".to_string()),
+        ..Default::default()
+    });
+
+    // The reservation of ms_1 is kept.
+    let d = disk.clone();
+    ms.spawn(async move {
+        match Lvs::create_or_import(pool_args(&d, false)).await {
+            Err(Error::DiskLocked {
+                owner, ..
+            }) => assert!(owner.contains("NVMe reservation"), "{owner}"),
+            Err(error) => panic!("unexpected import error: {error}"),
+            Ok(_) => panic!("pool reserved by another host was imported"),
+        }
+        assert!(Lvs::lookup("pool_remote").is_none());
+    })
+    .await;
+    assert_eq!(pool_1.get_pool().await.unwrap().name, "pool_remote");
+
+    // A forced import preempts the reservation of ms_1.
+    let d = disk.clone();
+    ms.spawn(async move {
+        let pool = Lvs::create_or_import(pool_args(&d, true)).await.unwrap();
+        assert_eq!(pool.name(), "pool_remote");
+        pool.export().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,144 @@
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom, Write},
+    time::Duration,
+};
+
+pub mod common;
+
+use common::{
+    compose::{Binary, Builder},
+    MayastorTest,
+};
+
+use io_engine::{
+    core::MayastorCliArgs,
+    lvs::{Error, ImportErrorReason, Lvs, PoolOwner},
+    persistent_store::{PersistentStore, PersistentStoreBuilder},
+    pool_backend::PoolArgs,
+};
+
+const ETCD_ENDPOINT: &str = "http://localhost:2379";
+const DISK_NAME: &str = "/tmp/disk_owner.img";
+const BDEV_NAME: &str = "aio:///tmp/disk_owner.img?blk_size=512";
+const POOL_NAME: &str = "pool_owner";
+const OTHER_NODE: &str = "other-node";
+
+/// Reads the blobstore super block of the test disk.
+fn read_super_block() -> Vec<u8> {
+    let mut file = OpenOptions::new().read(true).open(DISK_NAME).unwrap();
+    let mut page = vec![0u8; 4096];
+    file.read_exact(&mut page).unwrap();
+    page
+}
+
+/// Marks the blobstore of the test disk as not shut down cleanly, as if the
+/// node which imported it had crashed.
+fn mark_dirty() -> Vec<u8> {
+    let mut page = read_super_block();
+    page[16 .. 20].copy_from_slice(&0u32.to_le_bytes());
+    let crc = crc::crc32::checksum_castagnoli(&page[.. 4092]);
+    page[4092 ..].copy_from_slice(&crc.to_le_bytes());
+
+    let mut file = OpenOptions::new().write(true).open(DISK_NAME).unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.write_all(&page).unwrap();
+    file.sync_all().unwrap();
+    page
+}
+
+fn pool_args(force: bool) -> PoolArgs {
+    PoolArgs {
+        name: POOL_NAME.to_string(),
+        disks: vec![BDEV_NAME.to_string()],
+        uuid: None,
+        force,
+    }
+}
+
+#[tokio::test]
+async fn lvs_import_dirty_pool_owned_by_another_node() {
+    common::composer_init();
+
+    let _test = Builder::new()
+        .name("lvs-owner")
+        .add_container_spec(
+            common::compose::ContainerSpec::from_binary(
+                "etcd",
+                Binary::from_path(env!("ETCD_BIN")).with_args(vec![
+                    "--data-dir",
+                    "/tmp/etcd-data",
+                    "--advertise-client-urls",
+                    "http://0.0.0.0:2379",
+                    "--listen-client-urls",
+                    "http://0.0.0.0:2379",
+                ]),
+            )
+            .with_portmap("2379", "2379")
+            .with_portmap("2380", "2380"),
+        )
+        .with_logs(false)
+        .build()
+        .await
+        .unwrap();
+
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    common::delete_file(&[DISK_NAME.into()]);
+    common::truncate_file(DISK_NAME, 64 * 1024);
+
+    let pool_uuid = ms
+        .spawn(async {
+            PersistentStoreBuilder::new()
+                .with_endpoint(ETCD_ENDPOINT)
+                .with_timeout(Duration::from_secs(1))
+                .with_retries(5)
+                .connect()
+                .await;
+
+            let pool = Lvs::create_or_import(pool_args(false)).await.unwrap();
+            let uuid = pool.uuid();
+            pool.export().await.unwrap();
+            uuid
+        })
+        .await;
+
+    // Another node imported the pool and crashed.
+    let dirty = mark_dirty();
+    let key = format!("io-engine/pools/{pool_uuid}/owner");
+    ms.spawn(async move {
+        let owner = PoolOwner {
+            node: OTHER_NODE.to_string(),
+            imported_at: chrono::Utc::now(),
+        };
+        PersistentStore::put(&key, &owner).await.unwrap();
+    })
+    .await;
+
+    // The import is refused without touching the disk.
+    ms.spawn(async {
+        match Lvs::import_from_args(pool_args(false)).await {
+            Err(Error::Import {
+                reason:
+                    ImportErrorReason::OwnedBy {
+                        node,
+                    },
+                ..
+            }) => assert_eq!(node, OTHER_NODE),
+            Err(error) => panic!("unexpected import error: {error}"),
+            Ok(_) => panic!("pool owned by another node was imported"),
+        }
+        assert!(Lvs::lookup(POOL_NAME).is_none());
+    })
+    .await;
+    assert_eq!(read_super_block(), dirty);
+
+    // A forced import takes the pool over.
+    ms.spawn(async {
+        let pool = Lvs::import_from_args(pool_args(true)).await.unwrap();
+        pool.export().await.unwrap();
+    })
+    .await;
+
+    common::delete_file(&[DISK_NAME.into()]);
+}
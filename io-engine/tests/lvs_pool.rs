@@ -42,6 +42,7 @@ async fn lvs_pool_test() {
             name: "tpool".into(),
             disks: vec!["aio:///tmp/disk1.img".into()],
             uuid: None,
+            force: false,
         })
         .await
         .unwrap();
@@ -138,6 +139,7 @@ async fn lvs_pool_test() {
             name: "tpool2".to_string(),
             disks: vec!["malloc:///malloc0?size_mb=64".to_string()],
             uuid: None,
+            force: false,
         })
         .await
         .unwrap();
@@ -172,6 +174,7 @@ async fn lvs_pool_test() {
             name: "tpool".to_string(),
             disks: vec!["aio:///tmp/disk1.img".to_string()],
             uuid: None,
+            force: false,
         })
         .await
         .unwrap();
@@ -304,6 +307,7 @@ async fn lvs_pool_test() {
             name: "tpool".into(),
             disks: vec!["aio:///tmp/disk1.img".into()],
             uuid: None,
+            force: false,
         })
         .await
         .unwrap();
@@ -333,6 +337,7 @@ async fn lvs_pool_test() {
             name: "jpool".into(),
             disks: vec!["aio:///tmp/disk1.img".into()],
             uuid: None,
+            force: false,
         })
         .await
         .err()
@@ -348,6 +353,7 @@ async fn lvs_pool_test() {
             name: "tpool2".into(),
             disks: vec!["/tmp/disk2.img".into()],
             uuid: None,
+            force: false,
         })
         .await
         .unwrap();
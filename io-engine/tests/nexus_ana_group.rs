@@ -0,0 +1,176 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{
+                NvmeAnaState,
+                PublishNexusRequest,
+                SetNvmeAnaGroupStateRequest,
+                UnpublishNexusRequest,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+};
+use tonic::{Code, Status};
+
+async fn publish(
+    nex: &NexusBuilder,
+    ana_group: Option<u32>,
+) -> Result<(), Status> {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .publish_nexus(PublishNexusRequest {
+            uuid: nex.uuid(),
+            share: 1,
+            ana_group,
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn set_group_state(
+    rpc: &SharedRpcHandle,
+    ana_group: u32,
+    ana_state: i32,
+) -> Result<Vec<String>, Status> {
+    rpc.lock()
+        .await
+        .nexus
+        .set_nvme_ana_group_state(SetNvmeAnaGroupStateRequest {
+            ana_group,
+            ana_state,
+        })
+        .await
+        .map(|r| {
+            let mut names = r
+                .into_inner()
+                .nexuses
+                .into_iter()
+                .inspect(|n| assert_eq!(n.error, None, "{n:?}"))
+                .map(|n| n.name)
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        })
+}
+
+/// The ANA state of all the nexuses published in an ANA group is set
+/// together, and the group is reported in the nexus list.
+#[tokio::test]
+async fn nexus_ana_group() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine")
+                .with_args(vec!["-l", "1"])
+                .with_env("NEXUS_NVMF_ANA_ENABLE", "1"),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut nexuses = Vec::new();
+    for i in 0 .. 3 {
+        let mut nex = NexusBuilder::new(ms_0.clone())
+            .with_name(&format!("nexus{i}"))
+            .with_new_uuid()
+            .with_size_mb(8)
+            .with_bdev(&format!("malloc:///m{i}?size_mb=16"));
+        nex.create().await.unwrap();
+        nexuses.push(nex);
+    }
+
+    // ANA group 0 is reserved.
+    let err = publish(&nexuses[0], Some(0)).await.unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    publish(&nexuses[0], Some(7)).await.unwrap();
+    publish(&nexuses[1], Some(7)).await.unwrap();
+    publish(&nexuses[2], None).await.unwrap();
+    for (nex, ana_group) in nexuses.iter().zip([Some(7), Some(7), None]) {
+        assert_eq!(nex.get_nexus().await.unwrap().ana_group, ana_group);
+    }
+
+    let names = set_group_state(
+        &ms_0,
+        7,
+        NvmeAnaState::NvmeAnaNonOptimizedState as i32,
+    )
+    .await
+    .unwrap();
+    assert_eq!(names, ["nexus0", "nexus1"]);
+    for (nex, ana_state) in nexuses.iter().zip([
+        NvmeAnaState::NvmeAnaNonOptimizedState,
+        NvmeAnaState::NvmeAnaNonOptimizedState,
+        NvmeAnaState::NvmeAnaOptimizedState,
+    ]) {
+        let nexus = nex.get_nexus().await.unwrap();
+        assert_eq!(nexus.ana_state, ana_state as i32, "{nexus:?}");
+    }
+
+    // A nexus which fails to be published again keeps its ANA group.
+    nexuses[0]
+        .rpc()
+        .lock()
+        .await
+        .nexus
+        .publish_nexus(PublishNexusRequest {
+            uuid: nexuses[0].uuid(),
+            share: 0,
+            ana_group: Some(8),
+            ..Default::default()
+        })
+        .await
+        .expect_err("A nexus shared over NVMf cannot be shared over NBD");
+    assert_eq!(nexuses[0].get_nexus().await.unwrap().ana_group, Some(7));
+
+    // A group without nexuses is left alone, and an unknown state is refused.
+    let names = set_group_state(
+        &ms_0,
+        8,
+        NvmeAnaState::NvmeAnaInaccessibleState as i32,
+    )
+    .await
+    .unwrap();
+    assert!(names.is_empty());
+    let err = set_group_state(&ms_0, 7, 99).await.unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    // Unsharing a nexus takes it out of its group.
+    ms_0.lock()
+        .await
+        .nexus
+        .unpublish_nexus(UnpublishNexusRequest {
+            uuid: nexuses[1].uuid(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(nexuses[1].get_nexus().await.unwrap().ana_group, None);
+    let names = set_group_state(
+        &ms_0,
+        7,
+        NvmeAnaState::NvmeAnaOptimizedState as i32,
+    )
+    .await
+    .unwrap();
+    assert_eq!(names, ["nexus0"]);
+}
@@ -0,0 +1,130 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{ChildState, Nexus, RemoveChildNexusRequest},
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 8;
+
+async fn evacuate(
+    nex: &NexusBuilder,
+    repl: &ReplicaBuilder,
+    min_synced_children: u32,
+    timeout: Duration,
+) -> Result<Nexus, Status> {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .remove_child_nexus(RemoveChildNexusRequest {
+            uuid: nex.uuid(),
+            uri: repl.bdev(),
+            min_synced_children,
+            evacuation_timeout_ms: timeout.as_millis() as u64,
+            ..Default::default()
+        })
+        .await
+        .map(|r| r.into_inner().nexus.unwrap())
+}
+
+/// A child is only removed with evacuation once enough of the other children
+/// are synced, out-of-sync ones being rebuilt meanwhile.
+#[tokio::test]
+async fn nexus_child_evacuate() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                // Keep the rebuilds running for a few seconds.
+                "--rebuild-max-bandwidth",
+                "2MiB",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+    let mut repls = Vec::new();
+    for i in 0 .. 4 {
+        let mut repl = ReplicaBuilder::new(ms_0.clone())
+            .with_pool(&pool)
+            .with_name(&format!("r{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(false);
+        repl.create().await.unwrap();
+        repls.push(repl);
+    }
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repls[0])
+        .with_local_replica(&repls[1]);
+    nex.create().await.unwrap();
+
+    // Only one other child: the redundancy cannot be kept.
+    let err = evacuate(&nex, &repls[0], 2, Duration::from_secs(5))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::FailedPrecondition);
+    assert_eq!(nex.get_nexus().await.unwrap().children.len(), 2);
+
+    // The added child is rebuilt before the first one is removed.
+    nex.add_replica(&repls[2], true).await.unwrap();
+    let nexus = evacuate(&nex, &repls[0], 2, Duration::from_secs(60))
+        .await
+        .unwrap();
+    let mut uris: Vec<_> = nexus.children.iter().map(|c| &c.uri).collect();
+    uris.sort();
+    let mut expected = vec![repls[1].bdev(), repls[2].bdev()];
+    expected.sort();
+    assert_eq!(uris, expected.iter().collect::<Vec<_>>());
+    assert!(nexus
+        .children
+        .iter()
+        .all(|c| c.state == ChildState::Online as i32));
+
+    // A rebuild which does not complete in time fails the evacuation.
+    nex.add_replica(&repls[3], true).await.unwrap();
+    let err = evacuate(&nex, &repls[1], 2, Duration::from_secs(1))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::FailedPrecondition);
+    assert_eq!(nex.get_nexus().await.unwrap().children.len(), 3);
+
+    nex.wait_children_online(Duration::from_secs(60))
+        .await
+        .unwrap();
+}
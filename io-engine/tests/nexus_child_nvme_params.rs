@@ -0,0 +1,111 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{rpc::v1::GrpcConnect, Binary, Builder},
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+/// The NVMe parameters negotiated with the target are reported for the
+/// remote children of a nexus, as long as they are connected.
+#[tokio::test]
+async fn nexus_child_nvme_params() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_1 = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool_1)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_1.create().await.unwrap();
+    repl_1.create().await.unwrap();
+    repl_1.share().await.unwrap();
+
+    let mut pool_local = PoolBuilder::new(ms_nex.clone())
+        .with_name("pool_local")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_local = ReplicaBuilder::new(ms_nex.clone())
+        .with_pool(&pool_local)
+        .with_name("r_local")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_local.create().await.unwrap();
+    repl_local.create().await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_1)
+        .with_local_replica(&repl_local);
+    nex_0.create().await.unwrap();
+
+    let child = nex_0.get_nexus_replica_child(&repl_1).await.unwrap();
+    let params = child.nvme_params.unwrap();
+    assert!(params.num_io_queues > 0, "{params:?}");
+    assert!(params.io_queue_size > 0, "{params:?}");
+    assert!(params.max_xfer_size > 0, "{params:?}");
+    assert!(params.keep_alive_timeout_ms > 0, "{params:?}");
+
+    // Local children are not connected over NVMe-oF.
+    let child = nex_0.get_nexus_replica_child(&repl_local).await.unwrap();
+    assert_eq!(child.nvme_params, None);
+
+    // Once closed, the remote child has no controller to report on. The
+    // device of an offlined child is destroyed in the background.
+    nex_0
+        .offline_child_replica_wait(&repl_1, Duration::from_secs(10))
+        .await
+        .unwrap();
+    let start = Instant::now();
+    loop {
+        let child = nex_0.get_nexus_replica_child(&repl_1).await.unwrap();
+        if child.nvme_params.is_none() {
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(10), "{child:?}");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    nex_0.online_child_replica(&repl_1).await.unwrap();
+    nex_0
+        .wait_children_online(Duration::from_secs(60))
+        .await
+        .unwrap();
+    let child = nex_0.get_nexus_replica_child(&repl_1).await.unwrap();
+    assert!(child.nvme_params.is_some());
+}
@@ -0,0 +1,141 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{ChildState, ChildStateReason},
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+/// Waits for the child of the given replica to reach the given state and
+/// reason.
+async fn wait_child(
+    nex: &NexusBuilder,
+    repl: &ReplicaBuilder,
+    state: ChildState,
+    reason: ChildStateReason,
+    timeout: Duration,
+) {
+    let start = Instant::now();
+    loop {
+        let child = nex.get_nexus_replica_child(repl).await.unwrap();
+        if child.state == state as i32 && child.state_reason == reason as i32 {
+            return;
+        }
+        assert!(
+            start.elapsed() < timeout,
+            "child did not reach {state:?} ({reason:?}): {child:?}"
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// A remote child failing the health probe is faulted without any I/O, and
+/// brought back online once it responds again.
+#[tokio::test]
+async fn nexus_child_probe() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_0 = PoolBuilder::new(ms_nex.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_0 = ReplicaBuilder::new(ms_nex.clone())
+        .with_pool(&pool_0)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_0.create().await.unwrap();
+    repl_0.create().await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem1", POOL_SIZE);
+    let mut repl_1 = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool_1)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_1.create().await.unwrap();
+    repl_1.create().await.unwrap();
+    repl_1.share().await.unwrap();
+
+    // Probing is disabled by default.
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_1);
+    nex_0.create().await.unwrap();
+    assert_eq!(nex_0.get_nexus().await.unwrap().child_probe_interval_ms, 0);
+    nex_0.destroy().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_child_probe_interval(Duration::from_millis(500))
+        .with_local_replica(&repl_0)
+        .with_replica(&repl_1);
+    nex.create().await.unwrap();
+    assert_eq!(nex.get_nexus().await.unwrap().child_probe_interval_ms, 500);
+    nex.wait_children_online(Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    // With no I/O to the nexus, only the probe detects the lost child.
+    test.pause("ms_1").await.unwrap();
+    wait_child(
+        &nex,
+        &repl_1,
+        ChildState::Faulted,
+        ChildStateReason::AdminFailed,
+        Duration::from_secs(30),
+    )
+    .await;
+    assert_eq!(
+        nex.get_nexus_replica_child(&repl_0).await.unwrap().state,
+        ChildState::Online as i32
+    );
+
+    test.thaw("ms_1").await.unwrap();
+    nex.wait_children_online(Duration::from_secs(60))
+        .await
+        .unwrap();
+}
@@ -553,6 +553,7 @@ async fn init_ms_etcd_test() -> ComposeTest {
                 name: POOL_NAME_0.to_string(),
                 disks: vec![BDEV_NAME_0.to_string()],
                 uuid: None,
+                force: false,
             })
             .await
             .unwrap();
@@ -567,6 +568,7 @@ async fn init_ms_etcd_test() -> ComposeTest {
                 name: POOL_NAME_1.to_string(),
                 disks: vec![DISK_NAME_1.to_string()],
                 uuid: None,
+                force: false,
             })
             .await
             .unwrap();
@@ -0,0 +1,149 @@
+pub mod common;
+
+use std::{collections::HashMap, time::Duration};
+
+use common::MayastorTest;
+use io_engine::{
+    bdev::nexus::{
+        consistency_group_create,
+        consistency_group_destroy,
+        consistency_group_lookup,
+        consistency_groups,
+        nexus_create,
+        nexus_lookup_uuid_mut,
+        Error as NexusError,
+    },
+    core::{MayastorCliArgs, SnapshotParams},
+};
+use uuid::Uuid;
+
+static NEXUS_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Creates a nexus on a malloc child, returning its UUID.
+async fn create_nexus(name: &str) -> String {
+    let uuid = Uuid::new_v4().to_string();
+    let children = vec![format!("malloc:///{name}_child?size_mb=32")];
+    nexus_create(name, NEXUS_SIZE, Some(&uuid), &children)
+        .await
+        .unwrap();
+    uuid
+}
+
+fn is_frozen(uuid: &str) -> bool {
+    nexus_lookup_uuid_mut(uuid).unwrap().freeze_info().is_some()
+}
+
+/// Consistency groups freeze and thaw their nexuses as a whole, and a nexus
+/// can be a member of a single group.
+#[tokio::test]
+async fn nexus_consistency_group() {
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        let n0 = create_nexus("cg_nexus0").await;
+        let n1 = create_nexus("cg_nexus1").await;
+        let n2 = create_nexus("cg_nexus2").await;
+
+        let group =
+            consistency_group_create("cg0", vec![n0.clone(), n1.clone()])
+                .unwrap();
+        assert_eq!(group.name, "cg0");
+        assert_eq!(group.nexuses, vec![n0.clone(), n1.clone()]);
+        assert_eq!(consistency_group_lookup("cg0").unwrap().nexuses.len(), 2);
+        assert!(group.frozen_until().is_none());
+
+        assert!(matches!(
+            consistency_group_create("", vec![n2.clone()]),
+            Err(NexusError::InvalidArguments { .. })
+        ));
+        assert!(matches!(
+            consistency_group_create("cg1", vec![]),
+            Err(NexusError::InvalidArguments { .. })
+        ));
+        assert!(matches!(
+            consistency_group_create("cg1", vec![n2.clone(), n2.clone()]),
+            Err(NexusError::InvalidArguments { .. })
+        ));
+        assert!(matches!(
+            consistency_group_create("cg1", vec![Uuid::new_v4().to_string()]),
+            Err(NexusError::NexusNotFound { .. })
+        ));
+        assert!(matches!(
+            consistency_group_create("cg0", vec![n2.clone()]),
+            Err(NexusError::ConsistencyGroupExists { .. })
+        ));
+        assert!(matches!(
+            consistency_group_create("cg1", vec![n2.clone(), n1.clone()]),
+            Err(NexusError::ConsistencyGroupMember { .. })
+        ));
+
+        // All the nexuses of the group are frozen, and thawed, together.
+        let freezes = group.freeze(Duration::from_secs(30)).await.unwrap();
+        assert_eq!(freezes.len(), 2);
+        assert!(is_frozen(&n0) && is_frozen(&n1));
+        assert!(!is_frozen(&n2));
+        let deadline = freezes.iter().map(|f| f.deadline).min();
+        assert_eq!(group.frozen_until(), deadline);
+        group.thaw().await.unwrap();
+        assert!(!is_frozen(&n0) && !is_frozen(&n1));
+        assert!(group.frozen_until().is_none());
+
+        // The timeout is checked for each nexus.
+        assert!(matches!(
+            group.freeze(Duration::from_secs(3600)).await,
+            Err(NexusError::InvalidArguments { .. })
+        ));
+        assert!(!is_frozen(&n0));
+
+        // A nexus failing to freeze thaws the ones already frozen.
+        nexus_lookup_uuid_mut(&n1)
+            .unwrap()
+            .freeze(Duration::from_secs(30))
+            .await
+            .unwrap();
+        assert!(group.freeze(Duration::from_secs(30)).await.is_err());
+        assert!(!is_frozen(&n0));
+        assert!(is_frozen(&n1));
+        assert!(group.frozen_until().is_none());
+        group.thaw().await.unwrap();
+        assert!(!is_frozen(&n1));
+
+        // The replicas of every member, and only of the members, are needed
+        // to snapshot the group.
+        let replicas = HashMap::from([(n0.clone(), vec![])]);
+        assert!(matches!(
+            group
+                .create_snapshot(SnapshotParams::default(), replicas)
+                .await,
+            Err(NexusError::InvalidArguments { .. })
+        ));
+        let replicas = HashMap::from([
+            (n0.clone(), vec![]),
+            (n1.clone(), vec![]),
+            (n2.clone(), vec![]),
+        ]);
+        assert!(matches!(
+            group
+                .create_snapshot(SnapshotParams::default(), replicas)
+                .await,
+            Err(NexusError::InvalidArguments { .. })
+        ));
+
+        // Once destroyed, the nexuses of a group can join another one.
+        consistency_group_destroy("cg0").unwrap();
+        assert!(matches!(
+            consistency_group_destroy("cg0"),
+            Err(NexusError::ConsistencyGroupNotFound { .. })
+        ));
+        assert!(matches!(
+            consistency_group_lookup("cg0"),
+            Err(NexusError::ConsistencyGroupNotFound { .. })
+        ));
+        consistency_group_create("cg1", vec![n2, n1]).unwrap();
+        let groups = consistency_groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "cg1");
+        consistency_group_destroy("cg1").unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,159 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{RebuildStatsRequest, RebuildStatsResponse},
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    fio::{Fio, FioJob},
+    nexus::{test_fio_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 50;
+
+async fn rebuild_stats(nex: &NexusBuilder, uri: &str) -> RebuildStatsResponse {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_stats(RebuildStatsRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+/// Reads the given range of the nexus.
+async fn read_nexus(nex: &NexusBuilder, offset: DataSize, size: DataSize) {
+    test_fio_to_nexus(
+        nex,
+        Fio::new().with_job(
+            FioJob::new()
+                .with_rw("read")
+                .with_bs(4096)
+                .with_offset(offset)
+                .with_size(size),
+        ),
+    )
+    .await
+    .unwrap();
+}
+
+/// A read which misses the local child being rebuilt populates it: the
+/// segments read are copied ahead of the rebuild, and further reads of them
+/// are served by the local child.
+#[tokio::test]
+async fn nexus_copy_on_read_populate() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "2",
+                // Keep the rebuild running while the nexus is read.
+                "--rebuild-max-bandwidth",
+                "1MiB",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_1 = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool_1)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_1.create().await.unwrap();
+    repl_1.create().await.unwrap();
+    repl_1.share().await.unwrap();
+
+    let mut pool_local = PoolBuilder::new(ms_nex.clone())
+        .with_name("pool_local")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_local = ReplicaBuilder::new(ms_nex.clone())
+        .with_pool(&pool_local)
+        .with_name("r_local")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_local.create().await.unwrap();
+    repl_local.create().await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_1)
+        .with_copy_on_read(true);
+    nex_0.create().await.unwrap();
+    nex_0.publish().await.unwrap();
+
+    // Add the local child: it is rebuilt slowly, from its start.
+    nex_0.add_replica(&repl_local, false).await.unwrap();
+    let uri = repl_local.bdev();
+
+    // The end of the nexus is not rebuilt yet: reads miss the local child.
+    let offset = DataSize::from_mb(40);
+    let size = DataSize::from_mb(1);
+    read_nexus(&nex_0, offset, size).await;
+
+    let stats = rebuild_stats(&nex_0, &uri).await;
+    assert!(stats.progress < 50, "{stats:?}");
+    assert!(stats.cor_misses.unwrap() > 0, "{stats:?}");
+
+    // The segments read are copied to the local child ahead of the rebuild.
+    let mut populated = false;
+    for _ in 0 .. 50 {
+        let stats = rebuild_stats(&nex_0, &uri).await;
+        if stats.cor_populated.unwrap()
+            * stats.blocks_per_task
+            * stats.block_size
+            >= size.bytes()
+        {
+            populated = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(populated, "{:?}", rebuild_stats(&nex_0, &uri).await);
+
+    // Now the local child serves the reads of these segments.
+    let before = rebuild_stats(&nex_0, &uri).await;
+    read_nexus(&nex_0, offset, size).await;
+    let after = rebuild_stats(&nex_0, &uri).await;
+    assert!(after.cor_hits.unwrap() > before.cor_hits.unwrap());
+    assert_eq!(after.cor_misses, before.cor_misses);
+}
@@ -0,0 +1,143 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            host::RebalanceConnectionsRequest,
+            stats::{CoreIoStats, GetStatsRequest, ResourceType},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn core_stats(
+    rpc: &SharedRpcHandle,
+    resource_type: ResourceType,
+    name: &str,
+) -> Result<Vec<CoreIoStats>, Status> {
+    let stats = rpc
+        .lock()
+        .await
+        .stats
+        .get_stats(GetStatsRequest {
+            resource_types: vec![resource_type as i32],
+            name: Some(name.to_string()),
+            reset: false,
+        })
+        .await?
+        .into_inner()
+        .stats;
+    assert_eq!(stats.len(), 1, "{resource_type:?} '{name}'");
+    Ok(stats.into_iter().next().unwrap().cores)
+}
+
+/// The I/Os of a nexus are counted on the cores they are submitted on, and
+/// the NVMf connections are only moved off a poll group when it is hot.
+#[tokio::test]
+async fn nexus_core_stats() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1,2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repl);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    let cores = core_stats(&ms_0, ResourceType::Nexus, "nexus0")
+        .await
+        .unwrap();
+    let mut ids: Vec<_> = cores.iter().map(|c| c.core).collect();
+    ids.sort();
+    assert_eq!(ids, [1, 2]);
+    assert!(cores.iter().all(|c| c.num_ops == 0 && c.bytes == 0));
+
+    test_write_to_nexus(&nex, DataSize::from_bytes(0), 8, DataSize::from_kb(4))
+        .await
+        .unwrap();
+
+    let cores = core_stats(&ms_0, ResourceType::Nexus, "nexus0")
+        .await
+        .unwrap();
+    assert!(cores.iter().all(|c| c.queue_depth == 0), "{cores:?}");
+    assert!(cores.iter().map(|c| c.num_ops).sum::<u64>() >= 8, "{cores:?}");
+    assert!(
+        cores.iter().map(|c| c.bytes).sum::<u64>() >= 8 * 4096,
+        "{cores:?}"
+    );
+
+    // Only nexuses have per-core stats.
+    let cores = core_stats(&ms_0, ResourceType::Replica, "r0").await.unwrap();
+    assert!(cores.is_empty());
+    let err = ms_0
+        .lock()
+        .await
+        .stats
+        .get_stats(GetStatsRequest {
+            resource_types: vec![99],
+            name: None,
+            reset: false,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+
+    // Without connections, no poll group is hot.
+    let rebalanced = ms_0
+        .lock()
+        .await
+        .host
+        .rebalance_connections(RebalanceConnectionsRequest {
+            threshold: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let mut ids: Vec<_> =
+        rebalanced.poll_groups.iter().map(|p| p.core).collect();
+    ids.sort();
+    assert_eq!(ids, [1, 2]);
+    assert!(rebalanced.poll_groups.iter().all(|p| p.io_qpairs == 0));
+    assert_eq!(rebalanced.moved_from_core, None);
+}
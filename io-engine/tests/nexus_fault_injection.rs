@@ -20,6 +20,7 @@ use common::{
     replica::ReplicaBuilder,
     test::{add_fault_injection, list_fault_injections},
 };
+use io_engine::core::fault_injection::{FaultInjection, FaultIoStage};
 
 static POOL_SIZE: u64 = 60;
 static REPL_SIZE: u64 = 50;
@@ -173,6 +174,11 @@ async fn nexus_fault_injection_write() {
     test_injection_uri("domain=nexus&op=write&offset=64").await;
 }
 
+#[tokio::test]
+async fn nexus_fault_injection_write_completion() {
+    test_injection_uri("domain=nexus&op=write&stage=compl&offset=64").await;
+}
+
 #[tokio::test]
 async fn nexus_fault_injection_read_submission() {
     test_injection_uri("domain=nexus&op=read&stage=submit&offset=64").await;
@@ -183,6 +189,22 @@ async fn nexus_fault_injection_read() {
     test_injection_uri("domain=nexus&op=read&offset=64").await;
 }
 
+#[test]
+fn nexus_fault_injection_stage() {
+    let stage = |s: &str| {
+        FaultInjection::from_uri(&format!("inject://dev?stage={s}"))
+            .map(|inj| inj.fault_io_stage)
+    };
+
+    for s in ["submit", "s", "submission"] {
+        assert_eq!(stage(s).unwrap(), FaultIoStage::Submission, "{s}");
+    }
+    for s in ["compl", "c", "completion"] {
+        assert_eq!(stage(s).unwrap(), FaultIoStage::Completion, "{s}");
+    }
+    assert!(stage("done").is_err());
+}
+
 #[tokio::test]
 async fn nexus_fault_injection_time_based() {
     let test = create_compose_test().await;
@@ -1067,6 +1067,7 @@ async fn nexus_io_write_zeroes() {
                 name: POOL_NAME.to_string(),
                 disks: vec![BDEVNAME1.to_string()],
                 uuid: None,
+                force: false,
             })
             .await
             .unwrap();
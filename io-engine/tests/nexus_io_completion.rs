@@ -0,0 +1,470 @@
+#![cfg(feature = "fault-injection")]
+
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{
+                ChildState,
+                NexusEnospcPolicy,
+                NexusFlushTarget,
+                NexusIoErrorStats,
+            },
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+        ComposeTest,
+    },
+    file_io::DataSize,
+    fio::{Fio, FioJob},
+    nexus::{test_fio_to_nexus, NexusBuilder},
+    nvme::nvme_flush,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+    test::add_fault_injection,
+};
+
+use io_engine::bdev::nexus::{
+    NEXUS_ENOSPC_RETRY_INTERVAL,
+    NEXUS_IO_TRANSIENT_RETRIES,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 50;
+
+/// Returns the binary of the nexus node.
+fn nexus_node() -> Binary {
+    Binary::from_dbg("io-engine").with_args(vec!["-l", "3", "-Fcolor,compact"])
+}
+
+/// Creates a composer test with two replica nodes and a nexus node.
+async fn create_compose_test() -> ComposeTest {
+    create_compose_test_with(nexus_node()).await
+}
+
+/// Creates a composer test with two replica nodes and the given nexus node.
+async fn create_compose_test_with(ms_nex: Binary) -> ComposeTest {
+    common::composer_init();
+
+    Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .add_container_bin("ms_nex", ms_nex)
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap()
+}
+
+/// Creates a replica on each of the replica nodes, and a published nexus of
+/// them configured by the given function.
+async fn create_nexus(
+    test: &ComposeTest,
+    configure: impl FnOnce(NexusBuilder) -> NexusBuilder,
+) -> NexusBuilder {
+    let conn = GrpcConnect::new(test);
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_nex)
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE);
+
+    for (i, ms) in ["ms_0", "ms_1"].into_iter().enumerate() {
+        let ms = conn.grpc_handle_shared(ms).await.unwrap();
+
+        let mut pool = PoolBuilder::new(ms.clone())
+            .with_name(&format!("pool{i}"))
+            .with_new_uuid()
+            .with_malloc("mem0", POOL_SIZE);
+
+        let mut repl = ReplicaBuilder::new(ms)
+            .with_pool(&pool)
+            .with_name(&format!("r{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(false);
+
+        pool.create().await.unwrap();
+        repl.create().await.unwrap();
+        repl.share().await.unwrap();
+
+        nex = nex.with_replica(&repl);
+    }
+
+    let mut nex = configure(nex);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+    nex
+}
+
+/// Returns the device names of the nexus children.
+async fn child_devices(nex: &NexusBuilder) -> Vec<String> {
+    nex.get_nexus()
+        .await
+        .unwrap()
+        .children
+        .into_iter()
+        .map(|c| c.device_name.unwrap())
+        .collect()
+}
+
+/// Injects a fault into the child I/Os of the given device.
+async fn inject(nex: &NexusBuilder, dev: &str, inj_part: &str) {
+    let inj_uri = format!("inject://{dev}?domain=nexus&{inj_part}");
+    add_fault_injection(nex.rpc(), &inj_uri).await.unwrap();
+}
+
+/// Runs direct I/Os of the given kind on the given range of the nexus.
+async fn run_io(
+    nex: &NexusBuilder,
+    rw: &str,
+    offset: DataSize,
+    size: DataSize,
+) {
+    test_fio_to_nexus(
+        nex,
+        Fio::new().with_job(
+            FioJob::new()
+                .with_rw(rw)
+                .with_bs(4096)
+                .with_offset(offset)
+                .with_size(size),
+        ),
+    )
+    .await
+    .unwrap();
+}
+
+async fn io_errors(nex: &NexusBuilder) -> NexusIoErrorStats {
+    nex.get_nexus().await.unwrap().io_errors.unwrap()
+}
+
+async fn assert_children_online(nex: &NexusBuilder) {
+    let children = nex.get_nexus().await.unwrap().children;
+    assert!(
+        children
+            .iter()
+            .all(|c| c.state == ChildState::Online as i32),
+        "{children:?}"
+    );
+}
+
+/// Under the `Freeze` ENOSPC policy, a write failing with ENOSPC on all
+/// children is parked and resubmitted later, without faulting the children.
+/// A write failing with ENOSPC on some children only is resubmitted at once.
+#[tokio::test]
+async fn nexus_io_completion_enospc_park() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| {
+        n.with_enospc_policy(NexusEnospcPolicy::Freeze)
+    })
+    .await;
+    let devs = child_devices(&nex).await;
+
+    // Out of space on all children: the write is parked until the retry.
+    for dev in &devs {
+        inject(&nex, dev, "op=write&type=nospace&count=1").await;
+    }
+
+    let start = Instant::now();
+    run_io(&nex, "write", DataSize::from_bytes(0), DataSize::from_kb(4)).await;
+    assert!(start.elapsed() >= NEXUS_ENOSPC_RETRY_INTERVAL);
+    assert_children_online(&nex).await;
+
+    // Out of space on one child: the write is resubmitted, and the child
+    // kept as it succeeds the second time.
+    inject(&nex, &devs[0], "op=write&type=nospace&count=1").await;
+
+    let start = Instant::now();
+    run_io(&nex, "write", DataSize::from_bytes(0), DataSize::from_kb(4)).await;
+    assert!(start.elapsed() < NEXUS_ENOSPC_RETRY_INTERVAL);
+    assert_children_online(&nex).await;
+}
+
+/// A write failing with a transient error is retried without faulting the
+/// child, until the error persists for more than the allowed retries.
+#[tokio::test]
+async fn nexus_io_completion_transient_retry() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| n).await;
+    let devs = child_devices(&nex).await;
+
+    // The transport error clears before the retries are exhausted.
+    let retries = NEXUS_IO_TRANSIENT_RETRIES as u64 - 1;
+    inject(&nex, &devs[0], &format!("op=write&count={retries}")).await;
+
+    run_io(&nex, "write", DataSize::from_bytes(0), DataSize::from_kb(4)).await;
+    assert_children_online(&nex).await;
+
+    let errors = io_errors(&nex).await;
+    assert_eq!(errors.transport, retries);
+    assert_eq!(errors.retried, retries);
+
+    // The transport error persists: the child is faulted once the retries
+    // are exhausted, and the write completes on the other child.
+    inject(&nex, &devs[0], "op=write&offset=64").await;
+
+    run_io(
+        &nex,
+        "write",
+        DataSize::from_blocks(64, 512),
+        DataSize::from_kb(4),
+    )
+    .await;
+
+    let children = nex.get_nexus().await.unwrap().children;
+    assert_eq!(children[0].state, ChildState::Faulted as i32);
+    assert_eq!(children[1].state, ChildState::Online as i32);
+
+    let errors = io_errors(&nex).await;
+    let max = NEXUS_IO_TRANSIENT_RETRIES as u64;
+    assert_eq!(errors.transport, retries + max + 1);
+    assert_eq!(errors.retried, retries + max);
+}
+
+/// A read served by the read cache device which fails there invalidates the
+/// cached line, and is resubmitted to the children.
+#[tokio::test]
+async fn nexus_io_completion_read_cache_error() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| n).await;
+
+    nex.attach_read_cache("malloc:///rcache?size_mb=8", 0)
+        .await
+        .unwrap();
+
+    // Read the first cache line until it is filled.
+    let line = DataSize::from_kb(128);
+    let mut filled = false;
+    for _ in 0 .. 50 {
+        run_io(&nex, "read", DataSize::from_bytes(0), line).await;
+        let stats = nex.get_read_cache_stats().await.unwrap().unwrap();
+        if stats.cached_lines > 0 {
+            filled = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(filled, "{:?}", nex.get_read_cache_stats().await);
+
+    // The next read hits the cache device, which fails it.
+    let inj_uri = "inject://rcache?domain=block&op=read&count=1";
+    add_fault_injection(nex.rpc(), inj_uri).await.unwrap();
+
+    let before = nex.get_read_cache_stats().await.unwrap().unwrap();
+    run_io(&nex, "read", DataSize::from_bytes(0), DataSize::from_kb(4)).await;
+    let after = nex.get_read_cache_stats().await.unwrap().unwrap();
+
+    assert_eq!(after.hits, before.hits + 1);
+    assert_eq!(after.invalidations, before.invalidations + 1);
+    assert_eq!(after.misses, before.misses + 1);
+    assert_children_online(&nex).await;
+}
+
+/// A read stuck on a child for longer than the child I/O timeout is aborted
+/// and resubmitted to another child. The suspended replica cannot complete
+/// the abort either, so the read is completed by a reset of its controller,
+/// which reconnects once the replica resumes.
+#[tokio::test]
+async fn nexus_io_completion_read_timeout() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| {
+        n.with_child_io_timeout(Duration::from_millis(200))
+    })
+    .await;
+
+    // Reads are balanced between the children: those sent to the suspended
+    // replica time out, and are served by the other one.
+    test.pause("ms_0").await.unwrap();
+    run_io(&nex, "read", DataSize::from_bytes(0), DataSize::from_kb(16)).await;
+
+    let errors = io_errors(&nex).await;
+    assert!(errors.timeout > 0, "{errors:?}");
+
+    test.thaw("ms_0").await.unwrap();
+    assert_children_online(&nex).await;
+}
+
+/// Once a child is faulted, writes to segments not yet recorded by the I/O
+/// log journal wait for the journal device to persist them. A failing
+/// journal device is disabled, and the waiting writes released.
+#[tokio::test]
+async fn nexus_io_completion_journal_wait() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| n).await;
+    let devs = child_devices(&nex).await;
+
+    nex.attach_io_log_journal("malloc:///journal?size_mb=1")
+        .await
+        .unwrap();
+
+    // Fault the first child: its I/O log gets a journal slot.
+    inject(&nex, &devs[0], "op=write").await;
+    run_io(&nex, "write", DataSize::from_bytes(0), DataSize::from_kb(4)).await;
+
+    let n = nex.get_nexus().await.unwrap();
+    assert_eq!(n.children[0].state, ChildState::Faulted as i32);
+    let journal = n.io_log_journal.unwrap();
+    assert_eq!(journal.used_slots, 1);
+
+    // A write to another segment is persisted in the journal first.
+    run_io(&nex, "write", DataSize::from_mb(10), DataSize::from_kb(4)).await;
+
+    let after = nex.get_nexus().await.unwrap().io_log_journal.unwrap();
+    assert!(after.healthy);
+    assert!(after.flushes > journal.flushes, "{after:?}");
+
+    // The journal device fails: the write is released all the same.
+    let inj_uri = "inject://journal?domain=block&op=write";
+    add_fault_injection(nex.rpc(), inj_uri).await.unwrap();
+
+    run_io(&nex, "write", DataSize::from_mb(20), DataSize::from_kb(4)).await;
+
+    let failed = nex.get_nexus().await.unwrap().io_log_journal.unwrap();
+    assert!(!failed.healthy);
+    assert!(failed.flush_errors > 0, "{failed:?}");
+    assert_eq!(
+        nex.get_nexus().await.unwrap().children[1].state,
+        ChildState::Online as i32
+    );
+}
+
+/// With batched completions, nexus I/Os completing after child failures are
+/// accounted for like the others, whether they complete in a batch or not.
+#[tokio::test]
+async fn nexus_io_completion_batched() {
+    let test = create_compose_test_with(
+        nexus_node().with_env("NEXUS_BATCHED_IO_ENABLE", "1"),
+    )
+    .await;
+    let nex = create_nexus(&test, |n| n).await;
+    let devs = child_devices(&nex).await;
+
+    // Writes fail on the first child, which is faulted once the retries are
+    // exhausted: the writes in flight then are resubmitted to the other one.
+    inject(&nex, &devs[0], "op=write&offset=1024").await;
+
+    let size = DataSize::from_mb(4);
+    test_fio_to_nexus(
+        &nex,
+        Fio::new().with_job(
+            FioJob::new()
+                .with_rw("randwrite")
+                .with_bs(4096)
+                .with_iodepth(32)
+                .with_size(size),
+        ),
+    )
+    .await
+    .unwrap();
+
+    let children = nex.get_nexus().await.unwrap().children;
+    assert_eq!(children[0].state, ChildState::Faulted as i32);
+    assert_eq!(children[1].state, ChildState::Online as i32);
+
+    // All the writes have been completed, exactly once.
+    let cores = nex.get_stats().await.unwrap().cores;
+    assert!(cores.iter().all(|c| c.queue_depth == 0), "{cores:?}");
+    let ops: u64 = cores.iter().map(|c| c.num_ops).sum();
+    assert!(ops >= size.bytes() / 4096, "{cores:?}");
+}
+
+/// A nexus I/O split into several child I/Os completes once all of them have
+/// completed, and is resubmitted as a whole if any of them fails.
+#[tokio::test]
+async fn nexus_io_completion_split() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| n.with_max_io_size(16 * 1024)).await;
+    let devs = child_devices(&nex).await;
+
+    // Writes go to all children, reads to any of them: fail one child I/O
+    // of each on every child.
+    for rw in ["write", "read"] {
+        for dev in &devs {
+            inject(&nex, dev, &format!("op={rw}&count=1")).await;
+        }
+
+        test_fio_to_nexus(
+            &nex,
+            Fio::new().with_job(
+                FioJob::new()
+                    .with_rw(rw)
+                    .with_bs(64 * 1024)
+                    .with_size(DataSize::from_kb(64)),
+            ),
+        )
+        .await
+        .unwrap();
+    }
+
+    let n = nex.get_nexus().await.unwrap();
+    let splits = n.io_splits.unwrap();
+    assert!(splits.split_ios >= 2, "{splits:?}");
+    assert!(splits.child_ios >= 8, "{splits:?}");
+
+    let errors = n.io_errors.unwrap();
+    assert!(errors.transport >= 3, "{errors:?}");
+    assert!(errors.retried >= 2, "{errors:?}");
+    assert_children_online(&nex).await;
+}
+
+/// Under the quorum flush policy, a flush which fails transiently on some
+/// children is retried, and a flush which a majority of the children fail for
+/// good fails.
+#[tokio::test]
+async fn nexus_io_completion_flush_quorum() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| {
+        n.with_flush_policy(NexusFlushTarget::FlushQuorum, false)
+    })
+    .await;
+    let devs = child_devices(&nex).await;
+    let (_cg, path) = nex.nvmf_location().open().unwrap();
+
+    // The error clears on retry: the flush completes on all children.
+    inject(&nex, &devs[0], "op=flush&count=1").await;
+    assert!(nvme_flush(&path).success());
+    assert_children_online(&nex).await;
+
+    // The error persists: the child is faulted, and the flush has reached
+    // only one of the two children.
+    inject(&nex, &devs[0], "op=flush").await;
+    assert!(!nvme_flush(&path).success());
+
+    let children = nex.get_nexus().await.unwrap().children;
+    assert_eq!(children[0].state, ChildState::Faulted as i32);
+    assert_eq!(children[1].state, ChildState::Online as i32);
+}
+
+/// Under the write-through policy, a write is followed by a flush of the
+/// children before it completes: a child failing the flush is faulted.
+#[tokio::test]
+async fn nexus_io_completion_write_through() {
+    let test = create_compose_test().await;
+    let nex = create_nexus(&test, |n| {
+        n.with_flush_policy(NexusFlushTarget::FlushAll, true)
+    })
+    .await;
+    let devs = child_devices(&nex).await;
+
+    inject(&nex, &devs[0], "op=flush").await;
+    run_io(&nex, "write", DataSize::from_bytes(0), DataSize::from_kb(4)).await;
+
+    let children = nex.get_nexus().await.unwrap().children;
+    assert_eq!(children[0].state, ChildState::Faulted as i32);
+    assert_eq!(children[1].state, ChildState::Online as i32);
+}
@@ -0,0 +1,115 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{nexus::NexusIoCopyStats, GrpcConnect},
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use io_engine::bdev::nexus;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+static CACHE_SIZE: u64 = 4 * 1024 * 1024;
+
+async fn io_copies(nex: &NexusBuilder) -> NexusIoCopyStats {
+    nex.get_nexus().await.unwrap().io_copies.unwrap()
+}
+
+#[test]
+fn nexus_io_copy_rate() {
+    let stats = nexus::NexusIoCopyStats::default();
+    assert_eq!(stats.zero_copy_rate(), 0.0);
+
+    let stats = nexus::NexusIoCopyStats {
+        zero_copy_ios: 6,
+        buffer_allocations: 1,
+        copied_ios: 1,
+        ..Default::default()
+    };
+    assert_eq!(stats.zero_copy_rate(), 0.75);
+}
+
+/// The writes to a nexus are passed to its children as is, unless they go to
+/// its write cache.
+#[tokio::test]
+async fn nexus_io_copy() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+
+    let mut nexuses = Vec::new();
+    for (i, cache) in [None, Some(CACHE_SIZE)].into_iter().enumerate() {
+        let mut repl = ReplicaBuilder::new(ms_0.clone())
+            .with_pool(&pool)
+            .with_name(&format!("r{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(false);
+        repl.create().await.unwrap();
+
+        let mut nex = NexusBuilder::new(ms_0.clone())
+            .with_name(&format!("nexus{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_local_replica(&repl);
+        if let Some(capacity) = cache {
+            nex = nex.with_write_cache(capacity);
+        }
+        nex.create().await.unwrap();
+        nex.publish().await.unwrap();
+        assert_eq!(io_copies(&nex).await, NexusIoCopyStats::default());
+        nexuses.push(nex);
+    }
+
+    for nex in &nexuses {
+        test_write_to_nexus(
+            nex,
+            DataSize::from_bytes(0),
+            8,
+            DataSize::from_kb(4),
+        )
+        .await
+        .unwrap();
+    }
+
+    // Without a write cache, nothing is copied.
+    let stats = io_copies(&nexuses[0]).await;
+    assert!(stats.zero_copy_ios >= 8, "{stats:?}");
+    assert!(stats.zero_copy_bytes >= 8 * 4096, "{stats:?}");
+    assert!(stats.sgl_segments >= stats.zero_copy_ios, "{stats:?}");
+    assert!(stats.max_sgl_segments >= 1, "{stats:?}");
+    assert_eq!(stats.copied_ios, 0);
+    assert_eq!(stats.copied_bytes, 0);
+
+    // The writes to the cache are copied, the reads are not.
+    let stats = io_copies(&nexuses[1]).await;
+    assert!(stats.copied_ios >= 8, "{stats:?}");
+    assert!(stats.copied_bytes >= 8 * 4096, "{stats:?}");
+    assert!(stats.zero_copy_ios > 0, "{stats:?}");
+}
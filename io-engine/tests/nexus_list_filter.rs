@@ -0,0 +1,148 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{ListNexusOptions, NexusState},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+};
+use tonic::{Code, Status};
+
+async fn list_names(
+    rpc: &SharedRpcHandle,
+    options: ListNexusOptions,
+) -> Result<Vec<String>, Status> {
+    rpc.lock().await.nexus.list_nexus(options).await.map(|r| {
+        let mut names = r
+            .into_inner()
+            .nexus_list
+            .into_iter()
+            .map(|n| n.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    })
+}
+
+/// Listed nexuses are filtered by child, state and published status.
+#[tokio::test]
+async fn nexus_list_filter() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let children = [vec![0, 1], vec![2], vec![3, 4]];
+    let mut nexuses = Vec::new();
+    for (i, children) in children.into_iter().enumerate() {
+        let mut nex = NexusBuilder::new(ms_0.clone())
+            .with_name(&format!("nexus{i}"))
+            .with_new_uuid()
+            .with_size_mb(8)
+            .with_children(
+                children
+                    .into_iter()
+                    .map(|c| format!("malloc:///m{c}?size_mb=16"))
+                    .collect(),
+            );
+        nex.create().await.unwrap();
+        nexuses.push(nex);
+    }
+    nexuses[0].publish().await.unwrap();
+    nexuses[2]
+        .offline_child_bdev("malloc:///m4?size_mb=16")
+        .await
+        .unwrap();
+
+    for (options, names) in [
+        (ListNexusOptions::default(), vec!["nexus0", "nexus1", "nexus2"]),
+        (
+            ListNexusOptions {
+                child: Some("malloc:///m2?size_mb=16".to_string()),
+                ..Default::default()
+            },
+            vec!["nexus1"],
+        ),
+        (
+            // Children are also found by their device name.
+            ListNexusOptions {
+                child: Some("m3".to_string()),
+                ..Default::default()
+            },
+            vec!["nexus2"],
+        ),
+        (
+            ListNexusOptions {
+                child: Some("m5".to_string()),
+                ..Default::default()
+            },
+            vec![],
+        ),
+        (
+            ListNexusOptions {
+                state: Some(NexusState::NexusDegraded as i32),
+                ..Default::default()
+            },
+            vec!["nexus2"],
+        ),
+        (
+            ListNexusOptions {
+                published: Some(true),
+                ..Default::default()
+            },
+            vec!["nexus0"],
+        ),
+        (
+            ListNexusOptions {
+                published: Some(false),
+                ..Default::default()
+            },
+            vec!["nexus1", "nexus2"],
+        ),
+        (
+            // The filters apply to a nexus looked up by uuid as well.
+            ListNexusOptions {
+                uuid: Some(nexuses[0].uuid()),
+                published: Some(false),
+                ..Default::default()
+            },
+            vec![],
+        ),
+    ] {
+        assert_eq!(
+            list_names(&ms_0, options.clone()).await.unwrap(),
+            names,
+            "{options:?}"
+        );
+    }
+
+    let err = list_names(
+        &ms_0,
+        ListNexusOptions {
+            uuid: Some("bogus".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
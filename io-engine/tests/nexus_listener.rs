@@ -0,0 +1,162 @@
+pub mod common;
+
+use std::process::Command;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{Nexus, UpdateNexusListenerRequest},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    nvme::{nvme_connect, nvme_disconnect_nqn},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn update_listener(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    address: &str,
+    replace: bool,
+) -> Result<Nexus, Status> {
+    rpc.lock()
+        .await
+        .nexus
+        .update_nexus_listener(UpdateNexusListenerRequest {
+            uuid: uuid.to_string(),
+            address: address.to_string(),
+            replace,
+        })
+        .await
+        .map(|r| r.into_inner().nexus.unwrap())
+}
+
+/// Connects to the given NVMe-oF target on the given port.
+fn connect_on_port(ip: &str, port: u16, nqn: &str) -> bool {
+    let status = Command::new("nvme")
+        .args(["connect", "-t", "tcp", "-a", ip])
+        .args(["-s", &port.to_string()])
+        .args(["-n", nqn])
+        .status()
+        .unwrap();
+    if status.success() {
+        nvme_disconnect_nqn(nqn);
+    }
+    status.success()
+}
+
+/// A listener is added to the target of a published nexus, and its other
+/// listeners are removed on request, without unpublishing the nexus.
+#[tokio::test]
+async fn nexus_listener_update() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ip = ms_0.endpoint().ip().to_string();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repl);
+    nex.create().await.unwrap();
+
+    // The nexus must be published over NVMe-oF.
+    assert_eq!(
+        update_listener(&ms_0, &nex.uuid(), &ip, false)
+            .await
+            .unwrap_err()
+            .code(),
+        Code::InvalidArgument
+    );
+
+    nex.publish().await.unwrap();
+    let nqn = nex.nqn();
+
+    // The new listener is added next to the existing one.
+    update_listener(&ms_0, &nex.uuid(), &format!("{ip}:8430"), false)
+        .await
+        .unwrap();
+    assert!(connect_on_port(&ip, 8420, &nqn));
+    assert!(connect_on_port(&ip, 8430, &nqn));
+
+    // The target is moved to the new listener.
+    let nexus = update_listener(&ms_0, &nex.uuid(), &format!("{ip}:8430"), true)
+        .await
+        .unwrap();
+    assert!(
+        nexus.device_uri.starts_with(&format!("nvmf://{ip}:8430/")),
+        "{}",
+        nexus.device_uri
+    );
+    assert!(!nvme_connect(&ip, &nqn, false).success());
+    assert!(connect_on_port(&ip, 8430, &nqn));
+
+    // Without a port, the default one is used.
+    let nexus = update_listener(&ms_0, &nex.uuid(), &ip, true).await.unwrap();
+    assert!(
+        nexus.device_uri.starts_with(&format!("nvmf://{ip}:8420/")),
+        "{}",
+        nexus.device_uri
+    );
+    assert!(connect_on_port(&ip, 8420, &nqn));
+
+    for address in ["not-an-ip", "10.1.0.300", "10.1.0.2:99999"] {
+        assert_eq!(
+            update_listener(&ms_0, &nex.uuid(), address, false)
+                .await
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument,
+            "{address}"
+        );
+    }
+    assert_eq!(
+        update_listener(
+            &ms_0,
+            "6f1c2d3e-4a5b-4c6d-8e7f-901a2b3c4d5e",
+            &ip,
+            false
+        )
+        .await
+        .unwrap_err()
+        .code(),
+        Code::NotFound
+    );
+}
@@ -0,0 +1,145 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{nexus::UpdateNexusListenerRequest, GrpcConnect},
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+/// Returns the addresses of the paths of the only child of the nexus, and
+/// the active one.
+async fn child_paths(nex: &NexusBuilder) -> (Vec<String>, String) {
+    let nexus = nex.get_nexus().await.unwrap();
+    let paths = &nexus.children[0].paths;
+    let active = paths.iter().find(|p| p.active).unwrap();
+    (
+        paths.iter().map(|p| p.address.clone()).collect(),
+        active.address.clone(),
+    )
+}
+
+/// A remote child reachable through several addresses fails over to the next
+/// one when its active path is lost.
+#[tokio::test]
+async fn nexus_multipath_failover() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+    let ip = ms_1.endpoint().ip().to_string();
+
+    // The target nexus listens on two ports.
+    let mut pool = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let mut target = NexusBuilder::new(ms_1.clone())
+        .with_name("target0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repl);
+    target.create().await.unwrap();
+    let device_uri = target.publish().await.unwrap().device_uri;
+    let update_listener = |replace: bool| UpdateNexusListenerRequest {
+        uuid: target.uuid(),
+        address: format!("{ip}:8430"),
+        replace,
+    };
+    ms_1.lock()
+        .await
+        .nexus
+        .update_nexus_listener(update_listener(false))
+        .await
+        .unwrap();
+
+    let separator = if device_uri.contains('?') { '&' } else { '?' };
+    let child_uri = format!("{device_uri}{separator}paths={ip}:8430");
+    let mut nex = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_bdev(&child_uri);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    let (paths, active) = child_paths(&nex).await;
+    assert_eq!(paths, [format!("{ip}:8420"), format!("{ip}:8430")]);
+    assert_eq!(active, format!("{ip}:8420"));
+
+    // Remove the listener of the active path: the child reconnects through
+    // the other one instead of faulting.
+    ms_1.lock()
+        .await
+        .nexus
+        .update_nexus_listener(update_listener(true))
+        .await
+        .unwrap();
+    let start = Instant::now();
+    loop {
+        test_write_to_nexus(
+            &nex,
+            DataSize::from_bytes(0),
+            1,
+            DataSize::from_kb(4),
+        )
+        .await
+        .unwrap();
+        if child_paths(&nex).await.1 == format!("{ip}:8430") {
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "child did not fail over"
+        );
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    nex.wait_children_online(Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    // Alternate paths must be well formed.
+    let mut bad = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_bdev(&format!("{device_uri}{separator}paths={ip}:port"));
+    assert!(bad.create().await.is_err());
+}
@@ -0,0 +1,161 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            bdev::ListBdevOptions,
+            nexus::{ValidateNexusConfigRequest, ValidateNexusConfigResponse},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn validate(
+    rpc: &SharedRpcHandle,
+    size_mb: u64,
+    children: &[String],
+    block_size: u64,
+) -> Result<ValidateNexusConfigResponse, Status> {
+    rpc.lock()
+        .await
+        .nexus
+        .validate_nexus_config(ValidateNexusConfigRequest {
+            size: size_mb * 1024 * 1024,
+            children: children.to_vec(),
+            resv_key: 1,
+            block_size,
+            ..Default::default()
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+async fn bdev_exists(rpc: &SharedRpcHandle, name: &str) -> bool {
+    !rpc.lock()
+        .await
+        .bdev
+        .list(ListBdevOptions {
+            name: Some(name.to_string()),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .bdevs
+        .is_empty()
+}
+
+/// The preflight of a nexus configuration reports the problems of each child
+/// without creating anything.
+#[tokio::test]
+async fn nexus_preflight() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+    let mut repls = Vec::new();
+    for name in ["r0", "r1"] {
+        let mut repl = ReplicaBuilder::new(ms_0.clone())
+            .with_pool(&pool)
+            .with_name(name)
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(false);
+        repl.create().await.unwrap();
+        repls.push(repl);
+    }
+
+    let m0 = "malloc:///m0?size_mb=32".to_string();
+    let children = vec![repls[0].bdev(), m0.clone()];
+    let preflight = validate(&ms_0, REPL_SIZE, &children, 0).await.unwrap();
+    assert!(preflight.valid, "{preflight:?}");
+    assert_eq!(preflight.block_size, 512);
+    for (child, uri) in preflight.children.iter().zip(&children) {
+        assert_eq!(&child.uri, uri);
+        assert!(child.valid && child.reachable, "{child:?}");
+        assert!(child.num_blocks > 0);
+        assert_eq!(child.block_size, 512);
+    }
+    // The devices created for the checks are gone.
+    assert!(!bdev_exists(&ms_0, "m0").await);
+    assert!(bdev_exists(&ms_0, "r0").await);
+
+    // Too large for a child.
+    let preflight = validate(&ms_0, 64, &children, 0).await.unwrap();
+    assert!(!preflight.valid);
+    assert!(!preflight.children[1].valid);
+    assert!(preflight.children[1].reachable);
+
+    // A child of another block size, or given twice.
+    let m1 = "malloc:///m1?size_mb=32&blk_size=4096".to_string();
+    let preflight =
+        validate(&ms_0, REPL_SIZE, &[m0.clone(), m1], 0).await.unwrap();
+    assert!(!preflight.valid);
+    assert!(preflight.children[0].valid);
+    assert!(!preflight.children[1].valid);
+    let preflight =
+        validate(&ms_0, REPL_SIZE, &[m0.clone(), m0.clone()], 0).await.unwrap();
+    assert!(preflight.children.iter().all(|c| !c.valid), "{preflight:?}");
+
+    // A child of a nexus, or with a bad URI, is not opened.
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repls[1]);
+    nex.create().await.unwrap();
+    let children = vec![repls[1].bdev(), "bogus:///m2".to_string()];
+    let preflight = validate(&ms_0, REPL_SIZE, &children, 0).await.unwrap();
+    assert!(!preflight.valid);
+    for child in &preflight.children {
+        assert!(!child.valid && !child.reachable, "{child:?}");
+        assert_eq!(child.errors.len(), 1, "{child:?}");
+    }
+
+    // Problems of the whole configuration.
+    let preflight = validate(&ms_0, REPL_SIZE, &[], 1000).await.unwrap();
+    assert!(!preflight.valid);
+    assert_eq!(preflight.errors.len(), 2, "{preflight:?}");
+
+    let err = ms_0
+        .lock()
+        .await
+        .nexus
+        .validate_nexus_config(ValidateNexusConfigRequest {
+            size: REPL_SIZE * 1024 * 1024,
+            children: vec![m0],
+            resv_type: Some(99),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+}
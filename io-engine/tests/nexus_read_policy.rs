@@ -0,0 +1,176 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{Child, Nexus, SetNexusReadPolicyRequest},
+            stats::{GetStatsRequest, ResourceType},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    fio::{Fio, FioJob},
+    nexus::{test_fio_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn set_read_policy(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    prefer_local_reads: bool,
+) -> Result<Nexus, Status> {
+    rpc.lock()
+        .await
+        .nexus
+        .set_nexus_read_policy(SetNexusReadPolicyRequest {
+            uuid: uuid.to_string(),
+            prefer_local_reads,
+        })
+        .await
+        .map(|r| r.into_inner().nexus.unwrap())
+}
+
+/// Returns the number of bytes read from the given replica.
+async fn replica_bytes_read(rpc: &SharedRpcHandle, name: &str) -> u64 {
+    let stats = rpc
+        .lock()
+        .await
+        .stats
+        .get_stats(GetStatsRequest {
+            resource_types: vec![ResourceType::Replica as i32],
+            name: Some(name.to_string()),
+            reset: false,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .stats;
+    assert_eq!(stats.len(), 1);
+    stats[0].stats.as_ref().unwrap().bytes_read
+}
+
+async fn read_nexus(nex: &NexusBuilder) {
+    test_fio_to_nexus(
+        nex,
+        Fio::new().with_job(
+            FioJob::new()
+                .with_rw("read")
+                .with_bs(4096)
+                .with_size(DataSize::from_mb(4)),
+        ),
+    )
+    .await
+    .unwrap();
+}
+
+fn child<'a>(nexus: &'a Nexus, uri: &str) -> &'a Child {
+    nexus.children.iter().find(|c| c.uri == uri).unwrap()
+}
+
+/// With preferred-local reads, the remote children are left out of the read
+/// path until no healthy local child is left.
+#[tokio::test]
+async fn nexus_read_policy_prefer_local() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_1 = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool_1)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_1.create().await.unwrap();
+    repl_1.create().await.unwrap();
+    repl_1.share().await.unwrap();
+
+    let mut pool_local = PoolBuilder::new(ms_nex.clone())
+        .with_name("pool_local")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_local = ReplicaBuilder::new(ms_nex.clone())
+        .with_pool(&pool_local)
+        .with_name("r_local")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_local.create().await.unwrap();
+    repl_local.create().await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_1)
+        .with_local_replica(&repl_local);
+    nex_0.create().await.unwrap();
+    nex_0.publish().await.unwrap();
+
+    let remote_uri = nex_0.get_nexus_replica_child(&repl_1).await.unwrap().uri;
+    let local_uri = repl_local.bdev();
+
+    // By default, all healthy children serve reads.
+    let nexus = nex_0.get_nexus().await.unwrap();
+    assert!(!nexus.prefer_local_reads);
+    assert_eq!(child(&nexus, &local_uri).is_local, Some(true));
+    assert_eq!(child(&nexus, &remote_uri).is_local, Some(false));
+    assert!(nexus.children.iter().all(|c| c.reader));
+
+    // The remote child no longer serves reads.
+    let nexus = set_read_policy(&ms_nex, &nex_0.uuid(), true).await.unwrap();
+    assert!(nexus.prefer_local_reads);
+    assert!(child(&nexus, &local_uri).reader);
+    assert!(!child(&nexus, &remote_uri).reader);
+
+    let bytes_read = replica_bytes_read(&ms_1, "r1").await;
+    read_nexus(&nex_0).await;
+    assert_eq!(replica_bytes_read(&ms_1, "r1").await, bytes_read);
+
+    // Without a local child, the remote child serves reads again.
+    let nexus = nex_0.remove_child_replica(&repl_local).await.unwrap();
+    assert!(nexus.prefer_local_reads);
+    assert!(child(&nexus, &remote_uri).reader);
+
+    read_nexus(&nex_0).await;
+    assert!(replica_bytes_read(&ms_1, "r1").await > bytes_read);
+
+    assert_eq!(
+        set_read_policy(&ms_nex, "b5d6c0a4-57d9-4b62-9a31-2c1f4f2b6e01", true)
+            .await
+            .unwrap_err()
+            .code(),
+        Code::NotFound
+    );
+}
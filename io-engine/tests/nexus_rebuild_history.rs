@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+
+use io_engine::{
+    bdev::nexus::{
+        nexus_create,
+        nexus_lookup_mut,
+        NEXUS_REBUILD_HISTORY_MAX,
+        REBUILD_HISTORY_STASH_MAX,
+    },
+    core::MayastorCliArgs,
+};
+
+pub mod common;
+use common::compose::MayastorTest;
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+const NEXUS_SIZE: u64 = 4 * 1024 * 1024;
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+fn child_uri(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=8")
+}
+
+async fn create_nexus(name: &'static str, uuid: &str, src: String) {
+    let uuid = uuid.to_string();
+    get_ms()
+        .spawn(async move {
+            nexus_create(name, NEXUS_SIZE, Some(&uuid), &[src])
+                .await
+                .unwrap();
+        })
+        .await;
+}
+
+async fn destroy_nexus(name: &'static str) {
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut(name).unwrap().destroy().await.unwrap();
+        })
+        .await;
+}
+
+/// Returns the destination URIs of the rebuild history records of the nexus.
+async fn history(name: &'static str) -> Vec<String> {
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut(name)
+                .unwrap()
+                .rebuild_history()
+                .into_iter()
+                .map(|r| r.child_uri)
+                .collect()
+        })
+        .await
+}
+
+/// Adds a child to the nexus, waits until its rebuild is recorded in the
+/// history, and removes it again.
+async fn rebuild_child(name: &'static str, uri: String) {
+    let dst = uri.clone();
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut(name)
+                .unwrap()
+                .add_child(&dst, false)
+                .await
+                .unwrap();
+        })
+        .await;
+
+    let mut recorded = false;
+    for _ in 0 .. 100 {
+        if history(name).await.last() == Some(&uri) {
+            recorded = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert!(recorded, "rebuild of '{uri}' was not recorded");
+
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut(name)
+                .unwrap()
+                .remove_child(&uri)
+                .await
+                .unwrap();
+        })
+        .await;
+}
+
+/// The rebuild history is bounded per nexus, survives the re-creation of a
+/// nexus with the same UUID, and the histories of destroyed nexuses are
+/// evicted oldest first. Histories are kept in memory only, so this covers
+/// their whole lifetime within an io-engine instance.
+#[tokio::test]
+async fn nexus_rebuild_history_retention() {
+    get_ms();
+
+    // The oldest records of a nexus are dropped.
+    let uuid = uuid::Uuid::new_v4().to_string();
+    create_nexus("rh_nexus", &uuid, child_uri("rh_src")).await;
+
+    let total = NEXUS_REBUILD_HISTORY_MAX + 2;
+    for i in 0 .. total {
+        rebuild_child("rh_nexus", child_uri(&format!("rh_dst{i}"))).await;
+    }
+
+    let hist = history("rh_nexus").await;
+    assert_eq!(hist.len(), NEXUS_REBUILD_HISTORY_MAX);
+    assert_eq!(hist[0], child_uri("rh_dst2"));
+    assert_eq!(
+        hist.last(),
+        Some(&child_uri(&format!("rh_dst{}", total - 1)))
+    );
+
+    // The history is restored when the nexus is created again.
+    destroy_nexus("rh_nexus").await;
+    create_nexus("rh_nexus", &uuid, child_uri("rh_src")).await;
+    assert_eq!(history("rh_nexus").await, hist);
+
+    // A nexus with another UUID does not inherit it.
+    destroy_nexus("rh_nexus").await;
+    let other = uuid::Uuid::new_v4().to_string();
+    create_nexus("rh_nexus", &other, child_uri("rh_src")).await;
+    assert!(history("rh_nexus").await.is_empty());
+    destroy_nexus("rh_nexus").await;
+
+    // Fill the stash with the histories of newer nexuses: the history of the
+    // first nexus is the oldest, and is evicted.
+    let uuids = (0 .. REBUILD_HISTORY_STASH_MAX)
+        .map(|_| uuid::Uuid::new_v4().to_string())
+        .collect::<Vec<_>>();
+    for (i, u) in uuids.iter().enumerate() {
+        create_nexus("rh_stash", u, child_uri(&format!("rh_stash_src{i}")))
+            .await;
+        rebuild_child("rh_stash", child_uri(&format!("rh_stash_dst{i}"))).await;
+        destroy_nexus("rh_stash").await;
+    }
+
+    create_nexus("rh_nexus", &uuid, child_uri("rh_src")).await;
+    assert!(history("rh_nexus").await.is_empty());
+    destroy_nexus("rh_nexus").await;
+
+    // The newer histories are all retained.
+    for (i, u) in uuids.iter().enumerate() {
+        create_nexus("rh_stash", u, child_uri(&format!("rh_stash_src{i}")))
+            .await;
+        assert_eq!(
+            history("rh_stash").await,
+            vec![child_uri(&format!("rh_stash_dst{i}"))]
+        );
+        destroy_nexus("rh_stash").await;
+    }
+}
@@ -0,0 +1,162 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{RebuildStateRequest, RebuildStatsRequest},
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::{validate_replicas, ReplicaBuilder},
+};
+
+const POOL_SIZE: u64 = 60;
+const REPL_SIZE: u64 = 20;
+const CLUSTER_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Returns the state of the rebuild of the given child.
+async fn rebuild_state(nex: &NexusBuilder, uri: &str) -> String {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_state(RebuildStateRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.to_string(),
+        })
+        .await
+        .map(|r| r.into_inner().state)
+        .unwrap_or_default()
+}
+
+/// The rebuild of a thin destination which runs out of space is paused
+/// instead of failed, and resumes once space is freed on its pool.
+#[tokio::test]
+async fn nexus_rebuild_no_space() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_src",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .add_container_bin(
+            "ms_dst",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "3"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+    let ms_src = conn.grpc_handle_shared("ms_src").await.unwrap();
+    let ms_dst = conn.grpc_handle_shared("ms_dst").await.unwrap();
+
+    let mut pool_src = PoolBuilder::new(ms_src.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_src = ReplicaBuilder::new(ms_src.clone())
+        .with_pool(&pool_src)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_src.create().await.unwrap();
+    repl_src.create().await.unwrap();
+    repl_src.share().await.unwrap();
+
+    // The destination pool only has two free clusters left for the thin
+    // destination replica.
+    let mut pool_dst = PoolBuilder::new(ms_dst.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem1", POOL_SIZE);
+    pool_dst.create().await.unwrap();
+    let capacity = pool_dst.get_pool().await.unwrap().capacity;
+    let mut filler = ReplicaBuilder::new(ms_dst.clone())
+        .with_pool(&pool_dst)
+        .with_name("filler")
+        .with_new_uuid()
+        .with_size_kb((capacity - 2 * CLUSTER_SIZE) / 1024)
+        .with_thin(false);
+    filler.create().await.unwrap();
+    let mut repl_dst = ReplicaBuilder::new(ms_dst.clone())
+        .with_pool(&pool_dst)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(true);
+    repl_dst.create().await.unwrap();
+    repl_dst.share().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_src);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    test_write_to_nexus(
+        &nex,
+        DataSize::from_bytes(0),
+        16,
+        DataSize::from_mb(1),
+    )
+    .await
+    .unwrap();
+
+    nex.add_replica(&repl_dst, false).await.unwrap();
+    let uri = nex.get_nexus_replica_child(&repl_dst).await.unwrap().uri;
+
+    // The rebuild is paused, not failed.
+    let start = Instant::now();
+    while rebuild_state(&nex, &uri).await != "no_space" {
+        assert!(
+            start.elapsed() < Duration::from_secs(20),
+            "rebuild did not run out of space"
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    let stats = nex
+        .rpc()
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_stats(RebuildStatsRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(stats.is_no_space);
+    assert!(stats.no_space_count >= 1);
+    assert!(nex.wait_children_online(Duration::from_secs(1)).await.is_err());
+
+    // Once space is freed, the rebuild resumes on its next retry and
+    // completes.
+    filler.destroy().await.unwrap();
+    nex.wait_children_online(Duration::from_secs(30))
+        .await
+        .unwrap();
+
+    validate_replicas(&[repl_src, repl_dst]).await;
+}
@@ -0,0 +1,160 @@
+pub mod common;
+
+use std::time::{Duration, Instant, SystemTime};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{
+                AddChildNexusRequest,
+                Child,
+                ChildState,
+                StartRebuildRequest,
+            },
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+};
+use tonic::{Code, Status};
+
+fn malloc(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=64")
+}
+
+/// Adds a child whose rebuild is scheduled at the given time from now.
+async fn add_child_at(
+    nex: &NexusBuilder,
+    uri: &str,
+    rebuild_in: Duration,
+    seed_snapshot: Option<String>,
+) -> Result<(), Status> {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .add_child_nexus(AddChildNexusRequest {
+            uuid: nex.uuid(),
+            uri: uri.to_string(),
+            norebuild: false,
+            seed_snapshot,
+            rebuild_at: Some((SystemTime::now() + rebuild_in).into()),
+            ..Default::default()
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn start_rebuild(nex: &NexusBuilder, uri: &str) {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .start_rebuild(StartRebuildRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+}
+
+async fn get_child(nex: &NexusBuilder, uri: &str) -> Option<Child> {
+    nex.get_nexus()
+        .await
+        .unwrap()
+        .children
+        .into_iter()
+        .find(|c| c.uri == uri)
+}
+
+async fn wait_online(nex: &NexusBuilder, uri: &str) {
+    let start = Instant::now();
+    loop {
+        let child = get_child(nex, uri).await.unwrap();
+        if child.state == ChildState::Online as i32 {
+            return;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "{child:?}");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// A child added with a deferred rebuild stays out-of-sync until its rebuild
+/// is scheduled, or until it is started explicitly.
+#[tokio::test]
+async fn nexus_rebuild_schedule() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(60)
+        .with_bdev(&malloc("m0"));
+    nex.create().await.unwrap();
+
+    // The rebuild starts at the scheduled time.
+    add_child_at(&nex, &malloc("m1"), Duration::from_secs(5), None)
+        .await
+        .unwrap();
+    let child = get_child(&nex, &malloc("m1")).await.unwrap();
+    assert_eq!(child.state, ChildState::Degraded as i32, "{child:?}");
+    assert!(child.rebuild_scheduled_at.is_some(), "{child:?}");
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let child = get_child(&nex, &malloc("m1")).await.unwrap();
+    assert_eq!(child.state, ChildState::Degraded as i32, "{child:?}");
+    // No rebuild job is running yet.
+    assert_eq!(child.rebuild_progress, -1, "{child:?}");
+    wait_online(&nex, &malloc("m1")).await;
+    let child = get_child(&nex, &malloc("m1")).await.unwrap();
+    assert_eq!(child.rebuild_scheduled_at, None);
+
+    // A rebuild started explicitly replaces the schedule.
+    add_child_at(&nex, &malloc("m2"), Duration::from_secs(3600), None)
+        .await
+        .unwrap();
+    start_rebuild(&nex, &malloc("m2")).await;
+    wait_online(&nex, &malloc("m2")).await;
+    let child = get_child(&nex, &malloc("m2")).await.unwrap();
+    assert_eq!(child.rebuild_scheduled_at, None);
+
+    // A child without rebuild waits for its rebuild to be started.
+    let nexus = nex.add_child(&malloc("m3"), true).await.unwrap();
+    let child = nexus.children.iter().find(|c| c.uri == malloc("m3"));
+    assert_eq!(child.unwrap().rebuild_scheduled_at, None);
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let child = get_child(&nex, &malloc("m3")).await.unwrap();
+    assert_eq!(child.state, ChildState::Degraded as i32, "{child:?}");
+    start_rebuild(&nex, &malloc("m3")).await;
+    wait_online(&nex, &malloc("m3")).await;
+
+    // A seeded child cannot have its rebuild deferred, and is not attached.
+    let err = add_child_at(
+        &nex,
+        &malloc("m4"),
+        Duration::from_secs(5),
+        Some(uuid::Uuid::new_v4().to_string()),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    assert!(get_child(&nex, &malloc("m4")).await.is_none());
+}
@@ -0,0 +1,160 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{RebuildStatsRequest, RebuildStatsResponse},
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::Code;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn rebuild_stats(nex: &NexusBuilder, uri: &str) -> RebuildStatsResponse {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_stats(RebuildStatsRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+/// Rebuilds copy segments of the size, and with the number of tasks, set on
+/// the nexus, or else the node defaults.
+#[tokio::test]
+async fn nexus_rebuild_segments() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "2",
+                "--rebuild-segment-size",
+                "128KiB",
+                "--rebuild-tasks",
+                "2",
+                // Keep the rebuilds running while their stats are checked.
+                "--rebuild-max-bandwidth",
+                "1MiB",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut pool_local = PoolBuilder::new(ms_nex.clone())
+        .with_name("pool_local")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool_1.create().await.unwrap();
+    pool_local.create().await.unwrap();
+
+    let mut remote = Vec::new();
+    let mut local = Vec::new();
+    for i in 0 .. 2 {
+        let mut repl = ReplicaBuilder::new(ms_1.clone())
+            .with_pool(&pool_1)
+            .with_name(&format!("r1_{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(false);
+        repl.create().await.unwrap();
+        repl.share().await.unwrap();
+        remote.push(repl);
+
+        let mut repl = ReplicaBuilder::new(ms_nex.clone())
+            .with_pool(&pool_local)
+            .with_name(&format!("r_local_{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(false);
+        repl.create().await.unwrap();
+        local.push(repl);
+    }
+
+    // The node defaults apply to the nexus without rebuild options.
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&remote[0]);
+    nex_0.create().await.unwrap();
+    let nexus = nex_0.get_nexus().await.unwrap();
+    assert_eq!(nexus.rebuild_segment_size, 0);
+    assert_eq!(nexus.rebuild_tasks, 0);
+    nex_0.add_replica(&local[0], false).await.unwrap();
+    let stats = rebuild_stats(&nex_0, &local[0].bdev()).await;
+    assert_eq!(stats.blocks_per_task * stats.block_size, 128 * 1024);
+    assert_eq!(stats.tasks_total, 2);
+
+    let mut nex_1 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_rebuild_segments(64 * 1024, 4)
+        .with_replica(&remote[1]);
+    nex_1.create().await.unwrap();
+    let nexus = nex_1.get_nexus().await.unwrap();
+    assert_eq!(nexus.rebuild_segment_size, 64 * 1024);
+    assert_eq!(nexus.rebuild_tasks, 4);
+    nex_1.add_replica(&local[1], false).await.unwrap();
+    let stats = rebuild_stats(&nex_1, &local[1].bdev()).await;
+    assert_eq!(stats.blocks_per_task * stats.block_size, 64 * 1024);
+    assert_eq!(stats.tasks_total, 4);
+
+    // Segments must be whole blocks, and both must be within bounds.
+    for (i, (size, tasks)) in
+        [(1000, 0), (32 * 1024 * 1024, 0), (0, 1000)].into_iter().enumerate()
+    {
+        let mut nex = NexusBuilder::new(ms_nex.clone())
+            .with_name(&format!("nexus_bad{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_rebuild_segments(size, tasks)
+            .with_bdev(&format!("malloc:///bad{i}?size_mb={REPL_SIZE}"));
+        assert_eq!(
+            nex.create().await.unwrap_err().code(),
+            Code::InvalidArgument,
+            "{size} {tasks}"
+        );
+    }
+
+    for nex in [&nex_0, &nex_1] {
+        nex.wait_children_online(Duration::from_secs(60))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+
+use io_engine::{
+    bdev::nexus::{nexus_create, nexus_lookup_mut, Error, FaultReason},
+    core::MayastorCliArgs,
+};
+
+pub mod common;
+use common::compose::MayastorTest;
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+const NEXUS_NAME: &str = "nexus_rebuild_source";
+const NEXUS_SIZE: u64 = 8 * 1024 * 1024;
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+fn child_uri(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=16")
+}
+
+/// Spells the URI of a child differently, with its default block size.
+fn child_uri_alt(name: &str) -> String {
+    format!("malloc:///{name}?blk_size=512&size_mb=16")
+}
+
+/// Adds the given children to the nexus, without rebuilding them.
+async fn add_children(children: &'static [&'static str]) {
+    get_ms()
+        .spawn(async move {
+            let mut nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+            for c in children {
+                nexus.as_mut().add_child(&child_uri(c), true).await.unwrap();
+            }
+        })
+        .await;
+}
+
+/// Sets the rebuild source of a child, returning the reason of the failure.
+async fn set_rebuild_source(child: String, src: String) -> Option<String> {
+    get_ms()
+        .spawn(async move {
+            match nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .set_rebuild_source(&child, Some(&src))
+            {
+                Ok(()) => None,
+                Err(Error::InvalidRebuildSource {
+                    reason, ..
+                }) => Some(reason),
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+        })
+        .await
+}
+
+async fn rebuild_source(child: String) -> Option<String> {
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut(NEXUS_NAME).unwrap().rebuild_source(&child)
+        })
+        .await
+}
+
+/// Starts the rebuild of the given child, waits for it to end, and returns
+/// the child it was rebuilt from.
+async fn rebuild(child: &str) -> String {
+    let dst = child_uri(child);
+    let uri = dst.clone();
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .start_rebuild(&uri)
+                .await
+                .unwrap();
+        })
+        .await;
+
+    for _ in 0 .. 100 {
+        let dst = dst.clone();
+        let src = get_ms()
+            .spawn(async move {
+                nexus_lookup_mut(NEXUS_NAME)
+                    .unwrap()
+                    .rebuild_history()
+                    .into_iter()
+                    .find(|r| r.child_uri == dst)
+                    .map(|r| r.src_uri)
+            })
+            .await;
+        if let Some(src) = src {
+            return src;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("rebuild of '{dst}' does not end");
+}
+
+/// A child is rebuilt from its preferred source, however the URIs are
+/// spelled, and from another healthy child once the preferred source is not
+/// healthy. Only a healthy child other than the rebuilt one is accepted as
+/// a source.
+#[tokio::test]
+async fn nexus_rebuild_source() {
+    get_ms()
+        .spawn(async {
+            let children = [child_uri("c0"), child_uri("c1")];
+            nexus_create(NEXUS_NAME, NEXUS_SIZE, None, &children)
+                .await
+                .unwrap();
+        })
+        .await;
+    add_children(&["c2", "c3", "c4"]).await;
+
+    // Invalid sources are refused.
+    for (src, reason) in [
+        (child_uri("c2"), "a child cannot be rebuilt from itself"),
+        (child_uri_alt("c2"), "a child cannot be rebuilt from itself"),
+        (child_uri("c9"), "no such child"),
+        (child_uri("c3"), "the child is not healthy"),
+    ] {
+        assert_eq!(
+            set_rebuild_source(child_uri("c2"), src.clone()).await.as_deref(),
+            Some(reason),
+            "{src}"
+        );
+        assert_eq!(rebuild_source(child_uri("c2")).await, None);
+    }
+
+    // The preferred source applies whatever the spelling of the URIs, and
+    // is cleared once the child is rebuilt. Without it, c0 would be
+    // selected.
+    assert_eq!(
+        set_rebuild_source(child_uri_alt("c2"), child_uri_alt("c1")).await,
+        None
+    );
+    assert_eq!(rebuild_source(child_uri("c2")).await, Some(child_uri("c1")));
+    assert_eq!(rebuild("c2").await, child_uri("c1"));
+    assert_eq!(rebuild_source(child_uri("c2")).await, None);
+
+    // Another healthy child is selected once the preferred source is not
+    // healthy anymore.
+    assert_eq!(
+        set_rebuild_source(child_uri("c3"), child_uri("c1")).await,
+        None
+    );
+    get_ms()
+        .spawn(async {
+            nexus_lookup_mut(NEXUS_NAME)
+                .unwrap()
+                .fault_child(&child_uri("c1"), FaultReason::IoError)
+                .await
+                .unwrap();
+        })
+        .await;
+    assert_ne!(rebuild("c3").await, child_uri("c1"));
+
+    // Removing a child, under any spelling of its URI, clears its source.
+    assert_eq!(
+        set_rebuild_source(child_uri_alt("c4"), child_uri("c0")).await,
+        None
+    );
+    get_ms()
+        .spawn(async {
+            let mut nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+            nexus.as_mut().remove_child(&child_uri_alt("c4")).await.unwrap();
+            assert!(nexus.lookup_child(&child_uri("c4")).is_none());
+        })
+        .await;
+    assert_eq!(rebuild_source(child_uri("c4")).await, None);
+
+    get_ms()
+        .spawn(async {
+            nexus_lookup_mut(NEXUS_NAME).unwrap().destroy().await.unwrap();
+        })
+        .await;
+}
@@ -0,0 +1,177 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{RebuildStatsRequest, RebuildStatsResponse},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn rebuild_stats(nex: &NexusBuilder, uri: &str) -> RebuildStatsResponse {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_stats(RebuildStatsRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+fn replica(
+    rpc: &SharedRpcHandle,
+    pool: &PoolBuilder,
+    name: &str,
+) -> ReplicaBuilder {
+    ReplicaBuilder::new(rpc.clone())
+        .with_pool(pool)
+        .with_name(name)
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false)
+}
+
+/// Adds a local child to the nexus, and waits for its rebuild to read from
+/// the given number of sources, returning the URIs of these sources.
+async fn rebuild_sources(
+    nex: &mut NexusBuilder,
+    repl: &ReplicaBuilder,
+    count: usize,
+) -> Vec<String> {
+    nex.add_replica(repl, false).await.unwrap();
+
+    let start = Instant::now();
+    loop {
+        let stats = rebuild_stats(nex, &repl.bdev()).await;
+        let read: Vec<_> = stats
+            .sources
+            .iter()
+            .filter(|s| s.bytes_read > 0)
+            .map(|s| s.uri.clone())
+            .collect();
+        if read.len() >= count {
+            assert_eq!(stats.sources.len(), count, "{stats:?}");
+            return read;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "rebuild did not read from {count} sources: {stats:?}"
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// The rebuild of a nexus child reads from the rebuild source only, unless
+/// its reads are striped over all the healthy children.
+#[tokio::test]
+async fn nexus_rebuild_striped() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_2",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "3",
+                // Keep the rebuilds running while their stats are checked.
+                "--rebuild-max-bandwidth",
+                "1MiB",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_2 = conn.grpc_handle_shared("ms_2").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pools = Vec::new();
+    for (rpc, name) in [(&ms_1, "pool1"), (&ms_2, "pool2"), (&ms_nex, "pool3")]
+    {
+        let mut pool = PoolBuilder::new(rpc.clone())
+            .with_name(name)
+            .with_new_uuid()
+            .with_malloc("mem0", POOL_SIZE);
+        pool.create().await.unwrap();
+        pools.push(pool);
+    }
+    let mut replicas = Vec::new();
+    for (rpc, pool, name) in [
+        (&ms_1, &pools[0], "r1a"),
+        (&ms_2, &pools[1], "r2a"),
+        (&ms_nex, &pools[2], "r3a"),
+        (&ms_1, &pools[0], "r1b"),
+        (&ms_2, &pools[1], "r2b"),
+        (&ms_nex, &pools[2], "r3b"),
+    ] {
+        let mut repl = replica(rpc, pool, name);
+        repl.create().await.unwrap();
+        if rpc != &ms_nex {
+            repl.share().await.unwrap();
+        }
+        replicas.push(repl);
+    }
+
+    // By default, the rebuild only reads from its source.
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&replicas[0])
+        .with_replica(&replicas[1]);
+    nex_0.create().await.unwrap();
+    assert!(!nex_0.get_nexus().await.unwrap().striped_rebuild);
+    let sources = rebuild_sources(&mut nex_0, &replicas[2], 1).await;
+    assert_eq!(sources.len(), 1);
+
+    // Striped, the rebuild reads from both healthy children.
+    let mut nex_1 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_striped_rebuild(true)
+        .with_replica(&replicas[3])
+        .with_replica(&replicas[4]);
+    nex_1.create().await.unwrap();
+    assert!(nex_1.get_nexus().await.unwrap().striped_rebuild);
+    let mut sources = rebuild_sources(&mut nex_1, &replicas[5], 2).await;
+    sources.sort();
+    let mut expected = vec![replicas[3].shared_uri(), replicas[4].shared_uri()];
+    expected.sort();
+    assert_eq!(sources, expected);
+
+    nex_1
+        .wait_children_online(Duration::from_secs(60))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,165 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{
+                PauseRebuildRequest,
+                RebuildStatsRequest,
+                RebuildStatsResponse,
+                ResumeRebuildRequest,
+            },
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+static MAX_BANDWIDTH: u64 = 1024 * 1024;
+
+async fn rebuild_stats(nex: &NexusBuilder, uri: &str) -> RebuildStatsResponse {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_stats(RebuildStatsRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+fn eta(stats: &RebuildStatsResponse) -> Option<Duration> {
+    stats.eta.clone().map(|d| Duration::try_from(d).unwrap())
+}
+
+/// The throughput of a rebuild follows its bandwidth limit, and slows down
+/// while it is paused, with its ETA moving accordingly.
+#[tokio::test]
+async fn nexus_rebuild_throughput() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "2",
+                "--rebuild-max-bandwidth",
+                "1MiB",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_1 = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool_1)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_1.create().await.unwrap();
+    repl_1.create().await.unwrap();
+    repl_1.share().await.unwrap();
+
+    let mut pool_local = PoolBuilder::new(ms_nex.clone())
+        .with_name("pool_local")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_local = ReplicaBuilder::new(ms_nex.clone())
+        .with_pool(&pool_local)
+        .with_name("r_local")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool_local.create().await.unwrap();
+    repl_local.create().await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_1);
+    nex_0.create().await.unwrap();
+    nex_0.add_replica(&repl_local, false).await.unwrap();
+    let uri = repl_local.bdev();
+
+    // Let a few samples of the throughput be taken.
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    let running = rebuild_stats(&nex_0, &uri).await;
+    assert!(running.throughput > 0, "{running:?}");
+    assert!(running.throughput <= 2 * MAX_BANDWIDTH, "{running:?}");
+    assert!(running.avg_throughput > 0, "{running:?}");
+    assert!(running.avg_throughput <= 2 * MAX_BANDWIDTH, "{running:?}");
+    let remaining = running.blocks_remaining * running.block_size;
+    let expected =
+        Duration::from_secs_f64(remaining as f64 / running.throughput as f64);
+    let running_eta = eta(&running).unwrap();
+    let diff = if running_eta > expected {
+        running_eta - expected
+    } else {
+        expected - running_eta
+    };
+    assert!(diff < Duration::from_secs(1), "{running:?}");
+
+    // Nothing is transferred while the rebuild is paused.
+    ms_nex
+        .lock()
+        .await
+        .nexus
+        .pause_rebuild(PauseRebuildRequest {
+            nexus_uuid: nex_0.uuid(),
+            uri: uri.clone(),
+        })
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let paused = rebuild_stats(&nex_0, &uri).await;
+    assert!(paused.throughput < running.throughput, "{paused:?}");
+    assert!(paused.avg_throughput < running.avg_throughput, "{paused:?}");
+    if let Some(paused_eta) = eta(&paused) {
+        assert!(paused_eta > running_eta, "{paused:?}");
+    }
+
+    ms_nex
+        .lock()
+        .await
+        .nexus
+        .resume_rebuild(ResumeRebuildRequest {
+            nexus_uuid: nex_0.uuid(),
+            uri,
+        })
+        .await
+        .unwrap();
+    nex_0
+        .wait_children_online(Duration::from_secs(60))
+        .await
+        .unwrap();
+}
@@ -0,0 +1,129 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{
+                DestroyNexusRequest,
+                ListNexusOptions,
+                Nexus,
+                RestoreNexusRequest,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+};
+
+use tonic::Code;
+
+async fn list_marked(rpc: SharedRpcHandle, uuid: &str) -> Option<Nexus> {
+    rpc.lock()
+        .await
+        .nexus
+        .list_nexus(ListNexusOptions {
+            include_marked: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .nexus_list
+        .into_iter()
+        .find(|n| n.uuid == uuid)
+}
+
+/// A nexus marked for deletion by its name must be restorable by its uuid,
+/// and the generation must be shared by both ids.
+#[tokio::test]
+async fn nexus_destroy_by_name_restore_by_uuid() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus_restore")
+        .with_new_uuid()
+        .with_size_mb(10)
+        .with_bdev("malloc:///mem0?size_mb=10");
+    let created = nex_0.create().await.unwrap();
+
+    // Mark the nexus for deletion by its name.
+    ms_0.lock()
+        .await
+        .nexus
+        .destroy_nexus(DestroyNexusRequest {
+            uuid: nex_0.name(),
+            mark_for_deletion: true,
+            expected_generation: Some(created.generation),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let marked = list_marked(ms_0.clone(), &nex_0.uuid()).await.unwrap();
+    assert!(marked.deletion_deadline.is_some());
+    assert_eq!(marked.generation, created.generation + 1);
+
+    // The stale generation is rejected whichever id is used.
+    let err = ms_0
+        .lock()
+        .await
+        .nexus
+        .destroy_nexus(DestroyNexusRequest {
+            uuid: nex_0.uuid(),
+            expected_generation: Some(created.generation),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Aborted);
+
+    // Restore the nexus by its uuid.
+    let restored = ms_0
+        .lock()
+        .await
+        .nexus
+        .restore_nexus(RestoreNexusRequest {
+            uuid: nex_0.uuid(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .nexus
+        .unwrap();
+    assert!(restored.deletion_deadline.is_none());
+    assert_eq!(restored.generation, marked.generation + 1);
+
+    // Nothing is left marked under the name.
+    let err = ms_0
+        .lock()
+        .await
+        .nexus
+        .restore_nexus(RestoreNexusRequest {
+            uuid: nex_0.name(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+
+    nex_0.destroy().await.unwrap();
+    assert!(list_marked(ms_0.clone(), &nex_0.uuid()).await.is_none());
+}
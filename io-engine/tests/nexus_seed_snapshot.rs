@@ -0,0 +1,183 @@
+pub mod common;
+
+use std::convert::TryFrom;
+
+use chrono::Utc;
+use common::MayastorTest;
+use io_engine::{
+    bdev::nexus::{
+        nexus_create,
+        nexus_lookup_mut,
+        Error,
+        NexusReplicaSnapshotDescriptor,
+    },
+    core::{
+        CloneParams,
+        LogicalVolume,
+        MayastorCliArgs,
+        SnapshotOps,
+        SnapshotParams,
+        UntypedBdev,
+    },
+    lvs::{Lvol, Lvs},
+    pool_backend::PoolArgs,
+};
+use uuid::Uuid;
+
+const NEXUS_NAME: &str = "nexus_seed";
+const REPL_SIZE: u64 = 8 * 1024 * 1024;
+
+async fn create_pool(name: &str) -> Lvs {
+    Lvs::create_or_import(PoolArgs {
+        name: name.to_string(),
+        disks: vec![format!("malloc:///{name}?size_mb=64")],
+        uuid: None,
+        force: false,
+    })
+    .await
+    .unwrap()
+}
+
+fn child_uri(lvol: &Lvol) -> String {
+    format!("loopback:///{}", lvol.uuid())
+}
+
+fn lookup_lvol(uuid: &str) -> Lvol {
+    Lvol::try_from(UntypedBdev::lookup_by_uuid_str(uuid).unwrap()).unwrap()
+}
+
+/// Takes a nexus snapshot of the given replicas, and returns the UUIDs of
+/// their snapshots.
+async fn nexus_snapshot(replicas: &[&Lvol]) -> Vec<String> {
+    let params = SnapshotParams::new(
+        Some(NEXUS_NAME.to_string()),
+        Some(NEXUS_NAME.to_string()),
+        Some(Uuid::new_v4().to_string()),
+        Some(Uuid::new_v4().to_string()),
+        Some(Uuid::new_v4().to_string()),
+        Some(Utc::now().to_string()),
+        false,
+    );
+    let descriptors = replicas
+        .iter()
+        .map(|r| NexusReplicaSnapshotDescriptor {
+            replica_uuid: r.uuid(),
+            skip: false,
+            snapshot_uuid: Some(Uuid::new_v4().to_string()),
+        })
+        .collect::<Vec<_>>();
+    let snapshots = descriptors
+        .iter()
+        .map(|d| d.snapshot_uuid.clone().unwrap())
+        .collect();
+
+    let status = nexus_lookup_mut(NEXUS_NAME)
+        .unwrap()
+        .create_snapshot(params, descriptors)
+        .await
+        .unwrap();
+    assert!(status.replicas_done.iter().all(|r| r.status == 0));
+    snapshots
+}
+
+async fn create_clone(snapshot_uuid: &str, name: &str) -> Lvol {
+    lookup_lvol(snapshot_uuid)
+        .create_clone(CloneParams::new(
+            Some(name.to_string()),
+            Some(Uuid::new_v4().to_string()),
+            Some(snapshot_uuid.to_string()),
+            Some(Utc::now().to_string()),
+        ))
+        .await
+        .unwrap()
+}
+
+async fn add_seeded(uri: &str, snapshot_uuid: &str) -> Result<(), Error> {
+    nexus_lookup_mut(NEXUS_NAME)
+        .unwrap()
+        .add_child_seeded(uri, snapshot_uuid)
+        .await
+        .map(|_| ())
+}
+
+#[tokio::test]
+async fn nexus_seed_snapshot_lineage() {
+    common::composer_init();
+
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+
+    ms.spawn(async {
+        let pool_a = create_pool("seed_pool_a").await;
+        let pool_b = create_pool("seed_pool_b").await;
+        let repl_a = pool_a
+            .create_lvol("seed_a", REPL_SIZE, None, true)
+            .await
+            .unwrap();
+        let repl_b = pool_b
+            .create_lvol("seed_b", REPL_SIZE, None, true)
+            .await
+            .unwrap();
+
+        nexus_create(
+            NEXUS_NAME,
+            REPL_SIZE,
+            None,
+            &[child_uri(&repl_a), child_uri(&repl_b)],
+        )
+        .await
+        .unwrap();
+
+        let snapshots = nexus_snapshot(&[&repl_a, &repl_b]).await;
+        let (snap_a, snap_b) = (&snapshots[0], &snapshots[1]);
+
+        // Replica B is replaced by a clone of its snapshot.
+        nexus_lookup_mut(NEXUS_NAME)
+            .unwrap()
+            .remove_child(&child_uri(&repl_b))
+            .await
+            .unwrap();
+        let clone_b = create_clone(snap_b, "seed_b_clone").await;
+        let uri = child_uri(&clone_b);
+
+        // The clone is not seeded from the snapshot of replica A.
+        match add_seeded(&uri, snap_a).await {
+            Err(Error::SeedSnapshotMismatch {
+                child, ..
+            }) => assert_eq!(child, uri),
+            res => panic!("unexpected seeded add result: {res:?}"),
+        }
+
+        // A device which is not a local replica cannot be verified.
+        match add_seeded("malloc:///seed_m0?size_mb=8", snap_b).await {
+            Err(Error::SeedLineageUnknown {
+                ..
+            }) => {}
+            res => panic!("unexpected seeded add result: {res:?}"),
+        }
+
+        // Replica A was snapshotted again: the seed misses the writes held by
+        // the newer snapshot.
+        nexus_snapshot(&[&repl_a]).await;
+        match add_seeded(&uri, snap_b).await {
+            Err(Error::SeedSnapshotStale {
+                child, ..
+            }) => assert_eq!(child, child_uri(&repl_a)),
+            res => panic!("unexpected seeded add result: {res:?}"),
+        }
+        let nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+        assert!(nexus.lookup_child(&uri).is_none());
+
+        // A clone of the latest snapshot is seeded.
+        let snapshots = nexus_snapshot(&[&repl_a]).await;
+        let clone_a = create_clone(&snapshots[0], "seed_a_clone").await;
+        let uri = child_uri(&clone_a);
+        add_seeded(&uri, &snapshots[0]).await.unwrap();
+        let nexus = nexus_lookup_mut(NEXUS_NAME).unwrap();
+        assert!(nexus.lookup_child(&uri).is_some());
+
+        nexus.destroy().await.unwrap();
+        pool_a.destroy().await.unwrap();
+        pool_b.destroy().await.unwrap();
+    })
+    .await;
+}
@@ -0,0 +1,116 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{Nexus, NexusFlushTarget, NexusState, NexusStateReason},
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+};
+
+fn assert_state(
+    nexus: &Nexus,
+    state: NexusState,
+    reason: NexusStateReason,
+    message: &str,
+) {
+    assert_eq!(nexus.state, state as i32, "{nexus:?}");
+    assert_eq!(nexus.state_reason, reason as i32, "{nexus:?}");
+    assert_eq!(nexus.state_message, message);
+}
+
+/// The reason of the state of a nexus is reported along with the state, and
+/// a nexus under the quorum flush policy is faulted once the healthy
+/// children are not a majority.
+#[tokio::test]
+async fn nexus_state_reason() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let children = (0 .. 3)
+        .map(|i| format!("malloc:///m{i}?size_mb=16"))
+        .collect::<Vec<_>>();
+    let mut nex_quorum = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus_quorum")
+        .with_new_uuid()
+        .with_size_mb(8)
+        .with_children(children.clone())
+        .with_flush_policy(NexusFlushTarget::FlushQuorum, false);
+    nex_quorum.create().await.unwrap();
+
+    assert_state(
+        &nex_quorum.get_nexus().await.unwrap(),
+        NexusState::NexusOnline,
+        NexusStateReason::None,
+        "all children are healthy",
+    );
+
+    let nexus = nex_quorum.offline_child_bdev(&children[0]).await.unwrap();
+    assert_state(
+        &nexus,
+        NexusState::NexusDegraded,
+        NexusStateReason::ChildrenUnhealthy,
+        "2 of 3 children are healthy",
+    );
+
+    // A single healthy child out of three is not a flush quorum.
+    let nexus = nex_quorum.offline_child_bdev(&children[1]).await.unwrap();
+    assert_state(
+        &nexus,
+        NexusState::NexusFaulted,
+        NexusStateReason::QuorumLost,
+        "1 of 3 children are healthy, which is not a flush quorum",
+    );
+
+    // The last healthy child stays.
+    nex_quorum
+        .offline_child_bdev(&children[2])
+        .await
+        .expect_err("The last healthy child should not be offlined");
+
+    // Without the quorum flush policy, the nexus is only degraded.
+    let children = (3 .. 5)
+        .map(|i| format!("malloc:///m{i}?size_mb=16"))
+        .collect::<Vec<_>>();
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(8)
+        .with_children(children.clone());
+    nex.create().await.unwrap();
+
+    let nexus = nex.offline_child_bdev(&children[0]).await.unwrap();
+    assert_state(
+        &nexus,
+        NexusState::NexusDegraded,
+        NexusStateReason::ChildrenUnhealthy,
+        "1 of 2 children are healthy",
+    );
+
+    nex.shutdown().await.unwrap();
+    assert_state(
+        &nex.get_nexus().await.unwrap(),
+        NexusState::NexusShutdown,
+        NexusStateReason::ShutdownRequested,
+        "shutdown requested",
+    );
+}
@@ -0,0 +1,175 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::{
+                DestroyNexusRequest,
+                DestroyNexusResponse,
+                RebuildStateRequest,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nexus::{list_nexuses, test_write_to_nexus, NexusBuilder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+const POOL_SIZE: u64 = 100;
+const REPL_SIZE: u64 = 60;
+
+async fn destroy_nexus(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    verbose: bool,
+) -> Result<DestroyNexusResponse, Status> {
+    rpc.lock()
+        .await
+        .nexus
+        .destroy_nexus(DestroyNexusRequest {
+            uuid: uuid.to_string(),
+            verbose,
+            ..Default::default()
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+async fn rebuild_state(
+    nex: &NexusBuilder,
+    uri: &str,
+) -> Result<String, Status> {
+    nex.rpc()
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_state(RebuildStateRequest {
+            nexus_uuid: nex.uuid(),
+            uri: uri.to_string(),
+        })
+        .await
+        .map(|r| r.into_inner().state)
+}
+
+/// Destroying a nexus aborts its running rebuilds before its children are
+/// closed.
+#[tokio::test]
+async fn nexus_teardown_aborts_rebuild() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--rebuild-max-bandwidth",
+                "4MiB",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 2 * POOL_SIZE);
+    pool.create().await.unwrap();
+
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    let mut repl_1 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    repl_0.create().await.unwrap();
+    repl_1.create().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_0);
+    nex.create().await.unwrap();
+    nex.publish().await.unwrap();
+
+    test_write_to_nexus(
+        &nex,
+        DataSize::from_bytes(0),
+        16,
+        DataSize::from_mb(1),
+    )
+    .await
+    .unwrap();
+
+    // The bandwidth limit keeps the rebuild running for several seconds.
+    nex.add_replica(&repl_1, false).await.unwrap();
+    let uri = nex.get_nexus_replica_child(&repl_1).await.unwrap().uri;
+    assert_eq!(rebuild_state(&nex, &uri).await.unwrap(), "running");
+
+    let start = Instant::now();
+    let res = destroy_nexus(&ms_0, &nex.uuid(), true).await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(10));
+
+    // All phases are reported, the rebuilds being aborted first.
+    let phases = res
+        .teardown
+        .iter()
+        .map(|s| s.phase.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(phases, [
+        "AbortRebuilds",
+        "Unshare",
+        "DrainWriteCache",
+        "DetachReadCache",
+        "CloseIoLogJournal",
+        "CloseChildren",
+        "Persist",
+        "Unregister",
+    ]);
+    assert!(res.teardown.iter().all(|s| s.duration.is_some()));
+
+    assert!(list_nexuses(ms_0.clone()).await.unwrap().is_empty());
+    assert_eq!(
+        rebuild_state(&nex, &uri).await.unwrap_err().code(),
+        Code::NotFound
+    );
+
+    // No descriptor is left open on the rebuild destination.
+    repl_1.destroy().await.unwrap();
+
+    // Phases are only reported on request, and unknown nexuses cannot be
+    // destroyed.
+    nex.create().await.unwrap();
+    let res = destroy_nexus(&ms_0, &nex.uuid(), false).await.unwrap();
+    assert!(res.teardown.is_empty());
+    assert_eq!(
+        destroy_nexus(&ms_0, &nex.uuid(), true)
+            .await
+            .unwrap_err()
+            .code(),
+        Code::NotFound
+    );
+}
@@ -110,7 +110,7 @@ async fn nexus_thin_create_1() {
 
     let r = nex_0.create().await;
 
-    assert_eq!(r.unwrap_err().code(), tonic::Code::Internal);
+    assert_eq!(r.unwrap_err().code(), tonic::Code::FailedPrecondition);
 }
 
 #[tokio::test]
@@ -0,0 +1,153 @@
+pub mod common;
+
+use common::compose::{
+    rpc::{
+        v0::{
+            mayastor::{
+                AddChildNexusRequest,
+                CreateNexusRequest,
+                DestroyNexusRequest,
+                Null,
+                RebuildProgressRequest as V0RebuildProgressRequest,
+            },
+            GrpcConnect as GrpcConnectV0,
+        },
+        v1::{
+            nexus::RebuildProgressRequest,
+            GrpcConnect,
+        },
+    },
+    Binary,
+    Builder,
+};
+use io_engine::grpc::v0::DEPRECATED_METADATA_KEY;
+use tonic::{Code, Response};
+
+const NEXUS_UUID: &str = "4d4e5c6b-9a34-4d36-9f1e-2a8b0c7d1e5f";
+const CHILD_0: &str = "malloc:///d0?size_mb=64";
+const CHILD_1: &str = "malloc:///d1?size_mb=64";
+
+/// Returns the v1 method which replaces the v0 method of the response.
+fn replacement<T>(response: &Response<T>) -> Option<&str> {
+    response
+        .metadata()
+        .get(DEPRECATED_METADATA_KEY)
+        .map(|v| v.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn nexus_v0_deprecated() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--rebuild-max-bandwidth",
+                "4MiB",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let mut v0 = GrpcConnectV0::new(&test).grpc_handle("ms_0").await.unwrap();
+    let v1 = GrpcConnect::new(&test)
+        .grpc_handle_shared("ms_0")
+        .await
+        .unwrap();
+
+    // Responses of v0 nexus methods name the v1 method replacing them.
+    let created = v0
+        .mayastor
+        .create_nexus(CreateNexusRequest {
+            uuid: NEXUS_UUID.to_string(),
+            size: 64 * 1024 * 1024,
+            children: vec![CHILD_0.to_string()],
+        })
+        .await
+        .unwrap();
+    assert_eq!(replacement(&created), Some("NexusRpc::create_nexus"));
+
+    let listed = v0.mayastor.list_nexus(Null {}).await.unwrap();
+    assert_eq!(replacement(&listed), Some("NexusRpc::list_nexus"));
+
+    // Other v0 methods are not marked.
+    let pools = v0.mayastor.list_pools(Null {}).await.unwrap();
+    assert_eq!(replacement(&pools), None);
+
+    let added = v0
+        .mayastor
+        .add_child_nexus(AddChildNexusRequest {
+            uuid: NEXUS_UUID.to_string(),
+            uri: CHILD_1.to_string(),
+            norebuild: false,
+        })
+        .await
+        .unwrap();
+    assert_eq!(replacement(&added), Some("NexusRpc::add_child_nexus"));
+
+    // The progress of the rebuild is reported by both APIs while the
+    // bandwidth limit keeps it running.
+    let progress = v1
+        .lock()
+        .await
+        .nexus
+        .get_rebuild_progress(RebuildProgressRequest {
+            nexus_uuid: NEXUS_UUID.to_string(),
+            uri: CHILD_1.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .progress;
+    assert!(progress <= 100);
+
+    let v0_progress = v0
+        .mayastor
+        .get_rebuild_progress(V0RebuildProgressRequest {
+            uuid: NEXUS_UUID.to_string(),
+            uri: CHILD_1.to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        replacement(&v0_progress),
+        Some("NexusRpc::get_rebuild_progress")
+    );
+    assert!(v0_progress.into_inner().progress >= progress);
+
+    // There is no progress for a child which is not rebuilding, or for an
+    // unknown nexus.
+    for (nexus_uuid, uri) in [
+        (NEXUS_UUID, CHILD_0),
+        ("5a1d3f2c-0b7e-4a8d-8c6f-3e9b2d1a0c4e", CHILD_1),
+    ] {
+        let status = v1
+            .lock()
+            .await
+            .nexus
+            .get_rebuild_progress(RebuildProgressRequest {
+                nexus_uuid: nexus_uuid.to_string(),
+                uri: uri.to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    let destroyed = v0
+        .mayastor
+        .destroy_nexus(DestroyNexusRequest {
+            uuid: NEXUS_UUID.to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(replacement(&destroyed), Some("NexusRpc::destroy_nexus"));
+}
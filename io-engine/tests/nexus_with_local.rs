@@ -98,6 +98,7 @@ async fn create_nexus(h: &mut RpcHandle, children: Vec<String>) {
             nexus_info_key: nexus_name(),
             resv_type: None,
             preempt_policy: 0,
+            ..Default::default()
         })
         .await
         .unwrap();
@@ -147,6 +148,7 @@ async fn nexus_with_local() {
         .remove_child_nexus(RemoveChildNexusRequest {
             uri: child0.clone(),
             uuid: nexus_uuid(),
+            ..Default::default()
         })
         .await
         .unwrap();
@@ -158,6 +160,10 @@ async fn nexus_with_local() {
             uri: child0.clone(),
             uuid: nexus_uuid(),
             norebuild: false,
+            seed_snapshot: None,
+            rebuild_source: None,
+            rebuild_at: None,
+            ..Default::default()
         })
         .await
         .unwrap();
@@ -167,6 +173,10 @@ async fn nexus_with_local() {
             uri: child0.clone(),
             uuid: nexus_uuid(),
             norebuild: false,
+            seed_snapshot: None,
+            rebuild_source: None,
+            rebuild_at: None,
+            ..Default::default()
         })
         .await
         .expect_err("Should fail to add the same child again");
@@ -175,6 +185,7 @@ async fn nexus_with_local() {
     ms1.bdev
         .destroy(DestroyBdevRequest {
             uri: child0.clone(),
+            ..Default::default()
         })
         .await
         .unwrap();
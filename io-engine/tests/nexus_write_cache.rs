@@ -0,0 +1,250 @@
+#![cfg(feature = "fault-injection")]
+
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{nexus::NexusWriteCacheStats, GrpcConnect},
+        Binary,
+        Builder,
+        ComposeTest,
+    },
+    file_io::{test_write_to_file, DataSize},
+    nexus::{test_write_to_nexus, NexusBuilder},
+    nvme::{nvme_flush, nvme_write_fua},
+    nvmf::test_devices_identical,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+    test::add_fault_injection,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 50;
+static CACHE_SIZE: u64 = 16 * 1024 * 1024;
+static BLOCK_LEN: u64 = 512;
+
+#[allow(dead_code)]
+struct StorageBuilder {
+    pool_0: PoolBuilder,
+    pool_1: PoolBuilder,
+    repl_0: ReplicaBuilder,
+    repl_1: ReplicaBuilder,
+    nex_0: NexusBuilder,
+}
+
+/// Creates a composer test, with a nexus node running on two cores so that
+/// the cache is split into several shards.
+async fn create_compose_test() -> ComposeTest {
+    common::composer_init();
+
+    Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "3,4",
+                "-Fcolor,compact",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap()
+}
+
+/// Creates a nexus with the write cache enabled over two replicas.
+async fn create_test_storage(test: &ComposeTest) -> StorageBuilder {
+    let conn = GrpcConnect::new(test);
+
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+
+    let mut pool_0 = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool_0)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+
+    pool_0.create().await.unwrap();
+    repl_0.create().await.unwrap();
+    repl_0.share().await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+
+    let mut repl_1 = ReplicaBuilder::new(ms_1.clone())
+        .with_pool(&pool_1)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+
+    pool_1.create().await.unwrap();
+    repl_1.create().await.unwrap();
+    repl_1.share().await.unwrap();
+
+    let mut nex_0 = NexusBuilder::new(ms_nex.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_replica(&repl_0)
+        .with_replica(&repl_1)
+        .with_write_cache(CACHE_SIZE);
+
+    nex_0.create().await.unwrap();
+    nex_0.publish().await.unwrap();
+
+    StorageBuilder {
+        pool_0,
+        pool_1,
+        repl_0,
+        repl_1,
+        nex_0,
+    }
+}
+
+async fn cache_stats(nex: &NexusBuilder) -> NexusWriteCacheStats {
+    nex.get_nexus().await.unwrap().write_cache.unwrap()
+}
+
+/// Waits until all cached writes have reached the children.
+async fn wait_destaged(nex: &NexusBuilder) -> NexusWriteCacheStats {
+    for _ in 0 .. 100 {
+        let stats = cache_stats(nex).await;
+        if stats.dirty_bytes == 0 {
+            return stats;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("cached writes not destaged: {:?}", cache_stats(nex).await);
+}
+
+#[tokio::test]
+async fn nexus_write_cache_destage() {
+    let test = create_compose_test().await;
+
+    let StorageBuilder {
+        pool_0: _,
+        pool_1: _,
+        repl_0,
+        repl_1,
+        nex_0,
+    } = create_test_storage(&test).await;
+
+    let stats = cache_stats(&nex_0).await;
+    assert_eq!(stats.capacity, CACHE_SIZE);
+    assert_eq!(stats.cached_writes, 0);
+
+    // Writes complete from the cache, and reads return the cached data.
+    test_write_to_nexus(
+        &nex_0,
+        DataSize::from_bytes(0),
+        10,
+        DataSize::from_kb(64),
+    )
+    .await
+    .unwrap();
+
+    let stats = wait_destaged(&nex_0).await;
+    assert!(stats.cached_writes > 0);
+    assert_eq!(stats.errors, 0);
+
+    // Once destaged, the data is on both replicas.
+    test_devices_identical(&[repl_0.nvmf_location(), repl_1.nvmf_location()])
+        .await
+        .unwrap();
+
+    // A flush after the destage succeeds.
+    let (_cg, path) = nex_0.nvmf_location().open().unwrap();
+    assert!(nvme_flush(&path).success());
+}
+
+#[tokio::test]
+async fn nexus_write_cache_flush_error() {
+    let test = create_compose_test().await;
+
+    let StorageBuilder {
+        pool_0: _,
+        pool_1: _,
+        repl_0: _,
+        repl_1: _,
+        nex_0,
+    } = create_test_storage(&test).await;
+
+    let (_cg, path) = nex_0.nvmf_location().open().unwrap();
+
+    // A flush with nothing cached succeeds.
+    assert!(nvme_flush(&path).success());
+
+    // Make the writes fail on all children.
+    let children = nex_0.get_nexus().await.unwrap().children;
+    assert_eq!(children.len(), 2);
+    for child in children.iter() {
+        let dev_name = child.device_name.as_ref().unwrap();
+        let inj_uri = format!("inject://{dev_name}?domain=nexus&op=write");
+        add_fault_injection(nex_0.rpc(), &inj_uri).await.unwrap();
+    }
+
+    // The writes complete from the cache, but fail to reach the children.
+    test_write_to_file(&path, DataSize::from_bytes(0), 1, DataSize::from_kb(4))
+        .await
+        .ok();
+
+    let stats = wait_destaged(&nex_0).await;
+    assert!(stats.errors > 0);
+
+    // The next flush reports the lost writes.
+    assert!(!nvme_flush(&path).success());
+}
+
+#[tokio::test]
+async fn nexus_write_cache_fua() {
+    let test = create_compose_test().await;
+
+    let StorageBuilder {
+        pool_0: _,
+        pool_1: _,
+        repl_0,
+        repl_1,
+        nex_0,
+    } = create_test_storage(&test).await;
+
+    let (cg, path) = nex_0.nvmf_location().open().unwrap();
+
+    // FUA writes bypass the cache: they complete once on the children.
+    let before = cache_stats(&nex_0).await;
+    for i in 0 .. 8 {
+        assert!(nvme_write_fua(&path, i * 8, 8, BLOCK_LEN).success());
+    }
+    let after = cache_stats(&nex_0).await;
+    assert_eq!(after.cached_writes, before.cached_writes);
+    assert_eq!(after.dirty_bytes, 0);
+
+    // The data is on both replicas without waiting for the destage.
+    drop(cg);
+    test_devices_identical(&[repl_0.nvmf_location(), repl_1.nvmf_location()])
+        .await
+        .unwrap();
+}
@@ -0,0 +1,67 @@
+pub mod common;
+
+use common::MayastorTest;
+use io_engine::core::{MayastorCliArgs, Numa};
+use structopt::StructOpt;
+
+const DISK_NAME: &str = "/tmp/disk_numa.img";
+
+#[test]
+fn numa_node_args() {
+    let args = MayastorCliArgs::from_iter_safe([
+        "io-engine",
+        "--numa-aware",
+        "--numa-node",
+        "0000:5e:00.0=1,eth0=-1",
+    ])
+    .unwrap();
+    assert!(args.numa_aware);
+    assert_eq!(
+        args.numa_nodes,
+        [("0000:5e:00.0".to_string(), 1), ("eth0".to_string(), -1)]
+    );
+
+    for node in ["eth0", "=1", "eth0=-2", "eth0=one"] {
+        assert!(
+            MayastorCliArgs::from_iter_safe(["io-engine", "--numa-node", node])
+                .is_err(),
+            "{node}"
+        );
+    }
+}
+
+/// The NUMA nodes configured for devices take precedence over sysfs, and
+/// the reactor cores are found by node.
+#[tokio::test]
+async fn numa_device_node() {
+    common::delete_file(&[DISK_NAME.into()]);
+    common::truncate_file(DISK_NAME, 1024);
+
+    let args = MayastorCliArgs {
+        numa_aware: true,
+        numa_nodes: vec![
+            ("disk_numa.img".to_string(), 0),
+            ("0000:5e:00.0".to_string(), -1),
+        ],
+        ..Default::default()
+    };
+    let ms = MayastorTest::new(args);
+
+    ms.spawn(async {
+        assert_eq!(Numa::device_node(&format!("aio://{DISK_NAME}")), Some(0));
+        // A node of -1 disables the placement.
+        assert_eq!(Numa::device_node("pcie:///0000:5e:00.0"), None);
+        assert_eq!(Numa::device_node("aio:///tmp/no_such_disk.img"), None);
+        assert_eq!(Numa::device_node("malloc:///m0?size_mb=8"), None);
+        assert_eq!(Numa::device_node("not a uri"), None);
+
+        let cores = Numa::cores(Numa::core_node(0));
+        assert!(cores.contains(&0));
+        assert!(Numa::local_core(Numa::core_node(0)).is_some());
+        assert!(Numa::cores(u32::MAX).is_empty());
+        assert!(Numa::local_core(u32::MAX).is_none());
+    })
+    .await;
+
+    common::delete_file(&[DISK_NAME.into()]);
+}
@@ -0,0 +1,291 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            nexus::PublishNexusRequest,
+            replica::{Replica, ShareReplicaRequest, UnshareReplicaRequest},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    nvme::{find_mayastor_nvme_device, NmveConnectGuard},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use io_engine::{constants::NVME_NQN_PREFIX, core::NvmeIdentity};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+const SERIAL: &str = "ms-identity-0001";
+const MODEL: &str = "Mayastor NVMe controller identity";
+
+async fn share(
+    rpc: &SharedRpcHandle,
+    repl: &ReplicaBuilder,
+    eui64: &str,
+    serial: &str,
+    model: &str,
+) -> Result<Replica, Status> {
+    rpc.lock()
+        .await
+        .replica
+        .share_replica(ShareReplicaRequest {
+            uuid: repl.uuid(),
+            share: 1,
+            eui64: eui64.to_string(),
+            serial: serial.to_string(),
+            model: model.to_string(),
+            ..Default::default()
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+/// Checks the replica is seen by the host with the given serial and model.
+fn assert_identity(repl: &ReplicaBuilder, serial: &str, model: &str) {
+    let location = repl.nvmf_location();
+    let _cg = NmveConnectGuard::connect_addr(&location.addr, &location.nqn);
+    let device = find_mayastor_nvme_device(serial).unwrap();
+    assert_eq!(device.model.trim(), model);
+}
+
+#[test]
+fn nvme_identity_parse() {
+    let identity = NvmeIdentity::parse(
+        "00-11-22-33-44-55-66-77",
+        "",
+        SERIAL,
+        MODEL,
+    )
+    .unwrap();
+    assert_eq!(
+        identity.eui64,
+        Some([0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77])
+    );
+    assert!(identity.nguid.is_none());
+    assert_eq!(identity.serial.as_deref(), Some(SERIAL));
+    assert!(NvmeIdentity::parse("", "", "", "").unwrap().is_default());
+
+    for (eui64, nguid, serial, model) in [
+        ("0011", "", "", ""),
+        ("00112233445566zz", "", "", ""),
+        ("0000-0000-0000-0000", "", "", ""),
+        ("", "00112233445566778899aabbccddeeff00", "", ""),
+        ("", "", "serial-number-too-long", ""),
+        ("", "", "", "model\tnumber"),
+    ] {
+        assert!(
+            NvmeIdentity::parse(eui64, nguid, serial, model).is_err(),
+            "{eui64:?} {nguid:?} {serial:?} {model:?}"
+        );
+    }
+}
+
+#[test]
+fn nvme_identity_nqn() {
+    let identity = NvmeIdentity::default().with_nqn("").unwrap();
+    assert!(identity.is_default());
+
+    // A suffix is appended to the mayastor NQN prefix.
+    let identity = NvmeIdentity::default().with_nqn("vol-0:r0").unwrap();
+    assert_eq!(
+        identity.nqn.as_deref(),
+        Some(format!("{NVME_NQN_PREFIX}:vol-0:r0").as_str())
+    );
+    let identity = NvmeIdentity::default()
+        .with_nqn("nqn.2023-01.com.example:r0")
+        .unwrap();
+    assert_eq!(identity.nqn.as_deref(), Some("nqn.2023-01.com.example:r0"));
+
+    for nqn in [
+        "bad suffix",
+        "r0/1",
+        "nqn.23-01.com.example:r0",
+        "r".repeat(256).as_str(),
+    ] {
+        assert!(NvmeIdentity::default().with_nqn(nqn).is_err(), "{nqn}");
+    }
+}
+
+/// A replica is shared with the given identification, and shared again with
+/// it when the share is recreated without one.
+#[tokio::test]
+async fn nvme_identity_share() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(true);
+    let mut repl_1 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(true);
+    pool.create().await.unwrap();
+    repl_0.create().await.unwrap();
+    repl_1.create().await.unwrap();
+
+    share(&ms_0, &repl_0, "00-11-22-33-44-55-66-77", SERIAL, MODEL)
+        .await
+        .unwrap();
+    assert_identity(&repl_0, SERIAL, MODEL);
+
+    // The identification is kept by the replica.
+    ms_0.lock()
+        .await
+        .replica
+        .unshare_replica(UnshareReplicaRequest {
+            uuid: repl_0.uuid(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    share(&ms_0, &repl_0, "", "", "").await.unwrap();
+    assert_identity(&repl_0, SERIAL, MODEL);
+
+    // Invalid fields are refused, and the replica is not shared.
+    for (eui64, serial) in [("0011", ""), ("", "serial-number-too-long")] {
+        assert_eq!(
+            share(&ms_0, &repl_1, eui64, serial, "")
+                .await
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+    let replica = repl_1.get_replica().await.unwrap();
+    assert!(!replica.uri.starts_with("nvmf://"), "{}", replica.uri);
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repl_1);
+    nex.create().await.unwrap();
+    let error = ms_0
+        .lock()
+        .await
+        .nexus
+        .publish_nexus(PublishNexusRequest {
+            uuid: nex.uuid(),
+            share: 1,
+            nguid: "not-an-nguid".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+}
+
+/// A replica is shared under a custom NQN, and can be unshared and shared
+/// again under its default NQN.
+#[tokio::test]
+async fn nvme_identity_share_nqn() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(true);
+    pool.create().await.unwrap();
+    repl_0.create().await.unwrap();
+
+    let share_nqn = |nqn: &str| ShareReplicaRequest {
+        uuid: repl_0.uuid(),
+        share: 1,
+        nqn: nqn.to_string(),
+        ..Default::default()
+    };
+
+    // An invalid NQN is refused, and the replica is not shared.
+    let error = ms_0
+        .lock()
+        .await
+        .replica
+        .share_replica(share_nqn("bad nqn"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+    let replica = repl_0.get_replica().await.unwrap();
+    assert!(!replica.uri.starts_with("nvmf://"), "{}", replica.uri);
+
+    let replica = ms_0
+        .lock()
+        .await
+        .replica
+        .share_replica(share_nqn("volume-0"))
+        .await
+        .unwrap()
+        .into_inner();
+    let nqn = format!("{NVME_NQN_PREFIX}:volume-0");
+    assert!(replica.uri.contains(&format!("/{nqn}")), "{}", replica.uri);
+
+    // The subsystem is found by its bdev to be unshared.
+    ms_0.lock()
+        .await
+        .replica
+        .unshare_replica(UnshareReplicaRequest {
+            uuid: repl_0.uuid(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let replica = repl_0.get_replica().await.unwrap();
+    assert!(!replica.uri.starts_with("nvmf://"), "{}", replica.uri);
+
+    let replica = repl_0.share().await.unwrap();
+    assert!(!replica.uri.contains(&nqn), "{}", replica.uri);
+    assert!(replica.uri.contains(&repl_0.nqn()), "{}", replica.uri);
+}
@@ -7,7 +7,7 @@ use io_engine::{
         Reactor,
         UntypedBdev,
     },
-    subsys::{NvmfSubsystem, SubType},
+    subsys::{validate_nqn, NvmfSubsystem, SubType},
 };
 
 pub mod common;
@@ -194,3 +194,21 @@ async fn nvmf_set_target_interface() {
     // test_fail("10.15.0.0/16", vec!["-T", "mac:123"]).await;
     // test_fail("10.15.0.0/16", vec!["-T", "ip:hello"]).await;
 }
+
+#[test]
+fn nvmf_validate_nqn() {
+    assert!(validate_nqn("nqn.2019-05.io.openebs:node-name:n1").is_ok());
+    assert!(validate_nqn(
+        "nqn.2014-08.org.nvmexpress:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6"
+    )
+    .is_ok());
+    assert!(validate_nqn("nqn.2014-08.org.nvmexpress:uuid:bad").is_err());
+    assert!(validate_nqn("nqn.2019-13.io.openebs:x").is_err());
+    assert!(validate_nqn("nqn.2019-05:x").is_err());
+    assert!(validate_nqn("iqn.2019-05.io.openebs:x").is_err());
+    assert!(validate_nqn(&format!(
+        "nqn.2019-05.io.openebs:{}",
+        "a".repeat(220)
+    ))
+    .is_err());
+}
@@ -0,0 +1,116 @@
+pub mod common;
+
+use common::{
+    compose::{rpc::v1::GrpcConnect, Binary, Builder},
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use io_engine::core::MayastorCliArgs;
+use structopt::StructOpt;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+static KATO_MIN_MS: u32 = 25_000;
+
+#[test]
+fn nvmf_rtt_tuning_args() {
+    let args = MayastorCliArgs::from_iter_safe(["io-engine"]).unwrap();
+    assert!(!args.nvmf_rtt_tuning);
+    assert_eq!(args.nvmf_kato_min_ms, 0);
+    assert_eq!(args.nvmf_kato_max_ms, 30_000);
+
+    let args = MayastorCliArgs::from_iter_safe([
+        "io-engine",
+        "--nvmf-rtt-tuning",
+        "--nvmf-kato-min-ms",
+        "2000",
+        "--nvmf-kato-max-ms",
+        "60000",
+    ])
+    .unwrap();
+    assert!(args.nvmf_rtt_tuning);
+    assert_eq!(args.nvmf_kato_min_ms, 2000);
+    assert_eq!(args.nvmf_kato_max_ms, 60_000);
+
+    for (arg, value) in
+        [("--nvmf-kato-min-ms", "-1"), ("--nvmf-kato-max-ms", "30s")]
+    {
+        assert!(
+            MayastorCliArgs::from_iter_safe(["io-engine", arg, value])
+                .is_err(),
+            "{arg} {value}"
+        );
+    }
+}
+
+/// The keep-alive timeout of the controllers of remote children is raised
+/// to the floor of the tuning, when enabled.
+#[tokio::test]
+async fn nvmf_rtt_tuning() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .add_container_bin(
+            "ms_nex",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .add_container_bin(
+            "ms_tuned",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "3",
+                "--nvmf-rtt-tuning",
+                "--nvmf-kato-min-ms",
+                "25000",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+    let ms_nex = conn.grpc_handle_shared("ms_nex").await.unwrap();
+    let ms_tuned = conn.grpc_handle_shared("ms_tuned").await.unwrap();
+
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool_1.create().await.unwrap();
+
+    let mut kato = Vec::new();
+    for (i, rpc) in [&ms_nex, &ms_tuned].into_iter().enumerate() {
+        let mut repl = ReplicaBuilder::new(ms_1.clone())
+            .with_pool(&pool_1)
+            .with_name(&format!("r{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(false);
+        repl.create().await.unwrap();
+        repl.share().await.unwrap();
+
+        let mut nex = NexusBuilder::new(rpc.clone())
+            .with_name(&format!("nexus{i}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_replica(&repl);
+        nex.create().await.unwrap();
+
+        let child = nex.get_nexus_replica_child(&repl).await.unwrap();
+        kato.push(child.nvme_params.unwrap().keep_alive_timeout_ms);
+    }
+
+    // The target may round the keep-alive timeout up.
+    assert!(kato[1] >= KATO_MIN_MS, "{kato:?}");
+    assert!(kato[0] < kato[1], "{kato:?}");
+}
@@ -0,0 +1,108 @@
+pub mod common;
+
+use common::compose::{
+    rpc::v1::{
+        host::{
+            CreateNvmfTransportRequest,
+            NvmfTransport,
+            NvmfTransportType,
+        },
+        GrpcConnect,
+        SharedRpcHandle,
+    },
+    Binary,
+    Builder,
+};
+use tonic::{Code, Status};
+
+async fn list_transports(rpc: &SharedRpcHandle) -> Vec<NvmfTransport> {
+    rpc.lock()
+        .await
+        .host
+        .list_nvmf_transports(())
+        .await
+        .unwrap()
+        .into_inner()
+        .transports
+}
+
+async fn create_transport(
+    rpc: &SharedRpcHandle,
+    request: CreateNvmfTransportRequest,
+) -> Result<NvmfTransport, Status> {
+    rpc.lock()
+        .await
+        .host
+        .create_nvmf_transport(request)
+        .await
+        .map(|r| r.into_inner().transport.unwrap())
+}
+
+/// The TCP transport created at startup is listed with its options, and
+/// transports are only created once, with valid options.
+#[tokio::test]
+async fn nvmf_transport() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let transports = list_transports(&ms_0).await;
+    assert_eq!(transports.len(), 1);
+    let tcp = &transports[0];
+    assert_eq!(tcp.trtype, "TCP");
+    assert!(tcp.max_queue_depth > 0);
+    assert!(tcp.max_io_size > 0);
+    assert!(tcp.poll_groups > 0);
+
+    assert_eq!(
+        create_transport(
+            &ms_0,
+            CreateNvmfTransportRequest {
+                trtype: NvmfTransportType::Tcp as i32,
+                ..Default::default()
+            }
+        )
+        .await
+        .unwrap_err()
+        .code(),
+        Code::AlreadyExists
+    );
+
+    for request in [
+        CreateNvmfTransportRequest {
+            trtype: 99,
+            ..Default::default()
+        },
+        CreateNvmfTransportRequest {
+            trtype: NvmfTransportType::Rdma as i32,
+            max_queue_depth: Some(u16::MAX as u32 + 1),
+            ..Default::default()
+        },
+    ] {
+        assert_eq!(
+            create_transport(&ms_0, request.clone())
+                .await
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument,
+            "{request:?}"
+        );
+    }
+
+    // The options are left unchanged by the failed requests.
+    assert_eq!(list_transports(&ms_0).await, transports);
+}
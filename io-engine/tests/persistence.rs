@@ -28,7 +28,7 @@ use common::compose::{
 };
 use etcd_client::Client;
 
-use io_engine::bdev::nexus::{ChildInfo, NexusInfo};
+use io_engine::bdev::nexus::{ChildInfo, FaultReason, NexusInfo};
 
 use std::{convert::TryFrom, thread::sleep, time::Duration};
 use url::Url;
@@ -234,6 +234,8 @@ async fn persist_io_failure() {
     // Expect child2 to be faulted due to an I/O error.
     let child = child_info(&nexus_info, &uuid(&child2));
     assert!(!child.healthy);
+    assert_eq!(child.fault_reason, Some(FaultReason::IoError));
+    assert!(child.changed_at.is_some());
 
     // Create new child and add to nexus
     let child3 = create_and_share_bdevs(ms4, CHILD3_UUID).await;
@@ -255,6 +257,8 @@ async fn persist_io_failure() {
     let nexus_info: NexusInfo = serde_json::from_slice(value).unwrap();
     let child = child_info(&nexus_info, &uuid(&child3));
     assert!(!child.healthy);
+    assert_eq!(child.fault_reason, None);
+    let generation = child.generation;
 
     // Wait for rebuild to complete.
     loop {
@@ -293,6 +297,7 @@ async fn persist_io_failure() {
     let nexus_info: NexusInfo = serde_json::from_slice(value).unwrap();
     let child = child_info(&nexus_info, &uuid(&child3));
     assert!(child.healthy);
+    assert!(child.generation > generation);
 
     // Remove child3 and verify that it is unhealthy
     remove_child_nexus(ms1, nexus_uuid, &child3).await;
@@ -347,6 +352,59 @@ async fn persistent_store_connection() {
     assert!(get_nexus(ms1, nexus_uuid).await.is_some());
 }
 
+/// Children recorded as faulted, out-of-sync or duplicated, and children not
+/// recorded, are excluded from the recovery of a nexus.
+#[test]
+fn persist_recovery_plan() {
+    fn recorded_child(
+        uuid: &str,
+        healthy: bool,
+        reason: Option<FaultReason>,
+    ) -> ChildInfo {
+        ChildInfo {
+            uuid: uuid.to_string(),
+            healthy,
+            fault_reason: reason,
+            ..Default::default()
+        }
+    }
+
+    let info = NexusInfo {
+        clean_shutdown: false,
+        children: vec![
+            recorded_child("c1", true, None),
+            recorded_child("c2", false, Some(FaultReason::IoError)),
+            recorded_child("c3", false, None),
+            recorded_child("c4", true, None),
+        ],
+    };
+    let uris = ["c1", "c2", "c3", "c5", "c1"]
+        .iter()
+        .map(|u| format!("nvmf://10.0.0.1:8420/nqn:{u}?uuid={u}"))
+        .chain(["bdev:///plain".to_string()])
+        .collect::<Vec<_>>();
+
+    let plan = info.recovery_plan(&uris);
+    assert_eq!(plan.children, vec![uris[0].clone()]);
+
+    let reasons = plan
+        .excluded
+        .iter()
+        .map(|e| (e.uuid.as_deref(), e.reason.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reasons,
+        vec![
+            (Some("c2"), "recorded as faulted: I/O error"),
+            (Some("c3"), "recorded as out-of-sync"),
+            (Some("c5"), "not recorded"),
+            (Some("c1"), "duplicate child"),
+            (None, "URI has no UUID"),
+            (Some("c4"), "recorded as healthy, but no URI given"),
+        ]
+    );
+}
+
 /// Start the containers for the tests.
 async fn start_infrastructure(test_name: &str) -> ComposeTest {
     common::composer_init();
@@ -0,0 +1,158 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            host::PersistentStoreHealth,
+            nexus::NexusState,
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+        ContainerSpec,
+    },
+    nexus::NexusBuilder,
+};
+use etcd_client::Client;
+use io_engine::bdev::nexus::NexusInfo;
+
+static ETCD_ENDPOINT: &str = "0.0.0.0:2379";
+static TEST_NAME: &str = "ps-deferred";
+static CHILD1_UUID: &str = "5d8e3f3c-6c2a-4e4b-9f0e-3a1c2b7d9e11";
+static CHILD2_UUID: &str = "0b6f1a7e-2d4c-4a8b-b1e3-7c9d5f2a4b22";
+
+async fn get_health(rpc: &SharedRpcHandle) -> PersistentStoreHealth {
+    rpc.lock()
+        .await
+        .host
+        .get_persistent_store_health(())
+        .await
+        .unwrap()
+        .into_inner()
+        .health
+        .unwrap()
+}
+
+fn malloc_child(name: &str, uuid: &str) -> String {
+    format!("malloc:///{name}?size_mb=16&uuid={uuid}")
+}
+
+/// Nexus state updates are deferred while the store cannot be reached, and
+/// replayed once it is back. Without deferral, the nexus is shut down
+/// instead.
+#[tokio::test]
+async fn persistence_deferred() {
+    common::composer_init();
+
+    let etcd_endpoint = format!("http://etcd.{TEST_NAME}:2379");
+    let ps_args = |core: &'static str, deferred_ops: &'static str| {
+        vec![
+            "-l",
+            core,
+            "-p",
+            &etcd_endpoint,
+            "--ps-timeout",
+            "1s",
+            "--ps-retries",
+            "2",
+            "--ps-deferred-ops",
+            deferred_ops,
+        ]
+    };
+    let test = Builder::new()
+        .name(TEST_NAME)
+        .add_container_spec(
+            ContainerSpec::from_binary(
+                "etcd",
+                Binary::from_path(env!("ETCD_BIN")).with_args(vec![
+                    "--data-dir",
+                    "/tmp/etcd-data",
+                    "--advertise-client-urls",
+                    "http://0.0.0.0:2379",
+                    "--listen-client-urls",
+                    "http://0.0.0.0:2379",
+                ]),
+            )
+            .with_portmap("2379", "2379")
+            .with_portmap("2380", "2380"),
+        )
+        .add_container_bin(
+            "ms_defer",
+            Binary::from_dbg("io-engine").with_args(ps_args("1", "8")),
+        )
+        .add_container_bin(
+            "ms_nodefer",
+            Binary::from_dbg("io-engine").with_args(ps_args("2", "0")),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_defer = conn.grpc_handle_shared("ms_defer").await.unwrap();
+    let ms_nodefer = conn.grpc_handle_shared("ms_nodefer").await.unwrap();
+
+    let children = [
+        malloc_child("m0", CHILD1_UUID),
+        malloc_child("m1", CHILD2_UUID),
+    ];
+    let mut nexuses = Vec::new();
+    for (rpc, name) in
+        [(&ms_defer, "nexus_defer"), (&ms_nodefer, "nexus_nodefer")]
+    {
+        let mut nex = NexusBuilder::new(rpc.clone())
+            .with_name(name)
+            .with_new_uuid()
+            .with_size_mb(8)
+            .with_children(children.to_vec());
+        nex.create().await.unwrap();
+        nexuses.push(nex);
+    }
+
+    test.pause("etcd").await.unwrap();
+
+    // The removal of the child is saved later, and the nexus carries on.
+    let nexus = nexuses[0].remove_child_bdev(&children[1]).await.unwrap();
+    assert_eq!(nexus.children.len(), 1);
+    let health = get_health(&ms_defer).await;
+    assert!(!health.connected, "{health:?}");
+    assert_eq!(health.deferred_ops, 1);
+
+    // Without deferral, the removal fails and the nexus is shut down.
+    nexuses[1]
+        .remove_child_bdev(&children[1])
+        .await
+        .expect_err("Child removal should fail without the store");
+    let start = Instant::now();
+    loop {
+        let nexus = nexuses[1].get_nexus().await.unwrap();
+        if nexus.state == NexusState::NexusShutdown as i32 {
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "{nexus:?}");
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    // Once the store is back, the deferred update is replayed.
+    test.thaw("etcd").await.unwrap();
+    let start = Instant::now();
+    loop {
+        let health = get_health(&ms_defer).await;
+        if health.connected && health.deferred_ops == 0 {
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "{health:?}");
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    let mut etcd = Client::connect([ETCD_ENDPOINT], None).await.unwrap();
+    let response = etcd.get("nexus_defer", None).await.unwrap();
+    let value = response.kvs().first().unwrap().value();
+    let nexus_info: NexusInfo = serde_json::from_slice(value).unwrap();
+    assert_eq!(nexus_info.children.len(), 1);
+    assert_eq!(nexus_info.children[0].uuid, CHILD1_UUID);
+}
@@ -0,0 +1,162 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            host::PersistentStoreHealth,
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+        ContainerSpec,
+    },
+    nexus::NexusBuilder,
+};
+use io_engine::core::MayastorCliArgs;
+use structopt::StructOpt;
+
+static TEST_NAME: &str = "ps-health";
+
+async fn get_health(
+    rpc: &SharedRpcHandle,
+) -> (bool, Option<PersistentStoreHealth>) {
+    let response = rpc
+        .lock()
+        .await
+        .host
+        .get_persistent_store_health(())
+        .await
+        .unwrap()
+        .into_inner();
+    (response.enabled, response.health)
+}
+
+/// Waits until the persistent store connection is reported as `connected`.
+async fn wait_connected(
+    rpc: &SharedRpcHandle,
+    connected: bool,
+) -> PersistentStoreHealth {
+    let start = Instant::now();
+    loop {
+        let health = get_health(rpc).await.1.unwrap();
+        if health.connected == connected {
+            return health;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "{health:?}");
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+#[test]
+fn persistent_store_security_args() {
+    let args = MayastorCliArgs::from_iter_safe([
+        "io-engine",
+        "--ps-tls-ca",
+        "/etc/etcd/ca.pem",
+        "--ps-tls-cert",
+        "/etc/etcd/client.pem",
+        "--ps-tls-key",
+        "/etc/etcd/client-key.pem",
+        "--ps-username",
+        "io-engine",
+        "--ps-password",
+        "secret",
+    ])
+    .unwrap();
+    assert_eq!(args.ps_tls_ca.as_deref(), Some("/etc/etcd/ca.pem"));
+    assert_eq!(args.ps_username.as_deref(), Some("io-engine"));
+    assert_eq!(args.ps_password.as_deref(), Some("secret"));
+
+    // Certificates and keys, and user names and passwords, go in pairs.
+    for (arg, value) in [
+        ("--ps-tls-cert", "/etc/etcd/client.pem"),
+        ("--ps-tls-key", "/etc/etcd/client-key.pem"),
+        ("--ps-username", "io-engine"),
+        ("--ps-password", "secret"),
+    ] {
+        assert!(
+            MayastorCliArgs::from_iter_safe(["io-engine", arg, value])
+                .is_err(),
+            "{arg}"
+        );
+    }
+}
+
+/// The health of the persistent store connection follows the store going
+/// away and coming back, and is not reported without a store.
+#[tokio::test]
+async fn persistent_store_health() {
+    common::composer_init();
+
+    let etcd_endpoint = format!("http://etcd.{TEST_NAME}:2379");
+    let test = Builder::new()
+        .name(TEST_NAME)
+        .add_container_spec(ContainerSpec::from_binary(
+            "etcd",
+            Binary::from_path(env!("ETCD_BIN")).with_args(vec![
+                "--data-dir",
+                "/tmp/etcd-data",
+                "--advertise-client-urls",
+                "http://0.0.0.0:2379",
+                "--listen-client-urls",
+                "http://0.0.0.0:2379",
+            ]),
+        ))
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "-p",
+                &etcd_endpoint,
+                "--ps-timeout",
+                "1s",
+            ]),
+        )
+        .add_container_bin(
+            "ms_nostore",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_nostore = conn.grpc_handle_shared("ms_nostore").await.unwrap();
+
+    assert_eq!(get_health(&ms_nostore).await, (false, None));
+
+    let (enabled, health) = get_health(&ms_0).await;
+    assert!(enabled);
+    let health = health.unwrap();
+    assert!(health.connected, "{health:?}");
+    assert!(health.endpoint.contains("etcd"), "{health:?}");
+    assert!(!health.tls && !health.authenticated, "{health:?}");
+    assert!(health.last_success_time.is_some(), "{health:?}");
+    assert_eq!(health.last_error, None);
+
+    // Persisting the info of the nexus hangs for as long as the store is
+    // away, so the nexus is created on a handle of its own.
+    test.pause("etcd").await.unwrap();
+    let ms_0_nex = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let mut nex = NexusBuilder::new(ms_0_nex)
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(8)
+        .with_bdev("malloc:///m0?size_mb=16");
+    let create = tokio::spawn(async move { nex.create().await });
+
+    let health = wait_connected(&ms_0, false).await;
+    assert!(health.last_error.is_some(), "{health:?}");
+    assert!(health.last_error_time.is_some(), "{health:?}");
+
+    test.thaw("etcd").await.unwrap();
+    let health = wait_connected(&ms_0, true).await;
+    assert!(health.reconnects >= 1, "{health:?}");
+    create.await.unwrap().ok();
+}
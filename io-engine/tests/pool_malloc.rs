@@ -0,0 +1,134 @@
+#![cfg(feature = "malloc-pool-backend")]
+
+pub mod common;
+
+use common::compose::{
+    rpc::v1::{
+        pool::{
+            CreatePoolRequest,
+            ListPoolOptions,
+            Pool,
+            PoolType,
+            PoolTypeValue,
+        },
+        GrpcConnect,
+        SharedRpcHandle,
+    },
+    Binary,
+    Builder,
+};
+use io_engine::pool_backend::malloc_pool_disks;
+use tonic::{Code, Status};
+
+async fn create_pool(
+    rpc: &SharedRpcHandle,
+    name: &str,
+    pooltype: PoolType,
+    disks: &[&str],
+) -> Result<Pool, Status> {
+    rpc.lock()
+        .await
+        .pool
+        .create_pool(CreatePoolRequest {
+            name: name.to_string(),
+            uuid: None,
+            pooltype: pooltype as i32,
+            disks: disks.iter().map(|d| d.to_string()).collect(),
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+async fn list_pools(rpc: &SharedRpcHandle, pooltype: PoolType) -> Vec<String> {
+    let mut names: Vec<_> = rpc
+        .lock()
+        .await
+        .pool
+        .list_pools(ListPoolOptions {
+            name: None,
+            pooltype: Some(PoolTypeValue {
+                value: pooltype as i32,
+            }),
+            uuid: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .pools
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn malloc_pool_disk_size() {
+    assert_eq!(
+        malloc_pool_disks("p0", &[]).unwrap(),
+        ["malloc:///p0-disk?size_mb=64"]
+    );
+    assert_eq!(
+        malloc_pool_disks("p0", &["100MiB".into()]).unwrap(),
+        ["malloc:///p0-disk?size_mb=100"]
+    );
+    assert_eq!(
+        malloc_pool_disks("p0", &["malloc:///d?size_mb=8".into()]).unwrap(),
+        ["malloc:///d?size_mb=8"]
+    );
+    assert!(malloc_pool_disks("p0", &["0".into()]).is_err());
+    assert!(malloc_pool_disks("p0", &["many".into()]).is_err());
+    assert!(malloc_pool_disks("p0", &["a".into(), "b".into()]).is_err());
+}
+
+/// Malloc pools are created from a size, and are listed apart from the
+/// other pools.
+#[tokio::test]
+async fn pool_malloc() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let pool = create_pool(&ms_0, "pool0", PoolType::Malloc, &["32MiB"])
+        .await
+        .unwrap();
+    assert_eq!(pool.pooltype, PoolType::Malloc as i32);
+    assert!(pool.disks[0].starts_with("malloc:///pool0-disk"));
+    assert!(pool.capacity > 0 && pool.capacity <= 32 * 1024 * 1024);
+
+    let pool = create_pool(
+        &ms_0,
+        "pool1",
+        PoolType::Lvs,
+        &["malloc:///mem1?size_mb=32"],
+    )
+    .await
+    .unwrap();
+    // Lvs pools on malloc disks are malloc pools too.
+    assert_eq!(pool.pooltype, PoolType::Malloc as i32);
+
+    assert_eq!(list_pools(&ms_0, PoolType::Lvs).await, ["pool0", "pool1"]);
+    assert_eq!(list_pools(&ms_0, PoolType::Malloc).await, ["pool0", "pool1"]);
+
+    for disks in [&["0"][..], &["many"], &["1MiB", "2MiB"]] {
+        let err = create_pool(&ms_0, "pool2", PoolType::Malloc, disks)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument, "{disks:?}");
+    }
+    assert_eq!(list_pools(&ms_0, PoolType::Lvs).await.len(), 2);
+}
@@ -0,0 +1,110 @@
+pub mod common;
+
+use common::{
+    compose::{rpc::v1::GrpcConnect, Binary, Builder},
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::Code;
+
+const CLUSTER_SIZE: u64 = 4 * 1024 * 1024;
+
+#[tokio::test]
+async fn pool_overcommit_limit() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--pool-overcommit-limit",
+                "200",
+            ]),
+        )
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 100);
+    pool.create().await.unwrap();
+
+    let info = pool.get_pool().await.unwrap();
+    let capacity = info.capacity;
+    let limit = capacity / 100 * 200;
+    assert_eq!(info.overcommit_limit, limit);
+    assert_eq!(info.commitment_ratio, 0.0);
+
+    let thin = |name: &str, size: u64| {
+        ReplicaBuilder::new(ms_0.clone())
+            .with_pool(&pool)
+            .with_name(name)
+            .with_new_uuid()
+            .with_size_kb(size / 1024)
+            .with_thin(true)
+    };
+
+    // Thin replicas may be provisioned up to the limit.
+    thin("r0", capacity).create().await.unwrap();
+    thin("r1", capacity - CLUSTER_SIZE).create().await.unwrap();
+
+    let info = pool.get_pool().await.unwrap();
+    assert_eq!(info.committed, 2 * capacity - CLUSTER_SIZE);
+    assert!(info.commitment_ratio > 1.9 && info.commitment_ratio < 2.0);
+
+    // Beyond the limit, thin replicas are refused and nothing is committed.
+    let status = thin("r2", CLUSTER_SIZE).create().await.unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert!(status.message().contains("over-committed"));
+    assert_eq!(pool.get_replicas().await.unwrap().len(), 2);
+    assert_eq!(
+        pool.get_pool().await.unwrap().committed,
+        2 * capacity - CLUSTER_SIZE
+    );
+
+    // Thick replicas are only limited by the free space of the pool.
+    let mut thick = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r3")
+        .with_new_uuid()
+        .with_size_kb(CLUSTER_SIZE / 1024)
+        .with_thin(false);
+    thick.create().await.unwrap();
+
+    // Without a limit, pools may be over-committed at will.
+    let mut pool = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem1", 100);
+    pool.create().await.unwrap();
+    let info = pool.get_pool().await.unwrap();
+    assert_eq!(info.overcommit_limit, 0);
+    for i in 0 .. 3 {
+        ReplicaBuilder::new(ms_1.clone())
+            .with_pool(&pool)
+            .with_name(&format!("r{i}"))
+            .with_new_uuid()
+            .with_size_kb(info.capacity / 1024)
+            .with_thin(true)
+            .create()
+            .await
+            .unwrap();
+    }
+    assert!(pool.get_pool().await.unwrap().commitment_ratio > 2.9);
+}
@@ -0,0 +1,167 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            pool::{ListPoolOptions, SetPoolRebuildFairnessRequest},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+    MayastorTest,
+};
+use io_engine::{
+    core::{logical_volume::LogicalVolume, MayastorCliArgs},
+    lvs::{Lvs, PoolRebuildGuard},
+    pool_backend::PoolArgs,
+    sleep::mayastor_sleep,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static DISK_NAME: &str = "/tmp/disk_fairness.img";
+
+async fn set_fairness(
+    rpc: &SharedRpcHandle,
+    name: &str,
+    uuid: Option<String>,
+    ratio: Option<u32>,
+) -> Result<u32, Status> {
+    rpc.lock()
+        .await
+        .pool
+        .set_pool_rebuild_fairness(SetPoolRebuildFairnessRequest {
+            name: name.to_string(),
+            uuid,
+            ratio,
+        })
+        .await
+        .map(|r| r.into_inner().rebuild_fairness)
+}
+
+/// The rebuild fairness ratio of a pool is the node default, unless set for
+/// the pool.
+#[tokio::test]
+async fn pool_rebuild_fairness() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--pool-rebuild-fairness",
+                "20",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+
+    let pools = ms_0
+        .lock()
+        .await
+        .pool
+        .list_pools(ListPoolOptions::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .pools;
+    assert_eq!(pools[0].rebuild_fairness, 20);
+
+    assert_eq!(set_fairness(&ms_0, "pool0", None, Some(50)).await, Ok(50));
+    assert_eq!(
+        set_fairness(&ms_0, "pool0", Some(pool.uuid()), Some(0)).await,
+        Ok(0)
+    );
+    assert_eq!(set_fairness(&ms_0, "pool0", None, None).await, Ok(20));
+
+    let err = set_fairness(&ms_0, "pool0", None, Some(101))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    let err = set_fairness(&ms_0, "pool1", None, Some(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    let err = set_fairness(&ms_0, "pool0", Some("bogus".into()), Some(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+}
+
+/// The writes to a pool are regulated while a rebuild writes to one of its
+/// replicas, and the regulation stops once the last rebuild ends.
+#[tokio::test]
+async fn pool_rebuild_guard() {
+    common::delete_file(&[DISK_NAME.into()]);
+    common::truncate_file(DISK_NAME, 64 * 1024);
+
+    let args = MayastorCliArgs {
+        pool_rebuild_fairness: 30,
+        ..Default::default()
+    };
+    let ms = MayastorTest::new(args);
+
+    ms.spawn(async {
+        let pool = Lvs::create_or_import(PoolArgs {
+            name: "pool_fairness".to_string(),
+            disks: vec![format!("aio://{DISK_NAME}?blk_size=512")],
+            uuid: None,
+            force: false,
+        })
+        .await
+        .unwrap();
+        assert_eq!(pool.rebuild_fairness(), 30);
+        pool.set_rebuild_fairness(Some(200));
+        assert_eq!(pool.rebuild_fairness(), 100);
+        pool.set_rebuild_fairness(None);
+        assert_eq!(pool.rebuild_fairness(), 30);
+
+        let lvol = pool
+            .create_lvol("r0", 8 * 1024 * 1024, None, true)
+            .await
+            .unwrap();
+        let uri = format!("bdev:///{}?uuid={}", lvol.name(), lvol.uuid());
+
+        // Only the rebuilds to local replicas regulate their pool.
+        assert!(PoolRebuildGuard::new("malloc:///m0?size_mb=8").is_none());
+        assert!(!pool.rebuild_active());
+
+        let first = PoolRebuildGuard::new(&uri).unwrap();
+        let second = PoolRebuildGuard::new(&uri).unwrap();
+        assert!(pool.rebuild_active());
+
+        drop(first);
+        mayastor_sleep(Duration::from_secs(3)).await.unwrap();
+        assert!(pool.rebuild_active());
+
+        drop(second);
+        mayastor_sleep(Duration::from_secs(3)).await.unwrap();
+        assert!(!pool.rebuild_active());
+
+        pool.destroy().await.unwrap();
+    })
+    .await;
+
+    common::delete_file(&[DISK_NAME.into()]);
+}
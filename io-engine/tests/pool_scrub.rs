@@ -0,0 +1,160 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{
+            host::{GetPoolScrubStatusRequest, PoolScrubState, PoolScrubStatus},
+            json::JsonRpcRequest,
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+};
+
+async fn json_rpc(rpc: &SharedRpcHandle, method: &str, params: &str) {
+    rpc.lock()
+        .await
+        .json
+        .json_rpc_call(JsonRpcRequest {
+            method: method.to_string(),
+            params: params.to_string(),
+        })
+        .await
+        .unwrap();
+}
+
+/// Returns whether the scrubber is enabled, and the status of the given
+/// pools.
+async fn scrub_status(
+    rpc: &SharedRpcHandle,
+    pool: Option<&str>,
+) -> (bool, Vec<PoolScrubStatus>) {
+    let res = rpc
+        .lock()
+        .await
+        .host
+        .get_pool_scrub_status(GetPoolScrubStatusRequest {
+            pool: pool.map(String::from),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    (res.enabled, res.pools)
+}
+
+/// Waits for the given pool to complete a pass after the given number of
+/// passes, and returns its status.
+async fn wait_pass(rpc: &SharedRpcHandle, passes: u64) -> PoolScrubStatus {
+    let start = Instant::now();
+    loop {
+        let (_, pools) = scrub_status(rpc, Some("pool0")).await;
+        if pools[0].passes > passes {
+            return pools[0].clone();
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(30),
+            "no media scan completed"
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+#[tokio::test]
+async fn pool_scrub_unreadable_blocks() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--pool-scrub-rate",
+                "64",
+                "--pool-scrub-interval",
+                "0",
+            ]),
+        )
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+
+    // The pool disk is stacked on an error bdev, to inject read errors.
+    json_rpc(
+        &ms_0,
+        "bdev_malloc_create",
+        r#"{"name": "mem0", "num_blocks": 131072, "block_size": 512}"#,
+    )
+    .await;
+    json_rpc(&ms_0, "bdev_error_create", r#"{"base_name": "mem0"}"#).await;
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_bdev("bdev:///EE_mem0");
+    pool.create().await.unwrap();
+
+    // A pass reads the whole disk.
+    let status = wait_pass(&ms_0, 0).await;
+    assert_eq!(status.disk, "EE_mem0");
+    assert_eq!(status.bytes_total, 64 * 1024 * 1024);
+    assert_eq!(status.last_unreadable_blocks, 0);
+    assert!(status.last_completed.is_some());
+
+    // The pool is found by uuid too, and unknown pools are not reported.
+    let (enabled, pools) = scrub_status(&ms_0, Some(&pool.uuid())).await;
+    assert!(enabled);
+    assert_eq!(pools.len(), 1);
+    assert!(scrub_status(&ms_0, Some("pool1")).await.1.is_empty());
+
+    // A failed chunk is re-read block by block: the first failed read is
+    // that of the chunk, each further one that of an unreadable block.
+    json_rpc(
+        &ms_0,
+        "bdev_error_inject_error",
+        r#"{"name": "EE_mem0", "io_type": "read", "error_type": "failure",
+            "num": 3}"#,
+    )
+    .await;
+    let mut status = wait_pass(&ms_0, status.passes).await;
+    while status.last_unreadable_blocks == 0 {
+        status = wait_pass(&ms_0, status.passes).await;
+    }
+    assert_eq!(status.last_unreadable_blocks, 2);
+
+    // Errors found by a pass are not carried over to the next one.
+    let status = wait_pass(&ms_0, status.passes).await;
+    assert_eq!(status.last_unreadable_blocks, 0);
+
+    // Without a scrubber, pools are reported idle and never scanned.
+    let mut pool = PoolBuilder::new(ms_1.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem1", 64);
+    pool.create().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let (enabled, pools) = scrub_status(&ms_1, None).await;
+    assert!(!enabled);
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].state, PoolScrubState::Idle as i32);
+    assert_eq!(pools[0].disk, "mem1");
+    assert_eq!(pools[0].passes, 0);
+    assert_eq!(pools[0].bytes_scanned, 0);
+}
@@ -0,0 +1,273 @@
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+
+use io_engine::{
+    bdev::nexus::{nexus_create, nexus_lookup_mut, FaultReason},
+    core::MayastorCliArgs,
+    rebuild::{
+        PendingRebuild,
+        RebuildJob,
+        RebuildPriority,
+        RebuildScheduler,
+        RebuildSchedulerConfig,
+    },
+};
+
+pub mod common;
+use common::compose::MayastorTest;
+
+static MAYASTOR: OnceCell<MayastorTest> = OnceCell::new();
+
+const NEXUS_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Rebuild bandwidth low enough for the rebuilds to outlast the tests.
+const SLOW_BANDWIDTH: u64 = 64 * 1024;
+
+fn get_ms() -> &'static MayastorTest<'static> {
+    MAYASTOR.get_or_init(|| MayastorTest::new(MayastorCliArgs::default()))
+}
+
+fn child_uri(name: &str) -> String {
+    format!("malloc:///{name}?size_mb=16")
+}
+
+async fn create_nexus(name: &'static str, children: &[&str]) {
+    let children = children.iter().map(|c| child_uri(c)).collect::<Vec<_>>();
+    get_ms()
+        .spawn(async move {
+            nexus_create(name, NEXUS_SIZE, None, &children)
+                .await
+                .unwrap();
+        })
+        .await;
+}
+
+async fn destroy_nexus(name: &'static str) {
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut(name).unwrap().destroy().await.unwrap();
+        })
+        .await;
+}
+
+/// Adds a child to the nexus and starts its rebuild from the given source.
+async fn rebuild_child(name: &'static str, child: &str, src: &str) {
+    let (dst, src) = (child_uri(child), child_uri(src));
+    get_ms()
+        .spawn(async move {
+            let mut nexus = nexus_lookup_mut(name).unwrap();
+            nexus.as_mut().add_child(&dst, true).await.unwrap();
+            nexus.set_rebuild_source(&dst, Some(&src)).unwrap();
+            nexus.start_rebuild(&dst).await.unwrap();
+        })
+        .await;
+}
+
+/// Waits until the rebuild of the given child holds a slot and copies data.
+async fn wait_copying(child: &str) {
+    let dst = child_uri(child);
+    for _ in 0 .. 100 {
+        let dst = dst.clone();
+        let recovered = get_ms()
+            .spawn(async move {
+                RebuildJob::lookup(&dst)
+                    .unwrap()
+                    .stats()
+                    .await
+                    .blocks_recovered
+            })
+            .await;
+        if recovered > 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("rebuild of '{dst}' does not copy data");
+}
+
+/// Waits until the given number of rebuild jobs are queued, and returns them
+/// in the order they are to be started.
+async fn wait_pending(count: usize) -> Vec<PendingRebuild> {
+    for _ in 0 .. 100 {
+        let pending = RebuildScheduler::pending();
+        if pending.len() == count {
+            return pending;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!(
+        "expected {count} queued rebuilds: {:?}",
+        RebuildScheduler::pending()
+    );
+}
+
+fn dst_uris(pending: &[PendingRebuild]) -> Vec<String> {
+    pending.iter().map(|p| p.dst_uri.clone()).collect()
+}
+
+/// Queued rebuilds are started in the order of the number of healthy
+/// children of their nexus, as it is when the slot is handed over rather
+/// than when the jobs were queued.
+#[tokio::test]
+async fn rebuild_scheduler_order() {
+    get_ms();
+    RebuildScheduler::configure(RebuildSchedulerConfig {
+        max_concurrent: 1,
+        max_bandwidth: SLOW_BANDWIDTH,
+        ..Default::default()
+    });
+
+    // Holds the only slot.
+    create_nexus("rs_hold", &["hold0"]).await;
+    rebuild_child("rs_hold", "hold1", "hold0").await;
+    wait_copying("hold1").await;
+
+    // Queued first, with three healthy children.
+    create_nexus("rs_b", &["b0", "b1", "b2"]).await;
+    rebuild_child("rs_b", "b3", "b0").await;
+    wait_pending(1).await;
+
+    // Queued last, with two healthy children: started first.
+    create_nexus("rs_a", &["a0", "a1"]).await;
+    rebuild_child("rs_a", "a2", "a0").await;
+    let pending = wait_pending(2).await;
+    assert_eq!(dst_uris(&pending), vec![child_uri("a2"), child_uri("b3")]);
+    assert_eq!(pending[0].healthy_children, 2);
+    assert_eq!(pending[1].healthy_children, 3);
+
+    // Failing children of the queued job's nexus moves it ahead.
+    get_ms()
+        .spawn(async move {
+            let mut nexus = nexus_lookup_mut("rs_b").unwrap();
+            for c in ["b1", "b2"] {
+                nexus
+                    .as_mut()
+                    .fault_child(&child_uri(c), FaultReason::IoError)
+                    .await
+                    .unwrap();
+            }
+        })
+        .await;
+    let pending = wait_pending(2).await;
+    assert_eq!(dst_uris(&pending), vec![child_uri("b3"), child_uri("a2")]);
+    assert_eq!(pending[0].healthy_children, 1);
+
+    // The job moved ahead gets the slot once it is released.
+    destroy_nexus("rs_hold").await;
+    let pending = wait_pending(1).await;
+    assert_eq!(dst_uris(&pending), vec![child_uri("a2")]);
+    wait_copying("b3").await;
+
+    destroy_nexus("rs_a").await;
+    destroy_nexus("rs_b").await;
+    wait_pending(0).await;
+    RebuildScheduler::configure(Default::default());
+}
+
+/// The per-nexus limit queues the jobs of a nexus at its limit, without
+/// holding back the jobs of other nexuses.
+#[tokio::test]
+async fn rebuild_scheduler_per_nexus_limit() {
+    get_ms();
+    RebuildScheduler::configure(RebuildSchedulerConfig {
+        max_per_nexus: 1,
+        max_bandwidth: SLOW_BANDWIDTH,
+        ..Default::default()
+    });
+
+    create_nexus("rs_p", &["p0"]).await;
+    rebuild_child("rs_p", "p1", "p0").await;
+    wait_copying("p1").await;
+
+    rebuild_child("rs_p", "p2", "p0").await;
+    let pending = wait_pending(1).await;
+    assert_eq!(dst_uris(&pending), vec![child_uri("p2")]);
+    assert!(pending[0].nexus_limited);
+
+    // Another nexus is not limited.
+    create_nexus("rs_q", &["q0"]).await;
+    rebuild_child("rs_q", "q1", "q0").await;
+    wait_copying("q1").await;
+    assert_eq!(dst_uris(&wait_pending(1).await), vec![child_uri("p2")]);
+
+    // Removing the rebuilding child of the nexus lets its next job run.
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut("rs_p")
+                .unwrap()
+                .remove_child(&child_uri("p1"))
+                .await
+                .unwrap();
+        })
+        .await;
+    wait_pending(0).await;
+    wait_copying("p2").await;
+
+    destroy_nexus("rs_p").await;
+    destroy_nexus("rs_q").await;
+    RebuildScheduler::configure(Default::default());
+}
+
+/// Queued rebuilds of a higher priority are started first, whatever the
+/// health of their nexus, and a rebuild stopped while queued leaves the
+/// queue.
+#[tokio::test]
+async fn rebuild_scheduler_priority() {
+    get_ms();
+    RebuildScheduler::configure(RebuildSchedulerConfig {
+        max_concurrent: 1,
+        max_bandwidth: SLOW_BANDWIDTH,
+        ..Default::default()
+    });
+
+    create_nexus("rs_hold2", &["hold2"]).await;
+    rebuild_child("rs_hold2", "hold3", "hold2").await;
+    wait_copying("hold3").await;
+
+    // Queued with a single healthy child.
+    create_nexus("rs_c", &["c0"]).await;
+    rebuild_child("rs_c", "c1", "c0").await;
+    wait_pending(1).await;
+
+    // Queued with two healthy children, but with a high priority, set under
+    // another spelling of the URI of the child.
+    create_nexus("rs_d", &["d0", "d1"]).await;
+    let dst = child_uri("d2");
+    get_ms()
+        .spawn(async move {
+            let mut nexus = nexus_lookup_mut("rs_d").unwrap();
+            nexus.as_mut().add_child(&dst, true).await.unwrap();
+            nexus.set_rebuild_priority(
+                "malloc:///d2?blk_size=512&size_mb=16",
+                Some(RebuildPriority::High),
+            );
+            nexus.start_rebuild(&dst).await.unwrap();
+        })
+        .await;
+    let pending = wait_pending(2).await;
+    assert_eq!(dst_uris(&pending), vec![child_uri("d2"), child_uri("c1")]);
+    assert_eq!(pending[0].priority, RebuildPriority::High);
+    assert_eq!(pending[1].priority, RebuildPriority::Normal);
+
+    // Removing the child of a queued rebuild takes the rebuild out of the
+    // queue, without the slot being handed over.
+    get_ms()
+        .spawn(async move {
+            nexus_lookup_mut("rs_d")
+                .unwrap()
+                .remove_child(&child_uri("d2"))
+                .await
+                .unwrap();
+        })
+        .await;
+    assert_eq!(dst_uris(&wait_pending(1).await), vec![child_uri("c1")]);
+
+    destroy_nexus("rs_hold2").await;
+    wait_pending(0).await;
+    wait_copying("c1").await;
+
+    destroy_nexus("rs_c").await;
+    destroy_nexus("rs_d").await;
+    RebuildScheduler::configure(Default::default());
+}
@@ -0,0 +1,124 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            replica::{CreateReplicaRequest, Replica},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+    replica::{list_replicas, ReplicaBuilder},
+};
+use tonic::Code;
+
+const REPL_SIZE: u64 = 16 * 1024 * 1024;
+
+async fn create_compressed(
+    rpc: &SharedRpcHandle,
+    pool: &PoolBuilder,
+    name: &str,
+    thin: bool,
+) -> Result<Replica, tonic::Status> {
+    rpc.lock()
+        .await
+        .replica
+        .create_replica(CreateReplicaRequest {
+            name: name.to_string(),
+            uuid: uuid::Uuid::new_v4().to_string(),
+            pooluuid: pool.uuid(),
+            size: REPL_SIZE,
+            thin,
+            share: 1,
+            compress: true,
+            ..Default::default()
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+#[tokio::test]
+async fn replica_compress() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--compress-pm-path",
+                "/tmp/compress_pm",
+            ]),
+        )
+        .add_container_bin(
+            "ms_1",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "2"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+    let ms_1 = conn.grpc_handle_shared("ms_1").await.unwrap();
+
+    let mut pool_0 = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 100);
+    pool_0.create().await.unwrap();
+    let mut pool_1 = PoolBuilder::new(ms_1.clone())
+        .with_name("pool1")
+        .with_new_uuid()
+        .with_malloc("mem1", 100);
+    pool_1.create().await.unwrap();
+
+    // A compressed replica is shared through its compressed bdev.
+    let repl = create_compressed(&ms_0, &pool_0, "r0", true).await.unwrap();
+    assert!(repl.compressed);
+    assert!(repl.uri.starts_with("nvmf://"));
+
+    let listed = list_replicas(ms_0.clone()).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].compressed);
+    assert!(listed[0].compression_ratio >= 0.0);
+
+    // Destroying the replica removes its compressed bdev, so that the space
+    // can be used again by an uncompressed replica of the same name.
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool_0)
+        .with_name("r0")
+        .with_uuid(&repl.uuid)
+        .with_size_mb(16)
+        .with_thin(true);
+    repl.destroy().await.unwrap();
+    assert!(list_replicas(ms_0.clone()).await.unwrap().is_empty());
+
+    let created = repl.create().await.unwrap();
+    assert!(!created.compressed);
+    assert_eq!(created.compression_ratio, 0.0);
+    repl.destroy().await.unwrap();
+
+    // Only thin replicas can be compressed.
+    let status = create_compressed(&ms_0, &pool_0, "r1", false)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert!(list_replicas(ms_0.clone()).await.unwrap().is_empty());
+
+    // Compression needs a persistent memory path, and no replica is left
+    // behind when it is not configured.
+    let status = create_compressed(&ms_1, &pool_1, "r2", true)
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+    assert!(list_replicas(ms_1.clone()).await.unwrap().is_empty());
+}
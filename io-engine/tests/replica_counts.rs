@@ -0,0 +1,120 @@
+pub mod common;
+
+use std::time::{Duration, Instant};
+
+use common::{
+    compose::{
+        rpc::v1::{GrpcConnect, SharedRpcHandle},
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+    snapshot::{ReplicaSnapshotBuilder, SnapshotCloneBuilder},
+};
+use uuid::Uuid;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+fn snapshot(
+    rpc: &SharedRpcHandle,
+    replica_uuid: &str,
+    name: &str,
+) -> ReplicaSnapshotBuilder {
+    ReplicaSnapshotBuilder::new(rpc.clone())
+        .with_replica_uuid(replica_uuid)
+        .with_snapshot_uuid()
+        .with_snapshot_name(name)
+        .with_entity_id(&format!("{name}_e1"))
+        .with_txn_id(&format!("{name}_t1"))
+}
+
+/// Waits for the replica to report the given number of connected hosts.
+async fn wait_connections(repl: &ReplicaBuilder, count: u32) {
+    let start = Instant::now();
+    loop {
+        let replica = repl.get_replica().await.unwrap();
+        if replica.num_connections == count {
+            return;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "expected {count} connections: {replica:?}"
+        );
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// The listed replicas report their number of snapshots, of clones of these
+/// snapshots, and of connected hosts.
+#[tokio::test]
+async fn replica_counts() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(true);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let replica = repl.get_replica().await.unwrap();
+    assert_eq!(replica.num_snapshots, 0);
+    assert_eq!(replica.num_clones, 0);
+    assert_eq!(replica.num_connections, 0);
+
+    let mut snap_1 = snapshot(&ms_0, &repl.uuid(), "snap1");
+    snap_1.create_replica_snapshot().await.unwrap();
+    let mut snap_2 = snapshot(&ms_0, &repl.uuid(), "snap2");
+    snap_2.create_replica_snapshot().await.unwrap();
+    let mut clone_1 = SnapshotCloneBuilder::new(ms_0.clone())
+        .with_snapshot_uuid(&snap_1.snapshot_uuid())
+        .with_clone_name("clone1")
+        .with_clone_uuid(&Uuid::new_v4().to_string());
+    clone_1.create_snapshot_clone().await.unwrap();
+
+    let replica = repl.get_replica().await.unwrap();
+    assert_eq!(replica.num_snapshots, 2);
+    assert_eq!(replica.num_clones, 1);
+
+    // The clone has no snapshot, and so no clone, of its own.
+    let clone = ReplicaBuilder::new(ms_0.clone())
+        .with_uuid(&clone_1.clone_uuid())
+        .with_name(&clone_1.clone_name())
+        .get_replica()
+        .await
+        .unwrap();
+    assert_eq!(clone.num_snapshots, 0);
+    assert_eq!(clone.num_clones, 0);
+
+    // Only the hosts connected to the share are counted.
+    repl.share().await.unwrap();
+    wait_connections(&repl, 0).await;
+    let (cg, _path) = repl.nvmf_location().open().unwrap();
+    wait_connections(&repl, 1).await;
+    drop(cg);
+    wait_connections(&repl, 0).await;
+}
@@ -0,0 +1,157 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            replica::{FreezeReplicaRequest, ThawReplicaRequest},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nvme::nvme_write_fua,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tokio::task::JoinHandle;
+use tonic::Code;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 50;
+
+async fn freeze(
+    rpc: &SharedRpcHandle,
+    repl: &ReplicaBuilder,
+    timeout: Duration,
+) -> Result<(), tonic::Status> {
+    rpc.lock()
+        .await
+        .replica
+        .freeze_replica(FreezeReplicaRequest {
+            uuid: repl.uuid(),
+            timeout: Some(timeout.try_into().unwrap()),
+        })
+        .await
+        .map(|_| ())
+}
+
+async fn thaw(
+    rpc: &SharedRpcHandle,
+    repl: &ReplicaBuilder,
+) -> Result<(), tonic::Status> {
+    rpc.lock()
+        .await
+        .replica
+        .thaw_replica(ThawReplicaRequest {
+            uuid: repl.uuid(),
+        })
+        .await
+        .map(|_| ())
+}
+
+/// Writes a block to the given NVMe namespace, bypassing the page cache.
+fn write_block(path: &std::path::Path) -> JoinHandle<bool> {
+    let path = path.to_owned();
+    tokio::task::spawn_blocking(move || {
+        nvme_write_fua(path, 0, 1, 512).success()
+    })
+}
+
+/// Checks that the write does not complete within the given time.
+async fn assert_held(write: &mut JoinHandle<bool>, time: Duration) {
+    assert!(
+        tokio::time::timeout(time, write).await.is_err(),
+        "write completed on a frozen replica"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn replica_freeze_holds_io() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false)
+        .with_nvmf();
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let (_cg, path) = repl.nvmf_location().open().unwrap();
+
+    // Writes are held while the replica is frozen, and complete on thaw.
+    freeze(&ms_0, &repl, Duration::from_secs(30)).await.unwrap();
+    let mut write = write_block(&path);
+    assert_held(&mut write, Duration::from_secs(3)).await;
+
+    thaw(&ms_0, &repl).await.unwrap();
+    let written = tokio::time::timeout(Duration::from_secs(10), write)
+        .await
+        .expect("write must complete after thaw")
+        .unwrap();
+    assert!(written);
+    assert!(write_block(&path).await.unwrap());
+
+    // A frozen replica cannot be frozen again, and a thawed one cannot be
+    // thawed.
+    assert_eq!(
+        thaw(&ms_0, &repl).await.unwrap_err().code(),
+        Code::FailedPrecondition
+    );
+    freeze(&ms_0, &repl, Duration::from_secs(30)).await.unwrap();
+    assert_eq!(
+        freeze(&ms_0, &repl, Duration::from_secs(30))
+            .await
+            .unwrap_err()
+            .code(),
+        Code::FailedPrecondition
+    );
+    thaw(&ms_0, &repl).await.unwrap();
+
+    // Timeouts out of range are rejected.
+    for timeout in [Duration::ZERO, Duration::from_secs(61)] {
+        assert_eq!(
+            freeze(&ms_0, &repl, timeout).await.unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    // The replica is thawed once the freeze deadline expires.
+    freeze(&ms_0, &repl, Duration::from_secs(3)).await.unwrap();
+    let mut write = write_block(&path);
+    assert_held(&mut write, Duration::from_secs(1)).await;
+    let written = tokio::time::timeout(Duration::from_secs(10), write)
+        .await
+        .expect("write must complete after the freeze deadline")
+        .unwrap();
+    assert!(written);
+    assert_eq!(
+        thaw(&ms_0, &repl).await.unwrap_err().code(),
+        Code::FailedPrecondition
+    );
+}
@@ -0,0 +1,218 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            replica::{
+                CreateReplicaRequest,
+                DestroyReplicaRequest,
+                ReplicaOwner,
+                SetReplicaOwnerRequest,
+                ShareReplicaRequest,
+                UnshareReplicaRequest,
+            },
+            GrpcConnect,
+        },
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::Code;
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 8;
+
+fn owner(control_plane: &str) -> ReplicaOwner {
+    ReplicaOwner {
+        volume_uuid: "5cfd3cdb-9bb1-4fc2-9f66-fd0fb0ab6c65".to_string(),
+        control_plane: control_plane.to_string(),
+    }
+}
+
+/// A replica records its owner, and only the owner can destroy or (un)share
+/// it, unless the owner is overridden.
+#[tokio::test]
+async fn replica_owner() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+
+    let repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE);
+    let created = ms_0
+        .lock()
+        .await
+        .replica
+        .create_replica(CreateReplicaRequest {
+            name: repl.name(),
+            uuid: repl.uuid(),
+            pooluuid: pool.uuid(),
+            size: REPL_SIZE * 1024 * 1024,
+            thin: true,
+            owner: Some(owner("cp0")),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(created.owner, Some(owner("cp0")));
+    assert_eq!(repl.get_replica().await.unwrap().owner, Some(owner("cp0")));
+
+    // Another owner, or no owner at all, can't touch the replica.
+    for other in [Some(owner("cp1")), None] {
+        let err = ms_0
+            .lock()
+            .await
+            .replica
+            .share_replica(ShareReplicaRequest {
+                uuid: repl.uuid(),
+                share: 1,
+                owner: other.clone(),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied, "{other:?}");
+
+        let err = ms_0
+            .lock()
+            .await
+            .replica
+            .destroy_replica(DestroyReplicaRequest {
+                uuid: repl.uuid(),
+                owner: other.clone(),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied, "{other:?}");
+
+        let err = ms_0
+            .lock()
+            .await
+            .replica
+            .set_replica_owner(SetReplicaOwnerRequest {
+                uuid: repl.uuid(),
+                owner: other.clone(),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied, "{other:?}");
+    }
+
+    // The owner can, and so can anyone overriding it.
+    ms_0.lock()
+        .await
+        .replica
+        .share_replica(ShareReplicaRequest {
+            uuid: repl.uuid(),
+            share: 1,
+            owner: Some(owner("cp0")),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    ms_0.lock()
+        .await
+        .replica
+        .unshare_replica(UnshareReplicaRequest {
+            uuid: repl.uuid(),
+            owner: None,
+            override_owner: true,
+        })
+        .await
+        .unwrap();
+
+    // The replica is handed over, and the former owner is then rejected.
+    let replica = ms_0
+        .lock()
+        .await
+        .replica
+        .set_replica_owner(SetReplicaOwnerRequest {
+            uuid: repl.uuid(),
+            owner: Some(owner("cp1")),
+            override_owner: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(replica.owner, Some(owner("cp1")));
+    let err = ms_0
+        .lock()
+        .await
+        .replica
+        .destroy_replica(DestroyReplicaRequest {
+            uuid: repl.uuid(),
+            owner: Some(owner("cp0")),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::PermissionDenied);
+
+    // Once released, the replica can be mutated by anyone.
+    let replica = ms_0
+        .lock()
+        .await
+        .replica
+        .set_replica_owner(SetReplicaOwnerRequest {
+            uuid: repl.uuid(),
+            owner: None,
+            override_owner: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(replica.owner, None);
+    ms_0.lock()
+        .await
+        .replica
+        .destroy_replica(DestroyReplicaRequest {
+            uuid: repl.uuid(),
+            owner: Some(owner("cp0")),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let err = ms_0
+        .lock()
+        .await
+        .replica
+        .set_replica_owner(SetReplicaOwnerRequest {
+            uuid: repl.uuid(),
+            owner: Some(owner("cp0")),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::NotFound);
+}
@@ -93,6 +93,7 @@ async fn replica_snapshot() {
                 name: POOL1_NAME.to_string(),
                 disks: vec![format!("aio://{DISKNAME1}")],
                 uuid: None,
+                force: false,
             })
             .await
             .unwrap();
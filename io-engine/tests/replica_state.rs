@@ -0,0 +1,138 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            replica::{
+                Replica,
+                ReplicaState,
+                SetReplicaStateRequest,
+                ShareReplicaRequest,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nvme::{nvme_connect, nvme_write_fua},
+    pool::PoolBuilder,
+    replica::{list_replicas, ReplicaBuilder},
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn set_state(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    state: i32,
+) -> Result<Replica, Status> {
+    rpc.lock()
+        .await
+        .replica
+        .set_replica_state(SetReplicaStateRequest {
+            uuid: uuid.to_string(),
+            state,
+            ..Default::default()
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+/// A read-only replica rejects writes, and a replica in maintenance also
+/// rejects connections, while both keep their share.
+#[tokio::test]
+async fn replica_state_read_only_and_maintenance() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false)
+        .with_nvmf();
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    let replicas = list_replicas(ms_0.clone()).await.unwrap();
+    assert_eq!(replicas[0].state, ReplicaState::Online as i32);
+
+    let location = repl.nvmf_location();
+    let (cg, path) = location.open().unwrap();
+    assert!(nvme_write_fua(&path, 0, 1, 512).success());
+
+    // Writes fail with the namespace write protected.
+    let replica = set_state(&ms_0, &repl.uuid(), ReplicaState::ReadOnly as i32)
+        .await
+        .unwrap();
+    assert_eq!(replica.state, ReplicaState::ReadOnly as i32);
+    assert_eq!(replica.uri, repl.shared_uri());
+    assert!(!nvme_write_fua(&path, 0, 1, 512).success());
+    drop(cg);
+
+    // Hosts cannot connect, nor be allowed to, while in maintenance.
+    set_state(&ms_0, &repl.uuid(), ReplicaState::Maintenance as i32)
+        .await
+        .unwrap();
+    let addr = location.addr.ip().to_string();
+    assert!(!nvme_connect(&addr, &location.nqn, false).success());
+    let error = ms_0
+        .lock()
+        .await
+        .replica
+        .share_replica(ShareReplicaRequest {
+            uuid: repl.uuid(),
+            share: 1,
+            allowed_hosts: vec!["nqn.2019-05.io.openebs:host0".to_string()],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), Code::FailedPrecondition);
+
+    // Back online, the share serves writes again.
+    set_state(&ms_0, &repl.uuid(), ReplicaState::Online as i32)
+        .await
+        .unwrap();
+    let (_cg, path) = location.open().unwrap();
+    assert!(nvme_write_fua(&path, 0, 1, 512).success());
+
+    assert_eq!(
+        set_state(&ms_0, &repl.uuid(), 99).await.unwrap_err().code(),
+        Code::InvalidArgument
+    );
+    assert_eq!(
+        set_state(
+            &ms_0,
+            "0b8c5d3e-6f21-4b7a-9c44-1d2e3f4a5b6c",
+            ReplicaState::ReadOnly as i32
+        )
+        .await
+        .unwrap_err()
+        .code(),
+        Code::NotFound
+    );
+}
@@ -5,6 +5,12 @@ use common::{
     pool::PoolBuilder,
     replica::ReplicaBuilder,
 };
+use io_engine::lvs::{
+    LvolSpaceUsage,
+    LvolUsageSample,
+    LvolUsageThresholds,
+    LvolUsageWatch,
+};
 use io_engine_tests::{file_io::DataSize, nvmf::test_write_to_nvmf};
 
 #[tokio::test]
@@ -93,3 +99,46 @@ async fn replica_thin_used_space() {
     // total number of clusters.
     assert!(u_0_after.num_allocated_clusters < u_0_after.num_clusters);
 }
+
+fn usage_sample(uuid: &str, allocated_bytes: u64) -> LvolUsageSample {
+    LvolUsageSample {
+        uuid: uuid.to_string(),
+        name: uuid.to_string(),
+        pool_uuid: "pool".to_string(),
+        usage: LvolSpaceUsage {
+            capacity_bytes: 1000,
+            allocated_bytes,
+            ..Default::default()
+        },
+    }
+}
+
+/// A thin replica is reported once it has grown past a threshold since it
+/// was last reported, or shrunk to.
+#[test]
+fn replica_thin_usage_watch() {
+    let mut watch = LvolUsageWatch::new(
+        LvolUsageThresholds {
+            delta_bytes: 200,
+            delta_percent: 10,
+        },
+        false,
+    );
+    assert!(watch.update(vec![usage_sample("r1", 100)]).is_empty());
+    assert!(watch.update(vec![usage_sample("r1", 150)]).is_empty());
+
+    let changes = watch.update(vec![usage_sample("r1", 200)]);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].previous_allocated_bytes, Some(100));
+
+    // Growth is measured from the size last reported, or shrunk to.
+    assert!(watch.update(vec![usage_sample("r1", 250)]).is_empty());
+    assert!(watch.update(vec![usage_sample("r1", 50)]).is_empty());
+    assert_eq!(watch.update(vec![usage_sample("r1", 150)]).len(), 1);
+
+    // Without thresholds, new replicas and any growth are reported.
+    let mut watch = LvolUsageWatch::new(LvolUsageThresholds::default(), true);
+    assert_eq!(watch.update(vec![usage_sample("r1", 100)]).len(), 1);
+    assert!(watch.update(vec![usage_sample("r1", 100)]).is_empty());
+    assert_eq!(watch.update(vec![usage_sample("r1", 101)]).len(), 1);
+}
@@ -0,0 +1,161 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            replica::{
+                VerifyExtentType,
+                VerifyReplicaRequest,
+                VerifyReplicaResponse,
+            },
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nvmf::test_write_to_nvmf,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+    snapshot::ReplicaSnapshotBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+/// Verifies the replica, returning the progress notifications.
+async fn verify(
+    rpc: &SharedRpcHandle,
+    uuid: &str,
+    snapshot_uuid: Option<&str>,
+    chunk_size: u64,
+) -> Result<Vec<VerifyReplicaResponse>, Status> {
+    let mut stream = rpc
+        .lock()
+        .await
+        .replica
+        .verify_replica(VerifyReplicaRequest {
+            uuid: uuid.to_string(),
+            snapshot_uuid: snapshot_uuid.map(String::from),
+            chunk_size,
+        })
+        .await?
+        .into_inner();
+
+    let mut responses = Vec::new();
+    while let Some(response) = stream.message().await? {
+        responses.push(response);
+    }
+    Ok(responses)
+}
+
+/// The allocated clusters of a replica are read and compared with a snapshot,
+/// with the progress reported after every chunk.
+#[tokio::test]
+async fn replica_verify() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(true)
+        .with_nvmf();
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    test_write_to_nvmf(
+        &repl.nvmf_location(),
+        DataSize::from_bytes(0),
+        1,
+        DataSize::from_mb(1),
+    )
+    .await
+    .unwrap();
+
+    // Every allocated cluster of the replica is readable.
+    let responses = verify(&ms_0, &repl.uuid(), None, 1024 * 1024)
+        .await
+        .unwrap();
+    let last = responses.last().unwrap();
+    assert!(last.total_bytes > 0);
+    assert_eq!(last.verified_bytes, last.total_bytes);
+    assert_eq!(last.remaining_bytes, 0);
+    assert_eq!(last.io_error_bytes, 0);
+    assert_eq!(last.mismatch_bytes, 0);
+    // The whole clusters are verified in chunks of the given size.
+    assert_eq!(responses.len() as u64, 1 + last.total_bytes / (1024 * 1024));
+
+    let mut snap = ReplicaSnapshotBuilder::new(ms_0.clone())
+        .with_replica_uuid(&repl.uuid())
+        .with_snapshot_uuid()
+        .with_snapshot_name("snap1")
+        .with_entity_id("snap1_e1")
+        .with_txn_id("snap1_t1");
+    snap.create_replica_snapshot().await.unwrap();
+
+    // The data written after the snapshot differs from it.
+    test_write_to_nvmf(
+        &repl.nvmf_location(),
+        DataSize::from_mb(1),
+        1,
+        DataSize::from_kb(4),
+    )
+    .await
+    .unwrap();
+    let responses =
+        verify(&ms_0, &repl.uuid(), Some(&snap.snapshot_uuid()), 0)
+            .await
+            .unwrap();
+    let last = responses.last().unwrap();
+    assert_eq!(last.verified_bytes, last.total_bytes);
+    assert_eq!(last.mismatch_bytes, 4096);
+    let extents: Vec<_> = responses.iter().flat_map(|r| &r.extents).collect();
+    assert!(!extents.is_empty());
+    assert!(extents
+        .iter()
+        .all(|e| e.kind == VerifyExtentType::Mismatch as i32
+            && e.offset >= 1024 * 1024
+            && e.offset < 1024 * 1024 + 4096));
+
+    // The replica is only compared with a snapshot, in chunks of whole blocks.
+    for (snapshot, chunk_size) in [(Some(repl.uuid()), 0), (None, 1000)] {
+        assert_eq!(
+            verify(&ms_0, &repl.uuid(), snapshot.as_deref(), chunk_size)
+                .await
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+    assert_eq!(
+        verify(&ms_0, "8d1f3c2a-5b6e-4f70-9a81-b2c3d4e5f607", None, 0)
+            .await
+            .unwrap_err()
+            .code(),
+        Code::NotFound
+    );
+}
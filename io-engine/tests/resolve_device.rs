@@ -0,0 +1,116 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            host::{ResolveDeviceRequest, ResolveDeviceResponse},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::NexusBuilder,
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+};
+use tonic::{Code, Status};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 20;
+
+async fn resolve(
+    rpc: &SharedRpcHandle,
+    device: &str,
+) -> Result<ResolveDeviceResponse, Status> {
+    rpc.lock()
+        .await
+        .host
+        .resolve_device(ResolveDeviceRequest {
+            device: device.to_string(),
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+/// A device is resolved, by name or URI, to the pools, replicas, nexus
+/// children and shares referencing it.
+#[tokio::test]
+async fn resolve_device() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    let mut repl_0 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false)
+        .with_nvmf();
+    let mut repl_1 = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r1")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl_0.create().await.unwrap();
+    repl_1.create().await.unwrap();
+
+    let mut nex = NexusBuilder::new(ms_0.clone())
+        .with_name("nexus0")
+        .with_new_uuid()
+        .with_size_mb(REPL_SIZE)
+        .with_local_replica(&repl_1);
+    nex.create().await.unwrap();
+
+    // The pool device references the pool, its replicas and their shares.
+    let refs = resolve(&ms_0, "mem0").await.unwrap();
+    assert_eq!(refs.device, "mem0");
+    assert_eq!(refs.pools, ["pool0"]);
+    let mut replicas = refs.replicas.clone();
+    replicas.sort();
+    let mut expected = vec![repl_0.uuid(), repl_1.uuid()];
+    expected.sort();
+    assert_eq!(replicas, expected);
+    assert_eq!(refs.shares, [repl_0.shared_uri()]);
+    assert!(refs.nexus_children.is_empty());
+    assert!(refs.injections.is_empty());
+
+    // The replica device references the nexus child opened on it.
+    let refs = resolve(&ms_0, &repl_1.bdev()).await.unwrap();
+    assert!(refs.pools.is_empty());
+    assert_eq!(refs.replicas, [repl_1.uuid()]);
+    assert_eq!(refs.nexus_children.len(), 1);
+    assert_eq!(refs.nexus_children[0].nexus, "nexus0");
+    assert_eq!(refs.nexus_children[0].uri, repl_1.bdev());
+    assert!(refs.shares.is_empty());
+
+    assert_eq!(
+        resolve(&ms_0, "nodev").await.unwrap_err().code(),
+        Code::NotFound
+    );
+    assert_eq!(
+        resolve(&ms_0, "bogus:///nodev").await.unwrap_err().code(),
+        Code::InvalidArgument
+    );
+}
@@ -0,0 +1,28 @@
+use io_engine::core::{ResourceGeneration, ResourceKind};
+
+#[test]
+fn resource_generation_bump_and_check() {
+    let gen = ResourceGeneration::current(ResourceKind::Replica, "r1");
+    assert_eq!(
+        ResourceGeneration::current(ResourceKind::Replica, "r1"),
+        gen
+    );
+    assert!(
+        ResourceGeneration::check(ResourceKind::Replica, "r1", None).is_ok()
+    );
+    assert!(
+        ResourceGeneration::check(ResourceKind::Replica, "r1", Some(gen))
+            .is_ok()
+    );
+
+    let next = ResourceGeneration::bump(ResourceKind::Replica, "r1");
+    assert!(next > gen);
+    assert!(
+        ResourceGeneration::check(ResourceKind::Replica, "r1", Some(gen))
+            .is_err()
+    );
+
+    // A forgotten resource starts over with a newer generation.
+    ResourceGeneration::forget(ResourceKind::Replica, "r1");
+    assert!(ResourceGeneration::current(ResourceKind::Replica, "r1") > next);
+}
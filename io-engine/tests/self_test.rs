@@ -0,0 +1,137 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            pool::DestroyPoolRequest,
+            test::{RunSelfTestRequest, RunSelfTestResponse},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    nexus::list_nexuses,
+    pool::{list_pools, PoolBuilder},
+    replica::{list_replicas, ReplicaBuilder},
+};
+
+async fn run_self_test(rpc: &SharedRpcHandle) -> RunSelfTestResponse {
+    rpc.lock()
+        .await
+        .test
+        .run_self_test(RunSelfTestRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+}
+
+async fn destroy_pool(rpc: &SharedRpcHandle, pool: &PoolBuilder) {
+    rpc.lock()
+        .await
+        .pool
+        .destroy_pool(DestroyPoolRequest {
+            name: pool.name(),
+            uuid: Some(pool.uuid()),
+            validate_only: false,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+}
+
+fn stage_names(res: &RunSelfTestResponse) -> Vec<&str> {
+    res.stages.iter().map(|s| s.stage.as_str()).collect()
+}
+
+#[tokio::test]
+async fn self_test_node() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 60);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(20)
+        .with_thin(false);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+
+    // All stages are reported in order, and pass.
+    let res = run_self_test(&ms_0).await;
+    assert_eq!(stage_names(&res), [
+        "CreatePool",
+        "CreateReplica",
+        "CreateNexus",
+        "IoVerify",
+        "Snapshot",
+        "Cleanup",
+    ]);
+    for stage in &res.stages {
+        assert!(stage.passed, "{}: {:?}", stage.stage, stage.error);
+        assert!(stage.error.is_none());
+        assert!(stage.elapsed.is_some());
+    }
+    assert!(res.passed);
+
+    // The self-test leaves no resources behind, and keeps the existing ones.
+    let pools = list_pools(ms_0.clone()).await.unwrap();
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].name, "pool0");
+    let replicas = list_replicas(ms_0.clone()).await.unwrap();
+    assert_eq!(replicas.len(), 1);
+    assert_eq!(replicas[0].uuid, repl.uuid());
+    assert!(list_nexuses(ms_0.clone()).await.unwrap().is_empty());
+
+    // The self-test can be run again, since it uses new resources each time.
+    assert!(run_self_test(&ms_0).await.passed);
+    assert_eq!(list_pools(ms_0.clone()).await.unwrap().len(), 1);
+
+    // Once the memory of the node is used up, the pool of the self-test
+    // cannot be created: the stages depending on it are skipped, but the
+    // cleanup is still run.
+    let mut fillers = Vec::new();
+    loop {
+        let mut filler = PoolBuilder::new(ms_0.clone())
+            .with_name(&format!("fill{}", fillers.len()))
+            .with_new_uuid()
+            .with_malloc(&format!("fill{}", fillers.len()), 32);
+        if filler.create().await.is_err() {
+            break;
+        }
+        fillers.push(filler);
+        assert!(fillers.len() < 1024, "memory is never used up");
+    }
+
+    let res = run_self_test(&ms_0).await;
+    assert!(!res.passed);
+    assert_eq!(stage_names(&res), ["CreatePool", "Cleanup"]);
+    assert!(!res.stages[0].passed);
+    assert!(res.stages[0].error.is_some());
+    assert!(res.stages[1].passed);
+
+    for filler in &fillers {
+        destroy_pool(&ms_0, filler).await;
+    }
+    assert!(run_self_test(&ms_0).await.passed);
+}
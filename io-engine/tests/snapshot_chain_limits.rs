@@ -0,0 +1,158 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            snapshot::{FlattenCloneRequest, Replica},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    file_io::DataSize,
+    nvmf::test_write_to_nvmf,
+    pool::PoolBuilder,
+    replica::{validate_replicas, ReplicaBuilder},
+    snapshot::{ReplicaSnapshotBuilder, SnapshotCloneBuilder},
+};
+use tonic::{Code, Status};
+use uuid::Uuid;
+
+fn snapshot(
+    rpc: &SharedRpcHandle,
+    replica_uuid: &str,
+    name: &str,
+) -> ReplicaSnapshotBuilder {
+    ReplicaSnapshotBuilder::new(rpc.clone())
+        .with_replica_uuid(replica_uuid)
+        .with_snapshot_uuid()
+        .with_snapshot_name(name)
+        .with_entity_id(&format!("{name}_e1"))
+        .with_txn_id(&format!("{name}_t1"))
+}
+
+fn clone(
+    rpc: &SharedRpcHandle,
+    snapshot: &ReplicaSnapshotBuilder,
+    name: &str,
+) -> SnapshotCloneBuilder {
+    SnapshotCloneBuilder::new(rpc.clone())
+        .with_snapshot_uuid(&snapshot.snapshot_uuid())
+        .with_clone_name(name)
+        .with_clone_uuid(&Uuid::new_v4().to_string())
+}
+
+async fn flatten(
+    rpc: &SharedRpcHandle,
+    clone_uuid: &str,
+) -> Result<Replica, Status> {
+    rpc.lock()
+        .await
+        .snapshot
+        .flatten_clone(FlattenCloneRequest {
+            clone_uuid: clone_uuid.to_string(),
+        })
+        .await
+        .map(|r| r.into_inner())
+}
+
+#[tokio::test]
+async fn snapshot_chain_limits() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms",
+            Binary::from_dbg("io-engine").with_args(vec![
+                "-l",
+                "1",
+                "--max-snapshots-per-replica",
+                "2",
+                "--max-clone-chain-depth",
+                "1",
+            ]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms = conn.grpc_handle_shared("ms").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", 200);
+    let mut repl = ReplicaBuilder::new(ms.clone())
+        .with_pool(&pool)
+        .with_name("repl1")
+        .with_new_uuid()
+        .with_size_mb(40)
+        .with_thin(true);
+    pool.create().await.unwrap();
+    repl.create().await.unwrap();
+    repl.share().await.unwrap();
+    test_write_to_nvmf(
+        &repl.nvmf_location(),
+        DataSize::from_bytes(0),
+        30,
+        DataSize::from_mb(1),
+    )
+    .await
+    .unwrap();
+
+    // Snapshots of a replica are limited.
+    let mut snap_1 = snapshot(&ms, &repl.uuid(), "snap1");
+    snap_1.create_replica_snapshot().await.unwrap();
+    let mut snap_2 = snapshot(&ms, &repl.uuid(), "snap2");
+    snap_2.create_replica_snapshot().await.unwrap();
+    let status = snapshot(&ms, &repl.uuid(), "snap3")
+        .create_replica_snapshot()
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+    assert_eq!(snap_2.get_snapshots().await.unwrap().len(), 2);
+
+    // Clones of clones are limited.
+    let mut clone_1 = clone(&ms, &snap_1, "clone1");
+    clone_1.create_snapshot_clone().await.unwrap();
+    let mut clone_snap = snapshot(&ms, &clone_1.clone_uuid(), "clone1_snap1");
+    clone_snap.create_replica_snapshot().await.unwrap();
+    let status = clone(&ms, &clone_snap, "clone2")
+        .create_snapshot_clone()
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::OutOfRange);
+
+    // A clone with snapshots of its own cannot be flattened.
+    let status = flatten(&ms, &clone_1.clone_uuid()).await.unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+
+    // A flattened clone is detached from its snapshot, and keeps its data.
+    let mut clone_3 = clone(&ms, &snap_2, "clone3");
+    clone_3.create_snapshot_clone().await.unwrap();
+    let flattened = flatten(&ms, &clone_3.clone_uuid()).await.unwrap();
+    assert!(!flattened.is_clone);
+    assert!(flattened.snapshot_uuid.is_none());
+    assert!(flattened.thin);
+    assert!(clone_3.get_clones().await.unwrap().is_empty());
+
+    let mut restored = ReplicaBuilder::new(ms.clone())
+        .with_uuid(&clone_3.clone_uuid())
+        .with_name(&clone_3.clone_name());
+    restored.share().await.unwrap();
+    validate_replicas(&[repl.clone(), restored]).await;
+
+    // Only existing clones can be flattened.
+    let status = flatten(&ms, &repl.uuid()).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    let status = flatten(&ms, &Uuid::new_v4().to_string())
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
@@ -57,6 +57,7 @@ async fn create_test_pool(pool_name: &str, disk: String) -> Lvs {
         name: pool_name.to_string(),
         disks: vec![disk],
         uuid: None,
+        force: false,
     })
     .await
     .expect("Failed to create test pool");
@@ -349,12 +349,82 @@ async fn test_multireplica_nexus_snapshot() {
             },
         ];
 
-        nexus
+        let res = nexus
             .create_snapshot(snapshot_params, replicas)
             .await
-            .expect_err(
-                "Snapshot successfully created for a multireplica nexus",
-            );
+            .expect("Failed to create snapshot on a multireplica nexus");
+
+        // Both the local and the remote replica must be snapshotted.
+        let replica_status: Vec<(String, u32)> =
+            vec![(replica1_uuid(), 0), (replica2_uuid(), 0)];
+        check_nexus_snapshot_status(&res, &replica_status);
+    })
+    .await;
+}
+
+/// Snapshots of multi-replica nexuses must name every replica once, and may
+/// skip some of them.
+#[tokio::test]
+async fn test_multireplica_nexus_snapshot_replicas() {
+    let ms = get_ms();
+    let (_test, urls) = launch_instance(true).await;
+
+    ms.spawn(async move {
+        let mut nexus = create_nexus(&urls).await;
+
+        let snapshot_params = || {
+            SnapshotParams::new(
+                Some(String::from("e1")),
+                Some(String::from("p1")),
+                Some(Uuid::new_v4().to_string()),
+                Some(String::from("s1")),
+                Some(Uuid::new_v4().to_string()),
+                Some(Utc::now().to_string()),
+                false,
+            )
+        };
+        let replica = |replica_uuid: String, skip: bool| {
+            NexusReplicaSnapshotDescriptor {
+                replica_uuid,
+                skip,
+                snapshot_uuid: Some(Uuid::new_v4().to_string()),
+            }
+        };
+
+        // A missing, unknown or duplicated replica.
+        for replicas in [
+            vec![replica(replica1_uuid(), false)],
+            vec![
+                replica(replica1_uuid(), false),
+                replica(Uuid::new_v4().to_string(), false),
+            ],
+            vec![
+                replica(replica1_uuid(), false),
+                replica(replica1_uuid(), false),
+            ],
+        ] {
+            nexus
+                .as_mut()
+                .create_snapshot(snapshot_params(), replicas)
+                .await
+                .expect_err("Snapshot created with a wrong topology");
+        }
+
+        let res = nexus
+            .as_mut()
+            .create_snapshot(
+                snapshot_params(),
+                vec![
+                    replica(replica1_uuid(), false),
+                    replica(replica2_uuid(), true),
+                ],
+            )
+            .await
+            .expect("Failed to create snapshot on a multireplica nexus");
+        assert_eq!(res.replicas_skipped, vec![replica2_uuid()]);
+        assert_eq!(res.replicas_done.len(), 1);
+        assert_eq!(res.replicas_done[0].replica_uuid, replica1_uuid());
+        assert_eq!(res.replicas_done[0].status, 0);
     })
     .await;
 }
@@ -405,12 +475,6 @@ fn check_nexus_snapshot_status(
         "Some replicas were skipped while taking nexus snapshot"
     );
 
-    assert_eq!(
-        res.replicas_done.len(),
-        1,
-        "Not all replicas were processed while taking nexus snapshot"
-    );
-
     assert_eq!(
         res.replicas_done.len(),
         status.len(),
@@ -0,0 +1,172 @@
+pub mod common;
+
+use std::time::Duration;
+
+use chrono::Utc;
+use common::compose::{
+    rpc::v1::{
+        pool::CreatePoolRequest,
+        replica::CreateReplicaRequest,
+        GrpcConnect,
+    },
+    Builder,
+    MayastorTest,
+};
+use io_engine::{
+    core::{MayastorCliArgs, SnapshotParams},
+    grpc::{
+        remote_replica_snapshots,
+        RemoteReplicaSnapshot,
+        RemoteSnapshotError,
+        RemoteSnapshotOptions,
+    },
+};
+use uuid::Uuid;
+
+const REPLICA_SIZE: u64 = 16 * 1024 * 1024;
+
+fn snapshot_params(name: &str) -> SnapshotParams {
+    SnapshotParams::new(
+        Some(format!("{name}_e1")),
+        Some(String::from("p1")),
+        Some(Uuid::new_v4().to_string()),
+        Some(name.to_string()),
+        Some(Uuid::new_v4().to_string()),
+        Some(Utc::now().to_string()),
+        false,
+    )
+}
+
+/// The replicas of remote io-engines are snapshotted in parallel, and the
+/// outcome of each of them is reported.
+#[tokio::test]
+async fn snapshot_remote() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_dbg("ms1")
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let mut ms1 = conn.grpc_handle("ms1").await.unwrap();
+    let pool_uuid = Uuid::new_v4().to_string();
+    ms1.pool
+        .create_pool(CreatePoolRequest {
+            name: "pool1".to_string(),
+            uuid: Some(pool_uuid.clone()),
+            pooltype: 0,
+            disks: vec!["malloc:///disk0?size_mb=128".into()],
+        })
+        .await
+        .unwrap();
+    let replica_uuid = Uuid::new_v4().to_string();
+    ms1.replica
+        .create_replica(CreateReplicaRequest {
+            name: "r1".to_string(),
+            uuid: replica_uuid.clone(),
+            pooluuid: pool_uuid,
+            size: REPLICA_SIZE,
+            thin: false,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let endpoint = ms1.endpoint.to_string();
+
+    let ms = MayastorTest::new(MayastorCliArgs::default());
+    ms.spawn(async move {
+        let replica = RemoteReplicaSnapshot {
+            endpoint: endpoint.clone(),
+            replica_uuid: replica_uuid.clone(),
+            snapshot_uuid: Uuid::new_v4().to_string(),
+        };
+        let unknown = RemoteReplicaSnapshot {
+            replica_uuid: Uuid::new_v4().to_string(),
+            snapshot_uuid: Uuid::new_v4().to_string(),
+            ..replica.clone()
+        };
+
+        // One replica is snapshotted, the other one does not exist.
+        let report = remote_replica_snapshots(
+            snapshot_params("s1"),
+            vec![replica.clone(), unknown],
+            RemoteSnapshotOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!report.is_complete());
+        let done: Vec<_> = report.succeeded().collect();
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].replica.replica_uuid, replica_uuid);
+        assert_eq!(done[0].attempts, 1);
+        let snapshot = done[0].result.as_ref().unwrap();
+        assert_eq!(snapshot.snapshot_uuid, replica.snapshot_uuid);
+        assert_eq!(snapshot.source_uuid, replica_uuid);
+        let failed: Vec<_> = report.failed().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts, 1);
+
+        // Unreachable io-engines are retried until the retries run out.
+        let unreachable = RemoteReplicaSnapshot {
+            endpoint: "10.1.0.250:10124".to_string(),
+            snapshot_uuid: Uuid::new_v4().to_string(),
+            ..replica.clone()
+        };
+        let invalid = RemoteReplicaSnapshot {
+            endpoint: "not an endpoint".to_string(),
+            snapshot_uuid: Uuid::new_v4().to_string(),
+            ..replica
+        };
+        let report = remote_replica_snapshots(
+            snapshot_params("s2"),
+            vec![unreachable, invalid],
+            RemoteSnapshotOptions {
+                timeout: Duration::from_secs(1),
+                retries: 1,
+                retry_interval: Duration::from_millis(100),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.failed().count(), 2);
+        assert_eq!(report.replicas[0].attempts, 2);
+        assert_eq!(report.replicas[1].attempts, 1);
+        assert!(matches!(
+            report.replicas[1].result,
+            Err(RemoteSnapshotError::InvalidEndpoint {
+                ..
+            })
+        ));
+
+        // The snapshot must be named.
+        let params = SnapshotParams::new(
+            Some(String::from("e1")),
+            None,
+            Some(Uuid::new_v4().to_string()),
+            None,
+            Some(Uuid::new_v4().to_string()),
+            Some(Utc::now().to_string()),
+            false,
+        );
+        let err = remote_replica_snapshots(
+            params,
+            Vec::new(),
+            RemoteSnapshotOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            RemoteSnapshotError::InvalidParams {
+                ..
+            }
+        ));
+    })
+    .await;
+}
@@ -0,0 +1,173 @@
+pub mod common;
+
+use common::{
+    compose::{
+        rpc::v1::{
+            snapshot::{StreamSnapshotsRequest, StreamSnapshotsResponse},
+            GrpcConnect,
+            SharedRpcHandle,
+        },
+        Binary,
+        Builder,
+    },
+    pool::PoolBuilder,
+    replica::ReplicaBuilder,
+    snapshot::ReplicaSnapshotBuilder,
+};
+
+static POOL_SIZE: u64 = 60;
+static REPL_SIZE: u64 = 8;
+
+/// Returns the messages of a snapshot stream.
+async fn stream_snapshots(
+    rpc: &SharedRpcHandle,
+    request: StreamSnapshotsRequest,
+) -> Vec<StreamSnapshotsResponse> {
+    let mut stream = rpc
+        .lock()
+        .await
+        .snapshot
+        .stream_snapshots(request)
+        .await
+        .unwrap()
+        .into_inner();
+
+    let mut responses = Vec::new();
+    while let Some(response) = stream.message().await.unwrap() {
+        responses.push(response);
+    }
+    responses
+}
+
+fn uuids(responses: &[StreamSnapshotsResponse]) -> Vec<String> {
+    responses
+        .iter()
+        .flat_map(|r| r.snapshots.iter().map(|s| s.snapshot_uuid.clone()))
+        .collect()
+}
+
+/// Snapshots are streamed in pages, in the order of their uuids, and a
+/// truncated stream can be resumed from its page token.
+#[tokio::test]
+async fn snapshot_stream() {
+    common::composer_init();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine").with_args(vec!["-l", "1"]),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let mut pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_new_uuid()
+        .with_malloc("mem0", POOL_SIZE);
+    pool.create().await.unwrap();
+
+    let mut repls = Vec::new();
+    let mut snapshots = Vec::new();
+    for r in 0 .. 2 {
+        let mut repl = ReplicaBuilder::new(ms_0.clone())
+            .with_pool(&pool)
+            .with_name(&format!("r{r}"))
+            .with_new_uuid()
+            .with_size_mb(REPL_SIZE)
+            .with_thin(true);
+        repl.create().await.unwrap();
+        // Five snapshots of the first replica, one of the second.
+        let count = if r == 0 { 5 } else { 1 };
+        for s in 0 .. count {
+            let name = format!("snap{r}_{s}");
+            let mut snap = ReplicaSnapshotBuilder::new(ms_0.clone())
+                .with_replica_uuid(&repl.uuid())
+                .with_snapshot_uuid()
+                .with_snapshot_name(&name)
+                .with_entity_id(&format!("{name}_e1"))
+                .with_txn_id(&format!("{name}_t1"));
+            snap.create_replica_snapshot().await.unwrap();
+            if r == 0 {
+                snapshots.push(snap.snapshot_uuid());
+            }
+        }
+        repls.push(repl);
+    }
+    snapshots.sort();
+
+    let request = StreamSnapshotsRequest {
+        source_uuid: Some(repls[0].uuid()),
+        page_size: 2,
+        ..Default::default()
+    };
+    let responses = stream_snapshots(&ms_0, request.clone()).await;
+    let pages: Vec<_> = responses.iter().map(|r| r.snapshots.len()).collect();
+    assert_eq!(pages, [2, 2, 1]);
+    assert_eq!(uuids(&responses), snapshots);
+    assert!(responses.iter().all(|r| r.next_page_token.is_none()));
+
+    // Truncated, then resumed from the page token.
+    let responses = stream_snapshots(
+        &ms_0,
+        StreamSnapshotsRequest {
+            max_snapshots: 3,
+            ..request.clone()
+        },
+    )
+    .await;
+    assert_eq!(uuids(&responses), snapshots[.. 3]);
+    let token = responses.last().unwrap().next_page_token.clone();
+    assert_eq!(token.as_ref(), Some(&snapshots[2]));
+    let responses = stream_snapshots(
+        &ms_0,
+        StreamSnapshotsRequest {
+            page_token: token,
+            ..request.clone()
+        },
+    )
+    .await;
+    assert_eq!(uuids(&responses), snapshots[3 ..]);
+
+    // All the snapshots of the pool, in a single page by default.
+    let responses = stream_snapshots(
+        &ms_0,
+        StreamSnapshotsRequest {
+            pool: Some(pool.name()),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert_eq!(responses.len(), 1);
+    assert_eq!(responses[0].snapshots.len(), 6);
+
+    // Nothing matches an unknown pool, or a time in the future.
+    let responses = stream_snapshots(
+        &ms_0,
+        StreamSnapshotsRequest {
+            pool: Some("nopool".to_string()),
+            ..Default::default()
+        },
+    )
+    .await;
+    assert!(responses.is_empty());
+    let responses = stream_snapshots(
+        &ms_0,
+        StreamSnapshotsRequest {
+            created_after: Some(prost_types::Timestamp {
+                seconds: 4_000_000_000,
+                nanos: 0,
+            }),
+            ..request
+        },
+    )
+    .await;
+    assert!(responses.is_empty());
+}
@@ -0,0 +1,130 @@
+pub mod common;
+
+use common::{
+    bdev::list_bdevs,
+    compose::{rpc::v1::GrpcConnect, Binary, Builder},
+    pool::{list_pools, PoolBuilder},
+    replica::{list_replicas, ReplicaBuilder},
+};
+use io_engine::subsys::StartupConfig;
+
+const DISK_NAME: &str = "/tmp/startup_config.img";
+const CONFIG_FILE: &str = "/tmp/startup_config.yaml";
+const LOAD_FILE: &str = "/tmp/startup_config_load.json";
+
+/// The startup config, as seen from the container.
+const CONFIG: &str = r#"
+bdevs:
+  - malloc:///m1?size_mb=16
+pools:
+  - name: pool0
+    disks:
+      - aio:///host/tmp/startup_config.img?blk_size=512
+shares:
+  - name: m1
+  - name: r0
+    allowed_hosts: []
+"#;
+
+#[test]
+fn startup_config_load() {
+    // A missing file is an empty config, and JSON files are accepted.
+    assert_eq!(
+        StartupConfig::load("/tmp/startup_config_missing.yaml").unwrap(),
+        StartupConfig::default()
+    );
+    std::fs::write(
+        LOAD_FILE,
+        r#"{"pools": [{"name": "pool0", "disks": []}], "shares": []}"#,
+    )
+    .unwrap();
+    assert_ne!(
+        StartupConfig::load(LOAD_FILE).unwrap(),
+        StartupConfig::default()
+    );
+
+    // Unknown fields and malformed entries fail to parse.
+    for bad in [
+        "bdevs: [malloc:///m1?size_mb=16]\nvolumes: []\n",
+        "pools:\n  - name: pool0\n",
+        "shares:\n  - name: m1\n    protocol: iscsi\n",
+    ] {
+        std::fs::write(LOAD_FILE, bad).unwrap();
+        assert!(StartupConfig::load(LOAD_FILE).is_err(), "{bad}");
+    }
+
+    common::delete_file(&[LOAD_FILE.to_string()]);
+}
+
+#[tokio::test]
+async fn startup_config_apply() {
+    common::composer_init();
+
+    common::delete_file(&[DISK_NAME.to_string()]);
+    common::truncate_file(DISK_NAME, 64 * 1024);
+    std::fs::write(CONFIG_FILE, CONFIG).unwrap();
+
+    let test = Builder::new()
+        .name("cargo-test")
+        .network("10.1.0.0/16")
+        .unwrap()
+        .add_container_bin(
+            "ms_0",
+            Binary::from_dbg("io-engine")
+                .with_args(vec![
+                    "-l",
+                    "1",
+                    "--startup-config",
+                    "/host/tmp/startup_config.yaml",
+                ])
+                .with_bind("/tmp", "/host/tmp"),
+        )
+        .with_clean(true)
+        .build()
+        .await
+        .unwrap();
+
+    let conn = GrpcConnect::new(&test);
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    // The pool and the bdev are created and the bdev is shared. The replica
+    // does not exist yet, which does not stop the startup.
+    let pools = list_pools(ms_0.clone()).await.unwrap();
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].name, "pool0");
+    let bdevs = list_bdevs(&ms_0).await.unwrap();
+    let m1 = bdevs.iter().find(|b| b.name == "m1").unwrap();
+    assert!(m1.share_uri.starts_with("nvmf://"), "{}", m1.share_uri);
+
+    let pool = PoolBuilder::new(ms_0.clone())
+        .with_name("pool0")
+        .with_uuid(&pools[0].uuid);
+    let mut repl = ReplicaBuilder::new(ms_0.clone())
+        .with_pool(&pool)
+        .with_name("r0")
+        .with_new_uuid()
+        .with_size_mb(16)
+        .with_thin(true);
+    repl.create().await.unwrap();
+    assert!(!list_replicas(ms_0.clone()).await.unwrap()[0]
+        .uri
+        .starts_with("nvmf://"));
+
+    // On restart the pool is imported rather than created, and the replica
+    // is shared as well.
+    test.restart("ms_0").await.unwrap();
+    let ms_0 = conn.grpc_handle_shared("ms_0").await.unwrap();
+
+    let pools = list_pools(ms_0.clone()).await.unwrap();
+    assert_eq!(pools.len(), 1);
+    assert_eq!(pools[0].uuid, pool.uuid());
+    let replicas = list_replicas(ms_0.clone()).await.unwrap();
+    assert_eq!(replicas.len(), 1);
+    assert_eq!(replicas[0].uuid, repl.uuid());
+    assert!(replicas[0].uri.starts_with("nvmf://"), "{}", replicas[0].uri);
+    let bdevs = list_bdevs(&ms_0).await.unwrap();
+    let m1 = bdevs.iter().find(|b| b.name == "m1").unwrap();
+    assert!(m1.share_uri.starts_with("nvmf://"), "{}", m1.share_uri);
+
+    common::delete_file(&[DISK_NAME.to_string(), CONFIG_FILE.to_string()]);
+}
@@ -0,0 +1,124 @@
+pub mod common;
+
+use std::time::Duration;
+
+use common::MayastorTest;
+use futures::channel::oneshot;
+use io_engine::{
+    bdev::nexus::{nexus_create, nexus_lookup_mut},
+    core::{
+        lock::{
+            ProtectedSubsystems,
+            ResourceLockManager,
+            ResourceLockManagerConfig,
+        },
+        Bdev,
+        LogicalVolume,
+        MayastorCliArgs,
+        Reactors,
+        ResourceKind,
+    },
+    host::tombstone::Tombstones,
+    lvs::Lvs,
+    pool_backend::PoolArgs,
+    sleep::mayastor_sleep,
+};
+
+const POOL_NAME: &str = "tombstone_pool";
+const NEXUS_NAME: &str = "tombstone_nexus";
+const NEXUS_UUID: &str = "d6ff7c5a-3b1c-4d06-9c67-0a0e0b9d2c11";
+const NEXUS_CHILD: &str = "malloc:///tombstone_m0?size_mb=32";
+
+#[tokio::test]
+async fn tombstone_mark_and_purge() {
+    common::composer_init();
+    ResourceLockManager::initialize(
+        ResourceLockManagerConfig::default()
+            .with_subsystem(ProtectedSubsystems::NEXUS, 8),
+    );
+
+    // Marked objects expire right away.
+    let ms = MayastorTest::new(MayastorCliArgs {
+        tombstone_grace_period: 0,
+        ..Default::default()
+    });
+
+    ms.spawn(async {
+        let pool = Lvs::create_or_import(PoolArgs {
+            name: POOL_NAME.to_string(),
+            disks: vec![format!("malloc:///{POOL_NAME}?size_mb=64")],
+            uuid: None,
+            force: false,
+        })
+        .await
+        .unwrap();
+        let lvol = pool
+            .create_lvol("tombstone_repl", 8 * 1024 * 1024, None, true)
+            .await
+            .unwrap();
+        let uuid = lvol.uuid();
+
+        // Marking again keeps the deadline.
+        let deadline = Tombstones::mark(ResourceKind::Replica, &uuid);
+        assert!(Tombstones::is_marked(ResourceKind::Replica, &uuid));
+        assert_eq!(
+            Tombstones::deadline(ResourceKind::Replica, &uuid),
+            Some(deadline)
+        );
+        assert_eq!(Tombstones::mark(ResourceKind::Replica, &uuid), deadline);
+
+        assert!(Tombstones::restore(ResourceKind::Replica, &uuid));
+        assert!(!Tombstones::restore(ResourceKind::Replica, &uuid));
+        assert!(Tombstones::deadline(ResourceKind::Replica, &uuid).is_none());
+
+        Tombstones::mark(ResourceKind::Replica, &uuid);
+        Tombstones::purge_expired().await;
+        assert!(Bdev::lookup_by_uuid_str(&uuid).is_none());
+        assert!(!Tombstones::is_marked(ResourceKind::Replica, &uuid));
+
+        pool.destroy().await.unwrap();
+    })
+    .await;
+
+    // A nexus restored while its purge waits for the lock held by a gRPC
+    // method is kept.
+    ms.spawn(async {
+        nexus_create(
+            NEXUS_NAME,
+            16 * 1024 * 1024,
+            Some(NEXUS_UUID),
+            &[NEXUS_CHILD.to_string()],
+        )
+        .await
+        .unwrap();
+
+        let lock_manager = ResourceLockManager::get_instance();
+        let guard = lock_manager
+            .get_subsystem(ProtectedSubsystems::NEXUS)
+            .lock_resource(NEXUS_UUID, None)
+            .await
+            .unwrap();
+        Tombstones::mark(ResourceKind::Nexus, NEXUS_UUID);
+
+        let (sender, receiver) = oneshot::channel();
+        Reactors::master().send_future(async move {
+            Tombstones::purge_expired().await;
+            sender.send(()).ok();
+        });
+
+        mayastor_sleep(Duration::from_millis(500)).await.unwrap();
+        assert!(nexus_lookup_mut(NEXUS_NAME).is_some());
+        assert!(Tombstones::restore(ResourceKind::Nexus, NEXUS_UUID));
+        drop(guard);
+
+        receiver.await.unwrap();
+        assert!(nexus_lookup_mut(NEXUS_NAME).is_some());
+
+        // Without a method in progress, the nexus is purged.
+        Tombstones::mark(ResourceKind::Nexus, NEXUS_UUID);
+        Tombstones::purge_expired().await;
+        assert!(nexus_lookup_mut(NEXUS_NAME).is_none());
+        assert!(!Tombstones::is_marked(ResourceKind::Nexus, NEXUS_UUID));
+    })
+    .await;
+}
@@ -286,6 +286,7 @@ async fn issue_wipe_replica(
                 }),
                 chunk_size,
             }),
+            validate_only: false,
         })
         .await
         .unwrap()